@@ -0,0 +1,397 @@
+//! Backup autosave and crash recovery.
+//!
+//! Every document-changing WebSocket command bumps a revision counter and is appended,
+//! as its raw JSON text, to `journal.jsonl` in the workspace directory. A background
+//! thread periodically writes a snapshot of the graph (same format as an explicit
+//! save) to one of `AUTOSAVE_SLOTS` rotating files when the revision has changed, and
+//! prunes the journal to the commands the snapshot doesn't cover.
+//!
+//! On startup, if the newest snapshot or journal entry is newer than `document.json`
+//! (the last explicit save), the snapshot is loaded and the journal tail is replayed
+//! through the first client session. All files are written via a temp file + rename.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use cad_core::features::dag::FeatureGraph;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Number of rotating autosave snapshot files
+pub const AUTOSAVE_SLOTS: u64 = 3;
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+const DOCUMENT_FILE: &str = "document.json";
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// On-disk document: an explicit save or an autosave snapshot
+#[derive(Serialize, Deserialize)]
+pub struct SavedDocument {
+    /// Graph revision the document was taken at (0 for explicit saves)
+    #[serde(default)]
+    pub revision: u64,
+    pub saved_at_ms: u64,
+    pub graph: FeatureGraph,
+}
+
+/// One journaled command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub revision: u64,
+    pub at_ms: u64,
+    /// The command exactly as received over the WebSocket
+    pub command: String,
+}
+
+/// Write a file so readers see either the old or the new contents, never a partial write
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Explicitly save the document to the workspace
+pub fn save_document(dir: &Path, graph: &FeatureGraph) -> io::Result<u64> {
+    fs::create_dir_all(dir)?;
+    let saved_at_ms = now_ms();
+    let doc = SavedDocument { revision: 0, saved_at_ms, graph: graph.clone() };
+    let json = serde_json::to_vec(&doc).map_err(io::Error::other)?;
+    write_atomic(&dir.join(DOCUMENT_FILE), &json)?;
+    Ok(saved_at_ms)
+}
+
+fn read_document(path: &Path) -> Option<SavedDocument> {
+    let bytes = fs::read(path).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(doc) => Some(doc),
+        Err(e) => {
+            warn!("Ignoring unreadable document {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// The last explicit save, if any
+pub fn load_document(dir: &Path) -> Option<SavedDocument> {
+    read_document(&dir.join(DOCUMENT_FILE))
+}
+
+fn slot_path(dir: &Path, slot: u64) -> PathBuf {
+    dir.join(format!("autosave-{}.json", slot))
+}
+
+/// Journal entries in file order. A torn final line from a crash mid-append is skipped.
+fn read_journal(dir: &Path) -> Vec<JournalEntry> {
+    let Ok(text) = fs::read_to_string(dir.join(JOURNAL_FILE)) else { return Vec::new() };
+    text.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()
+}
+
+/// State to restore after a crash
+pub struct RecoveredSession {
+    pub graph: FeatureGraph,
+    /// Commands applied after the snapshot, to be replayed in order
+    pub journal: VecDeque<String>,
+    /// Time of the last change that was recovered
+    pub recovered_at_ms: u64,
+    /// Revision the document will be at once the journal is replayed
+    pub revision: u64,
+}
+
+/// Look for autosave data newer than the last explicit save
+pub fn recover(dir: &Path) -> Option<RecoveredSession> {
+    let explicit = load_document(dir);
+    let snapshot = (0..AUTOSAVE_SLOTS)
+        .filter_map(|slot| read_document(&slot_path(dir, slot)))
+        .max_by_key(|doc| (doc.revision, doc.saved_at_ms));
+    let base_revision = snapshot.as_ref().map_or(0, |doc| doc.revision);
+    let tail: Vec<JournalEntry> = read_journal(dir).into_iter()
+        .filter(|entry| entry.revision > base_revision)
+        .collect();
+
+    let last_change_ms = tail.iter().map(|e| e.at_ms)
+        .chain(snapshot.as_ref().map(|doc| doc.saved_at_ms))
+        .max()?;
+    let saved_at_ms = explicit.as_ref().map_or(0, |doc| doc.saved_at_ms);
+    if last_change_ms <= saved_at_ms {
+        return None;
+    }
+
+    // Journal without a snapshot: the commands were applied on top of the explicit save
+    let graph = match (snapshot, explicit) {
+        (Some(doc), _) | (None, Some(doc)) => doc.graph,
+        (None, None) => FeatureGraph::new(),
+    };
+    Some(RecoveredSession {
+        graph,
+        revision: tail.last().map_or(base_revision, |e| e.revision),
+        journal: tail.into_iter().map(|e| e.command).collect(),
+        recovered_at_ms: last_change_ms,
+    })
+}
+
+/// Shared autosave state: revision counter, journal feed, and the gate that keeps
+/// snapshots from interleaving with a command that is mid-way through changing the graph
+pub struct Autosave {
+    revision: AtomicU64,
+    gate: tokio::sync::Mutex<()>,
+    /// Taken by `stop`, which ends the worker
+    journal_tx: Mutex<Option<mpsc::Sender<JournalEntry>>>,
+    /// Journal tail still waiting to be replayed
+    pending_replay: Mutex<Option<VecDeque<String>>>,
+    /// Snapshots are held off until the recovered journal has been replayed
+    replaying: AtomicBool,
+    /// Set when the session was restored from autosave data
+    pub recovered_at_ms: Option<u64>,
+}
+
+impl Autosave {
+    /// Hold while applying a document-changing command
+    pub async fn begin(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.gate.lock().await
+    }
+
+    /// Record an applied command. Call while holding the guard from `begin`.
+    pub fn record(&self, command: &str) {
+        let revision = self.revision.fetch_add(1, Ordering::SeqCst) + 1;
+        let entry = JournalEntry { revision, at_ms: now_ms(), command: command.to_string() };
+        let sent = self.journal_tx.lock().unwrap().as_ref().is_some_and(|tx| tx.send(entry).is_ok());
+        if !sent {
+            warn!("Autosave worker stopped; command not journaled");
+        }
+    }
+
+    /// Revision of the last recorded command
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    /// Stop the worker once it has taken a last snapshot, letting go of the graph
+    pub fn stop(&self) {
+        self.journal_tx.lock().unwrap().take();
+    }
+
+    /// Take the recovered journal tail (only the first caller gets it).
+    /// The caller must call `finish_replay` once every command has been applied.
+    pub fn take_replay(&self) -> VecDeque<String> {
+        self.pending_replay.lock().unwrap().take().unwrap_or_default()
+    }
+
+    pub fn finish_replay(&self) {
+        self.replaying.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Start the autosave worker for `graph`, recovering from `dir` first.
+/// Returns the autosave handle; `graph` holds the recovered or saved document.
+pub fn start(dir: PathBuf, interval: Duration, graph: Arc<RwLock<FeatureGraph>>) -> Arc<Autosave> {
+    if let Err(e) = fs::create_dir_all(&dir) {
+        warn!("Cannot create workspace {}: {}", dir.display(), e);
+    }
+
+    let (revision, pending_replay, recovered_at_ms) = match recover(&dir) {
+        Some(session) => {
+            info!("Recovered session from autosave ({} journaled commands)", session.journal.len());
+            *graph.write().unwrap() = session.graph;
+            (session.revision, Some(session.journal), Some(session.recovered_at_ms))
+        }
+        None => {
+            if let Some(doc) = load_document(&dir) {
+                *graph.write().unwrap() = doc.graph;
+            }
+            // Autosave data older than the explicit save is stale; revisions restart at 0
+            let _ = fs::remove_file(dir.join(JOURNAL_FILE));
+            for slot in 0..AUTOSAVE_SLOTS {
+                let _ = fs::remove_file(slot_path(&dir, slot));
+            }
+            (0, None, None)
+        }
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let autosave = Arc::new(Autosave {
+        revision: AtomicU64::new(revision),
+        gate: tokio::sync::Mutex::new(()),
+        journal_tx: Mutex::new(Some(tx)),
+        replaying: AtomicBool::new(pending_replay.is_some()),
+        pending_replay: Mutex::new(pending_replay),
+        recovered_at_ms,
+    });
+
+    let worker = Worker {
+        journal: read_journal(&dir),
+        dir,
+        saved_revision: if recovered_at_ms.is_some() { 0 } else { revision },
+        next_slot: 0,
+        graph,
+        autosave: autosave.clone(),
+    };
+    std::thread::spawn(move || worker.run(rx, interval));
+    autosave
+}
+
+struct Worker {
+    dir: PathBuf,
+    /// Entries currently in the journal file
+    journal: Vec<JournalEntry>,
+    saved_revision: u64,
+    next_slot: u64,
+    graph: Arc<RwLock<FeatureGraph>>,
+    autosave: Arc<Autosave>,
+}
+
+impl Worker {
+    fn run(mut self, rx: mpsc::Receiver<JournalEntry>, interval: Duration) {
+        let mut next_tick = Instant::now() + interval;
+        loop {
+            match rx.recv_timeout(next_tick.saturating_duration_since(Instant::now())) {
+                Ok(entry) => {
+                    if let Err(e) = self.append(entry) {
+                        warn!("Failed to append to autosave journal: {}", e);
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    next_tick = Instant::now() + interval;
+                    if let Err(e) = self.snapshot() {
+                        warn!("Autosave failed: {}", e);
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    if let Err(e) = self.snapshot() {
+                        warn!("Final autosave failed: {}", e);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+
+    fn append(&mut self, entry: JournalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(&entry).map_err(io::Error::other)?;
+        line.push('\n');
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(self.dir.join(JOURNAL_FILE))?;
+        file.write_all(line.as_bytes())?;
+        self.journal.push(entry);
+        Ok(())
+    }
+
+    fn snapshot(&mut self) -> io::Result<()> {
+        if self.autosave.replaying.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        let (revision, graph) = {
+            let _gate = self.autosave.gate.blocking_lock();
+            let revision = self.autosave.revision.load(Ordering::SeqCst);
+            if revision == self.saved_revision {
+                return Ok(());
+            }
+            (revision, self.graph.read().unwrap().clone())
+        };
+
+        let doc = SavedDocument { revision, saved_at_ms: now_ms(), graph };
+        let json = serde_json::to_vec(&doc).map_err(io::Error::other)?;
+        write_atomic(&slot_path(&self.dir, self.next_slot), &json)?;
+        self.next_slot = (self.next_slot + 1) % AUTOSAVE_SLOTS;
+        self.saved_revision = revision;
+
+        // Commands journaled while the snapshot was being written stay in the journal
+        self.journal.retain(|entry| entry.revision > revision);
+        let mut text = String::new();
+        for entry in &self.journal {
+            text.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+            text.push('\n');
+        }
+        write_atomic(&self.dir.join(JOURNAL_FILE), text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cad_core::features::types::{Feature, FeatureType, ParameterValue};
+
+    fn workspace(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cad-autosave-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn pre_crash_graph() -> FeatureGraph {
+        let mut graph = FeatureGraph::new();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch);
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Expression("@depth * 2".into()));
+        extrude.dependencies = vec![sketch.id];
+        extrude.suppressed = true;
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+        graph.variables.add(cad_core::variables::Variable::new("depth", 12.5, cad_core::variables::Unit::Dimensionless)).unwrap();
+        graph
+    }
+
+    fn write_json(path: &Path, value: &impl Serialize) {
+        write_atomic(path, &serde_json::to_vec(value).unwrap()).unwrap();
+    }
+
+    fn journal_line(revision: u64, at_ms: u64, command: &str) -> String {
+        serde_json::to_string(&JournalEntry { revision, at_ms, command: command.into() }).unwrap()
+    }
+
+    #[test]
+    fn test_recover_snapshot_and_journal_tail() {
+        let dir = workspace("recover");
+        let graph = pre_crash_graph();
+        write_json(&dir.join(DOCUMENT_FILE), &SavedDocument { revision: 0, saved_at_ms: 1_000, graph: FeatureGraph::new() });
+        // Older rotating slot must lose to the newest revision
+        write_json(&slot_path(&dir, 0), &SavedDocument { revision: 4, saved_at_ms: 2_000, graph: FeatureGraph::new() });
+        write_json(&slot_path(&dir, 1), &SavedDocument { revision: 7, saved_at_ms: 3_000, graph: graph.clone() });
+        let journal = [
+            journal_line(7, 2_900, r#"{"command":"Regen"}"#),
+            journal_line(8, 3_100, r#"{"command":"ToggleSuppression","payload":{"id":"a"}}"#),
+            journal_line(9, 3_200, r#"{"command":"ClearSelection"}"#),
+            r#"{"revision":10,"at_ms":33"#.to_string(), // torn by the crash
+        ].join("\n");
+        fs::write(dir.join(JOURNAL_FILE), journal).unwrap();
+
+        let session = recover(&dir).expect("autosave is newer than the explicit save");
+        assert_eq!(session.recovered_at_ms, 3_200);
+        assert_eq!(session.revision, 9);
+        assert_eq!(session.journal.len(), 2);
+        assert!(session.journal[0].contains("ToggleSuppression"));
+
+        let recovered = &session.graph;
+        assert_eq!(recovered.sort_order, graph.sort_order);
+        for (id, feature) in &graph.nodes {
+            let restored = &recovered.nodes[id];
+            assert_eq!((restored.suppressed, &restored.parameters, &restored.dependencies),
+                       (feature.suppressed, &feature.parameters, &feature.dependencies));
+        }
+        assert_eq!(serde_json::to_value(&recovered.variables).unwrap(), serde_json::to_value(&graph.variables).unwrap());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_explicit_save_supersedes_autosave() {
+        let dir = workspace("explicit");
+        write_json(&slot_path(&dir, 2), &SavedDocument { revision: 3, saved_at_ms: 1_000, graph: pre_crash_graph() });
+        fs::write(dir.join(JOURNAL_FILE), journal_line(4, 1_500, r#"{"command":"Regen"}"#)).unwrap();
+        assert!(recover(&dir).is_some());
+
+        save_document(&dir, &FeatureGraph::new()).unwrap();
+        assert!(recover(&dir).is_none(), "Nothing changed after the explicit save");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,355 @@
+//! Documents open in this backend process.
+//!
+//! Each document has its own feature graph, workspace directory, autosave and
+//! revision counter. A session works on one active document at a time
+//! (`SetActiveDocument`) and hears about changes other sessions make to it
+//! through the document's update channel; nothing is sent across documents.
+//!
+//! Closing a document drops the manager's handle to it. Sessions only hold
+//! document ids between commands, so it is freed as soon as the commands in
+//! flight finish, and their later commands are refused.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use cad_core::evaluator::regen_diff::RegenHistory;
+use cad_core::features::dag::FeatureGraph;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::autosave;
+
+/// Updates a lagging session may fall behind by before it resyncs
+const UPDATE_CAPACITY: usize = 64;
+
+/// Directory under the root workspace that new documents are created in
+const NEW_DOCUMENTS_DIR: &str = "documents";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DocumentId(pub uuid::Uuid);
+
+impl fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Where OpenDocument takes a document from
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum DocumentSource {
+    /// A workspace directory, with its saved document and autosave files
+    Path(PathBuf),
+    /// An empty document in a fresh workspace
+    New,
+}
+
+/// One open document
+pub struct Document {
+    pub id: DocumentId,
+    pub graph: Arc<RwLock<FeatureGraph>>,
+    pub registry: Arc<RwLock<cad_core::topo::TopoRegistry>>,
+    /// Directory holding the saved document and autosave files
+    pub workspace: PathBuf,
+    pub autosave: Arc<autosave::Autosave>,
+    /// The last two differing regenerations, for DiffLastRegen
+    pub regens: Arc<Mutex<RegenHistory>>,
+    updates: broadcast::Sender<DocumentUpdate>,
+}
+
+/// A change to a document, sent to the sessions working on it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DocumentUpdate {
+    pub document: DocumentId,
+    /// Revision after the change
+    pub revision: u64,
+    /// The document was closed; nothing more will follow
+    pub closed: bool,
+    /// Session that made the change, which has already seen it
+    #[serde(skip)]
+    pub session: u64,
+}
+
+/// A document as ListDocuments and OpenDocument report it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DocumentInfo {
+    pub id: DocumentId,
+    pub name: String,
+    pub path: PathBuf,
+    pub revision: u64,
+}
+
+impl Document {
+    /// Open the document in `workspace`, recovering autosaved edits
+    fn open(id: DocumentId, workspace: PathBuf, interval: Duration) -> Document {
+        let graph = Arc::new(RwLock::new(FeatureGraph::new()));
+        let autosave = autosave::start(workspace.clone(), interval, graph.clone());
+        graph.write().unwrap().ensure_id_namespace();
+        let (updates, _) = broadcast::channel(UPDATE_CAPACITY);
+        Document {
+            id,
+            graph,
+            registry: Arc::new(RwLock::new(cad_core::topo::TopoRegistry::new())),
+            workspace,
+            autosave,
+            regens: Arc::new(Mutex::new(RegenHistory::default())),
+            updates,
+        }
+    }
+
+    pub fn info(&self) -> DocumentInfo {
+        DocumentInfo {
+            id: self.id,
+            name: self.workspace.file_name().map_or_else(|| self.workspace.display().to_string(), |n| n.to_string_lossy().into_owned()),
+            path: self.workspace.clone(),
+            revision: self.autosave.revision(),
+        }
+    }
+
+    /// Updates made to this document from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<DocumentUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Tell the other sessions on this document that `session` changed it
+    pub fn publish(&self, session: u64) {
+        // Nobody listening is fine
+        let _ = self.updates.send(DocumentUpdate { document: self.id, revision: self.autosave.revision(), closed: false, session });
+    }
+}
+
+impl Drop for Document {
+    fn drop(&mut self) {
+        self.autosave.stop();
+    }
+}
+
+/// The open documents, by id
+pub struct DocumentManager {
+    documents: RwLock<HashMap<DocumentId, Arc<Document>>>,
+    /// Ids of documents closed since startup, for a clearer error than "unknown"
+    closed: Mutex<HashSet<DocumentId>>,
+    /// Workspace of the startup document; new documents go beneath it
+    root: PathBuf,
+    /// Autosave interval of every document
+    interval: Duration,
+    /// Document new sessions start on
+    default: RwLock<Option<DocumentId>>,
+    next_session: AtomicU64,
+}
+
+impl DocumentManager {
+    pub fn new(root: PathBuf, interval: Duration) -> DocumentManager {
+        DocumentManager {
+            documents: RwLock::new(HashMap::new()),
+            closed: Mutex::new(HashSet::new()),
+            root,
+            interval,
+            default: RwLock::new(None),
+            next_session: AtomicU64::new(1),
+        }
+    }
+
+    /// Open a document. Paths are taken relative to the root workspace and
+    /// must stay beneath it. A workspace that is already open, however its path
+    /// is spelled, is not opened twice; its document is returned. The first
+    /// document opened becomes the default.
+    pub fn open(&self, source: DocumentSource) -> Result<Arc<Document>, String> {
+        let id = DocumentId(uuid::Uuid::new_v4());
+        let workspace = match source {
+            DocumentSource::Path(path) => self.confine(&path)?,
+            DocumentSource::New => self.confine(&Path::new(NEW_DOCUMENTS_DIR).join(id.to_string()))?,
+        };
+        if let Some(open) = self.documents.read().unwrap().values().find(|doc| doc.workspace == workspace) {
+            return Ok(open.clone());
+        }
+        // Loading and recovery read files, so other sessions' lookups don't wait on them
+        let document = Arc::new(Document::open(id, workspace, self.interval));
+        let mut documents = self.documents.write().unwrap();
+        if let Some(open) = documents.values().find(|doc| doc.workspace == document.workspace) {
+            // Another session opened it meanwhile. Ours has made no edits and
+            // replays nothing, so dropping it writes nothing over theirs.
+            return Ok(open.clone());
+        }
+        documents.insert(id, document.clone());
+        self.default.write().unwrap().get_or_insert(id);
+        Ok(document)
+    }
+
+    /// Canonical form of workspace `path`, resolved against the root, or an
+    /// error if it leads outside the root. The workspace need not exist yet:
+    /// its deepest existing ancestor is canonicalized (following any links)
+    /// and the rest of the path applied to it.
+    fn confine(&self, path: &Path) -> Result<PathBuf, String> {
+        std::fs::create_dir_all(&self.root).map_err(|e| format!("Cannot create workspace root: {}", e))?;
+        let root = self.root.canonicalize().map_err(|e| format!("Cannot resolve workspace root: {}", e))?;
+        let joined = root.join(path);
+        let components: Vec<Component> = joined.components().collect();
+        let existing = (0..=components.len()).rev()
+            .find(|&n| components[..n].iter().collect::<PathBuf>().exists())
+            .unwrap_or(0);
+        let mut resolved = components[..existing].iter().collect::<PathBuf>().canonicalize()
+            .map_err(|e| format!("Cannot resolve '{}': {}", path.display(), e))?;
+        for component in &components[existing..] {
+            match component {
+                Component::Normal(name) => resolved.push(name),
+                Component::CurDir => {}
+                Component::ParentDir => { resolved.pop(); }
+                _ => return Err(format!("Invalid workspace path '{}'", path.display())),
+            }
+        }
+        if !resolved.starts_with(&root) {
+            return Err(format!("Workspace '{}' is outside the root workspace", path.display()));
+        }
+        Ok(resolved)
+    }
+
+    /// The open document `id`
+    pub fn get(&self, id: DocumentId) -> Result<Arc<Document>, String> {
+        if let Some(document) = self.documents.read().unwrap().get(&id) {
+            return Ok(document.clone());
+        }
+        if self.closed.lock().unwrap().contains(&id) {
+            Err(format!("Document {} has been closed", id))
+        } else {
+            Err(format!("No open document {}", id))
+        }
+    }
+
+    /// Close document `id`, telling its sessions. Its memory is released once
+    /// commands already running on it finish.
+    pub fn close(&self, id: DocumentId) -> Result<(), String> {
+        let document = self.documents.write().unwrap().remove(&id);
+        let document = document.ok_or_else(|| self.get(id).err().unwrap_or_default())?;
+        self.closed.lock().unwrap().insert(id);
+        let mut default = self.default.write().unwrap();
+        if *default == Some(id) {
+            *default = None;
+        }
+        let _ = document.updates.send(DocumentUpdate { document: id, revision: document.autosave.revision(), closed: true, session: 0 });
+        Ok(())
+    }
+
+    /// The open documents, oldest workspace path first
+    pub fn list(&self) -> Vec<DocumentInfo> {
+        let mut infos: Vec<DocumentInfo> = self.documents.read().unwrap().values().map(|doc| doc.info()).collect();
+        infos.sort_by(|a, b| a.path.cmp(&b.path));
+        infos
+    }
+
+    /// Document a new session starts on: the first one opened, while it is open
+    pub fn default_document(&self) -> Option<DocumentId> {
+        *self.default.read().unwrap()
+    }
+
+    /// Id for a new session, to tell its own updates from others'
+    pub fn next_session_id(&self) -> u64 {
+        self.next_session.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cad_core::features::types::{Feature, FeatureType};
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    fn manager(name: &str) -> (DocumentManager, PathBuf) {
+        let root = std::env::temp_dir().join(format!("cad-documents-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        (DocumentManager::new(root.clone(), autosave::DEFAULT_INTERVAL), root)
+    }
+
+    #[test]
+    fn test_documents_are_separate_and_updates_stay_with_their_document() {
+        let (manager, root) = manager("separate");
+        let first = manager.open(DocumentSource::Path(root.clone())).unwrap();
+        let second = manager.open(DocumentSource::New).unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(manager.default_document(), Some(first.id));
+        assert_eq!(manager.list().len(), 2);
+        assert_eq!(manager.open(DocumentSource::Path(root.clone())).unwrap().id, first.id, "Already open");
+
+        let mut on_first = first.subscribe();
+        let mut on_second = second.subscribe();
+        first.graph.write().unwrap().add_node(Feature::new("Point1", FeatureType::Point));
+        first.autosave.record(r#"{"command":"CreateFeature"}"#);
+        first.publish(7);
+
+        let update = on_first.try_recv().unwrap();
+        assert_eq!((update.document, update.revision, update.session, update.closed), (first.id, 1, 7, false));
+        assert_eq!(on_second.try_recv(), Err(TryRecvError::Empty), "The other document's sessions hear nothing");
+        assert!(second.graph.read().unwrap().nodes.is_empty());
+        assert_eq!(second.autosave.revision(), 0);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_paths_stay_under_the_root_and_open_once() {
+        let (manager, root) = manager("confine");
+        let part = manager.open(DocumentSource::Path("parts/x".into())).unwrap();
+        assert!(part.workspace.ends_with("parts/x") && part.workspace.is_absolute());
+        for spelling in ["./parts/x", "parts/./x", "parts/y/../x"] {
+            assert_eq!(manager.open(DocumentSource::Path(spelling.into())).unwrap().id, part.id, "{} is already open", spelling);
+        }
+        let absolute = root.canonicalize().unwrap().join("parts").join("x");
+        assert_eq!(manager.open(DocumentSource::Path(absolute)).unwrap().id, part.id);
+
+        for outside in ["..", "../elsewhere", "parts/../../elsewhere", "/tmp"] {
+            let error = manager.open(DocumentSource::Path(outside.into())).err().unwrap();
+            assert!(error.contains("outside the root"), "{}: {}", outside, error);
+        }
+        assert_eq!(manager.list().len(), 1);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_concurrent_opens_of_a_workspace_share_one_document() {
+        let (manager, root) = manager("race");
+        let manager = Arc::new(manager);
+        let start = Arc::new(std::sync::Barrier::new(8));
+        let opens: Vec<_> = (0..8).map(|_| {
+            let (manager, start) = (manager.clone(), start.clone());
+            std::thread::spawn(move || {
+                start.wait();
+                manager.open(DocumentSource::Path("shared".into())).unwrap().id
+            })
+        }).collect();
+        let ids: HashSet<DocumentId> = opens.into_iter().map(|open| open.join().unwrap()).collect();
+        assert_eq!(ids.len(), 1, "Every session got the same document");
+        assert_eq!(manager.list().len(), 1);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_closing_refuses_commands_and_releases_the_document() {
+        let (manager, root) = manager("close");
+        let document = manager.open(DocumentSource::New).unwrap();
+        let id = document.id;
+        let mut updates = document.subscribe();
+        let graph = Arc::downgrade(&document.graph);
+        drop(document);
+
+        manager.close(id).unwrap();
+        let update = updates.try_recv().unwrap();
+        assert!(update.closed);
+        assert_eq!(updates.try_recv(), Err(TryRecvError::Closed), "The channel went with the document");
+        let error = manager.get(id).err().unwrap();
+        assert!(error.contains("has been closed"), "{}", error);
+        assert!(manager.close(id).is_err());
+        assert_eq!(manager.default_document(), None);
+        assert!(manager.get(DocumentId(uuid::Uuid::new_v4())).err().unwrap().starts_with("No open document"));
+
+        // The autosave worker lets go of the graph once it has taken its last snapshot
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while graph.upgrade().is_some() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(graph.upgrade().is_none(), "Closed document's graph is freed");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
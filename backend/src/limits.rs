@@ -0,0 +1,384 @@
+//! Message size caps and per-session rate limits for the WebSocket handler.
+//!
+//! Every text or binary message over `max_message_bytes` is answered with a
+//! LIMIT_EXCEEDED error instead of being handled. Commands are sorted into
+//! classes, each with a token bucket: a command over its class's rate is
+//! deferred until a token is free, and while anything is deferred later
+//! commands wait behind it so they still run in order. At most
+//! `max_outstanding` commands wait; beyond that they are rejected. A sweep
+//! regenerates once per row, so specs over `max_sweep_rows` are rejected too.
+//!
+//! Time comes from a `Clock`, so the limiter is tested without tokio's clock.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use cad_core::errors::{CadError, ErrorCode};
+
+/// Largest message handled by default
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+/// Commands a session may have waiting by default
+pub const DEFAULT_MAX_OUTSTANDING: usize = 32;
+/// Rows a sweep may evaluate by default
+pub const DEFAULT_MAX_SWEEP_ROWS: usize = 1000;
+/// The transport refuses messages this many times over the cap outright; those
+/// between the two get an error the session can carry on after
+const TRANSPORT_HEADROOM: usize = 4;
+
+/// Close code for a message too large to even read (RFC 6455 "Message Too Big")
+pub const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
+/// A token bucket's refill rate and size. A rate of zero or less leaves its class unlimited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub per_second: f64,
+    /// Commands that can run back to back after a quiet spell
+    pub burst: f64,
+}
+
+impl Rate {
+    pub const fn per_second(per_second: f64) -> Rate {
+        Rate { per_second, burst: per_second }
+    }
+}
+
+/// What a command costs the server, for rate limiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandClass {
+    /// A full regeneration
+    Regen,
+    /// Pointer-driven queries sent while hovering or dragging
+    Hover,
+    /// Edits to the document
+    Mutation,
+    /// Everything else
+    Query,
+}
+
+impl CommandClass {
+    const ALL: [CommandClass; 4] = [CommandClass::Regen, CommandClass::Hover, CommandClass::Mutation, CommandClass::Query];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn limit_name(self) -> &'static str {
+        match self {
+            CommandClass::Regen => "regen_rate",
+            CommandClass::Hover => "hover_rate",
+            CommandClass::Mutation => "mutation_rate",
+            CommandClass::Query => "query_rate",
+        }
+    }
+}
+
+/// Limits every WebSocket session is held to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerLimits {
+    pub max_message_bytes: usize,
+    pub regen: Rate,
+    pub hover: Rate,
+    pub mutation: Rate,
+    pub query: Rate,
+    /// Commands a session may have deferred at once
+    pub max_outstanding: usize,
+    /// Rows a single sweep may evaluate
+    pub max_sweep_rows: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        ServerLimits {
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            regen: Rate::per_second(2.0),
+            hover: Rate::per_second(60.0),
+            mutation: Rate::per_second(20.0),
+            query: Rate::per_second(30.0),
+            max_outstanding: DEFAULT_MAX_OUTSTANDING,
+            max_sweep_rows: DEFAULT_MAX_SWEEP_ROWS,
+        }
+    }
+}
+
+impl ServerLimits {
+    pub fn rate(&self, class: CommandClass) -> Rate {
+        match class {
+            CommandClass::Regen => self.regen,
+            CommandClass::Hover => self.hover,
+            CommandClass::Mutation => self.mutation,
+            CommandClass::Query => self.query,
+        }
+    }
+
+    /// Largest message the transport reads at all
+    pub fn transport_max_bytes(&self) -> usize {
+        self.max_message_bytes.saturating_mul(TRANSPORT_HEADROOM)
+    }
+
+    /// Error for a message of `len` bytes, if it is over the cap
+    pub fn check_message_size(&self, len: usize) -> Result<(), CadError> {
+        if len <= self.max_message_bytes {
+            return Ok(());
+        }
+        Err(limit_error(
+            format!("Message of {} bytes is over the {} byte limit", len, self.max_message_bytes),
+            "max_message_bytes",
+            self.max_message_bytes,
+        ))
+    }
+
+    /// Error for a sweep of `rows` rows, if it is over the cap
+    pub fn check_sweep_rows(&self, rows: usize) -> Result<(), CadError> {
+        if rows <= self.max_sweep_rows {
+            return Ok(());
+        }
+        Err(limit_error(
+            format!("Sweep of {} rows is over the {} row limit", rows, self.max_sweep_rows),
+            "max_sweep_rows",
+            self.max_sweep_rows,
+        ))
+    }
+}
+
+fn limit_error(message: String, limit: &str, max: impl serde::Serialize) -> CadError {
+    CadError::new(ErrorCode::LimitExceeded, message).with_detail("limit", limit).with_detail("max", max)
+}
+
+/// Source of the current time
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// One token bucket per command class
+pub struct RateLimiter<C: Clock = SystemClock> {
+    limits: ServerLimits,
+    clock: C,
+    buckets: [Bucket; 4],
+}
+
+impl<C: Clock> RateLimiter<C> {
+    /// A limiter with every bucket full as of `clock.now()`
+    pub fn with_clock(limits: ServerLimits, clock: C) -> RateLimiter<C> {
+        let now = clock.now();
+        let buckets = CommandClass::ALL.map(|class| Bucket { tokens: limits.rate(class).burst, updated: now });
+        RateLimiter { limits, clock, buckets }
+    }
+
+    fn refill(&mut self, class: CommandClass) -> &mut Bucket {
+        let now = self.clock.now();
+        let rate = self.limits.rate(class);
+        let bucket = &mut self.buckets[class.index()];
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate.per_second).min(rate.burst);
+        bucket.updated = now;
+        bucket
+    }
+
+    /// Take a token for a `class` command, or say how long until one is free
+    pub fn try_acquire(&mut self, class: CommandClass) -> Result<(), Duration> {
+        let per_second = self.limits.rate(class).per_second;
+        if per_second <= 0.0 {
+            return Ok(());
+        }
+        let bucket = self.refill(class);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+    }
+
+    /// When a `class` command could next run, without taking the token
+    pub fn available_at(&mut self, class: CommandClass) -> Instant {
+        let per_second = self.limits.rate(class).per_second;
+        if per_second <= 0.0 {
+            return self.clock.now();
+        }
+        let bucket = *self.refill(class);
+        if bucket.tokens >= 1.0 {
+            return bucket.updated;
+        }
+        bucket.updated + Duration::from_secs_f64((1.0 - bucket.tokens) / per_second)
+    }
+}
+
+/// What to do with a command just received
+#[derive(Debug, Clone, PartialEq)]
+pub enum Admission {
+    Run,
+    /// Held until its rate allows; `Throttle::take_ready` hands it back
+    Deferred,
+    /// Too much is already waiting; the client gets this error
+    Rejected(CadError),
+}
+
+/// A session's rate limiter and the commands it has deferred, in arrival order
+pub struct Throttle<C: Clock = SystemClock> {
+    limiter: RateLimiter<C>,
+    max_outstanding: usize,
+    deferred: VecDeque<(CommandClass, String)>,
+}
+
+impl Throttle {
+    pub fn new(limits: ServerLimits) -> Throttle {
+        Throttle::with_clock(limits, SystemClock)
+    }
+}
+
+impl<C: Clock> Throttle<C> {
+    pub fn with_clock(limits: ServerLimits, clock: C) -> Throttle<C> {
+        Throttle { limiter: RateLimiter::with_clock(limits, clock), max_outstanding: limits.max_outstanding, deferred: VecDeque::new() }
+    }
+
+    /// Run, defer or reject the `class` command `text`
+    pub fn admit(&mut self, class: CommandClass, text: &str) -> Admission {
+        if self.deferred.is_empty() && self.limiter.try_acquire(class).is_ok() {
+            return Admission::Run;
+        }
+        if self.deferred.len() >= self.max_outstanding {
+            let rate = self.limiter.limits.rate(class).per_second;
+            return Admission::Rejected(limit_error(
+                format!("{} commands are already waiting; {} is limited to {} per second", self.deferred.len(), class.limit_name(), rate),
+                "max_outstanding",
+                self.max_outstanding,
+            ));
+        }
+        self.deferred.push_back((class, text.to_string()));
+        Admission::Deferred
+    }
+
+    /// When the oldest deferred command can run; None when nothing waits
+    pub fn ready_at(&mut self) -> Option<Instant> {
+        let class = self.deferred.front()?.0;
+        Some(self.limiter.available_at(class))
+    }
+
+    /// The oldest deferred command, once its rate allows it to run
+    pub fn take_ready(&mut self) -> Option<String> {
+        let class = self.deferred.front()?.0;
+        self.limiter.try_acquire(class).ok()?;
+        self.deferred.pop_front().map(|(_, text)| text)
+    }
+
+    pub fn outstanding(&self) -> usize {
+        self.deferred.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// A clock tests move by hand
+    #[derive(Clone)]
+    struct ManualClock(Rc<Cell<Instant>>);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            self.0.set(self.0.get() + by);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    fn clock() -> ManualClock {
+        ManualClock(Rc::new(Cell::new(Instant::now())))
+    }
+
+    #[test]
+    fn test_buckets_refill_at_their_class_rate() {
+        let clock = clock();
+        let mut limiter = RateLimiter::with_clock(ServerLimits::default(), clock.clone());
+        assert!(limiter.try_acquire(CommandClass::Regen).is_ok());
+        assert!(limiter.try_acquire(CommandClass::Regen).is_ok());
+        assert_eq!(limiter.try_acquire(CommandClass::Regen), Err(Duration::from_millis(500)));
+        // Other classes have buckets of their own
+        for _ in 0..60 {
+            assert!(limiter.try_acquire(CommandClass::Hover).is_ok());
+        }
+        assert!(limiter.try_acquire(CommandClass::Hover).is_err());
+        assert!(limiter.try_acquire(CommandClass::Mutation).is_ok());
+
+        clock.advance(Duration::from_millis(499));
+        assert!(limiter.try_acquire(CommandClass::Regen).is_err());
+        clock.advance(Duration::from_millis(1));
+        assert!(limiter.try_acquire(CommandClass::Regen).is_ok());
+        // A long quiet spell refills no more than the burst
+        clock.advance(Duration::from_secs(60));
+        for _ in 0..2 {
+            assert!(limiter.try_acquire(CommandClass::Regen).is_ok());
+        }
+        assert!(limiter.try_acquire(CommandClass::Regen).is_err());
+
+        let unlimited = ServerLimits { query: Rate::per_second(0.0), ..ServerLimits::default() };
+        let mut limiter = RateLimiter::with_clock(unlimited, clock);
+        assert!((0..1000).all(|_| limiter.try_acquire(CommandClass::Query).is_ok()));
+    }
+
+    #[test]
+    fn test_commands_over_the_rate_wait_in_order_up_to_the_cap() {
+        let clock = clock();
+        let limits = ServerLimits { max_outstanding: 2, ..ServerLimits::default() };
+        let mut throttle = Throttle::with_clock(limits, clock.clone());
+        assert_eq!(throttle.admit(CommandClass::Regen, "regen 1"), Admission::Run);
+        assert_eq!(throttle.admit(CommandClass::Regen, "regen 2"), Admission::Run);
+        assert_eq!(throttle.ready_at(), None);
+        assert_eq!(throttle.admit(CommandClass::Regen, "regen 3"), Admission::Deferred);
+        // Has a token, but runs after the Regen it arrived behind
+        assert_eq!(throttle.admit(CommandClass::Mutation, "edit"), Admission::Deferred);
+        let Admission::Rejected(error) = throttle.admit(CommandClass::Hover, "hover") else { panic!("Over the cap") };
+        assert_eq!(error.code, ErrorCode::LimitExceeded);
+        assert_eq!(error.details["limit"], "max_outstanding");
+        assert_eq!(error.details["max"], 2);
+        assert_eq!(throttle.outstanding(), 2);
+
+        assert_eq!(throttle.ready_at(), Some(clock.now() + Duration::from_millis(500)));
+        assert_eq!(throttle.take_ready(), None);
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(throttle.take_ready().as_deref(), Some("regen 3"));
+        assert_eq!(throttle.ready_at(), Some(clock.now()));
+        assert_eq!(throttle.take_ready().as_deref(), Some("edit"));
+        assert_eq!(throttle.take_ready(), None);
+        assert_eq!(throttle.admit(CommandClass::Hover, "hover"), Admission::Run);
+    }
+
+    #[test]
+    fn test_messages_and_sweeps_over_their_caps_name_the_limit() {
+        let limits = ServerLimits { max_message_bytes: 1024, ..ServerLimits::default() };
+        assert!(limits.check_message_size(1024).is_ok());
+        let error = limits.check_message_size(100 * 1024 * 1024).unwrap_err();
+        assert_eq!(error.code, ErrorCode::LimitExceeded);
+        assert_eq!(error.details["limit"], "max_message_bytes");
+        assert_eq!(error.details["max"], 1024);
+        assert!(error.message.contains("104857600 bytes"), "{}", error.message);
+        assert_eq!(limits.transport_max_bytes(), 4096);
+
+        let limits = ServerLimits { max_sweep_rows: 10, ..ServerLimits::default() };
+        assert!(limits.check_sweep_rows(10).is_ok());
+        let error = limits.check_sweep_rows(11).unwrap_err();
+        assert_eq!(error.code, ErrorCode::LimitExceeded);
+        assert_eq!(error.details["limit"], "max_sweep_rows");
+        assert_eq!(error.details["max"], 10);
+    }
+}
@@ -1,32 +1,464 @@
 use axum::{
-    extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    extract::State,
+    extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+    extract::{FromRef, Path, Query, State},
+    http::{header, StatusCode},
     response::IntoResponse,
     routing::get,
-    Router,
+    Json, Router,
 };
 use std::net::SocketAddr;
 use tower_http::trace::TraceLayer;
 use tracing::{info, warn};
 use futures::{stream::StreamExt, SinkExt};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use cad_core::features::dag::FeatureGraph;
+use cad_core::features::snapshot::{GraphSnapshot, Regeneration};
 use serde::Deserialize;
 use serde_json::json;
+use cad_core::errors::{CadError, ErrorCode, Severity};
 
-/// Format a kernel error as a JSON message for the frontend
-fn format_error(code: &str, message: &str, severity: &str) -> String {
-    format!("ERROR_UPDATE:{}", json!({
-        "code": code,
-        "message": message,
-        "severity": severity
-    }))
+mod autosave;
+mod diff;
+mod documents;
+mod limits;
+mod sessions;
+
+use documents::{Document, DocumentId, DocumentManager, DocumentSource};
+use limits::{Admission, CommandClass, ServerLimits, Throttle};
+use sessions::{Registration, SessionConfig, SessionRegistry};
+
+/// ERROR_UPDATE message for `error`
+fn format_error(error: &CadError) -> String {
+    format!("ERROR_UPDATE:{}", serde_json::to_string(error).unwrap_or("{}".into()))
 }
 
-// Application State
-struct AppState {
-    graph: Arc<RwLock<FeatureGraph>>,
-    registry: Arc<RwLock<cad_core::topo::TopoRegistry>>,
+/// ERROR_UPDATE for an evaluation that stopped, after `action` ("Section failed").
+/// The code, and the feature to blame if there is one, come from the error
+fn format_evaluation_error(action: &str, error: cad_core::evaluator::runtime::KernelError) -> String {
+    let error = CadError::from(error);
+    format_error(&CadError { message: format!("{}: {}", action, error.message), ..error })
+}
+
+/// How long after the last UpdateSketchLive its edits are committed, by default
+const LIVE_SKETCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(750);
+
+/// Longest slice of an offending command echoed back in a COMMAND_ERROR
+const MAX_ECHOED_COMMAND_LEN: usize = 512;
+
+/// Build the COMMAND_ERROR message for a command that could not be parsed or dispatched.
+/// The offending text is echoed back (truncated) so the client can tell which request failed.
+fn format_command_error(message: &str, text: &str) -> String {
+    let echoed: String = text.chars().take(MAX_ECHOED_COMMAND_LEN).collect();
+    format_error(&CadError::new(ErrorCode::CommandError, format!("{} (command: {})", message, echoed)).with_detail("command", &echoed))
+}
+
+/// Parse a raw WebSocket text frame into a command.
+/// On failure, returns the COMMAND_ERROR message to send back to the client.
+fn parse_command(text: &str) -> Result<WebSocketCommand, String> {
+    serde_json::from_str(text)
+        .map_err(|e| format_command_error(&format!("Failed to parse command: {}", e), text))
+}
+
+/// Check that every feature/variable id a command refers to exists in the graph,
+/// so commands on stale ids fail loudly instead of being silently ignored.
+fn validate_command_refs(command: &WebSocketCommand, graph: &FeatureGraph) -> Result<(), String> {
+    use cad_core::topo::EntityId;
+
+    let feature = |id: &uuid::Uuid| -> Result<(), String> {
+        if graph.nodes.contains_key(&EntityId::from_uuid(*id)) {
+            Ok(())
+        } else {
+            Err(format!("Unknown feature id: {}", id))
+        }
+    };
+    let variable = |id: &uuid::Uuid| -> Result<(), String> {
+        if graph.variables.get(EntityId::from_uuid(*id)).is_some() {
+            Ok(())
+        } else {
+            Err(format!("Unknown variable id: {}", id))
+        }
+    };
+
+    match command {
+        WebSocketCommand::CreateFeature(cmd) => {
+            cmd.dependencies.iter().flatten().try_for_each(feature)
+        }
+        WebSocketCommand::UpdateFeature(cmd) => feature(&cmd.id),
+        WebSocketCommand::UpdateSketchLive { feature_id, .. } => feature(feature_id),
+        WebSocketCommand::DeleteFeature { id, .. }
+        | WebSocketCommand::GetRegions { id }
+        | WebSocketCommand::GetSolvedSketch { id }
+        | WebSocketCommand::ToggleSuppression { id }
+        | WebSocketCommand::ToggleVisibility { id }
+        | WebSocketCommand::ReorderFeature { id, .. } => feature(id),
+        WebSocketCommand::PickRegion { feature_id, .. }
+        | WebSocketCommand::GetRegionMedialAxis { feature_id, .. } => feature(feature_id),
+        WebSocketCommand::SetRollback { id }
+        | WebSocketCommand::EvaluateAt { rollback_id: id } => id.iter().try_for_each(feature),
+        WebSocketCommand::InsertFeature { after_id, dependencies, .. } => {
+            after_id.iter().try_for_each(feature)?;
+            dependencies.iter().flatten().try_for_each(feature)
+        }
+        WebSocketCommand::ProjectEntity { sketch_id, .. }
+        | WebSocketCommand::PatternSketchOnPlanes { sketch_id, .. }
+        | WebSocketCommand::GetConstraintGraph { sketch_id }
+        | WebSocketCommand::GetDimensionValues { sketch_id }
+        | WebSocketCommand::CleanupConstraints { sketch_id } => feature(sketch_id),
+        WebSocketCommand::QuerySnap { feature_id, .. }
+        | WebSocketCommand::ProbeSketch { sketch_id: feature_id, .. }
+        | WebSocketCommand::PreviewConstraint { sketch_id: feature_id, .. }
+        | WebSocketCommand::SplitEntity { feature_id, .. }
+        | WebSocketCommand::TrimEntity { feature_id, .. }
+        | WebSocketCommand::ExtendEntity { feature_id, .. }
+        | WebSocketCommand::PatternAlongPath { feature_id, .. }
+        | WebSocketCommand::AddSketchEntity { feature_id, .. }
+        | WebSocketCommand::ApplyPreset { feature_id, .. }
+        | WebSocketCommand::SuppressConstraintGroup { feature_id, .. }
+        | WebSocketCommand::ExplodeChain { feature_id, .. }
+        | WebSocketCommand::DeleteSketchEntity { feature_id, .. }
+        | WebSocketCommand::UpdateSketchEntityMeta { feature_id, .. }
+        | WebSocketCommand::ConvertSketchEntity { feature_id, .. }
+        | WebSocketCommand::ReplaceReference { feature_id, .. }
+        | WebSocketCommand::ApplyZombieRepair { owner: feature_id, .. } => feature(feature_id),
+        WebSocketCommand::SetFeatureMetadata { id, .. }
+        | WebSocketCommand::IsolateFeature { id } => feature(id),
+        WebSocketCommand::CopyFeatures { ids, sketch_id } => match sketch_id {
+            Some(sketch_id) => feature(sketch_id),
+            None => ids.iter().try_for_each(feature),
+        },
+        WebSocketCommand::PasteFeatures { target, .. } => target.iter().try_for_each(feature),
+        WebSocketCommand::CreateOffsetProfileFromFace { target_sketch, .. } => target_sketch.iter().try_for_each(feature),
+        WebSocketCommand::VariableUpdate(cmd) => variable(&cmd.id),
+        WebSocketCommand::VariableDelete { id }
+        | WebSocketCommand::VariableReorder { id, .. } => variable(id),
+        _ => Ok(()),
+    }
+}
+
+/// REGIONS_UPDATE payload: regions of the solved sketch, so the profiles offered
+/// match what regeneration (which solves first) will extrude, plus diagnostics
+/// for curves that don't close into a profile. The stored sketch is left untouched.
+fn solved_regions_payload(sketch: &cad_core::sketch::types::Sketch) -> serde_json::Value {
+    let mut solved = sketch.clone();
+    let solve = cad_core::sketch::solver::SketchSolver::solve_with_result(&mut solved);
+    let regions = cad_core::sketch::regions::find_regions(&solved.entities);
+    serde_json::json!({
+        "solve": solve,
+        "regions": cad_core::sketch::regions::summarize_regions(&regions),
+        "diagnostics": cad_core::sketch::chains::profile_diagnostics(&solved.entities, cad_core::sketch::chains::DEFAULT_CHAIN_TOLERANCE),
+    })
+}
+
+/// SOLVED_SKETCH payload: the entities with solved coordinates, from a copy
+/// so the stored sketch keeps its drawn positions
+fn solved_sketch_payload(sketch: &cad_core::sketch::types::Sketch) -> serde_json::Value {
+    let mut solved = sketch.clone();
+    let solve = cad_core::sketch::solver::SketchSolver::solve_with_result(&mut solved);
+    serde_json::json!({
+        "entities": solved.entities,
+        "solve": solve,
+    })
+}
+
+/// Build the feature a CreateFeature asks for. Parameters from the command
+/// win over those taken from `selected`.
+fn new_feature(
+    cmd: CreateCmd,
+    graph: &FeatureGraph,
+    registry: &cad_core::topo::TopoRegistry,
+    selected: &std::collections::HashSet<cad_core::topo::naming::TopoId>,
+) -> Result<cad_core::features::types::Feature, String> {
+    let f_type = match cmd.feature_type.as_str() {
+        "Sketch" => cad_core::features::types::FeatureType::Sketch,
+        "Extrude" => cad_core::features::types::FeatureType::Extrude,
+        "Revolve" => cad_core::features::types::FeatureType::Revolve,
+        "Fillet" => cad_core::features::types::FeatureType::Fillet,
+        "Chamfer" => cad_core::features::types::FeatureType::Chamfer,
+        "Boolean" => cad_core::features::types::FeatureType::Boolean,
+        "Plane" => cad_core::features::types::FeatureType::Plane,
+        "Axis" => cad_core::features::types::FeatureType::Axis,
+        "Point" => cad_core::features::types::FeatureType::Point,
+        "LinearPattern" => cad_core::features::types::FeatureType::LinearPattern,
+        "CircularPattern" => cad_core::features::types::FeatureType::CircularPattern,
+        "DeleteFace" => cad_core::features::types::FeatureType::DeleteFace,
+        "DatumPlane" => cad_core::features::types::FeatureType::DatumPlane,
+        "DatumAxis" => cad_core::features::types::FeatureType::DatumAxis,
+        "Box" => cad_core::features::types::FeatureType::Box,
+        "Torus" => cad_core::features::types::FeatureType::Torus,
+        "Cylinder" => cad_core::features::types::FeatureType::Cylinder,
+        _ => {
+            warn!("Unknown feature type: {}", cmd.feature_type);
+            cad_core::features::types::FeatureType::Point
+        }
+    };
+
+    let mut feature = cad_core::features::types::Feature::new(&cmd.name, f_type);
+    if let Some(deps) = cmd.dependencies {
+        feature.dependencies = deps.into_iter().map(cad_core::topo::EntityId::from_uuid).collect();
+    }
+
+    if cmd.from_selection {
+        let defaults = cad_core::features::from_selection::defaults_from_selection(graph, registry, feature.feature_type.clone(), selected)?;
+        if feature.dependencies.is_empty() {
+            feature.dependencies = defaults.dependencies;
+        }
+        feature.parameters.extend(defaults.params);
+    }
+    feature.parameters.extend(cmd.initial_params.unwrap_or_default());
+    Ok(feature)
+}
+
+/// Merge an UpdateFeature's parameters. A sketch is solved straight away,
+/// with its full analysis deferred; it is returned with the solve status.
+fn update_feature(
+    graph: &mut FeatureGraph,
+    cmd: UpdateCmd,
+) -> Result<Option<(&cad_core::sketch::types::Sketch, cad_core::sketch::solver::SolveResult)>, String> {
+    let entity_id = cad_core::topo::EntityId::from_uuid(cmd.id);
+    for value in cmd.params.values() {
+        if let cad_core::features::types::ParameterValue::Sketch(sketch) = value {
+            sketch.validate_entity_names()?;
+        }
+    }
+    graph.update_feature_params(entity_id, cmd.params)?;
+    let node = graph.nodes.get_mut(&entity_id).ok_or("Feature not found")?;
+    if node.feature_type != cad_core::features::types::FeatureType::Sketch {
+        return Ok(None);
+    }
+    match node.parameters.get_mut("sketch_data") {
+        Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+            let result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+            Ok(Some((&*sketch, result)))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn add_variable(graph: &mut FeatureGraph, cmd: VariableAddCmd) -> Result<(), String> {
+    let unit = cmd.unit.unwrap_or(cad_core::variables::Unit::Dimensionless);
+    let mut var = cad_core::variables::Variable::with_expression(&cmd.name, &cmd.expression, unit);
+    if let Some(desc) = cmd.description {
+        var.description = desc;
+    }
+    graph.variables.add(var)?;
+    cad_core::variables::evaluator::evaluate_all(&mut graph.variables);
+    Ok(())
+}
+
+/// Apply a VariableUpdate's fields in order, stopping at the first that fails
+fn update_variable(graph: &mut FeatureGraph, cmd: VariableUpdateCmd) -> Result<(), String> {
+    let entity_id = cad_core::topo::EntityId::from_uuid(cmd.id);
+    if let Some(ref name) = cmd.name {
+        graph.variables.update_name(entity_id, name)?;
+    }
+    if let Some(ref expr) = cmd.expression {
+        graph.variables.update_expression(entity_id, expr)?;
+    }
+    if let Some(unit) = cmd.unit {
+        graph.variables.update_unit(entity_id, unit)?;
+    }
+    if let Some(ref desc) = cmd.description {
+        graph.variables.update_description(entity_id, desc)?;
+    }
+    cad_core::variables::evaluator::evaluate_all(&mut graph.variables);
+    Ok(())
+}
+
+/// Apply a Batch's sub-commands to a copy of `graph`, each checked against
+/// the edits before it; the caller regenerates once afterwards. `graph` is
+/// only replaced when every sub-command succeeds. Returns the deletions made,
+/// so they can be undone like any other.
+fn apply_batch(
+    graph: &mut FeatureGraph,
+    registry: &cad_core::topo::TopoRegistry,
+    selected: &std::collections::HashSet<cad_core::topo::naming::TopoId>,
+    commands: Vec<WebSocketCommand>,
+) -> Result<Vec<cad_core::features::delete::Deletion>, String> {
+    let mut working = graph.clone();
+    let mut deletions = Vec::new();
+    for (i, command) in commands.into_iter().enumerate() {
+        let applied = validate_command_refs(&command, &working).and_then(|_| match command {
+            WebSocketCommand::CreateFeature(cmd) => {
+                let seed = cmd.seed.clone();
+                let mut feature = new_feature(cmd, &working, registry, selected)?;
+                // A batch is applied whole or not at all, so a seed that
+                // collides fails it rather than being quietly re-minted
+                if let Some(warning) = working.assign_feature_id(&mut feature, seed.as_deref()) {
+                    return Err(warning);
+                }
+                working.add_node(feature);
+                Ok(())
+            }
+            WebSocketCommand::UpdateFeature(cmd) => update_feature(&mut working, cmd).map(|_| ()),
+            WebSocketCommand::DeleteFeature { id, policy, dry_run: false } => {
+                deletions.push(working.delete_feature(cad_core::topo::EntityId::from_uuid(id), policy)?);
+                Ok(())
+            }
+            WebSocketCommand::VariableAdd(cmd) => add_variable(&mut working, cmd),
+            WebSocketCommand::VariableUpdate(cmd) => update_variable(&mut working, cmd),
+            WebSocketCommand::VariableDelete { id } => {
+                working.variables.remove(cad_core::topo::EntityId::from_uuid(id)).ok_or("Variable not found")?;
+                cad_core::variables::evaluator::evaluate_all(&mut working.variables);
+                Ok(())
+            }
+            WebSocketCommand::ToggleSuppression { id } => working.toggle_suppression(cad_core::topo::EntityId::from_uuid(id)).map(|_| ()),
+            WebSocketCommand::ToggleVisibility { id } => working.toggle_visibility(cad_core::topo::EntityId::from_uuid(id)).map(|_| ()),
+            _ => Err("Only feature and variable edits can be batched".to_string()),
+        });
+        applied.map_err(|e| format!("Batch command {} failed: {}", i, e))?;
+    }
+    *graph = working;
+    Ok(deletions)
+}
+
+/// How one session views its document. These options aren't shared with other
+/// sessions on the document, and changing them re-renders the session's last
+/// regeneration rather than regenerating.
+struct SessionView {
+    /// Fraction of triangles kept when decimating RENDER_UPDATE meshes (1.0 = full detail)
+    mesh_quality: f64,
+    /// Feature whose geometry alone is sent in RENDER_UPDATE, if any
+    isolated_feature: Option<cad_core::topo::EntityId>,
+    /// Include the center of mass in RENDER_UPDATE, for the viewport marker
+    show_center_of_mass: bool,
+    /// Include texture coordinates in RENDER_UPDATE (SetRenderOptions)
+    render_uvs: bool,
+    /// Model of the last regeneration, before isolation and decimation, with
+    /// the surfaces UVs are made from
+    last_render: Option<(cad_core::geometry::Tessellation, std::collections::HashMap<cad_core::topo::naming::TopoId, cad_core::topo::registry::KernelEntity>)>,
+}
+
+impl Default for SessionView {
+    fn default() -> Self {
+        SessionView { mesh_quality: 1.0, isolated_feature: None, show_center_of_mass: false, render_uvs: false, last_render: None }
+    }
+}
+
+impl SessionView {
+    /// RENDER_UPDATE for the last regeneration as this session views it, or
+    /// None before the first. An isolated feature that has since been deleted
+    /// ends isolation.
+    fn render(&mut self, state: &Document) -> Option<String> {
+        let (tessellation, surfaces) = self.last_render.as_ref()?;
+        let mut tessellation = tessellation.clone();
+        if let Some(id) = self.isolated_feature {
+            if state.graph.read().unwrap().isolate(id, &mut tessellation).is_err() {
+                self.isolated_feature = None;
+            }
+        }
+        let uv_surfaces = self.render_uvs.then_some(surfaces);
+        Some(render_update(tessellation, self.mesh_quality, self.show_center_of_mass, uv_surfaces))
+    }
+}
+
+/// RENDER_UPDATE message for a regenerated model. The center of mass is taken
+/// from the full mesh, so the marker doesn't move with the mesh quality, and
+/// attached after decimating. UVs are made from `uv_surfaces`, if given, after
+/// decimating, which drops them.
+fn render_update(
+    mut tessellation: cad_core::geometry::Tessellation,
+    mesh_quality: f64,
+    show_center_of_mass: bool,
+    uv_surfaces: Option<&std::collections::HashMap<cad_core::topo::naming::TopoId, cad_core::topo::registry::KernelEntity>>,
+) -> String {
+    let center_of_mass = show_center_of_mass
+        .then(|| cad_core::analysis::mesh_center_of_mass(&tessellation).map(|p| [p.x, p.y, p.z]))
+        .flatten();
+    if mesh_quality < 1.0 {
+        tessellation.decimate(mesh_quality);
+    }
+    tessellation.center_of_mass = center_of_mass;
+    if let Some(surfaces) = uv_surfaces {
+        tessellation.generate_uvs(surfaces);
+    }
+    let json = serde_json::to_string(&tessellation).unwrap_or("{}".into());
+    format!("RENDER_UPDATE:{}", json)
+}
+
+/// Program for the shared graph as it is now, regenerated from a snapshot so
+/// the lock is only held while copying
+fn current_program(state: &Document) -> Regeneration {
+    state.graph.read().unwrap().snapshot_for_serialization().regenerated().1
+}
+
+/// Evaluate a snapshot of the current graph, for read-only queries that need
+/// the model but must not disturb the shared graph or the client's view
+fn evaluate_snapshot(
+    state: &Document,
+    runtime: &cad_core::evaluator::Runtime,
+    generator: &cad_core::topo::IdGenerator,
+) -> Result<cad_core::evaluator::runtime::EvaluationResult, cad_core::evaluator::runtime::KernelError> {
+    runtime.evaluate(&current_program(state).program, generator)
+}
+
+/// Commands that change the document. These are journaled for crash recovery.
+fn is_document_command(command: &WebSocketCommand) -> bool {
+    if let WebSocketCommand::Batch(commands) = command {
+        return commands.iter().any(is_document_command);
+    }
+    matches!(command,
+        WebSocketCommand::CreateFeature(_)
+        | WebSocketCommand::UpdateFeature(_)
+        | WebSocketCommand::UpdateSketchLive { .. }
+        | WebSocketCommand::CommitSketch
+        | WebSocketCommand::DeleteFeature { dry_run: false, .. }
+        | WebSocketCommand::UndoDelete
+        | WebSocketCommand::RevertToLastGood
+        | WebSocketCommand::VariableAdd(_)
+        | WebSocketCommand::VariableUpdate(_)
+        | WebSocketCommand::VariableDelete { .. }
+        | WebSocketCommand::VariableReorder { .. }
+        | WebSocketCommand::ToggleSuppression { .. }
+        | WebSocketCommand::ToggleVisibility { .. }
+        | WebSocketCommand::RenameBody { .. }
+        | WebSocketCommand::SetRollback { .. }
+        | WebSocketCommand::ReorderFeature { .. }
+        | WebSocketCommand::InsertFeature { .. }
+        | WebSocketCommand::ProjectEntity { .. }
+        | WebSocketCommand::CreateOffsetProfileFromFace { .. }
+        | WebSocketCommand::PatternSketchOnPlanes { .. }
+        | WebSocketCommand::SplitEntity { .. }
+        | WebSocketCommand::TrimEntity { .. }
+        | WebSocketCommand::ExtendEntity { .. }
+        | WebSocketCommand::PatternAlongPath { .. }
+        | WebSocketCommand::AddSketchEntity { .. }
+        | WebSocketCommand::ApplyPreset { .. }
+        | WebSocketCommand::SuppressConstraintGroup { .. }
+        | WebSocketCommand::ExplodeChain { .. }
+        | WebSocketCommand::DeleteSketchEntity { .. }
+        | WebSocketCommand::UpdateSketchEntityMeta { .. }
+        | WebSocketCommand::ConvertSketchEntity { .. }
+        | WebSocketCommand::CleanupConstraints { .. }
+        | WebSocketCommand::PasteFeatures { .. }
+        | WebSocketCommand::SetFeatureMetadata { .. }
+        | WebSocketCommand::SetDocumentProperties { .. }
+        | WebSocketCommand::PublishMeasurement { .. }
+        | WebSocketCommand::ReplaceReference { .. }
+        | WebSocketCommand::ApplyZombieRepair { .. }
+        | WebSocketCommand::ImportStep { .. })
+}
+
+/// Rate-limit class of a command: pointer-driven queries sent while hovering
+/// or dragging, document edits, and regeneration each have their own rate
+fn command_class(command: &WebSocketCommand) -> CommandClass {
+    match command {
+        // Everything that evaluates the whole model, once or once per row
+        WebSocketCommand::Regen
+        | WebSocketCommand::RunSweep(_)
+        | WebSocketCommand::RunAnalysis(_)
+        | WebSocketCommand::ImportStep { .. }
+        | WebSocketCommand::EvaluateAt { .. }
+        | WebSocketCommand::DraftAnalysis { .. }
+        | WebSocketCommand::GetProjectedArea { .. }
+        | WebSocketCommand::GetSection { .. }
+        | WebSocketCommand::GetReport { .. } => CommandClass::Regen,
+        WebSocketCommand::Select(_)
+        | WebSocketCommand::QuerySnap { .. }
+        | WebSocketCommand::ProbeSketch { .. }
+        | WebSocketCommand::PreviewConstraint { .. } => CommandClass::Hover,
+        command if is_document_command(command) => CommandClass::Mutation,
+        _ => CommandClass::Query,
+    }
 }
 
 // --- API Protocol Definitions ---
@@ -40,21 +472,200 @@ enum WebSocketCommand {
     ClearSelection,
     CreateFeature(CreateCmd),
     UpdateFeature(UpdateCmd),
-    DeleteFeature { id: uuid::Uuid },
+    /// Solve and redraw a sketch being edited, without regenerating the model.
+    /// Replies with SKETCH_STATUS and SKETCH_RENDER; features built from the
+    /// sketch are pending rebuild until CommitSketch or the debounce runs out.
+    UpdateSketchLive { feature_id: uuid::Uuid, sketch_data: cad_core::sketch::types::Sketch },
+    /// Regenerate after live sketch edits
+    CommitSketch,
+    /// Commit live sketch edits this long after the last one; 0 waits for CommitSketch
+    SetLiveSketchDebounce { ms: u64 },
+    /// Delete a feature, dealing with its dependents per `policy`. With
+    /// `dry_run`, only reports what would be removed and orphaned.
+    DeleteFeature {
+        id: uuid::Uuid,
+        #[serde(default)]
+        policy: cad_core::features::delete::DeletePolicy,
+        #[serde(default)]
+        dry_run: bool,
+    },
+    /// Restore the features removed by this session's last DeleteFeature
+    UndoDelete,
+    /// Put the document back as it was at its last clean regeneration,
+    /// discarding the edits since (for when an edit leaves it failing)
+    RevertToLastGood,
+    /// Apply several document edits as one transaction: sub-commands run in
+    /// order without regenerating, then the model regenerates once. If any
+    /// sub-command fails, none of them take effect.
+    Batch(Vec<WebSocketCommand>),
     VariableAdd(VariableAddCmd),
     VariableUpdate(VariableUpdateCmd),
     VariableDelete { id: uuid::Uuid },
     VariableReorder { id: uuid::Uuid, new_index: usize },
     GetRegions { id: uuid::Uuid },
+    /// The sketch's entities as the server solves them, so clients need not solve themselves
+    GetSolvedSketch { id: uuid::Uuid },
+    PickRegion { feature_id: uuid::Uuid, point: [f64; 2] },
+    /// Approximate centerline of a region, by the `stable_id` GetRegions reports.
+    /// Without a spacing the boundary is sampled relative to the region's size.
+    GetRegionMedialAxis {
+        feature_id: uuid::Uuid,
+        region_key: String,
+        #[serde(default)]
+        sample_spacing: Option<f64>,
+    },
+    GetConstraintGraph { sketch_id: uuid::Uuid },
+    /// Measured value of each dimension once the sketch is solved; replies DIMENSION_VALUES
+    GetDimensionValues { sketch_id: uuid::Uuid },
+    GetDependencyGraph,
+    QuerySnap {
+        feature_id: uuid::Uuid,
+        #[serde(flatten)]
+        query: cad_core::sketch::snap::SnapQuery,
+    },
+    /// Nearest snappable entity feature to a point, for coordinate readouts
+    ProbeSketch { sketch_id: uuid::Uuid, point: [f64; 2] },
+    /// Solve with a constraint the user is about to add, without adding it
+    PreviewConstraint { sketch_id: uuid::Uuid, constraint: cad_core::sketch::types::SketchConstraint },
     SelectionGroupCreate { name: String },
     SelectionGroupRestore { name: String },
     SelectionGroupDelete { name: String },
     SelectionGroupsList,
+    GetSelectionBounds,
+    /// Select every active entity matching the query; "add" keeps the current selection
+    SelectByGeometry {
+        query: cad_core::topo::registry::GeometryQuery,
+        modifier: Option<String>,
+    },
+    MeasureEntity { id: cad_core::topo::naming::TopoId },
+    GetSection { origin: [f64; 3], normal: [f64; 3] },
+    /// Classify every face by its draft towards `pull_dir`, needing at least
+    /// `min_angle` degrees (1 if omitted); replies DRAFT_ANALYSIS
+    DraftAnalysis { pull_dir: [f64; 3], min_angle: Option<f64> },
+    /// Silhouette area of the model seen along `dir`; replies PROJECTED_AREA
+    GetProjectedArea { dir: [f64; 3] },
+    GetTopologyManifest,
+    /// Geometry hash and counts of the current model, for regression checks
+    GetGeometryHash,
+    /// Evaluate the model as if rolled back to `rollback_id` (None for all of
+    /// it) without moving the rollback bar; replies PREVIEW_UPDATE with the
+    /// tessellation and geometry hash, leaving the live render alone
+    EvaluateAt { rollback_id: Option<uuid::Uuid> },
+    /// Faces, edges and vertices the last regeneration that changed the model
+    /// added, removed or moved; replies REGEN_DIFF
+    DiffLastRegen,
     ToggleSuppression { id: uuid::Uuid },
+    /// Hide or show a feature's geometry without removing it from the model
+    ToggleVisibility { id: uuid::Uuid },
     SetRollback { id: Option<uuid::Uuid> },
     ReorderFeature { id: uuid::Uuid, new_index: usize },
     InsertFeature { feature_type: String, name: String, after_id: Option<uuid::Uuid>, dependencies: Option<Vec<uuid::Uuid>> },
     ProjectEntity { sketch_id: uuid::Uuid, topo_id: cad_core::topo::naming::TopoId },
+    /// Draw a planar face's outline, offset `offset` into the face, in `target_sketch`
+    /// (which must be on the face) or a new sketch on it. It follows the face on regeneration.
+    CreateOffsetProfileFromFace {
+        face: cad_core::topo::naming::TopoId,
+        offset: f64,
+        #[serde(default)]
+        target_sketch: Option<uuid::Uuid>,
+    },
+    RunSweep(cad_core::analysis::SweepSpec),
+    /// Wall thickness or stability check on one body; replies ANALYSIS_UPDATE
+    RunAnalysis(cad_core::analysis::AnalysisSpec),
+    /// Hole table or feature parameter tables; the reply is REPORT_UPDATE
+    GetReport { kind: cad_core::reporting::ReportKind },
+    PatternSketchOnPlanes { sketch_id: uuid::Uuid, normal: [f64; 3], count: usize, spacing: f64 },
+    SplitEntity { feature_id: uuid::Uuid, entity_id: uuid::Uuid, at: cad_core::sketch::edit::SplitAt },
+    /// Trim the line `target` back to where it crosses `cutting`, keeping the side `keep_point` is on
+    TrimEntity { feature_id: uuid::Uuid, target: uuid::Uuid, cutting: uuid::Uuid, keep_point: [f64; 2] },
+    /// Extend the line `target` from its end nearest `near_point` until it meets `cutting`
+    ExtendEntity { feature_id: uuid::Uuid, target: uuid::Uuid, cutting: uuid::Uuid, near_point: [f64; 2] },
+    /// Add an entity to a sketch, either as geometry or as an arc drawn by three
+    /// points, center/start/end, or tangent continuation; replies SKETCH_ENTITY_ADDED
+    AddSketchEntity { feature_id: uuid::Uuid, entity: cad_core::sketch::edit::EntitySpec },
+    /// Copy `entity_ids` to `count` evenly spaced stations along the chain through `path`
+    PatternAlongPath { feature_id: uuid::Uuid, entity_ids: Vec<uuid::Uuid>, path: uuid::Uuid, count: usize },
+    ListPresets,
+    /// Apply a preset to entities given by id or name
+    ApplyPreset { feature_id: uuid::Uuid, name: String, entity_ids: Vec<cad_core::sketch::meta::EntityRef> },
+    /// Suppress or unsuppress every constraint in a sketch's constraint group
+    SuppressConstraintGroup { feature_id: uuid::Uuid, group: String, suppressed: bool },
+    /// Constrain the joints of the chain through `entity_id` so its curves can be edited one by one
+    ExplodeChain { feature_id: uuid::Uuid, entity_id: uuid::Uuid },
+    /// Delete a sketch entity and the constraints that refer to it
+    DeleteSketchEntity { feature_id: uuid::Uuid, entity_id: uuid::Uuid },
+    /// Set a sketch entity's name, color or lock; only a lock change re-solves
+    UpdateSketchEntityMeta { feature_id: uuid::Uuid, entity_id: uuid::Uuid, meta: cad_core::sketch::meta::EntityMetaUpdate },
+    /// Turn an arc into its circle or a circle into an arc, keeping the entity id;
+    /// replies SKETCH_ENTITY_CONVERTED
+    ConvertSketchEntity { feature_id: uuid::Uuid, entity_id: uuid::Uuid, to: cad_core::sketch::edit::EntityConversion },
+    /// Delete a sketch's redundant constraints, keeping one of each duplicate set
+    CleanupConstraints { sketch_id: uuid::Uuid },
+    /// Copy features, or with `sketch_id` entities of that sketch
+    CopyFeatures {
+        ids: Vec<uuid::Uuid>,
+        #[serde(default)]
+        sketch_id: Option<uuid::Uuid>,
+    },
+    /// Paste a CLIPBOARD payload. `remap` maps the payload's external dependencies to
+    /// features of this document; `target` is the sketch receiving sketch entities.
+    PasteFeatures {
+        payload: cad_core::features::clipboard::ClipboardPayload,
+        #[serde(default)]
+        remap: std::collections::HashMap<uuid::Uuid, uuid::Uuid>,
+        #[serde(default)]
+        target: Option<uuid::Uuid>,
+    },
+    SetFeatureMetadata { id: uuid::Uuid, patch: cad_core::features::metadata::MetadataPatch },
+    /// Publish a face's area as a read-only variable, `@name`
+    PublishMeasurement { name: String, face: cad_core::topo::naming::TopoId },
+    /// Repoint a feature's reference, e.g. from a lost face to its replacement
+    ReplaceReference {
+        feature_id: uuid::Uuid,
+        old: cad_core::topo::naming::TopoId,
+        new: cad_core::topo::naming::TopoId,
+    },
+    /// Carry out one of the repairs a ZOMBIE_UPDATE suggested for a lost reference
+    ApplyZombieRepair {
+        owner: uuid::Uuid,
+        param_path: String,
+        action: cad_core::topo::registry::RepairAction,
+    },
+    SetDocumentProperties {
+        #[serde(default)]
+        set: std::collections::HashMap<String, cad_core::features::metadata::PropertyValue>,
+        #[serde(default)]
+        remove: Vec<String>,
+    },
+    FindFeatures { query: cad_core::features::metadata::FeatureQuery },
+    SetMeshQuality { ratio: f64 },
+    /// Report the center of mass with every RENDER_UPDATE, or stop
+    ShowCenterOfMass { show: bool },
+    /// Session render settings: `uvs` adds texture coordinates to RENDER_UPDATE
+    SetRenderOptions { uvs: bool },
+    /// Render only the geometry feature `id` produced, until ClearIsolation
+    IsolateFeature { id: uuid::Uuid },
+    ClearIsolation,
+    SetCurveResolution { segments: usize },
+    SaveDocument,
+    /// Open a workspace (`{"Path": "..."}`) or a new empty document (`"New"`)
+    /// and make it this session's active document
+    OpenDocument(DocumentSource),
+    /// Close a document for every session; their commands on it fail from then on
+    CloseDocument { id: DocumentId },
+    ListDocuments,
+    /// Document commands without a `document` field apply to
+    SetActiveDocument { id: DocumentId },
+    /// Name a body ("Housing"); an empty name clears it
+    RenameBody { body_id: cad_core::topo::naming::TopoId, name: String },
+    /// One file per body, by id or name; all bodies when `bodies` is empty
+    ExportBodies {
+        #[serde(default)]
+        bodies: Vec<cad_core::features::bodies::BodyRef>,
+        format: cad_core::features::bodies::ExportFormat,
+    },
+    /// Add the solids of a STEP file (its text) as ImportedBody features
+    ImportStep { data: String },
 }
 
 #[derive(Deserialize, Debug)]
@@ -69,13 +680,25 @@ struct CreateCmd {
     feature_type: String, 
     name: String,
     dependencies: Option<Vec<uuid::Uuid>>,
-    params: Option<std::collections::HashMap<String, cad_core::features::types::ParameterValue>>,
+    /// Parameters to start with; these win over anything taken from the selection
+    #[serde(alias = "params")]
+    initial_params: Option<std::collections::HashMap<String, cad_core::features::types::ParameterValue>>,
+    /// Fill in parameters and inputs from the current selection
+    #[serde(default)]
+    from_selection: bool,
+    /// Derive the new feature's id from this string and the document's id
+    /// namespace, so scripted construction gets the same ids every time
+    #[serde(default)]
+    seed: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 struct UpdateCmd {
     id: uuid::Uuid,
     params: std::collections::HashMap<String, cad_core::features::types::ParameterValue>,
+    /// Sketch entities, by id or name, whose lengths and areas SKETCH_STATUS should total
+    #[serde(default)]
+    highlight_entities: Vec<cad_core::sketch::meta::EntityRef>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -100,19 +723,38 @@ struct VariableUpdateCmd {
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "--diff") {
+        std::process::exit(diff::run(&args[1..]));
+    }
+
     tracing_subscriber::fmt::init();
 
-    let shared_state = Arc::new(AppState {
-        graph: Arc::new(RwLock::new(FeatureGraph::new())),
-        registry: Arc::new(RwLock::new(cad_core::topo::TopoRegistry::new())),
-    });
+    // Autosave configuration: CAD_WORKSPACE (directory), CAD_AUTOSAVE_SECS (interval)
+    let workspace = std::path::PathBuf::from(std::env::var("CAD_WORKSPACE").unwrap_or_else(|_| "workspace".into()));
+    let interval = std::env::var("CAD_AUTOSAVE_SECS").ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(autosave::DEFAULT_INTERVAL);
+
+    // The workspace is the document sessions start on; documents opened as New go beneath it
+    let manager = Arc::new(DocumentManager::new(workspace.clone(), interval));
+    manager.open(DocumentSource::Path(workspace)).expect("Workspace directory is not usable");
+
+    // Session limits: CAD_SESSION_IDLE_SECS (idle TTL), CAD_MAX_CONNECTIONS.
+    // Message size and command rates use ServerLimits' defaults
+    let sessions = Arc::new(SessionRegistry::new(SessionConfig::from_env()));
+    sessions.spawn_sweeper();
 
     // build our application with a route
     let app = Router::new()
         .route("/", get(root))
         .route("/ws", get(ws_handler))
+        .route("/report/:kind", get(report_csv))
+        .route("/stats", get(stats))
         .layer(TraceLayer::new_for_http())
-        .with_state(shared_state);
+        .with_state(AppState { documents: manager, sessions, limits: ServerLimits::default() });
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     info!("listening on {}", addr);
@@ -120,69 +762,489 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+/// Router state: the open documents, the connected sessions and their limits
+#[derive(Clone)]
+struct AppState {
+    documents: Arc<DocumentManager>,
+    sessions: Arc<SessionRegistry>,
+    limits: ServerLimits,
+}
+
+impl FromRef<AppState> for ServerLimits {
+    fn from_ref(state: &AppState) -> Self {
+        state.limits
+    }
+}
+
+impl FromRef<AppState> for Arc<DocumentManager> {
+    fn from_ref(state: &AppState) -> Self {
+        state.documents.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<SessionRegistry> {
+    fn from_ref(state: &AppState) -> Self {
+        state.sessions.clone()
+    }
+}
+
 async fn root() -> &'static str {
     "Hello from CAD Backend!"
 }
 
+/// Session counters and the number of open documents, as JSON
+async fn stats(
+    State(manager): State<Arc<DocumentManager>>,
+    State(sessions): State<Arc<SessionRegistry>>,
+) -> impl IntoResponse {
+    Json(json!({ "sessions": sessions.stats(), "documents": manager.list().len() }))
+}
+
+#[derive(Deserialize)]
+struct ReportQuery {
+    document: Option<DocumentId>,
+}
+
+/// A report table as CSV: `/report/Holes` or `/report/Features`, for the
+/// startup document unless `?document=<id>` names another
+async fn report_csv(
+    Path(kind): Path<cad_core::reporting::ReportKind>,
+    Query(query): Query<ReportQuery>,
+    State(manager): State<Arc<DocumentManager>>,
+) -> impl IntoResponse {
+    let state = match query.document.or(manager.default_document()).ok_or_else(|| "No document is open".to_string()).and_then(|id| manager.get(id)) {
+        Ok(document) => document,
+        Err(e) => return (StatusCode::NOT_FOUND, e).into_response(),
+    };
+    let generator = cad_core::topo::IdGenerator::new(&state.graph.read().unwrap().id_namespace);
+    match evaluate_snapshot(&state, &cad_core::evaluator::Runtime::new(), &generator) {
+        Ok(result) => {
+            let report = cad_core::reporting::feature_summary(&state.graph.read().unwrap(), &result);
+            ([(header::CONTENT_TYPE, "text/csv")], report.to_csv(kind)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Report failed: {}", e)).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SessionQuery {
+    /// Reconnect token from an earlier SESSION message
+    token: Option<uuid::Uuid>,
+}
+
 async fn ws_handler(
     ws: WebSocketUpgrade,
-    State(state): State<Arc<AppState>>,
+    Query(query): Query<SessionQuery>,
+    State(manager): State<Arc<DocumentManager>>,
+    State(sessions): State<Arc<SessionRegistry>>,
+    State(limits): State<ServerLimits>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    ws.max_message_size(limits.transport_max_bytes())
+        .max_frame_size(limits.transport_max_bytes())
+        .on_upgrade(move |socket| handle_socket(socket, manager, sessions, limits, query.token))
+}
+
+/// Close frame for a session the idle sweep evicted
+fn evicted_close_frame(sessions: &SessionRegistry) -> Message {
+    let reason = format!("Idle for over {} s", sessions.config().idle_ttl.as_secs());
+    Message::Close(Some(CloseFrame { code: sessions::CLOSE_GOING_AWAY, reason: reason.into() }))
+}
+
+/// Redundancy/conflict analysis of the last solved sketch, running on a
+/// blocking task so the solve's SKETCH_STATUS goes out without waiting for it
+struct PendingAnalysis {
+    cancel: cad_core::sketch::solver::AnalysisCancel,
+    task: tokio::task::JoinHandle<Option<cad_core::sketch::solver::SketchAnalysis>>,
+    /// Feature of the analyzed sketch
+    sketch_id: cad_core::topo::EntityId,
+}
+
+impl PendingAnalysis {
+    /// Start analyzing a sketch solved with `solve_deferred`, cancelling the
+    /// analysis of an earlier solve
+    fn restart(
+        pending: &mut Option<PendingAnalysis>,
+        sketch_id: cad_core::topo::EntityId,
+        sketch: &cad_core::sketch::types::Sketch,
+        solved: &cad_core::sketch::solver::SolveResult,
+    ) {
+        if let Some(previous) = pending.take() {
+            previous.cancel.cancel();
+        }
+        let cancel = cad_core::sketch::solver::AnalysisCancel::default();
+        let (sketch, solved, token) = (sketch.clone(), solved.clone(), cancel.clone());
+        let task = tokio::task::spawn_blocking(move || {
+            cad_core::sketch::solver::SketchSolver::analyze_cancellable(&sketch, &solved, &token)
+        });
+        *pending = Some(PendingAnalysis { cancel, task, sketch_id });
+    }
+}
+
+/// Flag the constraints on projected geometry whose model reference the last
+/// regeneration no longer has, for a SKETCH_STATUS
+fn flag_stale_external(
+    result: &mut cad_core::sketch::solver::SolveResult,
+    sketch: &cad_core::sketch::types::Sketch,
+    registry: &cad_core::topo::TopoRegistry,
+) {
+    let stale = sketch.external_references.iter()
+        .filter(|(_, topo_id)| registry.resolve(topo_id).is_none())
+        .map(|(entity, _)| *entity)
+        .collect();
+    result.flag_stale_external(sketch, &stale);
+}
+
+/// SKETCH_SOLVE_FAILED for an analysis that found constraints the solve left unsatisfied
+fn solve_failure(sketch_id: cad_core::topo::EntityId, analysis: &cad_core::sketch::solver::SketchAnalysis) -> Option<CadError> {
+    let unsatisfied = &analysis.conflicts.as_ref()?.unsatisfied_constraints;
+    if unsatisfied.is_empty() {
+        return None;
+    }
+    let message = format!("Sketch constraints could not all be satisfied ({} unsatisfied)", unsatisfied.len());
+    Some(CadError::new(ErrorCode::SketchSolveFailed, message).with_detail("sketch_id", sketch_id).with_detail("constraint_indices", unsatisfied))
+}
+
+/// Make `document` the session's active document: send its graph (and the
+/// recovery notice), regenerate it, and follow other sessions' changes to it
+async fn attach(
+    socket: &mut WebSocket,
+    runtime: &cad_core::evaluator::Runtime,
+    generator: &cad_core::topo::IdGenerator,
+    document: &Arc<Document>,
+    selection_state: &mut cad_core::topo::SelectionState,
+    view: &mut SessionView,
+) -> tokio::sync::broadcast::Receiver<documents::DocumentUpdate> {
+    let updates = document.subscribe();
+    selection_state.clear();
+    let (json, program) = document.graph.read().unwrap().snapshot_for_serialization().regenerated();
+    let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+    if let Some(recovered_at_ms) = document.autosave.recovered_at_ms {
+        let notice = json!({ "recovered_at_ms": recovered_at_ms });
+        let _ = socket.send(Message::Text(format!("RECOVERED_SESSION:{}", notice))).await;
+    }
+    // Send the tessellation so the viewport shows the document straight away
+    process_regen(socket, runtime, generator, &program, document, selection_state, view).await;
+    updates
+}
+
+/// The `document` a command names alongside `command` and `payload`, if any
+fn command_document(text: &str) -> Option<DocumentId> {
+    let envelope: serde_json::Value = serde_json::from_str(text).ok()?;
+    serde_json::from_value(envelope.get("document")?.clone()).ok()
+}
+
+/// Document a command runs on: the session's active one. A command may name
+/// its document, to guard against running on the wrong one, but not another:
+/// the session's selection and its replies belong to the active document.
+fn target_document(named: Option<DocumentId>, active: Option<DocumentId>) -> Result<DocumentId, String> {
+    let active = active.ok_or_else(|| "No active document; send OpenDocument or SetActiveDocument first".to_string())?;
+    match named {
+        Some(named) if named != active => Err(format!("Document {} is not active; send SetActiveDocument first", named)),
+        _ => Ok(active),
+    }
 }
 
-async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
+async fn handle_socket(mut socket: WebSocket, manager: Arc<DocumentManager>, sessions: Arc<SessionRegistry>, limits: ServerLimits, token: Option<uuid::Uuid>) {
     info!("Client connected");
-    
-    // Send initial graph state
-    let program = {
-        let json = {
-            let graph = state.graph.read().unwrap();
-            serde_json::to_string(&*graph).unwrap_or("{}".to_string())
-        };
-        
-        if socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await.is_err() {
+    let session_id = manager.next_session_id();
+    let (session, restored) = match sessions.connect(session_id, token) {
+        Ok(connected) => connected,
+        Err(rejected) => {
+            warn!("Refusing connection: already at {} sessions", rejected.max_connections);
+            let reason = json!({ "code": "TooManyConnections", "max_connections": rejected.max_connections }).to_string();
+            let _ = socket.send(Message::Close(Some(CloseFrame { code: sessions::CLOSE_TRY_AGAIN_LATER, reason: reason.into() }))).await;
             return;
         }
-        
-        // Generate initial program for tessellation
-        let mut graph = state.graph.write().unwrap();
-        graph.regenerate()
     };
+    let _registration = Registration::new(sessions.clone(), session.clone());
 
-    let runtime = cad_core::evaluator::Runtime::new();
-    let generator = cad_core::topo::IdGenerator::new("Session1"); 
+    let mut runtime = cad_core::evaluator::Runtime::new();
+    // Features take their ids from their own EntityIds when evaluated; anything
+    // else comes from the document's namespace, not the connection
+    let mut generators: std::collections::HashMap<DocumentId, cad_core::topo::IdGenerator> = std::collections::HashMap::new();
+    let mut generator_for = move |document: &Document| {
+        generators.entry(document.id)
+            .or_insert_with(|| cad_core::topo::IdGenerator::new(&document.graph.read().unwrap().id_namespace))
+            .clone()
+    };
     let mut selection_state = cad_core::topo::SelectionState::new();
-    
-    // Send initial tessellation so viewport shows content on page load
-    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
+    let mut view = SessionView::default();
+    // Deletes this session can undo, most recent last
+    let mut deletions: Vec<cad_core::features::delete::Deletion> = Vec::new();
+    let mut pending_analysis: Option<PendingAnalysis> = None;
+    // When uncommitted live sketch edits get committed, and how long after an edit that is
+    let mut live_commit_at: Option<tokio::time::Instant> = None;
+    let mut live_debounce = Some(LIVE_SKETCH_DEBOUNCE);
+    // Document that commands without a `document` field apply to, and other
+    // sessions' changes to it
+    let mut active = restored.as_ref().and_then(|state| state.document).filter(|id| manager.get(*id).is_ok())
+        .or(manager.default_document());
+    let mut updates = None;
+    // Commands journaled before a crash are replayed through the first session
+    // to open the document, which is held until they are done
+    let mut replay = std::collections::VecDeque::new();
+    let mut replaying: Option<Arc<Document>> = None;
 
-    while let Some(msg) = socket.recv().await {
-        let msg = if let Ok(msg) = msg {
-            msg
-        } else {
+    if let Some(document) = active.and_then(|id| manager.get(id).ok()) {
+        let generator = generator_for(&document);
+        updates = Some(attach(&mut socket, &runtime, &generator, &document, &mut selection_state, &mut view).await);
+        replay = document.autosave.take_replay();
+        replaying = (!replay.is_empty()).then_some(document);
+    }
+    let restored = match restored {
+        Some(state) => {
+            selection_state = state.selection;
+            broadcast_selection(&mut socket, &selection_state).await;
+            true
+        }
+        None => false,
+    };
+    let _ = socket.send(Message::Text(format!("SESSION:{}", json!({ "token": session.token, "restored": restored })))).await;
+    // Held while a message is handled, so the idle sweep can't evict mid-command
+    let mut busy = None;
+    // Commands over their rate, waiting their turn
+    let mut throttle = Throttle::new(limits);
+
+    loop {
+        session.save(active, &selection_state);
+        drop(busy.take());
+        // Replayed and deferred commands have been through the limits already
+        let (msg, throttled) = match replay.pop_front().or_else(|| throttle.take_ready()) {
+            Some(text) => (Message::Text(text), false),
+            None => {
+                if let Some(document) = replaying.take() {
+                    document.autosave.finish_replay();
+                }
+                // Deliver a finished sketch analysis while waiting for the next command
+                let next = async {
+                    match pending_analysis.as_mut() {
+                        Some(pending) => tokio::select! {
+                            analysis = &mut pending.task => Err((pending.sketch_id, analysis)),
+                            msg = socket.recv() => Ok(msg),
+                        },
+                        None => Ok(socket.recv().await),
+                    }
+                };
+                // Live sketch edits left alone past the debounce are committed
+                let next = async {
+                    match live_commit_at {
+                        Some(deadline) => tokio::select! {
+                            received = next => received,
+                            _ = tokio::time::sleep_until(deadline) => {
+                                Ok(Some(Ok(Message::Text(r#"{"command": "CommitSketch"}"#.to_string()))))
+                            }
+                        },
+                        None => next.await,
+                    }
+                };
+                // Sessions evicted while idle end as if the client had left
+                let next = async {
+                    tokio::select! {
+                        received = next => received,
+                        _ = session.evicted() => Ok(None),
+                    }
+                };
+                // Changes other sessions make to the active document
+                let update = async {
+                    match updates.as_mut() {
+                        Some(updates) => updates.recv().await,
+                        None => std::future::pending().await,
+                    }
+                };
+                // The oldest deferred command's rate allowing it to run
+                let deferred_ready = throttle.ready_at();
+                let deferred = async {
+                    match deferred_ready {
+                        Some(at) => tokio::time::sleep_until(at.into()).await,
+                        None => std::future::pending().await,
+                    }
+                };
+                let received = tokio::select! {
+                    received = next => Ok(received),
+                    update = update => Err(update),
+                    _ = deferred => continue,
+                };
+                let received = match received {
+                    Ok(received) => received,
+                    Err(Err(tokio::sync::broadcast::error::RecvError::Closed)) => {
+                        updates = None;
+                        continue;
+                    }
+                    Err(Ok(update)) if update.closed => {
+                        updates = None;
+                        let _ = socket.send(Message::Text(format!("DOCUMENT_CLOSED:{}", json!({ "id": update.document })))).await;
+                        continue;
+                    }
+                    Err(Ok(update)) if update.session == session_id => continue,
+                    // Changed by another session, or so often this one fell behind
+                    Err(update) => {
+                        let Some(document) = active.and_then(|id| manager.get(id).ok()) else { continue };
+                        if let Ok(update) = update {
+                            let _ = socket.send(Message::Text(format!("DOCUMENT_UPDATE:{}", json!(update)))).await;
+                        }
+                        let (json, program) = document.graph.read().unwrap().snapshot_for_serialization().regenerated();
+                        let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                        let generator = generator_for(&document);
+                        process_regen(&mut socket, &runtime, &generator, &program, &document, &mut selection_state, &mut view).await;
+                        continue;
+                    }
+                };
+                match received {
+                    Ok(Some(Ok(msg))) => (msg, true),
+                    Ok(Some(Err(e))) if e.to_string().contains("too long") => {
+                        // Over the transport's cap, so it was never read; say why before closing
+                        warn!("Closing session {}: {}", session_id, e);
+                        let reason = json!({ "code": "LimitExceeded", "max_message_bytes": limits.max_message_bytes }).to_string();
+                        let _ = socket.send(Message::Close(Some(CloseFrame { code: limits::CLOSE_MESSAGE_TOO_BIG, reason: reason.into() }))).await;
+                        return;
+                    }
+                    Ok(_) if session.is_evicted() => {
+                        let _ = socket.send(evicted_close_frame(&sessions)).await;
+                        return;
+                    }
+                    Ok(_) => return,
+                    Err((sketch_id, analysis)) => {
+                        pending_analysis = None;
+                        if let Ok(Some(analysis)) = analysis {
+                            let json = serde_json::to_string(&analysis).unwrap_or("{}".into());
+                            let _ = socket.send(Message::Text(format!("SKETCH_ANALYSIS:{}", json))).await;
+                            if let Some(error) = solve_failure(sketch_id, &analysis) {
+                                let _ = socket.send(Message::Text(format_error(&error))).await;
+                            }
+                        }
+                        continue;
+                    }
+                }
+            }
+        };
+
+        busy = Some(session.begin().await);
+        if session.is_evicted() {
+            let _ = socket.send(evicted_close_frame(&sessions)).await;
             return;
+        }
+
+        let size = match &msg {
+            Message::Text(text) => text.len(),
+            Message::Binary(data) => data.len(),
+            _ => 0,
         };
+        if let Err(error) = limits.check_message_size(size) {
+            warn!("Rejected message: {}", error);
+            let _ = socket.send(Message::Text(format_error(&error))).await;
+            continue;
+        }
 
         if let Message::Text(text) = msg {
             // New Logic: Parse JSON Command
-            let command: WebSocketCommand = match serde_json::from_str(&text) {
+            let command = match parse_command(&text) {
                 Ok(cmd) => cmd,
-                Err(e) => {
-                    warn!("Failed to parse command '{}': {}", text, e);
+                Err(error) => {
+                    warn!("Failed to parse command '{}'", text);
+                    let _ = socket.send(Message::Text(error)).await;
                     continue;
                 }
             };
+
+            if throttled {
+                match throttle.admit(command_class(&command), &text) {
+                    Admission::Run => {}
+                    Admission::Deferred => {
+                        info!("Deferred command over its rate; {} waiting", throttle.outstanding());
+                        continue;
+                    }
+                    Admission::Rejected(error) => {
+                        warn!("Rejected command: {}", error);
+                        let _ = socket.send(Message::Text(format_error(&error))).await;
+                        continue;
+                    }
+                }
+            }
             
             info!("Received command: {:?}", command);
 
+            // Choosing documents is about the session, not any one document
+            let attach_to = match &command {
+                WebSocketCommand::OpenDocument(source) => {
+                    match manager.open(source.clone()) {
+                        Ok(document) => {
+                            let _ = socket.send(Message::Text(format!("DOCUMENT_OPENED:{}", json!(document.info())))).await;
+                            Some(document.id)
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                            continue;
+                        }
+                    }
+                }
+                WebSocketCommand::SetActiveDocument { id } => Some(*id),
+                WebSocketCommand::CloseDocument { id } => {
+                    // This session hears of it like any other
+                    if let Err(e) = manager.close(*id) {
+                        let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                    }
+                    continue;
+                }
+                WebSocketCommand::ListDocuments => {
+                    let list = json!({ "active": active, "documents": manager.list() });
+                    let _ = socket.send(Message::Text(format!("DOCUMENTS_UPDATE:{}", list))).await;
+                    continue;
+                }
+                _ => None,
+            };
+            if let Some(id) = attach_to {
+                match manager.get(id) {
+                    Ok(document) => {
+                        active = Some(id);
+                        live_commit_at = None;
+                        let generator = generator_for(&document);
+                        updates = Some(attach(&mut socket, &runtime, &generator, &document, &mut selection_state, &mut view).await);
+                        replay = document.autosave.take_replay();
+                        replaying = (!replay.is_empty()).then_some(document);
+                    }
+                    Err(e) => {
+                        let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                    }
+                }
+                continue;
+            }
+
+            // Replayed commands belong to the document being recovered, whatever
+            // id it had when they were journaled
+            let document = match &replaying {
+                Some(document) => Ok(document.clone()),
+                None => target_document(command_document(&text), active).and_then(|id| manager.get(id)),
+            };
+            let state = match document {
+                Ok(document) => document,
+                Err(e) => {
+                    warn!("Rejected command: {}", e);
+                    let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                    continue;
+                }
+            };
+            let generator = generator_for(&state);
+
+            let validation = {
+                let graph = state.graph.read().unwrap();
+                validate_command_refs(&command, &graph)
+            };
+            if let Err(e) = validation {
+                warn!("Rejected command: {}", e);
+                let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                continue;
+            }
+
+            // Keep autosave snapshots from landing mid-command. Replayed commands are
+            // already in the journal, so only live ones are recorded.
+            let is_document_change = is_document_command(&command);
+            let autosave_guard = if is_document_change { Some(state.autosave.begin().await) } else { None };
+            let record = is_document_change && replaying.is_none();
+
             match command {
                 WebSocketCommand::Regen => {
-                    let program = {
-                        let mut graph = state.graph.write().unwrap();
-                        graph.regenerate()
-                    };
-                    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
+                    let program = current_program(&state);
+                    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
                 }
                 
                 WebSocketCommand::Select(cmd) => {
@@ -215,78 +1277,64 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                 }
 
                 WebSocketCommand::CreateFeature(cmd) => {
-                       let f_type = match cmd.feature_type.as_str() {
-                          "Sketch" => cad_core::features::types::FeatureType::Sketch,
-                          "Extrude" => cad_core::features::types::FeatureType::Extrude,
-                          "Revolve" => cad_core::features::types::FeatureType::Revolve,
-                          "Fillet" => cad_core::features::types::FeatureType::Fillet,
-                          "Chamfer" => cad_core::features::types::FeatureType::Chamfer,
-                          "Boolean" => cad_core::features::types::FeatureType::Boolean,
-                          "Plane" => cad_core::features::types::FeatureType::Plane,
-                          "Axis" => cad_core::features::types::FeatureType::Axis,
-                          "Point" => cad_core::features::types::FeatureType::Point,
-                          "LinearPattern" => cad_core::features::types::FeatureType::LinearPattern,
-                          "CircularPattern" => cad_core::features::types::FeatureType::CircularPattern,
-                          _ => {
-                              warn!("Unknown feature type: {}", cmd.feature_type);
-                              cad_core::features::types::FeatureType::Point
+                      let seed = cmd.seed.clone();
+                      let feature = {
+                          let graph = state.graph.read().unwrap();
+                          let registry = state.registry.read().unwrap();
+                          new_feature(cmd, &graph, &registry, &selection_state.selected)
+                      };
+                      let mut feature = match feature {
+                          Ok(feature) => feature,
+                          Err(e) => {
+                              let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                              continue;
                           }
                       };
-                      
-                      let mut feature = cad_core::features::types::Feature::new(&cmd.name, f_type);
-                      if let Some(deps) = cmd.dependencies {
-                          feature.dependencies = deps.into_iter().map(cad_core::topo::EntityId::from_uuid).collect();
-                      }
-                      
-                      let (json_update, program) = {
+
+                      let (json, id_warning) = {
                           let mut graph = state.graph.write().unwrap();
+                          let id_warning = graph.assign_feature_id(&mut feature, seed.as_deref());
                           graph.add_node(feature);
-                          let program = graph.regenerate();
-                          let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                          (Some(json), Some(program))
+                          (graph.snapshot_for_serialization(), id_warning)
                       };
-
-                      if let Some(json) = json_update {
-                          let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
-                      }
-                      
-                      if let Some(program) = program {
-                          process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
+                      let (json, program) = json.regenerated();
+                      if let Some(warning) = id_warning {
+                          warn!("{}", warning);
+                          let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::IdCollision, &warning).with_detail("ids", [&seed])))).await;
                       }
+                      let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                      process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
                 }
 
-                WebSocketCommand::UpdateFeature(cmd) => {
-                      let entity_id = cad_core::topo::EntityId::from_uuid(cmd.id);
-                      
-                      let (json_update, program, solve_result_json, error_msg) = {
+                WebSocketCommand::UpdateFeature(mut cmd) => {
+                      let feature_id = cmd.id;
+                      let (json_update, solve_result_json, error_msg) = {
                           let mut graph = state.graph.write().unwrap();
-                          match graph.update_feature_params(entity_id, cmd.params) {
-                              Ok(_) => {
-                                   let mut solve_result_json: Option<String> = None;
-                                   if let Some(node) = graph.nodes.get_mut(&entity_id) {
-                                       if node.feature_type == cad_core::features::types::FeatureType::Sketch {
-                                           if let Some(cad_core::features::types::ParameterValue::Sketch(ref mut sketch)) = node.parameters.get_mut("sketch_data") {
-                                               use cad_core::sketch::solver::SketchSolver;
-                                               let result = SketchSolver::solve_with_result(sketch);
-                                               solve_result_json = Some(serde_json::to_string(&result).unwrap_or("{}".into()));
-                                           }
-                                       }
-                                   }
-                                   
-                                   let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                                   let program = graph.regenerate();
-                                   (Some(json), Some(program), solve_result_json, None)
+                          let unit = graph.document_length_unit();
+                          let highlight = std::mem::take(&mut cmd.highlight_entities);
+                          match update_feature(&mut graph, cmd) {
+                              Ok(solved) => {
+                                   let solve_result_json = solved.map(|(sketch, mut result)| {
+                                       let highlight: Vec<_> = highlight.iter().filter_map(|entity| sketch.resolve_entity(entity).ok()).collect();
+                                       result.measure(sketch, &highlight, unit);
+                                       flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                       PendingAnalysis::restart(&mut pending_analysis, cad_core::topo::EntityId::from_uuid(feature_id), sketch, &result);
+                                       serde_json::to_string(&result).unwrap_or("{}".into())
+                                   });
+
+                                   (Some(graph.snapshot_for_serialization()), solve_result_json, None)
                               }
-                              Err(e) => (None, None, None, Some(format!("Failed to update feature: {}", e)))
+                              Err(e) => (None, None, Some(format!("Failed to update feature: {}", e)))
                           }
                       };
+                      let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
 
                       if let Some(json) = json_update {
                           let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
                       }
 
                       if let Some(err) = error_msg {
-                          let _ = socket.send(Message::Text(format_error("FEATURE_ERROR", &err, "error"))).await;
+                          let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", feature_id)))).await;
                       }
 
                       if let Some(ref solve_json) = solve_result_json {
@@ -294,120 +1342,189 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                       }
 
                       if let Some(program) = program {
-                          process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
+                          process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
                       }
                 }
 
-                WebSocketCommand::DeleteFeature { id } => {
-                       let entity_id = cad_core::topo::EntityId::from_uuid(id);
-                       let (json_update, program) = {
-                           let mut graph = state.graph.write().unwrap();
-                           if graph.remove_node(entity_id).is_some() {
-                               let program = graph.regenerate();
-                               let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                               (Some(json), Some(program))
-                           } else {
-                               (None, None)
-                           }
-                       };
-
-                       if let Some(json) = json_update {
-                           let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
-                       }
-                       if let Some(program) = program {
-                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
-                       }
-                }
-
-                WebSocketCommand::VariableAdd(cmd) => {
-                     let (json_update, program) = {
+                WebSocketCommand::UpdateSketchLive { feature_id, sketch_data } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let live = {
                         let mut graph = state.graph.write().unwrap();
-                        let unit = cmd.unit.unwrap_or(cad_core::variables::Unit::Dimensionless);
-                        let mut var = cad_core::variables::Variable::with_expression(&cmd.name, &cmd.expression, unit);
-                        if let Some(desc) = cmd.description {
-                            var.description = desc;
-                        }
-                        
-                        match graph.variables.add(var) {
-                            Ok(_) => {
-                                cad_core::variables::evaluator::evaluate_all(&mut graph.variables);
-                                let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                                let program = graph.regenerate();
-                                (Some(json), Some(program))
-                            }
-                            Err(e) => {
-                                warn!("Failed to add variable: {}", e);
-                                (None, None)
+                        let unit = graph.document_length_unit();
+                        // Where the last regeneration put the face or plane the sketch sits on
+                        let placement = match graph.nodes.get(&entity_id).and_then(|f| f.parameters.get("plane_ref")) {
+                            Some(cad_core::features::types::ParameterValue::Reference(plane)) => {
+                                state.registry.read().unwrap().resolve(plane).map(|e| e.geometry.clone())
                             }
-                        }
+                            _ => None,
+                        };
+                        let solved = graph.update_sketch_live(entity_id, sketch_data).map(|(sketch, mut result)| {
+                            result.measure(sketch, &[], unit);
+                            flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                            PendingAnalysis::restart(&mut pending_analysis, entity_id, sketch, &result);
+                            let render = json!({
+                                "feature_id": feature_id,
+                                "tessellation": runtime.render_sketch(entity_id, sketch, placement.as_ref()),
+                            });
+                            (serde_json::to_string(&result).unwrap_or("{}".into()), render)
+                        });
+                        solved.map(|(status, render)| (graph.snapshot_for_serialization(), status, render))
                     };
-                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
-                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await; }
-                }
-
-                WebSocketCommand::VariableUpdate(cmd) => {
-                     let entity_id = cad_core::topo::EntityId::from_uuid(cmd.id);
-                     let (json_update, program) = {
-                        let mut graph = state.graph.write().unwrap();
-                        let mut success = true;
-                        
-                        if let Some(ref name) = cmd.name {
-                            if graph.variables.update_name(entity_id, name).is_err() { success = false; }
-                        }
-                        if success {
-                            if let Some(ref expr) = cmd.expression {
-                                if graph.variables.update_expression(entity_id, expr).is_err() { success = false; }
-                            }
-                        }
-                        if success {
-                            if let Some(ref unit) = cmd.unit {
-                                if graph.variables.update_unit(entity_id, unit.clone()).is_err() { success = false; }
-                            }
+                    match live {
+                        Ok((graph_json, status, render)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", graph_json))).await;
+                            let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", status))).await;
+                            let _ = socket.send(Message::Text(format!("SKETCH_RENDER:{}", render))).await;
+                            live_commit_at = live_debounce.map(|debounce| tokio::time::Instant::now() + debounce);
                         }
-                        if success {
-                            if let Some(ref desc) = cmd.description {
-                                if graph.variables.update_description(entity_id, desc).is_err() { success = false; }
-                            }
-                        }
-                        
-                        if success {
-                            cad_core::variables::evaluator::evaluate_all(&mut graph.variables);
-                            let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                            let program = graph.regenerate();
-                            (Some(json), Some(program))
-                        } else {
-                            (None, None)
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
                         }
-                    };
-                    
-                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
-                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await; }
+                    }
                 }
 
-                WebSocketCommand::VariableDelete { id } => {
-                    let entity_id = cad_core::topo::EntityId::from_uuid(id);
-                    let (json_update, program) = {
+                WebSocketCommand::CommitSketch => {
+                    live_commit_at = None;
+                    let (json, program) = {
                         let mut graph = state.graph.write().unwrap();
-                         if graph.variables.remove(entity_id).is_some() {
-                             cad_core::variables::evaluator::evaluate_all(&mut graph.variables);
-                             let program = graph.regenerate();
-                             let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                             (Some(json), Some(program))
-                         } else {
-                             (None, None)
-                         }
-                    };
-                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
-                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await; }
+                        graph.commit_live_edit();
+                        graph.snapshot_for_serialization()
+                    }.regenerated();
+                    let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
                 }
 
-                WebSocketCommand::VariableReorder { id, new_index } => {
+                WebSocketCommand::SetLiveSketchDebounce { ms } => {
+                    live_debounce = (ms > 0).then(|| std::time::Duration::from_millis(ms));
+                }
+
+                WebSocketCommand::DeleteFeature { id, policy, dry_run } => {
                     let entity_id = cad_core::topo::EntityId::from_uuid(id);
-                    let json_update = {
+                    let result = {
                         let mut graph = state.graph.write().unwrap();
-                        match graph.variables.reorder(entity_id, new_index) {
-                            Ok(_) => Some(serde_json::to_string(&*graph).unwrap_or("{}".to_string())),
-                            Err(_) => None
+                        if dry_run {
+                            graph.plan_delete(entity_id, policy).map(|plan| (plan, None))
+                        } else {
+                            graph.delete_feature(entity_id, policy).map(|deletion| {
+                                let plan = deletion.plan.clone();
+                                deletions.push(deletion);
+                                (plan, Some(graph.snapshot_for_serialization()))
+                            })
+                        }
+                    };
+                    match result {
+                        Ok((plan, update)) => {
+                            let reply = json!({ "id": id, "policy": policy, "dry_run": dry_run, "removed": plan.removed, "orphaned": plan.orphaned });
+                            let _ = socket.send(Message::Text(format!("DELETE_RESULT:{}", reply))).await;
+                            if let Some((json, program)) = update.map(GraphSnapshot::regenerated) {
+                                let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                                process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                            }
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::UndoDelete => {
+                    match deletions.pop() {
+                        Some(deletion) => {
+                            let (json, program) = {
+                                let mut graph = state.graph.write().unwrap();
+                                graph.restore_deletion(deletion);
+                                graph.snapshot_for_serialization()
+                            }.regenerated();
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        None => { let _ = socket.send(Message::Text(format_command_error("No delete to undo", &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::RevertToLastGood => {
+                    let reverted = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.revert_to_last_good()
+                            .map(|()| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match reverted {
+                        Ok((json, program)) => {
+                            // Uncommitted live sketch edits went with the rest
+                            live_commit_at = None;
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::Batch(commands) => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        let registry = state.registry.read().unwrap();
+                        apply_batch(&mut graph, &registry, &selection_state.selected, commands)
+                            .map(|applied| (graph.snapshot_for_serialization(), applied))
+                    };
+                    match result {
+                        Ok((json, applied)) => {
+                            let (json, program) = json.regenerated();
+                            deletions.extend(applied);
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::VariableAdd(cmd) => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        add_variable(&mut graph, cmd).map(|_| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match result {
+                        Ok((json, program)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::VariableUpdate(cmd) => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        update_variable(&mut graph, cmd).map(|_| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match result {
+                        Ok((json, program)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::VariableDelete { id } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(id);
+                    let (json_update, program) = {
+                        let mut graph = state.graph.write().unwrap();
+                         if graph.variables.remove(entity_id).is_some() {
+                             cad_core::variables::evaluator::evaluate_all(&mut graph.variables);
+                             Some(graph.snapshot_for_serialization())
+                         } else {
+                             None
+                         }
+                    }.map(GraphSnapshot::regenerated).unzip();
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::VariableReorder { id, new_index } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(id);
+                    let json_update = {
+                        let mut graph = state.graph.write().unwrap();
+                        match graph.variables.reorder(entity_id, new_index) {
+                            Ok(_) => Some(graph.snapshot_for_serialization()),
+                            Err(_) => None
                         }
                     };
                     if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
@@ -416,26 +1533,243 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                 WebSocketCommand::GetRegions { id } => {
                     let entity_id = cad_core::topo::EntityId::from_uuid(id);
                     let regions_json = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let mut payload = solved_regions_payload(sketch);
+                                payload["feature_id"] = serde_json::json!(id.to_string());
+                                // Id to select each region by, for creating an extrude from the selection
+                                for region in payload["regions"].as_array_mut().into_iter().flatten() {
+                                    let topo_id = region["stable_id"].as_str()
+                                        .map(|key| cad_core::features::from_selection::region_topo_id(entity_id, key));
+                                    region["topo_id"] = serde_json::json!(topo_id);
+                                }
+                                Some(payload.to_string())
+                            }
+                            _ => None,
+                        }
+                    };
+                    if let Some(json) = regions_json {
+                        let _ = socket.send(Message::Text(format!("REGIONS_UPDATE:{}", json))).await;
+                    }
+                }
+
+                WebSocketCommand::GetSolvedSketch { id } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(id);
+                    let solved_json = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let mut payload = solved_sketch_payload(sketch);
+                                payload["feature_id"] = serde_json::json!(id.to_string());
+                                Some(payload.to_string())
+                            }
+                            _ => None,
+                        }
+                    };
+                    match solved_json {
+                        Some(json) => { let _ = socket.send(Message::Text(format!("SOLVED_SKETCH:{}", json))).await; }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::GetRegionMedialAxis { feature_id, region_key, sample_spacing } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let axis = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let mut solved = sketch.clone();
+                                cad_core::sketch::solver::SketchSolver::solve_with_result(&mut solved);
+                                let regions = cad_core::sketch::regions::summarize_regions(&cad_core::sketch::regions::find_regions(&solved.entities));
+                                match regions.iter().find(|r| r.stable_id == region_key) {
+                                    Some(summary) => {
+                                        let spacing = sample_spacing.unwrap_or(summary.region.area.abs().sqrt() / 50.0);
+                                        Ok(cad_core::sketch::regions::compute_medial_axis(&summary.region, spacing))
+                                    }
+                                    None => Err(format!("Region '{}' not found in sketch", region_key)),
+                                }
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        }
+                    };
+                    match axis {
+                        Ok(axis) => {
+                            let json = serde_json::json!({
+                                "feature_id": feature_id.to_string(),
+                                "region_key": region_key,
+                                "polylines": axis.polylines,
+                                "warning": axis.warning,
+                            });
+                            let _ = socket.send(Message::Text(format!("REGION_MEDIAL_AXIS:{}", json))).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &e).with_detail("feature_id", feature_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::PickRegion { feature_id, point } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let pick_json = {
                         let graph = state.graph.read().unwrap();
                         if let Some(node) = graph.nodes.get(&entity_id) {
                             if let Some(cad_core::features::types::ParameterValue::Sketch(ref sketch)) = node.parameters.get("sketch_data") {
                                 let regions = cad_core::sketch::regions::find_regions(&sketch.entities);
-                                let serializable_regions: Vec<serde_json::Value> = regions.iter().map(|r| {
-                                    serde_json::json!({
-                                        "id": r.id.to_string(),
-                                        "boundary_entity_ids": r.boundary_entity_ids.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
-                                        "boundary_points": r.boundary_points,
-                                        "voids": r.voids,
-                                        "centroid": r.centroid,
-                                        "area": r.area
-                                    })
-                                }).collect();
-                                Some(serde_json::to_string(&serializable_regions).unwrap_or("[]".into()))
+                                let picked = cad_core::sketch::regions::region_at_point(&regions, point)
+                                    .and_then(|id| regions.iter().find(|r| r.id == id));
+                                let result = match picked {
+                                    Some(r) => serde_json::json!({
+                                        "feature_id": feature_id.to_string(),
+                                        "point": point,
+                                        "region_id": r.id,
+                                        "area": r.area,
+                                        "centroid": r.centroid
+                                    }),
+                                    None => serde_json::json!({
+                                        "feature_id": feature_id.to_string(),
+                                        "point": point,
+                                        "region_id": null
+                                    }),
+                                };
+                                Some(result.to_string())
                             } else { None }
                         } else { None }
                     };
-                    if let Some(json) = regions_json {
-                        let _ = socket.send(Message::Text(format!("REGIONS_UPDATE:{}", json))).await;
+                    match pick_json {
+                        Some(json) => { let _ = socket.send(Message::Text(format!("REGION_PICK:{}", json))).await; }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::GetDimensionValues { sketch_id } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(sketch_id);
+                    let values_json = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let mut solved = sketch.clone();
+                                cad_core::sketch::solver::SketchSolver::solve_with_result(&mut solved);
+                                let values: Vec<serde_json::Value> = cad_core::sketch::solver::SketchSolver::constraint_current_values(&solved)
+                                    .into_iter()
+                                    .map(|(index, value)| serde_json::json!({ "index": index, "value": value }))
+                                    .collect();
+                                Some(serde_json::json!({ "sketch_id": sketch_id.to_string(), "values": values }).to_string())
+                            }
+                            _ => None,
+                        }
+                    };
+                    match values_json {
+                        Some(json) => { let _ = socket.send(Message::Text(format!("DIMENSION_VALUES:{}", json))).await; }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::GetConstraintGraph { sketch_id } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(sketch_id);
+                    let graph_json = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let constraint_graph = cad_core::sketch::solver::SketchSolver::constraint_graph(sketch);
+                                Some(serde_json::json!({
+                                    "sketch_id": sketch_id.to_string(),
+                                    "graph": constraint_graph
+                                }).to_string())
+                            }
+                            _ => None,
+                        }
+                    };
+                    match graph_json {
+                        Some(json) => { let _ = socket.send(Message::Text(format!("CONSTRAINT_GRAPH:{}", json))).await; }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::GetDependencyGraph => {
+                    let dependency_graph = state.graph.read().unwrap().dependency_graph();
+                    match dependency_graph {
+                        Ok(dependency_graph) => {
+                            let json = serde_json::to_string(&dependency_graph).unwrap_or_default();
+                            let _ = socket.send(Message::Text(format!("DEPENDENCY_GRAPH:{}", json))).await;
+                        }
+                        Err(cycle) => {
+                            let error = CadError::new(ErrorCode::FeatureError, "Features depend on each other in a cycle").with_detail("feature_id", cycle.first());
+                            let _ = socket.send(Message::Text(format_error(&error))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::QuerySnap { feature_id, query } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let candidates = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                Some(cad_core::sketch::snap::query_snaps(sketch, &query))
+                            }
+                            _ => None,
+                        }
+                    };
+                    match candidates {
+                        Some(candidates) => {
+                            let json = serde_json::json!({
+                                "feature_id": feature_id.to_string(),
+                                "cursor": query.cursor,
+                                "candidates": candidates
+                            });
+                            let _ = socket.send(Message::Text(format!("SNAP_RESULT:{}", json))).await;
+                        }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::ProbeSketch { sketch_id, point } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(sketch_id);
+                    let probe = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let radius = cad_core::sketch::snap::SnapConfig::default().snap_radius;
+                                Some(cad_core::sketch::snap::probe_point(sketch, point, radius))
+                            }
+                            _ => None,
+                        }
+                    };
+                    match probe {
+                        Some(probe) => {
+                            let json = serde_json::json!({
+                                "sketch_id": sketch_id.to_string(),
+                                "point": point,
+                                "probe": probe
+                            });
+                            let _ = socket.send(Message::Text(format!("PROBE_RESULT:{}", json))).await;
+                        }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
+                    }
+                }
+
+                WebSocketCommand::PreviewConstraint { sketch_id, constraint } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(sketch_id);
+                    let preview = {
+                        let graph = state.graph.read().unwrap();
+                        match graph.nodes.get(&entity_id).and_then(|node| node.parameters.get("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                use cad_core::sketch::solver::SketchSolver;
+                                let result = SketchSolver::preview_add_constraint(sketch, &constraint);
+                                Some((result.dof - SketchSolver::calculate_dof(sketch), result))
+                            }
+                            _ => None,
+                        }
+                    };
+                    match preview {
+                        Some((dof_delta, result)) => {
+                            let json = serde_json::json!({
+                                "sketch_id": sketch_id.to_string(),
+                                "dof_delta": dof_delta,
+                                "result": result
+                            });
+                            let _ = socket.send(Message::Text(format!("CONSTRAINT_PREVIEW:{}", json))).await;
+                        }
+                        None => { let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", entity_id)))).await; }
                     }
                 }
 
@@ -460,21 +1794,255 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                     broadcast_groups(&mut socket, &selection_state).await;
                 }
 
+                WebSocketCommand::GetSelectionBounds => {
+                    let bounds = {
+                        let registry = state.registry.read().unwrap();
+                        selection_state.bounding_box(&registry)
+                    };
+                    let json = match bounds {
+                        Some((min, max)) => serde_json::json!({
+                            "min": [min.x, min.y, min.z],
+                            "max": [max.x, max.y, max.z],
+                            "size": [max.x - min.x, max.y - min.y, max.z - min.z],
+                        }),
+                        None => serde_json::Value::Null,
+                    };
+                    let _ = socket.send(Message::Text(format!("SELECTION_BOUNDS:{}", json))).await;
+                }
+
+                WebSocketCommand::SelectByGeometry { query, modifier } => {
+                    let matches = state.registry.read().unwrap().find_by_geometry(&query);
+                    if modifier.as_deref() != Some("add") {
+                        selection_state.clear();
+                    }
+                    for id in matches {
+                        selection_state.select(id, true);
+                    }
+                    broadcast_selection(&mut socket, &selection_state).await;
+                }
+
+                WebSocketCommand::MeasureEntity { id } => {
+                    let measurement = state.registry.read().unwrap().resolve(&id).map(cad_core::analysis::measure_entity);
+                    match measurement {
+                        Some(measurement) => {
+                            let json = serde_json::to_string(&measurement).unwrap_or("{}".into());
+                            let _ = socket.send(Message::Text(format!("MEASUREMENT:{}", json))).await;
+                        }
+                        None => {
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::CommandError, "Entity not found in the current model")))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::GetTopologyManifest => {
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            // TopoId feature namespace -> feature name, as in process_regen's feature_id_map
+                            let features: std::collections::HashMap<String, String> = {
+                                let graph = state.graph.read().unwrap();
+                                graph.nodes.values()
+                                    .map(|f| (cad_core::topo::IdGenerator::new(&f.id.to_string()).next_id().to_string(), f.name.clone()))
+                                    .collect()
+                            };
+                            let json = json!({
+                                "features": features,
+                                "entries": result.manifest_entries(),
+                            });
+                            let _ = socket.send(Message::Text(format!("TOPOLOGY_MANIFEST:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Topology manifest failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::GetGeometryHash => {
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            let json = serde_json::to_string(&result.summary()).unwrap_or_default();
+                            let _ = socket.send(Message::Text(format!("GEOMETRY_HASH:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Geometry hash failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::EvaluateAt { rollback_id } => {
+                    let snapshot = state.graph.read().unwrap().snapshot_for_serialization();
+                    let program = snapshot.program_at(rollback_id.map(cad_core::topo::EntityId::from_uuid));
+                    match runtime.evaluate(&program, &generator) {
+                        Ok(result) => {
+                            let mut tessellation = result.tessellation.clone();
+                            if view.mesh_quality < 1.0 {
+                                tessellation.decimate(view.mesh_quality);
+                            }
+                            let json = json!({
+                                "rollback_id": rollback_id,
+                                "summary": result.summary(),
+                                "tessellation": tessellation,
+                            });
+                            let _ = socket.send(Message::Text(format!("PREVIEW_UPDATE:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Evaluation at rollback point failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::DraftAnalysis { pull_dir, min_angle } => {
+                    let pull_dir = cad_core::geometry::Vector3::from(pull_dir);
+                    let min_angle = min_angle.unwrap_or(cad_core::geometry::draft::DEFAULT_MIN_ANGLE_DEG);
+                    if pull_dir.norm() < 1e-12 {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::CommandError, "Pull direction must be non-zero")))).await;
+                        continue;
+                    }
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            let faces: Vec<_> = cad_core::geometry::draft::draft_analysis(&result.tessellation, pull_dir, min_angle)
+                                .into_iter()
+                                .map(|(face, status)| json!({ "face": face, "status": status }))
+                                .collect();
+                            let json = json!({ "pull_dir": [pull_dir.x, pull_dir.y, pull_dir.z], "min_angle": min_angle, "faces": faces });
+                            let _ = socket.send(Message::Text(format!("DRAFT_ANALYSIS:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Draft analysis failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::DiffLastRegen => {
+                    let diff = state.regens.lock().unwrap().diff();
+                    match diff {
+                        Some(diff) => {
+                            let json = serde_json::to_string(&diff).unwrap();
+                            let _ = socket.send(Message::Text(format!("REGEN_DIFF:{}", json))).await;
+                        }
+                        None => {
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::CommandError, "No earlier regeneration to compare with yet")))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::GetProjectedArea { dir } => {
+                    let dir = cad_core::geometry::Vector3::from(dir);
+                    if dir.norm() < 1e-12 {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::CommandError, "Projection direction must be non-zero")))).await;
+                        continue;
+                    }
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            let area = result.tessellation.projected_area(dir);
+                            let json = json!({ "dir": [dir.x, dir.y, dir.z], "area": area });
+                            let _ = socket.send(Message::Text(format!("PROJECTED_AREA:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Projected area failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::GetSection { origin, normal } => {
+                    let normal = cad_core::geometry::Vector3::from(normal);
+                    if normal.norm() < 1e-12 {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::CommandError, "Section plane normal must be non-zero")))).await;
+                        continue;
+                    }
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            let origin = cad_core::geometry::Point3::from(origin);
+                            let loops = cad_core::geometry::section::section(&result.tessellation, origin, normal);
+                            let (x_axis, y_axis) = cad_core::geometry::datum::plane_axes(&normal.normalize());
+                            let json = json!({
+                                "origin": [origin.x, origin.y, origin.z],
+                                "normal": [normal.x, normal.y, normal.z],
+                                "x_axis": [x_axis.x, x_axis.y, x_axis.z],
+                                "y_axis": [y_axis.x, y_axis.y, y_axis.z],
+                                "loops": loops,
+                            });
+                            let _ = socket.send(Message::Text(format!("SECTION_RESULT:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Section failed", e))).await;
+                        }
+                    }
+                }
+
                 WebSocketCommand::ToggleSuppression { id } => {
                      let entity_id = cad_core::topo::EntityId::from_uuid(id);
                      let (json_update, program) = {
                          let mut graph = state.graph.write().unwrap();
                          match graph.toggle_suppression(entity_id) {
-                             Ok(_) => {
-                                 let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                                 let program = graph.regenerate();
-                                 (Some(json), Some(program))
-                             }
-                             Err(_) => (None, None)
+                             Ok(_) => Some(graph.snapshot_for_serialization()),
+                             Err(_) => None
                          }
-                     };
+                     }.map(GraphSnapshot::regenerated).unzip();
                      if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
-                     if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await; }
+                     if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::ToggleVisibility { id } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(id);
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.toggle_visibility(entity_id).map(|_| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match result {
+                        Ok((json, program)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::RenameBody { body_id, name } => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.rename_body(body_id, &name).map(|_| graph.snapshot_for_serialization())
+                    };
+                    match result {
+                        Ok(json) => { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::ExportBodies { bodies, format } => {
+                    let mut snapshot = state.graph.read().unwrap().clone();
+                    match snapshot.export_bodies(&runtime, &generator, &bodies, format) {
+                        Ok(files) => {
+                            let json = json!({ "format": format, "files": files });
+                            let _ = socket.send(Message::Text(format!("EXPORT_RESULT:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let message = format!("Export failed: {}", e);
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::ExportFailed, &message).with_detail("format", format)))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::ImportStep { data } => {
+                    // Read the file off the runtime and without the lock; only adding the bodies needs it
+                    let read = tokio::task::spawn_blocking(move || cad_core::features::import::read_step(&data)).await
+                        .unwrap_or_else(|e| Err(CadError::new(ErrorCode::ImportFailed, format!("STEP import failed: {}", e)).with_detail("format", "STEP")));
+                    let result = read.map(|bodies| {
+                        let mut graph = state.graph.write().unwrap();
+                        let report = graph.add_step_bodies(bodies);
+                        (report, graph.snapshot_for_serialization())
+                    });
+                    match result {
+                        Ok((report, json)) => {
+                            let (json, program) = json.regenerated();
+                            info!("Imported {} bodies from STEP ({} warnings)", report.features.len(), report.warnings.len());
+                            let _ = socket.send(Message::Text(format!("IMPORT:{}", serde_json::to_string(&report).unwrap_or("{}".to_string())))).await;
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_error(&e))).await;
+                        }
+                    }
                 }
 
                 WebSocketCommand::SetRollback { id } => {
@@ -482,15 +2050,13 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                     let (json_update, program) = {
                         let mut graph = state.graph.write().unwrap();
                         if graph.set_rollback(entity_id) {
-                            let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                            let program = graph.regenerate();
-                            (Some(json), Some(program))
+                            Some(graph.snapshot_for_serialization())
                         } else {
-                            (None, None)
+                            None
                         }
-                    };
+                    }.map(GraphSnapshot::regenerated).unzip();
                     if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
-                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
                 }
 
                 WebSocketCommand::ReorderFeature { id, new_index } => {
@@ -502,23 +2068,13 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                     match result {
                         Ok(()) => {
                             // Reorder succeeded, send updated graph and regenerate
-                            let (json_update, program) = {
-                                let mut graph = state.graph.write().unwrap();
-                                let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                                let program = graph.regenerate();
-                                (json, program)
-                            };
+                            let (json_update, program) = state.graph.read().unwrap().snapshot_for_serialization().regenerated();
                             let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json_update))).await;
-                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
                         }
                         Err(err_msg) => {
-                            // Send error to client
-                            let error = serde_json::json!({
-                                "code": "REORDER_FAILED",
-                                "message": err_msg,
-                                "severity": "warning"
-                            });
-                            let _ = socket.send(Message::Text(format!("ERROR_UPDATE:{}", error))).await;
+                            let error = CadError::new(ErrorCode::ReorderFailed, err_msg).with_detail("feature_id", id);
+                            let _ = socket.send(Message::Text(format_error(&error))).await;
                         }
                     }
                 }
@@ -530,13 +2086,16 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                         "Revolve" => cad_core::features::types::FeatureType::Revolve,
                         "Boolean" => cad_core::features::types::FeatureType::Boolean,
                         "Cut" => cad_core::features::types::FeatureType::Cut,
+                        "DeleteFace" => cad_core::features::types::FeatureType::DeleteFace,
+                        "DatumPlane" => cad_core::features::types::FeatureType::DatumPlane,
+                        "DatumAxis" => cad_core::features::types::FeatureType::DatumAxis,
+                        "Box" => cad_core::features::types::FeatureType::Box,
+                        "Torus" => cad_core::features::types::FeatureType::Torus,
+                        "Cylinder" => cad_core::features::types::FeatureType::Cylinder,
                         _ => {
-                            let error = serde_json::json!({
-                                "code": "INSERT_FAILED",
-                                "message": format!("Unknown feature type: {}", feature_type),
-                                "severity": "error"
-                            });
-                            let _ = socket.send(Message::Text(format!("ERROR_UPDATE:{}", error))).await;
+                            let error = CadError::new(ErrorCode::InsertFailed, format!("Unknown feature type: {}", feature_type))
+                                .with_detail("feature_type", &feature_type);
+                            let _ = socket.send(Message::Text(format_error(&error))).await;
                             continue;
                         }
                     };
@@ -550,22 +2109,23 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                     
                     let (json_update, program) = {
                         let mut graph = state.graph.write().unwrap();
+                        if let Some(warning) = graph.assign_feature_id(&mut feature, None) {
+                            warn!("{}", warning);
+                        }
                         let success = graph.insert_node_at(feature, after_entity_id);
                         if !success && after_id.is_some() {
                             // Log warning but continue
                             tracing::warn!("Insert after ID not found, inserted at end");
                         }
-                        let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                        let program = graph.regenerate();
-                        (json, program)
-                    };
+                        graph.snapshot_for_serialization()
+                    }.regenerated();
                     let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json_update))).await;
-                    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await;
+                    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
                 }
 
                 WebSocketCommand::ProjectEntity { sketch_id, topo_id } => {
                      let entity_id = cad_core::topo::EntityId::from_uuid(sketch_id);
-                     let (json_update, program, error_msg) = {    
+                     let (json_update, error_msg) = {
                          let registry = state.registry.read().unwrap();
                          if let Some(kernel_entity) = registry.resolve(&topo_id) {
                             let mut graph = state.graph.write().unwrap();
@@ -614,72 +2174,990 @@ async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
                                         // But we need the position. 
                                         // Ideally, we add a "Projected" constraint which holds the TopoId.
                                         
-                                        let json = serde_json::to_string(&*graph).unwrap_or("{}".to_string());
-                                        let program = graph.regenerate();
-                                        (Some(json), Some(program), None)
+                                        let json = graph.snapshot_for_serialization();
+                                        (Some(json), None)
                                     } else {
-                                        (None, None, Some("Geometry type not supported for projection".to_string()))
+                                        (None, Some("Geometry type not supported for projection".to_string()))
                                     }
                                 } else {
-                                    (None, None, Some("Feature is not a sketch".to_string()))
+                                    (None, Some("Feature is not a sketch".to_string()))
                                 }
                             } else {
-                                (None, None, Some("Sketch feature not found".to_string()))
+                                (None, Some("Sketch feature not found".to_string()))
                             }
                          } else {
-                             (None, None, Some("Referenced entity not found in registry".to_string()))
+                             (None, Some("Referenced entity not found in registry".to_string()))
                          }
                      };
+                     let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
 
                      if let Some(err) = error_msg {
-                         let _ = socket.send(Message::Text(format_error("PROJECTION_FAILED", &err, "error"))).await;
+                         let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::ProjectionFailed, &err).with_detail("sketch_id", sketch_id)))).await;
                      }
                      if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
-                     if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state).await; }
+                     if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
                 }
-            }
-        }
-    }
-}
 
-// Helpers
+                WebSocketCommand::CreateOffsetProfileFromFace { face, offset, target_sketch } => {
+                    let target = target_sketch.map(cad_core::topo::EntityId::from_uuid);
+                    let created = evaluate_snapshot(&state, &runtime, &generator)
+                        .map_err(|e| format!("Offset profile failed: {}", e))
+                        .and_then(|result| {
+                            let mut graph = state.graph.write().unwrap();
+                            let profile = graph.create_offset_profile_from_face(face, offset, target, &result.tessellation, &result.topology_manifest)?;
+                            info!("Drew offset outline of {:?} as {} entities", face, profile.entities.len());
+                            Ok((serde_json::to_string(&profile).unwrap_or("{}".to_string()), graph.snapshot_for_serialization()))
+                        });
+                    match created {
+                        Ok((profile, json)) => {
+                            let (json, program) = json.regenerated();
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            let _ = socket.send(Message::Text(format!("OFFSET_PROFILE:{}", profile))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::ProjectionFailed, e).with_detail("sketch_id", target_sketch)))).await;
+                        }
+                    }
+                }
 
-async fn broadcast_selection(socket: &mut WebSocket, selection_state: &cad_core::topo::SelectionState) {
-    let update = serde_json::to_string(&selection_state.selected).unwrap_or("[]".into());
-    let _ = socket.send(Message::Text(format!("SELECTION_UPDATE:{}", update))).await;
-}
+                WebSocketCommand::PatternSketchOnPlanes { sketch_id, normal, count, spacing } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(sketch_id);
+                    let (json_update, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        match graph.pattern_sketch_on_planes(entity_id, normal, count, spacing) {
+                            Ok(created) => {
+                                info!("Patterned sketch {} onto {} planes", sketch_id, created.len() + 1);
+                                let json = graph.snapshot_for_serialization();
+                                (Some(json), None)
+                            }
+                            Err(e) => (None, Some(format!("Failed to pattern sketch: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
 
-async fn broadcast_groups(socket: &mut WebSocket, selection_state: &cad_core::topo::SelectionState) {
-    let groups = selection_state.list_groups();
-    let groups_json = serde_json::to_string(&groups).unwrap_or("[]".into());
-    let _ = socket.send(Message::Text(format!("SELECTION_GROUPS_UPDATE:{}", groups_json))).await;
-}
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", entity_id)))).await;
+                    }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
 
-async fn process_regen(
-    socket: &mut WebSocket, 
-    runtime: &cad_core::evaluator::Runtime, 
+                WebSocketCommand::SplitEntity { feature_id, entity_id, at } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (split_json, json_update, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        match graph.split_sketch_entity(sketch_id, cad_core::topo::EntityId::from_uuid(entity_id), at) {
+                            Ok((result, updated_features)) => {
+                                let split = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "result": result,
+                                    "updated_features": updated_features,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(split), Some(json), None)
+                            }
+                            Err(e) => (None, None, Some(format!("Failed to split entity: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(split) = split_json { let _ = socket.send(Message::Text(format!("SPLIT_RESULT:{}", split))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                command @ (WebSocketCommand::TrimEntity { .. } | WebSocketCommand::ExtendEntity { .. }) => {
+                    let (feature_id, target, cutting, point, extend) = match command {
+                        WebSocketCommand::TrimEntity { feature_id, target, cutting, keep_point } => (feature_id, target, cutting, keep_point, false),
+                        WebSocketCommand::ExtendEntity { feature_id, target, cutting, near_point } => (feature_id, target, cutting, near_point, true),
+                        _ => unreachable!(),
+                    };
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (target, cutting) = (cad_core::topo::EntityId::from_uuid(target), cad_core::topo::EntityId::from_uuid(cutting));
+                    let (trim_json, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let trimmed = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let edited = if extend {
+                                    cad_core::sketch::chains::extend_to(sketch, target, cutting, point)
+                                } else {
+                                    cad_core::sketch::chains::trim_to(sketch, target, cutting, point)
+                                };
+                                edited.map(|trim| {
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    (trim, serde_json::to_string(&result).unwrap_or("{}".into()))
+                                })
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match trimmed {
+                            Ok((trim, solve_json)) => {
+                                let trim = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "result": trim,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(trim), Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => {
+                                let action = if extend { "extend" } else { "trim" };
+                                (None, None, None, Some(format!("Failed to {} line: {}", action, e)))
+                            }
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(trim) = trim_json { let _ = socket.send(Message::Text(format!("TRIM_RESULT:{}", trim))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::PatternAlongPath { feature_id, entity_ids, path, count } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let entity_ids: Vec<_> = entity_ids.into_iter().map(cad_core::topo::EntityId::from_uuid).collect();
+                    let (pattern_json, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let patterned = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                cad_core::sketch::chains::pattern_along_path(sketch, &entity_ids, cad_core::topo::EntityId::from_uuid(path), count).map(|copies| {
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    (copies, serde_json::to_string(&result).unwrap_or("{}".into()))
+                                })
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match patterned {
+                            Ok((copies, solve_json)) => {
+                                let pattern = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "copies": copies,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(pattern), Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, None, Some(format!("Failed to pattern along path: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(pattern) = pattern_json { let _ = socket.send(Message::Text(format!("PATH_PATTERN_RESULT:{}", pattern))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::AddSketchEntity { feature_id, entity } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (added_json, warning, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let added = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                cad_core::sketch::edit::add_entity_spec(sketch, entity).map(|added| {
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    (added, serde_json::to_string(&result).unwrap_or("{}".into()))
+                                })
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match added {
+                            Ok((added, solve_json)) => {
+                                let added_json = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "entity_id": added.entity_id,
+                                    "geometry": added.geometry,
+                                    "warning": added.warning,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(added_json), added.warning, Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, None, None, Some(format!("Failed to add sketch entity: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(warning) = warning {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &warning).with_severity(Severity::Warning).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(added) = added_json { let _ = socket.send(Message::Text(format!("SKETCH_ENTITY_ADDED:{}", added))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::ListPresets => {
+                    let json = serde_json::to_string(cad_core::sketch::presets::PRESETS).unwrap_or("[]".to_string());
+                    let _ = socket.send(Message::Text(format!("PRESETS:{}", json))).await;
+                }
+
+                WebSocketCommand::ApplyPreset { feature_id, name, entity_ids } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let applied = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                entity_ids.iter().map(|entity| sketch.resolve_entity(entity)).collect::<Result<Vec<_>, _>>().and_then(|entity_ids| {
+                                    cad_core::sketch::presets::apply_preset(sketch, &name, &entity_ids).map(|added| (entity_ids.len(), added))
+                                }).map(|(count, added)| {
+                                    info!("Applied preset '{}' to {} entities ({} constraints added)", name, count, added);
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    serde_json::to_string(&result).unwrap_or("{}".into())
+                                })
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match applied {
+                            Ok(solve_json) => {
+                                let json = graph.snapshot_for_serialization();
+                                (Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, Some(format!("Failed to apply preset: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::SuppressConstraintGroup { feature_id, group, suppressed } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let applied = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                match sketch.set_group_suppression(&group, suppressed) {
+                                    0 => Err(format!("Sketch has no constraint group '{}'", group)),
+                                    _ => {
+                                        let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                        result.measure(sketch, &[], unit);
+                                        flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                        PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                        Ok(serde_json::to_string(&result).unwrap_or("{}".into()))
+                                    }
+                                }
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match applied {
+                            Ok(solve_json) => {
+                                let json = graph.snapshot_for_serialization();
+                                (Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, Some(e))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_command_error(&err, &text))).await;
+                    }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::ExplodeChain { feature_id, entity_id } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (explode_json, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let exploded = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let entity_ids = sketch.explode_chain(cad_core::topo::EntityId::from_uuid(entity_id));
+                                if entity_ids.is_empty() {
+                                    Err("Entity is not part of a chain".to_string())
+                                } else {
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    Ok((entity_ids, serde_json::to_string(&result).unwrap_or("{}".into())))
+                                }
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match exploded {
+                            Ok((entity_ids, solve_json)) => {
+                                let explode = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "entity_ids": entity_ids,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(explode), Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, None, Some(format!("Failed to explode chain: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(explode) = explode_json { let _ = socket.send(Message::Text(format!("EXPLODE_RESULT:{}", explode))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::DeleteSketchEntity { feature_id, entity_id } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let entity = cad_core::topo::EntityId::from_uuid(entity_id);
+                    let (delete_json, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let deleted = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                if sketch.entities.iter().any(|e| e.id == entity) {
+                                    let removed = sketch.remove_entity(entity);
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    Ok((removed, serde_json::to_string(&result).unwrap_or("{}".into())))
+                                } else {
+                                    Err(format!("Entity {} not found in sketch", entity_id))
+                                }
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match deleted {
+                            Ok((removed, solve_json)) => {
+                                let delete = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "entity_id": entity_id.to_string(),
+                                    "removed_constraints": removed,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(delete), Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, None, Some(format!("Failed to delete entity: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(delete) = delete_json { let _ = socket.send(Message::Text(format!("SKETCH_ENTITY_DELETED:{}", delete))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::UpdateSketchEntityMeta { feature_id, entity_id, meta } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let entity = cad_core::topo::EntityId::from_uuid(entity_id);
+                    let (json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let updated = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                sketch.set_entity_meta(entity, &meta).map(|lock_changed| lock_changed.then(|| {
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    serde_json::to_string(&result).unwrap_or("{}".into())
+                                }))
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match updated {
+                            Ok(solve_json) => (Some(graph.snapshot_for_serialization()), solve_json, None),
+                            Err(e) => (None, None, Some(format!("Failed to update entity: {}", e)))
+                        }
+                    };
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    // Names and colors don't change the geometry, so only a lock
+                    // change, which may move the solved sketch, regenerates
+                    match (json_update, solve_result_json) {
+                        (Some(json), Some(solve_json)) => {
+                            let (json, program) = json.regenerated();
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        (Some(json), None) => { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                        _ => {}
+                    }
+                }
+
+                WebSocketCommand::ConvertSketchEntity { feature_id, entity_id, to } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let entity = cad_core::topo::EntityId::from_uuid(entity_id);
+                    let (convert_json, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let converted = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                sketch.convert_entity(entity, to).map(|removed| {
+                                    let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                    result.measure(sketch, &[], unit);
+                                    flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                    PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                    (removed, serde_json::to_string(&result).unwrap_or("{}".into()))
+                                })
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match converted {
+                            Ok((removed, solve_json)) => {
+                                let convert = serde_json::json!({
+                                    "feature_id": feature_id.to_string(),
+                                    "entity_id": entity_id.to_string(),
+                                    "removed_constraints": removed,
+                                }).to_string();
+                                let json = graph.snapshot_for_serialization();
+                                (Some(convert), Some(json), Some(solve_json), None)
+                            }
+                            Err(e) => (None, None, None, Some(format!("Failed to convert entity: {}", e)))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", sketch_id)))).await;
+                    }
+                    if let Some(convert) = convert_json { let _ = socket.send(Message::Text(format!("SKETCH_ENTITY_CONVERTED:{}", convert))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::CleanupConstraints { sketch_id: feature_id } => {
+                    let sketch_id = cad_core::topo::EntityId::from_uuid(feature_id);
+                    let (cleanup_json, json_update, solve_result_json, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        let unit = graph.document_length_unit();
+                        let cleaned = match graph.nodes.get_mut(&sketch_id).and_then(|node| node.parameters.get_mut("sketch_data")) {
+                            Some(cad_core::features::types::ParameterValue::Sketch(sketch)) => {
+                                let removed = sketch.remove_redundant_constraints();
+                                let mut result = cad_core::sketch::solver::SketchSolver::solve_deferred(sketch);
+                                result.measure(sketch, &[], unit);
+                                flag_stale_external(&mut result, sketch, &state.registry.read().unwrap());
+                                PendingAnalysis::restart(&mut pending_analysis, sketch_id, sketch, &result);
+                                Ok((removed, serde_json::to_string(&result).unwrap_or("{}".into())))
+                            }
+                            _ => Err("Sketch feature not found".to_string()),
+                        };
+                        match cleaned {
+                            Ok((removed, solve_json)) => {
+                                let cleanup = serde_json::json!({
+                                    "sketch_id": feature_id.to_string(),
+                                    "removed": removed,
+                                }).to_string();
+                                if removed == 0 {
+                                    (Some(cleanup), None, Some(solve_json), None)
+                                } else {
+                                    let json = graph.snapshot_for_serialization();
+                                    (Some(cleanup), Some(json), Some(solve_json), None)
+                                }
+                            }
+                            Err(e) => (None, None, None, Some(e))
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(err) = error_msg {
+                        let _ = socket.send(Message::Text(format_command_error(&err, &text))).await;
+                    }
+                    if let Some(cleanup) = cleanup_json { let _ = socket.send(Message::Text(format!("CLEANUP_RESULT:{}", cleanup))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(solve_json) = solve_result_json { let _ = socket.send(Message::Text(format!("SKETCH_STATUS:{}", solve_json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                WebSocketCommand::CopyFeatures { ids, sketch_id } => {
+                    let ids: Vec<_> = ids.into_iter().map(cad_core::topo::EntityId::from_uuid).collect();
+                    let (copied, blamed) = {
+                        let graph = state.graph.read().unwrap();
+                        match sketch_id.map(cad_core::topo::EntityId::from_uuid) {
+                            Some(sketch_id) => (graph.copy_sketch_entities(sketch_id, &ids), Some(sketch_id)),
+                            // Copying features only fails on one that doesn't exist
+                            None => (graph.copy_features(&ids), ids.iter().find(|id| !graph.nodes.contains_key(id)).copied()),
+                        }
+                    };
+                    match copied {
+                        Ok(payload) => {
+                            let json = serde_json::to_string(&payload).unwrap_or("{}".to_string());
+                            let _ = socket.send(Message::Text(format!("CLIPBOARD:{}", json))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, format!("Failed to copy: {}", e)).with_detail("feature_id", blamed)))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::PasteFeatures { payload, remap, target } => {
+                    use cad_core::features::clipboard::PasteError;
+                    use cad_core::topo::EntityId;
+
+                    let remap = remap.into_iter().map(|(old, new)| (EntityId::from_uuid(old), EntityId::from_uuid(new))).collect();
+                    let (paste_json, json_update, unresolved, error_msg) = {
+                        let mut graph = state.graph.write().unwrap();
+                        match graph.paste(&payload, &remap, target.map(EntityId::from_uuid)) {
+                            Ok(result) => {
+                                info!("Pasted {} items", result.created.len());
+                                let paste = serde_json::to_string(&result).unwrap_or("{}".to_string());
+                                let json = graph.snapshot_for_serialization();
+                                (Some(paste), Some(json), None, None)
+                            }
+                            // Not an error: the client asks the user what to attach them to and pastes again
+                            Err(PasteError::Unresolved { references }) => (None, None, Some(references), None),
+                            Err(e) => (None, None, None, Some(format!("Failed to paste: {}", e))),
+                        }
+                    };
+                    let (json_update, program) = json_update.map(GraphSnapshot::regenerated).unzip();
+
+                    if let Some(references) = unresolved {
+                        let _ = socket.send(Message::Text(format!("PASTE_UNRESOLVED:{}", json!({ "references": references })))).await;
+                    }
+                    if let Some(err) = error_msg {
+                        // Sketch entities fail in their target sketch; a feature fragment has no feature in the document yet
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::FeatureError, &err).with_detail("feature_id", target)))).await;
+                    }
+                    if let Some(paste) = paste_json { let _ = socket.send(Message::Text(format!("PASTE_RESULT:{}", paste))).await; }
+                    if let Some(json) = json_update { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                    if let Some(program) = program { process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await; }
+                }
+
+                // Metadata is not read by regeneration, so these only broadcast the graph
+                WebSocketCommand::SetFeatureMetadata { id, patch } => {
+                    let entity_id = cad_core::topo::EntityId::from_uuid(id);
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.set_feature_metadata(entity_id, &patch)
+                            .map(|_| graph.snapshot_for_serialization())
+                    };
+                    match result {
+                        Ok(json) => { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::SetDocumentProperties { set, remove } => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.set_document_properties(&set, &remove)
+                            .map(|_| graph.snapshot_for_serialization())
+                    };
+                    match result {
+                        Ok(json) => { let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await; }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::PublishMeasurement { name, face } => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.publish_measurement(&name, face)
+                            .map(|_| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match result {
+                        Ok((json, program)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::ReplaceReference { feature_id, old, new } => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.replace_reference(cad_core::topo::EntityId::from_uuid(feature_id), old, new)
+                            .map(|_| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match result {
+                        Ok((json, program)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            // Regen re-runs the zombie check and reports the repaired list
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::ApplyZombieRepair { owner, param_path, action } => {
+                    let result = {
+                        let mut graph = state.graph.write().unwrap();
+                        graph.apply_zombie_repair(cad_core::topo::EntityId::from_uuid(owner), &param_path, &action)
+                            .map(|_| graph.snapshot_for_serialization())
+                    }.map(GraphSnapshot::regenerated);
+                    match result {
+                        Ok((json, program)) => {
+                            let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+                            process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                        }
+                        Err(e) => { let _ = socket.send(Message::Text(format_command_error(&e, &text))).await; }
+                    }
+                }
+
+                WebSocketCommand::FindFeatures { query } => {
+                    let ids: Vec<String> = {
+                        let graph = state.graph.read().unwrap();
+                        graph.find_features(&query).iter().map(|id| id.to_string()).collect()
+                    };
+                    let json = serde_json::to_string(&ids).unwrap_or("[]".into());
+                    let _ = socket.send(Message::Text(format!("FEATURES_FOUND:{}", json))).await;
+                }
+
+                WebSocketCommand::SetMeshQuality { ratio } => {
+                    if !(ratio > 0.0 && ratio <= 1.0) {
+                        let _ = socket.send(Message::Text(format_command_error("Mesh quality must be in (0, 1]", &text))).await;
+                        continue;
+                    }
+                    view.mesh_quality = ratio;
+                    if let Some(render) = view.render(&state) {
+                        let _ = socket.send(Message::Text(render)).await;
+                    }
+                }
+
+                WebSocketCommand::ShowCenterOfMass { show } => {
+                    view.show_center_of_mass = show;
+                    if let Some(render) = view.render(&state) {
+                        let _ = socket.send(Message::Text(render)).await;
+                    }
+                }
+
+                WebSocketCommand::SetRenderOptions { uvs } => {
+                    view.render_uvs = uvs;
+                    if let Some(render) = view.render(&state) {
+                        let _ = socket.send(Message::Text(render)).await;
+                    }
+                }
+
+                command @ (WebSocketCommand::IsolateFeature { .. } | WebSocketCommand::ClearIsolation) => {
+                    let isolated = match command {
+                        WebSocketCommand::IsolateFeature { id } => Some(cad_core::topo::EntityId::from_uuid(id)),
+                        _ => None,
+                    };
+                    view.isolated_feature = isolated;
+                    if let Some(render) = view.render(&state) {
+                        let _ = socket.send(Message::Text(render)).await;
+                    }
+                }
+
+                WebSocketCommand::SetCurveResolution { segments } => {
+                    if !(3..=1024).contains(&segments) {
+                        let _ = socket.send(Message::Text(format_command_error("Curve resolution must be between 3 and 1024 segments", &text))).await;
+                        continue;
+                    }
+                    runtime.tessellation_segments = segments;
+                    let program = current_program(&state);
+                    process_regen(&mut socket, &runtime, &generator, &program, &state, &mut selection_state, &mut view).await;
+                }
+
+                WebSocketCommand::RunSweep(spec) => {
+                    if let Err(error) = limits.check_sweep_rows(spec.variable_values.len()) {
+                        let _ = socket.send(Message::Text(format_error(&error))).await;
+                        continue;
+                    }
+                    // Rows regenerate off the runtime, on a snapshot so the shared graph stays unlocked
+                    let snapshot = state.graph.read().unwrap().snapshot_for_serialization();
+                    let segments = runtime.tessellation_segments;
+                    let total = spec.variable_values.len();
+                    let (rows_tx, mut rows_rx) = tokio::sync::mpsc::unbounded_channel();
+                    let sweep = tokio::task::spawn_blocking(move || {
+                        let runtime = cad_core::evaluator::Runtime::new().with_tessellation_segments(segments);
+                        for (index, values) in spec.variable_values.iter().enumerate() {
+                            let row = cad_core::analysis::evaluate_sweep_row(snapshot.graph(), &runtime, index, values, &spec.metrics);
+                            if rows_tx.send(row).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    let mut report = cad_core::analysis::SweepReport::default();
+                    while let Some(row) = rows_rx.recv().await {
+                        let progress = json!({
+                            "completed": report.rows.len() + 1,
+                            "total": total,
+                            "row": row
+                        });
+                        let _ = socket.send(Message::Text(format!("SWEEP_PROGRESS:{}", progress))).await;
+                        report.rows.push(row);
+                    }
+                    if let Err(e) = sweep.await {
+                        let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::RegenFailed, format!("Sweep failed: {}", e))))).await;
+                        continue;
+                    }
+                    let json = serde_json::to_string(&report).unwrap_or("{}".into());
+                    let _ = socket.send(Message::Text(format!("SWEEP_RESULT:{}", json))).await;
+                }
+
+                WebSocketCommand::RunAnalysis(spec) => {
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            // Wall thickness casts a ray per sample, so keep it off the runtime
+                            let tessellation = result.tessellation;
+                            let analyzed = tokio::task::spawn_blocking(move || cad_core::analysis::run_analysis(&tessellation, &spec)).await
+                                .unwrap_or_else(|e| Err(format!("Analysis failed: {}", e)));
+                            match analyzed {
+                                Ok(report) => {
+                                    let json = serde_json::to_string(&report).unwrap_or("{}".into());
+                                    let _ = socket.send(Message::Text(format!("ANALYSIS_UPDATE:{}", json))).await;
+                                }
+                                Err(e) => {
+                                    let _ = socket.send(Message::Text(format_command_error(&e, &text))).await;
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Analysis failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::GetReport { kind } => {
+                    match evaluate_snapshot(&state, &runtime, &generator) {
+                        Ok(result) => {
+                            let report = cad_core::reporting::feature_summary(&state.graph.read().unwrap(), &result);
+                            let body = match kind {
+                                cad_core::reporting::ReportKind::Holes => json!({ "kind": kind, "holes": report.holes }),
+                                cad_core::reporting::ReportKind::Features => json!({ "kind": kind, "features": report.features }),
+                            };
+                            let _ = socket.send(Message::Text(format!("REPORT_UPDATE:{}", body))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_evaluation_error("Report failed", e))).await;
+                        }
+                    }
+                }
+
+                WebSocketCommand::SaveDocument => {
+                    let graph = state.graph.read().unwrap().clone();
+                    let workspace = state.workspace.clone();
+                    let saved = tokio::task::spawn_blocking(move || autosave::save_document(&workspace, &graph)).await;
+                    match saved {
+                        Ok(Ok(saved_at_ms)) => {
+                            let _ = socket.send(Message::Text(format!("DOCUMENT_SAVED:{}", json!({ "saved_at_ms": saved_at_ms })))).await;
+                        }
+                        Ok(Err(e)) => {
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::SaveFailed, e.to_string())))).await;
+                        }
+                        Err(e) => {
+                            let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::SaveFailed, e.to_string())))).await;
+                        }
+                    }
+                }
+
+                // Handled before a document is picked
+                WebSocketCommand::OpenDocument(_)
+                | WebSocketCommand::CloseDocument { .. }
+                | WebSocketCommand::ListDocuments
+                | WebSocketCommand::SetActiveDocument { .. } => {}
+            }
+
+            if record {
+                state.autosave.record(&text);
+            }
+            drop(autosave_guard);
+            if is_document_change {
+                state.publish(session_id);
+            }
+        }
+    }
+}
+
+// Helpers
+
+async fn broadcast_selection(socket: &mut WebSocket, selection_state: &cad_core::topo::SelectionState) {
+    let update = serde_json::to_string(&selection_state.selected).unwrap_or("[]".into());
+    let _ = socket.send(Message::Text(format!("SELECTION_UPDATE:{}", update))).await;
+}
+
+async fn broadcast_groups(socket: &mut WebSocket, selection_state: &cad_core::topo::SelectionState) {
+    let groups = selection_state.list_groups();
+    let groups_json = serde_json::to_string(&groups).unwrap_or("[]".into());
+    let _ = socket.send(Message::Text(format!("SELECTION_GROUPS_UPDATE:{}", groups_json))).await;
+}
+
+/// Publish measurements of `result` to the variables and regenerate again
+/// while variables bound to them change, up to `MAX_BINDING_PASSES`
+/// evaluations in all. The graph is only locked between evaluations.
+/// `regenerated` follows the snapshot the last evaluation was built from.
+fn settle_measurement_bindings(
+    runtime: &cad_core::evaluator::Runtime,
+    generator: &cad_core::topo::IdGenerator,
+    state: &Arc<Document>,
+    mut result: cad_core::evaluator::runtime::EvaluationResult,
+    regenerated: &mut GraphSnapshot,
+) -> Result<cad_core::analysis::bindings::BoundRegeneration, cad_core::evaluator::runtime::KernelError> {
+    use cad_core::analysis::bindings::{BoundRegeneration, UnsettledBindings, MAX_BINDING_PASSES};
+    let mut passes = 1;
+    loop {
+        let (changed, snapshot) = {
+            let mut graph = state.graph.write().unwrap();
+            let changed = graph.bind_measurements(&result);
+            let snapshot = (!changed.is_empty() && passes < MAX_BINDING_PASSES).then(|| graph.snapshot_for_serialization());
+            (changed, snapshot)
+        };
+        match snapshot.map(GraphSnapshot::regenerated) {
+            Some((_, regen)) => {
+                result = runtime.evaluate(&regen.program, generator)?;
+                *regenerated = regen.snapshot;
+                passes += 1;
+            }
+            None => {
+                let unsettled = (!changed.is_empty()).then_some(UnsettledBindings { passes, variables: changed });
+                return Ok(BoundRegeneration { result, passes, unsettled });
+            }
+        }
+    }
+}
+
+/// ERROR_UPDATE payloads for features the regeneration rejected, named after
+/// the feature. A coded cause is sent as itself; an open profile names the
+/// sketch the feature was built from.
+fn feature_error_reports(errors: &[cad_core::evaluator::runtime::FeatureError], graph: &FeatureGraph) -> Vec<CadError> {
+    errors.iter().map(|error| {
+        let feature = graph.nodes.values().find(|f| f.id.to_string() == error.feature_id);
+        let name = feature.map_or(error.feature_id.as_str(), |f| f.name.as_str());
+        let message = format!("{}: {}", name, error.message);
+        match &error.cause {
+            Some(cause) if cause.code == ErrorCode::OpenProfile => {
+                let sketch_id = feature.into_iter()
+                    .flat_map(|f| &f.dependencies)
+                    .find(|id| graph.nodes.get(id).is_some_and(|dep| dep.feature_type == cad_core::features::types::FeatureType::Sketch));
+                CadError { message, ..cause.clone() }.with_detail("sketch_id", sketch_id)
+            }
+            Some(cause) => CadError { message, ..cause.clone() },
+            None => CadError::new(ErrorCode::FeatureError, message).with_detail("feature_id", &error.feature_id),
+        }
+    }).collect()
+}
+
+/// One ZOMBIE_REFERENCE warning per feature holding references that no longer resolve
+fn zombie_reports(zombies: &[cad_core::topo::registry::Zombie], graph: &FeatureGraph) -> Vec<CadError> {
+    let mut owners: Vec<cad_core::topo::EntityId> = Vec::new();
+    for zombie in zombies {
+        if !owners.contains(&zombie.owner) {
+            owners.push(zombie.owner);
+        }
+    }
+    owners.into_iter().map(|owner| {
+        let topo_ids: Vec<_> = zombies.iter().filter(|z| z.owner == owner).map(|z| z.id).collect();
+        let name = graph.nodes.get(&owner).map_or_else(|| owner.to_string(), |f| f.name.clone());
+        let message = format!("{}: {} reference(s) no longer resolve", name, topo_ids.len());
+        CadError::new(ErrorCode::ZombieReference, message).with_detail("feature_id", owner).with_detail("topo_ids", topo_ids)
+    }).collect()
+}
+
+/// Evaluate `program`, settle measurement bindings and record the result
+/// for DiffLastRegen, and a clean one for RevertToLastGood: the part of a
+/// regeneration that needs no socket
+fn evaluate_regen(
+    runtime: &cad_core::evaluator::Runtime,
+    generator: &cad_core::topo::IdGenerator,
+    program: &Regeneration,
+    state: &Arc<Document>,
+) -> Result<cad_core::analysis::bindings::BoundRegeneration, cad_core::evaluator::runtime::KernelError> {
+    let mut snapshot = program.snapshot.clone();
+    let bound = runtime.evaluate(&program.program, generator)
+        .and_then(|result| settle_measurement_bindings(runtime, generator, state, result, &mut snapshot))?;
+    state.regens.lock().unwrap().record(bound.result.snapshot());
+    if bound.unsettled.is_none() && bound.result.feature_errors.is_empty() {
+        state.graph.write().unwrap().record_good_regen(snapshot);
+    }
+    Ok(bound)
+}
+
+async fn process_regen(
+    socket: &mut WebSocket, 
+    runtime: &cad_core::evaluator::Runtime, 
     generator: &cad_core::topo::IdGenerator, 
-    program: &cad_core::evaluator::ast::Program, 
-    state: &Arc<AppState>,
-    selection_state: &mut cad_core::topo::SelectionState
+    program: &Regeneration, 
+    state: &Arc<Document>,
+    selection_state: &mut cad_core::topo::SelectionState,
+    view: &mut SessionView,
 ) {
-    match runtime.evaluate(program, generator) {
-        Ok(result) => {
+    match evaluate_regen(runtime, generator, program, state) {
+        Ok(bound) => {
+             let result = bound.result;
+             if let Some(unsettled) = &bound.unsettled {
+                 let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::RegenFailed, unsettled.to_string()).with_severity(Severity::Warning)))).await;
+             }
+             if bound.passes > 1 {
+                 // Bound variables changed value; refresh them on the client
+                 let json = state.graph.read().unwrap().snapshot_for_serialization();
+                 let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+             }
+
              // Validate References
              let mut registry = cad_core::topo::TopoRegistry::new();
              for (_, entity) in &result.topology_manifest {
                  registry.register(entity.clone());
              }
 
-             let required_refs = {
-                 let graph = state.graph.read().unwrap();
-                 graph.collect_all_references()
+             let (required_refs, feature_states, migration, renamed_json) = {
+                 let mut graph = state.graph.write().unwrap();
+                 // Remember datum placements so zombie sources degrade to the last good one
+                 graph.cache_datum_geometry(&result.topology_manifest);
+                 // Body names follow bodies whose ids changed
+                 let migration = graph.migrate_body_names(&result.tessellation.body_ids());
+                 // Projected sketch geometry follows the model it was projected from
+                 let reprojected = graph.reproject_sketches(&result.topology_manifest);
+                 let renamed_json = (!migration.migrated.is_empty() || reprojected > 0).then(|| graph.snapshot_for_serialization());
+                 (graph.collect_all_references(), graph.feature_states(), migration, renamed_json)
              };
+             if let Some(json) = renamed_json {
+                 let _ = socket.send(Message::Text(format!("GRAPH_UPDATE:{}", json))).await;
+             }
+             for lost in &migration.lost {
+                 let message = format!("Body '{}' was replaced by several bodies; rename the one it should name", lost.name);
+                 let _ = socket.send(Message::Text(format_error(&CadError::new(ErrorCode::BodyNameLost, &message).with_detail("name", &lost.name)))).await;
+             }
+             let states_json = serde_json::to_string(&feature_states).unwrap_or("{}".into());
+             let _ = socket.send(Message::Text(format!("FEATURE_STATES:{}", states_json))).await;
              
+             // Geometry from the last regeneration lets lost references report where they were
+             registry.carry_history(&state.registry.read().unwrap());
              let zombies = registry.validate_references(&required_refs);
              if !zombies.is_empty() {
                  let zombie_json = serde_json::to_string(&zombies).unwrap_or("[]".into());
                  let _ = socket.send(Message::Text(format!("ZOMBIE_UPDATE:{}", zombie_json))).await;
+                 let warnings = zombie_reports(&zombies, &state.graph.read().unwrap());
+                 for warning in &warnings {
+                     let _ = socket.send(Message::Text(format_error(warning))).await;
+                 }
              } else {
                  let _ = socket.send(Message::Text(format!("ZOMBIE_UPDATE:[]"))).await;
              }
@@ -718,15 +3196,692 @@ async fn process_regen(
                          );
                      }
                  }
+                 graph.tag_hidden(&mut tessellation);
              }
 
-             // Send Render Update
-             let json = serde_json::to_string(&tessellation).unwrap_or("{}".into());
-             let _ = socket.send(Message::Text(format!("RENDER_UPDATE:{}", json))).await;
+             let reports = feature_error_reports(&result.feature_errors, &state.graph.read().unwrap());
+             for report in &reports {
+                 let _ = socket.send(Message::Text(format_error(report))).await;
+             }
+
+             view.last_render = Some((tessellation, result.topology_manifest));
+             if let Some(render) = view.render(state) {
+                 let _ = socket.send(Message::Text(render)).await;
+             }
         }
         Err(e) => {
-            let error_msg = format_error("REGEN_FAILED", &format!("Regeneration failed: {}", e), "error");
-            let _ = socket.send(Message::Text(error_msg)).await;
+            let _ = socket.send(Message::Text(format_evaluation_error("Regeneration failed", e))).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_command_yields_command_error() {
+        let text = r#"{"command": "NotACommand", "payload": {}}"#;
+        let error = parse_command(text).expect_err("unknown command should fail to parse");
+        assert!(error.starts_with("ERROR_UPDATE:"));
+        let payload: serde_json::Value = serde_json::from_str(&error["ERROR_UPDATE:".len()..]).unwrap();
+        assert_eq!(payload["code"], "COMMAND_ERROR");
+        assert!(payload["message"].as_str().unwrap().contains("NotACommand"));
+        assert_eq!(payload["details"]["command"], text);
+
+        assert!(parse_command("not json at all").is_err());
+        assert!(parse_command(r#"{"command": "Regen"}"#).is_ok());
+    }
+
+    #[test]
+    fn test_update_of_missing_feature_is_rejected() {
+        let mut graph = FeatureGraph::new();
+        let sketch = cad_core::features::types::Feature::new("Sketch1", cad_core::features::types::FeatureType::Sketch);
+        let sketch_id = sketch.id;
+        graph.add_node(sketch);
+
+        let missing = uuid::Uuid::new_v4();
+        let update = parse_command(&format!(r#"{{"command": "UpdateFeature", "payload": {{"id": "{}", "params": {{}}}}}}"#, missing)).unwrap();
+        let error = validate_command_refs(&update, &graph).unwrap_err();
+        assert!(error.contains(&missing.to_string()));
+
+        let update = parse_command(&format!(r#"{{"command": "UpdateFeature", "payload": {{"id": "{}", "params": {{}}}}}}"#, sketch_id)).unwrap();
+        assert!(validate_command_refs(&update, &graph).is_ok());
+
+        let delete_var = parse_command(&format!(r#"{{"command": "VariableDelete", "payload": {{"id": "{}"}}}}"#, missing)).unwrap();
+        assert!(validate_command_refs(&delete_var, &graph).is_err());
+    }
+
+    #[test]
+    fn test_zombie_repair_command_parses_actions_and_checks_owner() {
+        let mut graph = FeatureGraph::new();
+        let extrude = cad_core::features::types::Feature::new("Extrude1", cad_core::features::types::FeatureType::Extrude);
+        let extrude_id = extrude.id;
+        graph.add_node(extrude);
+
+        let repair = |owner: &dyn std::fmt::Display, action: &str| parse_command(&format!(
+            r#"{{"command": "ApplyZombieRepair", "payload": {{"owner": "{}", "param_path": "profiles[0]", "action": {}}}}}"#,
+            owner, action,
+        ));
+        let suppress = repair(&extrude_id, r#""SuppressOwner""#).unwrap();
+        assert!(is_document_command(&suppress));
+        assert!(validate_command_refs(&suppress, &graph).is_ok());
+        let with = cad_core::topo::naming::TopoId::new(cad_core::topo::EntityId::new(), 0, cad_core::topo::naming::TopoRank::Edge);
+        let replace = repair(&extrude_id, &format!(r#"{{"Replace": {{"with": {}}}}}"#, serde_json::to_string(&with).unwrap()));
+        assert!(matches!(
+            replace,
+            Ok(WebSocketCommand::ApplyZombieRepair { action: cad_core::topo::registry::RepairAction::Replace { .. }, .. })
+        ));
+        let unknown_owner = repair(&uuid::Uuid::new_v4(), r#""ClearReference""#).unwrap();
+        assert!(validate_command_refs(&unknown_owner, &graph).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sketch_analysis_follows_deferred_solve() {
+        use cad_core::sketch::solver::SketchSolver;
+        use cad_core::sketch::types::{Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 });
+        sketch.add_constraint(SketchConstraint::Radius { entity: circle, value: 10.0, style: None });
+        sketch.add_constraint(SketchConstraint::Radius { entity: circle, value: 20.0, style: None });
+        let mut sync_sketch = sketch.clone();
+
+        let solved = SketchSolver::solve_deferred(&mut sketch);
+        assert!(solved.analysis_pending && solved.conflicts.is_none());
+        let mut pending = None;
+        let sketch_id = cad_core::topo::EntityId::new_deterministic("sketch");
+        PendingAnalysis::restart(&mut pending, sketch_id, &sketch, &solved);
+        let superseded = pending.as_ref().unwrap().cancel.clone();
+        PendingAnalysis::restart(&mut pending, sketch_id, &sketch, &solved);
+        assert!(superseded.is_cancelled(), "A new solve cancels the old analysis");
+
+        let analysis = pending.unwrap().task.await.unwrap().expect("Not cancelled");
+        let sync = SketchSolver::solve_with_result(&mut sync_sketch);
+        assert_eq!(serde_json::to_value(&analysis.conflicts).unwrap(), serde_json::to_value(&sync.conflicts).unwrap());
+        assert!(analysis.entity_statuses[0].involved_in_conflict);
+
+        // The conflict goes out as SKETCH_SOLVE_FAILED naming the sketch
+        let error = solve_failure(sketch_id, &analysis).expect("The radii conflict");
+        assert_eq!(error.code, ErrorCode::SketchSolveFailed);
+        assert!(error.check_details().is_ok());
+        assert_eq!(error.details["sketch_id"], json!(sketch_id));
+        let indices: Vec<usize> = serde_json::from_value(error.details["constraint_indices"].clone()).unwrap();
+        assert_eq!(indices, analysis.conflicts.unwrap().unsatisfied_constraints);
+        assert!(!indices.is_empty());
+    }
+
+    #[test]
+    fn test_zombies_and_feature_errors_are_coded() {
+        use cad_core::evaluator::runtime::FeatureError;
+        use cad_core::features::types::{Feature, FeatureType};
+        use cad_core::topo::naming::{TopoId, TopoRank};
+        use cad_core::topo::registry::Zombie;
+
+        let mut graph = FeatureGraph::new();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch);
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude);
+        extrude.dependencies.push(sketch.id);
+        let (sketch_id, extrude_id) = (sketch.id, extrude.id);
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+
+        let lost = |seed: &str| TopoId::new(cad_core::topo::EntityId::new_deterministic(seed), 1, TopoRank::Face);
+        let zombie = |id: TopoId| Zombie { id, owner: extrude_id, param_path: "face".into(), last_geometry: None, repairs: Vec::new() };
+        let reports = zombie_reports(&[zombie(lost("a")), zombie(lost("b"))], &graph);
+        assert_eq!(reports.len(), 1, "One warning per owning feature");
+        assert_eq!(reports[0].code, ErrorCode::ZombieReference);
+        assert_eq!(reports[0].severity, Severity::Warning);
+        assert!(reports[0].message.starts_with("Extrude1:"), "{}", reports[0].message);
+        assert_eq!(reports[0].details, json!({ "feature_id": extrude_id, "topo_ids": [lost("a"), lost("b")] }));
+
+        let open = cad_core::evaluator::runtime::KernelError::OpenProfile(vec!["line".into()]);
+        let errors = [
+            FeatureError { feature_id: extrude_id.to_string(), message: open.to_string(), cause: Some(open.into()) },
+            FeatureError { feature_id: extrude_id.to_string(), message: "Bad taper".into(), cause: None },
+        ];
+        let reports = feature_error_reports(&errors, &graph);
+        assert_eq!(reports[0].code, ErrorCode::OpenProfile);
+        assert_eq!(reports[0].details, json!({ "sketch_id": sketch_id, "entity_ids": ["line"] }));
+        assert_eq!(reports[1].code, ErrorCode::FeatureError);
+        assert_eq!(reports[1].message, "Extrude1: Bad taper");
+        assert_eq!(reports[1].details, json!({ "feature_id": extrude_id.to_string() }));
+    }
+
+    #[test]
+    fn test_sketch_status_flags_constraints_on_lost_projections() {
+        use cad_core::features::types::{Feature, FeatureType, ParameterValue};
+        use cad_core::sketch::solver::SketchSolver;
+        use cad_core::sketch::types::{Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+        use cad_core::topo::registry::AnalyticGeometry;
+
+        let mut square = Sketch::new(SketchPlane::default());
+        let corners = [[0.0, 0.0], [40.0, 0.0], [40.0, 40.0], [0.0, 40.0]];
+        for i in 0..4 {
+            square.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+        }
+        let mut graph = FeatureGraph::new();
+        let base = Feature::new("Base sketch", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(square));
+        let mut extrude = Feature::new("Base", FeatureType::Extrude).with_param("distance", ParameterValue::Float(10.0));
+        extrude.dependencies.push(base.id);
+        let (base_id, extrude_id) = (base.id, extrude.id);
+        graph.add_node(base);
+        graph.add_node(extrude);
+
+        let runtime = cad_core::evaluator::Runtime::new();
+        let generator = cad_core::topo::IdGenerator::new("Stale");
+        let regenerate = |graph: &mut FeatureGraph| {
+            let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+            graph.reproject_sketches(&result.topology_manifest);
+            let mut registry = cad_core::topo::TopoRegistry::new();
+            result.topology_manifest.values().for_each(|entity| registry.register(entity.clone()));
+            (registry, result.topology_manifest)
+        };
+        let (_, manifest) = regenerate(&mut graph);
+        let edge = manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Line { start, end }
+                if start[1] == 40.0 && end[1] == 40.0 && start[2] == 10.0))
+            .map(|e| e.id)
+            .expect("Box has a top back edge");
+
+        let mut holes = Sketch::new(SketchPlane::default());
+        let projected = holes.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [40.0, 0.0] });
+        holes.external_references.insert(projected, edge);
+        let hole = holes.add_entity(SketchGeometry::Circle { center: [20.0, 20.0], radius: 3.0 });
+        holes.add_constraint(SketchConstraint::OffsetFromExternal { entity: hole, external: projected, distance: 12.0 });
+        let holes = Feature::new("Holes", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(holes));
+        let holes_id = holes.id;
+        graph.add_node(holes);
+
+        let status = |graph: &FeatureGraph, registry: &cad_core::topo::TopoRegistry| {
+            let Some(ParameterValue::Sketch(sketch)) = graph.nodes[&holes_id].parameters.get("sketch_data") else { unreachable!() };
+            let mut solved = sketch.clone();
+            let mut result = SketchSolver::solve_deferred(&mut solved);
+            flag_stale_external(&mut result, &solved, registry);
+            result
+        };
+        let (registry, _) = regenerate(&mut graph);
+        assert!(status(&graph, &registry).stale_external_constraints.is_empty());
+
+        // A base rebuilt by another feature has edges in the same place, under other names
+        graph.remove_node(extrude_id).unwrap();
+        let mut rebuilt = Feature::new("Rebuilt", FeatureType::Extrude).with_param("distance", ParameterValue::Float(10.0));
+        rebuilt.dependencies.push(base_id);
+        graph.add_node(rebuilt);
+        let (registry, _) = regenerate(&mut graph);
+        assert!(registry.resolve(&edge).is_none());
+        let result = status(&graph, &registry);
+        assert_eq!(result.stale_external_constraints, vec![0]);
+        assert!(result.converged, "Held against the last projection");
+    }
+
+    #[test]
+    fn test_regions_are_computed_on_the_solved_sketch() {
+        use cad_core::sketch::types::{ConstraintPoint, Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+
+        // Rectangle whose last corner is left open; a Coincident closes it once solved
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]];
+        let ids: Vec<_> = (0..4).map(|i| {
+            let end = if i == 3 { [0.0, 1.0] } else { corners[(i + 1) % 4] };
+            sketch.add_entity(SketchGeometry::Line { start: corners[i], end })
+        }).collect();
+        sketch.add_constraint(SketchConstraint::Coincident {
+            points: [ConstraintPoint { id: ids[3], index: 1 }, ConstraintPoint { id: ids[0], index: 0 }],
+        });
+        assert!(cad_core::sketch::regions::find_regions(&sketch.entities).is_empty(), "Raw geometry is open");
+
+        let first = solved_regions_payload(&sketch);
+        assert_eq!(first["solve"]["converged"], true);
+        let regions = first["regions"].as_array().unwrap();
+        assert_eq!(regions.len(), 1, "Solved geometry closes the rectangle");
+        assert_eq!(regions[0]["orientation"], "CCW");
+        assert!(regions[0]["parent"].is_null());
+
+        let second = solved_regions_payload(&sketch);
+        assert_eq!(second["regions"][0]["stable_id"], regions[0]["stable_id"]);
+        assert!(cad_core::sketch::regions::find_regions(&sketch.entities).is_empty(), "Stored sketch is not modified");
+    }
+
+    #[test]
+    fn test_solved_sketch_squares_up_perturbed_rectangle() {
+        use cad_core::sketch::types::{ConstraintPoint, Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+
+        // Rectangle drawn slightly askew, constrained to be square
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let corners = [[0.0, 0.0], [10.2, 0.3], [9.8, 5.1], [-0.1, 4.7]];
+        let ids: Vec<_> = (0..4).map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] })).collect();
+        for i in 0..4 {
+            sketch.add_constraint(SketchConstraint::Coincident {
+                points: [ConstraintPoint { id: ids[i], index: 1 }, ConstraintPoint { id: ids[(i + 1) % 4], index: 0 }],
+            });
+        }
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: ids[0] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: ids[1] });
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: ids[2] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: ids[3] });
+
+        let payload = solved_sketch_payload(&sketch);
+        assert_eq!(payload["solve"]["converged"], true);
+        let entities: Vec<cad_core::sketch::types::SketchEntity> = serde_json::from_value(payload["entities"].clone()).unwrap();
+        for (i, entity) in entities.iter().enumerate() {
+            let SketchGeometry::Line { start, end } = entity.geometry else { panic!("Not a line") };
+            let (along, across) = if i % 2 == 0 { (0, 1) } else { (1, 0) };
+            assert!((start[across] - end[across]).abs() < 1e-6, "Side {} is squared up: {:?} {:?}", i, start, end);
+            assert!((start[along] - end[along]).abs() > 1.0);
+        }
+        assert_eq!(sketch.entities[1].geometry, SketchGeometry::Line { start: corners[1], end: corners[2] }, "Stored sketch is not modified");
+    }
+    #[test]
+    fn test_batch_applies_all_or_nothing_with_one_regen() {
+        let registry = cad_core::topo::TopoRegistry::new();
+        let selected = std::collections::HashSet::new();
+        let create = |name: &str| format!(r#"{{"command": "CreateFeature", "payload": {{"type": "Point", "name": "{}"}}}}"#, name);
+
+        let batch = parse_command(&format!(r#"{{"command": "Batch", "payload": [{}, {}, {}]}}"#, create("A"), create("B"), create("C"))).unwrap();
+        assert!(is_document_command(&batch));
+        let WebSocketCommand::Batch(commands) = batch else { panic!("Expected a batch") };
+        let mut graph = FeatureGraph::new();
+        let deletions = apply_batch(&mut graph, &registry, &selected, commands).unwrap();
+        assert!(deletions.is_empty());
+        let mut names: Vec<_> = graph.nodes.values().map(|f| f.name.clone()).collect();
+        names.sort();
+        assert_eq!(names, ["A", "B", "C"]);
+
+        // A failing sub-command rolls back the ones before it
+        let missing = uuid::Uuid::new_v4();
+        let update = format!(r#"{{"command": "UpdateFeature", "payload": {{"id": "{}", "params": {{}}}}}}"#, missing);
+        let batch = parse_command(&format!(r#"{{"command": "Batch", "payload": [{}, {}]}}"#, create("D"), update)).unwrap();
+        let WebSocketCommand::Batch(commands) = batch else { panic!("Expected a batch") };
+        let error = apply_batch(&mut graph, &registry, &selected, commands).unwrap_err();
+        assert!(error.contains("Batch command 1") && error.contains(&missing.to_string()), "{}", error);
+        assert_eq!(graph.nodes.len(), 3);
+
+        // Only edits can be batched
+        let batch = parse_command(r#"{"command": "Batch", "payload": [{"command": "SaveDocument"}]}"#).unwrap();
+        let WebSocketCommand::Batch(commands) = batch else { panic!("Expected a batch") };
+        assert!(apply_batch(&mut graph, &registry, &selected, commands).is_err());
+    }
+
+    #[test]
+    fn test_seeded_create_mints_ids_from_document_namespace() {
+        let registry = cad_core::topo::TopoRegistry::new();
+        let selected = std::collections::HashSet::new();
+        let script = r#"{"command": "Batch", "payload": [
+            {"command": "CreateFeature", "payload": {"type": "Point", "name": "A", "seed": "a"}},
+            {"command": "CreateFeature", "payload": {"type": "Point", "name": "B"}}
+        ]}"#;
+        let run = |namespace: &str| {
+            let mut graph = FeatureGraph::new();
+            graph.id_namespace = namespace.to_string();
+            let WebSocketCommand::Batch(commands) = parse_command(script).unwrap() else { panic!("Expected a batch") };
+            apply_batch(&mut graph, &registry, &selected, commands).unwrap();
+            let mut ids: Vec<_> = graph.nodes.values().map(|f| (f.name.clone(), f.id)).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(run("doc"), run("doc"));
+        let other = run("other");
+        assert!(run("doc").iter().zip(&other).all(|(a, b)| a.1 != b.1));
+
+        // A seed used twice fails the batch instead of being re-minted
+        let mut graph = FeatureGraph::new();
+        let WebSocketCommand::Batch(commands) = parse_command(script).unwrap() else { panic!("Expected a batch") };
+        apply_batch(&mut graph, &registry, &selected, commands).unwrap();
+        let WebSocketCommand::Batch(commands) = parse_command(script).unwrap() else { panic!("Expected a batch") };
+        let error = apply_batch(&mut graph, &registry, &selected, commands).unwrap_err();
+        assert!(error.contains("Batch command 0") && error.contains("already in use"), "{}", error);
+        assert_eq!(graph.nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_live_sketch_commands_are_journaled_and_checked() {
+        let graph = FeatureGraph::new();
+        let live = parse_command(&format!(
+            r#"{{"command": "UpdateSketchLive", "payload": {{"feature_id": "{}", "sketch_data": {}}}}}"#,
+            uuid::Uuid::new_v4(),
+            serde_json::to_string(&cad_core::sketch::types::Sketch::new(Default::default())).unwrap(),
+        )).unwrap();
+        assert!(is_document_command(&live));
+        assert!(validate_command_refs(&live, &graph).is_err(), "The sketch must exist");
+
+        let commit = parse_command(r#"{"command": "CommitSketch"}"#).unwrap();
+        assert!(is_document_command(&commit));
+        let debounce = parse_command(r#"{"command": "SetLiveSketchDebounce", "payload": {"ms": 0}}"#).unwrap();
+        assert!(!is_document_command(&debounce));
+    }
+
+    #[test]
+    fn test_entity_meta_and_conversions_are_journaled_and_names_stay_unique() {
+        let mut graph = FeatureGraph::new();
+        let meta = parse_command(&format!(
+            r#"{{"command": "UpdateSketchEntityMeta", "payload": {{"feature_id": "{}", "entity_id": "{}", "meta": {{"name": "base", "locked": true}}}}}}"#,
+            uuid::Uuid::new_v4(),
+            uuid::Uuid::new_v4(),
+        )).unwrap();
+        assert!(is_document_command(&meta));
+        assert!(validate_command_refs(&meta, &graph).is_err(), "The sketch must exist");
+        let convert = parse_command(&format!(
+            r#"{{"command": "ConvertSketchEntity", "payload": {{"feature_id": "{}", "entity_id": "{}", "to": {{"Arc": {{"start_angle": 0.0, "end_angle": 1.0}}}}}}}}"#,
+            uuid::Uuid::new_v4(),
+            uuid::Uuid::new_v4(),
+        )).unwrap();
+        assert!(is_document_command(&convert));
+        assert!(validate_command_refs(&convert, &graph).is_err());
+
+        let sketch = cad_core::features::types::Feature::new("Sketch1", cad_core::features::types::FeatureType::Sketch);
+        let id = sketch.id;
+        graph.add_node(sketch);
+        let mut sketch = cad_core::sketch::types::Sketch::new(Default::default());
+        for x in [0.0, 1.0] {
+            sketch.add_entity(cad_core::sketch::types::SketchGeometry::Point { pos: [x, 0.0] });
+        }
+        sketch.entities.iter_mut().for_each(|e| e.name = Some("base".to_string()));
+        let params = [("sketch_data".to_string(), cad_core::features::types::ParameterValue::Sketch(sketch))].into();
+        let error = update_feature(&mut graph, UpdateCmd { id: id.0, params, highlight_entities: Vec::new() }).unwrap_err();
+        assert_eq!(error, "Entity name 'base' is used more than once");
+        assert!(!graph.nodes[&id].parameters.contains_key("sketch_data"), "A rejected sketch isn't stored");
+    }
+
+    #[test]
+    fn test_document_commands_parse_and_commands_name_their_document() {
+        let id = uuid::Uuid::new_v4();
+        let open = parse_command(r#"{"command": "OpenDocument", "payload": {"Path": "parts/bracket"}}"#).unwrap();
+        assert!(matches!(open, WebSocketCommand::OpenDocument(DocumentSource::Path(ref p)) if p.ends_with("bracket")));
+        assert!(matches!(parse_command(r#"{"command": "OpenDocument", "payload": "New"}"#).unwrap(), WebSocketCommand::OpenDocument(DocumentSource::New)));
+        let close = parse_command(&format!(r#"{{"command": "CloseDocument", "payload": {{"id": "{}"}}}}"#, id)).unwrap();
+        assert!(matches!(close, WebSocketCommand::CloseDocument { id: DocumentId(closed) } if closed == id));
+        assert!(!is_document_command(&close), "Not journaled in any one document");
+        assert!(parse_command(r#"{"command": "ListDocuments"}"#).is_ok());
+
+        // Any command may name its document, which must be the session's active one
+        let text = format!(r#"{{"command": "Regen", "document": "{}"}}"#, id);
+        assert!(matches!(parse_command(&text).unwrap(), WebSocketCommand::Regen));
+        assert_eq!(command_document(&text), Some(DocumentId(id)));
+        assert_eq!(command_document(r#"{"command": "Regen"}"#), None);
+        let (active, other) = (DocumentId(id), DocumentId(uuid::Uuid::new_v4()));
+        assert_eq!(target_document(None, Some(active)), Ok(active));
+        assert_eq!(target_document(Some(active), Some(active)), Ok(active));
+        assert!(target_document(Some(other), Some(active)).unwrap_err().contains("not active"));
+        assert!(target_document(Some(active), None).is_err());
+    }
+
+    #[test]
+    fn test_add_sketch_entity_accepts_arc_specs() {
+        use cad_core::sketch::edit::EntitySpec;
+        let add = |entity: &str| parse_command(&format!(
+            r#"{{"command": "AddSketchEntity", "payload": {{"feature_id": "{}", "entity": {}}}}}"#, uuid::Uuid::new_v4(), entity,
+        )).unwrap();
+        let three = add(r#"{"ThreePointArc": {"start": [0, 0], "through": [1, 1], "end": [2, 0]}}"#);
+        assert!(matches!(three, WebSocketCommand::AddSketchEntity { entity: EntitySpec::ThreePointArc { .. }, .. }));
+        assert!(is_document_command(&three));
+        assert!(validate_command_refs(&three, &FeatureGraph::new()).is_err(), "The sketch must exist");
+        let tangent = add(&format!(r#"{{"TangentArc": {{"from": {{"id": "{}", "index": 1}}, "end": [5, 5]}}}}"#, uuid::Uuid::new_v4()));
+        assert!(matches!(tangent, WebSocketCommand::AddSketchEntity { entity: EntitySpec::TangentArc { .. }, .. }));
+        let line = add(r#"{"Geometry": {"Line": {"start": [0, 0], "end": [1, 0]}}}"#);
+        assert!(matches!(line, WebSocketCommand::AddSketchEntity { entity: EntitySpec::Geometry(_), .. }));
+    }
+
+    #[test]
+    fn test_evaluate_at_checks_its_rollback_point_and_is_not_journaled() {
+        let all = parse_command(r#"{"command": "EvaluateAt", "payload": {"rollback_id": null}}"#).unwrap();
+        assert!(matches!(all, WebSocketCommand::EvaluateAt { rollback_id: None }));
+        assert!(!is_document_command(&all));
+        assert!(validate_command_refs(&all, &FeatureGraph::new()).is_ok());
+        let at = parse_command(&format!(r#"{{"command": "EvaluateAt", "payload": {{"rollback_id": "{}"}}}}"#, uuid::Uuid::new_v4())).unwrap();
+        assert!(validate_command_refs(&at, &FeatureGraph::new()).is_err(), "The rollback feature must exist");
+    }
+
+    #[test]
+    fn test_render_update_reports_center_of_mass_when_shown() {
+        use cad_core::geometry::Point3;
+        // 2x4x6 box centered at the origin
+        let mut tessellation = cad_core::geometry::Tessellation::new();
+        let id = cad_core::topo::naming::TopoId::new(cad_core::topo::EntityId::new(), 0, cad_core::topo::naming::TopoRank::Face);
+        let p = |x: f64, y: f64, z: f64| Point3::new(x, y * 2.0, z * 3.0);
+        let quads = [
+            [p(-1., -1., -1.), p(-1., 1., -1.), p(1., 1., -1.), p(1., -1., -1.)],
+            [p(-1., -1., 1.), p(1., -1., 1.), p(1., 1., 1.), p(-1., 1., 1.)],
+            [p(-1., -1., -1.), p(1., -1., -1.), p(1., -1., 1.), p(-1., -1., 1.)],
+            [p(-1., 1., -1.), p(-1., 1., 1.), p(1., 1., 1.), p(1., 1., -1.)],
+            [p(-1., -1., -1.), p(-1., -1., 1.), p(-1., 1., 1.), p(-1., 1., -1.)],
+            [p(1., -1., -1.), p(1., 1., -1.), p(1., 1., 1.), p(1., -1., 1.)],
+        ];
+        for q in quads {
+            tessellation.add_triangle(q[0], q[1], q[2], id);
+            tessellation.add_triangle(q[0], q[2], q[3], id);
+        }
+
+        let payload = |message: String| -> serde_json::Value {
+            serde_json::from_str(message.strip_prefix("RENDER_UPDATE:").unwrap()).unwrap()
+        };
+        let shown = payload(render_update(tessellation.clone(), 1.0, true, None));
+        let com: [f64; 3] = serde_json::from_value(shown["center_of_mass"].clone()).unwrap();
+        assert!(com.iter().all(|c| c.abs() < 1e-6), "{:?}", com);
+        let decimated = payload(render_update(tessellation.clone(), 0.5, true, None));
+        let com: [f64; 3] = serde_json::from_value(decimated["center_of_mass"].clone()).unwrap();
+        assert!(com.iter().all(|c| c.abs() < 1e-6), "Kept at a lower mesh quality: {:?}", com);
+
+        let hidden = payload(render_update(tessellation, 1.0, false, None));
+        assert!(hidden.get("center_of_mass").is_none());
+        assert!(parse_command(r#"{"command": "ShowCenterOfMass", "payload": {"show": true}}"#).is_ok());
+    }
+
+    #[test]
+    fn test_regenerations_are_recorded_for_diff_last_regen() {
+        use cad_core::features::types::{Feature, FeatureType, ParameterValue};
+        use cad_core::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+        let root = std::env::temp_dir().join(format!("cad-regen-history-{}", std::process::id()));
+        let manager = DocumentManager::new(root.clone(), autosave::DEFAULT_INTERVAL);
+        let document = manager.open(DocumentSource::New).unwrap();
+        let mut square = Sketch::new(SketchPlane::default());
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        for i in 0..4 {
+            square.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+        }
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(square));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude).with_param("distance", ParameterValue::Float(5.0));
+        extrude.dependencies.push(sketch.id);
+        let extrude_id = extrude.id;
+        {
+            let mut graph = document.graph.write().unwrap();
+            graph.add_node(sketch);
+            graph.add_node(extrude);
+        }
+
+        let (runtime, generator) = (cad_core::evaluator::Runtime::new(), cad_core::topo::IdGenerator::new("History"));
+        let regenerate = || {
+            let (_, program) = document.graph.read().unwrap().snapshot_for_serialization().regenerated();
+            evaluate_regen(&runtime, &generator, &program, &document).unwrap();
+        };
+        regenerate();
+        assert!(document.regens.lock().unwrap().diff().is_none(), "One regeneration has nothing to compare with");
+
+        let taller = std::collections::HashMap::from([("distance".to_string(), ParameterValue::Float(6.0))]);
+        document.graph.write().unwrap().update_feature_params(extrude_id, taller).unwrap();
+        regenerate();
+        regenerate();
+        let diff = document.regens.lock().unwrap().diff().expect("Two different regenerations");
+        assert!(diff.added.is_empty() && diff.removed.is_empty(), "{:?}", diff);
+        assert!(!diff.moved.is_empty(), "The top face and its edges moved up");
+        let height = |bounds: Option<[[f64; 3]; 2]>| bounds.map(|[min, max]| max[2] - min[2]);
+        assert_eq!((height(diff.before.bounds), height(diff.after.bounds)), (Some(5.0), Some(6.0)));
+
+        // The clean regeneration's snapshot is what a revert goes back to
+        let tallest = std::collections::HashMap::from([("distance".to_string(), ParameterValue::Float(7.0))]);
+        let mut graph = document.graph.write().unwrap();
+        graph.update_feature_params(extrude_id, tallest).unwrap();
+        graph.revert_to_last_good().unwrap();
+        assert!(matches!(graph.nodes[&extrude_id].parameters.get("distance"), Some(ParameterValue::Float(d)) if *d == 6.0));
+        drop(graph);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_view_options_belong_to_the_session() {
+        use cad_core::geometry::Point3;
+        let root = std::env::temp_dir().join(format!("cad-session-view-{}", std::process::id()));
+        let manager = DocumentManager::new(root.clone(), autosave::DEFAULT_INTERVAL);
+        let document = manager.open(DocumentSource::New).unwrap();
+        let mut tessellation = cad_core::geometry::Tessellation::new();
+        let id = cad_core::topo::naming::TopoId::new(cad_core::topo::EntityId::new(), 0, cad_core::topo::naming::TopoRank::Face);
+        // Unit cube around the origin
+        let p = |x: f64, y: f64, z: f64| Point3::new(x, y, z);
+        let quads = [
+            [p(-1., -1., -1.), p(-1., 1., -1.), p(1., 1., -1.), p(1., -1., -1.)],
+            [p(-1., -1., 1.), p(1., -1., 1.), p(1., 1., 1.), p(-1., 1., 1.)],
+            [p(-1., -1., -1.), p(1., -1., -1.), p(1., -1., 1.), p(-1., -1., 1.)],
+            [p(-1., 1., -1.), p(-1., 1., 1.), p(1., 1., 1.), p(1., 1., -1.)],
+            [p(-1., -1., -1.), p(-1., -1., 1.), p(-1., 1., 1.), p(-1., 1., -1.)],
+            [p(1., -1., -1.), p(1., 1., -1.), p(1., 1., 1.), p(1., -1., 1.)],
+        ];
+        for q in quads {
+            tessellation.add_triangle(q[0], q[1], q[2], id);
+            tessellation.add_triangle(q[0], q[2], q[3], id);
+        }
+
+        let payload = |message: String| -> serde_json::Value {
+            serde_json::from_str(message.strip_prefix("RENDER_UPDATE:").unwrap()).unwrap()
+        };
+        let (mut mine, mut theirs) = (SessionView::default(), SessionView::default());
+        assert!(mine.render(&document).is_none(), "Nothing to render before the first regeneration");
+        for view in [&mut mine, &mut theirs] {
+            view.last_render = Some((tessellation.clone(), Default::default()));
+        }
+        mine.show_center_of_mass = true;
+        mine.render_uvs = true;
+        mine.isolated_feature = Some(cad_core::topo::EntityId::new());
+        assert!(payload(mine.render(&document).unwrap()).get("center_of_mass").is_some());
+        assert_eq!(mine.isolated_feature, None, "Isolating a feature that isn't there ends isolation");
+        let other = payload(theirs.render(&document).unwrap());
+        assert!(other.get("center_of_mass").is_none() && other.get("uvs").is_none(), "Another session's view is unchanged");
+        assert_eq!(other["indices"].as_array().unwrap().len(), tessellation.indices.len());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_render_update_adds_uvs_after_decimating() {
+        use cad_core::geometry::Point3;
+        use cad_core::topo::naming::{TopoId, TopoRank};
+        use cad_core::topo::registry::{AnalyticGeometry, KernelEntity};
+        // A 4 x 2 plane split into a fan of triangles, enough for decimation to drop some
+        let mut tessellation = cad_core::geometry::Tessellation::new();
+        let id = TopoId::new(cad_core::topo::EntityId::new(), 0, TopoRank::Face);
+        let corners: Vec<Point3> = (0..=8).map(|i| Point3::new(i as f64 * 0.5, 0.0, 0.0)).chain([Point3::new(4.0, 2.0, 0.0), Point3::new(0.0, 2.0, 0.0)]).collect();
+        for pair in corners.windows(2) {
+            tessellation.add_triangle(Point3::new(2.0, 1.0, 0.0), pair[0], pair[1], id);
+        }
+        let surfaces = std::collections::HashMap::from([(id, KernelEntity { id, geometry: AnalyticGeometry::Plane { origin: [0.0; 3], normal: [0.0, 0.0, 1.0] } })]);
+
+        let payload = |message: String| -> cad_core::geometry::Tessellation {
+            serde_json::from_str(message.strip_prefix("RENDER_UPDATE:").unwrap()).unwrap()
+        };
+        let plain = payload(render_update(tessellation.clone(), 1.0, false, None));
+        assert!(plain.uvs.is_none());
+        let decimated = payload(render_update(tessellation.clone(), 0.5, false, Some(&surfaces)));
+        assert!(decimated.indices.len() < tessellation.indices.len());
+        let uvs = decimated.uvs.unwrap();
+        assert_eq!(uvs.len(), decimated.vertices.len() / 3 * 2);
+        for (uv, position) in uvs.chunks(2).zip(decimated.vertices.chunks(3)) {
+            assert_eq!(uv, &position[..2], "On the z = 0 plane UVs are x and y");
+        }
+        assert!(parse_command(r#"{"command": "SetRenderOptions", "payload": {"uvs": true}}"#).is_ok());
+    }
+
+    #[test]
+    fn test_variable_add_with_empty_expression_reports_why() {
+        let text = r#"{"command": "VariableAdd", "payload": {"name": "width", "expression": "   "}}"#;
+        let WebSocketCommand::VariableAdd(cmd) = parse_command(text).unwrap() else { panic!("Expected VariableAdd") };
+        let mut graph = FeatureGraph::new();
+        let error = add_variable(&mut graph, cmd).unwrap_err();
+        assert!(graph.variables.get_by_name("width").is_none());
+
+        let message = format_command_error(&error, text);
+        let payload: serde_json::Value = serde_json::from_str(message.strip_prefix("ERROR_UPDATE:").unwrap()).unwrap();
+        assert_eq!(payload["code"], "COMMAND_ERROR");
+        let message = payload["message"].as_str().unwrap();
+        assert!(message.contains("'width'") && message.contains("Expression is empty"), "{}", message);
+    }
+
+    #[test]
+    fn test_import_step_adds_bodies_and_is_journaled() {
+        let data = include_str!("../../core/tests/fixtures/block_inch.step");
+        let command = parse_command(&json!({ "command": "ImportStep", "payload": { "data": data } }).to_string()).unwrap();
+        assert!(is_document_command(&command));
+        let WebSocketCommand::ImportStep { data } = command else { panic!("Expected ImportStep") };
+
+        // Read without the graph, as the handler does, then added under its lock
+        let graph = std::sync::RwLock::new(FeatureGraph::new());
+        let bodies = {
+            let _readers_still_welcome = graph.read().unwrap();
+            cad_core::features::import::read_step(&data).unwrap()
+        };
+        let report = graph.write().unwrap().add_step_bodies(bodies);
+        let graph = graph.into_inner().unwrap();
+        let payload = serde_json::to_value(&report).unwrap();
+        assert_eq!(payload["unit"], "inch");
+        assert_eq!(payload["features"].as_array().map(|f| f.len()), Some(1));
+        assert_eq!(graph.nodes[&report.features[0]].feature_type, cad_core::features::types::FeatureType::ImportedBody);
+    }
+
+    #[test]
+    fn test_commands_that_evaluate_the_model_take_the_regen_rate() {
+        use cad_core::analysis::{AnalysisSpec, SweepSpec};
+        use cad_core::topo::naming::{TopoId, TopoRank};
+
+        let body = TopoId::new(cad_core::topo::EntityId::new(), 0, TopoRank::Solid);
+        let regenerating = [
+            WebSocketCommand::Regen,
+            WebSocketCommand::RunSweep(SweepSpec::default()),
+            WebSocketCommand::RunAnalysis(AnalysisSpec::Stability { body, up_axis: [0.0, 0.0, 1.0] }),
+            WebSocketCommand::ImportStep { data: String::new() },
+            WebSocketCommand::EvaluateAt { rollback_id: None },
+            WebSocketCommand::GetReport { kind: cad_core::reporting::ReportKind::Holes },
+            WebSocketCommand::GetSection { origin: [0.0; 3], normal: [0.0, 0.0, 1.0] },
+        ];
+        for command in &regenerating {
+            assert_eq!(command_class(command), CommandClass::Regen, "{:?}", command);
+        }
+        assert_eq!(command_class(&WebSocketCommand::ClearSelection), CommandClass::Query);
+    }
+
+    #[test]
+    fn test_updates_do_not_wait_on_graph_serialization() {
+        use cad_core::features::types::{Feature, FeatureType, ParameterValue};
+        use std::sync::{Barrier, RwLock};
+
+        let mut graph = FeatureGraph::new();
+        let point = Feature::new("Point1", FeatureType::Point);
+        let point_id = point.id;
+        graph.add_node(point);
+        let graph = Arc::new(RwLock::new(graph));
+        let before = graph.read().unwrap().snapshot_for_serialization().to_json();
+
+        // The serializer takes its snapshot, then stays mid-encoding until the update is done
+        let (taken, updated) = (Arc::new(Barrier::new(2)), Arc::new(Barrier::new(2)));
+        let serializer = {
+            let (graph, taken, updated) = (graph.clone(), taken.clone(), updated.clone());
+            std::thread::spawn(move || {
+                let snapshot = graph.read().unwrap().snapshot_for_serialization();
+                taken.wait();
+                updated.wait();
+                snapshot.to_json()
+            })
+        };
+
+        taken.wait();
+        let cmd = UpdateCmd {
+            id: point_id.0,
+            params: [("x".to_string(), ParameterValue::Float(1.0))].into(),
+            highlight_entities: Vec::new(),
+        };
+        {
+            let mut graph = graph.try_write().expect("Serializing doesn't hold the lock");
+            update_feature(&mut graph, cmd).unwrap();
         }
+        updated.wait();
+        assert_eq!(serializer.join().unwrap(), before, "The snapshot predates the update");
+        assert_ne!(graph.read().unwrap().snapshot_for_serialization().to_json(), before);
     }
 }
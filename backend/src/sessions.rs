@@ -0,0 +1,326 @@
+//! Connected sessions: idle eviction, the connection cap and reconnect tokens.
+//!
+//! Every WebSocket connection registers here and gets a reconnect token. A
+//! session holds its `busy` lock while it handles a message and refreshes its
+//! restorable state (active document and selection) before letting go, so the
+//! idle sweep, which only evicts sessions it can lock, never drops one in the
+//! middle of a command or loses the command's effect on that state.
+//!
+//! Sessions that disconnect or are evicted leave their restorable state parked
+//! under their token for one idle TTL; connecting with `/ws?token=<token>`
+//! picks it up again. Connections over the cap are refused with a close frame.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use cad_core::topo::SelectionState;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::documents::DocumentId;
+
+pub const DEFAULT_IDLE_TTL: Duration = Duration::from_secs(30 * 60);
+pub const DEFAULT_MAX_CONNECTIONS: usize = 64;
+
+/// Close code for connections refused over the cap (RFC 6455 "Try Again Later")
+pub const CLOSE_TRY_AGAIN_LATER: u16 = 1013;
+/// Close code for sessions evicted as idle (RFC 6455 "Going Away")
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+
+/// Limits on sessions; from CAD_SESSION_IDLE_SECS and CAD_MAX_CONNECTIONS
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionConfig {
+    /// Sessions with no message for this long are evicted
+    pub idle_ttl: Duration,
+    /// Connections beyond this many are refused
+    pub max_connections: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig { idle_ttl: DEFAULT_IDLE_TTL, max_connections: DEFAULT_MAX_CONNECTIONS }
+    }
+}
+
+impl SessionConfig {
+    pub fn from_env() -> SessionConfig {
+        let var = |name: &str| std::env::var(name).ok().and_then(|s| s.parse::<u64>().ok()).filter(|&n| n > 0);
+        let defaults = SessionConfig::default();
+        SessionConfig {
+            idle_ttl: var("CAD_SESSION_IDLE_SECS").map_or(defaults.idle_ttl, Duration::from_secs),
+            max_connections: var("CAD_MAX_CONNECTIONS").map_or(defaults.max_connections, |n| n as usize),
+        }
+    }
+}
+
+/// What a reconnecting client gets back
+#[derive(Debug, Clone, Default)]
+pub struct RestorableState {
+    pub document: Option<DocumentId>,
+    pub selection: SelectionState,
+}
+
+/// One connected session as the registry sees it
+pub struct Session {
+    pub id: u64,
+    /// Token a client reconnects with to get this session's state back
+    pub token: uuid::Uuid,
+    busy: tokio::sync::Mutex<()>,
+    last_activity: Mutex<Instant>,
+    state: Mutex<RestorableState>,
+    evicted: AtomicBool,
+    eviction: Notify,
+}
+
+impl Session {
+    /// Mark the session active and hold it against eviction until the guard is dropped
+    pub async fn begin(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        let guard = self.busy.lock().await;
+        *self.last_activity.lock().unwrap() = Instant::now();
+        guard
+    }
+
+    /// Record what a reconnect should bring back; call before releasing `begin`'s guard
+    pub fn save(&self, document: Option<DocumentId>, selection: &SelectionState) {
+        *self.state.lock().unwrap() = RestorableState { document, selection: selection.clone() };
+    }
+
+    pub fn is_evicted(&self) -> bool {
+        self.evicted.load(Ordering::Acquire)
+    }
+
+    /// Resolves once the session has been evicted
+    pub async fn evicted(&self) {
+        self.eviction.notified().await
+    }
+}
+
+/// A session's place in the registry; dropping it disconnects the session,
+/// however its connection ends
+pub struct Registration {
+    registry: Arc<SessionRegistry>,
+    session: Arc<Session>,
+}
+
+impl Registration {
+    pub fn new(registry: Arc<SessionRegistry>, session: Arc<Session>) -> Registration {
+        Registration { registry, session }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.disconnect(&self.session);
+    }
+}
+
+/// Refusal of a connection over the cap
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rejected {
+    pub max_connections: usize,
+}
+
+/// Counters for the stats endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionStats {
+    pub active: usize,
+    pub max_connections: usize,
+    pub idle_ttl_secs: u64,
+    /// Disconnected or evicted sessions whose state can still be restored
+    pub parked: usize,
+    pub evicted_total: u64,
+    pub rejected_total: u64,
+    pub restored_total: u64,
+}
+
+pub struct SessionRegistry {
+    config: SessionConfig,
+    sessions: Mutex<HashMap<u64, Arc<Session>>>,
+    /// Restorable state of sessions gone, by token, with when they went
+    parked: Mutex<HashMap<uuid::Uuid, (RestorableState, Instant)>>,
+    evicted_total: AtomicU64,
+    rejected_total: AtomicU64,
+    restored_total: AtomicU64,
+}
+
+impl SessionRegistry {
+    pub fn new(config: SessionConfig) -> SessionRegistry {
+        SessionRegistry {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+            parked: Mutex::new(HashMap::new()),
+            evicted_total: AtomicU64::new(0),
+            rejected_total: AtomicU64::new(0),
+            restored_total: AtomicU64::new(0),
+        }
+    }
+
+    pub fn config(&self) -> SessionConfig {
+        self.config
+    }
+
+    /// Register session `id`, unless the cap is reached. With the token of a
+    /// session that went away within the TTL, its state comes back too.
+    pub fn connect(&self, id: u64, token: Option<uuid::Uuid>) -> Result<(Arc<Session>, Option<RestorableState>), Rejected> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= self.config.max_connections {
+            self.rejected_total.fetch_add(1, Ordering::Relaxed);
+            return Err(Rejected { max_connections: self.config.max_connections });
+        }
+        let restored = token.and_then(|token| self.parked.lock().unwrap().remove(&token)).map(|(state, _)| state);
+        if restored.is_some() {
+            self.restored_total.fetch_add(1, Ordering::Relaxed);
+        }
+        let session = Arc::new(Session {
+            id,
+            token: uuid::Uuid::new_v4(),
+            busy: tokio::sync::Mutex::new(()),
+            last_activity: Mutex::new(Instant::now()),
+            state: Mutex::new(restored.clone().unwrap_or_default()),
+            evicted: AtomicBool::new(false),
+            eviction: Notify::new(),
+        });
+        sessions.insert(id, session.clone());
+        Ok((session, restored))
+    }
+
+    /// Unregister a session whose connection ended, parking its state
+    pub fn disconnect(&self, session: &Session) {
+        if self.sessions.lock().unwrap().remove(&session.id).is_some() {
+            self.park(session, Instant::now());
+        }
+    }
+
+    fn park(&self, session: &Session, now: Instant) {
+        let state = session.state.lock().unwrap().clone();
+        self.parked.lock().unwrap().insert(session.token, (state, now));
+    }
+
+    /// Evict sessions idle for longer than the TTL as of `now`, parking their
+    /// state, and forget parked states older than the TTL. Sessions handling a
+    /// message are left alone. Returns the ids evicted.
+    pub fn evict_idle(&self, now: Instant) -> Vec<u64> {
+        let ttl = self.config.idle_ttl;
+        let idle = |session: &Session| now.saturating_duration_since(*session.last_activity.lock().unwrap()) > ttl;
+        let candidates: Vec<Arc<Session>> = self.sessions.lock().unwrap().values().filter(|s| idle(s)).cloned().collect();
+        let mut evicted = Vec::new();
+        for session in candidates {
+            let Ok(_busy) = session.busy.try_lock() else { continue };
+            // It may have taken a message between the scan and the lock
+            if !idle(&session) || self.sessions.lock().unwrap().remove(&session.id).is_none() {
+                continue;
+            }
+            self.park(&session, now);
+            session.evicted.store(true, Ordering::Release);
+            session.eviction.notify_one();
+            self.evicted_total.fetch_add(1, Ordering::Relaxed);
+            evicted.push(session.id);
+        }
+        self.parked.lock().unwrap().retain(|_, (_, since)| now.saturating_duration_since(*since) <= ttl);
+        evicted
+    }
+
+    /// Run `evict_idle` in the background, several times per TTL
+    pub fn spawn_sweeper(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let registry = self.clone();
+        let period = (registry.config.idle_ttl / 4).max(Duration::from_secs(1));
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(period);
+            loop {
+                ticks.tick().await;
+                let evicted = registry.evict_idle(Instant::now());
+                if !evicted.is_empty() {
+                    info!("Evicted {} idle session(s)", evicted.len());
+                }
+            }
+        })
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            active: self.sessions.lock().unwrap().len(),
+            max_connections: self.config.max_connections,
+            idle_ttl_secs: self.config.idle_ttl.as_secs(),
+            parked: self.parked.lock().unwrap().len(),
+            evicted_total: self.evicted_total.load(Ordering::Relaxed),
+            rejected_total: self.rejected_total.load(Ordering::Relaxed),
+            restored_total: self.restored_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cad_core::topo::naming::{TopoId, TopoRank};
+    use cad_core::topo::EntityId;
+
+    fn registry(idle_secs: u64, max_connections: usize) -> SessionRegistry {
+        SessionRegistry::new(SessionConfig { idle_ttl: Duration::from_secs(idle_secs), max_connections })
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_are_evicted_and_can_be_restored() {
+        let sessions = registry(60, 8);
+        let (idle, _) = sessions.connect(1, None).unwrap();
+        let (active, _) = sessions.connect(2, None).unwrap();
+        let document = Some(DocumentId(uuid::Uuid::new_v4()));
+        let mut selection = SelectionState::new();
+        selection.selected.insert(TopoId::new(EntityId::new(), 1, TopoRank::Face));
+        {
+            let _busy = idle.begin().await;
+            idle.save(document, &selection);
+        }
+
+        let later = Instant::now() + Duration::from_secs(30);
+        assert!(sessions.evict_idle(later).is_empty(), "Neither is idle yet");
+        *active.last_activity.lock().unwrap() = later;
+        let much_later = later + Duration::from_secs(45);
+        assert_eq!(sessions.evict_idle(much_later), vec![1]);
+        assert!(idle.is_evicted() && !active.is_evicted());
+        tokio::time::timeout(Duration::from_secs(1), idle.evicted()).await.expect("Eviction is signalled");
+
+        let stats = sessions.stats();
+        assert_eq!((stats.active, stats.parked, stats.evicted_total), (1, 1, 1));
+
+        // Reconnecting with the token brings back the document and selection, once
+        let (_, restored) = sessions.connect(3, Some(idle.token)).unwrap();
+        let restored = restored.expect("State was parked");
+        assert_eq!(restored.document, document);
+        assert_eq!(restored.selection.selected, selection.selected);
+        assert!(sessions.connect(4, Some(idle.token)).unwrap().1.is_none());
+        assert_eq!(sessions.stats().restored_total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_handling_a_message_are_not_evicted() {
+        let sessions = registry(1, 8);
+        let (session, _) = sessions.connect(1, None).unwrap();
+        let busy = session.begin().await;
+        let far = Instant::now() + Duration::from_secs(3600);
+        assert!(sessions.evict_idle(far).is_empty());
+        drop(busy);
+        assert_eq!(sessions.evict_idle(far), vec![1]);
+
+        // Parked state outlives the session by one TTL only
+        sessions.evict_idle(far + Duration::from_secs(2));
+        assert_eq!(sessions.stats().parked, 0);
+    }
+
+    #[test]
+    fn test_connections_over_the_cap_are_rejected() {
+        let sessions = registry(60, 2);
+        let (first, _) = sessions.connect(1, None).unwrap();
+        sessions.connect(2, None).unwrap();
+        assert_eq!(sessions.connect(3, None).err(), Some(Rejected { max_connections: 2 }));
+        assert_eq!(sessions.stats().rejected_total, 1);
+
+        sessions.disconnect(&first);
+        assert!(sessions.connect(4, None).is_ok(), "A slot freed up");
+        let stats = sessions.stats();
+        assert_eq!((stats.active, stats.parked), (2, 1), "The closed session's state is parked");
+    }
+}
@@ -0,0 +1,262 @@
+//! Measurement bindings
+//!
+//! After a regeneration, measurements of the evaluated model are injected into
+//! the variable context as read-only identifiers:
+//! - `@<Feature>.size_x` / `size_y` / `size_z` and `@<Feature>.volume` for every
+//!   feature with solid triangles, where `<Feature>` is the feature name with
+//!   anything but letters, digits and `_` replaced by `_` ("Extrude 1" → `Extrude_1`)
+//! - `@<name>` for each face area published with a `PublishedMeasurement`
+//!
+//! Variables referencing them can drive other features, so binding may change
+//! the model. `FeatureGraph::regenerate_bound` regenerates again while any
+//! variable's value changed, and gives up after `MAX_BINDING_PASSES`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::mesh::{mesh_bounds, mesh_volume};
+use crate::evaluator::runtime::{EvaluationResult, KernelError};
+use crate::evaluator::Runtime;
+use crate::features::dag::FeatureGraph;
+use crate::geometry::{Point3, Tessellation};
+use crate::topo::naming::TopoId;
+use crate::topo::{EntityId, IdGenerator};
+
+/// Regenerations (including the first) before bindings are reported as unsettled
+pub const MAX_BINDING_PASSES: usize = 5;
+
+/// Relative change below which a variable counts as settled
+const SETTLE_TOLERANCE: f64 = 1e-9;
+
+/// A face whose area is published as a read-only variable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishedMeasurement {
+    /// Identifier used in expressions, without the `@`
+    pub name: String,
+    pub face: TopoId,
+}
+
+/// Variables still changing when the pass limit was reached, e.g. a feature
+/// whose size depends on a measurement of itself
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnsettledBindings {
+    pub passes: usize,
+    pub variables: Vec<String>,
+}
+
+impl fmt::Display for UnsettledBindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.variables.iter().map(|name| format!("@{}", name)).collect();
+        write!(f, "Variables bound to measurements did not settle after {} regenerations: {}", self.passes, names.join(", "))
+    }
+}
+
+/// Outcome of `FeatureGraph::regenerate_bound`
+pub struct BoundRegeneration {
+    /// Result of the last regeneration
+    pub result: EvaluationResult,
+    pub passes: usize,
+    pub unsettled: Option<UnsettledBindings>,
+}
+
+/// Identifier prefix of a feature's measurements
+pub fn measurement_prefix(feature_name: &str) -> String {
+    feature_name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect()
+}
+
+/// Whether `name` can be published: a plain identifier, as after `@`
+pub fn is_valid_measurement_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn vertex(tess: &Tessellation, index: u32) -> Point3 {
+    let i = index as usize * 3;
+    Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64)
+}
+
+/// Measurements of an evaluated model, keyed by identifier (without the `@`)
+pub fn collect_measurements(graph: &FeatureGraph, result: &EvaluationResult) -> HashMap<String, f64> {
+    let tess = &result.tessellation;
+    let prefixes: HashMap<EntityId, String> = graph.nodes.values()
+        .map(|feature| (IdGenerator::new(&feature.id.to_string()).next_id(), measurement_prefix(&feature.name)))
+        .collect();
+
+    // Each feature's solid triangles, copied into a tessellation of their own
+    let mut bodies: HashMap<EntityId, Tessellation> = HashMap::new();
+    let mut face_areas: HashMap<TopoId, f64> = HashMap::new();
+    for (t, tri) in tess.indices.chunks_exact(3).enumerate() {
+        let Some(id) = tess.triangle_ids.get(t) else { continue };
+        if tess.is_reference(id) {
+            continue;
+        }
+        let [a, b, c] = [vertex(tess, tri[0]), vertex(tess, tri[1]), vertex(tess, tri[2])];
+        *face_areas.entry(*id).or_default() += (b - a).cross(&(c - a)).norm() / 2.0;
+        if prefixes.contains_key(&id.feature_id) {
+            bodies.entry(id.feature_id).or_default().add_triangle(a, b, c, *id);
+        }
+    }
+
+    let mut measurements = HashMap::new();
+    for (namespace, body) in &bodies {
+        let prefix = &prefixes[namespace];
+        if let Some(bounds) = mesh_bounds(body) {
+            let size = bounds.max - bounds.min;
+            measurements.insert(format!("{}.size_x", prefix), size.x);
+            measurements.insert(format!("{}.size_y", prefix), size.y);
+            measurements.insert(format!("{}.size_z", prefix), size.z);
+        }
+        measurements.insert(format!("{}.volume", prefix), mesh_volume(body));
+    }
+    for published in &graph.published_measurements {
+        if let Some(area) = face_areas.get(&published.face) {
+            measurements.insert(published.name.clone(), *area);
+        }
+    }
+    measurements
+}
+
+fn settled(before: Option<f64>, after: Option<f64>) -> bool {
+    match (before, after) {
+        (Some(a), Some(b)) => (a - b).abs() <= SETTLE_TOLERANCE * a.abs().max(b.abs()).max(1.0),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+impl FeatureGraph {
+    /// Publish the measurements of `result` to the variables and re-evaluate
+    /// them. Returns the names of variables whose value changed, in display
+    /// order; if any did, the model should be regenerated again.
+    pub fn bind_measurements(&mut self, result: &EvaluationResult) -> Vec<String> {
+        let measurements = collect_measurements(self, result);
+        let store = &mut self.variables;
+        let before: HashMap<EntityId, Option<f64>> = store.variables.iter().map(|(id, v)| (*id, v.cached_value)).collect();
+        store.measurements = measurements;
+        crate::variables::evaluator::evaluate_all(store);
+        store.ordered_variables().into_iter()
+            .filter(|v| !settled(before.get(&v.id).copied().flatten(), v.cached_value))
+            .map(|v| v.name.clone())
+            .collect()
+    }
+
+    /// Publish the area of `face` as `@name`, replacing an earlier publication
+    /// of the same name
+    pub fn publish_measurement(&mut self, name: &str, face: TopoId) -> Result<(), String> {
+        if !is_valid_measurement_name(name) {
+            return Err(format!("'{}' is not a valid measurement name", name));
+        }
+        if self.variables.get_by_name(name).is_some() {
+            return Err(format!("A variable named '{}' already exists", name));
+        }
+        self.published_measurements.retain(|p| p.name != name);
+        self.published_measurements.push(PublishedMeasurement { name: name.to_string(), face });
+        Ok(())
+    }
+
+    /// Regenerate and evaluate, then bind measurements and repeat while bound
+    /// variables change. Stops after `MAX_BINDING_PASSES` regenerations,
+    /// reporting the variables that were still changing.
+    pub fn regenerate_bound(&mut self, runtime: &Runtime, generator: &IdGenerator) -> Result<BoundRegeneration, KernelError> {
+        let mut passes = 0;
+        loop {
+            let program = self.regenerate();
+            let result = runtime.evaluate(&program, generator)?;
+            passes += 1;
+            let changed = self.bind_measurements(&result);
+            if changed.is_empty() {
+                return Ok(BoundRegeneration { result, passes, unsettled: None });
+            }
+            if passes >= MAX_BINDING_PASSES {
+                let unsettled = UnsettledBindings { passes, variables: changed };
+                return Ok(BoundRegeneration { result, passes, unsettled: Some(unsettled) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+    use crate::topo::registry::AnalyticGeometry;
+    use crate::units::LengthUnit;
+    use crate::variables::{Unit, Variable};
+
+    fn mm() -> Unit {
+        Unit::Length(LengthUnit::Millimeter)
+    }
+
+    /// Square sketch at `x` extruded by `distance`. Returns the extrude's id.
+    fn add_box(graph: &mut FeatureGraph, name: &str, x: f64, distance: ParameterValue) -> EntityId {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let corners = [[x, 0.0], [x + 10.0, 0.0], [x + 10.0, 10.0], [x, 10.0]];
+        for i in 0..4 {
+            sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+        }
+        let sketch_feature = Feature::new(&format!("{} sketch", name), FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new(name, FeatureType::Extrude).with_param("distance", distance);
+        extrude.dependencies.push(sketch_feature.id);
+        let id = extrude.id;
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        id
+    }
+
+    #[test]
+    fn test_variable_bound_to_box_height_drives_second_feature() {
+        let mut graph = FeatureGraph::new();
+        add_box(&mut graph, "Base", 0.0, ParameterValue::Float(8.0));
+        add_box(&mut graph, "Post", 20.0, ParameterValue::Expression("@post_height".to_string()));
+        graph.variables.add(Variable::with_expression("post_height", "@Base.size_z * 2", mm())).unwrap();
+
+        let bound = graph.regenerate_bound(&Runtime::new(), &IdGenerator::new("Bindings")).unwrap();
+        assert!(bound.unsettled.is_none());
+        assert_eq!(bound.passes, 2, "One pass to measure, one to apply");
+
+        let measurements = collect_measurements(&graph, &bound.result);
+        assert!((measurements["Base.size_z"] - 8.0).abs() < 1e-4);
+        assert!((measurements["Post.size_z"] - 16.0).abs() < 1e-4, "{:?}", measurements);
+        assert!((measurements["Base.volume"] - 800.0).abs() < 1e-2);
+        assert_eq!(graph.variables.get_by_name("post_height").unwrap().cached_value.map(|v| v.round()), Some(16.0));
+    }
+
+    #[test]
+    fn test_published_face_area() {
+        let mut graph = FeatureGraph::new();
+        add_box(&mut graph, "Plate", 0.0, ParameterValue::Float(2.0));
+        let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("Bindings")).unwrap();
+        // Top cap: the plane facing +Z at the extrude height
+        let face = result.topology_manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { origin, normal } if normal[2] > 0.99 && (origin[2] - 2.0).abs() < 1e-6))
+            .map(|e| e.id)
+            .expect("Extruded box has a top cap");
+        graph.publish_measurement("plate_area", face).unwrap();
+        assert!((collect_measurements(&graph, &result)["plate_area"] - 100.0).abs() < 1e-3);
+        assert!(graph.publish_measurement("plate.area", face).is_err());
+        assert!(graph.publish_measurement("2x", face).is_err());
+    }
+
+    #[test]
+    fn test_self_referencing_size_reports_unsettled() {
+        // The box's height flips between 10 and 20: a missing measurement falls
+        // back to the extrude's default distance of 10, which makes the height 20,
+        // which makes it 10 again
+        let mut graph = FeatureGraph::new();
+        add_box(&mut graph, "Flip", 0.0, ParameterValue::Expression("@flip_height".to_string()));
+        graph.variables.add(Variable::with_expression("flip_height", "30 - @Flip.size_z", mm())).unwrap();
+
+        let bound = graph.regenerate_bound(&Runtime::new(), &IdGenerator::new("Bindings")).unwrap();
+        let unsettled = bound.unsettled.expect("Oscillating bindings must be reported");
+        assert_eq!(unsettled.passes, MAX_BINDING_PASSES);
+        assert_eq!(unsettled.variables, vec!["flip_height".to_string()]);
+        assert_eq!(
+            unsettled.to_string(),
+            format!("Variables bound to measurements did not settle after {} regenerations: @flip_height", MAX_BINDING_PASSES)
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Measurements of model entities
+//!
+//! Reports the dimensions of a face, edge or vertex from its analytic geometry
+//! in the topology registry, e.g. a hole's diameter or a fillet's radii.
+
+use serde::Serialize;
+
+use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+use crate::topo::naming::TopoId;
+
+/// A measured dimension. Lengths are in model units, angles in radians.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MeasuredValue {
+    pub name: &'static str,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityMeasurement {
+    pub id: TopoId,
+    /// Geometry kind, as in `AnalyticGeometry::kind`
+    pub kind: &'static str,
+    pub values: Vec<MeasuredValue>,
+}
+
+/// Dimensions of `entity`: its geometry's parameters, plus diameters of round geometry
+pub fn measure_entity(entity: &KernelEntity) -> EntityMeasurement {
+    let mut values: Vec<MeasuredValue> = entity.geometry.scalar_parameters().into_iter()
+        .map(|(name, value)| MeasuredValue { name, value })
+        .collect();
+    match entity.geometry {
+        AnalyticGeometry::Cylinder { radius, .. }
+        | AnalyticGeometry::Sphere { radius, .. }
+        | AnalyticGeometry::Circle { radius, .. } => values.push(MeasuredValue { name: "diameter", value: 2.0 * radius }),
+        AnalyticGeometry::Torus { major_radius, minor_radius, .. } => {
+            values.push(MeasuredValue { name: "inner_radius", value: major_radius - minor_radius });
+            values.push(MeasuredValue { name: "outer_radius", value: major_radius + minor_radius });
+        }
+        _ => {}
+    }
+    EntityMeasurement { id: entity.id, kind: entity.geometry.kind(), values }
+}
@@ -0,0 +1,115 @@
+//! Mass properties computed directly from a triangle tessellation.
+
+use crate::geometry::{Aabb, Point3, Tessellation, Vector3};
+
+fn triangle_vertex(tess: &Tessellation, index: u32) -> Point3 {
+    let i = index as usize * 3;
+    Point3::new(
+        tess.vertices[i] as f64,
+        tess.vertices[i + 1] as f64,
+        tess.vertices[i + 2] as f64,
+    )
+}
+
+/// Enclosed volume of the triangles in a tessellation (divergence theorem).
+/// Each triangle contributes the signed volume of the tetrahedron it forms with
+/// the origin. The absolute value is returned so inward-facing winding does not
+/// flip the sign. Line and point primitives and reference geometry are ignored.
+pub fn mesh_volume(tess: &Tessellation) -> f64 {
+    let mut volume = 0.0;
+    for tri in solid_triangles(tess) {
+        let a = triangle_vertex(tess, tri[0]).coords;
+        let b = triangle_vertex(tess, tri[1]).coords;
+        let c = triangle_vertex(tess, tri[2]).coords;
+        volume += a.dot(&b.cross(&c)) / 6.0;
+    }
+    volume.abs()
+}
+
+/// Center of mass of the enclosed volume, assuming uniform density: the
+/// centroids of the origin tetrahedra `mesh_volume` sums, weighted by their
+/// signed volumes. None when nothing encloses a volume.
+pub fn mesh_center_of_mass(tess: &Tessellation) -> Option<Point3> {
+    center_of_mass(solid_triangles(tess).map(|tri| [0, 1, 2].map(|k| triangle_vertex(tess, tri[k]))))
+}
+
+/// Center of mass of the volume a closed set of triangles encloses, as
+/// `mesh_center_of_mass` computes it for a whole tessellation
+pub(crate) fn center_of_mass(triangles: impl IntoIterator<Item = [Point3; 3]>) -> Option<Point3> {
+    let mut volume = 0.0;
+    let mut moment = Vector3::zeros();
+    for [a, b, c] in triangles {
+        let tet_volume = a.coords.dot(&b.coords.cross(&c.coords)) / 6.0;
+        volume += tet_volume;
+        moment += (a.coords + b.coords + c.coords) * (tet_volume / 4.0);
+    }
+    (volume.abs() > 1e-12).then(|| Point3::from(moment / volume))
+}
+
+/// Axis-aligned bounds of the triangle vertices in a tessellation.
+/// Returns None when there are no triangles (e.g. sketch-only models).
+pub fn mesh_bounds(tess: &Tessellation) -> Option<Aabb> {
+    let mut bounds: Option<Aabb> = None;
+    for &index in solid_triangles(tess).flatten() {
+        bounds.get_or_insert_with(Aabb::empty).extend(&triangle_vertex(tess, index));
+    }
+    bounds
+}
+
+/// Triangle index triples, skipping datum planes and other reference geometry
+fn solid_triangles(tess: &Tessellation) -> impl Iterator<Item = &[u32]> {
+    tess.indices.chunks_exact(3)
+        .enumerate()
+        .filter(|(t, _)| tess.triangle_ids.get(*t).is_none_or(|id| !tess.is_reference(id)))
+        .map(|(_, tri)| tri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topo::naming::{TopoId, TopoRank};
+    use crate::topo::EntityId;
+
+    fn unit_cube() -> Tessellation {
+        let mut tess = Tessellation::new();
+        let id = TopoId::new(EntityId::new(), 0, TopoRank::Face);
+        let p = |x: f64, y: f64, z: f64| Point3::new(x, y, z);
+        let quads = [
+            [p(0., 0., 0.), p(0., 1., 0.), p(1., 1., 0.), p(1., 0., 0.)],
+            [p(0., 0., 1.), p(1., 0., 1.), p(1., 1., 1.), p(0., 1., 1.)],
+            [p(0., 0., 0.), p(1., 0., 0.), p(1., 0., 1.), p(0., 0., 1.)],
+            [p(0., 1., 0.), p(0., 1., 1.), p(1., 1., 1.), p(1., 1., 0.)],
+            [p(0., 0., 0.), p(0., 0., 1.), p(0., 1., 1.), p(0., 1., 0.)],
+            [p(1., 0., 0.), p(1., 1., 0.), p(1., 1., 1.), p(1., 0., 1.)],
+        ];
+        for q in quads {
+            tess.add_triangle(q[0], q[1], q[2], id);
+            tess.add_triangle(q[0], q[2], q[3], id);
+        }
+        tess
+    }
+
+    #[test]
+    fn test_unit_cube_volume_and_bounds() {
+        let tess = unit_cube();
+        assert!((mesh_volume(&tess) - 1.0).abs() < 1e-6);
+
+        let bounds = mesh_bounds(&tess).expect("cube has triangles");
+        assert!((bounds.max.x - bounds.min.x - 1.0).abs() < 1e-6);
+        assert!((bounds.max.z - bounds.min.z - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unit_cube_center_of_mass() {
+        let com = mesh_center_of_mass(&unit_cube()).expect("cube encloses a volume");
+        assert!((com - Point3::new(0.5, 0.5, 0.5)).norm() < 1e-6, "{:?}", com);
+    }
+
+    #[test]
+    fn test_empty_tessellation() {
+        let tess = Tessellation::new();
+        assert_eq!(mesh_volume(&tess), 0.0);
+        assert!(mesh_bounds(&tess).is_none());
+        assert!(mesh_center_of_mass(&tess).is_none());
+    }
+}
@@ -0,0 +1,19 @@
+//! Model analysis utilities.
+//!
+//! Provides evaluation-driven queries over a feature graph:
+//! - Mass properties derived from the tessellated result
+//! - Parameter sweeps (design tables) over global variables
+//! - Dimensions of individual faces and edges
+//! - Measurements published to variables after each regeneration
+//! - Wall thickness and stability checks on a body
+
+pub mod bindings;
+pub mod measure;
+pub mod mesh;
+pub mod physics;
+pub mod sweep;
+
+pub use measure::{measure_entity, EntityMeasurement, MeasuredValue};
+pub use mesh::{mesh_bounds, mesh_center_of_mass, mesh_volume};
+pub use physics::{min_wall_thickness, run_analysis, stability_check, AnalysisReport, AnalysisSpec, StabilityReport, ThinSpot};
+pub use sweep::{sweep, evaluate_sweep_row, Metric, MetricValue, SweepSpec, SweepRow, SweepReport};
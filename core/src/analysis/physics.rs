@@ -0,0 +1,346 @@
+//! Quick manufacturability and stability checks on a tessellated body.
+//!
+//! - `min_wall_thickness` casts rays inward from points sampled over the
+//!   body's faces and reports where they leave the body again too soon.
+//! - `stability_check` tests whether the body's center of mass lies above
+//!   the convex hull of the points it rests on.
+//!
+//! Both work from the mesh alone. The wall check costs one ray per sample
+//! against every triangle of the body, so its sample count is capped.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::datum::plane_axes;
+use crate::geometry::tessellation::BodyId;
+use crate::geometry::{Point3, Tessellation, Vector3};
+use crate::topo::naming::TopoId;
+
+/// Most rays `min_wall_thickness` casts, whatever the requested density
+pub const MAX_WALL_SAMPLES: usize = 20_000;
+
+/// Height above the lowest point within which the body counts as resting on the ground
+const CONTACT_TOLERANCE: f64 = 1e-3;
+
+/// Ray hits closer than this are the face the ray started on
+const RAY_EPSILON: f64 = 1e-6;
+
+/// A place where the body is thinner than the requested minimum
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThinSpot {
+    /// Midway through the wall, where it is thinnest between the two faces
+    pub position: [f64; 3],
+    pub thickness: f64,
+    /// The face the ray started on and the face it left the body through
+    pub face_pair: (TopoId, TopoId),
+}
+
+/// Whether a body stands up on its own
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StabilityReport {
+    pub stable: bool,
+    pub center_of_mass: [f64; 3],
+    /// Convex hull of the lowest points, counter-clockwise seen from above
+    pub support_polygon: Vec<[f64; 3]>,
+    /// Distance from the center of mass (seen from above) to the edge of the
+    /// support polygon; negative when it lies outside
+    pub margin: f64,
+}
+
+/// A check for `run_analysis`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AnalysisSpec {
+    /// Walls of `body` thinner than `threshold` (mm)
+    WallThickness {
+        body: BodyId,
+        threshold: f64,
+        /// Rays per mm² of surface
+        sample_density: f64,
+        /// Upper bound on the number of rays (at most `MAX_WALL_SAMPLES`)
+        max_samples: usize,
+    },
+    /// Whether `body` stays upright resting on its lowest point along `up_axis`
+    Stability { body: BodyId, up_axis: [f64; 3] },
+}
+
+/// Result of `run_analysis`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AnalysisReport {
+    WallThickness { body: BodyId, thin_spots: Vec<ThinSpot> },
+    Stability { body: BodyId, report: StabilityReport },
+}
+
+/// Run one check against an evaluated model's tessellation
+pub fn run_analysis(tess: &Tessellation, spec: &AnalysisSpec) -> Result<AnalysisReport, String> {
+    match spec {
+        AnalysisSpec::WallThickness { body, threshold, sample_density, max_samples } => {
+            let thin_spots = min_wall_thickness(tess, body, *sample_density, *threshold, *max_samples)?;
+            Ok(AnalysisReport::WallThickness { body: *body, thin_spots })
+        }
+        AnalysisSpec::Stability { body, up_axis } => {
+            let report = stability_check(tess, body, Vector3::from(*up_axis))?;
+            Ok(AnalysisReport::Stability { body: *body, report })
+        }
+    }
+}
+
+/// The body's triangles with the face each belongs to
+fn body_triangles(tess: &Tessellation, body: &BodyId) -> Result<Vec<(TopoId, [Point3; 3])>, String> {
+    let faces = &tess.bodies.iter().find(|b| b.id == *body)
+        .ok_or_else(|| "Body not found in the model".to_string())?
+        .faces;
+    let vertex = |index: u32| {
+        let i = index as usize * 3;
+        Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64)
+    };
+    Ok(tess.indices.chunks_exact(3)
+        .zip(&tess.triangle_ids)
+        .filter(|(_, face)| faces.contains(face))
+        .map(|(tri, face)| (*face, [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])]))
+        .collect())
+}
+
+/// Distance along `direction` at which a ray from `origin` meets the
+/// triangle (Möller–Trumbore), if it does
+fn ray_triangle(origin: &Point3, direction: &Vector3, [a, b, c]: &[Point3; 3]) -> Option<f64> {
+    let (ab, ac) = (b - a, c - a);
+    let p = direction.cross(&ac);
+    let det = ab.dot(&p);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let to_origin = origin - a;
+    let u = to_origin.dot(&p) / det;
+    let q = to_origin.cross(&ab);
+    let v = direction.dot(&q) / det;
+    if u < 0.0 || v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(ac.dot(&q) / det)
+}
+
+/// Regions of `body` thinner than `threshold`: a ray is cast inward from
+/// about `sample_density` points per mm² of surface (no more than
+/// `max_samples`) to where it leaves the body. One spot is returned per pair
+/// of faces, the thinnest, thinnest pair first.
+pub fn min_wall_thickness(
+    tess: &Tessellation,
+    body: &BodyId,
+    sample_density: f64,
+    threshold: f64,
+    max_samples: usize,
+) -> Result<Vec<ThinSpot>, String> {
+    let triangles = body_triangles(tess, body)?;
+    let area = |[a, b, c]: &[Point3; 3]| (b - a).cross(&(c - a)).norm() / 2.0;
+    let total_area: f64 = triangles.iter().map(|(_, t)| area(t)).sum();
+    let max_samples = max_samples.min(MAX_WALL_SAMPLES) as f64;
+    let density = sample_density.max(0.0).min(max_samples / total_area.max(f64::EPSILON));
+
+    let mut thinnest: HashMap<(TopoId, TopoId), ThinSpot> = HashMap::new();
+    // Fractional samples carried over, so small triangles still get their share
+    let mut carried = 0.0;
+    for (face, triangle) in &triangles {
+        carried += area(triangle) * density;
+        let count = carried.floor();
+        carried -= count;
+        let [a, b, c] = triangle;
+        let Some(inward) = (c - a).cross(&(b - a)).try_normalize(1e-12) else { continue };
+        for k in 0..count as usize {
+            // Spread over the triangle: stratified in one direction, golden-ratio steps in the other
+            let s = ((k as f64 + 0.5) / count).sqrt();
+            let t = (k as f64 * 0.618_033_988_75).fract();
+            let origin = Point3::from(a.coords * (1.0 - s) + b.coords * (s * (1.0 - t)) + c.coords * (s * t));
+            // The nearest face seen from inside the body
+            let exit = triangles.iter()
+                .filter(|(_, other)| (other[1] - other[0]).cross(&(other[2] - other[0])).dot(&inward) > 0.0)
+                .filter_map(|(other_face, other)| ray_triangle(&origin, &inward, other).map(|d| (d, *other_face)))
+                .filter(|(d, _)| *d > RAY_EPSILON)
+                .min_by(|x, y| x.0.total_cmp(&y.0));
+            let Some((thickness, exit_face)) = exit else { continue };
+            if thickness >= threshold {
+                continue;
+            }
+            let middle = origin + inward * (thickness / 2.0);
+            let spot = ThinSpot { position: [middle.x, middle.y, middle.z], thickness, face_pair: (*face, exit_face) };
+            thinnest.entry(spot.face_pair)
+                .and_modify(|s| if thickness < s.thickness { *s = spot.clone() })
+                .or_insert(spot);
+        }
+    }
+    let mut spots: Vec<ThinSpot> = thinnest.into_values().collect();
+    spots.sort_by(|a, b| a.thickness.total_cmp(&b.thickness));
+    Ok(spots)
+}
+
+/// Convex hull (Andrew's monotone chain), counter-clockwise
+fn convex_hull(mut points: Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    points.dedup_by(|a, b| (a[0] - b[0]).hypot(a[1] - b[1]) < CONTACT_TOLERANCE);
+    if points.len() < 3 {
+        return points;
+    }
+    let turn = |o: [f64; 2], a: [f64; 2], b: [f64; 2]| (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0]);
+    let mut hull: Vec<[f64; 2]> = Vec::with_capacity(points.len() * 2);
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2 && turn(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point starts the other chain
+        hull.pop();
+    }
+    hull
+}
+
+/// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let (ab, ap) = ([b[0] - a[0], b[1] - a[1]], [p[0] - a[0], p[1] - a[1]]);
+    let length_squared = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length_squared > 0.0 { ((ap[0] * ab[0] + ap[1] * ab[1]) / length_squared).clamp(0.0, 1.0) } else { 0.0 };
+    (ap[0] - ab[0] * t).hypot(ap[1] - ab[1] * t)
+}
+
+/// Whether `body`, resting on its lowest point along `up_axis`, keeps its
+/// center of mass (uniform density) over the convex hull of the points it
+/// touches the ground with. Resting on a point or an edge is unstable.
+pub fn stability_check(tess: &Tessellation, body: &BodyId, up_axis: Vector3) -> Result<StabilityReport, String> {
+    let up = up_axis.try_normalize(1e-12).ok_or_else(|| "Up axis must be non-zero".to_string())?;
+    let triangles = body_triangles(tess, body)?;
+
+    let center = super::mesh::center_of_mass(triangles.iter().map(|(_, t)| *t))
+        .ok_or_else(|| "Body encloses no volume".to_string())?;
+
+    let points = triangles.iter().flat_map(|(_, t)| t.iter());
+    let lowest = points.clone().map(|p| p.coords.dot(&up)).fold(f64::INFINITY, f64::min);
+    let (x_axis, y_axis) = plane_axes(&up);
+    let to_2d = |p: &Point3| [p.coords.dot(&x_axis), p.coords.dot(&y_axis)];
+    let contact: Vec<[f64; 2]> = points
+        .filter(|p| p.coords.dot(&up) - lowest < CONTACT_TOLERANCE)
+        .map(to_2d)
+        .collect();
+    let hull = convex_hull(contact);
+
+    let com = to_2d(&center);
+    let edge_distance = (0..hull.len())
+        .map(|i| segment_distance(com, hull[i], hull[(i + 1) % hull.len()]))
+        .fold(f64::INFINITY, f64::min);
+    let inside = hull.len() >= 3 && crate::geometry::utils_2d::point_in_polygon(com, &hull);
+    let margin = if inside { edge_distance } else { -edge_distance };
+    let support_polygon = hull.iter()
+        .map(|p| {
+            let p = x_axis * p[0] + y_axis * p[1] + up * lowest;
+            [p.x, p.y, p.z]
+        })
+        .collect();
+    Ok(StabilityReport {
+        stable: margin > 0.0,
+        center_of_mass: [center.x, center.y, center.z],
+        support_polygon,
+        margin,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topo::naming::TopoRank;
+    use crate::topo::EntityId;
+
+    /// Hexahedron from its bottom corners and top corners, both counter-clockwise
+    /// seen from above, one TopoId per face
+    fn add_block(tess: &mut Tessellation, feature: EntityId, bottom: [Point3; 4], top: [Point3; 4]) {
+        let faces = [
+            [bottom[0], bottom[3], bottom[2], bottom[1]],
+            [top[0], top[1], top[2], top[3]],
+            [bottom[0], bottom[1], top[1], top[0]],
+            [bottom[1], bottom[2], top[2], top[1]],
+            [bottom[2], bottom[3], top[3], top[2]],
+            [bottom[3], bottom[0], top[0], top[3]],
+        ];
+        for q in faces {
+            let id = TopoId::new(feature, tess.triangle_ids.len() as u64, TopoRank::Face);
+            tess.add_triangle(q[0], q[1], q[2], id);
+            tess.add_triangle(q[0], q[2], q[3], id);
+        }
+    }
+
+    fn add_box(tess: &mut Tessellation, feature: EntityId, min: [f64; 3], max: [f64; 3]) {
+        let corners = |z: f64| [
+            Point3::new(min[0], min[1], z),
+            Point3::new(max[0], min[1], z),
+            Point3::new(max[0], max[1], z),
+            Point3::new(min[0], max[1], z),
+        ];
+        add_block(tess, feature, corners(min[2]), corners(max[2]));
+    }
+
+    fn single_body(tess: &mut Tessellation, feature: EntityId) -> BodyId {
+        let body = TopoId::new(feature, 0, TopoRank::Solid);
+        tess.add_body_since(body, 0);
+        body
+    }
+
+    #[test]
+    fn test_thin_wall_region_is_flagged() {
+        // 20 x 20 x 10 open box with 1mm walls, except a 0.3mm stretch of the +X wall
+        let feature = EntityId::new();
+        let mut tess = Tessellation::new();
+        add_box(&mut tess, feature, [0.0, 0.0, 0.0], [20.0, 20.0, 1.0]);
+        add_box(&mut tess, feature, [0.0, 0.0, 1.0], [20.0, 1.0, 10.0]);
+        add_box(&mut tess, feature, [0.0, 19.0, 1.0], [20.0, 20.0, 10.0]);
+        add_box(&mut tess, feature, [0.0, 1.0, 1.0], [1.0, 19.0, 10.0]);
+        add_box(&mut tess, feature, [19.0, 1.0, 1.0], [20.0, 8.0, 10.0]);
+        add_box(&mut tess, feature, [19.7, 8.0, 1.0], [20.0, 12.0, 10.0]);
+        add_box(&mut tess, feature, [19.0, 12.0, 1.0], [20.0, 19.0, 10.0]);
+        let body = single_body(&mut tess, feature);
+
+        let spots = min_wall_thickness(&tess, &body, 2.0, 0.5, 5000).unwrap();
+        assert!(!spots.is_empty(), "The thin stretch is found");
+        for spot in &spots {
+            assert!((spot.thickness - 0.3).abs() < 1e-3, "{:?}", spot);
+            let [x, y, z] = spot.position;
+            assert!((x - 19.85).abs() < 1e-3 && (8.0..=12.0).contains(&y) && (1.0..=10.0).contains(&z), "{:?}", spot);
+        }
+        assert!(spots.iter().all(|s| s.face_pair.0 != s.face_pair.1));
+
+        assert!(min_wall_thickness(&tess, &body, 2.0, 0.2, 5000).unwrap().is_empty(), "Nothing is thinner than 0.2mm");
+        assert!(min_wall_thickness(&tess, &TopoId::new(EntityId::new(), 0, TopoRank::Solid), 2.0, 0.5, 5000).is_err());
+    }
+
+    #[test]
+    fn test_leaning_column_on_small_base_is_unstable() {
+        let square = |x: f64, z: f64| [
+            Point3::new(x, 0.0, z),
+            Point3::new(x + 1.0, 0.0, z),
+            Point3::new(x + 1.0, 1.0, z),
+            Point3::new(x, 1.0, z),
+        ];
+        let feature = EntityId::new();
+        let mut tess = Tessellation::new();
+        // 1 x 1 base, 40 tall, leaning 20 along X by the top
+        add_block(&mut tess, feature, square(0.0, 0.0), square(20.0, 40.0));
+        let body = single_body(&mut tess, feature);
+        let report = stability_check(&tess, &body, Vector3::z()).unwrap();
+        assert!(!report.stable, "{:?}", report);
+        assert!(report.margin < -5.0);
+        assert!((report.center_of_mass[0] - 10.5).abs() < 1e-6 && (report.center_of_mass[2] - 20.0).abs() < 1e-6);
+        assert_eq!(report.support_polygon.len(), 4);
+
+        let feature = EntityId::new();
+        let mut tess = Tessellation::new();
+        add_block(&mut tess, feature, square(0.0, 0.0), square(0.0, 40.0));
+        let body = single_body(&mut tess, feature);
+        let report = stability_check(&tess, &body, Vector3::z()).unwrap();
+        assert!(report.stable && (report.margin - 0.5).abs() < 1e-6, "{:?}", report);
+
+        // Lying on its side, it rests on a long face
+        let report = stability_check(&tess, &body, Vector3::x()).unwrap();
+        assert!(report.stable);
+    }
+}
@@ -0,0 +1,256 @@
+//! Parameter sweeps (design tables).
+//!
+//! A sweep re-evaluates the same feature graph once per row of variable
+//! overrides and collects the requested metrics for each row. Each row works
+//! on its own clone of the graph, so the caller's model is never modified and
+//! a failing row does not affect the others.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::evaluator::Runtime;
+use crate::features::dag::FeatureGraph;
+use crate::topo::IdGenerator;
+use super::mesh::{mesh_bounds, mesh_volume};
+
+/// A quantity to measure for each sweep row
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Metric {
+    /// Enclosed volume of the tessellated result (mm³)
+    Volume,
+    /// Bounding box dimensions [dx, dy, dz] (mm)
+    BoundingBox,
+    /// Volume multiplied by a density (mass units per mm³)
+    Mass { density: f64 },
+    /// Whether the row regenerated without a kernel error or a failed feature
+    RegenSuccess,
+}
+
+/// Measured value of a single metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum MetricValue {
+    Volume(f64),
+    BoundingBox([f64; 3]),
+    Mass(f64),
+    RegenSuccess(bool),
+}
+
+/// Input for a sweep: one map of variable overrides per row
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SweepSpec {
+    /// Variable name -> value (in the variable's own unit), one map per row
+    pub variable_values: Vec<HashMap<String, f64>>,
+    /// Metrics to collect for every row
+    pub metrics: Vec<Metric>,
+}
+
+/// Result of evaluating one row of a sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRow {
+    /// Index of the row in the spec
+    pub index: usize,
+    /// The variable overrides applied for this row
+    pub values: HashMap<String, f64>,
+    /// Collected metrics, in the order requested by the spec
+    pub metrics: Vec<MetricValue>,
+    /// Error message if the row could not be evaluated
+    pub error: Option<String>,
+}
+
+/// Result of a full sweep
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub rows: Vec<SweepRow>,
+}
+
+impl SweepReport {
+    /// Number of rows that evaluated without error
+    pub fn success_count(&self) -> usize {
+        self.rows.iter().filter(|r| r.error.is_none()).count()
+    }
+}
+
+/// Evaluate every row of the spec against clones of the graph.
+pub fn sweep(graph: &FeatureGraph, runtime: &Runtime, spec: &SweepSpec) -> SweepReport {
+    let rows = spec.variable_values.iter()
+        .enumerate()
+        .map(|(index, values)| evaluate_sweep_row(graph, runtime, index, values, &spec.metrics))
+        .collect();
+    SweepReport { rows }
+}
+
+/// Evaluate a single sweep row. Exposed so callers can report progress between rows.
+pub fn evaluate_sweep_row(
+    graph: &FeatureGraph,
+    runtime: &Runtime,
+    index: usize,
+    values: &HashMap<String, f64>,
+    metrics: &[Metric],
+) -> SweepRow {
+    let mut row = SweepRow {
+        index,
+        values: values.clone(),
+        metrics: Vec::new(),
+        error: None,
+    };
+
+    let mut working = graph.clone();
+
+    // Apply overrides in a stable order so error messages are deterministic
+    let mut names: Vec<&String> = values.keys().collect();
+    names.sort();
+    for name in names {
+        let var_id = match working.variables.get_by_name(name) {
+            Some(var) => var.id,
+            None => {
+                row.error = Some(format!("Unknown variable: @{}", name));
+                row.metrics = regen_failed_metrics(metrics);
+                return row;
+            }
+        };
+        if let Err(e) = working.variables.update_expression(var_id, &values[name].to_string()) {
+            row.error = Some(e);
+            row.metrics = regen_failed_metrics(metrics);
+            return row;
+        }
+    }
+    crate::variables::evaluator::evaluate_all(&mut working.variables);
+
+    let program = working.regenerate();
+    let generator = IdGenerator::new("Sweep");
+    let result = match runtime.evaluate(&program, &generator) {
+        Ok(result) => result,
+        Err(e) => {
+            row.error = Some(e.to_string());
+            row.metrics = regen_failed_metrics(metrics);
+            return row;
+        }
+    };
+    // Failed features are isolated rather than failing the evaluation
+    if !result.feature_errors.is_empty() {
+        let messages: Vec<String> = result.feature_errors.iter().map(|error| {
+            let name = working.nodes.values()
+                .find(|f| f.id.to_string() == error.feature_id)
+                .map_or(error.feature_id.as_str(), |f| f.name.as_str());
+            format!("{}: {}", name, error.message)
+        }).collect();
+        row.error = Some(messages.join("; "));
+        row.metrics = regen_failed_metrics(metrics);
+        return row;
+    }
+
+    let tess = &result.tessellation;
+    row.metrics = metrics.iter().map(|metric| match metric {
+        Metric::Volume => MetricValue::Volume(mesh_volume(tess)),
+        Metric::BoundingBox => {
+            let size = mesh_bounds(tess)
+                .map(|b| [b.max.x - b.min.x, b.max.y - b.min.y, b.max.z - b.min.z])
+                .unwrap_or([0.0; 3]);
+            MetricValue::BoundingBox(size)
+        }
+        Metric::Mass { density } => MetricValue::Mass(mesh_volume(tess) * density),
+        Metric::RegenSuccess => MetricValue::RegenSuccess(true),
+    }).collect();
+
+    row
+}
+
+/// Only the regen-success metric is meaningful for a row that failed to evaluate
+fn regen_failed_metrics(metrics: &[Metric]) -> Vec<MetricValue> {
+    metrics.iter()
+        .filter(|m| matches!(m, Metric::RegenSuccess))
+        .map(|_| MetricValue::RegenSuccess(false))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+    use crate::units::LengthUnit;
+    use crate::variables::{Unit, Variable};
+
+    fn extruded_square(height_expr: &str) -> FeatureGraph {
+        let mut graph = FeatureGraph::new();
+        graph.variables.add(Variable::new("height", 10.0, Unit::Length(LengthUnit::Millimeter))).unwrap();
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [10.0, 0.0], end: [10.0, 10.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [10.0, 10.0], end: [0.0, 10.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [0.0, 10.0], end: [0.0, 0.0] });
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let sketch_id = sketch_feature.id;
+
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Expression(height_expr.to_string()));
+        extrude.dependencies.push(sketch_id);
+
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        graph
+    }
+
+    fn row_values(height: f64) -> HashMap<String, f64> {
+        HashMap::from([("height".to_string(), height)])
+    }
+
+    #[test]
+    fn test_extrude_height_sweep_volumes_increase() {
+        let graph = extruded_square("@height");
+        let spec = SweepSpec {
+            variable_values: vec![row_values(5.0), row_values(10.0), row_values(20.0)],
+            metrics: vec![Metric::Volume, Metric::RegenSuccess],
+        };
+
+        let report = sweep(&graph, &Runtime::new(), &spec);
+        assert_eq!(report.rows.len(), 3);
+        assert_eq!(report.success_count(), 3);
+
+        let volumes: Vec<f64> = report.rows.iter().map(|row| match row.metrics[0] {
+            MetricValue::Volume(v) => v,
+            ref other => panic!("Expected volume, got {:?}", other),
+        }).collect();
+        assert!(volumes[0] < volumes[1] && volumes[1] < volumes[2], "Volumes not increasing: {:?}", volumes);
+        assert!((volumes[1] - 1000.0).abs() < 1.0, "10x10x10 extrude should be ~1000mm³, got {}", volumes[1]);
+
+        // Source graph is untouched
+        assert_eq!(graph.variables.get_by_name("height").unwrap().expression, "10");
+    }
+
+    #[test]
+    fn test_unknown_variable_records_error() {
+        let graph = extruded_square("@height");
+        let spec = SweepSpec {
+            variable_values: vec![HashMap::from([("width".to_string(), 3.0)]), row_values(4.0)],
+            metrics: vec![Metric::RegenSuccess],
+        };
+
+        let report = sweep(&graph, &Runtime::new(), &spec);
+        assert!(report.rows[0].error.as_deref().unwrap().contains("width"));
+        assert_eq!(report.rows[0].metrics, vec![MetricValue::RegenSuccess(false)]);
+        assert!(report.rows[1].error.is_none());
+    }
+
+    #[test]
+    fn test_failed_feature_fails_the_row() {
+        let mut graph = FeatureGraph::new();
+        graph.variables.add(Variable::new("height", 10.0, Unit::Length(LengthUnit::Millimeter))).unwrap();
+        graph.add_node(Feature::new("Box1", FeatureType::Box).with_param("width", ParameterValue::Expression("@height".to_string())));
+        let spec = SweepSpec {
+            variable_values: vec![row_values(0.0), row_values(4.0)],
+            metrics: vec![Metric::Volume, Metric::RegenSuccess],
+        };
+
+        let report = sweep(&graph, &Runtime::new(), &spec);
+        let error = report.rows[0].error.as_deref().expect("A flat box fails");
+        assert!(error.starts_with("Box1: "), "{}", error);
+        assert_eq!(report.rows[0].metrics, vec![MetricValue::RegenSuccess(false)]);
+        assert_eq!(report.rows[1].metrics[1], MetricValue::RegenSuccess(true));
+        assert_eq!(report.success_count(), 1);
+    }
+}
@@ -0,0 +1,258 @@
+//! Document comparison
+//!
+//! Diffs two saved documents feature by feature and variable by variable, for
+//! reviewing model changes and catching unintended ones in CI. Entities are
+//! matched by id, so a rename shows up as a rename rather than a removal plus
+//! an addition.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::features::dag::FeatureGraph;
+use crate::features::types::ParameterValue;
+use crate::topo::EntityId;
+
+/// A document is its feature graph (features, variables, rollback state)
+pub type Document = FeatureGraph;
+
+/// Relative tolerance below which two floats compare equal
+pub const FLOAT_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum FeatureChange {
+    Added { id: EntityId, name: String, feature_type: String },
+    Removed { id: EntityId, name: String, feature_type: String },
+    Renamed { id: EntityId, from: String, to: String },
+    /// A parameter set, cleared or changed; `from`/`to` are None when absent
+    ParameterChanged {
+        id: EntityId,
+        name: String,
+        parameter: String,
+        from: Option<Box<ParameterValue>>,
+        to: Option<Box<ParameterValue>>,
+    },
+    SuppressionChanged { id: EntityId, name: String, suppressed: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum VariableChange {
+    Added { id: EntityId, name: String, expression: String, unit: String },
+    Removed { id: EntityId, name: String, expression: String, unit: String },
+    Renamed { id: EntityId, from: String, to: String },
+    ExpressionChanged { id: EntityId, name: String, from: String, to: String },
+    UnitChanged { id: EntityId, name: String, from: String, to: String },
+}
+
+/// Differences between two documents, in document order
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DocumentDiff {
+    pub features: Vec<FeatureChange>,
+    pub variables: Vec<VariableChange>,
+}
+
+impl DocumentDiff {
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty() && self.variables.is_empty()
+    }
+}
+
+/// Compare `a` (before) with `b` (after)
+pub fn diff(a: &Document, b: &Document) -> DocumentDiff {
+    DocumentDiff { features: diff_features(a, b), variables: diff_variables(a, b) }
+}
+
+/// Feature ids in evaluation order, then any features missing from the cached order
+fn feature_order(graph: &FeatureGraph) -> Vec<EntityId> {
+    let mut ids: Vec<EntityId> = graph.sort_order.iter().copied()
+        .filter(|id| graph.nodes.contains_key(id))
+        .collect();
+    let listed: HashSet<EntityId> = ids.iter().copied().collect();
+    let mut rest: Vec<EntityId> = graph.nodes.keys().copied().filter(|id| !listed.contains(id)).collect();
+    rest.sort();
+    ids.extend(rest);
+    ids
+}
+
+fn diff_features(a: &Document, b: &Document) -> Vec<FeatureChange> {
+    let mut changes = Vec::new();
+    for id in feature_order(a) {
+        if !b.nodes.contains_key(&id) {
+            let old = &a.nodes[&id];
+            changes.push(FeatureChange::Removed { id, name: old.name.clone(), feature_type: format!("{:?}", old.feature_type) });
+        }
+    }
+    for id in feature_order(b) {
+        let new = &b.nodes[&id];
+        let Some(old) = a.nodes.get(&id) else {
+            changes.push(FeatureChange::Added { id, name: new.name.clone(), feature_type: format!("{:?}", new.feature_type) });
+            continue;
+        };
+        if old.name != new.name {
+            changes.push(FeatureChange::Renamed { id, from: old.name.clone(), to: new.name.clone() });
+        }
+        let mut parameters: Vec<&String> = old.parameters.keys().chain(new.parameters.keys()).collect();
+        parameters.sort();
+        parameters.dedup();
+        for parameter in parameters {
+            let (from, to) = (old.parameters.get(parameter), new.parameters.get(parameter));
+            let same = match (from, to) {
+                (Some(x), Some(y)) => values_match(x, y),
+                (None, None) => true,
+                _ => false,
+            };
+            if !same {
+                changes.push(FeatureChange::ParameterChanged {
+                    id,
+                    name: new.name.clone(),
+                    parameter: parameter.clone(),
+                    from: from.cloned().map(Box::new),
+                    to: to.cloned().map(Box::new),
+                });
+            }
+        }
+        if old.suppressed != new.suppressed {
+            changes.push(FeatureChange::SuppressionChanged { id, name: new.name.clone(), suppressed: new.suppressed });
+        }
+    }
+    changes
+}
+
+/// Variable ids in display order, then any missing from it
+fn variable_order(graph: &FeatureGraph) -> Vec<EntityId> {
+    let store = &graph.variables;
+    let mut ids: Vec<EntityId> = store.order.iter().copied()
+        .filter(|id| store.variables.contains_key(id))
+        .collect();
+    let listed: HashSet<EntityId> = ids.iter().copied().collect();
+    let mut rest: Vec<EntityId> = store.variables.keys().copied().filter(|id| !listed.contains(id)).collect();
+    rest.sort();
+    ids.extend(rest);
+    ids
+}
+
+fn diff_variables(a: &Document, b: &Document) -> Vec<VariableChange> {
+    let (before, after) = (&a.variables.variables, &b.variables.variables);
+    let mut changes = Vec::new();
+    for id in variable_order(a) {
+        if !after.contains_key(&id) {
+            let old = &before[&id];
+            changes.push(VariableChange::Removed { id, name: old.name.clone(), expression: old.expression.clone(), unit: old.unit.to_string() });
+        }
+    }
+    for id in variable_order(b) {
+        let new = &after[&id];
+        let Some(old) = before.get(&id) else {
+            changes.push(VariableChange::Added { id, name: new.name.clone(), expression: new.expression.clone(), unit: new.unit.to_string() });
+            continue;
+        };
+        if old.name != new.name {
+            changes.push(VariableChange::Renamed { id, from: old.name.clone(), to: new.name.clone() });
+        }
+        if old.expression.trim() != new.expression.trim() {
+            changes.push(VariableChange::ExpressionChanged { id, name: new.name.clone(), from: old.expression.clone(), to: new.expression.clone() });
+        }
+        if old.unit != new.unit {
+            changes.push(VariableChange::UnitChanged { id, name: new.name.clone(), from: old.unit.to_string(), to: new.unit.to_string() });
+        }
+    }
+    changes
+}
+
+/// Structural equality with floats compared to `FLOAT_TOLERANCE`, so values
+/// that only differ by round-off (e.g. re-solved sketches) don't show up
+fn values_match(a: &ParameterValue, b: &ParameterValue) -> bool {
+    match (serde_json::to_value(a), serde_json::to_value(b)) {
+        (Ok(x), Ok(y)) => json_match(&x, &y),
+        _ => a == b,
+    }
+}
+
+fn json_match(a: &serde_json::Value, b: &serde_json::Value) -> bool {
+    use serde_json::Value;
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() <= FLOAT_TOLERANCE * x.abs().max(y.abs()).max(1.0),
+            _ => x == y,
+        },
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_match(x, y)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(key, x)| y.get(key).is_some_and(|y| json_match(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Short human-readable form of a parameter value
+fn describe(value: &Option<Box<ParameterValue>>) -> String {
+    match value.as_deref() {
+        None => "(unset)".to_string(),
+        Some(ParameterValue::Float(v)) => v.to_string(),
+        Some(ParameterValue::String(s)) => format!("\"{}\"", s),
+        Some(ParameterValue::Bool(b)) => b.to_string(),
+        Some(ParameterValue::Expression(e)) => format!("= {}", e),
+        Some(ParameterValue::Sketch(sketch)) => {
+            format!("<sketch: {} entities, {} constraints>", sketch.entities.len(), sketch.constraints.len())
+        }
+        Some(ParameterValue::ProfileRegions(regions)) => format!("<{} regions>", regions.len()),
+        Some(ParameterValue::RegionSpecs(specs)) => format!("<{} region specs>", specs.len()),
+        Some(other) => serde_json::to_string(other).unwrap_or_default(),
+    }
+}
+
+fn with_unit(expression: &str, unit: &str) -> String {
+    if unit.is_empty() { expression.to_string() } else { format!("{} {}", expression, unit) }
+}
+
+impl fmt::Display for FeatureChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeatureChange::Added { name, feature_type, .. } => write!(f, "+ {} ({})", name, feature_type),
+            FeatureChange::Removed { name, feature_type, .. } => write!(f, "- {} ({})", name, feature_type),
+            FeatureChange::Renamed { from, to, .. } => write!(f, "~ {}: renamed to {}", from, to),
+            FeatureChange::ParameterChanged { name, parameter, from, to, .. } => {
+                write!(f, "~ {}: {} {} -> {}", name, parameter, describe(from), describe(to))
+            }
+            FeatureChange::SuppressionChanged { name, suppressed, .. } => {
+                write!(f, "~ {}: {}", name, if *suppressed { "suppressed" } else { "unsuppressed" })
+            }
+        }
+    }
+}
+
+impl fmt::Display for VariableChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableChange::Added { name, expression, unit, .. } => write!(f, "+ @{} = {}", name, with_unit(expression, unit)),
+            VariableChange::Removed { name, expression, unit, .. } => write!(f, "- @{} = {}", name, with_unit(expression, unit)),
+            VariableChange::Renamed { from, to, .. } => write!(f, "~ @{}: renamed to @{}", from, to),
+            VariableChange::ExpressionChanged { name, from, to, .. } => write!(f, "~ @{}: {} -> {}", name, from, to),
+            VariableChange::UnitChanged { name, from, to, .. } => write!(f, "~ @{}: unit {} -> {}", name, from, to),
+        }
+    }
+}
+
+/// Text report, one change per line, grouped into features and variables
+impl fmt::Display for DocumentDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        if !self.features.is_empty() {
+            writeln!(f, "Features:")?;
+            for change in &self.features {
+                writeln!(f, "  {}", change)?;
+            }
+        }
+        if !self.variables.is_empty() {
+            writeln!(f, "Variables:")?;
+            for change in &self.variables {
+                writeln!(f, "  {}", change)?;
+            }
+        }
+        Ok(())
+    }
+}
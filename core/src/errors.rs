@@ -0,0 +1,235 @@
+//! Error codes shared by the core and the backend
+//!
+//! Every error a client sees in ERROR_UPDATE is a `CadError`: a code from the
+//! closed `ErrorCode` registry, a human-readable message, a severity and a
+//! `details` object. Each code fixes the keys of its details (see
+//! `ErrorCode::detail_keys`); a key whose value isn't known is null, so
+//! clients can rely on the shape of every code.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Every error code a client can receive. Details are listed per code;
+/// ids are EntityId/TopoId strings as serialized elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// A command could not be parsed or refers to something that doesn't
+    /// exist. `command`: the offending command text, truncated
+    CommandError,
+    /// A feature could not be built or edited. `feature_id`
+    FeatureError,
+    /// Regeneration failed or did not settle. `feature_id`: the feature evaluation
+    /// stopped in; null when no one feature is to blame, as for bindings that don't settle
+    RegenFailed,
+    /// A sketch's constraints could not be satisfied. `sketch_id`,
+    /// `constraint_indices`: the unsatisfied constraints
+    SketchSolveFailed,
+    /// A profile is not closed. `sketch_id`, `entity_ids`: curves with a free end
+    OpenProfile,
+    /// The kernel was given geometry it can't use. `feature_id`
+    KernelInvalidGeometry,
+    /// A kernel operation (extrude, Boolean, ...) failed. `feature_id`
+    KernelOperationFailed,
+    /// A solid could not be meshed. `feature_id`
+    KernelTessellationFailed,
+    /// The kernel has no such operation. `feature_id`
+    KernelNotImplemented,
+    /// A reference no longer resolves. `feature_id`: the referencing
+    /// feature, `topo_ids`: the lost references
+    ZombieReference,
+    /// A parameter value is out of range. `feature_id`, `parameter`: its name
+    ValidationFailed,
+    /// Input is too large or took too long. `limit`: which one, `max`: its value
+    LimitExceeded,
+    /// A client-proposed feature id was already taken. `ids`: the proposed id
+    IdCollision,
+    /// A body name no longer names one body. `name`
+    BodyNameLost,
+    /// A file could not be imported. `format`
+    ImportFailed,
+    /// The model could not be exported. `format`
+    ExportFailed,
+    /// Geometry could not be projected into a sketch. `sketch_id`
+    ProjectionFailed,
+    /// The document could not be written. `path`
+    SaveFailed,
+    /// A feature can't move where asked. `feature_id`
+    ReorderFailed,
+    /// A feature could not be inserted. `feature_type`
+    InsertFailed,
+}
+
+impl ErrorCode {
+    /// Every code, in declaration order
+    pub const ALL: [ErrorCode; 20] = [
+        ErrorCode::CommandError,
+        ErrorCode::FeatureError,
+        ErrorCode::RegenFailed,
+        ErrorCode::SketchSolveFailed,
+        ErrorCode::OpenProfile,
+        ErrorCode::KernelInvalidGeometry,
+        ErrorCode::KernelOperationFailed,
+        ErrorCode::KernelTessellationFailed,
+        ErrorCode::KernelNotImplemented,
+        ErrorCode::ZombieReference,
+        ErrorCode::ValidationFailed,
+        ErrorCode::LimitExceeded,
+        ErrorCode::IdCollision,
+        ErrorCode::BodyNameLost,
+        ErrorCode::ImportFailed,
+        ErrorCode::ExportFailed,
+        ErrorCode::ProjectionFailed,
+        ErrorCode::SaveFailed,
+        ErrorCode::ReorderFailed,
+        ErrorCode::InsertFailed,
+    ];
+
+    /// The stable code sent to clients
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::CommandError => "COMMAND_ERROR",
+            ErrorCode::FeatureError => "FEATURE_ERROR",
+            ErrorCode::RegenFailed => "REGEN_FAILED",
+            ErrorCode::SketchSolveFailed => "SKETCH_SOLVE_FAILED",
+            ErrorCode::OpenProfile => "OPEN_PROFILE",
+            ErrorCode::KernelInvalidGeometry => "KERNEL_INVALID_GEOMETRY",
+            ErrorCode::KernelOperationFailed => "KERNEL_OPERATION_FAILED",
+            ErrorCode::KernelTessellationFailed => "KERNEL_TESSELLATION_FAILED",
+            ErrorCode::KernelNotImplemented => "KERNEL_NOT_IMPLEMENTED",
+            ErrorCode::ZombieReference => "ZOMBIE_REFERENCE",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::LimitExceeded => "LIMIT_EXCEEDED",
+            ErrorCode::IdCollision => "ID_COLLISION",
+            ErrorCode::BodyNameLost => "BODY_NAME_LOST",
+            ErrorCode::ImportFailed => "IMPORT_FAILED",
+            ErrorCode::ExportFailed => "EXPORT_FAILED",
+            ErrorCode::ProjectionFailed => "PROJECTION_FAILED",
+            ErrorCode::SaveFailed => "SAVE_FAILED",
+            ErrorCode::ReorderFailed => "REORDER_FAILED",
+            ErrorCode::InsertFailed => "INSERT_FAILED",
+        }
+    }
+
+    pub fn default_severity(self) -> Severity {
+        match self {
+            ErrorCode::ZombieReference | ErrorCode::IdCollision | ErrorCode::BodyNameLost | ErrorCode::ReorderFailed => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Keys of `details` for this code, always all present
+    pub fn detail_keys(self) -> &'static [&'static str] {
+        match self {
+            ErrorCode::CommandError => &["command"],
+            ErrorCode::FeatureError
+            | ErrorCode::RegenFailed
+            | ErrorCode::KernelInvalidGeometry
+            | ErrorCode::KernelOperationFailed
+            | ErrorCode::KernelTessellationFailed
+            | ErrorCode::KernelNotImplemented
+            | ErrorCode::ReorderFailed => &["feature_id"],
+            ErrorCode::SketchSolveFailed => &["sketch_id", "constraint_indices"],
+            ErrorCode::OpenProfile => &["sketch_id", "entity_ids"],
+            ErrorCode::ZombieReference => &["feature_id", "topo_ids"],
+            ErrorCode::ValidationFailed => &["feature_id", "parameter"],
+            ErrorCode::IdCollision => &["ids"],
+            ErrorCode::LimitExceeded => &["limit", "max"],
+            ErrorCode::BodyNameLost => &["name"],
+            ErrorCode::ImportFailed | ErrorCode::ExportFailed => &["format"],
+            ErrorCode::ProjectionFailed => &["sketch_id"],
+            ErrorCode::SaveFailed => &["path"],
+            ErrorCode::InsertFailed => &["feature_type"],
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error as reported to clients
+#[derive(Debug, Clone, PartialEq, Error, Serialize, Deserialize)]
+#[error("{message}")]
+pub struct CadError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub severity: Severity,
+    pub details: Value,
+}
+
+impl CadError {
+    /// Error with the code's default severity and every detail null
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        let details: Map<String, Value> = code.detail_keys().iter().map(|key| (key.to_string(), Value::Null)).collect();
+        Self { code, message: message.into(), severity: code.default_severity(), details: Value::Object(details) }
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Set detail `key`, which must be one of the code's `detail_keys`
+    pub fn with_detail(mut self, key: &str, value: impl Serialize) -> Self {
+        debug_assert!(self.code.detail_keys().contains(&key), "{} has no detail '{}'", self.code, key);
+        if let Value::Object(details) = &mut self.details {
+            details.insert(key.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+        }
+        self
+    }
+
+    /// Check that `details` has exactly the code's keys
+    pub fn check_details(&self) -> Result<(), String> {
+        let Value::Object(details) = &self.details else {
+            return Err(format!("{} details must be an object", self.code));
+        };
+        let mut keys: Vec<&str> = details.keys().map(String::as_str).collect();
+        let mut expected = self.code.detail_keys().to_vec();
+        keys.sort_unstable();
+        expected.sort_unstable();
+        if keys != expected {
+            return Err(format!("{} details have keys {:?}; expected {:?}", self.code, keys, expected));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_closed_and_consistent() {
+        let mut codes: Vec<&str> = ErrorCode::ALL.iter().map(|c| c.as_str()).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorCode::ALL.len(), "Codes are unique");
+        for (i, code) in ErrorCode::ALL.into_iter().enumerate() {
+            assert_eq!(code as usize, i, "ALL lists every code in order");
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str(), "Serializes as its code");
+            let error = CadError::new(code, "message");
+            assert!(error.check_details().is_ok());
+            assert!(error.details.as_object().unwrap().values().all(Value::is_null));
+        }
+    }
+
+    #[test]
+    fn test_details_are_checked() {
+        let error = CadError::new(ErrorCode::FeatureError, "Sketch feature not found").with_detail("feature_id", "abc");
+        assert_eq!(error.details, serde_json::json!({ "feature_id": "abc" }));
+        assert_eq!(error.to_string(), "Sketch feature not found");
+        let wrong = CadError { details: serde_json::json!({ "sketch_id": null }), ..error };
+        assert!(wrong.check_details().is_err());
+    }
+}
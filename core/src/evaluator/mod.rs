@@ -1,4 +1,5 @@
 pub mod ast;
 pub mod generator;
+pub mod regen_diff;
 pub mod runtime;
 pub use runtime::Runtime;
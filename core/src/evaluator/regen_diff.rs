@@ -0,0 +1,242 @@
+//! What changed between two regenerations
+//!
+//! A `RegenSnapshot` keeps the parts of an `EvaluationResult` a "what changed"
+//! view or a regression check compares: face, edge and vertex counts, the
+//! bounding box, and where each manifest entity is. Diffing two snapshots
+//! reports entities that appeared, disappeared or moved.
+//!
+//! `EvaluationResult::summary` answers a narrower question, whether two
+//! evaluations are the same model, with a geometry hash; use it for golden
+//! checks and this module to say what differs.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::runtime::EvaluationResult;
+use crate::geometry::tessellation::GEOMETRY_HASH_GRID;
+use crate::geometry::{ApproxEq, Point3};
+use crate::topo::naming::{TopoId, TopoRank};
+
+/// Entities whose position shifts by less than this are unmoved; the same
+/// grid that absorbs f32 round-off in geometry hashes
+const MOVE_TOLERANCE: f64 = GEOMETRY_HASH_GRID;
+
+/// Counts and extent of a regeneration
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct RegenCounts {
+    pub faces: usize,
+    pub edges: usize,
+    pub vertices: usize,
+    /// Min and max corner of the solid geometry; None when there is none
+    pub bounds: Option<[[f64; 3]; 2]>,
+}
+
+/// One regeneration, reduced for comparison
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RegenSnapshot {
+    pub counts: RegenCounts,
+    /// Every manifest entity, with the centroid of its tessellation if it has one
+    pub entities: HashMap<TopoId, Option<[f64; 3]>>,
+}
+
+/// An entity present in both regenerations but somewhere else
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MovedEntity {
+    pub id: TopoId,
+    pub from: [f64; 3],
+    pub to: [f64; 3],
+}
+
+/// Differences from an older snapshot to a newer one. Lists are ordered by
+/// feature, rank and local id so diffs of the same change compare equal.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegenDiff {
+    pub added: Vec<TopoId>,
+    pub removed: Vec<TopoId>,
+    pub moved: Vec<MovedEntity>,
+    pub before: RegenCounts,
+    pub after: RegenCounts,
+}
+
+impl RegenDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+fn order(id: &TopoId) -> (uuid::Uuid, TopoRank, u64) {
+    (id.feature_id.0, id.rank, id.local_id)
+}
+
+impl EvaluationResult {
+    /// Counts, bounds and entity positions, for diffing against another regeneration
+    pub fn snapshot(&self) -> RegenSnapshot {
+        let tess = &self.tessellation;
+        let vertex = |index: u32| {
+            let i = index as usize * 3;
+            [0, 1, 2].map(|k| tess.vertices[i + k] as f64)
+        };
+
+        let mut sums: HashMap<TopoId, ([f64; 3], usize)> = HashMap::new();
+        let mut bounds: Option<[[f64; 3]; 2]> = None;
+        let primitives = tess.indices.chunks_exact(3).zip(&tess.triangle_ids)
+            .chain(tess.line_indices.chunks_exact(2).zip(&tess.line_ids))
+            .chain(tess.point_indices.chunks_exact(1).zip(&tess.point_ids));
+        for (corners, id) in primitives {
+            let (sum, count) = sums.entry(*id).or_insert(([0.0; 3], 0));
+            for &index in corners {
+                let p = vertex(index);
+                for k in 0..3 {
+                    sum[k] += p[k];
+                }
+                *count += 1;
+                if corners.len() == 3 && !tess.is_reference(id) {
+                    let [min, max] = bounds.get_or_insert([p, p]);
+                    for k in 0..3 {
+                        min[k] = min[k].min(p[k]);
+                        max[k] = max[k].max(p[k]);
+                    }
+                }
+            }
+        }
+
+        let rank_count = |rank: TopoRank| self.topology_manifest.keys().filter(|id| id.rank == rank).count();
+        RegenSnapshot {
+            counts: RegenCounts {
+                faces: rank_count(TopoRank::Face),
+                edges: rank_count(TopoRank::Edge),
+                vertices: rank_count(TopoRank::Vertex),
+                bounds,
+            },
+            entities: self.topology_manifest.keys()
+                .map(|id| (*id, sums.get(id).map(|(sum, count)| sum.map(|s| s / *count as f64))))
+                .collect(),
+        }
+    }
+}
+
+impl RegenSnapshot {
+    /// What changed from this snapshot to `newer`. Entities without
+    /// tessellation are never reported as moved.
+    pub fn diff(&self, newer: &RegenSnapshot) -> RegenDiff {
+        let mut added: Vec<TopoId> = newer.entities.keys().filter(|id| !self.entities.contains_key(id)).copied().collect();
+        let mut removed: Vec<TopoId> = self.entities.keys().filter(|id| !newer.entities.contains_key(id)).copied().collect();
+        let mut moved: Vec<MovedEntity> = self.entities.iter()
+            .filter_map(|(id, from)| {
+                let (from, to) = ((*from)?, (*newer.entities.get(id)?)?);
+                let unmoved = Point3::from(from).approx_eq_tol(&Point3::from(to), MOVE_TOLERANCE);
+                (!unmoved).then_some(MovedEntity { id: *id, from, to })
+            })
+            .collect();
+        added.sort_by_key(order);
+        removed.sort_by_key(order);
+        moved.sort_by_key(|m| order(&m.id));
+        RegenDiff { added, removed, moved, before: self.counts.clone(), after: newer.counts.clone() }
+    }
+}
+
+/// The last two regenerations that differed, for DiffLastRegen
+#[derive(Debug, Clone, Default)]
+pub struct RegenHistory {
+    previous: Option<RegenSnapshot>,
+    latest: Option<RegenSnapshot>,
+}
+
+impl RegenHistory {
+    /// Keep `snapshot` as the latest regeneration. Regenerating an unchanged
+    /// model (another session catching up, a plain Regen) leaves the pair alone.
+    pub fn record(&mut self, snapshot: RegenSnapshot) {
+        if self.latest.as_ref() != Some(&snapshot) {
+            self.previous = self.latest.replace(snapshot);
+        }
+    }
+
+    /// Changes made by the latest regeneration, once there have been two
+    pub fn diff(&self) -> Option<RegenDiff> {
+        Some(self.previous.as_ref()?.diff(self.latest.as_ref()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Tessellation;
+    use crate::topo::naming::NamingContext;
+    use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+    use crate::topo::EntityId;
+
+    fn result(faces: &[(TopoId, Vec<[f64; 3]>)], edges: &[(TopoId, [f64; 3], [f64; 3])]) -> EvaluationResult {
+        let mut tessellation = Tessellation::new();
+        let mut topology_manifest = HashMap::new();
+        let p = |c: [f64; 3]| Point3::new(c[0], c[1], c[2]);
+        for (id, outline) in faces {
+            for i in 1..outline.len() - 1 {
+                tessellation.add_triangle(p(outline[0]), p(outline[i]), p(outline[i + 1]), *id);
+            }
+            topology_manifest.insert(*id, KernelEntity { id: *id, geometry: AnalyticGeometry::Mesh });
+        }
+        for (id, start, end) in edges {
+            tessellation.add_line(p(*start), p(*end), *id);
+            topology_manifest.insert(*id, KernelEntity { id: *id, geometry: AnalyticGeometry::Line { start: *start, end: *end } });
+        }
+        EvaluationResult { modified_entities: Vec::new(), logs: Vec::new(), tessellation, topology_manifest, feature_errors: Vec::new() }
+    }
+
+    #[test]
+    fn test_rounding_an_edge_adds_the_round_and_removes_the_sharp_edge() {
+        // Top and front of a unit cube meeting along y = 0, z = 1; a 0.2 round
+        // trims both faces back and replaces the edge with a curved face and two tangent edges.
+        // The kernel skips Fillet features for now, so both results are built by hand
+        let ctx = NamingContext::new(EntityId::new());
+        let (top, front, bottom) = (ctx.derive("Top", TopoRank::Face), ctx.derive("Front", TopoRank::Face), ctx.derive("Bottom", TopoRank::Face));
+        let edge = ctx.derive("TopFront", TopoRank::Edge);
+        let bottom_face = (bottom, vec![[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 0.0]]);
+        let sharp = result(
+            &[
+                (top, vec![[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0]]),
+                (front, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0]]),
+                bottom_face.clone(),
+            ],
+            &[(edge, [0.0, 0.0, 1.0], [1.0, 0.0, 1.0])],
+        );
+
+        let fillet = NamingContext::new(EntityId::new());
+        let round = fillet.derive("Round", TopoRank::Face);
+        let (top_tangent, front_tangent) = (fillet.derive("TopTangent", TopoRank::Edge), fillet.derive("FrontTangent", TopoRank::Edge));
+        let arc = |x: f64| (0..=4).map(move |i| {
+            let a = i as f64 * std::f64::consts::FRAC_PI_8;
+            [x, 0.2 - 0.2 * a.cos(), 0.8 + 0.2 * a.sin()]
+        });
+        let mut round_outline: Vec<[f64; 3]> = arc(0.0).collect();
+        round_outline.extend(arc(1.0).collect::<Vec<_>>().into_iter().rev());
+        let rounded = result(
+            &[
+                (top, vec![[0.0, 0.2, 1.0], [1.0, 0.2, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0]]),
+                (front, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.8], [0.0, 0.0, 0.8]]),
+                bottom_face,
+                (round, round_outline),
+            ],
+            &[(top_tangent, [0.0, 0.2, 1.0], [1.0, 0.2, 1.0]), (front_tangent, [0.0, 0.0, 0.8], [1.0, 0.0, 0.8])],
+        );
+
+        let diff = sharp.snapshot().diff(&rounded.snapshot());
+        let mut added = vec![round, top_tangent, front_tangent];
+        added.sort_by_key(order);
+        assert_eq!(diff.added, added);
+        assert_eq!(diff.removed, vec![edge]);
+        let moved: Vec<TopoId> = diff.moved.iter().map(|m| m.id).collect();
+        assert!(moved.contains(&top) && moved.contains(&front) && !moved.contains(&bottom), "{:?}", moved);
+        assert_eq!((diff.before.faces, diff.after.faces, diff.before.edges, diff.after.edges), (3, 4, 1, 2));
+        assert_eq!(diff.after.bounds, Some([[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]]));
+
+        // Regenerating the same model again keeps the fillet as the last change
+        let mut history = RegenHistory::default();
+        history.record(sharp.snapshot());
+        assert!(history.diff().is_none());
+        history.record(rounded.snapshot());
+        history.record(rounded.snapshot());
+        assert_eq!(history.diff(), Some(diff));
+        assert!(sharp.snapshot().diff(&sharp.snapshot()).is_empty());
+    }
+}
@@ -1,13 +1,15 @@
 use super::ast::{Program, Statement, Expression, Call, Value};
+use crate::errors::{CadError, ErrorCode};
 use crate::topo::{EntityId, IdGenerator};
 use crate::geometry::Tessellation;
+use crate::geometry::tessellation::BodyId;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use std::collections::HashMap;
 use truck_modeling::Solid;
 
 // Use the new MIT-compatible kernel abstraction
-use crate::kernel::{self, GeometryKernel, Polygon2D, Point2D, ExtrudeParams, Vector3D};
+use crate::kernel::{self, GeometryKernel, Polygon2D, Point2D, ExtrudeParams, TriangleMesh, Vector3D};
 
 #[derive(Debug, Error, Clone, Serialize, Deserialize)]
 pub enum KernelError {
@@ -17,8 +19,78 @@ pub enum KernelError {
     EvaluationError(String),
     #[error("Feature not implemented: {0}")]
     NotImplemented(String),
+    /// The current feature is invalid; evaluation continues with the next feature
+    #[error("Feature error: {0}")]
+    FeatureError(String),
+    /// The current feature's profile has curves with a free end (their ids);
+    /// evaluation continues with the next feature
+    #[error("Profile is not closed: {} curves have a free end", .0.len())]
+    OpenProfile(Vec<String>),
+    /// `error` stopped evaluation while building feature `feature_id`
+    #[error("{error}")]
+    InFeature { feature_id: String, error: Box<KernelError> },
 }
 
+impl KernelError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            KernelError::RuntimeError(_) => ErrorCode::RegenFailed,
+            KernelError::EvaluationError(_) => ErrorCode::ValidationFailed,
+            KernelError::NotImplemented(_) => ErrorCode::KernelNotImplemented,
+            KernelError::FeatureError(_) => ErrorCode::FeatureError,
+            KernelError::OpenProfile(_) => ErrorCode::OpenProfile,
+            KernelError::InFeature { error, .. } => error.code(),
+        }
+    }
+
+    /// Blame `feature_id` for this error, unless a feature already is
+    fn in_feature(self, feature_id: &str) -> Self {
+        match self {
+            KernelError::InFeature { .. } => self,
+            error => KernelError::InFeature { feature_id: feature_id.to_string(), error: Box::new(error) },
+        }
+    }
+}
+
+impl From<KernelError> for CadError {
+    fn from(error: KernelError) -> Self {
+        let code = error.code();
+        let message = error.to_string();
+        match error {
+            KernelError::OpenProfile(entity_ids) => CadError::new(code, message).with_detail("entity_ids", entity_ids),
+            KernelError::InFeature { feature_id, error } => {
+                let cad_error = CadError::from(*error);
+                if code.detail_keys().contains(&"feature_id") {
+                    cad_error.with_detail("feature_id", feature_id)
+                } else {
+                    cad_error
+                }
+            }
+            _ => CadError::new(code, message),
+        }
+    }
+}
+
+/// A feature that could not be built, reported instead of producing broken geometry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureError {
+    /// Context (feature id) the error was raised in
+    pub feature_id: String,
+    pub message: String,
+    /// Coded error behind the message, when it is more specific than FEATURE_ERROR
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<CadError>,
+}
+
+/// Program variable holding the solid of body `id`, so features can target
+/// one body of a multi-body feature
+pub fn body_variable(id: &BodyId) -> String {
+    format!("body_{}_{:016x}", id.feature_id, id.local_id)
+}
+
+/// Largest twist an extrude accepts, either way
+const MAX_EXTRUDE_TWIST_DEGREES: f64 = 90.0;
+
 /// Source entity type for a profile segment - used to group curved surfaces
 #[derive(Debug, Clone)]
 pub enum ProfileSegmentSource {
@@ -52,11 +124,97 @@ pub struct EvaluationResult {
     pub tessellation: Tessellation,
     /// Detailed manifest of all topology created, mapped by their stable TopoId
     pub topology_manifest: std::collections::HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    /// Features rejected during evaluation
+    #[serde(default)]
+    pub feature_errors: Vec<FeatureError>,
+}
+
+/// One topology manifest entry, flattened for inspection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    /// Feature the entity was derived in (the feature's TopoId namespace)
+    pub feature_id: EntityId,
+    /// Hash of the derivation seed, as hex
+    pub local_id: String,
+    pub rank: crate::topo::naming::TopoRank,
+    /// `AnalyticGeometry` variant
+    pub kind: &'static str,
+    /// Geometry parameters (origin, normal, radius, ...); empty for meshes
+    pub params: serde_json::Value,
+}
+
+/// Geometry hash and counts of an evaluation, for regression checks: equal
+/// summaries mean the same model, within `GEOMETRY_HASH_GRID`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EvaluationSummary {
+    /// `Tessellation::geometry_hash`, as hex so JSON clients keep every bit
+    pub geometry_hash: String,
+    pub triangles: usize,
+    pub lines: usize,
+    pub points: usize,
+    pub bodies: usize,
+    pub topology_entities: usize,
+    pub feature_errors: usize,
+}
+
+impl EvaluationResult {
+    /// Geometry hash and counts, cheap to compare between evaluations. It
+    /// says whether two models differ; `snapshot` and `RegenSnapshot::diff` say how.
+    pub fn summary(&self) -> EvaluationSummary {
+        EvaluationSummary {
+            geometry_hash: format!("{:016x}", self.tessellation.geometry_hash()),
+            triangles: self.tessellation.triangle_ids.len(),
+            lines: self.tessellation.line_ids.len(),
+            points: self.tessellation.point_ids.len(),
+            bodies: self.tessellation.bodies.len(),
+            topology_entities: self.topology_manifest.len(),
+            feature_errors: self.feature_errors.len(),
+        }
+    }
+
+    /// The topology manifest as a flat list, ordered by feature, rank and local id
+    /// so that manifests of successive regenerations can be diffed
+    pub fn manifest_entries(&self) -> Vec<ManifestEntry> {
+        let mut entries: Vec<ManifestEntry> = self.topology_manifest.values().map(|entity| {
+            let params = match serde_json::to_value(&entity.geometry) {
+                // Externally tagged: {"Plane": {...}}; keep the inner parameters
+                Ok(serde_json::Value::Object(map)) => map.into_iter().next().map(|(_, v)| v).unwrap_or_default(),
+                _ => serde_json::Value::Null,
+            };
+            ManifestEntry {
+                feature_id: entity.id.feature_id,
+                local_id: format!("{:016x}", entity.id.local_id),
+                rank: entity.id.rank,
+                kind: entity.geometry.kind(),
+                params,
+            }
+        }).collect();
+        entries.sort_by(|a, b| (a.feature_id.0, a.rank, &a.local_id).cmp(&(b.feature_id.0, b.rank, &b.local_id)));
+        entries
+    }
+
+    /// Compact JSON of `manifest_entries`
+    pub fn manifest_json(&self) -> String {
+        serde_json::to_string(&self.manifest_entries()).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// Solids built during an evaluation, by program variable
+pub struct EvaluatedSolids(HashMap<String, (Solid, TransformData)>);
+
+impl EvaluatedSolids {
+    /// STEP text of body `id`, in world coordinates
+    pub fn body_step(&self, id: &BodyId) -> Result<String, String> {
+        let (solid, t) = self.0.get(&body_variable(id)).ok_or_else(|| format!("No solid for body {:?}", id))?;
+        let world = kernel::transform_solid_to_world(solid, t.origin, t.x_axis, t.y_axis, t.normal);
+        kernel::default_kernel().export_step(&world).map_err(|e| format!("STEP export failed: {:?}", e))
+    }
 }
 
 /// The Evaluator Runtime environment.
 pub struct Runtime {
-    // Placeholder for memory/state
+    /// Line segments per full circle/ellipse when discretizing sketch curves and profiles
+    pub tessellation_segments: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -67,24 +225,54 @@ pub struct TransformData {
     pub normal: [f64; 3],
 }
 
+impl TransformData {
+    /// Map a mesh built in local Z-up coordinates onto the sketch plane
+    fn place_mesh(&self, mesh: &mut kernel::TriangleMesh) {
+        let (o, x, y, n) = (self.origin, self.x_axis, self.y_axis, self.normal);
+        for p in &mut mesh.positions {
+            let (u, v, w) = (p.x, p.y, p.z);
+            p.x = o[0] + u * x[0] + v * y[0] + w * n[0];
+            p.y = o[1] + u * x[1] + v * y[1] + w * n[1];
+            p.z = o[2] + u * x[2] + v * y[2] + w * n[2];
+        }
+    }
+}
+
 impl Runtime {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            tessellation_segments: crate::geometry::tessellation::DEFAULT_TESSELLATION_SEGMENTS,
+        }
+    }
+
+    /// Use `segments` line segments per full circle (minimum 3)
+    pub fn with_tessellation_segments(mut self, segments: usize) -> Self {
+        self.tessellation_segments = segments.max(3);
+        self
     }
 
     /// Evaluates a program and returns the result.
     pub fn evaluate(&self, program: &Program, initial_generator: &IdGenerator) -> Result<EvaluationResult, KernelError> {
+        self.evaluate_with_solids(program, initial_generator).map(|(result, _)| result)
+    }
+
+    /// Evaluate, keeping the solids behind the tessellation for export
+    pub fn evaluate_with_solids(&self, program: &Program, initial_generator: &IdGenerator) -> Result<(EvaluationResult, EvaluatedSolids), KernelError> {
         let mut modified = Vec::new();
         let mut logs = Vec::new();
         let mut tessellation = Tessellation::new();
         let mut topology_manifest = std::collections::HashMap::new();
         
-        // We use a local generator that can be swapped out when context changes
-        let mut current_generator = initial_generator.clone();
+        // We use a local generator that can be swapped out when context changes.
+        // Calls before any context count from the start of the caller's sequence,
+        // so evaluating a program again with the same generator gives the same ids.
+        let mut current_generator = initial_generator.restart();
         let mut solid_map: HashMap<String, (Solid, TransformData)> = HashMap::new();
         
         // Track which features are consumed by Boolean operations (should not be tessellated)
         let mut consumed_features: std::collections::HashSet<String> = std::collections::HashSet::new();
+        let mut feature_errors = Vec::new();
+        let mut current_context = String::new();
 
         for stmt in &program.statements {
             match stmt {
@@ -97,8 +285,26 @@ impl Runtime {
                         
                         // Pass is_consumed to suppress tessellation ONLY for consumed features
                         // Non-consumed features should still tessellate normally
-                        let res = self.mock_syscall(call, &current_generator, &mut modified, &mut logs, &mut tessellation, &mut topology_manifest, &mut solid_map, is_consumed)?;
-                        if let Some((solid, transform)) = res {
+                        let first_triangle = tessellation.triangle_ids.len();
+                        let bodies_before = tessellation.bodies.len();
+                        // A feature's ids come from its own EntityId, as `set_context`
+                        // would seed them, never from the caller's generator
+                        let feature_generator;
+                        let generator = if current_context.is_empty() && name.starts_with("feat_") {
+                            feature_generator = IdGenerator::new(context_id);
+                            &feature_generator
+                        } else {
+                            &current_generator
+                        };
+                        let res = self.mock_syscall(call, generator, &mut modified, &mut logs, &mut tessellation, &mut topology_manifest, &mut solid_map, is_consumed);
+                        let isolated = Self::isolate_feature_error(res, context_id, &mut feature_errors, &mut logs);
+                        if let Some((solid, transform)) = isolated.map_err(|e| e.in_feature(context_id))? {
+                            // Features that didn't split their output into bodies make one body
+                            if tessellation.bodies.len() == bodies_before {
+                                if let Some(body) = register_feature_body(&mut tessellation, &mut topology_manifest, first_triangle) {
+                                    solid_map.insert(body_variable(&body), (solid.clone(), transform.clone()));
+                                }
+                            }
                             solid_map.insert(name.clone(), (solid, transform));
                         }
                     }
@@ -115,6 +321,7 @@ impl Runtime {
                                 };
                                 logs.push(format!("Context switched to: {}", seed));
                                 current_generator = IdGenerator::new(&seed);
+                                current_context = seed;
                             }
                         } else if call.function == "set_consumed_features" {
                             // Handle consumed features list
@@ -130,19 +337,362 @@ impl Runtime {
                             }
                         } else {
                             // Pass false for is_assignment to permit tessellation
-                            self.mock_syscall(call, &current_generator, &mut modified, &mut logs, &mut tessellation, &mut topology_manifest, &mut solid_map, false)?;
+                            let res = self.mock_syscall(call, &current_generator, &mut modified, &mut logs, &mut tessellation, &mut topology_manifest, &mut solid_map, false);
+                            Self::isolate_feature_error(res, &current_context, &mut feature_errors, &mut logs)?;
                         }
                     }
                 }
             }
         }
 
-        Ok(EvaluationResult {
+        // Bodies a Boolean consumed by themselves, rather than as a whole feature
+        let consumed_bodies: Vec<BodyId> = tessellation.body_ids().into_iter()
+            .filter(|id| consumed_features.contains(&body_variable(id)))
+            .collect();
+        for id in &consumed_bodies {
+            tessellation.remove_body(id);
+        }
+
+        let result = EvaluationResult {
             modified_entities: modified,
             logs,
             tessellation,
             topology_manifest,
-        })
+            feature_errors,
+        };
+        Ok((result, EvaluatedSolids(solid_map)))
+    }
+
+    /// Turn a `FeatureError` into a recorded error for `feature_id` so the remaining
+    /// features still evaluate; any other error aborts evaluation.
+    fn isolate_feature_error<T>(
+        res: Result<Option<T>, KernelError>,
+        feature_id: &str,
+        feature_errors: &mut Vec<FeatureError>,
+        logs: &mut Vec<String>,
+    ) -> Result<Option<T>, KernelError> {
+        match res {
+            Err(KernelError::FeatureError(message)) => {
+                logs.push(format!("Feature {} failed: {}", feature_id, message));
+                feature_errors.push(FeatureError { feature_id: feature_id.to_string(), message, cause: None });
+                Ok(None)
+            }
+            Err(error @ KernelError::OpenProfile(_)) => {
+                let message = error.to_string();
+                logs.push(format!("Feature {} failed: {}", feature_id, message));
+                feature_errors.push(FeatureError { feature_id: feature_id.to_string(), message, cause: Some(error.into()) });
+                Ok(None)
+            }
+            other => other,
+        }
+    }
+
+    /// Tessellate one sketch on its own, without evaluating the rest of the
+    /// model: the curves, points and origin/axes a full regeneration would draw
+    /// for sketch feature `feature_id`, with the same TopoIds. `placement` is
+    /// the face or datum plane the sketch is placed on, if any. The sketch is
+    /// drawn as given; projected geometry keeps its stored positions.
+    pub fn render_sketch(
+        &self,
+        feature_id: EntityId,
+        sketch: &crate::sketch::types::Sketch,
+        placement: Option<&crate::topo::registry::AnalyticGeometry>,
+    ) -> Tessellation {
+        let mut sketch = sketch.clone();
+        if let Some(plane) = placement.and_then(crate::geometry::datum::sketch_plane_from) {
+            sketch.plane = plane;
+        }
+        // The id the "sketch" call takes from the feature's context
+        let id = IdGenerator::new(&feature_id.to_string()).next_id();
+        let mut tessellation = Tessellation::new();
+        self.add_sketch_geometry(&sketch, id, &mut tessellation, &mut HashMap::new());
+        tessellation
+    }
+
+    /// Draw a solved sketch's entities and its origin and axes into
+    /// `tessellation`, registering their geometry. `id` is the sketch's own id.
+    fn add_sketch_geometry(
+        &self,
+        sketch: &crate::sketch::types::Sketch,
+        id: EntityId,
+        tessellation: &mut Tessellation,
+        topology_manifest: &mut HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    ) {
+        use crate::geometry::Point3;
+
+        // Helper to transform 2D sketch points to 3D world space
+        let plane = &sketch.plane;
+        let origin = plane.origin;
+        let x_axis = plane.x_axis;
+        let y_axis = plane.y_axis;
+        let to_world = |x: f64, y: f64| -> Point3 {
+            origin + x_axis * x + y_axis * y
+        };
+        let z_axis = x_axis.cross(&y_axis);
+        let to_world_vec = |x: f64, y: f64, z: f64| -> [f64; 3] {
+            let v = x_axis * x + y_axis * y + z_axis * z;
+            [v[0], v[1], v[2]]
+        };
+
+        // Generate Visuals (Lines)
+        for entity in &sketch.entities {
+            // For Phase 1: Just lines and circles (as lines/polygons)
+            match &entity.geometry {
+                crate::sketch::types::SketchGeometry::Line { start, end } => {
+                    // Wrap SketchEntity ID into TopoId for selection
+                    // Treating the entity itself as the 'feature' scope for now, 
+                    // or just using its UUID as the unique identifier.
+                    let topo_id = crate::topo::naming::TopoId::new(
+                        entity.id, 
+                        0, 
+                        crate::topo::naming::TopoRank::Edge
+                    );
+
+                    // Register Line Analytic Geometry
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Line {
+                            start: { let p = to_world(start[0], start[1]); [p.x, p.y, p.z] },
+                            end: { let p = to_world(end[0], end[1]); [p.x, p.y, p.z] },
+                        }
+                    });
+
+                    tessellation.add_line(
+                        to_world(start[0], start[1]),
+                        to_world(end[0], end[1]),
+                        topo_id
+                    );
+
+                    // Add Vertices for endpoints
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 0, to_world(start[0], start[1]));
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 1, to_world(end[0], end[1]));
+                },
+                crate::sketch::types::SketchGeometry::Circle { center, radius } => {
+                    let topo_id = crate::topo::naming::TopoId::new(
+                        entity.id, 
+                        0, 
+                        crate::topo::naming::TopoRank::Edge
+                    );
+
+                    // Register Circle Analytic Geometry
+                    let normal = to_world_vec(0.0, 0.0, 1.0);
+                    let center_3d = { let p = to_world(center[0], center[1]); [p.x, p.y, p.z] };
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Circle {
+                            center: center_3d,
+                            normal,
+                            radius: *radius,
+                        }
+                    });
+
+                    // Discretize circle
+                    let segments = self.tessellation_segments;
+                    let mut prev_point = to_world(center[0] + radius, center[1]);
+
+                    for i in 1..=segments {
+                        let angle = (i as f64 / segments as f64) * 2.0 * std::f64::consts::PI;
+                        let x = center[0] + radius * angle.cos();
+                        let y = center[1] + radius * angle.sin();
+                        let curr_point = to_world(x, y);
+
+                        tessellation.add_line(prev_point, curr_point, topo_id);
+                        prev_point = curr_point;
+                    }
+
+                    // Add Center Vertex
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 0, to_world(center[0], center[1]));
+                },
+                crate::sketch::types::SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+                    let topo_id = crate::topo::naming::TopoId::new(
+                        entity.id, 
+                        0, 
+                        crate::topo::naming::TopoRank::Edge
+                    );
+
+                    let segments = self.tessellation_segments;
+                    // Normalize angles? No, just assume valid for now.
+                    // Ensure positive sweep?
+                    let mut sweep = end_angle - start_angle;
+                    if sweep < 0.0 { sweep += 2.0 * std::f64::consts::PI; }
+
+                    let start_x = center[0] + radius * start_angle.cos();
+                    let start_y = center[1] + radius * start_angle.sin();
+                    let mut prev_point = to_world(start_x, start_y);
+
+                    // Register Arc Analytic Geometry
+                    let normal = to_world_vec(0.0, 0.0, 1.0);
+                    let center_3d = { let p = to_world(center[0], center[1]); [p.x, p.y, p.z] };
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Circle {
+                            center: center_3d,
+                            normal,
+                            radius: *radius,
+                        }
+                    });
+
+
+
+                    for i in 1..=segments {
+                        let t = i as f64 / segments as f64;
+                        let angle = start_angle + sweep * t;
+
+                        let x = center[0] + radius * angle.cos();
+                        let y = center[1] + radius * angle.sin();
+                        let curr_point = to_world(x, y);
+
+                        tessellation.add_line(prev_point, curr_point, topo_id);
+                        prev_point = curr_point;
+                    }
+
+                    // Add Vertices for center and endpoints
+                    let end_x = center[0] + radius * end_angle.cos();
+                    let end_y = center[1] + radius * end_angle.sin();
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 0, to_world(center[0], center[1]));
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 1, to_world(start_x, start_y));
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 2, to_world(end_x, end_y));
+                },
+                crate::sketch::types::SketchGeometry::Point { pos } => {
+                    // Point entity - add the point and cross lines for visibility
+                    let topo_id = crate::topo::naming::TopoId::new(
+                        entity.id,
+                        0,
+                        crate::topo::naming::TopoRank::Vertex
+                    );
+
+                    // Add the center point
+                    let point_3d = to_world(pos[0], pos[1]);
+                    tessellation.add_point(point_3d, topo_id);
+
+                    // Register Point Analytic Geometry
+                    let center_3d = { let p = to_world(pos[0], pos[1]); [p.x, p.y, p.z] };
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Point { position: center_3d }
+                    });
+
+                    // Add cross lines for visibility (same as frontend)
+                    let size = 0.3;
+                    let cross_id = crate::topo::naming::TopoId::new(
+                        entity.id,
+                        1,
+                        crate::topo::naming::TopoRank::Edge
+                    );
+                    tessellation.add_line(
+                        to_world(pos[0] - size, pos[1]),
+                        to_world(pos[0] + size, pos[1]),
+                        cross_id
+                    );
+                    tessellation.add_line(
+                        to_world(pos[0], pos[1] - size),
+                        to_world(pos[0], pos[1] + size),
+                        cross_id
+                    );
+                },
+                crate::sketch::types::SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => {
+                    let topo_id = crate::topo::naming::TopoId::new(
+                        entity.id,
+                        0,
+                        crate::topo::naming::TopoRank::Edge
+                    );
+
+                    // Register Ellipse Analytic Geometry (Fallback to Mesh)
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Mesh
+                    });
+
+                    // Register Ellipse Analytic Geometry (approximated as Mesh for now strictly, or add Ellipse variant later)
+                    // For now, let's treat it as Mesh since AnalyticGeometry doesn't have Ellipse yet
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Mesh // Fallback
+                    });
+
+                    // Discretize ellipse with rotation
+                    let segments = self.tessellation_segments;
+                    let cos_r = rotation.cos();
+                    let sin_r = rotation.sin();
+
+                    let ellipse_point = |t: f64| -> [f64; 2] {
+                        let x_local = semi_major * t.cos();
+                        let y_local = semi_minor * t.sin();
+                        [
+                            center[0] + x_local * cos_r - y_local * sin_r,
+                            center[1] + x_local * sin_r + y_local * cos_r
+                        ]
+                    };
+
+                    let first = ellipse_point(0.0);
+                    let mut prev_point = to_world(first[0], first[1]);
+
+                    for i in 1..=segments {
+                        let t = (i as f64 / segments as f64) * 2.0 * std::f64::consts::PI;
+                        let pt = ellipse_point(t);
+                        let curr_point = to_world(pt[0], pt[1]);
+
+                        tessellation.add_line(prev_point, curr_point, topo_id);
+                        prev_point = curr_point;
+                    }
+
+                    // Add Center Vertex
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 0, to_world(center[0], center[1]));
+                },
+                crate::sketch::types::SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+                    let topo_id = crate::topo::naming::TopoId::new(
+                        entity.id,
+                        0,
+                        crate::topo::naming::TopoRank::Edge
+                    );
+                    // No analytic ellipse yet, as for the full ellipse
+                    topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
+                        id: topo_id,
+                        geometry: crate::topo::registry::AnalyticGeometry::Mesh
+                    });
+
+                    // Same sweep and segment count as a circular arc
+                    let segments = self.tessellation_segments;
+                    let mut sweep = end_angle - start_angle;
+                    if sweep < 0.0 { sweep += 2.0 * std::f64::consts::PI; }
+                    let point_at = |t: f64| {
+                        let p = crate::sketch::types::ellipse_point(*center, *semi_major, *semi_minor, *rotation, t);
+                        to_world(p[0], p[1])
+                    };
+
+                    let mut prev_point = point_at(*start_angle);
+                    for i in 1..=segments {
+                        let curr_point = point_at(start_angle + sweep * (i as f64 / segments as f64));
+                        tessellation.add_line(prev_point, curr_point, topo_id);
+                        prev_point = curr_point;
+                    }
+
+                    // Add Vertices for center and endpoints
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 0, to_world(center[0], center[1]));
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 1, point_at(*start_angle));
+                    add_sketch_vertex(tessellation, topology_manifest, entity.id, 2, point_at(*end_angle));
+                },
+            }
+        }
+
+        // Origin and axes, selectable and snappable but never part of a profile
+        for reference in crate::sketch::reference::ReferenceEntity::ALL {
+            let topo_id = reference.topo_id(id);
+            tessellation.mark_reference(topo_id);
+            let geometry = match reference.display_geometry(sketch) {
+                crate::sketch::types::SketchGeometry::Line { start, end } => {
+                    let (start, end) = (to_world(start[0], start[1]), to_world(end[0], end[1]));
+                    tessellation.add_line(start, end, topo_id);
+                    crate::topo::registry::AnalyticGeometry::Line { start: [start.x, start.y, start.z], end: [end.x, end.y, end.z] }
+                }
+                _ => {
+                    let p = to_world(0.0, 0.0);
+                    tessellation.add_point(p, topo_id);
+                    crate::topo::registry::AnalyticGeometry::Point { position: [p.x, p.y, p.z] }
+                }
+            };
+            topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity { id: topo_id, geometry });
+        }
     }
 
     fn mock_syscall(
@@ -158,24 +708,34 @@ impl Runtime {
     ) -> Result<Option<(Solid, TransformData)>, KernelError> {
         // Common imports for syscalls
         use crate::geometry::Point3;
-        use crate::topo::naming::{NamingContext, TopoRank};
+        use crate::topo::naming::{NamingContext, TopoId, TopoRank};
         use crate::topo::registry::{KernelEntity, AnalyticGeometry};
 
         match call.function.as_str() {
             "cube" => {
-                // Deterministic ID generation using the provided generator
+                // Args: width (X), height (Y), depth (Z). A single size makes a cube; default 10.
                 let id = generator.next_id();
                 modified.push(id);
                 logs.push(format!("Created cube with ID {}", id));
                 
                 let ctx = NamingContext::new(id);
+                let number_arg = |i: usize| match call.args.get(i) {
+                    Some(Expression::Value(Value::Number(n))) => Some(*n),
+                    _ => None,
+                };
+                let width = number_arg(0).unwrap_or(10.0);
+                let (height, depth) = (number_arg(1).unwrap_or(width), number_arg(2).unwrap_or(width));
+                if width <= 0.0 || height <= 0.0 || depth <= 0.0 {
+                    return Err(KernelError::FeatureError(format!(
+                        "Box dimensions must be positive (got {} x {} x {})", width, height, depth
+                    )));
+                }
 
                 // Use the new MIT-compatible Truck kernel
                 let kernel = kernel::default_kernel();
                 
-                // Create a 10x10x10 box
-                // Create a 10x10x10 box
-                match kernel.create_box(10.0, 10.0, 10.0) {
+                // Box from the origin to (width, height, depth)
+                match kernel.create_box(width, height, depth) {
                     Ok(solid) => {
                         if !is_assignment {
                             // Tessellate if this is a top-level expression call
@@ -210,6 +770,31 @@ impl Runtime {
 
                 Ok(None)
             }
+            "imported_body" => {
+                // Args: solid JSON stored by `FeatureGraph::import_step`
+                let id = generator.next_id();
+                modified.push(id);
+                let ctx = NamingContext::new(id);
+                let kernel = kernel::default_kernel();
+
+                let Some(Expression::Value(Value::String(json))) = call.args.first() else {
+                    return Err(KernelError::FeatureError("Imported body has no geometry".to_string()));
+                };
+                let solid = kernel.solid_from_json(json).map_err(|e| KernelError::FeatureError(e.to_string()))?;
+                if !is_assignment {
+                    match kernel.tessellate(&solid) {
+                        Ok(mesh) => kernel.mesh_to_tessellation(&mesh, tessellation, topology_manifest, &ctx, "ImportedBody"),
+                        Err(e) => logs.push(format!("Warning: Failed to tessellate imported body: {:?}", e)),
+                    }
+                }
+                let default_transform = TransformData {
+                    origin: [0.0, 0.0, 0.0],
+                    x_axis: [1.0, 0.0, 0.0],
+                    y_axis: [0.0, 1.0, 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                };
+                Ok(Some((solid, default_transform)))
+            }
             "sketch" => {
                 let id = generator.next_id();
                 modified.push(id);
@@ -219,294 +804,33 @@ impl Runtime {
                 if let Some(first_arg) = call.args.first() {
                     if let Expression::Value(Value::String(json)) = first_arg {
                         if let Ok(mut sketch) = serde_json::from_str::<crate::sketch::types::Sketch>(json) {
-                            // Update External References
-                            // This ensures that projected geometry matches the current state of referenced topology
+                            // Sketch placed on a face or datum plane follows it
+                            if let Some(plane) = reference_arg(call, 1, topology_manifest, logs)
+                                .and_then(|g| crate::geometry::datum::sketch_plane_from(&g))
                             {
-                                let origin = sketch.plane.origin;
-                                let x_axis = sketch.plane.x_axis;
-                                let y_axis = sketch.plane.y_axis;
-                                
-                                // Helper to project 3D point to 2D sketch plane
-                                let project_to_2d = |p: [f64; 3]| -> [f64; 2] {
-                                    let v = [p[0] - origin[0], p[1] - origin[1], p[2] - origin[2]];
-                                    let x = v[0]*x_axis[0] + v[1]*x_axis[1] + v[2]*x_axis[2];
-                                    let y = v[0]*y_axis[0] + v[1]*y_axis[1] + v[2]*y_axis[2];
-                                    [x, y]
-                                };
+                                sketch.plane = plane;
+                            }
 
-                                // Collect updates first to avoid borrow issues
-                                let mut updates: Vec<(crate::topo::EntityId, crate::sketch::types::SketchGeometry)> = Vec::new();
-                                
-                                for (entity_id, topo_id) in &sketch.external_references {
-                                    if let Some(kernel_entity) = topology_manifest.get(topo_id) {
-                                        let new_geo = match &kernel_entity.geometry {
-                                            AnalyticGeometry::Line { start, end } => {
-                                                Some(crate::sketch::types::SketchGeometry::Line {
-                                                    start: project_to_2d(*start),
-                                                    end: project_to_2d(*end),
-                                                })
-                                            },
-                                            // TODO: Support projecting other types (Circle -> Ellipse/Line, etc)
-                                            _ => None
-                                        };
-                                        
-                                        if let Some(geo) = new_geo {
-                                            updates.push((*entity_id, geo));
-                                        }
-                                    }
-                                }
-                                
-                                // Apply updates
-                                for (id, geo) in updates {
-                                    if let Some(entity) = sketch.entities.iter_mut().find(|e| e.id == id) {
-                                        entity.geometry = geo;
-                                    }
-                                }
+                            // Projected geometry follows the current state of the referenced topology
+                            let stale_references = sketch.reproject_external(topology_manifest);
+                            for failure in sketch.update_face_offsets(tessellation, topology_manifest) {
+                                logs.push(format!("Warning: {}", failure));
                             }
 
                             // Run solver (in-place)
-                            let converged = crate::sketch::solver::SketchSolver::solve(&mut sketch);
-                            if !converged {
+                            let mut solved = crate::sketch::solver::SketchSolver::solve_with_result(&mut sketch);
+                            if !solved.converged {
                                 logs.push("Warning: Sketch solver did not converge".to_string());
                             }
-
-                            // Helper to transform 2D sketch points to 3D world space
-                            let plane = &sketch.plane;
-                            let origin = plane.origin;
-                            let x_axis = plane.x_axis;
-                            let y_axis = plane.y_axis;
-                            let to_world = |x: f64, y: f64| -> Point3 {
-                                origin + x_axis * x + y_axis * y
-                            };
-                            let z_axis = x_axis.cross(&y_axis);
-                            let to_world_vec = |x: f64, y: f64, z: f64| -> [f64; 3] {
-                                let v = x_axis * x + y_axis * y + z_axis * z;
-                                [v[0], v[1], v[2]]
-                            };
-
-                            // Generate Visuals (Lines)
-                            for entity in &sketch.entities {
-                                // For Phase 1: Just lines and circles (as lines/polygons)
-                                match &entity.geometry {
-                                    crate::sketch::types::SketchGeometry::Line { start, end } => {
-                                        // Wrap SketchEntity ID into TopoId for selection
-                                        // Treating the entity itself as the 'feature' scope for now, 
-                                        // or just using its UUID as the unique identifier.
-                                        let topo_id = crate::topo::naming::TopoId::new(
-                                            entity.id, 
-                                            0, 
-                                            crate::topo::naming::TopoRank::Edge
-                                        );
-
-                                        // Register Line Analytic Geometry
-                                        topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
-                                            id: topo_id,
-                                            geometry: crate::topo::registry::AnalyticGeometry::Line {
-                                                start: { let p = to_world(start[0], start[1]); [p.x, p.y, p.z] },
-                                                end: { let p = to_world(end[0], end[1]); [p.x, p.y, p.z] },
-                                            }
-                                        });
-
-                                        tessellation.add_line(
-                                            to_world(start[0], start[1]),
-                                            to_world(end[0], end[1]),
-                                            topo_id
-                                        );
-
-                                        // Add Vertices for endpoints
-                                        let curr_gen = IdGenerator::new(&entity.id.to_string()); // Temp sub-generator
-                                        let v_start_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-                                        let v_end_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-                                        
-                                        tessellation.add_point(to_world(start[0], start[1]), v_start_id);
-                                        tessellation.add_point(to_world(end[0], end[1]), v_end_id);
-                                    },
-                                    crate::sketch::types::SketchGeometry::Circle { center, radius } => {
-                                        let topo_id = crate::topo::naming::TopoId::new(
-                                            entity.id, 
-                                            0, 
-                                            crate::topo::naming::TopoRank::Edge
-                                        );
-
-                                        // Register Circle Analytic Geometry
-                                        let normal = to_world_vec(0.0, 0.0, 1.0);
-                                        let center_3d = { let p = to_world(center[0], center[1]); [p.x, p.y, p.z] };
-                                        topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
-                                            id: topo_id,
-                                            geometry: crate::topo::registry::AnalyticGeometry::Circle {
-                                                center: center_3d,
-                                                normal,
-                                                radius: *radius,
-                                            }
-                                        });
-
-                                        // Discretize circle
-                                        let segments = 64;
-                                        let mut prev_point = to_world(center[0] + radius, center[1]);
-                                        
-                                        for i in 1..=segments {
-                                            let angle = (i as f64 / segments as f64) * 2.0 * std::f64::consts::PI;
-                                            let x = center[0] + radius * angle.cos();
-                                            let y = center[1] + radius * angle.sin();
-                                            let curr_point = to_world(x, y);
-
-                                            tessellation.add_line(prev_point, curr_point, topo_id);
-                                            prev_point = curr_point;
-                                        }
-
-                                        // Add Center Vertex
-                                        let curr_gen = IdGenerator::new(&entity.id.to_string());
-                                        let v_center_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-                                        tessellation.add_point(to_world(center[0], center[1]), v_center_id);
-                                    },
-                                    crate::sketch::types::SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
-                                        let topo_id = crate::topo::naming::TopoId::new(
-                                            entity.id, 
-                                            0, 
-                                            crate::topo::naming::TopoRank::Edge
-                                        );
-
-                                        let segments = 64;
-                                        // Normalize angles? No, just assume valid for now.
-                                        // Ensure positive sweep?
-                                        let mut sweep = end_angle - start_angle;
-                                        if sweep < 0.0 { sweep += 2.0 * std::f64::consts::PI; }
-                                        
-                                        let start_x = center[0] + radius * start_angle.cos();
-                                        let start_y = center[1] + radius * start_angle.sin();
-                                        let mut prev_point = to_world(start_x, start_y);
-
-                                        // Register Arc Analytic Geometry
-                                        let normal = to_world_vec(0.0, 0.0, 1.0);
-                                        let center_3d = { let p = to_world(center[0], center[1]); [p.x, p.y, p.z] };
-                                        topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
-                                            id: topo_id,
-                                            geometry: crate::topo::registry::AnalyticGeometry::Circle {
-                                                center: center_3d,
-                                                normal,
-                                                radius: *radius,
-                                            }
-                                        });
-
-
-
-                                        for i in 1..=segments {
-                                            let t = i as f64 / segments as f64;
-                                            let angle = start_angle + sweep * t;
-                                            
-                                            let x = center[0] + radius * angle.cos();
-                                            let y = center[1] + radius * angle.sin();
-                                            let curr_point = to_world(x, y);
-
-                                            tessellation.add_line(prev_point, curr_point, topo_id);
-                                            prev_point = curr_point;
-                                        }
-
-                                        // Add Vertices for endpoints and center
-                                        let curr_gen = IdGenerator::new(&entity.id.to_string());
-                                        let v_center_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-                                        let v_start_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-                                        let v_end_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-
-                                        tessellation.add_point(to_world(center[0], center[1]), v_center_id);
-                                        tessellation.add_point(to_world(start_x, start_y), v_start_id);
-                                        let end_x = center[0] + radius * end_angle.cos();
-                                        let end_y = center[1] + radius * end_angle.sin();
-                                        tessellation.add_point(to_world(end_x, end_y), v_end_id);
-                                    },
-                                    crate::sketch::types::SketchGeometry::Point { pos } => {
-                                        // Point entity - add the point and cross lines for visibility
-                                        let topo_id = crate::topo::naming::TopoId::new(
-                                            entity.id,
-                                            0,
-                                            crate::topo::naming::TopoRank::Vertex
-                                        );
-                                        
-                                        // Add the center point
-                                        let point_3d = to_world(pos[0], pos[1]);
-                                        tessellation.add_point(point_3d, topo_id);
-
-                                        // Register Point Analytic Geometry
-                                        let center_3d = { let p = to_world(pos[0], pos[1]); [p.x, p.y, p.z] };
-                                        topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
-                                            id: topo_id,
-                                            geometry: crate::topo::registry::AnalyticGeometry::Sphere {
-                                                center: center_3d,
-                                                radius: 0.0,
-                                            }
-                                        });
-                                        
-                                        // Add cross lines for visibility (same as frontend)
-                                        let size = 0.3;
-                                        let cross_id = crate::topo::naming::TopoId::new(
-                                            entity.id,
-                                            1,
-                                            crate::topo::naming::TopoRank::Edge
-                                        );
-                                        tessellation.add_line(
-                                            to_world(pos[0] - size, pos[1]),
-                                            to_world(pos[0] + size, pos[1]),
-                                            cross_id
-                                        );
-                                        tessellation.add_line(
-                                            to_world(pos[0], pos[1] - size),
-                                            to_world(pos[0], pos[1] + size),
-                                            cross_id
-                                        );
-                                    },
-                                    crate::sketch::types::SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => {
-                                        let topo_id = crate::topo::naming::TopoId::new(
-                                            entity.id,
-                                            0,
-                                            crate::topo::naming::TopoRank::Edge
-                                        );
-                                        
-                                        // Register Ellipse Analytic Geometry (Fallback to Mesh)
-                                        topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
-                                            id: topo_id,
-                                            geometry: crate::topo::registry::AnalyticGeometry::Mesh
-                                        });
-
-                                        // Register Ellipse Analytic Geometry (approximated as Mesh for now strictly, or add Ellipse variant later)
-                                        // For now, let's treat it as Mesh since AnalyticGeometry doesn't have Ellipse yet
-                                        topology_manifest.insert(topo_id, crate::topo::registry::KernelEntity {
-                                            id: topo_id,
-                                            geometry: crate::topo::registry::AnalyticGeometry::Mesh // Fallback
-                                        });
-
-                                        // Discretize ellipse with rotation
-                                        let segments = 64;
-                                        let cos_r = rotation.cos();
-                                        let sin_r = rotation.sin();
-                                        
-                                        let ellipse_point = |t: f64| -> [f64; 2] {
-                                            let x_local = semi_major * t.cos();
-                                            let y_local = semi_minor * t.sin();
-                                            [
-                                                center[0] + x_local * cos_r - y_local * sin_r,
-                                                center[1] + x_local * sin_r + y_local * cos_r
-                                            ]
-                                        };
-                                        
-                                        let first = ellipse_point(0.0);
-                                        let mut prev_point = to_world(first[0], first[1]);
-                                        
-                                        for i in 1..=segments {
-                                            let t = (i as f64 / segments as f64) * 2.0 * std::f64::consts::PI;
-                                            let pt = ellipse_point(t);
-                                            let curr_point = to_world(pt[0], pt[1]);
-
-                                            tessellation.add_line(prev_point, curr_point, topo_id);
-                                            prev_point = curr_point;
-                                        }
-
-                                        // Add Center Vertex
-                                        let curr_gen = IdGenerator::new(&entity.id.to_string());
-                                        let v_center_id = crate::topo::naming::TopoId::new(curr_gen.next_id(), 0, crate::topo::naming::TopoRank::Vertex);
-                                        tessellation.add_point(to_world(center[0], center[1]), v_center_id);
-                                    },
-                                }
+                            solved.flag_stale_external(&sketch, &stale_references);
+                            if !solved.stale_external_constraints.is_empty() {
+                                logs.push(format!(
+                                    "Warning: {} sketch constraint(s) use projected geometry whose reference was lost",
+                                    solved.stale_external_constraints.len()
+                                ));
                             }
+
+                            self.add_sketch_geometry(&sketch, id, tessellation, topology_manifest);
                         } else {
                             logs.push("Failed to deserialize sketch data".to_string());
                         }
@@ -531,6 +855,14 @@ impl Runtime {
                 // Region boundary points for region-based extrusion (JSON: [[[x,y], ...], ...])
                 // Each item is a Profile (list of loops: outer, inner...)
                 let mut profile_regions: Option<Vec<Vec<Vec<[f64; 2]>>>> = None;
+                // Face or datum plane the profile sketch is placed on (arg 6)
+                let ref_plane = reference_arg(call, 6, topology_manifest, logs)
+                    .and_then(|g| crate::geometry::datum::sketch_plane_from(&g));
+                // Per-region bodies (arg 7); when present they replace the profile selection
+                let mut region_specs: Option<Vec<crate::features::types::ResolvedRegionSpec>> = None;
+                // End scale in x and y (arg 8) and twist in degrees (arg 9)
+                let mut taper = (1.0, 1.0);
+                let mut twist_degrees = 0.0;
                 
                 for (i, arg) in call.args.iter().enumerate() {
                     match (i, arg) {
@@ -563,9 +895,29 @@ impl Runtime {
                                 }
                             }
                         },
+                        (7, Expression::Value(Value::String(s))) => {
+                            match serde_json::from_str::<Vec<crate::features::types::ResolvedRegionSpec>>(s) {
+                                Ok(specs) if !specs.is_empty() => region_specs = Some(specs),
+                                Ok(_) => {}
+                                Err(e) => logs.push(format!("Warning: Invalid region specs: {}", e)),
+                            }
+                        },
+                        (8, Expression::Value(Value::Vector(v))) if v.len() == 2 => taper = (v[0], v[1]),
+                        (9, Expression::Value(Value::Number(t))) => twist_degrees = *t,
                         _ => {}
                     }
                 }
+                if !(taper.0 > 0.0 && taper.1 > 0.0 && taper.0.is_finite() && taper.1.is_finite()) {
+                    return Err(KernelError::FeatureError(format!(
+                        "Extrude taper must be positive (got {} x {})", taper.0, taper.1
+                    )));
+                }
+                if twist_degrees.is_nan() || twist_degrees.abs() > MAX_EXTRUDE_TWIST_DEGREES {
+                    return Err(KernelError::FeatureError(format!(
+                        "Extrude twist must be within ±{}° (got {}°)", MAX_EXTRUDE_TWIST_DEGREES, twist_degrees
+                    )));
+                }
+                let end_transform = |params: ExtrudeParams| params.with_end_transform(taper, twist_degrees.to_radians());
                 
                 logs.push(format!("Extruding distance={}, offset={}, op={}, profiles={:?}, regions={}", 
                     distance, start_offset, _operation, profile_selection, 
@@ -574,6 +926,11 @@ impl Runtime {
                 // Parse sketch and generate 3D geometry
                 if let Some(json) = sketch_json {
                     if let Ok(mut sketch) = serde_json::from_str::<crate::sketch::types::Sketch>(&json) {
+                        if let Some(plane) = ref_plane {
+                            sketch.plane = plane;
+                        }
+                        sketch.reproject_external(topology_manifest);
+                        sketch.update_face_offsets(tessellation, topology_manifest);
                         // Solve constraints first
                         crate::sketch::solver::SketchSolver::solve(&mut sketch);
                         
@@ -591,6 +948,69 @@ impl Runtime {
                             normal,
                         };
                         
+                        // Multi-body extrude: each specified region becomes its own body with its own depth
+                        if let Some(specs) = region_specs {
+                            let summaries = crate::sketch::regions::summarize_regions(
+                                &crate::sketch::regions::find_regions_with_segments(&sketch.entities, self.tessellation_segments));
+                            let picks: Vec<(&str, Option<[f64; 2]>)> = specs.iter()
+                                .map(|spec| (spec.region_key.as_str(), spec.centroid))
+                                .collect();
+                            let matches = crate::sketch::regions::match_region_keys(&summaries, &picks);
+
+                            let kernel = kernel::default_kernel();
+                            let mut combined_result: Option<(Solid, TransformData)> = None;
+                            for (spec, matched) in specs.iter().zip(matches) {
+                                let Some(index) = matched else {
+                                    logs.push(format!("Warning: Region '{}' not found in sketch, skipping it", spec.region_key));
+                                    continue;
+                                };
+                                let region = &summaries[index];
+                                if region.stable_id != spec.region_key {
+                                    logs.push(format!("Warning: Region '{}' matched by position to '{}'", spec.region_key, region.stable_id));
+                                }
+
+                                let to_points = |pts: &[[f64; 2]]| pts.iter().map(|p| Point2D::new(p[0], p[1])).collect::<Vec<_>>();
+                                let polygon = Polygon2D::with_holes(
+                                    to_points(&region.region.boundary_points),
+                                    region.region.voids.iter().map(|v| to_points(v)).collect(),
+                                );
+                                let extrude_params = end_transform(ExtrudeParams::linear(spec.distance)
+                                    .with_direction(Vector3D::new(0.0, 0.0, 1.0)));
+                                let solid = match kernel.extrude_polygon(&polygon, &extrude_params) {
+                                    Ok(solid) => solid,
+                                    Err(e) => {
+                                        logs.push(format!("Warning: Extrusion failed for region '{}': {:?}", spec.region_key, e));
+                                        continue;
+                                    }
+                                };
+                                let body_id = ctx.derive(&format!("Body_{}", spec.region_key), crate::topo::naming::TopoRank::Solid);
+                                solid_map.insert(body_variable(&body_id), (solid.clone(), transform_data.clone()));
+                                if !is_assignment {
+                                    match kernel.tessellate(&solid) {
+                                        Ok(mut mesh) => {
+                                            if !extrude_params.is_straight() {
+                                                let mut loops = vec![region.region.boundary_points.clone()];
+                                                loops.extend(region.region.voids.iter().cloned());
+                                                group_tapered_side_faces(&mut mesh, &profile_segments(&loops, &sketch.entities));
+                                            }
+                                            transform_data.place_mesh(&mut mesh);
+                                            let first_triangle = tessellation.triangle_ids.len();
+                                            kernel.mesh_to_tessellation(&mesh, tessellation, topology_manifest, &ctx, &format!("Region_{}", spec.region_key));
+
+                                            topology_manifest.insert(body_id, crate::topo::registry::KernelEntity {
+                                                id: body_id,
+                                                geometry: crate::topo::registry::AnalyticGeometry::Mesh,
+                                            });
+                                            tessellation.add_body_since(body_id, first_triangle);
+                                        }
+                                        Err(e) => logs.push(format!("Warning: Tessellation failed for region '{}': {:?}", spec.region_key, e)),
+                                    }
+                                }
+                                combined_result = Some((solid, transform_data.clone()));
+                            }
+                            return Ok(combined_result);
+                        }
+
                         // Determine which loops to extrude
                         // Priority: profile_regions (exact boundary points) > find_closed_loops
                         // Type: Vec<Vec<Vec<[f64; 2]>>> where inner is [Outer, Hole1, Hole2...]
@@ -628,7 +1048,7 @@ impl Runtime {
                             
                             
                             // Use robust region detection instead of simple chain finding
-                            let regions = crate::sketch::regions::find_regions(&filtered_entities);
+                            let regions = crate::sketch::regions::find_regions_with_segments(&filtered_entities, self.tessellation_segments);
                             logs.push(format!("Found {} regions for extrusion", regions.len()));
                             
                             // Convert regions to the expected 2D point array format: Vec<Vec<Vec<[f64; 2]>>>
@@ -662,68 +1082,8 @@ impl Runtime {
                         if loop_segments.is_empty() { 
                             {
                                 logs.push("Attempting to reconstruct segment metadata from sketch geometry...".to_string());
-                                let entities = &sketch.entities;
-                                let EPSILON = 1e-4;
-
                                 for profile_loops in &loops_2d {
-                                    let mut profile_segs = Vec::new();
-                                    for loop_pts in profile_loops {
-                                        let mut segments = Vec::new();
-                                        let len = loop_pts.len();
-                                        if len > 0 {
-                                            for i in 0..len {
-                                                let p1 = loop_pts[i];
-                                                // Handle closed loop wrapping
-                                                let p2 = loop_pts[(i + 1) % len];
-                                                
-                                                let mut source = ProfileSegmentSource::Unknown;
-                                                
-                                                // Try to match against sketch entities
-                                                for entity in entities {
-                                                    match &entity.geometry {
-                                                        crate::sketch::types::SketchGeometry::Circle { center, radius } => {
-                                                            let d1 = ((p1[0]-center[0]).powi(2) + (p1[1]-center[1]).powi(2)).sqrt();
-                                                            let d2 = ((p2[0]-center[0]).powi(2) + (p2[1]-center[1]).powi(2)).sqrt();
-                                                            if (d1 - radius).abs() < EPSILON && (d2 - radius).abs() < EPSILON {
-                                                                source = ProfileSegmentSource::Circle {
-                                                                    entity_id: entity.id.to_string(),
-                                                                    center: *center,
-                                                                    radius: *radius,
-                                                                };
-                                                                break;
-                                                            }
-                                                        },
-                                                        crate::sketch::types::SketchGeometry::Arc { center, radius, .. } => {
-                                                            let d1 = ((p1[0]-center[0]).powi(2) + (p1[1]-center[1]).powi(2)).sqrt();
-                                                            let d2 = ((p2[0]-center[0]).powi(2) + (p2[1]-center[1]).powi(2)).sqrt();
-                                                            if (d1 - radius).abs() < EPSILON && (d2 - radius).abs() < EPSILON {
-                                                                // Ideally check angles too, but distance is sufficient for now
-                                                                // to distinguish from other geometry
-                                                                source = ProfileSegmentSource::Arc {
-                                                                    entity_id: entity.id.to_string(),
-                                                                    center: *center,
-                                                                    radius: *radius,
-                                                                };
-                                                                break;
-                                                            }
-                                                        },
-                                                        crate::sketch::types::SketchGeometry::Line { start, end } => {
-                                                            // Check if points are on the line segment
-                                                            // Distance from point to line check
-                                                            // For now, if it's not a curve, we don't strictly need to group it 
-                                                            // unless we want single-face selection for collinear segments?
-                                                            // Current behavior for lines is fine (Plane).
-                                                        },
-                                                        _ => {}
-                                                    }
-                                                }
-                                                
-                                                segments.push(ProfileSegment { p1, p2, source });
-                                            }
-                                        }
-                                        profile_segs.push(segments);
-                                    }
-                                    loop_segments.push(profile_segs);
+                                    loop_segments.push(profile_segments(profile_loops, &sketch.entities));
                                 }
                                 logs.push("Reconstructed segment metadata.".to_string());
                             }
@@ -759,8 +1119,8 @@ impl Runtime {
                             };
                             
                             // 2. Create extrusion parameters
-                            let extrude_params = ExtrudeParams::linear(distance)
-                                .with_direction(Vector3D::new(0.0, 0.0, 1.0)); // Truck extrudes in Z
+                            let extrude_params = end_transform(ExtrudeParams::linear(distance)
+                                .with_direction(Vector3D::new(0.0, 0.0, 1.0))); // Truck extrudes in Z
                             
                             // 3. Extrude the polygon
                             match kernel.extrude_polygon(&polygon, &extrude_params) {
@@ -771,6 +1131,13 @@ impl Runtime {
                                         // Tessellate each region independently (no boolean union)
                                         match kernel.tessellate(&solid) {
                                             Ok(mut mesh) => {
+                                                // Curved side faces of a tapered extrusion aren't merged
+                                                // by shape, so group them by profile curve
+                                                if !extrude_params.is_straight() {
+                                                    if let Some(segments) = loop_segments.get(i) {
+                                                        group_tapered_side_faces(&mut mesh, segments);
+                                                    }
+                                                }
                                                 // 5. Transform from local Z-up space to sketch plane space
                                                 for p in &mut mesh.positions {
                                                     let u = p.x;
@@ -815,6 +1182,16 @@ impl Runtime {
 
                         if loops_2d.is_empty() {
                             logs.push("Warning: No closed loops found for extrusion".to_string());
+                            let profile: Vec<_> = sketch.entities.iter().filter(|e| !e.is_construction).cloned().collect();
+                            let open: Vec<String> = crate::sketch::chains::build_chains(&profile, crate::sketch::chains::DEFAULT_CHAIN_TOLERANCE)
+                                .iter()
+                                .filter(|chain| !chain.closed)
+                                .flat_map(|chain| chain.entity_ids())
+                                .map(|id| id.to_string())
+                                .collect();
+                            if !open.is_empty() {
+                                return Err(KernelError::OpenProfile(open));
+                            }
                         }
 
                         return Ok(combined_result);
@@ -860,12 +1237,20 @@ impl Runtime {
                 let mut sketch_json: Option<String> = None;
                 let mut angle_degrees: f64 = 360.0;
                 let mut axis = "X";
+                // "Cut" (arg 4) subtracts the revolution from the body in arg 5
+                let mut operation = "Add";
+                let mut cut_target = String::new();
+                // Edge or datum axis to revolve about (arg 3), overrides `axis`
+                let ref_axis = reference_arg(call, 3, topology_manifest, logs)
+                    .and_then(|g| crate::geometry::datum::direction_from(&g));
                 
                 for (i, arg) in call.args.iter().enumerate() {
                     match (i, arg) {
                         (0, Expression::Value(Value::String(s))) => sketch_json = Some(s.clone()),
                         (1, Expression::Value(Value::Number(a))) => angle_degrees = *a,
                         (2, Expression::Value(Value::String(ax))) => axis = ax.as_str(),
+                        (4, Expression::Value(Value::String(op))) => operation = op.as_str(),
+                        (5, Expression::Variable(target)) => cut_target = target.clone(),
                         _ => {}
                     }
                 }
@@ -877,52 +1262,131 @@ impl Runtime {
                     if let Ok(mut sketch) = serde_json::from_str::<crate::sketch::types::Sketch>(&json) {
                          crate::sketch::solver::SketchSolver::solve(&mut sketch);
                          
-                         // Collect profile points from line segments
-                         let mut profile_points: Vec<Point2D> = Vec::new();
-                         // (Existing logic extracts points, but we need Point2D now)
-                         
-                         for entity in &sketch.entities {
-                             if entity.is_construction { continue; }
-                             match &entity.geometry {
-                                 crate::sketch::types::SketchGeometry::Line { start, end } => {
-                                      // Simple chaining logic 
-                                      if profile_points.is_empty() {
-                                          profile_points.push(Point2D::new(start[0], start[1]));
-                                          profile_points.push(Point2D::new(end[0], end[1]));
-                                      } else {
-                                          let last = profile_points.last().unwrap();
-                                          if (last.x - start[0]).abs() < 1e-6 && (last.y - start[1]).abs() < 1e-6 {
-                                              profile_points.push(Point2D::new(end[0], end[1]));
-                                          } else {
-                                              // Disconnected? Start new chain?
-                                              // Truck requires a single closed wire for now.
-                                              // For now, let's just append and hope it's connected or single chain.
-                                              profile_points.push(Point2D::new(start[0], start[1]));
-                                              profile_points.push(Point2D::new(end[0], end[1]));
-                                          }
-                                      }
-                                 },
-                                 _ => {}
+                         // Profile: the largest closed chain, or a lone open chain (closed by the wire builder)
+                         use crate::sketch::chains::{build_chains, find_branch_points, DEFAULT_CHAIN_TOLERANCE};
+                         if let Some(branch) = find_branch_points(&sketch.entities, DEFAULT_CHAIN_TOLERANCE).first() {
+                             return Err(KernelError::FeatureError(format!(
+                                 "Revolve profile is ambiguous: {} curves meet at ({:.3}, {:.3})",
+                                 branch.entity_ids.len(), branch.position[0], branch.position[1])));
+                         }
+                         let chains = build_chains(&sketch.entities, DEFAULT_CHAIN_TOLERANCE);
+                         let closed: Vec<(&crate::sketch::chains::Chain, Vec<[f64; 2]>)> = chains.iter()
+                             .filter(|c| c.closed)
+                             .map(|c| (c, c.points(self.tessellation_segments)))
+                             .collect();
+                         // Closed chains inside another are its voids, as in the region find_regions
+                         // reports, so a partial revolution's caps are rings rather than discs
+                         use crate::geometry::utils_2d::{point_in_polygon, polygon_area, polygon_signed_area};
+                         let inside = |inner: &[[f64; 2]], outer: &[[f64; 2]]| {
+                             !std::ptr::eq(inner, outer) && inner.first().is_some_and(|p| point_in_polygon(*p, outer))
+                         };
+                         let (outer, inner): (Vec<_>, Vec<_>) = closed.iter()
+                             .partition(|(_, points)| !closed.iter().any(|(_, other)| inside(points, other)));
+                         if outer.len() > 1 {
+                             logs.push(format!("Warning: Revolve uses the largest of {} closed profiles", outer.len()));
+                         }
+                         let (profile_chain, profile) = outer.into_iter()
+                             .max_by(|a, b| polygon_area(&a.1).total_cmp(&polygon_area(&b.1)))
+                             .map(|(chain, points)| (*chain, points.clone()))
+                             .or_else(|| match chains.as_slice() {
+                                 [only] => Some((only, only.points(self.tessellation_segments))),
+                                 _ => None,
+                             })
+                             .map_or((None, Vec::new()), |(chain, points)| (Some(chain), points));
+                         // Only the outermost loops inside the profile; islands within those are separate solids
+                         let voids: Vec<&(&crate::sketch::chains::Chain, Vec<[f64; 2]>)> = inner.iter().copied()
+                             .filter(|(_, points)| inside(points, &profile))
+                             .filter(|(_, points)| !inner.iter().any(|(_, other)| inside(points, other)))
+                             .collect();
+                         let profile_curves: Vec<_> = profile_chain.into_iter()
+                             .chain(voids.iter().map(|(chain, _)| *chain))
+                             .flat_map(|chain| chain.segments.iter().map(|s| s.geometry().clone()))
+                             .collect();
+                         // Kernel faces want the outer loop counter-clockwise and holes clockwise
+                         let oriented = |points: &[[f64; 2]], ccw: bool| -> Vec<Point2D> {
+                             let reverse = (polygon_signed_area(points) > 0.0) != ccw;
+                             let mut points: Vec<Point2D> = points.iter().map(|p| Point2D::new(p[0], p[1])).collect();
+                             if reverse {
+                                 points.reverse();
                              }
+                             points
+                         };
+                         let profile_points = oriented(&profile, true);
+                         let profile_polygon = Polygon2D::with_holes(profile_points.clone(), voids.iter().map(|(_, points)| oriented(points, false)).collect());
+                         if !voids.is_empty() {
+                             logs.push(format!("Revolve profile has {} void(s)", voids.len()));
+                         }
+
+                         // Revolving a profile that straddles the axis self-intersects
+                         if let Some(message) = profile_crossing_axis(&profile_points, ref_axis.as_ref().map(|(origin, dir)| {
+                             let plane = &sketch.plane;
+                             let rel = origin - plane.origin;
+                             ([rel.dot(&plane.x_axis), rel.dot(&plane.y_axis)], [dir.dot(&plane.x_axis), dir.dot(&plane.y_axis)])
+                         }), axis) {
+                             return Err(KernelError::FeatureError(message));
                          }
 
-                         let axis_enum = match axis {
-                             "X" => kernel::RevolveAxis::X,
-                             "Y" => kernel::RevolveAxis::Y,
-                             "Z" => kernel::RevolveAxis::Z,
+                         let axis_enum = match (ref_axis, axis) {
+                             // The profile is revolved in sketch coordinates, so express the axis there
+                             (Some((origin, dir)), _) => {
+                                 let plane = &sketch.plane;
+                                 let rel = origin - plane.origin;
+                                 kernel::RevolveAxis::Custom {
+                                     origin: kernel::Point3D::new(rel.dot(&plane.x_axis), rel.dot(&plane.y_axis), 0.0),
+                                     direction: kernel::Vector3D::new(dir.dot(&plane.x_axis), dir.dot(&plane.y_axis), 0.0),
+                                 }
+                             }
+                             (None, "X") => kernel::RevolveAxis::X,
+                             (None, "Y") => kernel::RevolveAxis::Y,
+                             (None, "Z") => kernel::RevolveAxis::Z,
                              _ => kernel::RevolveAxis::X,
                          };
                          
+                         use crate::geometry::revolution::{classify_revolved_faces, RevolutionAxis};
+                         use crate::geometry::{Point3, Vector3};
+                         let revolution_axis = match axis_enum {
+                             kernel::RevolveAxis::X => RevolutionAxis::new(Point3::origin(), Vector3::x()),
+                             kernel::RevolveAxis::Y => RevolutionAxis::new(Point3::origin(), Vector3::y()),
+                             kernel::RevolveAxis::Z => RevolutionAxis::new(Point3::origin(), Vector3::z()),
+                             kernel::RevolveAxis::Custom { origin, direction } => RevolutionAxis::new(
+                                 Point3::new(origin.x, origin.y, origin.z),
+                                 Vector3::new(direction.x, direction.y, direction.z),
+                             ),
+                         };
+
                          let params = kernel::RevolveParams {
                              angle: angle_degrees.to_radians(),
                              axis: axis_enum,
                          };
                          
-                         match kernel.revolve_profile(&profile_points, &params) {
+                         match kernel.revolve_profile(&profile_polygon, &params) {
+                             Ok(tool) if operation == "Cut" => {
+                                 let Some((target, transform)) = solid_map.get(&cut_target) else {
+                                     return Err(KernelError::FeatureError(format!("Revolve cut has no body to cut ({})", cut_target)));
+                                 };
+                                 // Truck's Boolean can't intersect the revolved surfaces every revolve
+                                 // has (it retries for minutes before giving up), so the meshes are cut.
+                                 // The result is a mesh only: later features get no solid from it.
+                                 let tessellated = |solid: &Solid| kernel.tessellate(solid)
+                                     .map_err(|e| KernelError::FeatureError(format!("Revolve cut failed: {}", e)));
+                                 let mut mesh = kernel::mesh_boolean::subtract(&tessellated(target)?, &tessellated(&tool)?);
+                                 if !is_assignment {
+                                     // Back to model space with the target's placement
+                                     transform.place_mesh(&mut mesh);
+                                     let first_triangle = tessellation.triangle_ids.len();
+                                     kernel.mesh_to_tessellation(&mesh, tessellation, topology_manifest, &ctx, "RevolveCut");
+                                     register_feature_body(tessellation, topology_manifest, first_triangle);
+                                     logs.push(format!("Cut revolution from {}", cut_target));
+                                 } else {
+                                     logs.push(format!("Warning: Revolve cut of {} has no solid for the feature consuming it", cut_target));
+                                 }
+                                 return Ok(None);
+                             }
                              Ok(solid) => {
                                  if !is_assignment {
                                      match kernel.tessellate(&solid) {
                                          Ok(mesh) => {
+                                             let first_triangle = tessellation.triangle_ids.len();
                                              kernel.mesh_to_tessellation(
                                                  &mesh,
                                                  tessellation,
@@ -930,6 +1394,9 @@ impl Runtime {
                                                  &ctx,
                                                  "Revolve"
                                              );
+                                             // Faces swept by a single profile curve are exact planes, cylinders, cones, spheres or tori
+                                             classify_revolved_faces(tessellation, first_triangle, topology_manifest,
+                                                 &profile_curves, &revolution_axis, self.tessellation_segments);
                                              logs.push("Created revolution using Truck kernel".to_string());
                                          }
                                          Err(e) => logs.push(format!("Tessellation failed: {:?}", e)),
@@ -1076,6 +1543,8 @@ impl Runtime {
                 
                 let mut input_solid_var = String::new();
                 let mut radius = 0.0;
+                let mut radius_end = None;
+                let mut continuity = crate::geometry::blend::Continuity::G1;
                 let mut edges: Vec<String> = Vec::new();
                 
                 for (i, arg) in call.args.iter().enumerate() {
@@ -1087,15 +1556,47 @@ impl Runtime {
                              edges = arr.iter().filter_map(|v| {
                                  if let Value::String(s) = v { Some(s.clone()) } else { None }
                              }).collect();
+                        },
+                        (3, Expression::Value(Value::Number(r))) => radius_end = Some(*r),
+                        (4, Expression::Value(Value::String(c))) => match crate::geometry::blend::Continuity::from_param(c) {
+                            Some(c) => continuity = c,
+                            None => logs.push(format!("Warning: Unknown fillet continuity '{}', using G1", c)),
                         },
                          _ => {}
                     }
                 }
-                
-                logs.push(format!("INFO: Fillet operation skipped - Truck CAD kernel v0.6 does not support fillet/edge rounding operations. \
-                    Parameters saved: Input={}, Radius={:.2}mm, Edges={:?}. Feature will apply when kernel support is added.", 
-                    input_solid_var, radius, edges));
-                
+                let radius_end = radius_end.unwrap_or(radius);
+                logs.push(format!("Fillet: input={}, radius={:.2} to {:.2}, continuity={:?}, edges={}",
+                    input_solid_var, radius, radius_end, continuity, edges.len()));
+
+                use crate::geometry::blend::{blend_chains, chain_face_id, tessellate_chain, BlendEdge, BlendSpec};
+                let mut blend_edges = Vec::new();
+                for edge in &edges {
+                    let topo_id = serde_json::from_str::<TopoId>(edge)
+                        .map_err(|e| KernelError::FeatureError(format!("Invalid fillet edge: {}", e)))?;
+                    blend_edges.push(BlendEdge::from_tessellation(tessellation, topo_id).map_err(KernelError::FeatureError)?);
+                }
+                // Consumed fillets add no faces, like any other feature
+                if is_assignment {
+                    return Ok(None);
+                }
+                let ctx = NamingContext::new(id);
+                let spec = BlendSpec { radius_start: radius, radius_end, continuity, segments: (self.tessellation_segments / 4).max(2) };
+                for chain in blend_chains(&blend_edges) {
+                    let face = chain_face_id(&ctx, &chain);
+                    tessellate_chain(tessellation, &chain, &spec, face).map_err(KernelError::FeatureError)?;
+                    // A constant circular blend is a cylinder; anything else has no analytic surface here
+                    let geometry = if radius_end == radius && continuity == crate::geometry::blend::Continuity::G1 {
+                        let [n1, n2] = chain[0].normals;
+                        let axis_start = chain[0].start - (n1 + n2) * (radius / (1.0 + n1.dot(&n2)));
+                        let axis_dir = (chain[chain.len() - 1].end - chain[0].start).normalize();
+                        AnalyticGeometry::Cylinder { axis_start: axis_start.into(), axis_dir: axis_dir.into(), radius }
+                    } else {
+                        AnalyticGeometry::Mesh
+                    };
+                    topology_manifest.insert(face, KernelEntity { id: face, geometry });
+                }
+
                 Ok(None)
             }
             "chamfer" => {
@@ -1136,6 +1637,9 @@ impl Runtime {
                 let mut direction: [f64; 3] = [1.0, 0.0, 0.0];
                 let mut count: i32 = 3;
                 let mut spacing: f64 = 10.0;
+                // Edge, datum axis or plane normal to pattern along (arg 4), overrides `direction`
+                let ref_direction = reference_arg(call, 4, topology_manifest, logs)
+                    .and_then(|g| crate::geometry::datum::direction_from(&g));
                 
                 for (i, arg) in call.args.iter().enumerate() {
                     match (i, arg) {
@@ -1152,6 +1656,9 @@ impl Runtime {
                         _ => {}
                     }
                 }
+                if let Some((_, dir)) = ref_direction {
+                    direction = [dir.x, dir.y, dir.z];
+                }
                 
                 logs.push(format!("Linear pattern: source={}, direction={:?}, count={}, spacing={}", 
                     source_var, direction, count, spacing));
@@ -1278,6 +1785,9 @@ impl Runtime {
                 let mut center: [f64; 3] = [0.0, 0.0, 0.0];
                 let mut count: i32 = 6;
                 let mut angle_span: f64 = 360.0;
+                // Edge or datum axis to pattern around (arg 5), overrides `axis` and `center`
+                let ref_axis = reference_arg(call, 5, topology_manifest, logs)
+                    .and_then(|g| crate::geometry::datum::direction_from(&g));
                 
                 for (i, arg) in call.args.iter().enumerate() {
                     match (i, arg) {
@@ -1308,9 +1818,13 @@ impl Runtime {
                     let ctx = NamingContext::new(id);
                     
                     // Axis vector
-                    let axis_vec: [f64; 3] = match axis {
-                        "X" => [1.0, 0.0, 0.0],
-                        "Y" => [0.0, 1.0, 0.0],
+                    let axis_vec: [f64; 3] = match (ref_axis, axis) {
+                        (Some((origin, dir)), _) => {
+                            center = [origin.x, origin.y, origin.z];
+                            [dir.x, dir.y, dir.z]
+                        }
+                        (None, "X") => [1.0, 0.0, 0.0],
+                        (None, "Y") => [0.0, 1.0, 0.0],
                         _ => [0.0, 0.0, 1.0], // Z default
                     };
                     
@@ -1417,12 +1931,161 @@ impl Runtime {
                 
                 Ok(None)
             }
+            "delete_face" => {
+                // Defeaturing on the tessellated result: remove faces and heal the gap
+                // Args: [face TopoId json strings], heal mode ("Cap" | "ExtendNeighbors")
+                use crate::geometry::defeature::{delete_faces, HealMode};
+
+                let id = generator.next_id();
+                modified.push(id);
+
+                let mut faces: Vec<crate::topo::naming::TopoId> = Vec::new();
+                let mut mode = HealMode::Cap;
+                for (i, arg) in call.args.iter().enumerate() {
+                    match (i, arg) {
+                        (0, Expression::Value(Value::Array(arr))) => {
+                            for v in arr {
+                                if let Value::String(s) = v {
+                                    match serde_json::from_str(s) {
+                                        Ok(topo_id) => faces.push(topo_id),
+                                        Err(e) => logs.push(format!("DeleteFace: ignoring invalid face id {}: {}", s, e)),
+                                    }
+                                }
+                            }
+                        },
+                        (1, Expression::Value(Value::String(s))) if s == "ExtendNeighbors" => {
+                            mode = HealMode::ExtendNeighbors;
+                        },
+                        _ => {}
+                    }
+                }
+
+                let report = delete_faces(tessellation, &faces, mode);
+                for face in &report.healed_faces {
+                    topology_manifest.remove(face);
+                }
+                for patch_id in &report.patch_ids {
+                    // Caps are planar by construction; record the plane from the patch triangles
+                    if let Some(t) = tessellation.triangle_ids.iter().position(|tid| tid == patch_id) {
+                        let v = |k: usize| {
+                            let i = tessellation.indices[t * 3 + k] as usize * 3;
+                            Point3::new(tessellation.vertices[i] as f64, tessellation.vertices[i + 1] as f64, tessellation.vertices[i + 2] as f64)
+                        };
+                        let (a, b, c) = (v(0), v(1), v(2));
+                        let n = (b - a).cross(&(c - a)).normalize();
+                        topology_manifest.insert(*patch_id, KernelEntity {
+                            id: *patch_id,
+                            geometry: AnalyticGeometry::Plane { origin: [a.x, a.y, a.z], normal: [n.x, n.y, n.z] },
+                        });
+                    }
+                }
+                logs.push(format!("DeleteFace: healed {} face(s) with {:?}", report.healed_faces.len(), mode));
+                for err in &report.errors {
+                    logs.push(format!("DeleteFace error: face {} ({:?}): {}", err.face.local_id, err.face.feature_id, err.reason));
+                }
+
+                if report.healed_faces.is_empty() && !report.errors.is_empty() {
+                    let reasons: Vec<String> = report.errors.iter()
+                        .map(|e| format!("face {}: {}", e.face.local_id, e.reason))
+                        .collect();
+                    return Err(KernelError::RuntimeError(format!("Delete face failed: {}", reasons.join("; "))));
+                }
+                Ok(None)
+            }
+            "datum_plane" | "datum_axis" => {
+                // Args: definition JSON, display size, last evaluated geometry JSON (may be empty)
+                use crate::geometry::datum;
+
+                let id = generator.next_id();
+                modified.push(id);
+                let is_plane = call.function == "datum_plane";
+                let topo_id = TopoId::new(id, 0, if is_plane { TopoRank::Face } else { TopoRank::Edge });
+
+                let string_arg = |i: usize| match call.args.get(i) {
+                    Some(Expression::Value(Value::String(s))) => s.as_str(),
+                    _ => "",
+                };
+                let size = match call.args.get(1) {
+                    Some(Expression::Value(Value::Number(n))) if *n > 0.0 => *n,
+                    _ => datum::DEFAULT_DATUM_SIZE,
+                };
+
+                let resolved = if is_plane {
+                    serde_json::from_str::<datum::DatumPlaneDef>(string_arg(0))
+                        .map_err(|e| format!("Invalid datum plane definition: {}", e))
+                        .and_then(|def| datum::resolve_plane(&def, topology_manifest))
+                } else {
+                    serde_json::from_str::<datum::DatumAxisDef>(string_arg(0))
+                        .map_err(|e| format!("Invalid datum axis definition: {}", e))
+                        .and_then(|def| datum::resolve_axis(&def, topology_manifest, size))
+                };
+
+                // A datum whose sources are gone keeps its last evaluated placement
+                let geometry = match resolved {
+                    Ok(geometry) => geometry,
+                    Err(e) => match serde_json::from_str::<AnalyticGeometry>(string_arg(2)) {
+                        Ok(cached) => {
+                            logs.push(format!("Warning: Datum {} using last evaluated definition: {}", id, e));
+                            cached
+                        }
+                        Err(_) => {
+                            logs.push(format!("Datum {} failed: {}", id, e));
+                            return Ok(None);
+                        }
+                    },
+                };
+
+                topology_manifest.insert(topo_id, KernelEntity { id: topo_id, geometry: geometry.clone() });
+                datum::tessellate_datum(tessellation, &geometry, topo_id, size);
+                logs.push(format!("Created datum {} with ID {}", if is_plane { "plane" } else { "axis" }, id));
+                Ok(None)
+            }
             "sphere" => {
                 let id = generator.next_id();
                 modified.push(id);
                 logs.push(format!("Created sphere with ID {}", id));
                 Ok(None)
             }
+            "torus" => {
+                // Args: major radius, minor radius. Centered on the origin around Z.
+                let id = generator.next_id();
+                modified.push(id);
+                let number_arg = |i: usize| match call.args.get(i) {
+                    Some(Expression::Value(Value::Number(n))) => *n,
+                    _ => 0.0,
+                };
+                let (major_radius, minor_radius) = (number_arg(0), number_arg(1));
+                if minor_radius <= 0.0 || major_radius <= minor_radius {
+                    return Err(KernelError::FeatureError(format!(
+                        "Torus needs 0 < minor radius < major radius (got {} and {})", minor_radius, major_radius
+                    )));
+                }
+                let first_triangle = tessellation.triangle_ids.len();
+                add_torus(tessellation, topology_manifest, &NamingContext::new(id), major_radius, minor_radius, self.tessellation_segments);
+                register_feature_body(tessellation, topology_manifest, first_triangle);
+                logs.push(format!("Created torus with ID {}", id));
+                Ok(None)
+            }
+            "cylinder" => {
+                // Args: radius, height. Base centered on the origin, rising along Z.
+                let id = generator.next_id();
+                modified.push(id);
+                let number_arg = |i: usize| match call.args.get(i) {
+                    Some(Expression::Value(Value::Number(n))) => *n,
+                    _ => 0.0,
+                };
+                let (radius, height) = (number_arg(0), number_arg(1));
+                if radius <= 0.0 || height <= 0.0 {
+                    return Err(KernelError::FeatureError(format!(
+                        "Cylinder radius and height must be positive (got {} and {})", radius, height
+                    )));
+                }
+                let first_triangle = tessellation.triangle_ids.len();
+                add_cylinder(tessellation, topology_manifest, &NamingContext::new(id), radius, height, self.tessellation_segments);
+                register_feature_body(tessellation, topology_manifest, first_triangle);
+                logs.push(format!("Created cylinder with ID {}", id));
+                Ok(None)
+            }
             "error" => {
                 Err(KernelError::RuntimeError("Forced error".into()))
             }
@@ -1433,6 +2096,261 @@ impl Runtime {
     }
 }
 
+/// Check that a revolve profile lies on one side of its axis, given either a custom axis
+/// (origin and direction in sketch coordinates) or a named one. Points on the axis are
+/// allowed. Returns a description of the problem if the profile crosses the axis.
+/// The Z axis is perpendicular to the profile plane, so any profile is valid for it.
+fn profile_crossing_axis(profile: &[Point2D], custom: Option<([f64; 2], [f64; 2])>, axis: &str) -> Option<String> {
+    let (origin, dir, name) = match (custom, axis) {
+        (Some((origin, dir)), _) => (origin, dir, "the revolve axis".to_string()),
+        (None, "Z") => return None,
+        (None, "Y") => ([0.0, 0.0], [0.0, 1.0], "the Y axis".to_string()),
+        (None, _) => ([0.0, 0.0], [1.0, 0.0], "the X axis".to_string()),
+    };
+    let len = (dir[0] * dir[0] + dir[1] * dir[1]).sqrt();
+    if len < 1e-12 {
+        // Axis parallel to the sketch normal: nothing to straddle
+        return None;
+    }
+    // Signed distance of each point from the axis line
+    let side = |p: &Point2D| (dir[0] * (p.y - origin[1]) - dir[1] * (p.x - origin[0])) / len;
+    let tolerance = 1e-6;
+    let positive = profile.iter().any(|p| side(p) > tolerance);
+    let negative = profile.iter().any(|p| side(p) < -tolerance);
+    (positive && negative).then(|| format!(
+        "Revolve profile crosses {}; all profile points must lie on one side of the axis", name
+    ))
+}
+
+/// The sketch entity each edge of a profile's loops was drawn from, matched
+/// by position, so the side faces of an extrude can be grouped per entity
+/// Give each side face of a tapered or twisted extrusion the face id of the
+/// first segment cut from the same circle, arc or ellipse, so every profile
+/// curve becomes one face. The kernel numbers the faces bottom, sides in
+/// segment order (exterior loop, then holes), top.
+fn group_tapered_side_faces(mesh: &mut TriangleMesh, segments: &[Vec<ProfileSegment>]) {
+    let mut first_face: HashMap<&str, u32> = HashMap::new();
+    let mut remap: HashMap<u32, u32> = HashMap::new();
+    let sides = segments.iter().filter(|l| l.len() >= 3).flatten();
+    for (face, segment) in (1u32..).zip(sides) {
+        let entity_id = match &segment.source {
+            ProfileSegmentSource::Circle { entity_id, .. }
+            | ProfileSegmentSource::Arc { entity_id, .. }
+            | ProfileSegmentSource::Ellipse { entity_id, .. } => entity_id.as_str(),
+            ProfileSegmentSource::Line { .. } | ProfileSegmentSource::Unknown => continue,
+        };
+        remap.insert(face, *first_face.entry(entity_id).or_insert(face));
+    }
+    for face_id in &mut mesh.face_ids {
+        if let Some(first) = remap.get(face_id) {
+            *face_id = *first;
+        }
+    }
+    mesh.exact_faces = true;
+}
+
+fn profile_segments(profile_loops: &[Vec<[f64; 2]>], entities: &[crate::sketch::types::SketchEntity]) -> Vec<Vec<ProfileSegment>> {
+    const EPSILON: f64 = 1e-4;
+    let mut profile_segs = Vec::new();
+    for loop_pts in profile_loops {
+        let mut segments = Vec::new();
+        let len = loop_pts.len();
+        if len > 0 {
+            for i in 0..len {
+                let p1 = loop_pts[i];
+                // Handle closed loop wrapping
+                let p2 = loop_pts[(i + 1) % len];
+
+                let mut source = ProfileSegmentSource::Unknown;
+
+                // Try to match against sketch entities
+                for entity in entities {
+                    match &entity.geometry {
+                        crate::sketch::types::SketchGeometry::Circle { center, radius } => {
+                            let d1 = ((p1[0]-center[0]).powi(2) + (p1[1]-center[1]).powi(2)).sqrt();
+                            let d2 = ((p2[0]-center[0]).powi(2) + (p2[1]-center[1]).powi(2)).sqrt();
+                            if (d1 - radius).abs() < EPSILON && (d2 - radius).abs() < EPSILON {
+                                source = ProfileSegmentSource::Circle {
+                                    entity_id: entity.id.to_string(),
+                                    center: *center,
+                                    radius: *radius,
+                                };
+                                break;
+                            }
+                        },
+                        crate::sketch::types::SketchGeometry::Arc { center, radius, .. } => {
+                            let d1 = ((p1[0]-center[0]).powi(2) + (p1[1]-center[1]).powi(2)).sqrt();
+                            let d2 = ((p2[0]-center[0]).powi(2) + (p2[1]-center[1]).powi(2)).sqrt();
+                            if (d1 - radius).abs() < EPSILON && (d2 - radius).abs() < EPSILON {
+                                // Ideally check angles too, but distance is sufficient for now
+                                // to distinguish from other geometry
+                                source = ProfileSegmentSource::Arc {
+                                    entity_id: entity.id.to_string(),
+                                    center: *center,
+                                    radius: *radius,
+                                };
+                                break;
+                            }
+                        },
+                        crate::sketch::types::SketchGeometry::Line { start, end } => {
+                            // Check if points are on the line segment
+                            // Distance from point to line check
+                            // For now, if it's not a curve, we don't strictly need to group it 
+                            // unless we want single-face selection for collinear segments?
+                            // Current behavior for lines is fine (Plane).
+                        },
+                        _ => {}
+                    }
+                }
+
+                segments.push(ProfileSegment { p1, p2, source });
+            }
+        }
+        profile_segs.push(segments);
+    }
+    profile_segs
+}
+
+/// Record the solid triangles added since `first_triangle` as one body,
+/// named in the namespace of the feature that produced them
+fn register_feature_body(
+    tessellation: &mut Tessellation,
+    topology_manifest: &mut HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    first_triangle: usize,
+) -> Option<BodyId> {
+    let face = tessellation.triangle_ids[first_triangle..].iter().find(|id| !tessellation.is_reference(id))?;
+    let id = crate::topo::naming::NamingContext::new(face.feature_id).derive("Body", crate::topo::naming::TopoRank::Solid);
+    topology_manifest.insert(id, crate::topo::registry::KernelEntity {
+        id,
+        geometry: crate::topo::registry::AnalyticGeometry::Mesh,
+    });
+    tessellation.add_body_since(id, first_triangle);
+    Some(id)
+}
+
+/// Tessellate a torus around Z through the origin: `segments` rings around the
+/// axis, each its own face (TopoId "TorusRing_<i>") of `segments / 2` quads
+fn add_torus(
+    tessellation: &mut Tessellation,
+    topology_manifest: &mut HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    ctx: &crate::topo::naming::NamingContext,
+    major_radius: f64,
+    minor_radius: f64,
+    segments: usize,
+) {
+    use crate::geometry::{Point3, Vector3};
+    use std::f64::consts::TAU;
+
+    let sides = (segments / 2).max(3);
+    // Point on the surface and its normal, `u` around the axis and `v` around the tube
+    let sample = |u: usize, v: usize| {
+        let (u, v) = (TAU * u as f64 / segments as f64, TAU * v as f64 / sides as f64);
+        let normal = Vector3::new(v.cos() * u.cos(), v.cos() * u.sin(), v.sin());
+        let point = Point3::new(major_radius * u.cos(), major_radius * u.sin(), 0.0) + normal * minor_radius;
+        (point, normal)
+    };
+    let group = tessellation.new_smoothing_group();
+    for ring in 0..segments {
+        let face = ctx.derive(&format!("TorusRing_{}", ring), crate::topo::naming::TopoRank::Face);
+        topology_manifest.insert(face, crate::topo::registry::KernelEntity {
+            id: face,
+            geometry: crate::topo::registry::AnalyticGeometry::Torus {
+                center: [0.0, 0.0, 0.0],
+                axis: [0.0, 0.0, 1.0],
+                major_radius,
+                minor_radius,
+            },
+        });
+        for side in 0..sides {
+            let [a, b, c, d] = [(ring, side), (ring + 1, side), (ring + 1, side + 1), (ring, side + 1)].map(|(u, v)| sample(u, v));
+            tessellation.add_smooth_triangle([a.0, b.0, c.0], [a.1, b.1, c.1], face, group);
+            tessellation.add_smooth_triangle([a.0, c.0, d.0], [a.1, c.1, d.1], face, group);
+        }
+    }
+}
+
+/// Tessellate a cylinder around Z standing on the origin: the caps
+/// ("CylinderBottom", "CylinderTop") and one smooth side face ("CylinderSide")
+fn add_cylinder(
+    tessellation: &mut Tessellation,
+    topology_manifest: &mut HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    ctx: &crate::topo::naming::NamingContext,
+    radius: f64,
+    height: f64,
+    segments: usize,
+) {
+    use crate::geometry::{Point3, Vector3};
+    use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+    use std::f64::consts::TAU;
+
+    let segments = segments.max(3);
+    let mut face = |name: &str, geometry: AnalyticGeometry| {
+        let id = ctx.derive(name, crate::topo::naming::TopoRank::Face);
+        topology_manifest.insert(id, KernelEntity { id, geometry });
+        id
+    };
+    let bottom = face("CylinderBottom", AnalyticGeometry::Plane { origin: [0.0, 0.0, 0.0], normal: [0.0, 0.0, -1.0] });
+    let top = face("CylinderTop", AnalyticGeometry::Plane { origin: [0.0, 0.0, height], normal: [0.0, 0.0, 1.0] });
+    let side = face("CylinderSide", AnalyticGeometry::Cylinder { axis_start: [0.0, 0.0, 0.0], axis_dir: [0.0, 0.0, 1.0], radius });
+
+    let normal = |i: usize| {
+        let angle = TAU * i as f64 / segments as f64;
+        Vector3::new(angle.cos(), angle.sin(), 0.0)
+    };
+    let rim = |i: usize, z: f64| Point3::new(0.0, 0.0, z) + normal(i) * radius;
+    let group = tessellation.new_smoothing_group();
+    for i in 0..segments {
+        tessellation.add_triangle(Point3::new(0.0, 0.0, 0.0), rim(i + 1, 0.0), rim(i, 0.0), bottom);
+        tessellation.add_triangle(Point3::new(0.0, 0.0, height), rim(i, height), rim(i + 1, height), top);
+        let (n0, n1) = (normal(i), normal(i + 1));
+        tessellation.add_smooth_triangle([rim(i, 0.0), rim(i + 1, 0.0), rim(i + 1, height)], [n0, n1, n1], side, group);
+        tessellation.add_smooth_triangle([rim(i, 0.0), rim(i + 1, height), rim(i, height)], [n0, n1, n0], side, group);
+    }
+}
+
+/// Tessellate and register a sketch point as a vertex. Its TopoId comes from
+/// the entity and the point's `ConstraintPoint` index, so it survives regens.
+fn add_sketch_vertex(
+    tessellation: &mut Tessellation,
+    topology_manifest: &mut HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    entity: EntityId,
+    index: u8,
+    p: crate::geometry::Point3,
+) {
+    let id = crate::sketch::types::ConstraintPoint { id: entity, index }.topo_id();
+    tessellation.add_point(p, id);
+    // Registered as a point so datums can be built from vertices
+    topology_manifest.insert(id, crate::topo::registry::KernelEntity {
+        id,
+        geometry: crate::topo::registry::AnalyticGeometry::Point { position: [p.x, p.y, p.z] },
+    });
+}
+
+/// Geometry behind an optional TopoId reference argument (serialized as a JSON string).
+/// Missing or empty arguments give None; references that don't resolve are logged.
+fn reference_arg(
+    call: &Call,
+    index: usize,
+    topology_manifest: &HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    logs: &mut Vec<String>,
+) -> Option<crate::topo::registry::AnalyticGeometry> {
+    let Some(Expression::Value(Value::String(json))) = call.args.get(index) else { return None };
+    if json.is_empty() {
+        return None;
+    }
+    let Ok(topo_id) = serde_json::from_str::<crate::topo::naming::TopoId>(json) else {
+        logs.push(format!("Warning: Invalid reference argument {} to {}", index, call.function));
+        return None;
+    };
+    match topology_manifest.get(&topo_id) {
+        Some(entity) => Some(entity.geometry.clone()),
+        None => {
+            logs.push(format!("Warning: Reference {:?} for {} not found, using stored placement", topo_id, call.function));
+            None
+        }
+    }
+}
+
 
 // NOTE: The add_mesh_to_tessellation function has been removed.
 // Mesh-to-tessellation conversion is now handled by TruckKernel::mesh_to_tessellation()
@@ -1473,6 +2391,19 @@ mod tests {
         assert_eq!(res.modified_entities.len(), 1);
     }
 
+    #[test]
+    fn test_reevaluation_with_one_generator_repeats_ids() {
+        use crate::evaluator::ast::*;
+        let runtime = Runtime::new();
+        let generator = IdGenerator::new("Test");
+        let prog = Program {
+            statements: vec![Statement::Expression(Expression::Call(Call { function: "sphere".into(), args: vec![] }))],
+        };
+        let first = runtime.evaluate(&prog, &generator).unwrap();
+        let second = runtime.evaluate(&prog, &generator).unwrap();
+        assert_eq!(first.modified_entities, second.modified_entities);
+    }
+
     #[test]
     fn test_evaluate_error() {
         use crate::evaluator::ast::*;
@@ -1489,6 +2420,18 @@ mod tests {
         
         let res = runtime.evaluate(&prog, &generator);
         assert!(res.is_err());
+
+        // Inside a feature, the feature is blamed
+        let prog = Program {
+            statements: vec![Statement::Assignment {
+                name: "feat_Broken".into(),
+                expr: Expression::Call(Call { function: "error".into(), args: vec![] }),
+            }]
+        };
+        let error = CadError::from(runtime.evaluate(&prog, &generator).unwrap_err());
+        assert_eq!(error.code, ErrorCode::RegenFailed);
+        assert_eq!(error.message, "Runtime error: Forced error");
+        assert_eq!(error.details, serde_json::json!({ "feature_id": "Broken" }));
     }
     
     #[test]
@@ -1540,8 +2483,9 @@ mod tests {
         
         // Should have 1 line (2 indices) in tessellation
         assert!(res.tessellation.line_indices.len() >= 2);
-        // 1 line = 2 line vertices + 2 point vertices (start/end) = 4 vertices * 3 coords = 12
-        assert_eq!(res.tessellation.vertices.len(), 12);
+        // 1 line = 2 line vertices + 2 point vertices (start/end) = 4 vertices,
+        // plus the origin point and 2 axis lines = 5 more; 9 vertices * 3 coords = 27
+        assert_eq!(res.tessellation.vertices.len(), 27);
     }
 
     #[test]
@@ -1579,6 +2523,343 @@ mod tests {
         assert!(res.tessellation.indices.len() >= 6, "Should have triangle indices for 3D geometry");
     }
 
+    #[test]
+    fn test_manifest_of_extruded_box() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+        use crate::topo::naming::TopoRank;
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 6.0], [0.0, 6.0]];
+        for i in 0..4 {
+            sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+        }
+        let prog = Program {
+            statements: vec![Statement::Expression(Expression::Call(Call {
+                function: "extrude".into(),
+                args: vec![
+                    Expression::Value(Value::String(serde_json::to_string(&sketch).unwrap())),
+                    Expression::Value(Value::Number(4.0)),
+                    Expression::Value(Value::String("Add".into())),
+                ],
+            }))],
+        };
+        let res = Runtime::new().evaluate(&prog, &IdGenerator::new("TestManifest")).unwrap();
+
+        let entries = res.manifest_entries();
+        let faces: Vec<_> = entries.iter().filter(|e| e.rank == TopoRank::Face).collect();
+        let edges: Vec<_> = entries.iter().filter(|e| e.rank == TopoRank::Edge).collect();
+        assert_eq!(faces.len(), 6, "{:#?}", faces);
+        assert!(faces.iter().all(|f| f.kind == "Plane" && f.params["normal"].is_array()));
+        assert_eq!(edges.len(), 12, "{:#?}", edges);
+        assert!(edges.iter().all(|e| e.kind == "Line" && e.params["start"].is_array()));
+
+        let json: serde_json::Value = serde_json::from_str(&res.manifest_json()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), entries.len());
+        assert_eq!(res.manifest_json(), res.manifest_json(), "Ordering is deterministic");
+    }
+
+    #[test]
+    fn test_delete_face_caps_extruded_box() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+        use crate::geometry::defeature::healed_patch_id;
+
+        let runtime = Runtime::new();
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [10.0, 0.0], end: [10.0, 10.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [10.0, 10.0], end: [0.0, 10.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [0.0, 10.0], end: [0.0, 0.0] });
+        let extrude = Statement::Expression(Expression::Call(Call {
+            function: "extrude".into(),
+            args: vec![
+                Expression::Value(Value::String(serde_json::to_string(&sketch).unwrap())),
+                Expression::Value(Value::Number(10.0)),
+                Expression::Value(Value::String("Add".into())),
+            ],
+        }));
+
+        let base = runtime.evaluate(&Program { statements: vec![extrude.clone()] }, &IdGenerator::new("TestDeleteFace")).expect("Extrude eval failed");
+        // Pick the planar side face at y = 0
+        let tess = &base.tessellation;
+        let side_tri = (0..tess.triangle_ids.len())
+            .find(|&t| (0..3).all(|k| tess.vertices[tess.indices[t * 3 + k] as usize * 3 + 1].abs() < 1e-4))
+            .expect("Extrude should produce a side face");
+        let face = tess.triangle_ids[side_tri];
+
+        let delete = Statement::Expression(Expression::Call(Call {
+            function: "delete_face".into(),
+            args: vec![
+                Expression::Value(Value::Array(vec![Value::String(serde_json::to_string(&face).unwrap())])),
+                Expression::Value(Value::String("Cap".into())),
+            ],
+        }));
+        let res = runtime.evaluate(&Program { statements: vec![extrude, delete] }, &IdGenerator::new("TestDeleteFace")).expect("Delete face eval failed");
+
+        assert!(!res.tessellation.triangle_ids.contains(&face), "Deleted face should be gone");
+        let patch = healed_patch_id(&face);
+        assert!(res.tessellation.triangle_ids.contains(&patch), "Cap patch should be added");
+        assert!(res.topology_manifest.contains_key(&patch));
+        assert!(!res.topology_manifest.contains_key(&face));
+    }
+
+    #[test]
+    fn test_box_feature_takes_its_size() {
+        use crate::features::dag::FeatureGraph;
+        use crate::features::types::{Feature, FeatureType, ParameterValue};
+
+        let mut graph = FeatureGraph::new();
+        graph.add_node(Feature::new("Box1", FeatureType::Box)
+            .with_param("width", ParameterValue::Float(20.0))
+            .with_param("height", ParameterValue::Float(5.0))
+            .with_param("depth", ParameterValue::Float(8.0)));
+        let res = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("TestBox")).unwrap();
+        let bounds = crate::analysis::mesh_bounds(&res.tessellation).expect("The box is tessellated");
+        let size = bounds.max - bounds.min;
+        assert!((size.x - 20.0).abs() < 1e-5 && (size.y - 5.0).abs() < 1e-5 && (size.z - 8.0).abs() < 1e-5, "{:?}", size);
+        assert_eq!(res.tessellation.body_ids().len(), 1);
+    }
+
+    #[test]
+    fn test_extrude_taper_and_twist() {
+        use crate::features::dag::FeatureGraph;
+        use crate::features::types::{Feature, FeatureType, ParameterValue};
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+        // Faces of a 10 high extrude of `geometry`, with the top cap's vertex bounds
+        let extrude = |geometry: Vec<SketchGeometry>, params: &[(&str, f64)]| {
+            let mut sketch = Sketch::new(SketchPlane::default());
+            for g in geometry {
+                sketch.add_entity(g);
+            }
+            let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+            let mut extrude = Feature::new("Extrude1", FeatureType::Extrude).with_param("distance", ParameterValue::Float(10.0));
+            for (name, value) in params {
+                extrude = extrude.with_param(name, ParameterValue::Float(*value));
+            }
+            extrude.dependencies.push(sketch_feature.id);
+            let mut graph = FeatureGraph::new();
+            graph.add_node(sketch_feature);
+            graph.add_node(extrude);
+            let res = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("TestTaper")).unwrap();
+            assert!(res.feature_errors.is_empty(), "{:?}", res.feature_errors);
+
+            let t = &res.tessellation;
+            let faces: std::collections::HashSet<_> = t.triangle_ids.iter().copied().collect();
+            let corner = |v: u32| [t.vertices[v as usize * 3], t.vertices[v as usize * 3 + 1], t.vertices[v as usize * 3 + 2]];
+            let top = t.indices.chunks(3).position(|tri| tri.iter().all(|&v| (corner(v)[2] - 10.0).abs() < 1e-4)).expect("Top cap");
+            let top_id = t.triangle_ids[top];
+            let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+            for (tri, id) in t.indices.chunks(3).zip(&t.triangle_ids) {
+                for p in tri.iter().filter(|_| *id == top_id).map(|&v| corner(v)) {
+                    min = [min[0].min(p[0]), min[1].min(p[1])];
+                    max = [max[0].max(p[0]), max[1].max(p[1])];
+                }
+            }
+            (faces.len(), top_id, min, max)
+        };
+        let square: Vec<SketchGeometry> = {
+            let c = [[-5.0, -5.0], [5.0, -5.0], [5.0, 5.0], [-5.0, 5.0]];
+            (0..4).map(|i| SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] }).collect()
+        };
+
+        // Turned 45°, the corners of the top cap sit on the axes
+        let (faces, _, min, max) = extrude(square.clone(), &[("twist_degrees", 45.0)]);
+        assert_eq!(faces, 6, "4 sides and 2 caps");
+        let half_diagonal = 50f32.sqrt();
+        for v in [min[0], min[1], -max[0], -max[1]] {
+            assert!((v + half_diagonal).abs() < 1e-3, "{:?} {:?}", min, max);
+        }
+
+        let (faces, _, min, max) = extrude(square.clone(), &[("taper_x", 0.5), ("taper_y", 0.2)]);
+        assert_eq!(faces, 6);
+        assert!((min[0] + 2.5).abs() < 1e-4 && (max[1] - 1.0).abs() < 1e-4, "{:?} {:?}", min, max);
+
+        // The caps keep their ids whatever the taper (each run is a new feature, so compare within it)
+        let (_, straight_top, ..) = extrude(square.clone(), &[]);
+        let (_, tapered_top, ..) = extrude(square.clone(), &[("taper", 0.5), ("twist_degrees", 30.0)]);
+        assert_eq!(straight_top.local_id, tapered_top.local_id);
+
+        // A twisted circle still has one side face
+        let (faces, ..) = extrude(vec![SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 }], &[("taper", 0.5), ("twist_degrees", 20.0)]);
+        assert_eq!(faces, 3);
+
+        let sketch_error = |params: &[(&str, f64)]| {
+            let mut sketch = Sketch::new(SketchPlane::default());
+            sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 });
+            let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+            let mut extrude = Feature::new("Extrude1", FeatureType::Extrude);
+            for (name, value) in params {
+                extrude = extrude.with_param(name, ParameterValue::Float(*value));
+            }
+            extrude.dependencies.push(sketch_feature.id);
+            let mut graph = FeatureGraph::new();
+            graph.add_node(sketch_feature);
+            graph.add_node(extrude);
+            Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("TestTaper")).unwrap().feature_errors.len()
+        };
+        assert_eq!(sketch_error(&[("taper", 0.0)]), 1);
+        assert_eq!(sketch_error(&[("twist_degrees", 120.0)]), 1);
+    }
+
+    #[test]
+    fn test_extrude_of_open_profile_reports_its_free_curves() {
+        use crate::features::dag::FeatureGraph;
+        use crate::features::types::{Feature, FeatureType, ParameterValue};
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let a = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let b = sketch.add_entity(SketchGeometry::Line { start: [10.0, 0.0], end: [10.0, 10.0] });
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude).with_param("distance", ParameterValue::Float(10.0));
+        extrude.dependencies.push(sketch_feature.id);
+        let extrude_id = extrude.id.to_string();
+        let mut graph = FeatureGraph::new();
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+
+        let res = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("TestOpen")).unwrap();
+        assert_eq!(res.feature_errors.len(), 1);
+        assert_eq!(res.feature_errors[0].feature_id, extrude_id);
+        let cause = res.feature_errors[0].cause.as_ref().expect("Open profiles carry a coded cause");
+        assert_eq!(cause.code, ErrorCode::OpenProfile);
+        assert!(cause.check_details().is_ok());
+        let mut free: Vec<String> = serde_json::from_value(cause.details["entity_ids"].clone()).unwrap();
+        free.sort();
+        let mut expected = vec![a.to_string(), b.to_string()];
+        expected.sort();
+        assert_eq!(free, expected);
+    }
+
+    #[test]
+    fn test_fillet_blends_tangent_edges_of_an_extrude() {
+        use crate::features::dag::FeatureGraph;
+        use crate::features::types::{Feature, FeatureType, ParameterValue};
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+        // A 10 x 4 rectangle whose front side is drawn as two lines, so its top
+        // front edge comes out as two tangent edges
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for (start, end) in [([0.0, 0.0], [5.0, 0.0]), ([5.0, 0.0], [10.0, 0.0]), ([10.0, 0.0], [10.0, 4.0]), ([10.0, 4.0], [0.0, 4.0]), ([0.0, 4.0], [0.0, 0.0])] {
+            sketch.add_entity(SketchGeometry::Line { start, end });
+        }
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude).with_param("distance", ParameterValue::Float(6.0));
+        extrude.dependencies.push(sketch_feature.id);
+        let extrude_id = extrude.id;
+        let mut graph = FeatureGraph::new();
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        let runtime = Runtime::new();
+        let res = runtime.evaluate(&graph.regenerate(), &IdGenerator::new("TestFillet")).unwrap();
+
+        // Edges lying along the top front (y = 0, z = 6)
+        let t = &res.tessellation;
+        let vertex = |i: u32| [t.vertices[i as usize * 3] as f64, t.vertices[i as usize * 3 + 1] as f64, t.vertices[i as usize * 3 + 2] as f64];
+        let mut top_front: Vec<crate::topo::naming::TopoId> = t.line_indices.chunks_exact(2).zip(&t.line_ids)
+            .filter(|(pair, _)| pair.iter().all(|&i| { let p = vertex(i); p[1].abs() < 1e-4 && (p[2] - 6.0).abs() < 1e-4 }))
+            .map(|(_, id)| *id)
+            .collect();
+        top_front.sort_by_key(|id| id.local_id);
+        top_front.dedup();
+        assert_eq!(top_front.len(), 2, "The split side gives two edges");
+
+        let edges = top_front.iter().map(|id| serde_json::to_string(id).unwrap()).collect();
+        let mut fillet = Feature::new("Fillet1", FeatureType::Fillet)
+            .with_param("radius", ParameterValue::Float(1.0))
+            .with_param("radius_end", ParameterValue::Float(2.0))
+            .with_param("edges", ParameterValue::List(edges));
+        fillet.dependencies.push(extrude_id);
+        graph.add_node(fillet);
+        let res = runtime.evaluate(&graph.regenerate(), &IdGenerator::new("TestFillet")).unwrap();
+        assert!(res.feature_errors.is_empty(), "{:?}", res.feature_errors);
+
+        // One blend face over both edges, its radius running from one end of the chain to the other
+        let t = &res.tessellation;
+        let vertex = |i: u32| [t.vertices[i as usize * 3] as f64, t.vertices[i as usize * 3 + 1] as f64, t.vertices[i as usize * 3 + 2] as f64];
+        let blend_faces: std::collections::HashSet<_> = t.triangle_ids.iter()
+            .filter(|id| matches!(res.topology_manifest.get(id).map(|e| &e.geometry), Some(crate::topo::registry::AnalyticGeometry::Mesh)))
+            .filter(|id| !top_front.iter().any(|edge| edge.feature_id == id.feature_id))
+            .collect();
+        assert_eq!(blend_faces.len(), 1, "Tangent edges blend as one face");
+        let blend = **blend_faces.iter().next().unwrap();
+        let radius_at = |x: f64| {
+            let points: Vec<[f64; 3]> = t.indices.chunks_exact(3).zip(&t.triangle_ids)
+                .filter(|(_, id)| **id == blend)
+                .flat_map(|(tri, _)| tri.iter().map(|&i| vertex(i)).collect::<Vec<_>>())
+                .filter(|p| (p[0] - x).abs() < 1e-4)
+                .collect();
+            assert!(!points.is_empty(), "Blend reaches x = {}", x);
+            // Every section point touches or lies between the faces at the same radius
+            let radius = points.iter().map(|p| p[1].max(6.0 - p[2])).fold(0.0, f64::max);
+            let center = [radius, 6.0 - radius];
+            for p in &points {
+                let distance = ((p[1] - center[0]).powi(2) + (p[2] - center[1]).powi(2)).sqrt();
+                assert!((distance - radius).abs() < 1e-3, "Circular section at x = {}: {} vs {}", x, distance, radius);
+            }
+            radius
+        };
+        let (a, middle, b) = (radius_at(0.0), radius_at(5.0), radius_at(10.0));
+        let mut ends = [a, b];
+        ends.sort_by(f64::total_cmp);
+        assert!((ends[0] - 1.0).abs() < 1e-3 && (ends[1] - 2.0).abs() < 1e-3, "End radii {} and {}", a, b);
+        assert!((middle - 1.5).abs() < 1e-3, "Radius halfway {}", middle);
+    }
+
+    #[test]
+    fn test_cylinder_feature() {
+        use crate::features::dag::FeatureGraph;
+        use crate::features::types::{Feature, FeatureType, ParameterValue};
+
+        let mut graph = FeatureGraph::new();
+        graph.add_node(Feature::new("Cylinder1", FeatureType::Cylinder)
+            .with_param("radius", ParameterValue::Float(3.0))
+            .with_param("height", ParameterValue::Float(7.0)));
+        let res = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("TestCylinder")).unwrap();
+        let faces: std::collections::HashSet<_> = res.tessellation.triangle_ids.iter().collect();
+        assert_eq!(faces.len(), 3, "Two caps and one side");
+        let (min, max) = res.tessellation.body_bounds(&res.tessellation.body_ids()[0]).expect("The cylinder is a body");
+        assert!((min.x + 3.0).abs() < 1e-5 && (max.x - 3.0).abs() < 1e-5, "{:?} {:?}", min, max);
+        assert!(min.z.abs() < 1e-5 && (max.z - 7.0).abs() < 1e-5, "{:?} {:?}", min, max);
+
+        graph.add_node(Feature::new("Cylinder2", FeatureType::Cylinder).with_param("radius", ParameterValue::Float(-1.0)));
+        let res = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("TestCylinder")).unwrap();
+        assert_eq!(res.feature_errors.len(), 1);
+    }
+
+    #[test]
+    fn test_torus_feature() {
+        use crate::features::dag::FeatureGraph;
+        use crate::features::types::{Feature, FeatureType, ParameterValue};
+
+        let mut graph = FeatureGraph::new();
+        graph.add_node(Feature::new("Torus1", FeatureType::Torus)
+            .with_param("major_radius", ParameterValue::Float(10.0))
+            .with_param("minor_radius", ParameterValue::Float(2.0)));
+        let runtime = Runtime::new().with_tessellation_segments(16);
+        let res = runtime.evaluate(&graph.regenerate(), &IdGenerator::new("TestTorus")).unwrap();
+
+        // 16 rings of 8 quads, two triangles each, three vertices per triangle
+        assert_eq!(res.tessellation.vertices.len() / 3, 16 * 8 * 2 * 3);
+        let (min, max) = res.tessellation.body_bounds(&res.tessellation.body_ids()[0]).expect("The torus is a body");
+        for (low, high, extent) in [(min.x, max.x, 12.0), (min.y, max.y, 12.0), (min.z, max.z, 2.0)] {
+            assert!((low + extent).abs() < 1e-5 && (high - extent).abs() < 1e-5, "{} {} {}", low, high, extent);
+        }
+
+        let rings: std::collections::HashSet<_> = res.tessellation.triangle_ids.iter().collect();
+        assert_eq!(rings.len(), 16, "One face per ring");
+        let again = runtime.evaluate(&graph.regenerate(), &IdGenerator::new("TestTorus")).unwrap();
+        assert!(again.tessellation.triangle_ids.iter().all(|id| rings.contains(id)), "Ring ids are stable");
+
+        let mut bad = FeatureGraph::new();
+        bad.add_node(Feature::new("Torus1", FeatureType::Torus).with_param("minor_radius", ParameterValue::Float(12.0)));
+        let res = runtime.evaluate(&bad.regenerate(), &IdGenerator::new("TestTorus")).unwrap();
+        assert_eq!(res.feature_errors.len(), 1);
+    }
+
     #[test]
     fn test_revolve_with_sketch() {
         use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
@@ -1616,6 +2897,187 @@ mod tests {
         assert!(res.tessellation.indices.len() >= 6, "Should have triangle indices for 3D geometry");
     }
 
+    #[test]
+    fn test_revolve_profile_crossing_axis_is_rejected() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+
+        // Triangle straddling the X axis (y from -2 to 5)
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Line { start: [5.0, -2.0], end: [10.0, 5.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [10.0, 5.0], end: [5.0, 5.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [5.0, 5.0], end: [5.0, -2.0] });
+        let json = serde_json::to_string(&sketch).unwrap();
+
+        let revolve = |axis: &str| Program {
+            statements: vec![
+                Statement::Expression(Expression::Call(Call {
+                    function: "set_context".into(),
+                    args: vec![Expression::Value(Value::String("Revolve1".into()))],
+                })),
+                Statement::Expression(Expression::Call(Call {
+                    function: "revolve".into(),
+                    args: vec![
+                        Expression::Value(Value::String(json.clone())),
+                        Expression::Value(Value::Number(360.0)),
+                        Expression::Value(Value::String(axis.into())),
+                    ],
+                })),
+            ]
+        };
+
+        let res = Runtime::new().evaluate(&revolve("X"), &IdGenerator::new("TestRevolveCross")).unwrap();
+        assert_eq!(res.feature_errors.len(), 1);
+        assert_eq!(res.feature_errors[0].feature_id, "Revolve1");
+        assert!(res.feature_errors[0].message.contains("crosses the X axis"), "{}", res.feature_errors[0].message);
+        assert!(res.tessellation.indices.is_empty(), "No geometry for an invalid revolve");
+
+        // The same profile lies entirely on one side of the Y axis
+        let res = Runtime::new().evaluate(&revolve("Y"), &IdGenerator::new("TestRevolveCross")).unwrap();
+        assert!(res.feature_errors.is_empty());
+    }
+
+    #[test]
+    fn test_ellipse_arc_discretizes_into_partial_polyline() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+        use crate::topo::naming::{TopoId, TopoRank};
+
+        // Quarter of a 4 x 2 ellipse centered at (1, 2), from the +X to the +Y vertex
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let arc = sketch.add_entity(SketchGeometry::EllipseArc {
+            center: [1.0, 2.0], semi_major: 4.0, semi_minor: 2.0, rotation: 0.0,
+            start_angle: 0.0, end_angle: std::f64::consts::FRAC_PI_2,
+        });
+        let prog = Program {
+            statements: vec![Statement::Expression(Expression::Call(Call {
+                function: "sketch".into(),
+                args: vec![Expression::Value(Value::String(serde_json::to_string(&sketch).unwrap()))],
+            }))],
+        };
+        let res = Runtime::new().with_tessellation_segments(8).evaluate(&prog, &IdGenerator::new("TestEllipseArc")).unwrap();
+        let t = &res.tessellation;
+        let edge = TopoId::new(arc, 0, TopoRank::Edge);
+        let vertex = |i: u32| {
+            let i = i as usize * 3;
+            [t.vertices[i] as f64, t.vertices[i + 1] as f64]
+        };
+        let segments: Vec<[[f64; 2]; 2]> = t.line_ids.iter().enumerate()
+            .filter(|(_, id)| **id == edge)
+            .map(|(i, _)| [vertex(t.line_indices[2 * i]), vertex(t.line_indices[2 * i + 1])])
+            .collect();
+        assert_eq!(segments.len(), 8);
+        let close = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5;
+        assert!(close(segments[0][0], [5.0, 2.0]), "Starts at the +X vertex: {:?}", segments[0][0]);
+        assert!(close(segments[7][1], [1.0, 4.0]), "Ends at the +Y vertex: {:?}", segments[7][1]);
+        for [a, b] in &segments {
+            assert!(a[0] >= 1.0 - 1e-6 && a[1] >= 2.0 - 1e-6, "Stays in the first quadrant");
+            let on_ellipse = ((b[0] - 1.0) / 4.0).powi(2) + ((b[1] - 2.0) / 2.0).powi(2);
+            assert!((on_ellipse - 1.0).abs() < 1e-5);
+        }
+        // Center and both endpoints are selectable vertices
+        let vertices: Vec<u64> = t.point_ids.iter().filter(|id| id.feature_id == arc).map(|id| id.local_id).collect();
+        assert_eq!(vertices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_revolve_quarter_circle_registers_torus() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+        use crate::topo::registry::AnalyticGeometry;
+
+        // Quarter disc centered 5 from the Y axis: the arc sweeps a torus band,
+        // the radial line an annulus and the vertical line a cylinder
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Line { start: [5.0, 0.0], end: [7.0, 0.0] });
+        sketch.add_entity(SketchGeometry::Arc { center: [5.0, 0.0], radius: 2.0, start_angle: 0.0, end_angle: std::f64::consts::FRAC_PI_2 });
+        sketch.add_entity(SketchGeometry::Line { start: [5.0, 2.0], end: [5.0, 0.0] });
+        let prog = Program {
+            statements: vec![Statement::Expression(Expression::Call(Call {
+                function: "revolve".into(),
+                args: vec![
+                    Expression::Value(Value::String(serde_json::to_string(&sketch).unwrap())),
+                    Expression::Value(Value::Number(360.0)),
+                    Expression::Value(Value::String("Y".into())),
+                ],
+            }))],
+        };
+        let res = Runtime::new().evaluate(&prog, &IdGenerator::new("TestRevolveTorus")).unwrap();
+
+        let tori: Vec<_> = res.topology_manifest.values()
+            .filter(|e| matches!(e.geometry, AnalyticGeometry::Torus { .. }))
+            .collect();
+        assert!(!tori.is_empty(), "Kinds: {:?}", res.topology_manifest.values().map(|e| e.geometry.kind()).collect::<Vec<_>>());
+        for torus in &tori {
+            match torus.geometry {
+                AnalyticGeometry::Torus { center, axis, major_radius, minor_radius } => {
+                    assert!(center.iter().all(|c| c.abs() < 1e-9));
+                    assert_eq!(axis, [0.0, 1.0, 0.0]);
+                    assert!((major_radius - 5.0).abs() < 1e-9 && (minor_radius - 2.0).abs() < 1e-9);
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert!(res.topology_manifest.values().any(|e| matches!(e.geometry, AnalyticGeometry::Cylinder { radius, .. } if (radius - 5.0).abs() < 1e-9)));
+
+        let measured = crate::analysis::measure_entity(tori[0]);
+        assert_eq!(measured.kind, "Torus");
+        let value = |name: &str| measured.values.iter().find(|v| v.name == name).map(|v| v.value);
+        assert_eq!(value("major_radius"), Some(5.0));
+        assert_eq!(value("minor_radius"), Some(2.0));
+    }
+
+    #[test]
+    fn test_partial_revolve_of_annulus_has_ring_caps() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+
+        // Washer cross-section 10 from the Y axis, a quarter turn: both caps are rings
+        let (center, outer, inner) = ([10.0, 0.0], 3.0, 1.5);
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Circle { center, radius: outer });
+        sketch.add_entity(SketchGeometry::Circle { center, radius: inner });
+        let prog = Program {
+            statements: vec![Statement::Expression(Expression::Call(Call {
+                function: "revolve".into(),
+                args: vec![
+                    Expression::Value(Value::String(serde_json::to_string(&sketch).unwrap())),
+                    Expression::Value(Value::Number(90.0)),
+                    Expression::Value(Value::String("Y".into())),
+                ],
+            }))],
+        };
+        let res = Runtime::new().with_tessellation_segments(32).evaluate(&prog, &IdGenerator::new("TestRevolveAnnulus")).unwrap();
+        assert!(res.logs.iter().any(|l| l.contains("1 void")), "{:?}", res.logs);
+
+        let t = &res.tessellation;
+        let vertex = |i: u32| {
+            let i = i as usize * 3;
+            [t.vertices[i] as f64, t.vertices[i + 1] as f64, t.vertices[i + 2] as f64]
+        };
+        // Cap triangles lie in the sketch plane (z = 0) and, a quarter turn on, in x = 0;
+        // there the profile's x runs along -z
+        for (name, in_cap, radial) in [
+            ("start", (|p: [f64; 3]| p[2].abs() < 1e-4) as fn([f64; 3]) -> bool, (|p: [f64; 3]| p[0]) as fn([f64; 3]) -> f64),
+            ("end", |p: [f64; 3]| p[0].abs() < 1e-4, |p: [f64; 3]| -p[2]),
+        ] {
+            let mut area = 0.0;
+            for tri in t.indices.chunks_exact(3) {
+                let p = [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])];
+                if !p.iter().all(|v| in_cap(*v)) {
+                    continue;
+                }
+                let planar = p.map(|v| [radial(v), v[1]]);
+                let centroid = [(planar[0][0] + planar[1][0] + planar[2][0]) / 3.0, (planar[0][1] + planar[1][1] + planar[2][1]) / 3.0];
+                let from_center = (centroid[0] - center[0]).hypot(centroid[1] - center[1]);
+                assert!(from_center > inner * 0.95, "{} cap triangle {:?} is inside the void", name, planar);
+                area += crate::geometry::utils_2d::polygon_area(&planar);
+            }
+            let ring = std::f64::consts::PI * (outer * outer - inner * inner);
+            assert!((area - ring).abs() < 0.05 * ring, "{} cap area {} is a ring's {}", name, area, ring);
+        }
+    }
+
     #[test]
     #[ignore] // TODO: Truck boolean operations are panic-prone("This wire is not simple"). Re-enable when Truck is more stable.
     fn test_boolean_operations() {
@@ -1670,4 +3132,36 @@ mod tests {
         assert!(res.logs.iter().any(|l| l.contains("STEP Export")), "Logs should contain export output");
         assert!(res.logs.iter().any(|l| l.contains("ISO-10303-21")), "Logs should contain STEP header");
     }
+
+    #[test]
+    fn test_circle_respects_tessellation_segments() {
+        use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry};
+        use crate::evaluator::ast::*;
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let circle_id = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 });
+        let json = serde_json::to_string(&sketch).unwrap();
+        let prog = Program {
+            statements: vec![
+                Statement::Assignment {
+                    name: "s".into(),
+                    expr: Expression::Call(Call {
+                        function: "sketch".into(),
+                        args: vec![Expression::Value(Value::String(json))],
+                    })
+                }
+            ]
+        };
+
+        let circle_edges = |runtime: &Runtime| {
+            let res = runtime.evaluate(&prog, &IdGenerator::new("Segments")).expect("Should succeed");
+            res.tessellation.line_ids.iter().filter(|id| id.feature_id == circle_id).count()
+        };
+        assert_eq!(circle_edges(&Runtime::new()), 64);
+        assert_eq!(circle_edges(&Runtime::new().with_tessellation_segments(16)), 16);
+
+        // Extrude profiles come from the same discretization
+        let regions = crate::sketch::regions::find_regions_with_segments(&sketch.entities, 16);
+        assert_eq!(regions[0].boundary_points.len(), 16);
+    }
 }
@@ -0,0 +1,292 @@
+//! Names for solid bodies, and exporting bodies one file each
+//!
+//! Names live on the `FeatureGraph`, keyed by the body's stable `BodyId`. A
+//! body's id changes when an upstream edit changes how its feature splits its
+//! output (e.g. region specs added to an extrude). `migrate_body_names` moves
+//! a name to the new id when that is unambiguous and reports it otherwise.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::clipboard::topo_namespace;
+use super::dag::{FeatureGraph, FeatureState};
+use crate::evaluator::runtime::Runtime;
+use crate::geometry::tessellation::BodyId;
+use crate::topo::naming::TopoRank;
+use crate::topo::{EntityId, IdGenerator};
+
+/// A user-given body name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodyName {
+    pub body: BodyId,
+    pub name: String,
+}
+
+/// A body given by id or by name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BodyRef {
+    Id(BodyId),
+    Name(String),
+}
+
+/// Outcome of `migrate_body_names`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BodyNameMigration {
+    /// Names moved to a body's new id: (name, old id, new id)
+    pub migrated: Vec<(String, BodyId, BodyId)>,
+    /// Names whose body was replaced by several candidates. They are kept
+    /// under the old id, e.g. for an undo to bring the body back.
+    pub lost: Vec<BodyName>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Stl,
+    Step,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Stl => "stl",
+            ExportFormat::Step => "step",
+        }
+    }
+}
+
+/// One exported body
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BodyExport {
+    pub body: BodyId,
+    pub file_name: String,
+    pub content: String,
+}
+
+impl FeatureGraph {
+    /// Name `body`, replacing its earlier name. An empty name clears it.
+    /// Names are unique across bodies, since they become file names.
+    pub fn rename_body(&mut self, body: BodyId, name: &str) -> Result<(), String> {
+        if body.rank != TopoRank::Solid {
+            return Err(format!("{:?} is not a body", body));
+        }
+        let name = name.trim();
+        if self.body_names.iter().any(|n| n.name == name && n.body != body) {
+            return Err(format!("Another body is already named '{}'", name));
+        }
+        self.body_names.retain(|n| n.body != body);
+        if !name.is_empty() {
+            self.body_names.push(BodyName { body, name: name.to_string() });
+        }
+        Ok(())
+    }
+
+    pub fn body_name(&self, body: &BodyId) -> Option<&str> {
+        self.body_names.iter().find(|n| n.body == *body).map(|n| n.name.as_str())
+    }
+
+    /// Remove the names of the bodies `feature` produced, returning them
+    pub(super) fn take_body_names(&mut self, feature: EntityId) -> Vec<BodyName> {
+        let namespace = topo_namespace(feature);
+        let (taken, kept) = std::mem::take(&mut self.body_names).into_iter()
+            .partition(|n| n.body.feature_id == namespace);
+        self.body_names = kept;
+        taken
+    }
+
+    /// Carry names over to the bodies of a new evaluation. A named body that
+    /// is gone while its feature, still active, now makes exactly one unnamed
+    /// body passes its name to that body. If there are several, the name is
+    /// reported lost. Features that make no bodies at the moment (suppressed,
+    /// rolled back, consumed by a Boolean) keep their names untouched.
+    pub fn migrate_body_names(&mut self, bodies: &[BodyId]) -> BodyNameMigration {
+        let states = self.feature_states();
+        let active: Vec<EntityId> = self.nodes.keys()
+            .filter(|id| states.get(id) == Some(&FeatureState::Active))
+            .map(|id| topo_namespace(*id))
+            .collect();
+        let mut gone: HashMap<EntityId, Vec<usize>> = HashMap::new();
+        for (i, n) in self.body_names.iter().enumerate() {
+            if !bodies.contains(&n.body) && active.contains(&n.body.feature_id) {
+                gone.entry(n.body.feature_id).or_default().push(i);
+            }
+        }
+
+        let mut migration = BodyNameMigration::default();
+        let mut namespaces: Vec<EntityId> = gone.keys().copied().collect();
+        namespaces.sort();
+        for namespace in namespaces {
+            let unnamed: Vec<BodyId> = bodies.iter().copied()
+                .filter(|b| b.feature_id == namespace && self.body_name(b).is_none())
+                .collect();
+            match (gone[&namespace].as_slice(), unnamed.as_slice()) {
+                (_, []) => {}
+                (&[i], &[to]) => {
+                    let from = self.body_names[i].body;
+                    self.body_names[i].body = to;
+                    migration.migrated.push((self.body_names[i].name.clone(), from, to));
+                }
+                (names, _) => migration.lost.extend(names.iter().map(|&i| self.body_names[i].clone())),
+            }
+        }
+        migration
+    }
+
+    /// The body `body` refers to, if it is one of `bodies`
+    pub fn resolve_body(&self, body: &BodyRef, bodies: &[BodyId]) -> Option<BodyId> {
+        let id = match body {
+            BodyRef::Id(id) => *id,
+            BodyRef::Name(name) => self.body_names.iter().find(|n| n.name == *name)?.body,
+        };
+        bodies.contains(&id).then_some(id)
+    }
+
+    /// File name for `body`: its name with unsafe characters replaced, or
+    /// its id for unnamed bodies
+    pub fn body_file_name(&self, body: &BodyId, format: ExportFormat) -> String {
+        let stem = match self.body_name(body) {
+            Some(name) => name.chars()
+                .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ' | '.') { c } else { '_' })
+                .collect(),
+            None => format!("body_{:016x}", body.local_id),
+        };
+        format!("{}.{}", stem, format.extension())
+    }
+
+    /// Regenerate and export `bodies`, or every body when empty, one file each
+    pub fn export_bodies(
+        &mut self,
+        runtime: &Runtime,
+        generator: &IdGenerator,
+        bodies: &[BodyRef],
+        format: ExportFormat,
+    ) -> Result<Vec<BodyExport>, String> {
+        let program = self.regenerate();
+        let (result, solids) = runtime.evaluate_with_solids(&program, generator).map_err(|e| e.to_string())?;
+        let available = result.tessellation.body_ids();
+        let selected = if bodies.is_empty() {
+            available.clone()
+        } else {
+            bodies.iter()
+                .map(|b| self.resolve_body(b, &available).ok_or_else(|| format!("No body {:?} in the model", b)))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        selected.into_iter().map(|body| {
+            let file_name = self.body_file_name(&body, format);
+            let content = match format {
+                ExportFormat::Stl => {
+                    let name = self.body_name(&body).unwrap_or("body");
+                    result.tessellation.body_stl(&body, name).ok_or_else(|| format!("No mesh for body {:?}", body))?
+                }
+                ExportFormat::Step => solids.body_step(&body)?,
+            };
+            Ok(BodyExport { body, file_name, content })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::delete::DeletePolicy;
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+    /// Sketch of a 10x10 square at `x`, extruded. Returns (sketch, extrude) ids.
+    fn add_box(graph: &mut FeatureGraph, x: f64) -> (EntityId, EntityId) {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let c = [[x, 0.0], [x + 10.0, 0.0], [x + 10.0, 10.0], [x, 10.0]];
+        for i in 0..4 {
+            sketch.add_entity(SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] });
+        }
+        let sketch_feature = Feature::new("Sketch", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0));
+        extrude.dependencies = vec![sketch_feature.id];
+        let ids = (sketch_feature.id, extrude.id);
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        ids
+    }
+
+    fn body_ids(graph: &mut FeatureGraph) -> Vec<BodyId> {
+        Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("bodies")).unwrap().tessellation.body_ids()
+    }
+
+    #[test]
+    fn test_body_name_persists_across_regeneration() {
+        let mut graph = FeatureGraph::new();
+        let (_, housing_extrude) = add_box(&mut graph, 0.0);
+        add_box(&mut graph, 30.0);
+        let bodies = body_ids(&mut graph);
+        let housing = bodies.iter().copied().find(|b| b.feature_id == topo_namespace(housing_extrude)).unwrap();
+
+        graph.rename_body(housing, "Housing").unwrap();
+        let lid = bodies.iter().copied().find(|b| *b != housing).unwrap();
+        assert!(graph.rename_body(lid, "Housing").is_err(), "Names are unique");
+        graph.rename_body(lid, " Lid ").unwrap();
+
+        let mut graph: FeatureGraph = serde_json::from_str(&serde_json::to_string(&graph).unwrap()).unwrap();
+        let after = body_ids(&mut graph);
+        assert_eq!(graph.migrate_body_names(&after), BodyNameMigration::default());
+        assert_eq!(graph.body_name(&housing), Some("Housing"));
+        assert_eq!(graph.resolve_body(&BodyRef::Name("Lid".to_string()), &after), Some(lid));
+
+        // Deleting the feature that made a body drops its name; undo restores it
+        let deletion = graph.delete_feature(housing_extrude, DeletePolicy::Refuse).unwrap();
+        assert_eq!(graph.body_name(&housing), None);
+        graph.restore_deletion(deletion);
+        assert_eq!(graph.body_name(&housing), Some("Housing"));
+    }
+
+    #[test]
+    fn test_body_name_follows_a_changed_body_id() {
+        let mut graph = FeatureGraph::new();
+        let (sketch, extrude) = add_box(&mut graph, 0.0);
+        let before = body_ids(&mut graph);
+        graph.rename_body(before[0], "Housing").unwrap();
+
+        // Extruding by region names the body after the region instead
+        let Some(ParameterValue::Sketch(data)) = graph.nodes[&sketch].parameters.get("sketch_data") else { unreachable!() };
+        let regions = crate::sketch::regions::summarize_regions(&crate::sketch::regions::find_regions(&data.entities));
+        let specs = vec![crate::features::types::RegionSpec {
+            region_key: regions[0].stable_id.clone(),
+            depth_override: None,
+            direction: crate::features::types::RegionDirection::Normal,
+            centroid: None,
+        }];
+        graph.nodes.get_mut(&extrude).unwrap().parameters.insert("region_specs".to_string(), ParameterValue::RegionSpecs(specs));
+        let after = body_ids(&mut graph);
+        assert_ne!(after, before);
+
+        let migration = graph.migrate_body_names(&after);
+        assert_eq!(migration.migrated, vec![("Housing".to_string(), before[0], after[0])]);
+        assert_eq!(graph.body_name(&after[0]), Some("Housing"));
+    }
+
+    #[test]
+    fn test_export_per_body_is_named_after_bodies() {
+        let mut graph = FeatureGraph::new();
+        add_box(&mut graph, 0.0);
+        add_box(&mut graph, 30.0);
+        let bodies = body_ids(&mut graph);
+        graph.rename_body(bodies[0], "Housing/v2").unwrap();
+
+        let runtime = Runtime::new();
+        let generator = IdGenerator::new("bodies");
+        let files = graph.export_bodies(&runtime, &generator, &[], ExportFormat::Stl).unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.file_name.as_str()).collect();
+        assert_eq!(names, vec!["Housing_v2.stl".to_string(), format!("body_{:016x}.stl", bodies[1].local_id)]);
+        assert!(files[0].content.starts_with("solid Housing/v2\n"));
+        assert_eq!(files[0].content.matches("facet normal").count(), 12, "A box is twelve triangles");
+
+        let files = graph.export_bodies(&runtime, &generator, &[BodyRef::Name("Housing/v2".to_string())], ExportFormat::Step).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_name, "Housing_v2.step");
+        assert!(files[0].content.contains("ISO-10303-21"));
+        assert!(graph.export_bodies(&runtime, &generator, &[BodyRef::Name("Lid".to_string())], ExportFormat::Stl).is_err());
+    }
+}
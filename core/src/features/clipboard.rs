@@ -0,0 +1,567 @@
+//! Copy and paste of features and sketch entities.
+//!
+//! A copy is a self-contained fragment: the copied features keep their original
+//! ids and list the features outside the copy they depend on. Pasting mints new
+//! ids for everything in the fragment and rewrites references between copied
+//! items; references to the outside go through a caller-supplied remap, so a
+//! fragment can be pasted into another document or onto different parents.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::dag::FeatureGraph;
+use super::ids::mint_sketch_entity_id;
+use super::types::{Feature, FeatureType, ParameterValue};
+use crate::sketch::reference::ReferenceEntity;
+use crate::sketch::solver::SketchSolver;
+use crate::sketch::types::{Sketch, SketchConstraint, SketchConstraintEntry, SketchEntity, SketchOperation};
+use crate::topo::naming::TopoId;
+use crate::topo::{EntityId, IdGenerator};
+
+/// Serialized clipboard contents, as sent to and received from the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum ClipboardPayload {
+    Features {
+        /// Copied features in regeneration order, with their original ids
+        features: Vec<Feature>,
+        /// Features outside the copy that copied features depend on or reference
+        external_dependencies: Vec<EntityId>,
+    },
+    SketchEntities {
+        entities: Vec<SketchEntity>,
+        /// Constraints whose entities are all in the copy
+        constraints: Vec<SketchConstraintEntry>,
+        #[serde(default)]
+        external_references: HashMap<EntityId, TopoId>,
+        /// Constraints that also involved entities outside the copy and were left behind
+        #[serde(default)]
+        dropped_constraints: Vec<SketchConstraint>,
+    },
+}
+
+/// Outcome of a successful paste
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasteResult {
+    /// Features created (or, for sketch entities, entities created), in order
+    pub created: Vec<EntityId>,
+    /// Id in the payload → id of its pasted copy
+    pub id_map: HashMap<EntityId, EntityId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum PasteError {
+    /// External dependencies that neither the remap nor the graph resolves.
+    /// The client can prompt for replacements and paste again with a remap.
+    Unresolved { references: Vec<EntityId> },
+    Invalid { message: String },
+}
+
+impl std::fmt::Display for PasteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasteError::Unresolved { references } => {
+                let ids: Vec<String> = references.iter().map(|id| id.to_string()).collect();
+                write!(f, "Unresolved references: {}", ids.join(", "))
+            }
+            PasteError::Invalid { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for PasteError {
+    fn from(message: String) -> Self {
+        PasteError::Invalid { message }
+    }
+}
+
+/// Id the runtime derives a feature's TopoIds from
+pub(crate) fn topo_namespace(feature_id: EntityId) -> EntityId {
+    IdGenerator::new(&feature_id.to_string()).next_id()
+}
+
+fn sketch_data(feature: &Feature) -> Option<&Sketch> {
+    match feature.parameters.get("sketch_data") {
+        Some(ParameterValue::Sketch(sketch)) => Some(sketch),
+        _ => None,
+    }
+}
+
+/// TopoIds a feature points at: reference parameters and a sketch's projected geometry
+fn referenced_topo_ids(feature: &Feature) -> Vec<TopoId> {
+    feature.parameters.values().flat_map(|value| match value {
+        ParameterValue::Reference(id) => vec![*id],
+        ParameterValue::Sketch(sketch) => sketch.external_references.values().copied().collect(),
+        _ => Vec::new(),
+    }).collect()
+}
+
+/// Copy of `feature` with the ids in `map` replaced: its own id, dependencies,
+/// references, sketch entities and the TopoIds its sketch projects from. Ids
+/// in string parameters (entity lists, serialized TopoIds, datum definitions)
+/// are replaced where a whole value, or a whole string inside a JSON value, is
+/// one. Region keys hash their entity ids and are found again by centroid.
+fn remap_feature(feature: &Feature, map: &HashMap<EntityId, EntityId>) -> Feature {
+    let id = |id: EntityId| map.get(&id).copied().unwrap_or(id);
+    let topo = |topo: &TopoId| TopoId { feature_id: id(topo.feature_id), ..*topo };
+
+    let mut copy = feature.clone();
+    copy.id = id(copy.id);
+    copy.dependencies.iter_mut().for_each(|dep| *dep = id(*dep));
+    copy.consumed_by = copy.consumed_by.map(id);
+    for value in copy.parameters.values_mut() {
+        match value {
+            ParameterValue::Reference(reference) => *reference = topo(reference),
+            ParameterValue::Sketch(sketch) => {
+                let mut remapped = sketch.with_remapped_entity_ids(id);
+                remapped.external_references.values_mut().for_each(|source| *source = topo(source));
+                remapped.face_offsets.iter_mut().for_each(|offset| offset.face = topo(&offset.face));
+                *sketch = remapped;
+            }
+            ParameterValue::String(text) => remap_text(text, map),
+            ParameterValue::List(entries) => entries.iter_mut().for_each(|entry| remap_text(entry, map)),
+            ParameterValue::Float(_)
+            | ParameterValue::Bool(_)
+            | ParameterValue::ProfileRegions(_)
+            | ParameterValue::Expression(_)
+            | ParameterValue::RegionSpecs(_) => {}
+        }
+    }
+    copy
+}
+
+/// Replace `text` if it is a mapped id, or JSON holding mapped ids as strings
+fn remap_text(text: &mut String, map: &HashMap<EntityId, EntityId>) {
+    fn remap_value(value: &mut serde_json::Value, map: &HashMap<EntityId, EntityId>) -> bool {
+        match value {
+            serde_json::Value::String(s) => match s.parse().ok().and_then(|id| map.get(&EntityId(id))) {
+                Some(new) => {
+                    *s = new.to_string();
+                    true
+                }
+                None => false,
+            },
+            serde_json::Value::Array(items) => items.iter_mut().fold(false, |changed, item| remap_value(item, map) | changed),
+            serde_json::Value::Object(fields) => fields.values_mut().fold(false, |changed, field| remap_value(field, map) | changed),
+            _ => false,
+        }
+    }
+
+    let mut value = serde_json::Value::String(text.clone());
+    if remap_value(&mut value, map) {
+        *text = value.as_str().unwrap_or_default().to_string();
+    } else if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(text) {
+        if remap_value(&mut json, map) {
+            *text = json.to_string();
+        }
+    }
+}
+
+impl FeatureGraph {
+    /// Feature owning each TopoId namespace: feature namespaces and sketch entity ids
+    pub(crate) fn topo_owners(&self) -> HashMap<EntityId, EntityId> {
+        let mut owners = HashMap::new();
+        for feature in self.nodes.values() {
+            owners.insert(topo_namespace(feature.id), feature.id);
+            if let Some(sketch) = sketch_data(feature) {
+                owners.extend(sketch.entities.iter().map(|e| (e.id, feature.id)));
+            }
+        }
+        owners
+    }
+
+    /// Copy features into a clipboard fragment. Dependencies among `ids` travel
+    /// with the copy; anything else they depend on or reference is listed in
+    /// `external_dependencies`.
+    pub fn copy_features(&self, ids: &[EntityId]) -> Result<ClipboardPayload, String> {
+        if let Some(missing) = ids.iter().find(|id| !self.nodes.contains_key(id)) {
+            return Err(format!("Feature {} not found", missing));
+        }
+        let selected: HashSet<EntityId> = ids.iter().copied().collect();
+        let mut order: Vec<EntityId> = self.sort_order.iter().copied().filter(|id| selected.contains(id)).collect();
+        for id in ids {
+            if !order.contains(id) {
+                order.push(*id);
+            }
+        }
+
+        let owners = self.topo_owners();
+        let mut external_dependencies = Vec::new();
+        for feature in order.iter().map(|id| &self.nodes[id]) {
+            let mut upstream = Self::upstream_ids(feature);
+            upstream.extend(referenced_topo_ids(feature).iter().filter_map(|topo| owners.get(&topo.feature_id).copied()));
+            for dep in upstream {
+                if !selected.contains(&dep) && !external_dependencies.contains(&dep) {
+                    external_dependencies.push(dep);
+                }
+            }
+        }
+
+        Ok(ClipboardPayload::Features {
+            features: order.iter().map(|id| self.nodes[id].clone()).collect(),
+            external_dependencies,
+        })
+    }
+
+    /// Copy entities of a sketch feature. Constraints among the copied entities
+    /// come along; constraints that also involve other entities are reported in
+    /// `dropped_constraints`.
+    pub fn copy_sketch_entities(&self, sketch_id: EntityId, entity_ids: &[EntityId]) -> Result<ClipboardPayload, String> {
+        let feature = self.nodes.get(&sketch_id).ok_or_else(|| format!("Feature {} not found", sketch_id))?;
+        let sketch = sketch_data(feature).ok_or_else(|| format!("Feature '{}' is not a sketch", feature.name))?;
+        if let Some(missing) = entity_ids.iter().find(|id| !sketch.entities.iter().any(|e| e.id == **id)) {
+            return Err(format!("Entity {} not found in sketch", missing));
+        }
+
+        let selected: HashSet<EntityId> = entity_ids.iter().copied().collect();
+        let mut constraints = Vec::new();
+        let mut dropped_constraints = Vec::new();
+        for entry in &sketch.constraints {
+            // The origin and axes exist in every sketch, so constraints to them travel along
+            let mut involved = SketchSolver::get_constraint_entities(&entry.constraint);
+            involved.retain(|id| !ReferenceEntity::is_reference(*id));
+            if !involved.iter().any(|id| selected.contains(id)) {
+                continue;
+            }
+            if involved.iter().all(|id| selected.contains(id)) {
+                constraints.push(entry.clone());
+            } else {
+                dropped_constraints.push(entry.constraint.clone());
+            }
+        }
+
+        Ok(ClipboardPayload::SketchEntities {
+            entities: sketch.entities.iter().filter(|e| selected.contains(&e.id)).cloned().collect(),
+            constraints,
+            external_references: sketch.external_references.iter()
+                .filter(|(id, _)| selected.contains(id))
+                .map(|(id, topo)| (*id, *topo))
+                .collect(),
+            dropped_constraints,
+        })
+    }
+
+    /// Paste a clipboard fragment with fresh ids.
+    ///
+    /// Features are appended to the tree. Each external dependency is replaced by
+    /// its entry in `remap`, or kept if a feature with that id exists in this
+    /// graph; otherwise the paste is rejected with the unresolved ids. Sketch
+    /// entities are added to the sketch feature `target_sketch`.
+    /// The graph is left unchanged on error.
+    pub fn paste(
+        &mut self,
+        payload: &ClipboardPayload,
+        remap: &HashMap<EntityId, EntityId>,
+        target_sketch: Option<EntityId>,
+    ) -> Result<PasteResult, PasteError> {
+        match payload {
+            ClipboardPayload::Features { features, external_dependencies } => {
+                self.paste_features(features, external_dependencies, remap)
+            }
+            ClipboardPayload::SketchEntities { entities, constraints, external_references, .. } => {
+                let target = target_sketch.ok_or_else(|| "Pasting sketch entities needs a target sketch".to_string())?;
+                self.paste_sketch_entities(target, entities, constraints, external_references)
+            }
+        }
+    }
+
+    fn paste_features(
+        &mut self,
+        features: &[Feature],
+        external_dependencies: &[EntityId],
+        remap: &HashMap<EntityId, EntityId>,
+    ) -> Result<PasteResult, PasteError> {
+        let mut id_map = HashMap::new();
+        let mut unresolved = Vec::new();
+        for dep in external_dependencies {
+            match remap.get(dep).copied().unwrap_or(*dep) {
+                target if self.nodes.contains_key(&target) => {
+                    id_map.insert(*dep, target);
+                }
+                _ => unresolved.push(*dep),
+            }
+        }
+        if !unresolved.is_empty() {
+            return Err(PasteError::Unresolved { references: unresolved });
+        }
+
+        // Everything the fragment defines gets a new id: features, their TopoId
+        // namespaces and their sketch entities
+        let mut rewrite = HashMap::new();
+        for feature in features {
+            let new_id = self.mint_unused_feature_id();
+            id_map.insert(feature.id, new_id);
+            rewrite.insert(topo_namespace(feature.id), topo_namespace(new_id));
+            if let Some(sketch) = sketch_data(feature) {
+                rewrite.extend(sketch.entities.iter().map(|e| (e.id, mint_sketch_entity_id(new_id, &format!("copy:{}", e.id)))));
+            }
+        }
+        for (old, new) in &id_map {
+            if old != new {
+                rewrite.insert(*old, *new);
+                rewrite.insert(topo_namespace(*old), topo_namespace(*new));
+            }
+        }
+
+        let mut pasted = Vec::with_capacity(features.len());
+        for feature in features {
+            let mut copy = remap_feature(feature, &rewrite);
+            copy.name = format!("{} (copy)", feature.name);
+            // Recomputed on regeneration from the Booleans that consume it
+            copy.consumed_by = None;
+            if let Some(missing) = copy.dependencies.iter().find(|dep| {
+                !self.nodes.contains_key(dep) && !id_map.values().any(|new| new == *dep)
+            }) {
+                return Err(PasteError::Invalid { message: format!("'{}' depends on {}, which is neither copied nor listed as external", feature.name, missing) });
+            }
+            pasted.push(copy);
+        }
+
+        let created: Vec<EntityId> = pasted.iter().map(|f| f.id).collect();
+        for feature in pasted {
+            self.add_node(feature);
+        }
+        if let Err(cycle) = self.sort() {
+            for id in &created {
+                self.take_node(*id);
+            }
+            let _ = self.sort();
+            return Err(PasteError::Invalid { message: format!("Paste would create a dependency cycle through {} features", cycle.len()) });
+        }
+
+        id_map.retain(|old, _| !external_dependencies.contains(old));
+        Ok(PasteResult { created, id_map })
+    }
+
+    fn paste_sketch_entities(
+        &mut self,
+        target: EntityId,
+        entities: &[SketchEntity],
+        constraints: &[SketchConstraintEntry],
+        external_references: &HashMap<EntityId, TopoId>,
+    ) -> Result<PasteResult, PasteError> {
+        let feature = self.nodes.get_mut(&target).ok_or_else(|| format!("Feature {} not found", target))?;
+        if feature.feature_type != FeatureType::Sketch {
+            return Err(format!("Feature '{}' is not a sketch", feature.name).into());
+        }
+        let Some(ParameterValue::Sketch(sketch)) = feature.parameters.get_mut("sketch_data") else {
+            return Err(format!("Sketch '{}' has no sketch data", feature.name).into());
+        };
+
+        if let Some(entry) = constraints.iter().find(|entry| {
+            SketchSolver::get_constraint_entities(&entry.constraint).iter()
+                .any(|id| !entities.iter().any(|e| e.id == *id) && !ReferenceEntity::is_reference(*id))
+        }) {
+            return Err(format!("Constraint {:?} refers to entities outside the payload", entry.constraint).into());
+        }
+        // Numbered up front so each new id can be derived from its number
+        let first_seq = sketch.next_seq.max(1);
+        let seqs: Vec<u64> = (first_seq..).take(entities.len()).collect();
+        let id_map: HashMap<EntityId, EntityId> = entities.iter().zip(&seqs)
+            .map(|(e, seq)| (e.id, mint_sketch_entity_id(target, &seq.to_string())))
+            .collect();
+        if let Some(taken) = id_map.values().find(|id| sketch.entities.iter().any(|e| e.id == **id)) {
+            return Err(PasteError::Invalid { message: format!("Pasted entity id {} is already in use in the sketch", taken) });
+        }
+
+        let mut fragment = Sketch {
+            entities: entities.to_vec(),
+            constraints: constraints.to_vec(),
+            external_references: external_references.clone(),
+            ..Sketch::new(sketch.plane.clone())
+        }.with_remapped_entity_ids(|id| id_map.get(&id).copied().unwrap_or(id));
+
+        // Added in payload order: entities first, then the constraints on them
+        for entity in &mut fragment.entities {
+            entity.creation_seq = sketch.take_creation_seq();
+            sketch.history.push(SketchOperation::AddGeometry { id: entity.id, geometry: entity.geometry.clone() });
+        }
+        for entry in &mut fragment.constraints {
+            entry.creation_seq = sketch.take_creation_seq();
+            sketch.history.push(SketchOperation::AddConstraint { constraint: entry.constraint.clone() });
+        }
+        let created = fragment.entities.iter().map(|e| e.id).collect();
+        sketch.entities.extend(fragment.entities);
+        sketch.constraints.extend(fragment.constraints);
+        sketch.external_references.extend(fragment.external_references);
+        Ok(PasteResult { created, id_map })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::Runtime;
+    use crate::sketch::types::{ConstraintPoint, SketchGeometry, SketchPlane};
+
+    fn rectangle_sketch() -> (Sketch, Vec<EntityId>) {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let c = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]];
+        let ids: Vec<EntityId> = (0..4)
+            .map(|i| sketch.add_entity(SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] }))
+            .collect();
+        for i in 0..4 {
+            sketch.add_constraint(SketchConstraint::Coincident {
+                points: [ConstraintPoint { id: ids[i], index: 1 }, ConstraintPoint { id: ids[(i + 1) % 4], index: 0 }],
+            });
+        }
+        (sketch, ids)
+    }
+
+    #[test]
+    fn test_paste_sketch_and_extrude_makes_independent_copy() {
+        let (sketch, _) = rectangle_sketch();
+        let mut graph = FeatureGraph::new();
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0));
+        extrude.dependencies = vec![sketch_feature.id];
+        let originals = [sketch_feature.id, extrude.id];
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+
+        let payload = graph.copy_features(&originals).unwrap();
+        match &payload {
+            ClipboardPayload::Features { features, external_dependencies } => {
+                assert_eq!(features.len(), 2);
+                assert!(external_dependencies.is_empty(), "The extrude's sketch is part of the copy");
+            }
+            other => panic!("Unexpected payload {:?}", other),
+        }
+        // The payload survives the trip to the client and back
+        let payload: ClipboardPayload = serde_json::from_str(&serde_json::to_string(&payload).unwrap()).unwrap();
+
+        let result = graph.paste(&payload, &HashMap::new(), None).unwrap();
+        assert_eq!(result.created.len(), 2);
+        assert_eq!(graph.nodes.len(), 4);
+        let (sketch_copy, extrude_copy) = (&graph.nodes[&result.created[0]], &graph.nodes[&result.created[1]]);
+        assert!(originals.iter().all(|id| !result.created.contains(id)));
+        assert_eq!(extrude_copy.dependencies, vec![sketch_copy.id], "Internal dependency follows the copy");
+        let entity_ids = |id: &EntityId| -> HashSet<EntityId> {
+            sketch_data(&graph.nodes[id]).unwrap().entities.iter().map(|e| e.id).collect()
+        };
+        assert!(entity_ids(&originals[0]).is_disjoint(&entity_ids(&sketch_copy.id)));
+
+        let program = graph.regenerate();
+        let evaluated = Runtime::new().evaluate(&program, &IdGenerator::new("clipboard")).unwrap();
+        let faces_of = |feature: EntityId| -> HashSet<TopoId> {
+            let namespace = topo_namespace(feature);
+            evaluated.tessellation.triangle_ids.iter().filter(|id| id.feature_id == namespace).copied().collect()
+        };
+        let (original_faces, copied_faces) = (faces_of(originals[1]), faces_of(result.created[1]));
+        assert_eq!(original_faces.len(), 6, "Original box is intact");
+        assert_eq!(copied_faces.len(), 6, "Copy is a complete second box");
+        assert!(original_faces.is_disjoint(&copied_faces));
+    }
+
+    #[test]
+    fn test_paste_remaps_ids_held_in_parameters() {
+        let (sketch, lines) = rectangle_sketch();
+        let mut graph = FeatureGraph::new();
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let edge = TopoId::new(topo_namespace(sketch_feature.id), 3, crate::topo::naming::TopoRank::Edge);
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("profiles", ParameterValue::List(lines.iter().map(|id| id.to_string()).collect()))
+            .with_param("edges", ParameterValue::List(vec![serde_json::to_string(&edge).unwrap()]))
+            .with_param("face", ParameterValue::Reference(edge))
+            .with_param("label", ParameterValue::String(format!("from {}", lines[0])));
+        extrude.dependencies = vec![sketch_feature.id];
+        let originals = [sketch_feature.id, extrude.id];
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+
+        let payload = graph.copy_features(&originals).unwrap();
+        graph.id_namespace = "doc".to_string();
+        let result = graph.paste(&payload, &HashMap::new(), None).unwrap();
+        assert_eq!(result.created[0], EntityId::new_deterministic("doc:1"), "Copies are minted in the document namespace");
+        let copied_lines: Vec<String> = sketch_data(&graph.nodes[&result.created[0]]).unwrap()
+            .entities.iter().map(|e| e.id.to_string()).collect();
+        let copied_edge = TopoId { feature_id: topo_namespace(result.created[0]), ..edge };
+        let copy = &graph.nodes[&result.created[1]];
+        assert_eq!(copy.parameters["profiles"], ParameterValue::List(copied_lines));
+        assert_eq!(copy.parameters["edges"], ParameterValue::List(vec![serde_json::to_string(&copied_edge).unwrap()]));
+        assert_eq!(copy.parameters["face"], ParameterValue::Reference(copied_edge));
+        assert_eq!(copy.parameters["label"], ParameterValue::String(format!("from {}", lines[0])), "Free text is left alone");
+    }
+
+    #[test]
+    fn test_paste_rejects_unresolved_external_dependency() {
+        let (sketch, _) = rectangle_sketch();
+        let mut graph = FeatureGraph::new();
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch.clone()));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude);
+        extrude.dependencies = vec![sketch_feature.id];
+        let sketch_id = sketch_feature.id;
+        let extrude_id = extrude.id;
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+
+        let payload = graph.copy_features(&[extrude_id]).unwrap();
+        let mut other = FeatureGraph::new();
+        match other.paste(&payload, &HashMap::new(), None) {
+            Err(PasteError::Unresolved { references }) => assert_eq!(references, vec![sketch_id]),
+            other => panic!("Expected unresolved references, got {:?}", other),
+        }
+        assert!(other.nodes.is_empty(), "Rejected paste leaves the graph untouched");
+
+        let replacement = Feature::new("Other sketch", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let replacement_id = replacement.id;
+        other.add_node(replacement);
+        let result = other.paste(&payload, &HashMap::from([(sketch_id, replacement_id)]), None).unwrap();
+        assert_eq!(other.nodes[&result.created[0]].dependencies, vec![replacement_id]);
+    }
+
+    #[test]
+    fn test_copy_sketch_entities_drops_outside_constraints() {
+        let (sketch, ids) = rectangle_sketch();
+        let mut graph = FeatureGraph::new();
+        let feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let sketch_id = feature.id;
+        graph.add_node(feature);
+
+        // Two adjacent sides: their shared corner comes along, the other three corners don't
+        let payload = graph.copy_sketch_entities(sketch_id, &ids[..2]).unwrap();
+        match &payload {
+            ClipboardPayload::SketchEntities { entities, constraints, dropped_constraints, .. } => {
+                assert_eq!(entities.len(), 2);
+                assert_eq!(constraints.len(), 1);
+                assert_eq!(dropped_constraints.len(), 2);
+            }
+            other => panic!("Unexpected payload {:?}", other),
+        }
+
+        let result = graph.paste(&payload, &HashMap::new(), Some(sketch_id)).unwrap();
+        let sketch = sketch_data(&graph.nodes[&sketch_id]).unwrap();
+        assert_eq!(sketch.entities.len(), 6);
+        assert!(result.created.iter().all(|id| !ids.contains(id)));
+        match sketch.constraints.last().map(|e| &e.constraint) {
+            Some(SketchConstraint::Coincident { points }) => {
+                assert_eq!([points[0].id, points[1].id], [result.id_map[&ids[0]], result.id_map[&ids[1]]]);
+            }
+            other => panic!("Unexpected constraint {:?}", other),
+        }
+        assert!(graph.paste(&payload, &HashMap::new(), None).is_err(), "Sketch entities need a target sketch");
+
+        // Pasted ids come from the sketch's id and numbering, so a second paste
+        // gets new ones, and an id that is somehow taken fails the paste
+        let next_seq = sketch_data(&graph.nodes[&sketch_id]).unwrap().next_seq;
+        assert_eq!(result.id_map[&ids[0]], mint_sketch_entity_id(sketch_id, &(next_seq - 3).to_string()));
+        let again = graph.paste(&payload, &HashMap::new(), Some(sketch_id)).unwrap();
+        assert!(again.created.iter().all(|id| !result.created.contains(id)));
+        let ParameterValue::Sketch(sketch) = graph.nodes.get_mut(&sketch_id).unwrap().parameters.get_mut("sketch_data").unwrap() else { unreachable!() };
+        let mut squatter = sketch.entities[0].clone();
+        squatter.id = mint_sketch_entity_id(sketch_id, &sketch.next_seq.to_string());
+        sketch.entities.push(squatter);
+        match graph.paste(&payload, &HashMap::new(), Some(sketch_id)) {
+            Err(PasteError::Invalid { message }) => assert!(message.contains("already in use"), "{}", message),
+            other => panic!("Expected a collision, got {:?}", other),
+        }
+    }
+}
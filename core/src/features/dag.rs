@@ -1,4 +1,4 @@
-use super::types::Feature;
+use super::types::{Feature, ParameterValue, RegionDirection, RegionSpec, ResolvedRegionSpec};
 use crate::topo::EntityId;
 use crate::variables::VariableStore;
 use std::collections::{HashMap, HashSet};
@@ -20,6 +20,22 @@ impl Context {
     }
 }
 
+/// Whether a feature takes part in regeneration, and if not, why.
+/// Only `Active` features emit program statements or demand references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeatureState {
+    Active,
+    /// Suppressed by the user
+    Suppressed,
+    /// Not suppressed itself, but an upstream feature is suppressed.
+    /// This is intentional absence, not an error.
+    SuppressedUpstream,
+    /// After the rollback bar
+    RolledBack,
+    /// The feature has an error (see `Feature::error`), or an upstream feature does
+    Errored,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FeatureGraph {
     pub nodes: HashMap<EntityId, Feature>,
@@ -32,8 +48,51 @@ pub struct FeatureGraph {
     /// This is for temporary preview mode, not permanent suppression
     #[serde(default)]
     pub rollback_point: Option<EntityId>,
+    /// Document-level properties (part number, revision, author, ...)
+    #[serde(default)]
+    pub document_properties: HashMap<String, super::metadata::PropertyValue>,
+    /// Face areas published for use in variable expressions
+    #[serde(default)]
+    pub published_measurements: Vec<crate::analysis::bindings::PublishedMeasurement>,
+    /// User-given body names (see `rename_body`)
+    #[serde(default)]
+    pub body_names: Vec<super::bodies::BodyName>,
+    /// Namespace feature ids are minted in (see `mint_feature_id`)
+    #[serde(default)]
+    pub id_namespace: String,
+    /// Feature ids minted from the namespace without a seed so far
+    #[serde(default)]
+    pub id_sequence: u64,
+    /// Features built from a sketch being edited live; their geometry predates
+    /// the edit until the sketch is committed (see `update_sketch_live`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_rebuild: Vec<EntityId>,
+    /// The graph as of its last clean regeneration (see `record_good_regen`).
+    /// Kept for this session only; never saved.
+    #[serde(skip)]
+    pub last_good_snapshot: Option<super::snapshot::GraphSnapshot>,
 }
 
+/// Feature history as an adjacency list, for drawing the history tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    /// Features in `order`
+    pub nodes: Vec<DependencyNode>,
+    /// Topological order used by `regenerate`
+    pub order: Vec<EntityId>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyNode {
+    pub id: EntityId,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub feature_type: super::types::FeatureType,
+    /// Parents: features this one is built from
+    pub depends_on: Vec<EntityId>,
+    /// Children: features built from this one, in `order`
+    pub dependents: Vec<EntityId>,
+}
 
 impl FeatureGraph {
     pub fn new() -> Self {
@@ -82,7 +141,19 @@ impl FeatureGraph {
         true
     }
 
-    pub fn remove_node(&mut self, id: EntityId) -> Option<Feature> {
+    /// Remove a feature nothing depends on. If other features depend on it,
+    /// nothing is removed and their ids are returned; see `delete_feature`
+    /// for cascading or orphaning deletes.
+    pub fn remove_node(&mut self, id: EntityId) -> Result<Option<Feature>, Vec<EntityId>> {
+        let dependents = self.downstream_of(id);
+        if !dependents.is_empty() {
+            return Err(dependents);
+        }
+        Ok(self.take_node(id))
+    }
+
+    /// Remove a feature regardless of its dependents
+    pub(crate) fn take_node(&mut self, id: EntityId) -> Option<Feature> {
         // Remove from sort order
         if let Some(pos) = self.sort_order.iter().position(|&x| x == id) {
             self.sort_order.remove(pos);
@@ -93,6 +164,13 @@ impl FeatureGraph {
     /// Performs a topological sort of the features.
     /// Returns Ok(sorted_ids) or Err(cycle_ids) if a cycle is detected.
     pub fn sort(&mut self) -> Result<Vec<EntityId>, Vec<EntityId>> {
+        let sorted = self.topological_order()?;
+        self.sort_order = sorted.clone();
+        Ok(sorted)
+    }
+
+    /// The order `sort` gives, without storing it
+    pub fn topological_order(&self) -> Result<Vec<EntityId>, Vec<EntityId>> {
         let mut sorted = Vec::new();
         let mut visited = HashSet::new();
         let mut temp_visited = HashSet::new();
@@ -132,8 +210,7 @@ impl FeatureGraph {
                 }
             }
         }
-        
-        self.sort_order = sorted.clone();
+
         Ok(sorted)
     }
 
@@ -177,12 +254,50 @@ impl FeatureGraph {
         Err("Feature not found".to_string())
     }
 
+    /// Toggles whether a feature is drawn. Returns the new hidden state,
+    /// or error if not found.
+    pub fn toggle_visibility(&mut self, id: EntityId) -> Result<bool, String> {
+        let feature = self.nodes.get_mut(&id).ok_or_else(|| "Feature not found".to_string())?;
+        feature.hidden = !feature.hidden;
+        Ok(feature.hidden)
+    }
+
+    /// Tag the geometry of hidden features in `tessellation` so the client
+    /// can skip drawing it
+    pub fn tag_hidden(&self, tessellation: &mut crate::geometry::Tessellation) {
+        let mut hidden: Vec<EntityId> = self.nodes.values()
+            .filter(|f| f.hidden && !f.suppressed)
+            .map(|f| crate::topo::IdGenerator::new(&f.id.to_string()).next_id())
+            .collect();
+        hidden.sort();
+        tessellation.hidden_features = hidden;
+    }
+
+    /// Reduce `tessellation` to the geometry feature `id` produced, to inspect
+    /// its contribution on its own
+    pub fn isolate(&self, id: EntityId, tessellation: &mut crate::geometry::Tessellation) -> Result<(), String> {
+        if !self.nodes.contains_key(&id) {
+            return Err("Feature not found".to_string());
+        }
+        tessellation.retain_feature(super::clipboard::topo_namespace(id));
+        Ok(())
+    }
+
     pub fn update_feature_params(&mut self, id: EntityId, params: HashMap<String, super::types::ParameterValue>) -> Result<(), String> {
         if let Some(feature) = self.nodes.get_mut(&id) {
             // Merge params
-            for (k, v) in params {
+            for (k, mut v) in params {
+                if let super::types::ParameterValue::Sketch(sketch) = &mut v {
+                    if let Some(super::types::ParameterValue::Sketch(stored)) = feature.parameters.get(&k) {
+                        sketch.continue_numbering(stored);
+                        stored.keep_locked(sketch);
+                    }
+                    sketch.number_additions();
+                }
                 feature.parameters.insert(k, v);
             }
+            // New inputs supersede the error; regeneration reports any that remains
+            feature.error = None;
             return Ok(());
         }
         Err("Feature not found".to_string())
@@ -202,14 +317,17 @@ impl FeatureGraph {
         use crate::evaluator::ast::{Statement, Expression, Call, Value};
         use super::types::FeatureType;
 
+        let states = self.feature_states();
+        let is_active = |id: &EntityId| states.get(id) == Some(&FeatureState::Active);
+
         // Pre-process: Collect features consumed by active Boolean operations
         // These features should compute their solids but NOT tessellate for display
-        let mut consumed_features: std::collections::HashSet<EntityId> = std::collections::HashSet::new();
+        let mut consumed_features: std::collections::HashSet<String> = std::collections::HashSet::new();
         
         for id in &self.sort_order {
             if let Some(feature) = self.nodes.get(id) {
-                // Only consider non-suppressed Boolean features
-                if feature.suppressed {
+                // Only consider Boolean features that actually run
+                if !is_active(id) {
                     continue;
                 }
                 if feature.feature_type == FeatureType::Boolean {
@@ -219,34 +337,28 @@ impl FeatureGraph {
                         _ => false, // Default: consume tool body
                     };
                     
-                    // Get body_list from parameters: [target_id, tool_id]
-                    if let Some(crate::features::types::ParameterValue::List(body_ids)) = feature.parameters.get("body_list") {
-                        for (idx, body_id_str) in body_ids.iter().enumerate() {
-                            // Parse UUID and mark as consumed
-                            if let Ok(body_uuid) = uuid::Uuid::parse_str(body_id_str) {
-                                let body_entity_id = EntityId(body_uuid);
-                                
-                                // idx 0 = target body (always consumed - replaced by boolean result)
-                                // idx 1 = tool body (consumed only if keep_tool_body is false)
-                                if idx == 0 {
-                                    // Target body is always consumed
-                                    consumed_features.insert(body_entity_id);
-                                } else if idx == 1 && !keep_tool_body {
-                                    // Tool body consumed only if not keeping it
-                                    consumed_features.insert(body_entity_id);
-                                }
-                            }
+                    // idx 0 = target body (always consumed - replaced by boolean result)
+                    // idx 1 = tool body (consumed only if keep_tool_body is false)
+                    for idx in 0..2 {
+                        if idx == 1 && keep_tool_body {
+                            continue;
+                        }
+                        if let Some((_, consumed)) = Self::boolean_operand(feature, idx) {
+                            consumed_features.insert(consumed);
                         }
                     }
                 }
+                // A cutting revolve replaces the body it cuts
+                if let Some((_, consumed)) = self.revolve_cut_target(feature, &is_active) {
+                    consumed_features.insert(consumed);
+                }
             }
         }
         
         // Send consumed features to evaluator via a special call
         if !consumed_features.is_empty() {
-            let consumed_list: Vec<String> = consumed_features.iter()
-                .map(|id| id.to_string())
-                .collect();
+            let mut consumed_list: Vec<String> = consumed_features.into_iter().collect();
+            consumed_list.sort();
             let consumed_stmt = Statement::Expression(Expression::Call(Call {
                 function: "set_consumed_features".to_string(),
                 args: vec![Expression::Value(Value::Array(
@@ -258,7 +370,7 @@ impl FeatureGraph {
 
         for id in &self.sort_order {
             if let Some(feature) = self.nodes.get(id) {
-                if feature.suppressed {
+                if !is_active(id) {
                     continue;
                 }
                 
@@ -280,6 +392,10 @@ impl FeatureGraph {
                              let _resolved_count = resolved_sketch.resolve_expressions(&self.variables);
                              if let Ok(json) = serde_json::to_string(&resolved_sketch) {
                                  args.push(Expression::Value(Value::String(json)));
+                                 // Face or datum plane the sketch is placed on
+                                 if let Some(plane_ref) = Self::reference_param_json(feature, "plane_ref") {
+                                     args.push(Expression::Value(Value::String(plane_ref)));
+                                 }
                              }
                          }
 
@@ -304,10 +420,7 @@ impl FeatureGraph {
                         }
                         
                         // Get distance parameter (default 10.0)
-                        let mut distance = match feature.parameters.get("distance") {
-                            Some(crate::features::types::ParameterValue::Float(d)) => *d,
-                            _ => 10.0, // Default distance
-                        };
+                        let mut distance = self.resolve_float_param(feature, "distance", 10.0);
                         
                         // Check for flip_direction parameter
                         if let Some(crate::features::types::ParameterValue::Bool(flip)) = feature.parameters.get("flip_direction") {
@@ -325,10 +438,7 @@ impl FeatureGraph {
                         args.push(Expression::Value(Value::String(operation)));
 
                         // Get start_offset parameter (default 0.0)
-                        let start_offset = match feature.parameters.get("start_offset") {
-                            Some(crate::features::types::ParameterValue::Float(d)) => *d,
-                            _ => 0.0,
-                        };
+                        let start_offset = self.resolve_float_param(feature, "start_offset", 0.0);
                         args.push(Expression::Value(Value::Number(start_offset)));
 
                         // Get profiles parameter (optional List or String)
@@ -353,6 +463,35 @@ impl FeatureGraph {
                                 args.push(Expression::Value(Value::String(json)));
                             }
                         }
+
+                        // Plane reference of the profile sketch; optional args before it are padded
+                        let plane_ref = feature.dependencies.first()
+                            .and_then(|dep_id| self.nodes.get(dep_id))
+                            .and_then(|dep| Self::reference_param_json(dep, "plane_ref"));
+                        if let Some(plane_ref) = plane_ref {
+                            args.resize(6, Expression::Value(Value::String(String::new())));
+                            args.push(Expression::Value(Value::String(plane_ref)));
+                        }
+
+                        // Per-region bodies, with depths resolved against the variables
+                        if let Some(crate::features::types::ParameterValue::RegionSpecs(specs)) = feature.parameters.get("region_specs") {
+                            let resolved = self.resolve_region_specs(feature, specs);
+                            if let Ok(json) = serde_json::to_string(&resolved) {
+                                args.resize(7, Expression::Value(Value::String(String::new())));
+                                args.push(Expression::Value(Value::String(json)));
+                            }
+                        }
+
+                        // End taper (uniform, or per axis) and twist, only when set
+                        let taper = self.resolve_float_param(feature, "taper", 1.0);
+                        let taper_x = self.resolve_float_param(feature, "taper_x", taper);
+                        let taper_y = self.resolve_float_param(feature, "taper_y", taper);
+                        let twist_degrees = self.resolve_float_param(feature, "twist_degrees", 0.0);
+                        if taper_x != 1.0 || taper_y != 1.0 || twist_degrees != 0.0 {
+                            args.resize(8, Expression::Value(Value::String(String::new())));
+                            args.push(Expression::Value(Value::Vector(vec![taper_x, taper_y])));
+                            args.push(Expression::Value(Value::Number(twist_degrees)));
+                        }
                         Some(Call {
                             function: "extrude".to_string(),
                             args, 
@@ -386,6 +525,18 @@ impl FeatureGraph {
                             _ => "X".to_string(),
                         };
                         args.push(Expression::Value(Value::String(axis)));
+
+                        // Edge or datum axis, takes precedence over `axis`
+                        if let Some(axis_ref) = Self::reference_param_json(feature, "axis_ref") {
+                            args.push(Expression::Value(Value::String(axis_ref)));
+                        }
+
+                        // Cut: subtract the revolved solid from the target body
+                        if let Some((target_var, _)) = self.revolve_cut_target(feature, &is_active) {
+                            args.resize(4, Expression::Value(Value::String(String::new())));
+                            args.push(Expression::Value(Value::String("Cut".to_string())));
+                            args.push(Expression::Variable(target_var));
+                        }
                          
                         Some(Call {
                             function: "revolve".to_string(),
@@ -406,11 +557,8 @@ impl FeatureGraph {
                             args.push(Expression::Value(Value::String(String::new())));
                         }
                         
-                        // Radius (default 1.0)
-                        let radius = match feature.parameters.get("radius") {
-                            Some(crate::features::types::ParameterValue::Float(r)) => *r,
-                            _ => 1.0, 
-                        };
+                        // Radius (default 1.0), a literal or an expression
+                        let radius = self.resolve_float_param(feature, "radius", 1.0);
                         args.push(Expression::Value(Value::Number(radius)));
 
                         // Edges List
@@ -426,6 +574,14 @@ impl FeatureGraph {
                             args.push(Expression::Value(Value::Array(vec![])))
                         }
 
+                        // Variable radius: `radius` at the start of each edge chain, `radius_end` at its end
+                        args.push(Expression::Value(Value::Number(self.resolve_float_param(feature, "radius_end", radius))));
+                        let continuity = match feature.parameters.get("continuity") {
+                            Some(crate::features::types::ParameterValue::String(c)) => c.clone(),
+                            _ => "G1".to_string(),
+                        };
+                        args.push(Expression::Value(Value::String(continuity)));
+
                         Some(Call {
                             function: "fillet".to_string(),
                             args, 
@@ -481,6 +637,7 @@ impl FeatureGraph {
                         // Reference points - no kernel call needed
                         None
                     },
+                    FeatureType::DatumPlane | FeatureType::DatumAxis => self.datum_call(feature),
                     FeatureType::Boolean => {
                         // Boolean operations: union, intersect, subtract
                         let mut args = Vec::new();
@@ -491,16 +648,7 @@ impl FeatureGraph {
                             _ => "Union".to_string(),
                         };
                         
-                        // Get body_list parameter
-                        let body_ids: Vec<String> = match feature.parameters.get("body_list") {
-                            Some(crate::features::types::ParameterValue::List(list)) => list.clone(),
-                            _ => vec![],
-                        };
-                        
-                        if body_ids.len() >= 2 {
-                            // Get target (first body) and tool (second body) feature variables
-                            let target_var = format!("feat_{}", body_ids[0]);
-                            let tool_var = format!("feat_{}", body_ids[1]);
+                        if let (Some((target_var, _)), Some((tool_var, _))) = (Self::boolean_operand(feature, 0), Self::boolean_operand(feature, 1)) {
                             
                             args.push(Expression::Variable(target_var));
                             args.push(Expression::Variable(tool_var));
@@ -567,6 +715,11 @@ impl FeatureGraph {
                                 _ => 10.0,
                             };
                             args.push(Expression::Value(Value::Number(spacing)));
+
+                            // Edge, datum axis or plane normal, takes precedence over `direction`
+                            if let Some(direction_ref) = Self::reference_param_json(feature, "direction_ref") {
+                                args.push(Expression::Value(Value::String(direction_ref)));
+                            }
                             
                             Some(Call {
                                 function: "linear_pattern".to_string(),
@@ -621,6 +774,11 @@ impl FeatureGraph {
                                 _ => 360.0,
                             };
                             args.push(Expression::Value(Value::Number(angle)));
+
+                            // Edge or datum axis, takes precedence over `axis` and `center`
+                            if let Some(axis_ref) = Self::reference_param_json(feature, "axis_ref") {
+                                args.push(Expression::Value(Value::String(axis_ref)));
+                            }
                             
                             Some(Call {
                                 function: "circular_pattern".to_string(),
@@ -631,6 +789,61 @@ impl FeatureGraph {
                             None
                         }
                     },
+                    FeatureType::DeleteFace => {
+                        // Face ids are serialized TopoIds; the heal mode defaults to capping
+                        let faces: Vec<Value> = match feature.parameters.get("faces") {
+                            Some(crate::features::types::ParameterValue::List(list)) => {
+                                list.iter().map(|s| Value::String(s.clone())).collect()
+                            },
+                            Some(crate::features::types::ParameterValue::Reference(id)) => {
+                                serde_json::to_string(id).map(|s| vec![Value::String(s)]).unwrap_or_default()
+                            },
+                            _ => vec![],
+                        };
+                        let heal_mode = match feature.parameters.get("heal_mode") {
+                            Some(crate::features::types::ParameterValue::String(s)) => s.clone(),
+                            _ => "Cap".to_string(),
+                        };
+
+                        if faces.is_empty() {
+                            None
+                        } else {
+                            Some(Call {
+                                function: "delete_face".to_string(),
+                                args: vec![
+                                    Expression::Value(Value::Array(faces)),
+                                    Expression::Value(Value::String(heal_mode)),
+                                ],
+                            })
+                        }
+                    },
+                    FeatureType::Box => Some(Call {
+                        function: "cube".to_string(),
+                        args: ["width", "height", "depth"].iter()
+                            .map(|name| Expression::Value(Value::Number(self.resolve_float_param(feature, name, 10.0))))
+                            .collect(),
+                    }),
+                    FeatureType::Torus => Some(Call {
+                        function: "torus".to_string(),
+                        args: vec![
+                            Expression::Value(Value::Number(self.resolve_float_param(feature, "major_radius", 10.0))),
+                            Expression::Value(Value::Number(self.resolve_float_param(feature, "minor_radius", 2.0))),
+                        ],
+                    }),
+                    FeatureType::Cylinder => Some(Call {
+                        function: "cylinder".to_string(),
+                        args: vec![
+                            Expression::Value(Value::Number(self.resolve_float_param(feature, "radius", 5.0))),
+                            Expression::Value(Value::Number(self.resolve_float_param(feature, "height", 10.0))),
+                        ],
+                    }),
+                    FeatureType::ImportedBody => match feature.parameters.get("solid") {
+                        Some(crate::features::types::ParameterValue::String(json)) => Some(Call {
+                            function: "imported_body".to_string(),
+                            args: vec![Expression::Value(Value::String(json.clone()))],
+                        }),
+                        _ => None,
+                    },
                     _ => None
                 };
 
@@ -642,20 +855,256 @@ impl FeatureGraph {
                      };
                      _program.statements.push(stmt);
                 }
-                
-                // Check rollback point AFTER generating this feature
-                // Rollback is inclusive - we generate up to and including the rollback feature
-                if let Some(rb_id) = self.rollback_point {
-                    if *id == rb_id {
-                        break;
-                    }
-                }
             }
         }
         
         _program
     }
 
+    /// Compute the regeneration state of every feature in sort order.
+    /// Precedence: a feature's own suppression, then the rollback bar (inclusive),
+    /// then errors (its own or upstream), then suppression inherited from any
+    /// upstream feature.
+    pub fn feature_states(&self) -> HashMap<EntityId, FeatureState> {
+        let rollback_index = self.rollback_point.and_then(|id| self.get_feature_index(id));
+        let mut states = HashMap::new();
+
+        for (index, id) in self.sort_order.iter().enumerate() {
+            let Some(feature) = self.nodes.get(id) else { continue };
+            let state = if feature.suppressed {
+                FeatureState::Suppressed
+            } else if rollback_index.is_some_and(|rb| index > rb) {
+                FeatureState::RolledBack
+            } else if feature.error.is_some()
+                || Self::upstream_ids(feature).iter().any(|up| states.get(up) == Some(&FeatureState::Errored))
+            {
+                FeatureState::Errored
+            } else if Self::upstream_ids(feature).iter().any(|up| matches!(
+                states.get(up),
+                Some(FeatureState::Suppressed | FeatureState::SuppressedUpstream)
+            )) {
+                FeatureState::SuppressedUpstream
+            } else {
+                FeatureState::Active
+            };
+            states.insert(*id, state);
+        }
+        states
+    }
+
+    /// Program variable and consumed-list entry of a Boolean's target (`index`
+    /// 0) or tool (1): the `body_list` feature, or just one of its bodies when
+    /// `target_body`/`tool_body` references one
+    fn boolean_operand(feature: &Feature, index: usize) -> Option<(String, String)> {
+        let Some(ParameterValue::List(body_list)) = feature.parameters.get("body_list") else { return None };
+        let feature_id = uuid::Uuid::parse_str(body_list.get(index)?).ok()?;
+        let body_param = if index == 0 { "target_body" } else { "tool_body" };
+        match feature.parameters.get(body_param) {
+            Some(ParameterValue::Reference(body)) => {
+                let var = crate::evaluator::runtime::body_variable(body);
+                Some((var.clone(), var))
+            }
+            _ => Some((format!("feat_{}", feature_id), feature_id.to_string())),
+        }
+    }
+
+    /// Program variable and consumed-list entry of the body a Cut revolve
+    /// subtracts from: the `body_list` target like a Boolean's, otherwise the
+    /// latest active solid feature before it. None unless `operation` is Cut.
+    fn revolve_cut_target(&self, feature: &Feature, is_active: &impl Fn(&EntityId) -> bool) -> Option<(String, String)> {
+        use super::types::FeatureType;
+        if feature.feature_type != FeatureType::Revolve
+            || !matches!(feature.parameters.get("operation"), Some(ParameterValue::String(op)) if op == "Cut") {
+            return None;
+        }
+        if let Some(target) = Self::boolean_operand(feature, 0) {
+            return Some(target);
+        }
+        let position = self.sort_order.iter().position(|id| *id == feature.id)?;
+        self.sort_order[..position].iter().rev()
+            .filter(|id| is_active(id))
+            .find(|id| self.nodes.get(id).is_some_and(|f| matches!(f.feature_type,
+                FeatureType::Extrude | FeatureType::Revolve | FeatureType::Boolean)))
+            .map(|id| (format!("feat_{}", id), id.to_string()))
+    }
+
+    /// Features whose output this feature consumes: explicit dependencies plus Boolean bodies
+    pub(super) fn upstream_ids(feature: &Feature) -> Vec<EntityId> {
+        let mut ids = feature.dependencies.clone();
+        if let Some(super::types::ParameterValue::List(bodies)) = feature.parameters.get("body_list") {
+            ids.extend(bodies.iter().filter_map(|b| uuid::Uuid::parse_str(b).ok()).map(EntityId));
+        }
+        ids
+    }
+
+    /// Features that consume `id`'s output directly, in sort order
+    pub(super) fn downstream_of(&self, id: EntityId) -> Vec<EntityId> {
+        let mut ids: Vec<EntityId> = self.sort_order.iter().copied()
+            .filter(|other| self.nodes.get(other).is_some_and(|f| Self::upstream_ids(f).contains(&id)))
+            .collect();
+        let mut rest: Vec<EntityId> = self.nodes.values()
+            .filter(|f| !ids.contains(&f.id) && Self::upstream_ids(f).contains(&id))
+            .map(|f| f.id)
+            .collect();
+        rest.sort();
+        ids.extend(rest);
+        ids
+    }
+
+    /// Read a numeric feature parameter that may be a literal or a variable expression.
+    /// Expressions are evaluated against the graph's variables (length variables resolve in mm).
+    /// Falls back to `default` if the parameter is missing or the expression fails to evaluate.
+    /// A `Reference` parameter serialized for use as a syscall argument
+    fn reference_param_json(feature: &Feature, name: &str) -> Option<String> {
+        match feature.parameters.get(name) {
+            Some(crate::features::types::ParameterValue::Reference(id)) => serde_json::to_string(id).ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn resolve_float_param(&self, feature: &Feature, name: &str, default: f64) -> f64 {
+        match feature.parameters.get(name) {
+            Some(crate::features::types::ParameterValue::Float(v)) => *v,
+            Some(crate::features::types::ParameterValue::Expression(expr)) => {
+                crate::variables::evaluator::evaluate(expr, &self.variables).unwrap_or(default)
+            }
+            _ => default,
+        }
+    }
+
+    /// Signed distance of each region of a multi-body extrude: the region's depth
+    /// override (or the feature's distance), negated by `flip_direction` and by a
+    /// Reverse region direction.
+    fn resolve_region_specs(&self, feature: &Feature, specs: &[RegionSpec]) -> Vec<ResolvedRegionSpec> {
+        let distance = self.resolve_float_param(feature, "distance", 10.0);
+        let flip = matches!(feature.parameters.get("flip_direction"), Some(ParameterValue::Bool(true)));
+        specs.iter().map(|spec| {
+            let depth = spec.depth_override.as_ref()
+                .map(|expr| crate::variables::evaluator::evaluate(expr, &self.variables).unwrap_or(distance))
+                .unwrap_or(distance);
+            let sign = if flip != (spec.direction == RegionDirection::Reverse) { -1.0 } else { 1.0 };
+            ResolvedRegionSpec { region_key: spec.region_key.clone(), distance: sign * depth, centroid: spec.centroid }
+        }).collect()
+    }
+
+    /// Pattern a sketch onto a stack of parallel planes.
+    /// Produces `count` sketches in total: the source sketch is instance 0 and `count - 1`
+    /// new sketch features are added at `i * spacing` along `normal`, each a copy of the
+    /// source geometry and constraints. Feature and entity ids are derived from the source
+    /// id and instance index, so repeating the pattern yields the same ids.
+    /// Returns the ids of the newly created sketch features.
+    pub fn pattern_sketch_on_planes(
+        &mut self,
+        sketch_id: EntityId,
+        normal: [f64; 3],
+        count: usize,
+        spacing: f64,
+    ) -> Result<Vec<EntityId>, String> {
+        use super::types::{FeatureType, ParameterValue};
+
+        let source = self.nodes.get(&sketch_id).ok_or_else(|| "Feature not found".to_string())?;
+        if source.feature_type != FeatureType::Sketch {
+            return Err(format!("Feature '{}' is not a sketch", source.name));
+        }
+        let sketch = match source.parameters.get("sketch_data") {
+            Some(ParameterValue::Sketch(s)) => s.clone(),
+            _ => return Err(format!("Sketch '{}' has no sketch data", source.name)),
+        };
+        let direction = crate::geometry::Vector3::new(normal[0], normal[1], normal[2]);
+        let length = direction.norm();
+        if length < 1e-9 {
+            return Err("Pattern normal must be non-zero".to_string());
+        }
+        let direction = direction / length;
+        if count < 2 {
+            return Err("Pattern count must be at least 2".to_string());
+        }
+
+        let source_name = source.name.clone();
+        let seed = |i: usize| format!("{}:plane_pattern:{}", sketch_id, i);
+        if (1..count).any(|i| self.nodes.contains_key(&EntityId::new_deterministic(&seed(i)))) {
+            return Err(format!("Sketch '{}' is already patterned", source_name));
+        }
+
+        let mut after = sketch_id;
+        let mut created = Vec::with_capacity(count - 1);
+        for i in 1..count {
+            let seed = seed(i);
+            let mut copy = sketch.with_remapped_entity_ids(|id| {
+                EntityId::new_deterministic(&format!("{}:{}", seed, id))
+            });
+            copy.plane.origin = sketch.plane.origin + direction * (spacing * i as f64);
+
+            let mut feature = Feature::new(&format!("{} ({})", source_name, i + 1), FeatureType::Sketch)
+                .with_param("sketch_data", ParameterValue::Sketch(copy));
+            feature.id = EntityId::new_deterministic(&seed);
+
+            // Keep the instances grouped right after the source in the feature tree
+            let id = feature.id;
+            self.insert_node_at(feature, Some(after));
+            after = id;
+            created.push(id);
+        }
+        Ok(created)
+    }
+
+    /// Move the stored sketches' projected geometry to where the model now
+    /// puts it (see `Sketch::reproject_external`), so sketches solved between
+    /// regenerations hold against the current model. Call after a successful
+    /// evaluation. Returns the number of sketches whose projections moved.
+    pub fn reproject_sketches(
+        &mut self,
+        topology_manifest: &HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    ) -> usize {
+        use super::types::ParameterValue;
+
+        let mut changed = 0;
+        for feature in self.nodes.values_mut() {
+            let Some(ParameterValue::Sketch(sketch)) = feature.parameters.get_mut("sketch_data") else { continue };
+            if sketch.external_references.is_empty() {
+                continue;
+            }
+            let before = sketch.entities.clone();
+            sketch.reproject_external(topology_manifest);
+            if sketch.entities != before {
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Split a line or arc of a sketch feature (see `sketch::edit::split_entity`).
+    /// Features consuming the sketch whose `profiles` selection named the original
+    /// entity are updated to select both halves.
+    /// Returns the split result and the ids of the updated features.
+    pub fn split_sketch_entity(
+        &mut self,
+        sketch_id: EntityId,
+        entity_id: EntityId,
+        at: crate::sketch::edit::SplitAt,
+    ) -> Result<(crate::sketch::edit::SplitResult, Vec<EntityId>), String> {
+        use super::types::ParameterValue;
+
+        let node = self.nodes.get_mut(&sketch_id).ok_or_else(|| "Feature not found".to_string())?;
+        let result = match node.parameters.get_mut("sketch_data") {
+            Some(ParameterValue::Sketch(sketch)) => crate::sketch::edit::split_entity(sketch, entity_id, at)?,
+            _ => return Err(format!("Feature '{}' is not a sketch", node.name)),
+        };
+
+        let original = entity_id.to_string();
+        let halves: Vec<String> = result.halves.iter().map(|id| id.to_string()).collect();
+        let mut updated = Vec::new();
+        for feature in self.nodes.values_mut().filter(|f| f.dependencies.contains(&sketch_id)) {
+            let Some(ParameterValue::List(profiles)) = feature.parameters.get_mut("profiles") else { continue };
+            if let Some(pos) = profiles.iter().position(|p| *p == original) {
+                profiles.splice(pos..=pos, halves.iter().cloned());
+                profiles.dedup();
+                updated.push(feature.id);
+            }
+        }
+        Ok((result, updated))
+    }
+
     /// Set rollback point to a specific feature (inclusive).
     /// Pass None to disable rollback and show full model.
     /// Returns true if the feature exists, false otherwise.
@@ -669,6 +1118,13 @@ impl FeatureGraph {
         true
     }
 
+    /// The program regeneration would run with the rollback bar at `rollback`
+    /// (inclusive; None for the whole model), for looking at an earlier point
+    /// in the history. The graph, its own rollback bar included, is untouched.
+    pub fn program_at(&self, rollback: Option<EntityId>) -> Program {
+        self.snapshot_for_serialization().program_at(rollback)
+    }
+
     /// Get the index of a feature in the sorted order (for UI display).
     /// Returns None if feature not found or sort order not computed.
     pub fn get_feature_index(&self, id: EntityId) -> Option<usize> {
@@ -686,18 +1142,67 @@ impl FeatureGraph {
         vec![]
     }
 
-    /// Collects all topological IDs referenced by any feature in the graph.
+    /// Collects all topological IDs referenced by active features.
     /// This is used to validate that referenced geometry still exists after regeneration.
-    pub fn collect_all_references(&self) -> Vec<crate::topo::naming::TopoId> {
+    /// Suppressed and rolled-back features (and their dependents) don't demand their
+    /// references, so intentionally absent geometry isn't reported as a zombie.
+    /// Each reference comes with the feature and parameter path holding it.
+    pub fn collect_all_references(&self) -> Vec<crate::topo::registry::ReferenceSite> {
+        let states = self.feature_states();
         let mut all_refs = Vec::new();
-        for feature in self.nodes.values() {
-            if !feature.suppressed {
-                all_refs.extend(feature.collect_references());
+        for id in &self.sort_order {
+            if states.get(id) != Some(&FeatureState::Active) {
+                continue;
+            }
+            if let Some(feature) = self.nodes.get(id) {
+                all_refs.extend(feature.reference_paths().into_iter().map(|(param_path, topo_id)| {
+                    crate::topo::registry::ReferenceSite { id: topo_id, owner: feature.id, param_path }
+                }));
             }
         }
         all_refs
     }
 
+    /// Repoint a feature from `old` to `new`, e.g. to repair a reference the
+    /// zombie check reports as lost. Rewrites reference parameters and the
+    /// sources of projected sketch geometry. Errors if the feature doesn't
+    /// reference `old`, or if `new` is a different kind of entity.
+    pub fn replace_reference(
+        &mut self,
+        feature_id: EntityId,
+        old: crate::topo::naming::TopoId,
+        new: crate::topo::naming::TopoId,
+    ) -> Result<(), String> {
+        let feature = self.nodes.get_mut(&feature_id).ok_or_else(|| "Feature not found".to_string())?;
+        if old.rank != new.rank {
+            return Err(format!("Cannot replace a {:?} reference with a {:?}", old.rank, new.rank));
+        }
+        let mut replaced = 0;
+        for value in feature.parameters.values_mut() {
+            match value {
+                ParameterValue::Reference(id) if *id == old => {
+                    *id = new;
+                    replaced += 1;
+                }
+                ParameterValue::Sketch(sketch) => {
+                    for id in sketch.external_references.values_mut().filter(|id| **id == old) {
+                        *id = new;
+                        replaced += 1;
+                    }
+                    for face_offset in sketch.face_offsets.iter_mut().filter(|f| f.face == old) {
+                        face_offset.face = new;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if replaced == 0 {
+            return Err(format!("Feature '{}' does not reference {:?}", feature.name, old));
+        }
+        feature.error = None;
+        Ok(())
+    }
+
     /// Get all features that depend on the given feature (its dependents/children).
     pub fn get_dependents(&self, id: EntityId) -> Vec<EntityId> {
         self.nodes.values()
@@ -706,6 +1211,31 @@ impl FeatureGraph {
             .collect()
     }
 
+    /// Export the graph with explicit forward and back edges. Edges to
+    /// features missing from the graph are left out. Err(cycle_ids) when
+    /// features depend on each other in a cycle, as for `sort`.
+    pub fn dependency_graph(&self) -> Result<DependencyGraph, Vec<EntityId>> {
+        // Same order regenerate walks
+        let order = self.topological_order()?;
+
+        let nodes = order.iter().map(|id| {
+            let feature = &self.nodes[id];
+            DependencyNode {
+                id: *id,
+                name: feature.name.clone(),
+                feature_type: feature.feature_type.clone(),
+                depends_on: feature.dependencies.iter().copied()
+                    .filter(|dep| self.nodes.contains_key(dep))
+                    .collect(),
+                dependents: order.iter().copied()
+                    .filter(|other| self.nodes[other].dependencies.contains(id))
+                    .collect(),
+            }
+        }).collect();
+
+        Ok(DependencyGraph { nodes, order })
+    }
+
     /// Attempts to move a feature to a new position in sort_order.
     /// Returns Err if the move would violate dependency constraints:
     /// - A feature cannot be placed before any of its dependencies (parents)
@@ -827,6 +1357,76 @@ mod tests {
         assert!(sorted.contains(&branch_b.id));
     }
 
+    #[test]
+    fn test_dependency_graph_of_chain() {
+        let mut graph = FeatureGraph::new();
+        let sketch = create_feature("Sketch", vec![]);
+        let extrude = create_feature("Extrude", vec![sketch.id]);
+        let fillet = create_feature("Fillet", vec![extrude.id]);
+        // Added out of order: the export follows the topological order
+        graph.add_node(fillet.clone());
+        graph.add_node(extrude.clone());
+        graph.add_node(sketch.clone());
+
+        let deps = graph.dependency_graph().unwrap();
+        assert_eq!(deps.order, vec![sketch.id, extrude.id, fillet.id]);
+        let names: Vec<&str> = deps.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["Sketch", "Extrude", "Fillet"]);
+
+        assert!(deps.nodes[0].depends_on.is_empty());
+        assert_eq!(deps.nodes[0].dependents, vec![extrude.id]);
+        assert_eq!(deps.nodes[1].depends_on, vec![sketch.id]);
+        assert_eq!(deps.nodes[1].dependents, vec![fillet.id]);
+        assert_eq!(deps.nodes[2].depends_on, vec![extrude.id]);
+        assert!(deps.nodes[2].dependents.is_empty());
+
+        let json = serde_json::to_value(&deps).unwrap();
+        assert_eq!(json["nodes"][1]["type"], "Sketch");
+        assert_eq!(json["nodes"][1]["depends_on"][0], serde_json::to_value(sketch.id).unwrap());
+    }
+
+    #[test]
+    fn test_replace_reference_clears_zombie() {
+        use crate::evaluator::Runtime;
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+        use crate::topo::naming::{TopoId, TopoRank};
+        use crate::topo::{IdGenerator, TopoRegistry};
+
+        let mut base_sketch = Sketch::new(SketchPlane::default());
+        let base_line = base_sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let base = Feature::new("Base", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(base_sketch));
+        // Edge of a line that no longer exists
+        let dangling = TopoId::new(EntityId::new(), 0, TopoRank::Edge);
+        let mut user = Feature::new("User", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(Sketch::new(SketchPlane::default())))
+            .with_param("ref", ParameterValue::Reference(dangling));
+        user.dependencies = vec![base.id];
+        let user_id = user.id;
+
+        let mut graph = FeatureGraph::new();
+        graph.add_node(base);
+        graph.add_node(user);
+
+        let zombies = |graph: &mut FeatureGraph| {
+            let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("Replace")).unwrap();
+            let mut registry = TopoRegistry::new();
+            for entity in result.topology_manifest.values() {
+                registry.register(entity.clone());
+            }
+            registry.validate_references(&graph.collect_all_references())
+        };
+        assert_eq!(zombies(&mut graph).iter().map(|z| (z.id, z.owner)).collect::<Vec<_>>(), vec![(dangling, user_id)]);
+
+        let edge = TopoId::new(base_line, 0, TopoRank::Edge);
+        let vertex = TopoId::new(base_line, 0, TopoRank::Vertex);
+        assert!(graph.replace_reference(user_id, dangling, vertex).is_err(), "An edge can't become a vertex");
+        graph.replace_reference(user_id, dangling, edge).unwrap();
+        assert_eq!(graph.nodes[&user_id].parameters["ref"], ParameterValue::Reference(edge));
+        assert!(zombies(&mut graph).is_empty());
+        assert!(graph.replace_reference(user_id, dangling, edge).is_err(), "Nothing left to replace");
+    }
+
     #[test]
     fn test_cycle_detection() {
         let mut graph = FeatureGraph::new();
@@ -845,6 +1445,7 @@ mod tests {
         graph.add_node(feat1);
         graph.add_node(feat2);
 
+        assert!(graph.dependency_graph().is_err(), "The export doesn't leave out the cycle");
         let result = graph.sort();
         assert!(result.is_err(), "Should detect cycle");
     }
@@ -1037,7 +1638,8 @@ mod tests {
         
         let refs = graph.collect_all_references();
         assert_eq!(refs.len(), 1);
-        assert_eq!(refs[0], ref_id);
+        assert_eq!(refs[0].id, ref_id);
+        assert_eq!(refs[0].param_path, "target");
     }
 
     #[test]
@@ -1099,6 +1701,37 @@ mod tests {
         assert!(!graph.set_rollback(Some(invalid_id)), "set_rollback should return false for invalid ID");
     }
 
+    #[test]
+    fn test_program_at_matches_rolling_back_without_moving_the_bar() {
+        use crate::evaluator::Runtime;
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+        use crate::topo::IdGenerator;
+
+        let mut graph = FeatureGraph::new();
+        let ids: Vec<EntityId> = (0..4).map(|i| {
+            let mut sketch = Sketch::new(SketchPlane::default());
+            sketch.add_entity(SketchGeometry::Line { start: [0.0, i as f64], end: [10.0, i as f64] });
+            let feature = Feature::new(&format!("Sketch{}", i + 1), FeatureType::Sketch)
+                .with_param("sketch_data", ParameterValue::Sketch(sketch));
+            let id = feature.id;
+            graph.add_node(feature);
+            id
+        }).collect();
+        graph.set_rollback(Some(ids[3]));
+        let hash = |program: &Program| Runtime::new().evaluate(program, &IdGenerator::new("TestProgramAt")).unwrap().summary().geometry_hash;
+
+        let at_second = graph.program_at(Some(ids[1]));
+        let mut rolled_back = graph.clone();
+        rolled_back.set_rollback(Some(ids[1]));
+        let expected = rolled_back.regenerate();
+        assert_eq!(at_second.to_string(), expected.to_string());
+        assert_eq!(hash(&at_second), hash(&expected));
+        assert_ne!(hash(&at_second), hash(&graph.program_at(None)), "Later sketches add geometry");
+
+        assert_eq!(graph.rollback_point, Some(ids[3]), "The bar stays where the user put it");
+        assert_eq!(graph.program_at(Some(ids[3])).to_string(), graph.regenerate().to_string());
+    }
+
     #[test]
     fn test_reorder_feature() {
         let mut graph = FeatureGraph::new();
@@ -1223,4 +1856,506 @@ mod tests {
             } else { panic!("Expected Call expression"); }
         }
     }
+
+    #[test]
+    fn test_pattern_sketch_on_planes_regenerates_offset_stack() {
+        use crate::evaluator::Runtime;
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+        use crate::topo::IdGenerator;
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line_id = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let source = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let source_id = source.id;
+
+        let mut graph = FeatureGraph::new();
+        graph.add_node(source);
+        let created = graph.pattern_sketch_on_planes(source_id, [0.0, 0.0, 2.0], 3, 5.0).unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(graph.sort_order, vec![source_id, created[0], created[1]]);
+
+        // Ids are derived from the source, so a second pattern of an identical graph matches
+        let mut replay = graph.clone();
+        replay.remove_node(created[0]).unwrap();
+        replay.remove_node(created[1]).unwrap();
+        assert_eq!(replay.pattern_sketch_on_planes(source_id, [0.0, 0.0, 1.0], 3, 5.0).unwrap(), created);
+        assert!(graph.pattern_sketch_on_planes(source_id, [0.0, 0.0, 1.0], 3, 5.0).is_err());
+
+        let program = graph.regenerate();
+        let result = Runtime::new().evaluate(&program, &IdGenerator::new("Pattern")).unwrap();
+        let tess = &result.tessellation;
+        // Sketch lines only, not the origin axes
+        let mut zs: Vec<f32> = tess.line_indices.chunks_exact(2).zip(&tess.line_ids)
+            .filter(|(_, id)| !tess.is_reference(id))
+            .map(|(seg, _)| tess.vertices[seg[0] as usize * 3 + 2])
+            .collect();
+        zs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(zs, vec![0.0, 5.0, 10.0]);
+
+        // Each copy owns distinct entity ids so its edges don't shadow the source's
+        let line_owners: HashSet<EntityId> = tess.line_ids.iter().filter(|t| !tess.is_reference(t)).map(|t| t.feature_id).collect();
+        assert_eq!(line_owners.len(), 3);
+        assert!(line_owners.contains(&line_id));
+    }
+
+    /// Truth table over (T suppressed?, T before/after the rollback bar, T has a dependent?).
+    ///
+    /// Graph, in order: Base (sketch with two lines) -> T (sketch with its own line,
+    /// referencing Base's first line) -> [D (depends on T, references T's line)] -> Tail.
+    /// "before" puts the rollback bar on Tail, "after" puts it on Base.
+    #[test]
+    fn test_suppression_rollback_truth_table() {
+        use crate::evaluator::ast::Statement;
+        use crate::evaluator::Runtime;
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+        use crate::topo::naming::{TopoId, TopoRank};
+        use crate::topo::{IdGenerator, TopoRegistry};
+
+        let line = |sketch: &mut Sketch, y: f64| {
+            sketch.add_entity(SketchGeometry::Line { start: [0.0, y], end: [10.0, y] })
+        };
+        let edge = |entity: EntityId| TopoId::new(entity, 0, TopoRank::Edge);
+
+        for suppressed in [false, true] {
+            for before_rollback in [true, false] {
+                for has_dependent in [false, true] {
+                    let case = format!("suppressed={} before={} dependent={}", suppressed, before_rollback, has_dependent);
+
+                    let mut base_sketch = Sketch::new(SketchPlane::default());
+                    let base_line = line(&mut base_sketch, 0.0);
+                    line(&mut base_sketch, 5.0);
+                    let base = Feature::new("Base", FeatureType::Sketch)
+                        .with_param("sketch_data", ParameterValue::Sketch(base_sketch));
+
+                    let mut t_sketch = Sketch::new(SketchPlane::default());
+                    let t_line = line(&mut t_sketch, 20.0);
+                    let mut t = Feature::new("T", FeatureType::Sketch)
+                        .with_param("sketch_data", ParameterValue::Sketch(t_sketch))
+                        .with_param("ref", ParameterValue::Reference(edge(base_line)));
+                    t.dependencies = vec![base.id];
+                    t.suppressed = suppressed;
+
+                    let mut d_sketch = Sketch::new(SketchPlane::default());
+                    line(&mut d_sketch, 30.0);
+                    let mut d = Feature::new("D", FeatureType::Sketch)
+                        .with_param("sketch_data", ParameterValue::Sketch(d_sketch))
+                        .with_param("ref", ParameterValue::Reference(edge(t_line)));
+                    d.dependencies = vec![t.id];
+
+                    let tail = Feature::new("Tail", FeatureType::Sketch);
+
+                    let mut graph = FeatureGraph::new();
+                    graph.add_node(base.clone());
+                    graph.add_node(t.clone());
+                    if has_dependent {
+                        graph.add_node(d.clone());
+                    }
+                    graph.add_node(tail.clone());
+                    graph.set_rollback(Some(if before_rollback { tail.id } else { base.id }));
+
+                    // Feature states
+                    let states = graph.feature_states();
+                    let expected_t = match (suppressed, before_rollback) {
+                        (true, _) => FeatureState::Suppressed,
+                        (false, true) => FeatureState::Active,
+                        (false, false) => FeatureState::RolledBack,
+                    };
+                    assert_eq!(states[&t.id], expected_t, "{}", case);
+                    if has_dependent {
+                        let expected_d = match (suppressed, before_rollback) {
+                            (_, false) => FeatureState::RolledBack,
+                            (true, true) => FeatureState::SuppressedUpstream,
+                            (false, true) => FeatureState::Active,
+                        };
+                        assert_eq!(states[&d.id], expected_d, "{}", case);
+                    }
+
+                    // Program statements: one assignment per active feature
+                    let program = graph.regenerate();
+                    let assigned: Vec<EntityId> = [base.id, t.id, d.id, tail.id].into_iter()
+                        .filter(|id| program.statements.iter().any(|s| {
+                            matches!(s, Statement::Assignment { name, .. } if name == &format!("feat_{}", id))
+                        }))
+                        .collect();
+                    let t_runs = !suppressed && before_rollback;
+                    let mut expected_assigned = vec![base.id];
+                    if t_runs {
+                        expected_assigned.push(t.id);
+                    }
+                    if t_runs && has_dependent {
+                        expected_assigned.push(d.id);
+                    }
+                    if before_rollback {
+                        expected_assigned.push(tail.id);
+                    }
+                    assert_eq!(assigned, expected_assigned, "{}", case);
+                    let feature_statements = program.statements.iter()
+                        .filter(|s| matches!(s, Statement::Assignment { .. }))
+                        .count();
+                    assert_eq!(feature_statements, expected_assigned.len(), "{}", case);
+
+                    // Required references
+                    let refs: HashSet<TopoId> = graph.collect_all_references().into_iter().map(|site| site.id).collect();
+                    let mut expected_refs = HashSet::new();
+                    if t_runs {
+                        expected_refs.insert(edge(base_line));
+                        if has_dependent {
+                            expected_refs.insert(edge(t_line));
+                        }
+                    }
+                    assert_eq!(refs, expected_refs, "{}", case);
+
+                    // Zombie payload (as computed by the backend): never any phantoms
+                    let result = Runtime::new().evaluate(&program, &IdGenerator::new("Truth")).unwrap();
+                    let mut registry = TopoRegistry::new();
+                    for entity in result.topology_manifest.values() {
+                        registry.register(entity.clone());
+                    }
+                    let zombies = registry.validate_references(&graph.collect_all_references());
+                    assert!(zombies.is_empty(), "{}: unexpected zombies {:?}", case, zombies);
+
+                    // Restoring everything brings every feature back deterministically
+                    if suppressed {
+                        graph.toggle_suppression(t.id).unwrap();
+                    }
+                    graph.set_rollback(None);
+                    assert!(graph.feature_states().values().all(|s| *s == FeatureState::Active), "{}", case);
+                    let restored = graph.regenerate();
+                    let restored_count = restored.statements.iter()
+                        .filter(|s| matches!(s, Statement::Assignment { .. }))
+                        .count();
+                    assert_eq!(restored_count, if has_dependent { 4 } else { 3 }, "{}", case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_suppressed_rollback_point_still_stops_regeneration() {
+        let mut graph = FeatureGraph::new();
+        let f1 = create_feature("F1", vec![]);
+        let mut f2 = create_feature("F2", vec![]);
+        f2.suppressed = true;
+        let f3 = create_feature("F3", vec![]);
+        graph.add_node(f1.clone());
+        graph.add_node(f2.clone());
+        graph.add_node(f3.clone());
+        graph.set_rollback(Some(f2.id));
+
+        let program = graph.regenerate();
+        let emitted = |id: EntityId| program.statements.iter().any(|s| {
+            matches!(s, crate::evaluator::ast::Statement::Assignment { name, .. } if name == &format!("feat_{}", id))
+        });
+        assert!(emitted(f1.id));
+        assert!(!emitted(f2.id));
+        assert!(!emitted(f3.id), "Features after a suppressed rollback point must stay rolled back");
+    }
+
+    #[test]
+    fn test_region_specs_extrude_each_region_to_its_own_depth() {
+        use crate::features::types::RegionSpec;
+        use crate::sketch::regions::{find_regions, summarize_regions};
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for x0 in [0.0, 20.0] {
+            let c = [[x0, 0.0], [x0 + 10.0, 0.0], [x0 + 10.0, 10.0], [x0, 10.0]];
+            for i in 0..4 {
+                sketch.add_entity(SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] });
+            }
+        }
+        let summary = summarize_regions(&find_regions(&sketch.entities));
+        let key_at = |x: f64| summary.iter().find(|r| (r.region.centroid[0] - x).abs() < 1.0).unwrap().stable_id.clone();
+        let spec = |region_key: String, depth_override: Option<&str>| RegionSpec {
+            region_key,
+            depth_override: depth_override.map(str::to_string),
+            direction: RegionDirection::Normal,
+            centroid: None,
+        };
+
+        let mut graph = FeatureGraph::new();
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0))
+            .with_param("region_specs", ParameterValue::RegionSpecs(vec![
+                spec(key_at(5.0), None),
+                spec(key_at(25.0), Some("10 + 5")),
+                spec("region_missing".to_string(), None),
+            ]));
+        extrude.dependencies = vec![sketch_feature.id];
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+
+        let program = graph.regenerate();
+        let result = crate::evaluator::runtime::Runtime::new()
+            .evaluate(&program, &crate::topo::IdGenerator::new("region_specs"))
+            .unwrap();
+
+        let bodies = &result.tessellation.bodies;
+        assert_eq!(bodies.len(), 2, "One body per matched region");
+        assert_ne!(bodies[0].id, bodies[1].id);
+        assert!(bodies.iter().all(|b| result.topology_manifest.contains_key(&b.id)), "Bodies are registered for referencing");
+        let expected = [([0.0, 0.0, 0.0], [10.0, 10.0, 5.0]), ([20.0, 0.0, 0.0], [30.0, 10.0, 15.0])];
+        for (body, (min, max)) in bodies.iter().zip(expected) {
+            let (lo, hi) = result.tessellation.body_bounds(&body.id).unwrap();
+            for k in 0..3 {
+                assert!((lo[k] - min[k]).abs() < 1e-4 && (hi[k] - max[k]).abs() < 1e-4, "Body bounds {:?} - {:?}", lo, hi);
+            }
+        }
+        assert!(result.logs.iter().any(|l| l.starts_with("Warning") && l.contains("region_missing")));
+    }
+
+    /// Sketch of 10x10 squares at each of `xs`, extruded by `distance`. Returns the extrude's id.
+    fn add_squares_extrude(graph: &mut FeatureGraph, xs: &[f64], distance: f64) -> EntityId {
+        use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for &x0 in xs {
+            let c = [[x0, 0.0], [x0 + 10.0, 0.0], [x0 + 10.0, 10.0], [x0, 10.0]];
+            for i in 0..4 {
+                sketch.add_entity(SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] });
+            }
+        }
+        let sketch_feature = Feature::new("Sketch", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(distance));
+        extrude.dependencies = vec![sketch_feature.id];
+        let id = extrude.id;
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        id
+    }
+
+    fn evaluate(graph: &mut FeatureGraph) -> crate::evaluator::runtime::EvaluationResult {
+        crate::evaluator::runtime::Runtime::new()
+            .evaluate(&graph.regenerate(), &crate::topo::IdGenerator::new("bodies"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_suppressing_an_earlier_feature_keeps_later_ids() {
+        let mut graph = FeatureGraph::new();
+        let torus = Feature::new("Torus", FeatureType::Torus);
+        let torus_id = torus.id;
+        graph.add_node(torus);
+        let extrude = add_squares_extrude(&mut graph, &[0.0], 5.0);
+        let sketch = graph.nodes[&extrude].dependencies[0];
+        let namespaces: Vec<EntityId> = [sketch, extrude].iter()
+            .map(|id| crate::topo::IdGenerator::new(&id.to_string()).next_id())
+            .collect();
+
+        // One generator across regenerations, as the server keeps it
+        let runtime = crate::evaluator::runtime::Runtime::new();
+        let generator = crate::topo::IdGenerator::new("suppression");
+        let later_ids = |graph: &mut FeatureGraph| {
+            let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+            let mut ids: Vec<crate::topo::naming::TopoId> = result.topology_manifest.keys()
+                .chain(&result.tessellation.triangle_ids)
+                .chain(&result.tessellation.line_ids)
+                .filter(|id| namespaces.contains(&id.feature_id))
+                .copied()
+                .collect();
+            ids.sort_by_key(|id| (id.feature_id.0, id.rank, id.local_id));
+            ids.dedup();
+            ids
+        };
+
+        let before = later_ids(&mut graph);
+        assert!(!before.is_empty());
+        graph.toggle_suppression(torus_id).unwrap();
+        assert_eq!(later_ids(&mut graph), before, "Suppressing the torus");
+        graph.toggle_suppression(torus_id).unwrap();
+        assert_eq!(later_ids(&mut graph), before, "Unsuppressing it again");
+    }
+
+    #[test]
+    fn test_hidden_extrude_keeps_mass_but_is_tagged() {
+        let mut graph = FeatureGraph::new();
+        let extrude = add_squares_extrude(&mut graph, &[0.0], 5.0);
+        assert_eq!(graph.toggle_visibility(extrude), Ok(true));
+
+        let mut tess = evaluate(&mut graph).tessellation;
+        assert!((crate::analysis::mesh::mesh_volume(&tess) - 500.0).abs() < 1e-3, "Hidden geometry still has volume");
+        graph.tag_hidden(&mut tess);
+        let namespace = crate::topo::IdGenerator::new(&extrude.to_string()).next_id();
+        assert_eq!(tess.hidden_features, vec![namespace]);
+        let render: serde_json::Value = serde_json::to_value(&tess).unwrap();
+        assert_eq!(render["hidden_features"], serde_json::json!([namespace]));
+        assert!(tess.triangle_ids.iter().any(|id| id.feature_id == namespace), "Triangles are still sent");
+
+        assert_eq!(graph.toggle_visibility(extrude), Ok(false));
+        graph.tag_hidden(&mut tess);
+        assert!(tess.hidden_features.is_empty());
+        assert!(graph.toggle_visibility(EntityId::new()).is_err());
+    }
+
+    #[test]
+    fn test_isolate_second_extrude_renders_only_its_faces() {
+        let mut graph = FeatureGraph::new();
+        let first = add_squares_extrude(&mut graph, &[0.0], 5.0);
+        let second = add_squares_extrude(&mut graph, &[30.0], 5.0);
+        let full = evaluate(&mut graph).tessellation;
+
+        let mut isolated = full.clone();
+        graph.isolate(second, &mut isolated).unwrap();
+        let namespace = crate::topo::IdGenerator::new(&second.to_string()).next_id();
+        assert!(!isolated.triangle_ids.is_empty());
+        assert!(isolated.triangle_ids.iter().all(|id| id.feature_id == namespace));
+        assert!(isolated.line_ids.iter().all(|id| id.feature_id == namespace));
+        assert_eq!(isolated.indices.len(), isolated.triangle_ids.len() * 3);
+        assert_eq!(isolated.line_indices.len(), isolated.line_ids.len() * 2);
+        assert_eq!(
+            isolated.triangle_ids.len(),
+            full.triangle_ids.iter().filter(|id| id.feature_id == namespace).count(),
+            "All of the feature's faces stay"
+        );
+        assert_eq!(isolated.body_ids(), vec![full.body_ids()[1]]);
+        assert!((crate::analysis::mesh::mesh_volume(&isolated) - 500.0).abs() < 1e-3, "Only one box is left");
+
+        let first_namespace = crate::topo::IdGenerator::new(&first.to_string()).next_id();
+        assert!(full.triangle_ids.iter().any(|id| id.feature_id == first_namespace));
+        assert!(graph.isolate(EntityId::new(), &mut isolated).is_err());
+    }
+
+    #[test]
+    fn test_disjoint_extrudes_are_separate_bodies() {
+        let mut graph = FeatureGraph::new();
+        add_squares_extrude(&mut graph, &[0.0], 5.0);
+        add_squares_extrude(&mut graph, &[30.0], 5.0);
+        let result = evaluate(&mut graph);
+
+        let tess = &result.tessellation;
+        let bodies = tess.body_ids();
+        assert_eq!(bodies.len(), 2);
+        assert_ne!(bodies[0], bodies[1]);
+        assert!(bodies.iter().all(|b| result.topology_manifest.contains_key(b)));
+        for face in tess.triangle_ids.iter().filter(|id| !tess.is_reference(id)) {
+            assert!(tess.body_of(face).is_some_and(|b| b.feature_id == face.feature_id), "Every solid face belongs to its feature's body");
+        }
+        assert_eq!(evaluate(&mut graph).tessellation.body_ids(), bodies, "Body ids are stable across regenerations");
+    }
+
+    #[test]
+    fn test_boolean_target_body_consumes_only_that_body() {
+        let mut graph = FeatureGraph::new();
+        let pair = add_squares_extrude(&mut graph, &[0.0, 20.0], 5.0);
+        // Drill through the right square; the tool overshoots so no faces are coplanar
+        let mut hole = crate::sketch::types::Sketch::new(Default::default());
+        hole.add_entity(crate::sketch::types::SketchGeometry::Circle { center: [25.0, 5.0], radius: 2.0 });
+        let hole_sketch = Feature::new("Hole sketch", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(hole));
+        let mut tool = Feature::new("Hole", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(9.0))
+            .with_param("start_offset", ParameterValue::Float(-2.0));
+        tool.dependencies = vec![hole_sketch.id];
+        let tool_id = tool.id;
+        graph.add_node(hole_sketch);
+        graph.add_node(tool);
+        // Without region specs the two squares extrude as one body; split them
+        let Some(ParameterValue::Sketch(sketch)) = graph.nodes[&graph.nodes[&pair].dependencies[0]].parameters.get("sketch_data") else {
+            unreachable!()
+        };
+        let regions = crate::sketch::regions::summarize_regions(&crate::sketch::regions::find_regions(&sketch.entities));
+        let specs = regions.iter().map(|r| crate::features::types::RegionSpec {
+            region_key: r.stable_id.clone(),
+            depth_override: None,
+            direction: RegionDirection::Normal,
+            centroid: None,
+        }).collect();
+        graph.nodes.get_mut(&pair).unwrap().parameters.insert("region_specs".to_string(), ParameterValue::RegionSpecs(specs));
+
+        let before = evaluate(&mut graph);
+        let right = before.tessellation.body_ids().into_iter()
+            .find(|b| before.tessellation.body_bounds(b).is_some_and(|(lo, _)| (lo.x - 20.0).abs() < 1e-4))
+            .expect("Region body at x = 20");
+
+        let boolean = Feature::new("Drill", FeatureType::Boolean)
+            .with_param("operation", ParameterValue::String("Subtract".to_string()))
+            .with_param("body_list", ParameterValue::List(vec![pair.to_string(), tool_id.to_string()]))
+            .with_param("target_body", ParameterValue::Reference(right));
+        graph.add_node(boolean);
+        let result = evaluate(&mut graph);
+
+        let tess = &result.tessellation;
+        let mut spans: Vec<(f64, f64)> = tess.body_ids().iter()
+            .filter_map(|b| tess.body_bounds(b))
+            .map(|(lo, hi)| (lo.x.round(), hi.x.round()))
+            .collect();
+        spans.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(spans, vec![(0.0, 10.0), (20.0, 30.0)], "The other region stays; the drilled one replaces its body");
+    }
+
+    /// A cylinder of radius 5 along X (x from 0 to 10) and a revolve whose
+    /// V-shaped profile cuts a groove around its middle. Returns the
+    /// graph and the cylinder and groove feature ids.
+    fn grooved_cylinder() -> (FeatureGraph, EntityId, EntityId) {
+        use crate::sketch::types::{Sketch, SketchGeometry};
+        let mut graph = FeatureGraph::new();
+        let mut revolve = |name: &str, outline: &[[f64; 2]], operation: &str| {
+            let mut sketch = Sketch::new(Default::default());
+            for (i, start) in outline.iter().enumerate() {
+                sketch.add_entity(SketchGeometry::Line { start: *start, end: outline[(i + 1) % outline.len()] });
+            }
+            let sketch = Feature::new(&format!("{} sketch", name), FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+            let mut feature = Feature::new(name, FeatureType::Revolve)
+                .with_param("axis", ParameterValue::String("X".to_string()))
+                .with_param("operation", ParameterValue::String(operation.to_string()));
+            feature.dependencies = vec![sketch.id];
+            let id = feature.id;
+            graph.add_node(sketch);
+            graph.add_node(feature);
+            id
+        };
+        let cylinder = revolve("Cylinder", &[[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]], "Add");
+        let groove = revolve("Groove", &[[4.0, 5.5], [5.0, 4.0], [6.0, 5.5]], "Cut");
+        (graph, cylinder, groove)
+    }
+
+    #[test]
+    fn test_revolve_cut_subtracts_from_the_body_before_it() {
+        use crate::evaluator::ast::{Call, Expression, Statement, Value};
+        let (mut graph, cylinder, groove) = grooved_cylinder();
+        let program = graph.regenerate();
+        let call = |function: &str| program.statements.iter().find_map(|s| match s {
+            Statement::Expression(Expression::Call(call)) if call.function == function => Some(call.clone()),
+            Statement::Assignment { expr: Expression::Call(call), .. } if call.function == function => Some(call.clone()),
+            _ => None,
+        });
+        let Some(Call { args: consumed, .. }) = call("set_consumed_features") else { panic!("The cylinder is consumed") };
+        assert_eq!(consumed, vec![Expression::Value(Value::Array(vec![Value::String(cylinder.to_string())]))]);
+
+        let revolves: Vec<&Statement> = program.statements.iter()
+            .filter(|s| matches!(s, Statement::Assignment { expr: Expression::Call(c), .. } if c.function == "revolve"))
+            .collect();
+        let Statement::Assignment { name, expr: Expression::Call(cut) } = revolves[1] else { unreachable!() };
+        assert_eq!(name, &format!("feat_{}", groove));
+        assert_eq!(cut.args[3..], [
+            Expression::Value(Value::String(String::new())),
+            Expression::Value(Value::String("Cut".to_string())),
+            Expression::Variable(format!("feat_{}", cylinder)),
+        ]);
+
+        // An explicit target wins over the preceding body
+        let other = Feature::new("Other", FeatureType::Boolean);
+        let other_id = other.id;
+        graph.add_node(other);
+        graph.nodes.get_mut(&groove).unwrap().parameters
+            .insert("body_list".to_string(), ParameterValue::List(vec![other_id.to_string()]));
+        assert_eq!(graph.revolve_cut_target(&graph.nodes[&groove], &|_| true).map(|(var, _)| var), Some(format!("feat_{}", other_id)));
+    }
+
+    #[test]
+    fn test_revolve_cut_grooves_a_cylinder() {
+        let (mut graph, _, groove) = grooved_cylinder();
+        let result = evaluate(&mut graph);
+        assert!(result.feature_errors.is_empty(), "{:?}", result.feature_errors);
+        let grooved = crate::analysis::mesh::mesh_volume(&result.tessellation);
+
+        graph.nodes.get_mut(&groove).unwrap().suppressed = true;
+        let plain = crate::analysis::mesh::mesh_volume(&evaluate(&mut graph).tessellation);
+        // Inside the cylinder the V is a triangle of area 2/3 centred at radius 14/3 (Pappus)
+        let ring = 2.0 * std::f64::consts::PI * (14.0 / 3.0) * (2.0 / 3.0);
+        assert!((plain - grooved - ring).abs() < 0.1 * ring, "Removed {} of {}", plain - grooved, ring);
+    }
 }
@@ -0,0 +1,224 @@
+//! Datum plane and axis features.
+//!
+//! Parameters:
+//! - `datum_type`: DatumPlane: "Offset" (default), "Angle", "ThreePoints", "Midplane";
+//!   DatumAxis: "Edge" (default), "TwoPoints", "PlaneIntersection"
+//! - `ref_a`, `ref_b`, `ref_c`: TopoId references, in the order of the definition's fields
+//! - `distance` (Offset), `angle` in degrees (Angle): Float or Expression
+//! - `size`: edge length of the displayed quad / axis segment
+//! - `last_evaluated`: geometry from the last successful evaluation, maintained by
+//!   `cache_datum_geometry` and used when the sources become zombies
+
+use std::collections::HashMap;
+
+use super::dag::FeatureGraph;
+use super::types::{Feature, FeatureType, ParameterValue};
+use crate::evaluator::ast::{Call, Expression, Value};
+use crate::geometry::datum::{datum_topo_id, DatumAxisDef, DatumPlaneDef, DEFAULT_DATUM_SIZE};
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::registry::KernelEntity;
+
+pub const LAST_EVALUATED: &str = "last_evaluated";
+
+fn reference(feature: &Feature, name: &str) -> Option<TopoId> {
+    match feature.parameters.get(name) {
+        Some(ParameterValue::Reference(id)) => Some(*id),
+        _ => None,
+    }
+}
+
+/// TopoId the runtime registers a datum feature's geometry under, or None for other features
+pub fn datum_geometry_id(feature: &Feature) -> Option<TopoId> {
+    match feature.feature_type {
+        FeatureType::DatumPlane => Some(datum_topo_id(feature.id, TopoRank::Face)),
+        FeatureType::DatumAxis => Some(datum_topo_id(feature.id, TopoRank::Edge)),
+        _ => None,
+    }
+}
+
+impl FeatureGraph {
+    /// Definition JSON for a datum feature, or None if required references are missing
+    fn datum_definition(&self, feature: &Feature) -> Option<String> {
+        let kind = match feature.parameters.get("datum_type") {
+            Some(ParameterValue::String(s)) => s.as_str(),
+            _ => "",
+        };
+        let (a, b, c) = (reference(feature, "ref_a"), reference(feature, "ref_b"), reference(feature, "ref_c"));
+        let json = match feature.feature_type {
+            FeatureType::DatumPlane => serde_json::to_string(&match kind {
+                "Angle" => DatumPlaneDef::Angle {
+                    base: a?,
+                    edge: b?,
+                    angle: self.resolve_float_param(feature, "angle", 0.0),
+                },
+                "ThreePoints" => DatumPlaneDef::ThreePoints { points: [a?, b?, c?] },
+                "Midplane" => DatumPlaneDef::Midplane { a: a?, b: b? },
+                _ => DatumPlaneDef::Offset {
+                    base: a?,
+                    distance: self.resolve_float_param(feature, "distance", 0.0),
+                },
+            }),
+            FeatureType::DatumAxis => serde_json::to_string(&match kind {
+                "TwoPoints" => DatumAxisDef::TwoPoints { a: a?, b: b? },
+                "PlaneIntersection" => DatumAxisDef::PlaneIntersection { a: a?, b: b? },
+                _ => DatumAxisDef::Edge { edge: a? },
+            }),
+            _ => return None,
+        };
+        json.ok()
+    }
+
+    /// Program call for a datum feature: definition, display size, last evaluated geometry
+    pub(crate) fn datum_call(&self, feature: &Feature) -> Option<Call> {
+        let function = match feature.feature_type {
+            FeatureType::DatumPlane => "datum_plane",
+            FeatureType::DatumAxis => "datum_axis",
+            _ => return None,
+        };
+        let cached = match feature.parameters.get(LAST_EVALUATED) {
+            Some(ParameterValue::String(s)) => s.clone(),
+            _ => String::new(),
+        };
+        Some(Call {
+            function: function.to_string(),
+            args: vec![
+                Expression::Value(Value::String(self.datum_definition(feature).unwrap_or_default())),
+                Expression::Value(Value::Number(self.resolve_float_param(feature, "size", DEFAULT_DATUM_SIZE))),
+                Expression::Value(Value::String(cached)),
+            ],
+        })
+    }
+
+    /// Remember each datum's evaluated geometry so it can fall back to it if its
+    /// sources later disappear. Call after a successful evaluation.
+    /// Returns the number of datums whose cached geometry changed.
+    pub fn cache_datum_geometry(&mut self, topology_manifest: &HashMap<TopoId, KernelEntity>) -> usize {
+        let mut changed = 0;
+        for feature in self.nodes.values_mut() {
+            let Some(entity) = datum_geometry_id(feature).and_then(|id| topology_manifest.get(&id)) else { continue };
+            let Ok(json) = serde_json::to_string(&entity.geometry) else { continue };
+            let value = ParameterValue::String(json);
+            if feature.parameters.get(LAST_EVALUATED) != Some(&value) {
+                feature.parameters.insert(LAST_EVALUATED.to_string(), value);
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::{EvaluationResult, Runtime};
+    use crate::sketch::types::{Sketch, SketchEntity, SketchGeometry, SketchPlane};
+    use crate::topo::registry::AnalyticGeometry;
+    use crate::topo::{EntityId, IdGenerator};
+
+    fn rectangle(ids: &[EntityId; 4], w: f64, h: f64) -> Sketch {
+        let corners = [[0.0, 0.0], [w, 0.0], [w, h], [0.0, h]];
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for (i, id) in ids.iter().enumerate() {
+            sketch.entities.push(SketchEntity {
+                id: *id,
+                geometry: SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] },
+                is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
+            });
+        }
+        sketch
+    }
+
+    fn evaluate(graph: &mut FeatureGraph) -> EvaluationResult {
+        let program = graph.regenerate();
+        Runtime::new().evaluate(&program, &IdGenerator::new("datum_test")).unwrap()
+    }
+
+    /// Planar face of the extruded box whose normal points along `sign` * Y
+    fn y_face(result: &EvaluationResult, sign: f64) -> TopoId {
+        result.topology_manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { normal, .. } if normal[1] * sign > 0.9))
+            .map(|e| e.id)
+            .expect("box has a face along Y")
+    }
+
+    fn point_y(result: &EvaluationResult, point: EntityId) -> f64 {
+        match result.topology_manifest[&TopoId::new(point, 0, TopoRank::Vertex)].geometry {
+            AnalyticGeometry::Point { position } => position[1],
+            ref other => panic!("Expected point geometry, got {:?}", other),
+        }
+    }
+
+    /// Box (rectangle extruded along Z) with a midplane datum between its Y faces
+    /// and a sketch containing a single point placed on that datum.
+    fn midplane_model(line_ids: &[EntityId; 4], point: EntityId) -> (FeatureGraph, EntityId, EntityId) {
+        let mut graph = FeatureGraph::new();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(rectangle(line_ids, 20.0, 10.0)));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0));
+        extrude.dependencies = vec![sketch.id];
+        let (sketch_id, extrude_id) = (sketch.id, extrude.id);
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+
+        let result = evaluate(&mut graph);
+        let mut datum = Feature::new("Midplane", FeatureType::DatumPlane)
+            .with_param("datum_type", ParameterValue::String("Midplane".into()))
+            .with_param("ref_a", ParameterValue::Reference(y_face(&result, -1.0)))
+            .with_param("ref_b", ParameterValue::Reference(y_face(&result, 1.0)));
+        datum.dependencies = vec![extrude_id];
+
+        let mut on_datum = Sketch::new(SketchPlane::default());
+        on_datum.entities.push(SketchEntity { id: point, geometry: SketchGeometry::Point { pos: [0.0, 0.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false });
+        let mut sketch2 = Feature::new("Sketch2", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(on_datum))
+            .with_param("plane_ref", ParameterValue::Reference(datum_geometry_id(&datum).unwrap()));
+        sketch2.dependencies = vec![datum.id];
+        let datum_id = datum.id;
+        graph.add_node(datum);
+        graph.add_node(sketch2);
+
+        (graph, sketch_id, datum_id)
+    }
+
+    #[test]
+    fn test_sketch_on_midplane_stays_centered() {
+        let line_ids = [0, 1, 2, 3].map(|i| EntityId::new_deterministic(&format!("datum_test:line{}", i)));
+        let point = EntityId::new_deterministic("datum_test:point");
+        let (mut graph, sketch_id, datum_id) = midplane_model(&line_ids, point);
+
+        let result = evaluate(&mut graph);
+        assert!((point_y(&result, point) - 5.0).abs() < 1e-6);
+        let datum_face = datum_geometry_id(&graph.nodes[&datum_id]).unwrap();
+        assert!(result.tessellation.is_reference(&datum_face), "Datum renders as reference geometry");
+
+        // Move the y = H face: the datum and the sketch on it follow
+        graph.nodes.get_mut(&sketch_id).unwrap().parameters
+            .insert("sketch_data".into(), ParameterValue::Sketch(rectangle(&line_ids, 20.0, 30.0)));
+        let result = evaluate(&mut graph);
+        assert!((point_y(&result, point) - 15.0).abs() < 1e-6, "Sketch should follow the midplane");
+    }
+
+    #[test]
+    fn test_zombie_datum_keeps_last_evaluated_placement() {
+        let line_ids = [0, 1, 2, 3].map(|i| EntityId::new_deterministic(&format!("datum_zombie:line{}", i)));
+        let point = EntityId::new_deterministic("datum_zombie:point");
+        let (mut graph, _, datum_id) = midplane_model(&line_ids, point);
+
+        let result = evaluate(&mut graph);
+        assert_eq!(graph.cache_datum_geometry(&result.topology_manifest), 1);
+        assert_eq!(graph.cache_datum_geometry(&result.topology_manifest), 0, "Unchanged geometry is not re-cached");
+
+        // One source face disappears
+        let missing = TopoId::new(EntityId::new(), 0, TopoRank::Face);
+        graph.nodes.get_mut(&datum_id).unwrap().parameters
+            .insert("ref_b".into(), ParameterValue::Reference(missing));
+        let result = evaluate(&mut graph);
+        assert!((point_y(&result, point) - 5.0).abs() < 1e-6);
+        assert!(result.logs.iter().any(|l| l.contains("last evaluated")), "Fallback should be reported");
+    }
+}
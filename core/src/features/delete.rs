@@ -0,0 +1,254 @@
+//! Deleting features that other features depend on
+//!
+//! `FeatureGraph::remove_node` refuses to delete a feature with dependents.
+//! `delete_feature` takes a `DeletePolicy` saying what to do with them instead,
+//! and returns a `Deletion` that `restore_deletion` puts back as a unit, so a
+//! cascade is undone in one step. `plan_delete` reports what a delete would
+//! do without changing the graph, for confirming counts first.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::bodies::BodyName;
+use super::dag::FeatureGraph;
+use super::types::{Feature, ParameterValue};
+use crate::topo::EntityId;
+
+/// What to do with the features that depend on a deleted feature
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeletePolicy {
+    /// Don't delete a feature anything depends on
+    #[default]
+    Refuse,
+    /// Delete the whole downstream subtree too
+    Cascade,
+    /// Keep the direct dependents, without the edge to the deleted feature,
+    /// and mark them errored
+    Orphan,
+}
+
+/// Features a delete removes and orphans, in sort order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeletePlan {
+    pub removed: Vec<EntityId>,
+    pub orphaned: Vec<EntityId>,
+}
+
+/// A delete that happened: what `restore_deletion` needs to undo it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deletion {
+    pub plan: DeletePlan,
+    /// Removed features with their index in the sort order, ascending
+    removed: Vec<(Option<usize>, Feature)>,
+    /// Orphaned features as they were before the delete
+    orphaned: Vec<Feature>,
+    /// Rollback point cleared because its feature was removed
+    rollback_point: Option<EntityId>,
+    /// Names of the bodies the removed features made
+    #[serde(default)]
+    body_names: Vec<BodyName>,
+}
+
+impl FeatureGraph {
+    /// What deleting `id` under `policy` would remove and orphan. Errors if
+    /// the feature doesn't exist, or if `policy` is `Refuse` and something
+    /// depends on it.
+    pub fn plan_delete(&self, id: EntityId, policy: DeletePolicy) -> Result<DeletePlan, String> {
+        let feature = self.nodes.get(&id).ok_or_else(|| "Feature not found".to_string())?;
+        let dependents = self.downstream_of(id);
+        match policy {
+            DeletePolicy::Refuse if !dependents.is_empty() => {
+                let names: Vec<&str> = dependents.iter().map(|d| self.nodes[d].name.as_str()).collect();
+                Err(format!("'{}' is used by {}", feature.name, names.join(", ")))
+            }
+            DeletePolicy::Refuse => Ok(DeletePlan { removed: vec![id], orphaned: Vec::new() }),
+            DeletePolicy::Orphan => Ok(DeletePlan { removed: vec![id], orphaned: dependents }),
+            DeletePolicy::Cascade => {
+                let mut subtree = HashSet::from([id]);
+                let mut queue = vec![id];
+                while let Some(next) = queue.pop() {
+                    for dependent in self.downstream_of(next) {
+                        if subtree.insert(dependent) {
+                            queue.push(dependent);
+                        }
+                    }
+                }
+                let mut removed: Vec<EntityId> = self.sort_order.iter().copied().filter(|f| subtree.contains(f)).collect();
+                let mut rest: Vec<EntityId> = subtree.into_iter().filter(|f| !removed.contains(f)).collect();
+                rest.sort();
+                removed.extend(rest);
+                Ok(DeletePlan { removed, orphaned: Vec::new() })
+            }
+        }
+    }
+
+    /// Delete `id` and deal with its dependents according to `policy`
+    pub fn delete_feature(&mut self, id: EntityId, policy: DeletePolicy) -> Result<Deletion, String> {
+        let plan = self.plan_delete(id, policy)?;
+        let name = self.nodes[&id].name.clone();
+
+        let mut orphaned = Vec::new();
+        for dependent in &plan.orphaned {
+            let Some(feature) = self.nodes.get_mut(dependent) else { continue };
+            orphaned.push(feature.clone());
+            feature.dependencies.retain(|d| *d != id);
+            if let Some(ParameterValue::List(bodies)) = feature.parameters.get_mut("body_list") {
+                bodies.retain(|b| b != &id.to_string());
+            }
+            feature.error = Some(format!("Input '{}' was deleted", name));
+        }
+
+        // Positions in the original order; plan.removed is in sort order
+        let indices: Vec<Option<usize>> = plan.removed.iter().map(|f| self.get_feature_index(*f)).collect();
+        let removed = plan.removed.iter().zip(indices)
+            .filter_map(|(f, index)| self.take_node(*f).map(|feature| (index, feature)))
+            .collect();
+        let body_names = plan.removed.iter().flat_map(|f| self.take_body_names(*f)).collect();
+
+        let rollback_point = self.rollback_point.filter(|rb| plan.removed.contains(rb));
+        if rollback_point.is_some() {
+            self.rollback_point = None;
+        }
+        Ok(Deletion { plan, removed, orphaned, rollback_point, body_names })
+    }
+
+    /// Undo a `delete_feature`: put back the removed features at their old
+    /// positions and the orphaned features as they were
+    pub fn restore_deletion(&mut self, deletion: Deletion) {
+        for (index, feature) in deletion.removed {
+            let id = feature.id;
+            self.nodes.insert(id, feature);
+            if let Some(index) = index {
+                self.sort_order.insert(index.min(self.sort_order.len()), id);
+            }
+        }
+        for feature in deletion.orphaned {
+            if let Some(current) = self.nodes.get_mut(&feature.id) {
+                *current = feature;
+            }
+        }
+        if deletion.rollback_point.is_some() {
+            self.rollback_point = deletion.rollback_point;
+        }
+        self.body_names.extend(deletion.body_names);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::dag::FeatureState;
+    use crate::features::types::FeatureType;
+
+    /// Sketch → Extrude → Fillet, plus an unrelated sketch.
+    /// Returns [sketch, extrude, fillet, other].
+    fn chain() -> (FeatureGraph, [EntityId; 4]) {
+        let mut graph = FeatureGraph::new();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch);
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude);
+        extrude.dependencies.push(sketch.id);
+        let mut fillet = Feature::new("Fillet1", FeatureType::Fillet);
+        fillet.dependencies.push(extrude.id);
+        let other = Feature::new("Sketch2", FeatureType::Sketch);
+        let ids = [sketch.id, extrude.id, fillet.id, other.id];
+        for feature in [sketch, extrude, fillet, other] {
+            graph.add_node(feature);
+        }
+        graph.sort().unwrap();
+        (graph, ids)
+    }
+
+    #[test]
+    fn test_refuse_keeps_used_feature() {
+        let (mut graph, [sketch, extrude, fillet, _]) = chain();
+        let err = graph.delete_feature(sketch, DeletePolicy::Refuse).unwrap_err();
+        assert_eq!(err, "'Sketch1' is used by Extrude1");
+        assert_eq!(graph.remove_node(sketch).unwrap_err(), vec![extrude]);
+        assert_eq!(graph.nodes.len(), 4);
+
+        let deletion = graph.delete_feature(fillet, DeletePolicy::Refuse).unwrap();
+        assert_eq!(deletion.plan, DeletePlan { removed: vec![fillet], orphaned: vec![] });
+        assert!(!graph.nodes.contains_key(&fillet));
+    }
+
+    #[test]
+    fn test_cascade_dry_run_reports_subtree() {
+        let (graph, [sketch, extrude, fillet, _]) = chain();
+        let plan = graph.plan_delete(sketch, DeletePolicy::Cascade).unwrap();
+        assert_eq!(plan.removed, vec![sketch, extrude, fillet]);
+        assert!(plan.orphaned.is_empty());
+        assert_eq!(graph.nodes.len(), 4, "A dry run changes nothing");
+        assert!(graph.plan_delete(EntityId::new(), DeletePolicy::Cascade).is_err());
+    }
+
+    #[test]
+    fn test_undo_cascade_restores_subtree_as_a_unit() {
+        let (mut graph, [sketch, extrude, fillet, other]) = chain();
+        graph.set_rollback(Some(fillet));
+        let before = graph.sort_order.clone();
+
+        let deletion = graph.delete_feature(extrude, DeletePolicy::Cascade).unwrap();
+        assert_eq!(deletion.plan.removed, vec![extrude, fillet]);
+        assert_eq!(graph.sort_order, before.iter().copied().filter(|f| *f == sketch || *f == other).collect::<Vec<_>>());
+        assert_eq!(graph.rollback_point, None);
+
+        graph.restore_deletion(deletion);
+        assert_eq!(graph.sort_order, before);
+        assert_eq!(graph.nodes[&fillet].dependencies, vec![extrude]);
+        assert_eq!(graph.rollback_point, Some(fillet));
+        let states = graph.feature_states();
+        assert!([sketch, extrude, fillet].iter().all(|f| states[f] == FeatureState::Active));
+    }
+
+    #[test]
+    fn test_orphan_marks_dependents_errored() {
+        let (mut graph, [sketch, extrude, fillet, other]) = chain();
+        let deletion = graph.delete_feature(sketch, DeletePolicy::Orphan).unwrap();
+        assert_eq!(deletion.plan, DeletePlan { removed: vec![sketch], orphaned: vec![extrude] });
+        assert!(graph.nodes[&extrude].dependencies.is_empty());
+        assert_eq!(graph.nodes[&extrude].error.as_deref(), Some("Input 'Sketch1' was deleted"));
+
+        let states = graph.feature_states();
+        assert_eq!(states[&extrude], FeatureState::Errored);
+        assert_eq!(states[&fillet], FeatureState::Errored, "Downstream of an errored feature");
+        assert_eq!(states[&other], FeatureState::Active);
+        assert!(!graph.regenerate().statements.is_empty());
+
+        graph.restore_deletion(deletion);
+        assert_eq!(graph.nodes[&extrude].dependencies, vec![sketch]);
+        assert!(graph.nodes[&extrude].error.is_none());
+        assert_eq!(graph.feature_states()[&fillet], FeatureState::Active);
+    }
+
+    #[test]
+    fn test_repairing_an_orphan_clears_its_error() {
+        use crate::topo::naming::{TopoId, TopoRank};
+        use crate::topo::registry::RepairAction;
+
+        let axis = TopoId::new(EntityId::new(), 1, TopoRank::Edge);
+        let orphaned = || {
+            let (mut graph, [sketch, extrude, fillet, _]) = chain();
+            graph.nodes.get_mut(&extrude).unwrap().parameters.insert("axis".into(), ParameterValue::Reference(axis));
+            graph.delete_feature(sketch, DeletePolicy::Orphan).unwrap();
+            assert_eq!(graph.feature_states()[&fillet], FeatureState::Errored);
+            (graph, extrude, fillet)
+        };
+
+        let (mut graph, extrude, fillet) = orphaned();
+        graph.update_feature_params(extrude, std::collections::HashMap::from([("profiles".to_string(), ParameterValue::List(vec![]))])).unwrap();
+        assert!(graph.nodes[&extrude].error.is_none());
+        assert_eq!(graph.feature_states()[&fillet], FeatureState::Active);
+
+        let (mut graph, extrude, fillet) = orphaned();
+        let other = TopoId::new(EntityId::new(), 1, TopoRank::Edge);
+        assert!(graph.replace_reference(extrude, other, axis).is_err());
+        assert!(graph.nodes[&extrude].error.is_some(), "A failed repair changes nothing");
+        graph.replace_reference(extrude, axis, other).unwrap();
+        assert_eq!(graph.feature_states()[&fillet], FeatureState::Active);
+
+        let (mut graph, extrude, fillet) = orphaned();
+        graph.apply_zombie_repair(extrude, "axis", &RepairAction::ClearReference).unwrap();
+        assert_eq!(graph.feature_states()[&fillet], FeatureState::Active);
+    }
+}
@@ -0,0 +1,81 @@
+//! Sketch profiles from a face's offset outline
+//!
+//! `create_offset_profile_from_face` draws a planar face's boundary, offset
+//! into the face, in a sketch on that face: for instance the border to pocket
+//! around the top of a part. The outline stays linked to the face (see
+//! `sketch::face_offset`), so it follows the face when the model changes.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::dag::FeatureGraph;
+use super::types::{Feature, FeatureType, ParameterValue};
+use crate::geometry::datum::sketch_plane_from;
+use crate::geometry::Tessellation;
+use crate::sketch::face_offset::face_offset_outline;
+use crate::sketch::types::Sketch;
+use crate::topo::naming::TopoId;
+use crate::topo::registry::KernelEntity;
+use crate::topo::EntityId;
+
+/// Outcome of `create_offset_profile_from_face`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OffsetProfile {
+    /// The sketch drawn into, new or given
+    pub sketch_id: EntityId,
+    pub entities: Vec<EntityId>,
+}
+
+impl FeatureGraph {
+    /// Draw `face`'s outline offset by `offset` into the face, in `target` or
+    /// in a new sketch on the face. `tessellation` and `manifest` are the
+    /// current model's. A target sketch must be placed on `face`.
+    pub fn create_offset_profile_from_face(
+        &mut self,
+        face: TopoId,
+        offset: f64,
+        target: Option<EntityId>,
+        tessellation: &Tessellation,
+        manifest: &HashMap<TopoId, KernelEntity>,
+    ) -> Result<OffsetProfile, String> {
+        let plane = manifest.get(&face)
+            .ok_or_else(|| "Face not found in the model".to_string())
+            .and_then(|e| sketch_plane_from(&e.geometry).ok_or_else(|| "Face is not planar; only planar faces can be offset".to_string()))?;
+        let outline = face_offset_outline(&face, offset, &plane, tessellation, manifest)?;
+        let owner = self.topo_owners().get(&face.feature_id).copied();
+
+        let sketch_id = match target {
+            Some(id) => {
+                let feature = self.nodes.get(&id).ok_or_else(|| "Sketch feature not found".to_string())?;
+                if feature.feature_type != FeatureType::Sketch {
+                    return Err(format!("Feature '{}' is not a sketch", feature.name));
+                }
+                if feature.parameters.get("plane_ref") != Some(&ParameterValue::Reference(face)) {
+                    return Err(format!("Sketch '{}' is not on that face", feature.name));
+                }
+                id
+            }
+            None => {
+                let mut feature = Feature::new(&self.unused_feature_name("Offset Sketch"), FeatureType::Sketch)
+                    .with_param("sketch_data", ParameterValue::Sketch(Sketch::new(plane)))
+                    .with_param("plane_ref", ParameterValue::Reference(face));
+                self.assign_feature_id(&mut feature, None);
+                let id = feature.id;
+                self.add_node(feature);
+                id
+            }
+        };
+
+        let feature = self.nodes.get_mut(&sketch_id).expect("sketch exists");
+        let Some(ParameterValue::Sketch(sketch)) = feature.parameters.get_mut("sketch_data") else {
+            return Err(format!("Sketch '{}' has no sketch data", feature.name));
+        };
+        let entities = sketch.add_face_offset(face, offset, &outline);
+        // Evaluated after the face it follows
+        if let Some(owner) = owner.filter(|o| !feature.dependencies.contains(o)) {
+            feature.dependencies.push(owner);
+        }
+        Ok(OffsetProfile { sketch_id, entities })
+    }
+}
@@ -0,0 +1,232 @@
+//! Parameters for a new feature taken from the current selection
+//!
+//! When a feature is created with `from_selection`, `defaults_from_selection`
+//! turns what the user had selected into that feature's starting parameters
+//! and inputs: sketch regions become an extrude's profile, edges become a
+//! fillet's edges, and a planar face becomes a sketch's plane. A selection
+//! that doesn't fit the feature type is an error saying what to select.
+//!
+//! Sketch regions have no kernel topology of their own. They are selected by
+//! `region_topo_id`, a Face-rank id in the sketch's TopoId namespace derived
+//! from the region's stable id.
+
+use std::collections::{HashMap, HashSet};
+
+use super::clipboard::topo_namespace;
+use super::dag::FeatureGraph;
+use super::types::{FeatureType, ParameterValue, RegionDirection, RegionSpec};
+use crate::geometry::datum::sketch_plane_from;
+use crate::sketch::regions::{find_regions, summarize_regions};
+use crate::sketch::solver::SketchSolver;
+use crate::topo::naming::{NamingContext, TopoId, TopoRank};
+use crate::topo::registry::TopoRegistry;
+use crate::topo::EntityId;
+
+/// Starting parameters and inputs for a feature created from a selection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelectionDefaults {
+    pub params: HashMap<String, ParameterValue>,
+    pub dependencies: Vec<EntityId>,
+}
+
+/// Selectable id of the sketch region `stable_id` (see `summarize_regions`)
+/// in the sketch feature `sketch_feature`
+pub fn region_topo_id(sketch_feature: EntityId, stable_id: &str) -> TopoId {
+    NamingContext::new(topo_namespace(sketch_feature)).derive(stable_id, TopoRank::Face)
+}
+
+/// Parameters a new `feature_type` feature takes from `selected`. Errors with
+/// what the feature needs selected when the selection doesn't fit. Types with
+/// no selection mapping get no defaults.
+pub fn defaults_from_selection(
+    graph: &FeatureGraph,
+    registry: &TopoRegistry,
+    feature_type: FeatureType,
+    selected: &HashSet<TopoId>,
+) -> Result<SelectionDefaults, String> {
+    let owners = graph.topo_owners();
+    let mut selected: Vec<(TopoId, Option<EntityId>)> = selected.iter()
+        .map(|id| (*id, owners.get(&id.feature_id).copied()))
+        .collect();
+    selected.sort_by_key(|(id, _)| (id.feature_id, id.rank, id.local_id));
+    match feature_type {
+        FeatureType::Extrude => extrude_defaults(graph, &selected),
+        FeatureType::Fillet => fillet_defaults(graph, registry, &selected),
+        FeatureType::Sketch => sketch_defaults(registry, &selected),
+        _ => Ok(SelectionDefaults::default()),
+    }
+}
+
+/// Selected sketch regions, all from one sketch, as per-region profiles
+fn extrude_defaults(graph: &FeatureGraph, selected: &[(TopoId, Option<EntityId>)]) -> Result<SelectionDefaults, String> {
+    const NEEDS: &str = "Extrude needs one or more regions of a single sketch selected";
+    let sketch_id = match selected {
+        [(_, Some(owner)), ..] if selected.iter().all(|(_, o)| *o == Some(*owner)) => *owner,
+        [] => return Err(NEEDS.to_string()),
+        _ => return Err(format!("{}; the selection spans several features", NEEDS)),
+    };
+    let Some(ParameterValue::Sketch(sketch)) = graph.nodes.get(&sketch_id)
+        .filter(|f| f.feature_type == FeatureType::Sketch)
+        .and_then(|f| f.parameters.get("sketch_data"))
+    else {
+        return Err(format!("{}; the selection is not in a sketch", NEEDS));
+    };
+
+    // Regions as regeneration will see them, after solving
+    let mut solved = sketch.clone();
+    SketchSolver::solve_with_result(&mut solved);
+    let summaries = summarize_regions(&find_regions(&solved.entities));
+    let specs = selected.iter().map(|(id, _)| {
+        summaries.iter()
+            .find(|s| region_topo_id(sketch_id, &s.stable_id) == *id)
+            .map(|s| RegionSpec {
+                region_key: s.stable_id.clone(),
+                depth_override: None,
+                direction: RegionDirection::default(),
+                centroid: Some(s.region.centroid),
+            })
+            .ok_or_else(|| format!("{}; a selected item is not a region of the sketch", NEEDS))
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(SelectionDefaults {
+        params: HashMap::from([("region_specs".to_string(), ParameterValue::RegionSpecs(specs))]),
+        dependencies: vec![sketch_id],
+    })
+}
+
+/// Selected model edges; the input is the latest feature that made any of them
+fn fillet_defaults(graph: &FeatureGraph, registry: &TopoRegistry, selected: &[(TopoId, Option<EntityId>)]) -> Result<SelectionDefaults, String> {
+    const NEEDS: &str = "Fillet needs only edges of the model selected";
+    let on_model = |(id, owner): &(TopoId, Option<EntityId>)| {
+        id.rank == TopoRank::Edge
+            && registry.resolve(id).is_some()
+            && owner.and_then(|o| graph.nodes.get(&o)).is_some_and(|f| f.feature_type != FeatureType::Sketch)
+    };
+    if selected.is_empty() || !selected.iter().all(on_model) {
+        return Err(NEEDS.to_string());
+    }
+    let edges = selected.iter().filter_map(|(id, _)| serde_json::to_string(id).ok()).collect();
+    let input = selected.iter().filter_map(|(_, owner)| *owner).max_by_key(|f| graph.get_feature_index(*f));
+
+    Ok(SelectionDefaults {
+        params: HashMap::from([("edges".to_string(), ParameterValue::List(edges))]),
+        dependencies: input.into_iter().collect(),
+    })
+}
+
+/// A single planar face or datum plane as the sketch plane
+fn sketch_defaults(registry: &TopoRegistry, selected: &[(TopoId, Option<EntityId>)]) -> Result<SelectionDefaults, String> {
+    const NEEDS: &str = "Sketch needs a single planar face or datum plane selected";
+    let [(face, owner)] = selected else {
+        return Err(NEEDS.to_string());
+    };
+    let planar = face.rank == TopoRank::Face
+        && registry.resolve(face).is_some_and(|e| sketch_plane_from(&e.geometry).is_some());
+    if !planar {
+        return Err(NEEDS.to_string());
+    }
+
+    Ok(SelectionDefaults {
+        params: HashMap::from([("plane_ref".to_string(), ParameterValue::Reference(*face))]),
+        dependencies: owner.iter().copied().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::{EvaluationResult, Runtime};
+    use crate::features::types::Feature;
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+    use crate::topo::registry::AnalyticGeometry;
+    use crate::topo::IdGenerator;
+
+    /// Two separate squares, 10x10 at the origin and 10x10 at x = 20
+    fn two_squares() -> Sketch {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for x in [0.0, 20.0] {
+            let corners = [[x, 0.0], [x + 10.0, 0.0], [x + 10.0, 10.0], [x, 10.0]];
+            for i in 0..4 {
+                sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+            }
+        }
+        sketch
+    }
+
+    fn evaluate(graph: &mut FeatureGraph) -> (EvaluationResult, TopoRegistry) {
+        let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("FromSelection")).unwrap();
+        let mut registry = TopoRegistry::new();
+        for entity in result.topology_manifest.values() {
+            registry.register(entity.clone());
+        }
+        (result, registry)
+    }
+
+    #[test]
+    fn test_extrude_from_selected_region() {
+        let mut graph = FeatureGraph::new();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(two_squares()));
+        let sketch_id = sketch.id;
+        graph.add_node(sketch);
+
+        let Some(ParameterValue::Sketch(data)) = graph.nodes[&sketch_id].parameters.get("sketch_data") else { unreachable!() };
+        let right = summarize_regions(&find_regions(&data.entities)).into_iter()
+            .find(|s| s.region.centroid[0] > 20.0)
+            .unwrap();
+        let selected = HashSet::from([region_topo_id(sketch_id, &right.stable_id)]);
+        let defaults = defaults_from_selection(&graph, &TopoRegistry::new(), FeatureType::Extrude, &selected).unwrap();
+        assert_eq!(defaults.dependencies, vec![sketch_id]);
+        let Some(ParameterValue::RegionSpecs(specs)) = defaults.params.get("region_specs") else { panic!("No profile recorded") };
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].region_key, right.stable_id);
+
+        // The recorded profile extrudes just that square
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude).with_param("distance", ParameterValue::Float(5.0));
+        extrude.dependencies = defaults.dependencies;
+        extrude.parameters.extend(defaults.params);
+        graph.add_node(extrude);
+        let (result, _) = evaluate(&mut graph);
+        assert!((crate::analysis::mesh::mesh_volume(&result.tessellation) - 500.0).abs() < 1e-3);
+
+        let stray = HashSet::from([TopoId::new(topo_namespace(sketch_id), 7, TopoRank::Face)]);
+        let err = defaults_from_selection(&graph, &TopoRegistry::new(), FeatureType::Extrude, &stray).unwrap_err();
+        assert!(err.contains("not a region"), "{}", err);
+        let err = defaults_from_selection(&graph, &TopoRegistry::new(), FeatureType::Extrude, &HashSet::new()).unwrap_err();
+        assert!(err.starts_with("Extrude needs"), "{}", err);
+    }
+
+    #[test]
+    fn test_sketch_on_selected_face() {
+        let mut graph = FeatureGraph::new();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(two_squares()));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude).with_param("distance", ParameterValue::Float(5.0));
+        extrude.dependencies = vec![sketch.id];
+        let extrude_id = extrude.id;
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+        let (result, registry) = evaluate(&mut graph);
+
+        let top = result.topology_manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { origin, normal } if origin[2] == 5.0 && normal[2] > 0.5))
+            .map(|e| e.id)
+            .expect("Extrude has a top face");
+        let defaults = defaults_from_selection(&graph, &registry, FeatureType::Sketch, &HashSet::from([top])).unwrap();
+        assert_eq!(defaults.params.get("plane_ref"), Some(&ParameterValue::Reference(top)));
+        assert_eq!(defaults.dependencies, vec![extrude_id]);
+
+        let edge = result.topology_manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Line { .. }) && e.id.feature_id == topo_namespace(extrude_id))
+            .map(|e| e.id)
+            .unwrap();
+        let err = defaults_from_selection(&graph, &registry, FeatureType::Sketch, &HashSet::from([edge])).unwrap_err();
+        assert_eq!(err, "Sketch needs a single planar face or datum plane selected");
+        assert!(defaults_from_selection(&graph, &registry, FeatureType::Sketch, &HashSet::from([top, edge])).is_err());
+
+        let fillet = defaults_from_selection(&graph, &registry, FeatureType::Fillet, &HashSet::from([edge])).unwrap();
+        assert_eq!(fillet.dependencies, vec![extrude_id]);
+        assert_eq!(fillet.params.get("edges"), Some(&ParameterValue::List(vec![serde_json::to_string(&edge).unwrap()])));
+        assert!(defaults_from_selection(&graph, &registry, FeatureType::Fillet, &HashSet::from([top])).is_err());
+    }
+}
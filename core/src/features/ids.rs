@@ -0,0 +1,152 @@
+//! Feature ids minted in a document's namespace
+//!
+//! Every document carries an id namespace. Feature ids minted server-side are
+//! derived from it, and evaluation seeds each feature's id generator from the
+//! feature's own id (`set_context`), so the same construction applied to two
+//! documents with the same namespace yields the same EntityIds and TopoIds,
+//! while documents with different namespaces never share them.
+
+use super::dag::FeatureGraph;
+use super::types::Feature;
+use crate::topo::EntityId;
+
+impl FeatureGraph {
+    /// Give the document a random namespace if it has none yet (new documents,
+    /// and ones saved before namespaces existed)
+    pub fn ensure_id_namespace(&mut self) {
+        if self.id_namespace.is_empty() {
+            self.id_namespace = uuid::Uuid::new_v4().to_string();
+        }
+    }
+
+    /// Mint a feature id. With a `seed` the id depends only on the namespace
+    /// and the seed, so scripted construction is reproducible; without one it
+    /// is the next id in the document's sequence.
+    pub fn mint_feature_id(&mut self, seed: Option<&str>) -> EntityId {
+        match seed {
+            Some(seed) => EntityId::new_deterministic(&format!("{}:seed:{}", self.id_namespace, seed)),
+            None => {
+                self.id_sequence += 1;
+                EntityId::new_deterministic(&format!("{}:{}", self.id_namespace, self.id_sequence))
+            }
+        }
+    }
+
+    /// Next id in the document's sequence that no feature has yet
+    pub fn mint_unused_feature_id(&mut self) -> EntityId {
+        let mut id = self.mint_feature_id(None);
+        while self.nodes.contains_key(&id) {
+            id = self.mint_feature_id(None);
+        }
+        id
+    }
+
+    /// Replace `feature`'s id with a minted one before it is added. An id
+    /// already in the graph (e.g. a seed used twice) is rejected and re-minted
+    /// from the sequence; the returned warning says so.
+    pub fn assign_feature_id(&mut self, feature: &mut Feature, seed: Option<&str>) -> Option<String> {
+        let id = self.mint_feature_id(seed);
+        if self.nodes.contains_key(&id) {
+            feature.id = self.mint_unused_feature_id();
+            return Some(format!("Feature id {} is already in use; a new one was minted", id));
+        }
+        feature.id = id;
+        None
+    }
+}
+
+/// Id for an entity the server adds to sketch `sketch_id` (pasting, copying a
+/// sketch). Derived from the sketch's minted id and `seed`, so it is as
+/// reproducible, and as distinct between documents, as the sketch's own id.
+pub fn mint_sketch_entity_id(sketch_id: EntityId, seed: &str) -> EntityId {
+    EntityId::new_deterministic(&format!("{}:entity:{}", sketch_id, seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::Runtime;
+    use crate::features::types::{FeatureType, ParameterValue};
+    use crate::sketch::types::{Sketch, SketchEntity, SketchGeometry, SketchPlane};
+    use crate::topo::naming::TopoId;
+    use crate::topo::IdGenerator;
+    use std::collections::HashSet;
+
+    fn document(namespace: &str) -> FeatureGraph {
+        let mut graph = FeatureGraph::new();
+        graph.id_namespace = namespace.to_string();
+        graph
+    }
+
+    /// Sketch a square and extrude it, the way a client script would. Returns
+    /// the feature ids and the TopoIds the model evaluates to, leaving out the
+    /// sketch's own curves, whose ids the script chooses.
+    fn run_script(graph: &mut FeatureGraph) -> (Vec<EntityId>, HashSet<TopoId>) {
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for i in 0..4 {
+            sketch.entities.push(SketchEntity {
+                id: EntityId::new_deterministic(&format!("ids_test:line{}", i)),
+                geometry: SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] },
+                is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
+            });
+        }
+        let lines: Vec<EntityId> = sketch.entities.iter().map(|e| e.id).collect();
+
+        let mut sketch = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        assert_eq!(graph.assign_feature_id(&mut sketch, None), None);
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0))
+            .with_param("profiles", ParameterValue::List(lines.iter().map(|id| id.to_string()).collect()));
+        extrude.dependencies = vec![sketch.id];
+        assert_eq!(graph.assign_feature_id(&mut extrude, Some("base")), None);
+        let ids = vec![sketch.id, extrude.id];
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+
+        let generator = IdGenerator::new(&graph.id_namespace);
+        let result = Runtime::new().evaluate(&graph.regenerate(), &generator).unwrap();
+        let topo_ids = result.topology_manifest.keys().filter(|id| !lines.contains(&id.feature_id)).copied().collect();
+        (ids, topo_ids)
+    }
+
+    #[test]
+    fn test_same_namespace_reproduces_ids_and_different_ones_are_disjoint() {
+        let (ids_a, topo_a) = run_script(&mut document("workshop"));
+        let (ids_b, topo_b) = run_script(&mut document("workshop"));
+        assert!(!topo_a.is_empty());
+        assert_eq!(ids_a, ids_b);
+        assert_eq!(topo_a, topo_b);
+
+        let (ids_c, topo_c) = run_script(&mut document("other"));
+        assert!(ids_c.iter().all(|id| !ids_a.contains(id)));
+        assert!(topo_c.is_disjoint(&topo_a));
+    }
+
+    #[test]
+    fn test_reused_seed_is_reminted_with_warning() {
+        let mut graph = document("workshop");
+        let mut first = Feature::new("Point1", FeatureType::Point);
+        assert_eq!(graph.assign_feature_id(&mut first, Some("p")), None);
+        let first_id = first.id;
+        graph.add_node(first);
+
+        let mut second = Feature::new("Point2", FeatureType::Point);
+        let warning = graph.assign_feature_id(&mut second, Some("p")).expect("collision is reported");
+        assert!(warning.contains(&first_id.to_string()));
+        assert_ne!(second.id, first_id);
+        assert_eq!(graph.id_sequence, 1, "Re-minted from the sequence");
+
+        let mut fresh = document("");
+        fresh.ensure_id_namespace();
+        assert!(!fresh.id_namespace.is_empty());
+        let namespace = fresh.id_namespace.clone();
+        fresh.ensure_id_namespace();
+        assert_eq!(fresh.id_namespace, namespace, "An existing namespace is kept");
+    }
+}
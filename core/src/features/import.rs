@@ -0,0 +1,124 @@
+//! Importing STEP files as ImportedBody features
+//!
+//! Each solid in the file becomes one ImportedBody feature, named after its
+//! product, holding the solid's exact geometry already scaled to mm and
+//! placed where the file's assembly puts it. The file itself isn't kept, so
+//! regeneration never re-reads it.
+
+use serde::Serialize;
+
+use super::dag::FeatureGraph;
+use super::types::{Feature, FeatureType, ParameterValue};
+use crate::errors::{CadError, ErrorCode};
+use crate::kernel::{self, GeometryKernel, KernelOpError};
+use crate::topo::EntityId;
+
+/// Outcome of `import_step`, sent to the client as `IMPORT:`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportReport {
+    /// The ImportedBody features created, in file order
+    pub features: Vec<EntityId>,
+    /// The file's length unit
+    pub unit: String,
+    pub scale_to_mm: f64,
+    /// Skipped entities and limits hit
+    pub warnings: Vec<String>,
+}
+
+/// The solids of a STEP file, read but not yet added to a graph. Reading is
+/// the slow part of an import, so it happens before the graph is locked.
+#[derive(Debug, Clone)]
+pub struct StepBodies {
+    /// Each solid's product name and serialized solid, in file order
+    bodies: Vec<(String, String)>,
+    unit: String,
+    scale_to_mm: f64,
+    warnings: Vec<String>,
+}
+
+/// Read the solids of `step_data`. Fails if the file can't be read or has
+/// no importable solid.
+pub fn read_step(step_data: &str) -> Result<StepBodies, CadError> {
+    let kernel = kernel::default_kernel();
+    let imported = kernel.import_step(step_data).map_err(|e| match e {
+        KernelOpError::LimitExceeded(_) => CadError::from(e),
+        _ => CadError::new(ErrorCode::ImportFailed, format!("STEP import failed: {}", e)).with_detail("format", "STEP"),
+    })?;
+    if imported.solids.is_empty() {
+        let mut message = "STEP file has no solid that can be imported".to_string();
+        if !imported.warnings.is_empty() {
+            message = format!("{}: {}", message, imported.warnings.join("; "));
+        }
+        return Err(CadError::new(ErrorCode::ImportFailed, message).with_detail("format", "STEP"));
+    }
+
+    let bodies = imported.solids.iter()
+        .map(|s| kernel.solid_to_json(&s.solid).map(|json| (s.name.clone(), json)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(StepBodies { bodies, unit: imported.unit, scale_to_mm: imported.scale_to_mm, warnings: imported.warnings })
+}
+
+impl FeatureGraph {
+    /// Add one ImportedBody feature per solid in `step_data`. Fails, adding
+    /// nothing, if the file can't be read or has no importable solid.
+    pub fn import_step(&mut self, step_data: &str) -> Result<ImportReport, CadError> {
+        read_step(step_data).map(|bodies| self.add_step_bodies(bodies))
+    }
+
+    /// Add one ImportedBody feature per solid `read_step` found
+    pub fn add_step_bodies(&mut self, step: StepBodies) -> ImportReport {
+        let mut ids = Vec::new();
+        for (name, json) in step.bodies {
+            let mut feature = Feature::new(&self.unused_feature_name(&name), FeatureType::ImportedBody)
+                .with_param("solid", ParameterValue::String(json));
+            self.assign_feature_id(&mut feature, None);
+            ids.push(feature.id);
+            self.add_node(feature);
+        }
+        ImportReport { features: ids, unit: step.unit, scale_to_mm: step.scale_to_mm, warnings: step.warnings }
+    }
+
+    /// `name`, or `name (2)`, `name (3)`, ... if a feature already has it
+    pub(crate) fn unused_feature_name(&self, name: &str) -> String {
+        let taken = |candidate: &str| self.nodes.values().any(|f| f.name == candidate);
+        if !taken(name) {
+            return name.to_string();
+        }
+        (2..).map(|n| format!("{} ({})", name, n)).find(|candidate| !taken(candidate)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::Runtime;
+    use crate::topo::IdGenerator;
+
+    const ASSEMBLY: &str = include_str!("../../tests/fixtures/bracket_assembly_inch.step");
+
+    #[test]
+    fn test_import_adds_one_body_per_placed_solid() {
+        let mut graph = FeatureGraph::new();
+        let report = graph.import_step(ASSEMBLY).unwrap();
+        assert_eq!(report.features.len(), 2);
+        assert_eq!(report.unit, "inch");
+        assert_eq!(report.warnings.len(), 1, "The surface model is skipped: {:?}", report.warnings);
+        let names: Vec<_> = report.features.iter().map(|id| graph.nodes[id].name.as_str()).collect();
+        assert_eq!(names, ["Block", "Block (2)"]);
+
+        let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("import_test")).unwrap();
+        assert_eq!(result.tessellation.bodies.len(), 2);
+        let com = crate::analysis::mesh_center_of_mass(&result.tessellation).unwrap();
+        // Blocks centered (f32 vertices) at (266.7, 25.4, 38.1) and (-25.4, 139.7, 38.1)
+        assert!((com.x - 120.65).abs() < 1e-4 && (com.y - 82.55).abs() < 1e-4 && (com.z - 38.1).abs() < 1e-4, "{:?}", com);
+
+        let surfaces_only = ASSEMBLY.replace("MANIFOLD_SOLID_BREP", "SHELL_BASED_SURFACE_MODEL");
+        let error = graph.import_step(&surfaces_only).unwrap_err();
+        assert!(error.message.starts_with("STEP file has no solid that can be imported: #16: SHELL_BASED_SURFACE_MODEL"), "{}", error);
+        assert_eq!(error.details, serde_json::json!({ "format": "STEP" }));
+        let error = graph.import_step("not a STEP file").unwrap_err();
+        assert_eq!(error.code, ErrorCode::ImportFailed);
+        assert!(error.message.starts_with("STEP import failed"));
+        assert_eq!(graph.nodes.len(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! Live sketch editing
+//!
+//! While a sketch is being edited, the client needs it solved and redrawn
+//! after every change, but regenerating the whole model each time makes
+//! editing unusable on models with heavy downstream features. A live update
+//! stores and solves the sketch without regenerating (the caller draws it with
+//! `Runtime::render_sketch`) and marks the features built from it as pending
+//! rebuild. Committing clears the mark; the caller then regenerates once.
+
+use super::dag::FeatureGraph;
+use super::types::{FeatureType, ParameterValue};
+use crate::sketch::solver::{SketchSolver, SolveResult};
+use crate::sketch::types::Sketch;
+use crate::topo::EntityId;
+
+impl FeatureGraph {
+    /// Replace sketch `id`'s data with `sketch` and solve it, without
+    /// regenerating. Locked entities keep their stored geometry. Features built from the sketch, directly or through
+    /// others, are added to `pending_rebuild`. The solve is deferred like an
+    /// UpdateFeature's; returns the stored sketch and its solve result.
+    pub fn update_sketch_live(&mut self, id: EntityId, mut sketch: Sketch) -> Result<(&Sketch, SolveResult), String> {
+        let feature = self.nodes.get(&id).ok_or_else(|| "Feature not found".to_string())?;
+        if feature.feature_type != FeatureType::Sketch {
+            return Err(format!("'{}' is not a sketch", feature.name));
+        }
+        sketch.validate_entity_names()?;
+        if let Some(ParameterValue::Sketch(stored)) = feature.parameters.get("sketch_data") {
+            sketch.continue_numbering(stored);
+            stored.keep_locked(&mut sketch);
+        }
+        for downstream in self.built_from(id) {
+            if !self.pending_rebuild.contains(&downstream) {
+                self.pending_rebuild.push(downstream);
+            }
+        }
+
+        let feature = self.nodes.get_mut(&id).ok_or_else(|| "Feature not found".to_string())?;
+        sketch.number_additions();
+        feature.parameters.insert("sketch_data".to_string(), ParameterValue::Sketch(sketch));
+        let Some(ParameterValue::Sketch(stored)) = feature.parameters.get_mut("sketch_data") else {
+            unreachable!("sketch_data was just set")
+        };
+        let result = SketchSolver::solve_deferred(stored);
+        Ok((&*stored, result))
+    }
+
+    /// End live editing; the caller regenerates to rebuild what was pending
+    pub fn commit_live_edit(&mut self) {
+        self.pending_rebuild.clear();
+    }
+
+    /// Features built from `id`, directly or through others, in sort order
+    fn built_from(&self, id: EntityId) -> Vec<EntityId> {
+        let mut found = Vec::new();
+        let mut queue = vec![id];
+        while let Some(next) = queue.pop() {
+            for dependent in self.downstream_of(next) {
+                if !found.contains(&dependent) {
+                    found.push(dependent);
+                    queue.push(dependent);
+                }
+            }
+        }
+        found.sort_by_key(|f| self.sort_order.iter().position(|s| s == f).unwrap_or(usize::MAX));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::Runtime;
+    use crate::features::types::Feature;
+    use crate::kernel::solid_operation_count;
+    use crate::sketch::types::{ConstraintPoint, SketchConstraint, SketchEntity, SketchGeometry, SketchPlane};
+    use crate::topo::IdGenerator;
+
+    fn square(size: f64) -> Sketch {
+        let corners = [[0.0, 0.0], [size, 0.0], [size, size], [0.0, size]];
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for i in 0..4 {
+            sketch.entities.push(SketchEntity {
+                id: EntityId::new_deterministic(&format!("live_test:line{}", i)),
+                geometry: SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] },
+                is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
+            });
+        }
+        let start = sketch.entities[0].id;
+        sketch.add_constraint(SketchConstraint::Fix { point: ConstraintPoint { id: start, index: 0 }, position: [0.0, 0.0] });
+        sketch
+    }
+
+    /// Sketch, an extrude of it, and a point placed on the extrude.
+    /// Returns the graph and the three feature ids.
+    fn model() -> (FeatureGraph, [EntityId; 3]) {
+        let sketch = square(10.0);
+        let profiles = sketch.entities.iter().map(|e| e.id.to_string()).collect();
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0))
+            .with_param("profiles", ParameterValue::List(profiles));
+        extrude.dependencies = vec![sketch.id];
+        let mut point = Feature::new("Point1", FeatureType::Point);
+        point.dependencies = vec![extrude.id];
+        let ids = [sketch.id, extrude.id, point.id];
+        let mut graph = FeatureGraph::new();
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+        graph.add_node(point);
+        (graph, ids)
+    }
+
+    fn geometry_hash(graph: &mut FeatureGraph) -> u64 {
+        Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("live_test")).unwrap().tessellation.geometry_hash()
+    }
+
+    #[test]
+    fn test_live_updates_skip_the_kernel_and_commit_matches_full_regen() {
+        let (mut graph, [sketch_id, extrude_id, point_id]) = model();
+        // Non-fast path: update the sketch and regenerate
+        let mut full = graph.clone();
+        let edited = ParameterValue::Sketch(square(14.0));
+        full.update_feature_params(sketch_id, [("sketch_data".to_string(), edited)].into()).unwrap();
+        let expected = geometry_hash(&mut full);
+
+        let before = geometry_hash(&mut graph);
+        let runtime = Runtime::new();
+        let operations = solid_operation_count();
+        for size in [11.0, 12.5, 14.0] {
+            let (sketch, result) = graph.update_sketch_live(sketch_id, square(size)).unwrap();
+            assert!(result.converged);
+            let render = runtime.render_sketch(sketch_id, sketch, None);
+            assert!(render.line_ids.iter().any(|id| id.feature_id == sketch.entities[0].id));
+            assert!(render.indices.is_empty(), "Only curves and points are drawn");
+        }
+        assert_eq!(solid_operation_count(), operations, "No solids are built while editing live");
+        assert_eq!(graph.pending_rebuild, vec![extrude_id, point_id]);
+        let json = serde_json::to_value(&graph).unwrap();
+        assert_eq!(json["pending_rebuild"].as_array().map(|a| a.len()), Some(2));
+
+        graph.commit_live_edit();
+        assert!(graph.pending_rebuild.is_empty());
+        let committed = geometry_hash(&mut graph);
+        assert_ne!(committed, before);
+        assert_eq!(committed, expected, "Committing builds what a regular update would");
+
+        let error = graph.update_sketch_live(extrude_id, square(1.0)).unwrap_err();
+        assert_eq!(error, "'Extrude1' is not a sketch");
+    }
+
+    #[test]
+    fn test_edits_do_not_move_locked_entities() {
+        let (mut graph, [sketch_id, ..]) = model();
+        let Some(ParameterValue::Sketch(stored)) = graph.nodes.get_mut(&sketch_id).unwrap().parameters.get_mut("sketch_data") else { panic!() };
+        let line = stored.entities[0].id;
+        stored.entities[0].locked = true;
+        let held = stored.entities[0].geometry.clone();
+
+        // A drag that moves every side, sent by a client that doesn't know about locks
+        let (sketch, _) = graph.update_sketch_live(sketch_id, square(14.0)).unwrap();
+        assert_eq!(sketch.entities[0].geometry, held, "The locked line is put back");
+        assert!(sketch.entities[0].locked);
+        assert_eq!(sketch.entities[1].geometry, SketchGeometry::Line { start: [14.0, 0.0], end: [14.0, 14.0] });
+
+        graph.update_feature_params(sketch_id, [("sketch_data".to_string(), ParameterValue::Sketch(square(20.0)))].into()).unwrap();
+        let Some(ParameterValue::Sketch(stored)) = graph.nodes[&sketch_id].parameters.get("sketch_data") else { panic!() };
+        assert_eq!((stored.entities[0].id, &stored.entities[0].geometry), (line, &held), "UpdateFeature keeps it too");
+    }
+}
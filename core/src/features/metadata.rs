@@ -0,0 +1,329 @@
+//! Engineering metadata for features and documents.
+//!
+//! Notes, tags and custom properties are bookkeeping only: they are stored with the
+//! graph (and therefore included in GRAPH_UPDATE and saved documents) but never read
+//! by regeneration. Editing them leaves the generated program untouched, so callers
+//! don't need to regenerate after a metadata change.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::dag::FeatureGraph;
+use super::types::FeatureType;
+use crate::topo::EntityId;
+
+/// Maximum length of a property key or tag (in characters)
+pub const MAX_KEY_LEN: usize = 64;
+/// Maximum length of a string property value (in characters)
+pub const MAX_VALUE_LEN: usize = 1024;
+/// Maximum length of a feature's notes (in characters)
+pub const MAX_NOTES_LEN: usize = 8192;
+/// Maximum number of properties on a single feature or document
+pub const MAX_PROPERTIES: usize = 128;
+/// Maximum number of tags on a single feature
+pub const MAX_TAGS: usize = 32;
+
+/// Well-known document property keys
+pub const PART_NUMBER: &str = "part_number";
+pub const REVISION: &str = "revision";
+pub const AUTHOR: &str = "author";
+/// Length unit values are displayed in, as a symbol ("mm", "in", ...); model values stay in mm
+pub const UNITS: &str = "units";
+
+/// Value of a custom property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum PropertyValue {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    /// Calendar date as ISO-8601 `YYYY-MM-DD`
+    Date(String),
+}
+
+impl PropertyValue {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            PropertyValue::String(s) if s.chars().count() > MAX_VALUE_LEN => {
+                Err(format!("Property value exceeds {} characters", MAX_VALUE_LEN))
+            }
+            PropertyValue::Number(n) if !n.is_finite() => {
+                Err("Property number must be finite".to_string())
+            }
+            PropertyValue::Date(d) if !is_iso_date(d) => {
+                Err(format!("Invalid date '{}', expected YYYY-MM-DD", d))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_iso_date(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return false;
+    }
+    if !parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit())) {
+        return false;
+    }
+    let month: u32 = parts[1].parse().unwrap_or(0);
+    let day: u32 = parts[2].parse().unwrap_or(0);
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// Validate a property key or tag: non-empty, no surrounding whitespace, bounded length
+pub fn validate_key(key: &str) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("Key must not be empty".to_string());
+    }
+    if key.trim() != key {
+        return Err(format!("Key '{}' has leading or trailing whitespace", key));
+    }
+    if key.chars().count() > MAX_KEY_LEN {
+        return Err(format!("Key exceeds {} characters", MAX_KEY_LEN));
+    }
+    Ok(())
+}
+
+/// Partial update of a feature's metadata. Omitted fields are left unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataPatch {
+    /// Properties to add or overwrite
+    #[serde(default)]
+    pub set: HashMap<String, PropertyValue>,
+    /// Property keys to remove (applied before `set`)
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// Replacement notes
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Replacement tag list
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Apply removals then insertions to a property map, validating everything first
+/// so a rejected patch leaves the map unchanged.
+fn patch_properties(
+    properties: &mut HashMap<String, PropertyValue>,
+    set: &HashMap<String, PropertyValue>,
+    remove: &[String],
+) -> Result<(), String> {
+    for (key, value) in set {
+        validate_key(key)?;
+        value.validate().map_err(|e| format!("{}: {}", key, e))?;
+    }
+    let remaining = properties.keys()
+        .filter(|k| !remove.contains(k) && !set.contains_key(*k))
+        .count();
+    if remaining + set.len() > MAX_PROPERTIES {
+        return Err(format!("At most {} properties are allowed", MAX_PROPERTIES));
+    }
+
+    for key in remove {
+        properties.remove(key);
+    }
+    for (key, value) in set {
+        properties.insert(key.clone(), value.clone());
+    }
+    Ok(())
+}
+
+/// Criteria for `FeatureGraph::find_features`. All given criteria must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeatureQuery {
+    /// Case-insensitive substring of the feature name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Exact tag
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub feature_type: Option<FeatureType>,
+    /// Property that must be present with exactly this value
+    #[serde(default)]
+    pub property: Option<(String, PropertyValue)>,
+}
+
+impl FeatureGraph {
+    /// Update a feature's notes, tags and properties.
+    /// Does not touch anything regeneration reads, so no regen is needed afterwards.
+    pub fn set_feature_metadata(&mut self, id: EntityId, patch: &MetadataPatch) -> Result<(), String> {
+        let feature = self.nodes.get_mut(&id).ok_or_else(|| "Feature not found".to_string())?;
+
+        if let Some(notes) = &patch.notes {
+            if notes.chars().count() > MAX_NOTES_LEN {
+                return Err(format!("Notes exceed {} characters", MAX_NOTES_LEN));
+            }
+        }
+        if let Some(tags) = &patch.tags {
+            if tags.len() > MAX_TAGS {
+                return Err(format!("At most {} tags are allowed", MAX_TAGS));
+            }
+            for tag in tags {
+                validate_key(tag).map_err(|e| format!("Invalid tag: {}", e))?;
+            }
+        }
+        patch_properties(&mut feature.metadata, &patch.set, &patch.remove)?;
+
+        if let Some(notes) = &patch.notes {
+            feature.notes = notes.clone();
+        }
+        if let Some(tags) = &patch.tags {
+            let mut unique: Vec<String> = Vec::with_capacity(tags.len());
+            for tag in tags {
+                if !unique.contains(tag) {
+                    unique.push(tag.clone());
+                }
+            }
+            feature.tags = unique;
+        }
+        Ok(())
+    }
+
+    /// Update document-level properties (part number, revision, author, ...)
+    pub fn set_document_properties(
+        &mut self,
+        set: &HashMap<String, PropertyValue>,
+        remove: &[String],
+    ) -> Result<(), String> {
+        patch_properties(&mut self.document_properties, set, remove)
+    }
+
+    /// Display unit from the `units` document property, millimeters if unset or unknown
+    pub fn document_length_unit(&self) -> crate::units::LengthUnit {
+        match self.document_properties.get(UNITS) {
+            Some(PropertyValue::String(symbol)) => crate::units::LengthUnit::from_symbol(symbol),
+            _ => None,
+        }
+        .unwrap_or(crate::units::LengthUnit::Millimeter)
+    }
+
+    /// Find features matching a query, in feature-tree order
+    pub fn find_features(&self, query: &FeatureQuery) -> Vec<EntityId> {
+        let name = query.name.as_ref().map(|n| n.to_lowercase());
+        self.sort_order.iter()
+            .filter_map(|id| self.nodes.get(id))
+            .filter(|f| name.as_ref().is_none_or(|n| f.name.to_lowercase().contains(n.as_str())))
+            .filter(|f| query.tag.as_ref().is_none_or(|t| f.tags.contains(t)))
+            .filter(|f| query.feature_type.as_ref().is_none_or(|t| f.feature_type == *t))
+            .filter(|f| query.property.as_ref().is_none_or(|(k, v)| f.metadata.get(k) == Some(v)))
+            .map(|f| f.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::types::Feature;
+
+    /// 20 features: 10 sketches, 10 extrudes; every third tagged "machined",
+    /// even extrudes carry material = steel.
+    fn tagged_graph() -> FeatureGraph {
+        let mut graph = FeatureGraph::new();
+        for i in 0..20 {
+            let (name, ftype) = if i % 2 == 0 {
+                (format!("Sketch{}", i), FeatureType::Sketch)
+            } else {
+                (format!("Extrude{}", i), FeatureType::Extrude)
+            };
+            let feature = Feature::new(&name, ftype);
+            let id = feature.id;
+            graph.add_node(feature);
+
+            let mut patch = MetadataPatch::default();
+            if i % 3 == 0 {
+                patch.tags = Some(vec!["machined".to_string()]);
+            }
+            if i % 4 == 1 {
+                patch.set.insert("material".to_string(), PropertyValue::String("steel".to_string()));
+            }
+            graph.set_feature_metadata(id, &patch).unwrap();
+        }
+        graph
+    }
+
+    fn names(graph: &FeatureGraph, ids: &[EntityId]) -> Vec<String> {
+        ids.iter().map(|id| graph.nodes[id].name.clone()).collect()
+    }
+
+    #[test]
+    fn test_find_features_across_graph() {
+        let graph = tagged_graph();
+
+        let by_name = graph.find_features(&FeatureQuery { name: Some("extrude1".into()), ..Default::default() });
+        assert_eq!(names(&graph, &by_name), vec!["Extrude1", "Extrude11", "Extrude13", "Extrude15", "Extrude17", "Extrude19"]);
+
+        let by_tag = graph.find_features(&FeatureQuery { tag: Some("machined".into()), ..Default::default() });
+        assert_eq!(by_tag.len(), 7);
+
+        let by_type = graph.find_features(&FeatureQuery { feature_type: Some(FeatureType::Extrude), ..Default::default() });
+        assert_eq!(by_type.len(), 10);
+
+        let steel = ("material".to_string(), PropertyValue::String("steel".to_string()));
+        let by_prop = graph.find_features(&FeatureQuery { property: Some(steel.clone()), ..Default::default() });
+        assert_eq!(names(&graph, &by_prop), vec!["Extrude1", "Extrude5", "Extrude9", "Extrude13", "Extrude17"]);
+
+        // Criteria combine
+        let combined = graph.find_features(&FeatureQuery {
+            tag: Some("machined".into()),
+            property: Some(steel),
+            ..Default::default()
+        });
+        assert_eq!(names(&graph, &combined), vec!["Extrude9"]);
+    }
+
+    #[test]
+    fn test_metadata_edits_do_not_affect_regeneration() {
+        let mut graph = tagged_graph();
+        let before = graph.regenerate().statements;
+
+        let id = graph.sort_order[3];
+        let patch = MetadataPatch {
+            notes: Some("Check wall thickness with supplier".into()),
+            set: HashMap::from([("inspected".to_string(), PropertyValue::Date("2024-05-01".into()))]),
+            ..Default::default()
+        };
+        graph.set_feature_metadata(id, &patch).unwrap();
+        graph.set_document_properties(
+            &HashMap::from([(PART_NUMBER.to_string(), PropertyValue::String("PN-1001".into()))]),
+            &[],
+        ).unwrap();
+
+        assert_eq!(graph.regenerate().statements, before);
+
+        // Metadata survives a save/load round trip
+        let loaded: FeatureGraph = serde_json::from_str(&serde_json::to_string(&graph).unwrap()).unwrap();
+        assert_eq!(loaded.nodes[&id].notes, "Check wall thickness with supplier");
+        assert_eq!(loaded.document_properties[PART_NUMBER], PropertyValue::String("PN-1001".into()));
+    }
+
+    #[test]
+    fn test_invalid_metadata_is_rejected_without_changes() {
+        let mut graph = tagged_graph();
+        let id = graph.sort_order[1];
+
+        let bad_key = MetadataPatch {
+            set: HashMap::from([
+                ("".to_string(), PropertyValue::Bool(true)),
+                ("ok".to_string(), PropertyValue::Bool(true)),
+            ]),
+            ..Default::default()
+        };
+        assert!(graph.set_feature_metadata(id, &bad_key).is_err());
+        assert!(!graph.nodes[&id].metadata.contains_key("ok"));
+
+        let long_value = MetadataPatch {
+            set: HashMap::from([("desc".to_string(), PropertyValue::String("x".repeat(MAX_VALUE_LEN + 1)))]),
+            ..Default::default()
+        };
+        assert!(graph.set_feature_metadata(id, &long_value).is_err());
+
+        let bad_date = MetadataPatch {
+            set: HashMap::from([("due".to_string(), PropertyValue::Date("next week".into()))]),
+            ..Default::default()
+        };
+        assert!(graph.set_feature_metadata(id, &bad_date).is_err());
+    }
+}
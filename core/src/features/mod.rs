@@ -1,2 +1,15 @@
 pub mod types;
 pub mod dag;
+pub mod metadata;
+pub mod datum;
+pub mod clipboard;
+pub mod delete;
+pub mod from_selection;
+pub mod bodies;
+pub mod repair;
+pub mod ids;
+pub mod live;
+pub mod import;
+pub mod face_offset;
+pub mod recovery;
+pub mod snapshot;
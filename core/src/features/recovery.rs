@@ -0,0 +1,92 @@
+//! Recovering from edits that break regeneration
+//!
+//! After every clean regeneration (no evaluation error, no failed feature)
+//! the caller records the snapshot it regenerated with `record_good_regen`.
+//! When an edit leaves the model failing, `revert_to_last_good` puts the
+//! graph back as it was then, discarding the edits since.
+
+use super::dag::FeatureGraph;
+use super::snapshot::GraphSnapshot;
+
+impl FeatureGraph {
+    /// Remember `regenerated`, the snapshot a clean regeneration was built
+    /// from, as the graph to revert to. It is shared, not copied.
+    pub fn record_good_regen(&mut self, regenerated: GraphSnapshot) {
+        self.last_good_snapshot = Some(regenerated);
+    }
+
+    /// Restore the graph as of the last clean regeneration. The snapshot is
+    /// kept, so reverting again has the same result. Feature ids minted
+    /// since aren't handed out again.
+    pub fn revert_to_last_good(&mut self) -> Result<(), String> {
+        let snapshot = self.last_good_snapshot.take()
+            .ok_or_else(|| "The model has not regenerated cleanly yet; nothing to revert to".to_string())?;
+        let id_sequence = self.id_sequence;
+        *self = snapshot.graph().clone();
+        self.id_sequence = self.id_sequence.max(id_sequence);
+        self.last_good_snapshot = Some(snapshot);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::{EvaluationResult, Runtime};
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+    use crate::topo::IdGenerator;
+
+    /// Triangle above the X axis, or straddling it when `bottom` is negative
+    fn triangle(bottom: f64) -> Sketch {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Line { start: [5.0, bottom], end: [10.0, 5.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [10.0, 5.0], end: [5.0, 5.0] });
+        sketch.add_entity(SketchGeometry::Line { start: [5.0, 5.0], end: [5.0, bottom] });
+        sketch
+    }
+
+    fn evaluate(graph: &mut FeatureGraph) -> EvaluationResult {
+        Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("Recovery")).unwrap()
+    }
+
+    #[test]
+    fn test_revert_restores_last_clean_regeneration() {
+        let mut graph = FeatureGraph::new();
+        assert!(graph.revert_to_last_good().is_err(), "Nothing recorded yet");
+
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(triangle(2.0)));
+        let mut revolve = Feature::new("Revolve1", FeatureType::Revolve).with_param("axis", ParameterValue::String("X".into()));
+        revolve.dependencies.push(sketch.id);
+        let sketch_id = sketch.id;
+        graph.add_node(sketch);
+        graph.add_node(revolve);
+        let good = evaluate(&mut graph);
+        assert!(good.feature_errors.is_empty() && !good.tessellation.indices.is_empty());
+        graph.record_good_regen(graph.snapshot_for_serialization());
+
+        // Dragging the profile across the axis breaks the revolve
+        graph.nodes.get_mut(&sketch_id).unwrap().parameters.insert("sketch_data".into(), ParameterValue::Sketch(triangle(-2.0)));
+        let mut extra = Feature::new("Point1", FeatureType::Point);
+        extra.id = graph.mint_feature_id(None);
+        extra.dependencies.push(sketch_id);
+        let extra_id = extra.id;
+        graph.add_node(extra);
+        let broken = evaluate(&mut graph);
+        assert_eq!(broken.feature_errors.len(), 1);
+
+        graph.revert_to_last_good().unwrap();
+        assert_eq!(graph.nodes.len(), 2, "Edits since are discarded");
+        let reverted = evaluate(&mut graph);
+        assert!(reverted.feature_errors.is_empty());
+        assert_eq!(reverted.tessellation.geometry_hash(), good.tessellation.geometry_hash(), "The same geometry renders again");
+
+        assert_eq!(graph.id_sequence, 1, "The discarded feature's id stays used");
+        assert_ne!(graph.mint_feature_id(None), extra_id, "Ids aren't handed out twice");
+
+        graph.revert_to_last_good().unwrap();
+        assert!(graph.last_good_snapshot.as_ref().unwrap().graph().last_good_snapshot.is_none(), "Snapshots don't nest");
+        let json = serde_json::to_value(&graph).unwrap();
+        assert!(json.get("last_good_snapshot").is_none(), "The snapshot isn't saved");
+    }
+}
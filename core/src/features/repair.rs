@@ -0,0 +1,190 @@
+//! Repairing lost references
+//!
+//! The zombie check (`TopoRegistry::validate_references`) reports each lost
+//! reference with the feature and parameter path holding it, plus suggested
+//! `RepairAction`s. `apply_zombie_repair` carries one of them out; the caller
+//! regenerates afterwards.
+
+use super::dag::FeatureGraph;
+use super::types::ParameterValue;
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::registry::RepairAction;
+use crate::topo::EntityId;
+
+/// Splits "profiles[2]" into ("profiles", Some(2)) and "ref_a" into ("ref_a", None)
+fn split_param_path(param_path: &str) -> Result<(&str, Option<usize>), String> {
+    let Some((name, rest)) = param_path.split_once('[') else { return Ok((param_path, None)) };
+    rest.strip_suffix(']')
+        .and_then(|index| index.parse().ok())
+        .map(|index| (name, Some(index)))
+        .ok_or_else(|| format!("Invalid parameter path '{}'", param_path))
+}
+
+impl FeatureGraph {
+    /// Fix the reference `owner` holds at `param_path` (as reported by the zombie
+    /// check) with `action`. Clearing a profile entry removes it from the list;
+    /// an extrude left with no profiles extrudes the whole sketch. Replacing a
+    /// profile entry requires an edge of the extrude's own sketch.
+    pub fn apply_zombie_repair(&mut self, owner: EntityId, param_path: &str, action: &RepairAction) -> Result<(), String> {
+        let (name, index) = split_param_path(param_path)?;
+        let feature = self.nodes.get(&owner).ok_or_else(|| "Feature not found".to_string())?;
+        // Entities a profile entry may point at
+        let profile_entities: Vec<EntityId> = feature.dependencies.first()
+            .and_then(|dep| self.nodes.get(dep))
+            .and_then(|dep| match dep.parameters.get("sketch_data") {
+                Some(ParameterValue::Sketch(sketch)) => Some(sketch.entities.iter().map(|e| e.id).collect()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let feature = self.nodes.get_mut(&owner).ok_or_else(|| "Feature not found".to_string())?;
+        let missing = format!("Feature '{}' has no reference at '{}'", feature.name, param_path);
+        if *action == RepairAction::SuppressOwner {
+            if !feature.reference_paths().iter().any(|(path, _)| path == param_path) {
+                return Err(missing);
+            }
+            feature.suppressed = true;
+            return Ok(());
+        }
+        match (feature.parameters.get_mut(name), index, action) {
+            (Some(ParameterValue::Reference(id)), None, RepairAction::Replace { with }) => {
+                if id.rank != with.rank {
+                    return Err(format!("Cannot replace a {:?} reference with a {:?}", id.rank, with.rank));
+                }
+                *id = *with;
+            }
+            (Some(ParameterValue::Reference(_)), None, RepairAction::ClearReference) => {
+                feature.parameters.remove(name);
+            }
+            (Some(ParameterValue::List(entries)), Some(i), _) if name == "profiles" && i < entries.len() => match action {
+                RepairAction::Replace { with } => {
+                    if *with != TopoId::new(with.feature_id, 0, TopoRank::Edge) || !profile_entities.contains(&with.feature_id) {
+                        return Err("A profile can only be replaced by a curve of the same sketch".to_string());
+                    }
+                    entries[i] = with.feature_id.to_string();
+                }
+                RepairAction::ClearReference => {
+                    entries.remove(i);
+                }
+                RepairAction::SuppressOwner => unreachable!("handled above"),
+            },
+            _ => return Err(missing),
+        }
+        feature.error = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::{EvaluationResult, Runtime};
+    use crate::features::types::{Feature, FeatureType};
+    use crate::sketch::types::{Sketch, SketchEntity, SketchGeometry, SketchPlane};
+    use crate::topo::registry::{Zombie, TopoRegistry};
+    use crate::topo::IdGenerator;
+
+    fn line(id: EntityId, start: [f64; 2], end: [f64; 2]) -> SketchEntity {
+        SketchEntity { id, geometry: SketchGeometry::Line { start, end }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false }
+    }
+
+    /// 10x10 square sketch and an extrude of its four lines.
+    /// Returns the graph, sketch, extrude and line ids.
+    fn square_extrude() -> (FeatureGraph, EntityId, EntityId, [EntityId; 4]) {
+        let lines = [0, 1, 2, 3].map(|i| EntityId::new_deterministic(&format!("repair_test:line{}", i)));
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for (i, id) in lines.iter().enumerate() {
+            sketch.entities.push(line(*id, corners[i], corners[(i + 1) % 4]));
+        }
+        let sketch = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0))
+            .with_param("profiles", ParameterValue::List(lines.iter().map(|id| id.to_string()).collect()));
+        extrude.dependencies = vec![sketch.id];
+        let (sketch_id, extrude_id) = (sketch.id, extrude.id);
+        let mut graph = FeatureGraph::new();
+        graph.add_node(sketch);
+        graph.add_node(extrude);
+        (graph, sketch_id, extrude_id, lines)
+    }
+
+    fn sketch_mut(graph: &mut FeatureGraph, id: EntityId) -> &mut Sketch {
+        match graph.nodes.get_mut(&id).unwrap().parameters.get_mut("sketch_data") {
+            Some(ParameterValue::Sketch(sketch)) => sketch,
+            _ => panic!("not a sketch"),
+        }
+    }
+
+    /// Regenerate and run the zombie check the way the backend does, carrying
+    /// geometry over from the previous registry
+    fn regen(graph: &mut FeatureGraph, previous: &TopoRegistry) -> (EvaluationResult, TopoRegistry, Vec<Zombie>) {
+        let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("repair_test")).unwrap();
+        let mut registry = TopoRegistry::new();
+        for entity in result.topology_manifest.values() {
+            registry.register(entity.clone());
+        }
+        registry.carry_history(previous);
+        let zombies = registry.validate_references(&graph.collect_all_references());
+        (result, registry, zombies)
+    }
+
+    #[test]
+    fn test_redrawn_profile_line_is_suggested_and_repairs_extrude() {
+        let (mut graph, sketch_id, extrude_id, lines) = square_extrude();
+        let (result, registry, zombies) = regen(&mut graph, &TopoRegistry::new());
+        assert!(zombies.is_empty());
+        let faces = result.topology_manifest.keys().filter(|id| id.rank == TopoRank::Face).count();
+
+        // Delete the bottom line and draw it again: the extrude still names the old one
+        let redrawn = EntityId::new_deterministic("repair_test:redrawn");
+        let sketch = sketch_mut(&mut graph, sketch_id);
+        sketch.entities.retain(|e| e.id != lines[0]);
+        sketch.entities.push(line(redrawn, [0.0, 0.0], [10.0, 0.0]));
+        let (_, registry, zombies) = regen(&mut graph, &registry);
+
+        assert_eq!(zombies.len(), 1);
+        let zombie = &zombies[0];
+        assert_eq!(zombie.id, TopoId::new(lines[0], 0, TopoRank::Edge));
+        assert_eq!((zombie.owner, zombie.param_path.as_str()), (extrude_id, "profiles[0]"));
+        assert!(zombie.last_geometry.is_some(), "Geometry from before the delete is reported");
+        let suggested = &zombie.repairs[0].action;
+        assert_eq!(suggested, &RepairAction::Replace { with: TopoId::new(redrawn, 0, TopoRank::Edge) });
+
+        graph.apply_zombie_repair(zombie.owner, &zombie.param_path, suggested).unwrap();
+        let (result, _, zombies) = regen(&mut graph, &registry);
+        assert!(zombies.is_empty(), "Repaired reference resolves: {:?}", zombies);
+        let repaired_faces = result.topology_manifest.keys().filter(|id| id.rank == TopoRank::Face).count();
+        assert_eq!(repaired_faces, faces, "The extrude is whole again");
+    }
+
+    #[test]
+    fn test_deleted_profile_line_can_be_cleared_or_suppressed() {
+        let (mut graph, sketch_id, extrude_id, lines) = square_extrude();
+        let (_, registry, _) = regen(&mut graph, &TopoRegistry::new());
+        sketch_mut(&mut graph, sketch_id).entities.retain(|e| e.id != lines[2]);
+        let (_, registry, zombies) = regen(&mut graph, &registry);
+
+        assert_eq!(zombies.len(), 1);
+        assert_eq!(zombies[0].param_path, "profiles[2]");
+        let actions: Vec<&RepairAction> = zombies[0].repairs.iter().map(|r| &r.action).collect();
+        assert_eq!(actions, vec![&RepairAction::ClearReference, &RepairAction::SuppressOwner], "No curve looks like the lost one");
+        let foreign = TopoId::new(EntityId::new(), 0, TopoRank::Edge);
+        let err = graph.apply_zombie_repair(extrude_id, "profiles[2]", &RepairAction::Replace { with: foreign }).unwrap_err();
+        assert_eq!(err, "A profile can only be replaced by a curve of the same sketch");
+        assert!(graph.apply_zombie_repair(extrude_id, "profiles[9]", &RepairAction::ClearReference).is_err());
+
+        let mut cleared = graph.clone();
+        cleared.apply_zombie_repair(extrude_id, "profiles[2]", &RepairAction::ClearReference).unwrap();
+        match &cleared.nodes[&extrude_id].parameters["profiles"] {
+            ParameterValue::List(entries) => assert_eq!(entries.len(), 3),
+            other => panic!("Expected a list, got {:?}", other),
+        }
+        assert!(regen(&mut cleared, &registry).2.is_empty());
+
+        graph.apply_zombie_repair(extrude_id, "profiles[2]", &RepairAction::SuppressOwner).unwrap();
+        assert!(graph.nodes[&extrude_id].suppressed);
+        assert!(regen(&mut graph, &registry).2.is_empty(), "Suppressed features don't demand references");
+    }
+}
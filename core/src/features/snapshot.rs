@@ -0,0 +1,105 @@
+//! Copies of the graph taken to be read outside its lock
+//!
+//! Encoding a large document as JSON takes far longer than copying it. A
+//! session holding the shared graph's lock takes a `GraphSnapshot` instead and
+//! serializes or regenerates that once the lock is released, so edits from
+//! other sessions don't wait on it.
+//!
+//! Snapshots share the copy they took, so the one a clean regeneration was
+//! built from can be kept for `revert_to_last_good` without copying again.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::dag::FeatureGraph;
+use crate::evaluator::ast::Program;
+use crate::topo::EntityId;
+
+/// The graph as it was when the snapshot was taken. Displays as the graph's JSON.
+#[derive(Debug, Clone)]
+pub struct GraphSnapshot(Arc<FeatureGraph>);
+
+/// A program with the snapshot it was regenerated from
+#[derive(Debug, Clone)]
+pub struct Regeneration {
+    pub program: Program,
+    pub snapshot: GraphSnapshot,
+}
+
+impl FeatureGraph {
+    /// Copy the graph for serializing or regenerating after the lock on it
+    /// is released. The last good regeneration, which is never saved, is left out.
+    pub fn snapshot_for_serialization(&self) -> GraphSnapshot {
+        GraphSnapshot(Arc::new(FeatureGraph {
+            nodes: self.nodes.clone(),
+            sort_order: self.sort_order.clone(),
+            variables: self.variables.clone(),
+            rollback_point: self.rollback_point,
+            document_properties: self.document_properties.clone(),
+            published_measurements: self.published_measurements.clone(),
+            body_names: self.body_names.clone(),
+            id_namespace: self.id_namespace.clone(),
+            id_sequence: self.id_sequence,
+            pending_rebuild: self.pending_rebuild.clone(),
+            last_good_snapshot: None,
+        }))
+    }
+}
+
+impl GraphSnapshot {
+    pub fn graph(&self) -> &FeatureGraph {
+        &self.0
+    }
+
+    /// The snapshot with its program (see `FeatureGraph::regenerate`), for
+    /// regenerating once the lock it was taken under is released
+    pub fn regenerated(mut self) -> (GraphSnapshot, Regeneration) {
+        let program = Arc::make_mut(&mut self.0).regenerate();
+        let snapshot = self.clone();
+        (self, Regeneration { program, snapshot })
+    }
+
+    /// The program for the snapshot with its rollback bar at `rollback`
+    /// instead (inclusive; None for the whole model)
+    pub fn program_at(mut self, rollback: Option<EntityId>) -> Program {
+        let graph = Arc::make_mut(&mut self.0);
+        graph.rollback_point = rollback;
+        graph.regenerate()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self.graph()).unwrap_or("{}".to_string())
+    }
+}
+
+impl fmt::Display for GraphSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::types::{Feature, FeatureType};
+
+    #[test]
+    fn test_snapshot_serializes_like_the_graph() {
+        let mut graph = FeatureGraph::new();
+        graph.add_node(Feature::new("Point1", FeatureType::Point));
+        graph.record_good_regen(graph.snapshot_for_serialization());
+        graph.add_node(Feature::new("Point2", FeatureType::Point));
+
+        let snapshot = graph.snapshot_for_serialization();
+        assert!(snapshot.graph().last_good_snapshot.is_none());
+        assert_eq!(snapshot.to_json(), serde_json::to_string(&graph).unwrap());
+        assert_eq!(format!("GRAPH_UPDATE:{}", snapshot), format!("GRAPH_UPDATE:{}", snapshot.to_json()));
+        let (snapshot, regen) = snapshot.regenerated();
+        assert_eq!(regen.program.to_string(), graph.regenerate().to_string());
+        assert!(Arc::ptr_eq(&snapshot.0, &regen.snapshot.0), "The program's snapshot is shared, not copied");
+
+        // Later edits don't reach the snapshot
+        graph.add_node(Feature::new("Point3", FeatureType::Point));
+        assert_eq!(snapshot.graph().nodes.len(), 2);
+    }
+}
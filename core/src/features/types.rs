@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use crate::topo::EntityId;
 use std::collections::HashMap;
 
+// Sketches are stored inline; a feature holds a handful of parameters at most
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ParameterValue {
     Float(f64),
@@ -16,6 +18,8 @@ pub enum ParameterValue {
     ProfileRegions(Vec<Vec<Vec<[f64; 2]>>>),
     /// Expression that may reference variables, e.g. "@thickness * 2"
     Expression(String),
+    /// Per-region settings of a multi-body extrude
+    RegionSpecs(Vec<RegionSpec>),
 }
 
 
@@ -45,6 +49,37 @@ pub enum ExtrudeDirection {
     Custom([f64; 3]),   // Custom direction vector
 }
 
+/// Which way a single region of an extrude goes, relative to the feature's direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum RegionDirection {
+    #[default]
+    Normal,
+    Reverse,
+}
+
+/// One region of a multi-body extrude. Each spec becomes its own body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionSpec {
+    /// `stable_id` of the region as reported by GetRegions
+    pub region_key: String,
+    /// Depth for this region (expression, may reference variables); the feature's `distance` if None
+    #[serde(default)]
+    pub depth_override: Option<String>,
+    #[serde(default)]
+    pub direction: RegionDirection,
+    /// Region centroid when it was picked, used to find it again if its key no longer matches
+    #[serde(default)]
+    pub centroid: Option<[f64; 2]>,
+}
+
+/// A `RegionSpec` with its depth evaluated to a signed distance, as passed to the runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedRegionSpec {
+    pub region_key: String,
+    pub distance: f64,
+    pub centroid: Option<[f64; 2]>,
+}
+
 /// Axis definition for revolve features
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub enum RevolveAxis {
@@ -125,6 +160,20 @@ pub enum FeatureType {
     Plane,
     Axis,
     Point,
+    /// Plane resolved from model topology (offset, angle, three points, midplane)
+    DatumPlane,
+    /// Axis resolved from model topology (two points, edge, plane intersection)
+    DatumAxis,
+    // Direct edits
+    DeleteFace,
+    /// Solid read from a STEP file; see `features::import`
+    ImportedBody,
+    /// Box from the origin to (`width`, `height`, `depth`)
+    Box,
+    /// Torus around Z through the origin (`major_radius`, `minor_radius`), meshed directly
+    Torus,
+    /// Cylinder around Z standing on the origin (`radius`, `height`), meshed directly
+    Cylinder,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -135,10 +184,27 @@ pub struct Feature {
     pub parameters: HashMap<String, ParameterValue>,
     pub dependencies: Vec<EntityId>, // IDs of features this feature depends on
     pub suppressed: bool,
+    /// Hidden features still regenerate and count in mass properties; the
+    /// client just doesn't draw them. Suppression removes the geometry instead.
+    #[serde(default)]
+    pub hidden: bool,
     /// If set, this feature's geometry is consumed by a Boolean operation
     /// The geometry should still be computed but not tessellated for display
     #[serde(default)]
     pub consumed_by: Option<EntityId>,
+    /// User-defined engineering properties (material, supplier, ...).
+    /// Not used by regeneration; see `features::metadata`.
+    #[serde(default)]
+    pub metadata: HashMap<String, super::metadata::PropertyValue>,
+    /// Free-text notes shown in the feature tree
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Why the feature can't regenerate, e.g. an input was deleted out from under it.
+    /// Editing or repairing the feature's inputs clears it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 impl Feature {
@@ -150,7 +216,12 @@ impl Feature {
             parameters: HashMap::new(),
             dependencies: Vec::new(),
             suppressed: false,
+            hidden: false,
             consumed_by: None,
+            metadata: HashMap::new(),
+            notes: String::new(),
+            tags: Vec::new(),
+            error: None,
         }
     }
 
@@ -160,12 +231,31 @@ impl Feature {
     }
 
     pub fn collect_references(&self) -> Vec<crate::topo::naming::TopoId> {
+        self.reference_paths().into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Topology references with the parameter path holding each, sorted by parameter.
+    /// Reference parameters use their name; sketch entities chosen as
+    /// `profiles` are the edges the sketch registers for them, e.g. "profiles[1]".
+    pub fn reference_paths(&self) -> Vec<(String, crate::topo::naming::TopoId)> {
+        use crate::topo::naming::{TopoId, TopoRank};
         let mut refs = Vec::new();
-        for val in self.parameters.values() {
-            if let ParameterValue::Reference(id) = val {
-                refs.push(*id);
+        for (name, val) in &self.parameters {
+            match val {
+                ParameterValue::Reference(id) => refs.push((name.clone(), *id)),
+                ParameterValue::List(entries) if name == "profiles" => {
+                    for (i, entry) in entries.iter().enumerate() {
+                        if let Ok(uuid) = uuid::Uuid::parse_str(entry) {
+                            let edge = TopoId::new(EntityId::from_uuid(uuid), 0, TopoRank::Edge);
+                            refs.push((format!("{}[{}]", name, i), edge));
+                        }
+                    }
+                }
+                _ => {}
             }
         }
+        // Stable, so list entries stay in index order
+        refs.sort_by(|a, b| a.0.split('[').next().cmp(&b.0.split('[').next()));
         refs
     }
 }
@@ -0,0 +1,355 @@
+//! Blend (fillet) surfaces along straight model edges
+//!
+//! Builds the rounded face a fillet puts on a convex edge between two planar
+//! faces, as tessellation. Edges that continue one another in a straight line
+//! between the same pair of faces form a chain, blended as a single face with
+//! one smoothing group, so a fillet over an edge split in two renders and picks
+//! as one round.
+//!
+//! Continuity:
+//! - `G1` sweeps a circular arc tangent to both faces: the rolling-ball blend.
+//! - `G2` approximates a curvature-continuous blend. The kernel only knows
+//!   circular blends, so the profile is a quartic Bézier whose first and last
+//!   three control points are collinear. Its curvature is zero where it meets
+//!   the faces and ramps up towards the middle. It touches the faces at the
+//!   same setback as the circular blend of that radius. It bulges further
+//!   towards the sharp edge than the arc does.
+//!
+//! The radius can change from the start of a chain to its end (variable-radius
+//! fillet); it is interpolated linearly by distance along the chain.
+//!
+//! The kernel can't trim solids, so the blend is added to the model as faces
+//! of its own; the body's faces still meet at the sharp edge underneath.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Point3, Tessellation, Vector3};
+use crate::topo::naming::{NamingContext, TopoId, TopoRank};
+
+/// Edge ends closer than this are joined
+const JOIN_TOLERANCE: f64 = 1e-6;
+
+/// Joined edges whose directions differ by less than this (radians) are tangent
+const TANGENT_TOLERANCE: f64 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Continuity {
+    /// Tangent: a circular cross-section
+    #[default]
+    G1,
+    /// Curvature-continuous, approximated (see the module docs)
+    G2,
+}
+
+impl Continuity {
+    /// The fillet's `continuity` parameter, "G1" or "G2"
+    pub fn from_param(value: &str) -> Option<Continuity> {
+        match value {
+            "G1" => Some(Continuity::G1),
+            "G2" => Some(Continuity::G2),
+            _ => None,
+        }
+    }
+}
+
+/// A straight edge to blend and the outward normals of the two faces meeting at it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlendEdge {
+    pub id: TopoId,
+    pub start: Point3,
+    pub end: Point3,
+    pub normals: [Vector3; 2],
+}
+
+impl BlendEdge {
+    fn direction(&self) -> Vector3 {
+        (self.end - self.start).normalize()
+    }
+
+    fn reversed(&self) -> BlendEdge {
+        BlendEdge { start: self.end, end: self.start, ..*self }
+    }
+
+    fn same_faces(&self, other: &BlendEdge) -> bool {
+        let close = |a: &Vector3, b: &Vector3| (a - b).norm() < TANGENT_TOLERANCE;
+        let [a, b] = &self.normals;
+        let [c, d] = &other.normals;
+        (close(a, c) && close(b, d)) || (close(a, d) && close(b, c))
+    }
+
+    /// `next`, turned to run on from this edge's end, if the two are tangent-connected
+    fn continued_by(&self, next: &BlendEdge) -> Option<BlendEdge> {
+        let next = if (next.start - self.end).norm() < JOIN_TOLERANCE {
+            *next
+        } else if (next.end - self.end).norm() < JOIN_TOLERANCE {
+            next.reversed()
+        } else {
+            return None;
+        };
+        let aligned = self.direction().dot(&next.direction()) > TANGENT_TOLERANCE.cos();
+        (aligned && self.same_faces(&next)).then_some(next)
+    }
+}
+
+/// Edges of a tessellated model closer to a straight line than this are on it
+const EDGE_TOLERANCE: f64 = 1e-4;
+
+impl BlendEdge {
+    /// Straight model edge `id` of `tess`, with the normals of the two planar
+    /// faces whose triangles lie along it
+    pub fn from_tessellation(tess: &Tessellation, id: TopoId) -> Result<BlendEdge, String> {
+        let vertex = |i: u32| {
+            let v = &tess.vertices[i as usize * 3..i as usize * 3 + 3];
+            Point3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+        };
+        let points: Vec<Point3> = tess.line_indices.chunks_exact(2).zip(&tess.line_ids)
+            .filter(|(_, line)| **line == id)
+            .flat_map(|(pair, _)| [vertex(pair[0]), vertex(pair[1])])
+            .collect();
+        let Some(&first) = points.first() else {
+            return Err(format!("Edge {:?} is not in the model", id));
+        };
+        let Some(direction) = points.iter().map(|p| p - first).find(|d| d.norm() > EDGE_TOLERANCE).map(|d| d.normalize()) else {
+            return Err("Blend needs an edge of non-zero length".to_string());
+        };
+        let off_line = |p: &Point3| (p - first - direction * (p - first).dot(&direction)).norm();
+        if points.iter().any(|p| off_line(p) > EDGE_TOLERANCE) {
+            return Err("Fillet only blends straight edges".to_string());
+        }
+        let along = |p: &Point3| (p - first).dot(&direction);
+        let start = first + direction * points.iter().map(along).fold(f64::INFINITY, f64::min);
+        let end = first + direction * points.iter().map(along).fold(f64::NEG_INFINITY, f64::max);
+
+        // Faces with a triangle side lying on the edge
+        let on_edge = |p: &Point3| off_line(p) < EDGE_TOLERANCE && (-EDGE_TOLERANCE..=(end - start).norm() + EDGE_TOLERANCE).contains(&(p - start).dot(&direction));
+        let mut faces: Vec<(TopoId, Vector3)> = Vec::new();
+        for (triangle, face) in tess.indices.chunks_exact(3).zip(&tess.triangle_ids) {
+            let corners = [vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2])];
+            if corners.iter().filter(|p| on_edge(p)).count() < 2 || faces.iter().any(|(f, _)| f == face) {
+                continue;
+            }
+            let normal = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+            if normal.norm() > EDGE_TOLERANCE * EDGE_TOLERANCE {
+                faces.push((*face, normal.normalize()));
+            }
+        }
+        match faces.as_slice() {
+            [(_, n1), (_, n2)] => Ok(BlendEdge { id, start, end, normals: [*n1, *n2] }),
+            _ => Err(format!("Edge {:?} should join two faces, found {}", id, faces.len())),
+        }
+    }
+}
+
+/// Radii and shape of a blend
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlendSpec {
+    /// Radius at the start of each chain
+    pub radius_start: f64,
+    /// Radius at the end of each chain; equal to `radius_start` for a constant fillet
+    pub radius_end: f64,
+    pub continuity: Continuity,
+    /// Facets across the blend's cross-section
+    pub segments: usize,
+}
+
+fn order(id: &TopoId) -> (uuid::Uuid, TopoRank, u64) {
+    (id.feature_id.0, id.rank, id.local_id)
+}
+
+/// Split `edges` into tangent-connected chains, each running head to tail.
+/// A chain runs from its lowest-ordered end edge, so the result doesn't depend
+/// on the order or direction the edges were given in.
+pub fn blend_chains(edges: &[BlendEdge]) -> Vec<Vec<BlendEdge>> {
+    let mut unused: Vec<BlendEdge> = edges.to_vec();
+    let mut chains = Vec::new();
+    while !unused.is_empty() {
+        let mut chain = vec![unused.remove(0)];
+        loop {
+            let last = chain[chain.len() - 1];
+            let Some((i, next)) = unused.iter().enumerate().find_map(|(i, e)| last.continued_by(e).map(|n| (i, n))) else {
+                break;
+            };
+            unused.remove(i);
+            chain.push(next);
+        }
+        loop {
+            let first = chain[0].reversed();
+            let Some((i, previous)) = unused.iter().enumerate().find_map(|(i, e)| first.continued_by(e).map(|p| (i, p))) else {
+                break;
+            };
+            unused.remove(i);
+            chain.insert(0, previous.reversed());
+        }
+        if order(&chain[chain.len() - 1].id) < order(&chain[0].id) {
+            chain = chain.iter().rev().map(BlendEdge::reversed).collect();
+        }
+        chains.push(chain);
+    }
+    chains.sort_by_key(|chain| order(&chain[0].id));
+    chains
+}
+
+/// Face id of the blend over `chain`, from its set of source edges, so
+/// regenerating a fillet over the same edges names its faces the same
+pub fn chain_face_id(ctx: &NamingContext, chain: &[BlendEdge]) -> TopoId {
+    let mut sources: Vec<TopoId> = chain.iter().map(|e| e.id).collect();
+    sources.sort_by_key(order);
+    let seed: Vec<String> = sources.iter().map(|id| format!("{}:{}", id.feature_id.0, id.local_id)).collect();
+    ctx.derive(&format!("Blend_{}", seed.join(",")), TopoRank::Face)
+}
+
+/// Cross-section of the blend at `corner` on the edge running along `tangent`:
+/// points from the first face to the second with their outward normals
+fn profile(corner: Point3, tangent: Vector3, [n1, n2]: [Vector3; 2], radius: f64, spec: &BlendSpec) -> Result<Vec<(Point3, Vector3)>, String> {
+    let cos = n1.dot(&n2);
+    if cos > TANGENT_TOLERANCE.cos() {
+        return Err("Faces meeting at the edge are tangent; there is nothing to blend".to_string());
+    }
+    if cos < -TANGENT_TOLERANCE.cos() {
+        return Err("Faces meeting at the edge fold back on each other".to_string());
+    }
+    // Center of the circle tangent to both faces, and where it touches them
+    let center = corner - (n1 + n2) * (radius / (1.0 + cos));
+    let (touch1, touch2) = (center + n1 * radius, center + n2 * radius);
+    let segments = spec.segments.max(1);
+
+    match spec.continuity {
+        Continuity::G1 => {
+            let angle = cos.acos();
+            Ok((0..=segments).map(|i| {
+                let u = i as f64 / segments as f64;
+                let normal = (n1 * ((1.0 - u) * angle).sin() + n2 * (u * angle).sin()) / angle.sin();
+                (center + normal * radius, normal)
+            }).collect())
+        }
+        Continuity::G2 => {
+            let control = [touch1, touch1 + (corner - touch1) * 0.5, corner, touch2 + (corner - touch2) * 0.5, touch2];
+            let point = |u: f64| {
+                let w = [(1.0 - u).powi(4), 4.0 * u * (1.0 - u).powi(3), 6.0 * u * u * (1.0 - u).powi(2), 4.0 * u.powi(3) * (1.0 - u), u.powi(4)];
+                Point3::from((0..5).map(|k| control[k].coords * w[k]).sum::<Vector3>())
+            };
+            let derivative = |u: f64| {
+                let w = [(1.0 - u).powi(3), 3.0 * u * (1.0 - u).powi(2), 3.0 * u * u * (1.0 - u), u.powi(3)];
+                (0..4).map(|k| (control[k + 1] - control[k]) * (4.0 * w[k])).sum::<Vector3>()
+            };
+            // The profile's normal turns from n1 to n2; orient it by the first face
+            let side = if derivative(0.0).cross(&tangent).dot(&n1) < 0.0 { -1.0 } else { 1.0 };
+            Ok((0..=segments).map(|i| {
+                let u = i as f64 / segments as f64;
+                (point(u), derivative(u).cross(&tangent).normalize() * side)
+            }).collect())
+        }
+    }
+}
+
+/// Add the blend over `chain` to `tess` as face `id`, in one smoothing group
+pub fn tessellate_chain(tess: &mut Tessellation, chain: &[BlendEdge], spec: &BlendSpec, id: TopoId) -> Result<(), String> {
+    let length: f64 = chain.iter().map(|e| (e.end - e.start).norm()).sum();
+    if chain.is_empty() || length < JOIN_TOLERANCE {
+        return Err("Blend needs an edge of non-zero length".to_string());
+    }
+    if spec.radius_start <= 0.0 || spec.radius_end <= 0.0 {
+        return Err(format!("Blend radius must be positive, got {} to {}", spec.radius_start, spec.radius_end));
+    }
+
+    let mut sections = Vec::with_capacity(chain.len() + 1);
+    let mut along = 0.0;
+    for (i, edge) in chain.iter().enumerate() {
+        let radius = spec.radius_start + (spec.radius_end - spec.radius_start) * along / length;
+        sections.push(profile(edge.start, edge.direction(), edge.normals, radius, spec)?);
+        along += (edge.end - edge.start).norm();
+        if i == chain.len() - 1 {
+            sections.push(profile(edge.end, edge.direction(), edge.normals, spec.radius_end, spec)?);
+        }
+    }
+
+    let group = tess.new_smoothing_group();
+    for pair in sections.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        for i in 0..a.len() - 1 {
+            for [p, q, r] in [[a[i], b[i], b[i + 1]], [a[i], b[i + 1], a[i + 1]]] {
+                let facing = (q.0 - p.0).cross(&(r.0 - p.0)).dot(&(p.1 + q.1 + r.1));
+                let [p, q, r] = if facing < 0.0 { [p, r, q] } else { [p, q, r] };
+                tess.add_smooth_triangle([p.0, q.0, r.0], [p.1, q.1, r.1], id, group);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::ApproxEq;
+    use crate::topo::EntityId;
+
+    const UP: Vector3 = Vector3::new(0.0, 0.0, 1.0);
+    const FRONT: Vector3 = Vector3::new(0.0, -1.0, 0.0);
+
+    /// Vertices of `tess` lying in the plane x = `x`
+    fn section(tess: &Tessellation, x: f64) -> Vec<Point3> {
+        tess.vertices.chunks_exact(3)
+            .map(|v| Point3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+            .filter(|p| (p.x - x).abs() < 1e-6)
+            .collect()
+    }
+
+    #[test]
+    fn test_variable_radius_blend_over_tangent_edges_is_one_face() {
+        // The top-front edge of a box, split at x = 1, plus the top-right edge
+        // at x = 2 that meets it at a corner
+        let ctx = NamingContext::new(EntityId::new());
+        let edge = |seed: &str, start: [f64; 3], end: [f64; 3], normals: [Vector3; 2]| BlendEdge {
+            id: ctx.derive(seed, TopoRank::Edge), start: Point3::from(start), end: Point3::from(end), normals,
+        };
+        let first = edge("A", [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [UP, FRONT]);
+        let second = edge("B", [2.0, 0.0, 1.0], [1.0, 0.0, 1.0], [FRONT, UP]);
+        let side = edge("C", [2.0, 0.0, 1.0], [2.0, 1.0, 1.0], [UP, Vector3::new(1.0, 0.0, 0.0)]);
+
+        let chains = blend_chains(&[second, side, first]);
+        assert_eq!(chains.len(), 2, "The corner edge is not tangent to the others");
+        let chain = chains.iter().find(|c| c.len() == 2).unwrap();
+        assert_eq!(blend_chains(&[first, side, second.reversed()]), chains, "Chains don't depend on input order");
+        let id = chain_face_id(&ctx, chain);
+        assert_eq!(id, chain_face_id(&ctx, &[second.reversed(), first.reversed()]));
+        assert_ne!(id, chain_face_id(&ctx, &chain[..1]));
+
+        // Orient the chain from x = 0 so the radius goes 0.2 there to 0.5 at x = 2
+        let chain: Vec<BlendEdge> = if chain[0].start.x < 1.0 { chain.clone() } else { chain.iter().rev().map(BlendEdge::reversed).collect() };
+        let spec = BlendSpec { radius_start: 0.2, radius_end: 0.5, continuity: Continuity::G1, segments: 8 };
+        let mut tess = Tessellation::new();
+        tessellate_chain(&mut tess, &chain, &spec, id).unwrap();
+        assert!(tess.triangle_ids.iter().all(|t| *t == id));
+        assert!(tess.smoothing_group.iter().all(|g| *g == tess.smoothing_group[0]), "One smoothing group for the whole chain");
+        for (x, radius) in [(0.0, 0.2), (1.0, 0.35), (2.0, 0.5)] {
+            let center = Point3::new(x, radius, 1.0 - radius);
+            let points = section(&tess, x);
+            assert!(!points.is_empty());
+            for p in points {
+                assert!(((p - center).norm() - radius).abs() < 1e-5, "Radius {} at x = {}, got {}", radius, x, (p - center).norm());
+            }
+        }
+
+        // G2 meets the faces where G1 does, with the faces' own normals
+        let mut smooth = Tessellation::new();
+        tessellate_chain(&mut smooth, &chain, &BlendSpec { continuity: Continuity::G2, ..spec }, id).unwrap();
+        let ends = section(&smooth, 2.0);
+        assert!(ends.iter().any(|p| p.approx_eq_tol(&Point3::new(2.0, 0.5, 1.0), 1e-5)));
+        assert!(ends.iter().any(|p| p.approx_eq_tol(&Point3::new(2.0, 0.0, 0.5), 1e-5)));
+        let normals: Vec<Vector3> = smooth.normals.chunks_exact(3).map(|n| Vector3::new(n[0] as f64, n[1] as f64, n[2] as f64)).collect();
+        assert!(normals.iter().any(|n| n.approx_eq_tol(&UP, 1e-5)) && normals.iter().any(|n| n.approx_eq_tol(&FRONT, 1e-5)));
+        assert!(normals.iter().all(|n| n.y <= 1e-6 && n.z >= -1e-6), "Normals face out of the box");
+    }
+
+    #[test]
+    fn test_blend_rejects_flat_edges_and_bad_radii() {
+        let ctx = NamingContext::new(EntityId::new());
+        let flat = BlendEdge { id: ctx.derive("Flat", TopoRank::Edge), start: Point3::origin(), end: Point3::new(1.0, 0.0, 0.0), normals: [UP, UP] };
+        let spec = BlendSpec { radius_start: 1.0, radius_end: 1.0, continuity: Continuity::G1, segments: 4 };
+        assert!(tessellate_chain(&mut Tessellation::new(), &[flat], &spec, flat.id).is_err());
+        let sharp = BlendEdge { normals: [UP, FRONT], ..flat };
+        assert!(tessellate_chain(&mut Tessellation::new(), &[sharp], &BlendSpec { radius_end: 0.0, ..spec }, sharp.id).is_err());
+        assert_eq!(Continuity::from_param("G2"), Some(Continuity::G2));
+        assert_eq!(Continuity::from_param("C2"), None);
+    }
+}
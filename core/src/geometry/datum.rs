@@ -0,0 +1,279 @@
+//! Datum planes and axes.
+//!
+//! A datum is reference geometry defined relative to existing topology (faces, edges,
+//! vertices, other datums). It is resolved at regeneration time from the topology
+//! manifest into an `AnalyticGeometry::Plane` or `Line` registered under the datum
+//! feature's own TopoId, so sketches, revolves and patterns can reference it exactly
+//! like model faces and edges.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{Point3, Tessellation, Vector3};
+use crate::sketch::types::SketchPlane;
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+use crate::topo::{EntityId, IdGenerator};
+
+/// Default edge length of the displayed datum quad / axis segment
+pub const DEFAULT_DATUM_SIZE: f64 = 50.0;
+
+/// Tolerance for parallel / degenerate checks on unit vectors
+const DATUM_TOLERANCE: f64 = 1e-6;
+
+type Manifest = HashMap<TopoId, KernelEntity>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DatumPlaneDef {
+    /// Parallel to a planar face (or datum plane) at a signed distance along its normal
+    Offset { base: TopoId, distance: f64 },
+    /// A planar face rotated about an edge by `angle` degrees
+    Angle { base: TopoId, edge: TopoId, angle: f64 },
+    /// Plane through three points or vertices
+    ThreePoints { points: [TopoId; 3] },
+    /// Plane halfway between two parallel planar faces
+    Midplane { a: TopoId, b: TopoId },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DatumAxisDef {
+    /// Axis through two points or vertices
+    TwoPoints { a: TopoId, b: TopoId },
+    /// Along a linear edge, or through the center of a circular edge along its normal
+    Edge { edge: TopoId },
+    /// Intersection line of two non-parallel planes
+    PlaneIntersection { a: TopoId, b: TopoId },
+}
+
+/// TopoId under which a datum feature registers its geometry.
+/// Matches the first id the runtime draws after `set_context(feature_id)`.
+pub fn datum_topo_id(feature_id: EntityId, rank: TopoRank) -> TopoId {
+    TopoId::new(IdGenerator::new(&feature_id.to_string()).next_id(), 0, rank)
+}
+
+fn lookup<'a>(manifest: &'a Manifest, id: &TopoId) -> Result<&'a AnalyticGeometry, String> {
+    manifest.get(id)
+        .map(|e| &e.geometry)
+        .ok_or_else(|| format!("Reference {:?} no longer exists", id))
+}
+
+fn plane_of(manifest: &Manifest, id: &TopoId) -> Result<(Point3, Vector3), String> {
+    match lookup(manifest, id)? {
+        AnalyticGeometry::Plane { origin, normal } => {
+            let n = Vector3::from(*normal);
+            if n.norm() < DATUM_TOLERANCE {
+                return Err("Referenced plane has no normal".to_string());
+            }
+            Ok((Point3::from(*origin), n.normalize()))
+        }
+        other => Err(format!("Expected a planar face, found {:?}", other)),
+    }
+}
+
+/// Point-like geometry: sketch points and vertices, or circle and sphere centers
+fn point_of(manifest: &Manifest, id: &TopoId) -> Result<Point3, String> {
+    match lookup(manifest, id)? {
+        AnalyticGeometry::Point { position: center }
+        | AnalyticGeometry::Sphere { center, .. }
+        | AnalyticGeometry::Circle { center, .. } => Ok(Point3::from(*center)),
+        other => Err(format!("Expected a point or vertex, found {:?}", other)),
+    }
+}
+
+/// Origin and unit direction of a linear edge, datum axis, or circular edge's axis
+fn line_of(manifest: &Manifest, id: &TopoId) -> Result<(Point3, Vector3), String> {
+    match lookup(manifest, id)? {
+        AnalyticGeometry::Line { start, end } => {
+            let d = Vector3::from(*end) - Vector3::from(*start);
+            if d.norm() < DATUM_TOLERANCE {
+                return Err("Referenced edge has zero length".to_string());
+            }
+            Ok((Point3::from(*start), d.normalize()))
+        }
+        AnalyticGeometry::Circle { center, normal, .. } => Ok((Point3::from(*center), Vector3::from(*normal).normalize())),
+        AnalyticGeometry::Cylinder { axis_start, axis_dir, .. } => Ok((Point3::from(*axis_start), Vector3::from(*axis_dir).normalize())),
+        other => Err(format!("Expected an edge or axis, found {:?}", other)),
+    }
+}
+
+fn plane_geometry(origin: Point3, normal: Vector3) -> AnalyticGeometry {
+    AnalyticGeometry::Plane { origin: origin.into(), normal: normal.into() }
+}
+
+/// Evaluate a datum plane definition against the current topology
+pub fn resolve_plane(def: &DatumPlaneDef, manifest: &Manifest) -> Result<AnalyticGeometry, String> {
+    match def {
+        DatumPlaneDef::Offset { base, distance } => {
+            let (origin, normal) = plane_of(manifest, base)?;
+            Ok(plane_geometry(origin + normal * *distance, normal))
+        }
+        DatumPlaneDef::Angle { base, edge, angle } => {
+            let (_, normal) = plane_of(manifest, base)?;
+            let (pivot, axis) = line_of(manifest, edge)?;
+            let rotation = nalgebra::Rotation3::from_axis_angle(&nalgebra::Unit::new_normalize(axis), angle.to_radians());
+            Ok(plane_geometry(pivot, rotation * normal))
+        }
+        DatumPlaneDef::ThreePoints { points } => {
+            let [p0, p1, p2] = [
+                point_of(manifest, &points[0])?,
+                point_of(manifest, &points[1])?,
+                point_of(manifest, &points[2])?,
+            ];
+            let normal = (p1 - p0).cross(&(p2 - p0));
+            if normal.norm() < DATUM_TOLERANCE {
+                return Err("Datum plane points are collinear".to_string());
+            }
+            Ok(plane_geometry(p0, normal.normalize()))
+        }
+        DatumPlaneDef::Midplane { a, b } => {
+            let (oa, na) = plane_of(manifest, a)?;
+            let (ob, nb) = plane_of(manifest, b)?;
+            if 1.0 - na.dot(&nb).abs() > DATUM_TOLERANCE {
+                return Err("Midplane faces are not parallel".to_string());
+            }
+            // The midpoint of the two origins is equidistant from both planes
+            Ok(plane_geometry(nalgebra::center(&oa, &ob), na))
+        }
+    }
+}
+
+/// Evaluate a datum axis definition against the current topology.
+/// Unbounded axes (plane intersections) are clipped to `size` around the plane origins.
+pub fn resolve_axis(def: &DatumAxisDef, manifest: &Manifest, size: f64) -> Result<AnalyticGeometry, String> {
+    let (start, end) = match def {
+        DatumAxisDef::TwoPoints { a, b } => {
+            let (pa, pb) = (point_of(manifest, a)?, point_of(manifest, b)?);
+            if (pb - pa).norm() < DATUM_TOLERANCE {
+                return Err("Datum axis points coincide".to_string());
+            }
+            (pa, pb)
+        }
+        DatumAxisDef::Edge { edge } => match lookup(manifest, edge)? {
+            AnalyticGeometry::Line { start, end } => (Point3::from(*start), Point3::from(*end)),
+            _ => {
+                let (origin, dir) = line_of(manifest, edge)?;
+                (origin - dir * (size / 2.0), origin + dir * (size / 2.0))
+            }
+        },
+        DatumAxisDef::PlaneIntersection { a, b } => {
+            let (oa, na) = plane_of(manifest, a)?;
+            let (ob, nb) = plane_of(manifest, b)?;
+            let dir = na.cross(&nb);
+            let len2 = dir.norm_squared();
+            if len2 < DATUM_TOLERANCE {
+                return Err("Planes are parallel and do not intersect".to_string());
+            }
+            // Point on both planes: (d1 (n2 x u) + d2 (u x n1)) / |u|^2
+            let (d1, d2) = (na.dot(&oa.coords), nb.dot(&ob.coords));
+            let on_line = Point3::from((nb.cross(&dir) * d1 + dir.cross(&na) * d2) / len2);
+            let dir = dir.normalize();
+            let mid = nalgebra::center(&oa, &ob);
+            let center = on_line + dir * (mid - on_line).dot(&dir);
+            (center - dir * (size / 2.0), center + dir * (size / 2.0))
+        }
+    };
+    Ok(AnalyticGeometry::Line { start: start.into(), end: end.into() })
+}
+
+/// In-plane axes for a plane normal. Planes facing mostly along Z keep world X as
+/// their x axis; others use the world axis least aligned with the normal.
+pub fn plane_axes(normal: &Vector3) -> (Vector3, Vector3) {
+    let n = normal.normalize();
+    let reference = if n.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+    let x_axis = (reference - n * n.dot(&reference)).normalize();
+    (x_axis, n.cross(&x_axis))
+}
+
+/// Sketch plane lying on a planar face or datum plane
+pub fn sketch_plane_from(geometry: &AnalyticGeometry) -> Option<SketchPlane> {
+    match geometry {
+        AnalyticGeometry::Plane { origin, normal } => {
+            let normal = Vector3::from(*normal).normalize();
+            let (x_axis, y_axis) = plane_axes(&normal);
+            Some(SketchPlane { origin: Point3::from(*origin), normal, x_axis, y_axis })
+        }
+        _ => None,
+    }
+}
+
+/// Origin and unit direction usable as a revolve axis or pattern direction:
+/// linear edges and datum axes, circular edges (their axis) and planes (their normal)
+pub fn direction_from(geometry: &AnalyticGeometry) -> Option<(Point3, Vector3)> {
+    let (origin, dir) = match geometry {
+        AnalyticGeometry::Line { start, end } => (Point3::from(*start), Vector3::from(*end) - Vector3::from(*start)),
+        AnalyticGeometry::Circle { center, normal, .. } | AnalyticGeometry::Plane { origin: center, normal } => {
+            (Point3::from(*center), Vector3::from(*normal))
+        }
+        AnalyticGeometry::Cylinder { axis_start, axis_dir, .. } => (Point3::from(*axis_start), Vector3::from(*axis_dir)),
+        _ => return None,
+    };
+    (dir.norm() > DATUM_TOLERANCE).then(|| (origin, dir.normalize()))
+}
+
+/// Render a datum as lightweight reference geometry: a `size` x `size` quad centered
+/// on a plane's origin, or the axis segment itself.
+pub fn tessellate_datum(tess: &mut Tessellation, geometry: &AnalyticGeometry, id: TopoId, size: f64) {
+    match geometry {
+        AnalyticGeometry::Plane { origin, normal } => {
+            let origin = Point3::from(*origin);
+            let (x_axis, y_axis) = plane_axes(&Vector3::from(*normal));
+            let half = size / 2.0;
+            let corner = |sx: f64, sy: f64| origin + x_axis * (sx * half) + y_axis * (sy * half);
+            let [a, b, c, d] = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+            tess.add_triangle(a, b, c, id);
+            tess.add_triangle(a, c, d, id);
+        }
+        AnalyticGeometry::Line { start, end } => {
+            tess.add_line(Point3::from(*start), Point3::from(*end), id);
+        }
+        _ => return,
+    }
+    tess.mark_reference(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(entries: Vec<(TopoId, AnalyticGeometry)>) -> Manifest {
+        entries.into_iter().map(|(id, geometry)| (id, KernelEntity { id, geometry })).collect()
+    }
+
+    fn id(rank: TopoRank) -> TopoId {
+        TopoId::new(EntityId::new(), 0, rank)
+    }
+
+    #[test]
+    fn test_resolve_datum_definitions() {
+        let (floor, wall, edge) = (id(TopoRank::Face), id(TopoRank::Face), id(TopoRank::Edge));
+        let points = [id(TopoRank::Vertex), id(TopoRank::Vertex), id(TopoRank::Vertex)];
+        let m = manifest(vec![
+            (floor, AnalyticGeometry::Plane { origin: [0.0, 0.0, 0.0], normal: [0.0, 0.0, 1.0] }),
+            (wall, AnalyticGeometry::Plane { origin: [3.0, 0.0, 0.0], normal: [1.0, 0.0, 0.0] }),
+            (edge, AnalyticGeometry::Line { start: [0.0, 0.0, 0.0], end: [10.0, 0.0, 0.0] }),
+            (points[0], AnalyticGeometry::Point { position: [0.0, 0.0, 2.0] }),
+            (points[1], AnalyticGeometry::Point { position: [1.0, 0.0, 2.0] }),
+            (points[2], AnalyticGeometry::Point { position: [0.0, 1.0, 2.0] }),
+        ]);
+
+        let offset = resolve_plane(&DatumPlaneDef::Offset { base: floor, distance: 4.0 }, &m).unwrap();
+        assert_eq!(offset, AnalyticGeometry::Plane { origin: [0.0, 0.0, 4.0], normal: [0.0, 0.0, 1.0] });
+
+        let AnalyticGeometry::Plane { normal, .. } = resolve_plane(&DatumPlaneDef::Angle { base: floor, edge, angle: 90.0 }, &m).unwrap() else { panic!() };
+        assert!((normal[1] + 1.0).abs() < 1e-9, "Z rotated 90° about X points to -Y: {:?}", normal);
+
+        let AnalyticGeometry::Plane { origin, normal } = resolve_plane(&DatumPlaneDef::ThreePoints { points }, &m).unwrap() else { panic!() };
+        assert_eq!((origin[2], normal), (2.0, [0.0, 0.0, 1.0]));
+
+        assert!(resolve_plane(&DatumPlaneDef::Midplane { a: floor, b: wall }, &m).is_err(), "Non-parallel faces");
+
+        let AnalyticGeometry::Line { start, end } = resolve_axis(&DatumAxisDef::PlaneIntersection { a: floor, b: wall }, &m, 10.0).unwrap() else { panic!() };
+        assert_eq!((start[0], start[2], end[0], end[2]), (3.0, 0.0, 3.0, 0.0));
+        assert!(((end[1] - start[1]).abs() - 10.0).abs() < 1e-9);
+
+        let missing = resolve_axis(&DatumAxisDef::Edge { edge: id(TopoRank::Edge) }, &m, 10.0);
+        assert!(missing.unwrap_err().contains("no longer exists"));
+    }
+}
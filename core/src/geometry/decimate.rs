@@ -0,0 +1,398 @@
+//! Edge-collapse decimation for display tessellations.
+//!
+//! Vertices are welded by position, then edges are collapsed greedily in order of
+//! quadric error (Garland–Heckbert) until the target triangle count is reached.
+//! Collapses are half-edge collapses: the surviving vertex keeps its original position,
+//! so every output vertex lies on the input surface.
+//!
+//! Feature edges — edges where the face TopoId changes, or open boundaries — are
+//! preserved: a vertex on a feature edge may only slide along that same edge, and
+//! vertices where several feature edges meet are never removed. Each surviving triangle
+//! keeps the TopoId of the face it came from.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Matrix4, Point3, Tessellation, Vector3};
+use crate::topo::naming::TopoId;
+
+/// Grid used to weld coincident vertices of the unindexed triangle soup
+const WELD_SCALE: f64 = 1e5;
+/// Collapses that rotate any remaining triangle's normal by more than ~60° are rejected
+const MIN_NORMAL_COS: f64 = 0.5;
+
+type Quadric = Matrix4;
+
+struct Mesh {
+    positions: Vec<Point3>,
+    /// Welded triangles; collapsed triangles become None
+    triangles: Vec<Option<[usize; 3]>>,
+    triangle_ids: Vec<TopoId>,
+    /// Smoothing group of each welded triangle
+    smoothing_groups: Vec<u32>,
+    /// Triangles incident to each vertex (may reference collapsed triangles)
+    vertex_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    /// Original smooth normal of each vertex on each face it touches
+    normals: HashMap<(usize, TopoId), Vector3>,
+}
+
+impl Tessellation {
+    /// Reduce the triangle count to roughly `target_ratio` of the current count.
+    /// Feature edges between faces are kept and triangles keep their face TopoIds.
+    /// Line and point primitives are carried over unchanged. Ratios >= 1 are a no-op;
+    /// the result may keep more triangles than requested if no further collapse is valid.
+    pub fn decimate(&mut self, target_ratio: f64) {
+        let triangle_count = self.indices.len() / 3;
+        if target_ratio >= 1.0 || triangle_count == 0 {
+            return;
+        }
+        let target = ((triangle_count as f64) * target_ratio.max(0.0)).ceil() as usize;
+
+        let mut mesh = Mesh::weld(self);
+        mesh.collapse_to(target);
+        self.rebuild_from(&mesh);
+    }
+
+    fn vertex_at(&self, index: u32) -> Point3 {
+        let i = index as usize * 3;
+        Point3::new(self.vertices[i] as f64, self.vertices[i + 1] as f64, self.vertices[i + 2] as f64)
+    }
+
+    fn normal_at(&self, index: u32) -> Vector3 {
+        let i = index as usize * 3;
+        if i + 2 >= self.normals.len() {
+            return Vector3::zeros();
+        }
+        Vector3::new(self.normals[i] as f64, self.normals[i + 1] as f64, self.normals[i + 2] as f64)
+    }
+
+    fn rebuild_from(&mut self, mesh: &Mesh) {
+        let mut out = Tessellation::new();
+        out.feature_id_map = std::mem::take(&mut self.feature_id_map);
+        out.reference_ids = std::mem::take(&mut self.reference_ids);
+        out.hidden_features = std::mem::take(&mut self.hidden_features);
+        // Bodies list faces, not triangles, so they survive the collapse as is.
+        // UVs are per vertex and are left out; they are generated afterwards.
+        out.bodies = std::mem::take(&mut self.bodies);
+        out.center_of_mass = self.center_of_mass.take();
+
+        for ((tri, id), group) in mesh.triangles.iter().zip(&mesh.triangle_ids).zip(&mesh.smoothing_groups) {
+            let Some([a, b, c]) = *tri else { continue };
+            let [pa, pb, pc] = [mesh.positions[a], mesh.positions[b], mesh.positions[c]];
+            let flat = (pb - pa).cross(&(pc - pa)).normalize();
+            let normal = |v: usize| mesh.normals.get(&(v, *id)).copied().unwrap_or(flat);
+            out.add_smooth_triangle([pa, pb, pc], [normal(a), normal(b), normal(c)], *id, *group);
+        }
+        for (segment, id) in self.line_indices.chunks_exact(2).zip(&self.line_ids) {
+            out.add_line(self.vertex_at(segment[0]), self.vertex_at(segment[1]), *id);
+        }
+        for (&index, id) in self.point_indices.iter().zip(&self.point_ids) {
+            out.add_point(self.vertex_at(index), *id);
+        }
+
+        *self = out;
+    }
+}
+
+impl Mesh {
+    fn weld(tess: &Tessellation) -> Self {
+        let mut mesh = Mesh {
+            positions: Vec::new(),
+            triangles: Vec::new(),
+            triangle_ids: Vec::new(),
+            smoothing_groups: Vec::new(),
+            vertex_triangles: Vec::new(),
+            quadrics: Vec::new(),
+            normals: HashMap::new(),
+        };
+        let mut welded: HashMap<(i64, i64, i64), usize> = HashMap::new();
+
+        for (source, (corners, id)) in tess.indices.chunks_exact(3).zip(&tess.triangle_ids).enumerate() {
+            let mut tri = [0usize; 3];
+            for (slot, &index) in tri.iter_mut().zip(corners) {
+                let p = tess.vertex_at(index);
+                let key = (
+                    (p.x * WELD_SCALE).round() as i64,
+                    (p.y * WELD_SCALE).round() as i64,
+                    (p.z * WELD_SCALE).round() as i64,
+                );
+                let v = *welded.entry(key).or_insert_with(|| {
+                    mesh.positions.push(p);
+                    mesh.vertex_triangles.push(Vec::new());
+                    mesh.quadrics.push(Quadric::zeros());
+                    mesh.positions.len() - 1
+                });
+                mesh.normals.entry((v, *id)).or_insert_with(|| tess.normal_at(index));
+                *slot = v;
+            }
+            if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+                continue;
+            }
+
+            let t = mesh.triangles.len();
+            mesh.triangles.push(Some(tri));
+            mesh.triangle_ids.push(*id);
+            mesh.smoothing_groups.push(tess.smoothing_group.get(source).copied().unwrap_or(source as u32));
+            for &v in &tri {
+                mesh.vertex_triangles[v].push(t);
+            }
+
+            let [a, b, c] = tri.map(|v| mesh.positions[v]);
+            let normal = (b - a).cross(&(c - a));
+            if normal.norm() > f64::EPSILON {
+                let n = normal.normalize();
+                let plane = nalgebra::Vector4::new(n.x, n.y, n.z, -n.dot(&a.coords));
+                let quadric = plane * plane.transpose();
+                for &v in &tri {
+                    mesh.quadrics[v] += quadric;
+                }
+            }
+        }
+        mesh
+    }
+
+    fn live_count(&self) -> usize {
+        self.triangles.iter().filter(|t| t.is_some()).count()
+    }
+
+    /// Live triangles around `v` as (triangle index, corners)
+    fn around(&self, v: usize) -> impl Iterator<Item = (usize, [usize; 3])> + '_ {
+        self.vertex_triangles[v].iter().filter_map(|&t| self.triangles[t].map(|tri| (t, tri)))
+    }
+
+    fn neighbors(&self, v: usize) -> HashSet<usize> {
+        self.around(v).flat_map(|(_, tri)| tri).filter(|&w| w != v).collect()
+    }
+
+    /// Face ids of the live triangles sharing edge (u, w)
+    fn edge_faces(&self, u: usize, w: usize) -> Vec<TopoId> {
+        self.around(u)
+            .filter(|(_, tri)| tri.contains(&w))
+            .map(|(t, _)| self.triangle_ids[t])
+            .collect()
+    }
+
+    fn is_feature_edge(&self, u: usize, w: usize) -> bool {
+        let faces = self.edge_faces(u, w);
+        faces.len() != 2 || faces[0] != faces[1]
+    }
+
+    fn same_faces(a: &[TopoId], b: &[TopoId]) -> bool {
+        a.len() == b.len() && a.iter().all(|id| b.contains(id)) && b.iter().all(|id| a.contains(id))
+    }
+
+    /// Whether `u` may be merged into its neighbor `v` without changing topology,
+    /// crossing a feature edge, or folding the surface.
+    fn can_collapse(&self, u: usize, v: usize) -> bool {
+        let neighbors_u = self.neighbors(u);
+        if !neighbors_u.contains(&v) {
+            return false;
+        }
+
+        // Feature vertices may only slide along their own feature curve
+        let mut features: Vec<usize> = neighbors_u.iter().copied().filter(|&w| self.is_feature_edge(u, w)).collect();
+        features.sort_unstable();
+        match features.as_slice() {
+            [] => {}
+            [a, b] if (*a == v || *b == v)
+                && Self::same_faces(&self.edge_faces(u, *a), &self.edge_faces(u, *b)) => {}
+            _ => return false,
+        }
+
+        // Link condition: the only shared neighbors are the apexes of the triangles on (u, v)
+        let apexes: HashSet<usize> = self.around(u)
+            .filter(|(_, tri)| tri.contains(&v))
+            .flat_map(|(_, tri)| tri)
+            .filter(|&w| w != u && w != v)
+            .collect();
+        let shared: HashSet<usize> = neighbors_u.intersection(&self.neighbors(v)).copied().collect();
+        if shared != apexes {
+            return false;
+        }
+
+        // Reject collapses that degenerate or flip the triangles that remain
+        let target = self.positions[v];
+        self.around(u).filter(|(_, tri)| !tri.contains(&v)).all(|(_, tri)| {
+            let [a, b, c] = tri.map(|w| self.positions[w]);
+            let before = (b - a).cross(&(c - a));
+            let [a, b, c] = tri.map(|w| if w == u { target } else { self.positions[w] });
+            let after = (b - a).cross(&(c - a));
+            after.norm() > f64::EPSILON
+                && before.norm() > f64::EPSILON
+                && before.normalize().dot(&after.normalize()) > MIN_NORMAL_COS
+        })
+    }
+
+    fn collapse_cost(&self, u: usize, v: usize) -> f64 {
+        let p = self.positions[v];
+        let h = nalgebra::Vector4::new(p.x, p.y, p.z, 1.0);
+        (h.transpose() * (self.quadrics[u] + self.quadrics[v]) * h)[0].max(0.0)
+    }
+
+    /// Collapse edges in passes of independent, cheapest-first collapses until
+    /// `target` triangles remain or no valid collapse is left.
+    fn collapse_to(&mut self, target: usize) {
+        let mut live = self.live_count();
+        while live > target {
+            let mut candidates: Vec<(f64, f64, usize, usize)> = Vec::new();
+            for u in 0..self.positions.len() {
+                let mut neighbors: Vec<usize> = self.neighbors(u).into_iter().collect();
+                neighbors.sort_unstable();
+                for v in neighbors {
+                    let length = (self.positions[u] - self.positions[v]).norm_squared();
+                    candidates.push((self.collapse_cost(u, v), length, u, v));
+                }
+            }
+            candidates.sort_by(|a, b| {
+                a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2)).then(a.3.cmp(&b.3))
+            });
+
+            // Vertices whose neighborhood changed this pass; their candidates are stale
+            let mut dirty: HashSet<usize> = HashSet::new();
+            let mut collapsed = 0;
+            for (_, _, u, v) in candidates {
+                if live <= target {
+                    break;
+                }
+                if dirty.contains(&u) || dirty.contains(&v) || !self.can_collapse(u, v) {
+                    continue;
+                }
+
+                dirty.extend(self.neighbors(u));
+                dirty.insert(u);
+                let incident: Vec<usize> = self.around(u).map(|(t, _)| t).collect();
+                for t in incident {
+                    let Some(tri) = self.triangles[t] else { continue };
+                    if tri.contains(&v) {
+                        self.triangles[t] = None;
+                        live -= 1;
+                    } else {
+                        self.triangles[t] = Some(tri.map(|w| if w == u { v } else { w }));
+                        self.vertex_triangles[v].push(t);
+                    }
+                }
+                self.vertex_triangles[u].clear();
+                let merged = self.quadrics[u];
+                self.quadrics[v] += merged;
+                collapsed += 1;
+            }
+
+            if collapsed == 0 {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::mesh_bounds;
+    use crate::topo::naming::TopoRank;
+    use crate::topo::EntityId;
+    use std::f64::consts::TAU;
+
+    /// Cylinder of radius 5, height 10 around Z: 64 segments, 4 rings on the side, fan caps.
+    fn cylinder() -> (Tessellation, [TopoId; 3]) {
+        let feature = EntityId::new();
+        let side = TopoId::new(feature, 0, TopoRank::Face);
+        let bottom = TopoId::new(feature, 1, TopoRank::Face);
+        let top = TopoId::new(feature, 2, TopoRank::Face);
+        let (segments, rings, radius, height) = (64, 4, 5.0, 10.0);
+
+        let rim = |i: usize, z: f64| {
+            let angle = TAU * (i % segments) as f64 / segments as f64;
+            Point3::new(radius * angle.cos(), radius * angle.sin(), z)
+        };
+        let radial = |p: Point3| Vector3::new(p.x, p.y, 0.0).normalize();
+
+        let mut tess = Tessellation::new();
+        for i in 0..segments {
+            for r in 0..rings {
+                let z0 = height * r as f64 / rings as f64;
+                let z1 = height * (r + 1) as f64 / rings as f64;
+                let (a, b, c, d) = (rim(i, z0), rim(i + 1, z0), rim(i + 1, z1), rim(i, z1));
+                tess.add_triangle_with_normals(a, b, c, radial(a), radial(b), radial(c), side);
+                tess.add_triangle_with_normals(a, c, d, radial(a), radial(c), radial(d), side);
+            }
+            tess.add_triangle(Point3::new(0.0, 0.0, 0.0), rim(i + 1, 0.0), rim(i, 0.0), bottom);
+            tess.add_triangle(Point3::new(0.0, 0.0, height), rim(i, height), rim(i + 1, height), top);
+        }
+        (tess, [side, bottom, top])
+    }
+
+    #[test]
+    fn test_decimate_cylinder_keeps_shape_and_faces() {
+        let (mut tess, faces) = cylinder();
+        let before = tess.triangle_ids.len();
+        let bounds_before = mesh_bounds(&tess).unwrap();
+
+        tess.decimate(0.25);
+
+        let after = tess.triangle_ids.len();
+        assert!(after < before / 2, "Expected a large reduction, {} -> {}", before, after);
+        assert_eq!(tess.indices.len(), after * 3);
+
+        let bounds_after = mesh_bounds(&tess).unwrap();
+        let tolerance = 0.05 * 5.0;
+        for axis in 0..3 {
+            assert!((bounds_after.min[axis] - bounds_before.min[axis]).abs() < tolerance);
+            assert!((bounds_after.max[axis] - bounds_before.max[axis]).abs() < tolerance);
+        }
+
+        // Every face survives and keeps its id; no triangle spans two faces' caps
+        for face in faces {
+            assert!(tess.triangle_ids.contains(&face), "Face {:?} lost", face);
+        }
+        for (tri, id) in tess.indices.chunks_exact(3).zip(&tess.triangle_ids) {
+            let zs: Vec<f32> = tri.iter().map(|&i| tess.vertices[i as usize * 3 + 2]).collect();
+            if *id == faces[1] {
+                assert!(zs.iter().all(|z| z.abs() < 1e-4), "Bottom cap left its plane");
+            }
+            if *id == faces[2] {
+                assert!(zs.iter().all(|z| (z - 10.0).abs() < 1e-4), "Top cap left its plane");
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimate_preserves_lines_and_noop_ratio() {
+        let (mut tess, _) = cylinder();
+        let line_id = TopoId::new(EntityId::new(), 0, TopoRank::Edge);
+        tess.add_line(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, 10.0), line_id);
+
+        let original = tess.triangle_ids.len();
+        tess.decimate(1.0);
+        assert_eq!(tess.triangle_ids.len(), original);
+
+        tess.decimate(0.5);
+        assert_eq!(tess.line_ids, vec![line_id]);
+        let end = tess.line_indices[1] as usize * 3;
+        assert_eq!(tess.vertices[end + 2], 10.0);
+    }
+
+    #[test]
+    fn test_decimate_keeps_bodies_and_center_of_mass() {
+        let (mut tess, first) = cylinder();
+        let (second, second_faces) = cylinder();
+        for (corners, id) in second.indices.chunks_exact(3).zip(&second.triangle_ids) {
+            let [a, b, c] = [0, 1, 2].map(|k| second.vertex_at(corners[k]) + Vector3::new(20.0, 0.0, 0.0));
+            tess.add_triangle(a, b, c, *id);
+        }
+        let body = |faces: [TopoId; 3]| crate::geometry::tessellation::TessellationBody {
+            id: TopoId::new(faces[0].feature_id, 0, TopoRank::Solid),
+            faces: faces.to_vec(),
+        };
+        tess.bodies = vec![body(first), body(second_faces)];
+        tess.center_of_mass = Some([10.0, 0.0, 5.0]);
+        let bodies = tess.bodies.clone();
+
+        tess.decimate(0.5);
+        assert!(tess.triangle_ids.len() < second.triangle_ids.len() * 2);
+        assert_eq!(tess.bodies, bodies);
+        assert_eq!(tess.center_of_mass, Some([10.0, 0.0, 5.0]));
+        for body in &tess.bodies {
+            assert!(tess.triangle_ids.iter().any(|id| body.faces.contains(id)), "Each body keeps triangles");
+        }
+    }
+}
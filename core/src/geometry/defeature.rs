@@ -0,0 +1,498 @@
+//! Face deletion (defeaturing) on tessellated bodies.
+//!
+//! Removes the triangles of a face and closes the resulting gap, either by
+//! capping the open boundary with a planar patch or by extending the planar
+//! neighbours of the deleted face until they meet. This is a first pass that
+//! works on the tessellation; vertices are matched by position since the
+//! tessellation does not share vertices between faces.
+
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use super::{Point3, Tessellation, Vector3};
+use super::triangulate::earcut;
+use super::utils_3d::{plane_plane_intersect, Plane};
+use crate::topo::naming::{NamingContext, TopoId, TopoRank};
+
+/// Distance tolerance for planarity checks on f32 tessellation data
+const PLANAR_TOLERANCE: f64 = 1e-4;
+
+/// How the gap left by a deleted face is closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum HealMode {
+    /// Re-triangulate the open boundary loop as a planar patch
+    #[default]
+    Cap,
+    /// Extend the adjacent planar faces to their intersection
+    ExtendNeighbors,
+}
+
+/// Why a face could not be deleted and healed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaceHealError {
+    pub face: TopoId,
+    pub reason: String,
+}
+
+/// Outcome of a face deletion
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealReport {
+    /// Faces that were removed and healed
+    pub healed_faces: Vec<TopoId>,
+    /// Ids of the patches created by capping (one per capped face)
+    pub patch_ids: Vec<TopoId>,
+    /// Faces that could not be healed; the tessellation is unchanged for these
+    pub errors: Vec<FaceHealError>,
+}
+
+/// Deterministic id of the planar patch that replaces a deleted face
+pub fn healed_patch_id(face: &TopoId) -> TopoId {
+    NamingContext::new(face.feature_id).derive(&format!("HealedPatch_{}", face.local_id), TopoRank::Face)
+}
+
+type VertexKey = (i64, i64, i64);
+
+fn vertex_key(p: &Point3) -> VertexKey {
+    // f32 vertices: quantize to 1e-5 so coincident vertices from different faces match
+    let q = 1e5;
+    ((p.x * q).round() as i64, (p.y * q).round() as i64, (p.z * q).round() as i64)
+}
+
+fn vertex_at(tess: &Tessellation, index: u32) -> Point3 {
+    let i = index as usize * 3;
+    Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64)
+}
+
+fn triangle_points(tess: &Tessellation, tri: usize) -> [Point3; 3] {
+    [
+        vertex_at(tess, tess.indices[tri * 3]),
+        vertex_at(tess, tess.indices[tri * 3 + 1]),
+        vertex_at(tess, tess.indices[tri * 3 + 2]),
+    ]
+}
+
+fn triangle_count(tess: &Tessellation) -> usize {
+    tess.indices.len() / 3
+}
+
+/// Triangles belonging to a face
+fn face_triangles(tess: &Tessellation, face: &TopoId) -> Vec<usize> {
+    (0..triangle_count(tess))
+        .filter(|&t| tess.triangle_ids.get(t) == Some(face))
+        .collect()
+}
+
+/// Area-weighted (unnormalized) normal of a set of triangles
+fn summed_normal(tess: &Tessellation, tris: &[usize]) -> Vector3 {
+    tris.iter().fold(Vector3::zeros(), |acc, &t| {
+        let [a, b, c] = triangle_points(tess, t);
+        acc + (b - a).cross(&(c - a))
+    })
+}
+
+/// Directed boundary edges of a triangle group, oriented as in the group's triangles
+fn boundary_edges(tess: &Tessellation, tris: &[usize], positions: &mut HashMap<VertexKey, Point3>) -> Vec<(VertexKey, VertexKey)> {
+    let mut edges = Vec::new();
+    for &t in tris {
+        let pts = triangle_points(tess, t);
+        let keys = pts.map(|p| vertex_key(&p));
+        for i in 0..3 {
+            positions.insert(keys[i], pts[i]);
+            let (a, b) = (keys[i], keys[(i + 1) % 3]);
+            if a != b {
+                edges.push((a, b));
+            }
+        }
+    }
+    let edge_set: HashSet<(VertexKey, VertexKey)> = edges.iter().copied().collect();
+    edges.into_iter().filter(|(a, b)| !edge_set.contains(&(*b, *a))).collect()
+}
+
+/// Chain directed boundary edges into closed loops
+fn chain_loops(edges: &[(VertexKey, VertexKey)]) -> Result<Vec<Vec<VertexKey>>, String> {
+    let mut next: HashMap<VertexKey, VertexKey> = HashMap::new();
+    for &(a, b) in edges {
+        if next.insert(a, b).is_some() {
+            return Err("Face boundary is not a simple loop (non-manifold vertex)".to_string());
+        }
+    }
+
+    // Start from the smallest key for determinism
+    let mut starts: Vec<VertexKey> = next.keys().copied().collect();
+    starts.sort();
+
+    let mut visited: HashSet<VertexKey> = HashSet::new();
+    let mut loops = Vec::new();
+    for start in starts {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut loop_keys = vec![start];
+        visited.insert(start);
+        let mut current = start;
+        loop {
+            let n = *next.get(&current).ok_or("Face boundary is not closed")?;
+            if n == start {
+                break;
+            }
+            if !visited.insert(n) {
+                return Err("Face boundary is not a simple loop".to_string());
+            }
+            loop_keys.push(n);
+            current = n;
+        }
+        loops.push(loop_keys);
+    }
+    Ok(loops)
+}
+
+/// Remove the given triangles, keeping index/id arrays aligned.
+/// Vertex data is left in place (unreferenced vertices are harmless).
+fn remove_triangles(tess: &mut Tessellation, remove: &HashSet<usize>) {
+    let mut indices = Vec::with_capacity(tess.indices.len());
+    let mut ids = Vec::with_capacity(tess.triangle_ids.len());
+    let mut groups = Vec::with_capacity(tess.smoothing_group.len());
+    for t in 0..triangle_count(tess) {
+        if remove.contains(&t) {
+            continue;
+        }
+        indices.extend_from_slice(&tess.indices[t * 3..t * 3 + 3]);
+        if let Some(id) = tess.triangle_ids.get(t) {
+            ids.push(*id);
+        }
+        if let Some(group) = tess.smoothing_group.get(t) {
+            groups.push(*group);
+        }
+    }
+    tess.indices = indices;
+    tess.triangle_ids = ids;
+    tess.smoothing_group = groups;
+}
+
+/// Best-fit plane through a face's triangles, or None if the face is not planar
+fn face_plane(tess: &Tessellation, tris: &[usize]) -> Option<Plane> {
+    let normal = summed_normal(tess, tris);
+    if normal.norm() < 1e-12 {
+        return None;
+    }
+    let points: Vec<Point3> = tris.iter().flat_map(|&t| triangle_points(tess, t)).collect();
+    let plane = Plane::new(super::utils_3d::points_centroid(&points), normal);
+    if points.iter().all(|p| plane.distance(p) < PLANAR_TOLERANCE) {
+        Some(plane)
+    } else {
+        None
+    }
+}
+
+/// Boundary loops of a face, each running the way the face's triangles do
+/// (counter-clockwise seen against the face normal for the outer loop,
+/// clockwise for holes)
+pub fn face_boundary_loops(tess: &Tessellation, face: &TopoId) -> Result<Vec<Vec<Point3>>, String> {
+    let tris = face_triangles(tess, face);
+    if tris.is_empty() {
+        return Err("Face not found in tessellation".to_string());
+    }
+    let mut positions = HashMap::new();
+    let edges = boundary_edges(tess, &tris, &mut positions);
+    let loops = chain_loops(&edges)?;
+    Ok(loops.into_iter().map(|keys| keys.iter().map(|k| positions[k]).collect()).collect())
+}
+
+/// Delete faces from a tessellation and heal the gaps.
+/// Faces are processed in order; a face that cannot be healed is reported in
+/// `errors` and left untouched.
+pub fn delete_faces(tess: &mut Tessellation, faces: &[TopoId], mode: HealMode) -> HealReport {
+    let mut report = HealReport::default();
+    for face in faces {
+        let result = match mode {
+            HealMode::Cap => cap_face(tess, face).map(Some),
+            HealMode::ExtendNeighbors => extend_neighbors(tess, face).map(|_| None),
+        };
+        match result {
+            Ok(patch) => {
+                report.healed_faces.push(*face);
+                report.patch_ids.extend(patch);
+            }
+            Err(reason) => report.errors.push(FaceHealError { face: *face, reason }),
+        }
+    }
+    report
+}
+
+fn cap_face(tess: &mut Tessellation, face: &TopoId) -> Result<TopoId, String> {
+    let tris = face_triangles(tess, face);
+    if tris.is_empty() {
+        return Err("Face not found in tessellation".to_string());
+    }
+
+    let mut positions = HashMap::new();
+    let edges = boundary_edges(tess, &tris, &mut positions);
+    let loops = chain_loops(&edges)?;
+    if loops.len() != 1 {
+        return Err(format!("Face has {} boundary loops; capping supports a single loop", loops.len()));
+    }
+    let mut points: Vec<Point3> = loops[0].iter().map(|k| positions[k]).collect();
+    if points.len() < 3 {
+        return Err("Face boundary is degenerate".to_string());
+    }
+
+    // Newell normal of the loop
+    let mut normal = Vector3::zeros();
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        normal += a.coords.cross(&b.coords);
+    }
+    if normal.norm() < 1e-12 {
+        return Err("Face boundary encloses no area".to_string());
+    }
+    // Keep the patch facing the same way as the removed face
+    if normal.dot(&summed_normal(tess, &tris)) < 0.0 {
+        points.reverse();
+        normal = -normal;
+    }
+
+    let plane = Plane::new(super::utils_3d::points_centroid(&points), normal);
+    if points.iter().any(|p| plane.distance(p) > PLANAR_TOLERANCE) {
+        return Err("Boundary loop is not planar; cannot cap with a planar patch".to_string());
+    }
+
+    // 2D basis with u x v = normal so the loop projects counter-clockwise
+    let u = (points[1] - points[0]).normalize();
+    let v = plane.normal.cross(&u);
+    let polygon: Vec<[f64; 2]> = points.iter()
+        .map(|p| {
+            let d = p - plane.origin;
+            [d.dot(&u), d.dot(&v)]
+        })
+        .collect();
+    let triangles = earcut(&polygon, &[]).map_err(|e| format!("Cap triangulation failed: {}", e))?;
+
+    let remove: HashSet<usize> = tris.into_iter().collect();
+    remove_triangles(tess, &remove);
+
+    let patch_id = healed_patch_id(face);
+    let group = tess.new_smoothing_group();
+    for [a, b, c] in triangles {
+        tess.add_smooth_triangle([points[a], points[b], points[c]], [plane.normal; 3], patch_id, group);
+    }
+    Ok(patch_id)
+}
+
+fn extend_neighbors(tess: &mut Tessellation, face: &TopoId) -> Result<(), String> {
+    let tris = face_triangles(tess, face);
+    if tris.is_empty() {
+        return Err("Face not found in tessellation".to_string());
+    }
+    let removed: HashSet<usize> = tris.iter().copied().collect();
+
+    let mut positions = HashMap::new();
+    let edges = boundary_edges(tess, &tris, &mut positions);
+    let boundary_keys: HashSet<VertexKey> = edges.iter().flat_map(|(a, b)| [*a, *b]).collect();
+
+    // Index the remaining triangles by directed edge and by vertex
+    let mut edge_owner: HashMap<(VertexKey, VertexKey), TopoId> = HashMap::new();
+    let mut vertex_faces: HashMap<VertexKey, HashSet<TopoId>> = HashMap::new();
+    let mut face_tris: HashMap<TopoId, Vec<usize>> = HashMap::new();
+    for t in 0..triangle_count(tess) {
+        if removed.contains(&t) {
+            continue;
+        }
+        let Some(id) = tess.triangle_ids.get(t).copied() else { continue };
+        let keys = triangle_points(tess, t).map(|p| vertex_key(&p));
+        for i in 0..3 {
+            edge_owner.insert((keys[i], keys[(i + 1) % 3]), id);
+            vertex_faces.entry(keys[i]).or_default().insert(id);
+        }
+        face_tris.entry(id).or_default().push(t);
+    }
+
+    // Neighbours across each boundary edge, weighted by shared boundary length
+    let mut shared_length: HashMap<TopoId, f64> = HashMap::new();
+    for (a, b) in &edges {
+        let neighbor = edge_owner.get(&(*b, *a)).ok_or("Face boundary is open (no neighbouring face)")?;
+        *shared_length.entry(*neighbor).or_default() += (positions[b] - positions[a]).norm();
+    }
+    if shared_length.len() < 2 {
+        return Err("Face needs at least two neighbouring faces to extend".to_string());
+    }
+
+    // The two neighbours sharing the longest boundary are extended to meet;
+    // the remaining neighbours are end faces that get trimmed to the new corner.
+    let mut ranked: Vec<(TopoId, f64)> = shared_length.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.local_id.cmp(&b.0.local_id)));
+    if ranked.len() > 2 && ranked[2].1 > ranked[1].1 - PLANAR_TOLERANCE {
+        return Err("Cannot tell which neighbouring faces to extend; several share equally long boundaries".to_string());
+    }
+    let sides = [ranked[0].0, ranked[1].0];
+
+    let mut planes: HashMap<TopoId, Plane> = HashMap::new();
+    for (id, _) in &ranked {
+        let plane = face_plane(tess, &face_tris[id])
+            .ok_or_else(|| format!("Neighbouring face {} is not planar; only planar neighbours can be extended", id.local_id))?;
+        planes.insert(*id, plane);
+    }
+
+    let (line_point, line_dir) = plane_plane_intersect(&planes[&sides[0]], &planes[&sides[1]])
+        .ok_or("Neighbouring faces are parallel and cannot be extended to meet")?;
+
+    // Target position for each boundary vertex
+    let mut moves: HashMap<VertexKey, Point3> = HashMap::new();
+    for key in &boundary_keys {
+        let p = positions[key];
+        let ends: Vec<TopoId> = vertex_faces.get(key)
+            .map(|faces| faces.iter().filter(|f| !sides.contains(f) && planes.contains_key(f)).copied().collect())
+            .unwrap_or_default();
+        let target = match ends.as_slice() {
+            [] => line_point + line_dir * (p - line_point).dot(&line_dir),
+            [end] => {
+                let plane = planes[end];
+                let denom = plane.normal.dot(&line_dir);
+                if denom.abs() < 1e-9 {
+                    return Err("End face is parallel to the extended edge".to_string());
+                }
+                let t = plane.normal.dot(&(plane.origin - line_point)) / denom;
+                line_point + line_dir * t
+            }
+            _ => return Err("Boundary vertex touches several end faces".to_string()),
+        };
+        moves.insert(*key, target);
+    }
+
+    remove_triangles(tess, &removed);
+
+    // Move every vertex (triangles, edges and points) sitting on the old boundary
+    for i in 0..tess.vertices.len() / 3 {
+        let p = vertex_at(tess, i as u32);
+        if let Some(target) = moves.get(&vertex_key(&p)) {
+            tess.vertices[i * 3] = target.x as f32;
+            tess.vertices[i * 3 + 1] = target.y as f32;
+            tess.vertices[i * 3 + 2] = target.z as f32;
+        }
+    }
+
+    // Drop triangles that collapsed when boundary vertices merged
+    let degenerate: HashSet<usize> = (0..triangle_count(tess))
+        .filter(|&t| {
+            let keys = triangle_points(tess, t).map(|p| vertex_key(&p));
+            keys[0] == keys[1] || keys[1] == keys[2] || keys[0] == keys[2]
+        })
+        .collect();
+    remove_triangles(tess, &degenerate);
+    Ok(())
+}
+
+/// True if every triangle edge is shared by exactly one oppositely-oriented edge,
+/// i.e. the triangles form a closed, consistently oriented surface.
+pub fn is_watertight(tess: &Tessellation) -> bool {
+    let mut edge_counts: HashMap<(VertexKey, VertexKey), i32> = HashMap::new();
+    for t in 0..triangle_count(tess) {
+        let keys = triangle_points(tess, t).map(|p| vertex_key(&p));
+        for i in 0..3 {
+            let (a, b) = (keys[i], keys[(i + 1) % 3]);
+            if a == b {
+                continue;
+            }
+            *edge_counts.entry((a, b)).or_default() += 1;
+        }
+    }
+    !edge_counts.is_empty()
+        && edge_counts.iter().all(|(&(a, b), &count)| edge_counts.get(&(b, a)) == Some(&count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topo::EntityId;
+
+    /// Prism from a counter-clockwise YZ profile extruded along +X, one face id per face.
+    /// Returns the tessellation and the side-face ids (side i joins profile[i] and profile[i+1]).
+    fn prism(profile: &[[f64; 2]], length: f64) -> (Tessellation, Vec<TopoId>) {
+        let feature = EntityId::new();
+        let ctx = NamingContext::new(feature);
+        let mut tess = Tessellation::new();
+        let p = |x: f64, yz: [f64; 2]| Point3::new(x, yz[0], yz[1]);
+
+        // Profile is CCW in (y, z); viewed from -X that is clockwise, so the x=0 cap reverses it
+        let cap = earcut(profile, &[]).unwrap();
+        let start_id = ctx.derive("start", TopoRank::Face);
+        let end_id = ctx.derive("end", TopoRank::Face);
+        for &[a, b, c] in &cap {
+            tess.add_triangle(p(0.0, profile[a]), p(0.0, profile[c]), p(0.0, profile[b]), start_id);
+            tess.add_triangle(p(length, profile[a]), p(length, profile[b]), p(length, profile[c]), end_id);
+        }
+
+        let mut sides = Vec::new();
+        for i in 0..profile.len() {
+            let a = profile[i];
+            let b = profile[(i + 1) % profile.len()];
+            let id = ctx.derive(&format!("side{}", i), TopoRank::Face);
+            tess.add_triangle(p(0.0, a), p(0.0, b), p(length, b), id);
+            tess.add_triangle(p(0.0, a), p(length, b), p(length, a), id);
+            sides.push(id);
+        }
+        (tess, sides)
+    }
+
+    #[test]
+    fn test_prism_helper_is_watertight() {
+        let (tess, _) = prism(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]], 10.0);
+        assert!(is_watertight(&tess));
+    }
+
+    #[test]
+    fn test_delete_box_face_and_cap() {
+        let (mut tess, sides) = prism(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]], 10.0);
+        let top = sides[1];
+
+        let report = delete_faces(&mut tess, &[top], HealMode::Cap);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.patch_ids, vec![healed_patch_id(&top)]);
+        assert!(!tess.triangle_ids.contains(&top));
+        assert!(is_watertight(&tess), "Capped box should be closed");
+        assert!((crate::analysis::mesh_volume(&tess) - 1000.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_delete_chamfer_extends_neighbors() {
+        // Box with a chamfer between the y=0 face and the z=10 face
+        let (mut tess, sides) = prism(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [2.0, 10.0], [0.0, 8.0]], 10.0);
+        let chamfer = sides[3];
+        assert!(is_watertight(&tess));
+
+        let report = delete_faces(&mut tess, &[chamfer], HealMode::ExtendNeighbors);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.patch_ids.is_empty());
+        assert!(is_watertight(&tess), "Extended box should be closed");
+        assert!((crate::analysis::mesh_volume(&tess) - 1000.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_box_face_cannot_extend() {
+        // Every neighbour of a box face shares an equal boundary and opposite ones are parallel
+        let (mut tess, sides) = prism(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]], 10.0);
+        let before = tess.indices.clone();
+
+        let report = delete_faces(&mut tess, &[sides[1]], HealMode::ExtendNeighbors);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].face, sides[1]);
+        assert_eq!(tess.indices, before, "Failed heal must not modify the mesh");
+    }
+
+    #[test]
+    fn test_parallel_neighbors_cannot_extend() {
+        // Thin slot face between two parallel walls: the walls share the longest boundaries
+        let (mut tess, sides) = prism(&[[0.0, 0.0], [10.0, 0.0], [10.0, 1.0], [0.0, 1.0]], 10.0);
+        let report = delete_faces(&mut tess, &[sides[1]], HealMode::ExtendNeighbors);
+        assert!(report.errors[0].reason.contains("parallel"), "{}", report.errors[0].reason);
+    }
+
+    #[test]
+    fn test_unknown_face_reports_error() {
+        let (mut tess, _) = prism(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]], 10.0);
+        let missing = TopoId::new(EntityId::new(), 42, TopoRank::Face);
+        let report = delete_faces(&mut tess, &[missing], HealMode::Cap);
+        assert_eq!(report.errors[0].face, missing);
+        assert!(report.healed_faces.is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Draft analysis for molded parts
+//!
+//! Classifies faces by the angle between their surface and the direction the
+//! part is pulled out of the mold. Faces must lean away from the pull (positive
+//! draft) to release; faces leaning into it are undercut.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Point3, Tessellation, Vector3};
+use crate::topo::naming::TopoId;
+
+/// Draft usually asked of molded faces, in degrees
+pub const DEFAULT_MIN_ANGLE_DEG: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DraftStatus {
+    /// Leans away from the pull by at least the minimum angle
+    Positive,
+    /// Leans into the pull by at least the minimum angle (undercut)
+    Negative,
+    /// Within the minimum angle of parallel to the pull; needs draft added
+    NearVertical,
+}
+
+/// Classify each face of `tess` by its draft relative to `pull_dir`.
+///
+/// A face's draft is the angle between its area-weighted average normal and
+/// the plane perpendicular to `pull_dir`: 90° for a face looking straight
+/// along the pull, 0° for one parallel to it, negative when it looks against
+/// it. Faces whose normals cancel out (closed or strongly curved faces) count
+/// as near-vertical. Reference geometry is skipped, and a zero `pull_dir`
+/// gives an empty map.
+pub fn draft_analysis(tess: &Tessellation, pull_dir: Vector3, min_angle_deg: f64) -> HashMap<TopoId, DraftStatus> {
+    if pull_dir.norm() < 1e-12 {
+        return HashMap::new();
+    }
+    let pull = pull_dir.normalize();
+    let vertex = |i: u32| {
+        let i = i as usize * 3;
+        Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64)
+    };
+
+    // Twice the area times the unit normal, summed over each face's triangles
+    let mut normals: HashMap<TopoId, Vector3> = HashMap::new();
+    for (tri, id) in tess.indices.chunks_exact(3).zip(&tess.triangle_ids) {
+        if tess.is_reference(id) {
+            continue;
+        }
+        let (a, b, c) = (vertex(tri[0]), vertex(tri[1]), vertex(tri[2]));
+        *normals.entry(*id).or_insert_with(Vector3::zeros) += (b - a).cross(&(c - a));
+    }
+
+    let min_sin = min_angle_deg.to_radians().sin();
+    normals.into_iter()
+        .map(|(id, sum)| {
+            let norm = sum.norm();
+            let along = if norm > 1e-12 { sum.dot(&pull) / norm } else { 0.0 };
+            let status = if along >= min_sin && along > 0.0 {
+                DraftStatus::Positive
+            } else if along <= -min_sin && along < 0.0 {
+                DraftStatus::Negative
+            } else {
+                DraftStatus::NearVertical
+            };
+            (id, status)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topo::naming::TopoRank;
+    use crate::topo::EntityId;
+
+    #[test]
+    fn test_box_with_one_tapered_face() {
+        // Unit box whose +x side leans in to x = 0.9 at the top, a draft of about 5.7°
+        let feature = EntityId::new();
+        let face = |n: u64| TopoId::new(feature, n, TopoRank::Face);
+        let b = [Point3::new(0., 0., 0.), Point3::new(1., 0., 0.), Point3::new(1., 1., 0.), Point3::new(0., 1., 0.)];
+        let t = [Point3::new(0., 0., 1.), Point3::new(0.9, 0., 1.), Point3::new(0.9, 1., 1.), Point3::new(0., 1., 1.)];
+        let quads = [
+            [b[0], b[3], b[2], b[1]], // bottom
+            [t[0], t[1], t[2], t[3]], // top
+            [b[0], b[1], t[1], t[0]], // front
+            [b[3], t[3], t[2], b[2]], // back
+            [b[0], t[0], t[3], b[3]], // left
+            [b[1], b[2], t[2], t[1]], // tapered
+        ];
+        let mut tess = Tessellation::new();
+        for (n, q) in quads.iter().enumerate() {
+            tess.add_triangle(q[0], q[1], q[2], face(n as u64));
+            tess.add_triangle(q[0], q[2], q[3], face(n as u64));
+        }
+
+        let up = draft_analysis(&tess, Vector3::z(), 1.0);
+        assert_eq!(up.len(), 6);
+        assert_eq!(up[&face(0)], DraftStatus::Negative);
+        assert_eq!(up[&face(1)], DraftStatus::Positive);
+        for side in 2..5 {
+            assert_eq!(up[&face(side)], DraftStatus::NearVertical, "Face {}", side);
+        }
+        assert_eq!(up[&face(5)], DraftStatus::Positive, "Tapered face releases upwards");
+
+        // Pulled the other way the taper is an undercut, and past its angle it needs more draft
+        let down = draft_analysis(&tess, -Vector3::z(), 1.0);
+        assert_eq!(down[&face(5)], DraftStatus::Negative);
+        assert_eq!(draft_analysis(&tess, Vector3::z(), 10.0)[&face(5)], DraftStatus::NearVertical);
+        assert!(draft_analysis(&tess, Vector3::zeros(), 1.0).is_empty());
+    }
+}
@@ -8,27 +8,42 @@ pub type Transform3 = na::Isometry3<f64>;
 pub const EPSILON: f64 = 1e-6;
 
 pub trait ApproxEq {
-    fn approx_eq(&self, other: &Self) -> bool;
+    /// Equal to within `tol`: the difference of two numbers, or the distance
+    /// between two points or vectors
+    fn approx_eq_tol(&self, other: &Self, tol: f64) -> bool;
+
+    /// Equal to within `EPSILON`
+    fn approx_eq(&self, other: &Self) -> bool {
+        self.approx_eq_tol(other, EPSILON)
+    }
 }
 
 impl ApproxEq for f64 {
-    fn approx_eq(&self, other: &Self) -> bool {
-        (self - other).abs() < EPSILON
+    fn approx_eq_tol(&self, other: &Self, tol: f64) -> bool {
+        (self - other).abs() < tol
     }
 }
 
 impl ApproxEq for Point3 {
-    fn approx_eq(&self, other: &Self) -> bool {
-        na::distance_squared(self, other) < EPSILON * EPSILON
+    fn approx_eq_tol(&self, other: &Self, tol: f64) -> bool {
+        na::distance_squared(self, other) < tol * tol
     }
 }
 
 impl ApproxEq for Vector3 {
-    fn approx_eq(&self, other: &Self) -> bool {
-        (self - other).norm_squared() < EPSILON * EPSILON
+    fn approx_eq_tol(&self, other: &Self, tol: f64) -> bool {
+        (self - other).norm_squared() < tol * tol
     }
 }
 
+/// Points equal to within `rel` of their distance from the origin, for models
+/// far from it where a fixed tolerance is below f32/f64 round-off. Near the
+/// origin the tolerance bottoms out at `rel` itself.
+pub fn approx_eq_rel(p1: &Point3, p2: &Point3, rel: f64) -> bool {
+    let scale = p1.coords.norm().max(p2.coords.norm()).max(1.0);
+    p1.approx_eq_tol(p2, rel * scale)
+}
+
 pub mod primitives;
 pub use primitives::*;
 
@@ -42,8 +57,41 @@ pub use intersection::*;
 pub mod utils_2d;
 pub mod utils_3d;
 
+pub mod defeature;
+pub mod offset;
+pub mod decimate;
+pub mod datum;
+pub mod section;
+pub mod revolution;
+pub mod uv;
+pub mod draft;
+pub mod triangulate;
+pub mod blend;
+
 pub fn dist_sq(p1: &Point3, p2: &Point3) -> f64 {
 
     na::distance_squared(p1, p2)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_eq_with_tolerance() {
+        let a = Point3::new(1.0, 2.0, 3.0);
+        let b = a + Vector3::new(1e-4, 0.0, 0.0);
+        assert!(!a.approx_eq(&b));
+        assert!(!a.approx_eq_tol(&b, 1e-6));
+        assert!(a.approx_eq_tol(&b, 1e-3));
+        assert!((b - a).approx_eq_tol(&Vector3::zeros(), 1e-3));
+        assert!(1.0f64.approx_eq_tol(&1.0001, 1e-3) && !1.0f64.approx_eq(&1.0001));
+
+        // 1e-3 apart, a million units out: off by round-off, not by design
+        let far = Point3::new(1e6, 0.0, 0.0);
+        let shifted = far + Vector3::new(0.0, 1e-3, 0.0);
+        assert!(!far.approx_eq_tol(&shifted, 1e-6));
+        assert!(approx_eq_rel(&far, &shifted, 1e-8));
+        assert!(!approx_eq_rel(&a, &b, 1e-8), "Near the origin the relative tolerance doesn't grow");
+    }
+}
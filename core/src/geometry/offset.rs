@@ -0,0 +1,176 @@
+//! Offsetting closed 2D outlines.
+//!
+//! An outline is a set of closed loops bounding one region: the loop with the
+//! largest area is the outer boundary, the others are holes. Offsetting by a
+//! positive distance moves every loop into the region (the outer boundary
+//! shrinks, holes grow); a negative distance moves them out. Polygon corners
+//! stay sharp: each edge moves parallel to itself and neighbours are
+//! re-intersected.
+
+use std::f64::consts::PI;
+
+use nalgebra::{self as na, Matrix3};
+
+use super::utils_2d::{cross_2d, dot_2d, line_line_intersect_unbounded, normalize_2d, perpendicular_ccw, polygon_signed_area, EPSILON};
+
+/// One closed loop of an outline
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutlineLoop {
+    /// Corners in order, either winding, without repeating the first
+    Polygon(Vec<[f64; 2]>),
+    Circle { center: [f64; 2], radius: f64 },
+}
+
+/// Fewest corners a loop needs to be taken for a circle
+const MIN_CIRCLE_POINTS: usize = 8;
+
+impl OutlineLoop {
+    /// Loop through `points`: a circle if they all lie within `tolerance` of
+    /// one (a tessellated hole), otherwise a polygon without the corners
+    /// where it runs straight on
+    pub fn from_points(points: &[[f64; 2]], tolerance: f64) -> Self {
+        match fit_circle(points) {
+            Some((center, radius)) if points.len() >= MIN_CIRCLE_POINTS
+                && points.iter().all(|p| ((p[0] - center[0]).hypot(p[1] - center[1]) - radius).abs() < tolerance) =>
+            {
+                OutlineLoop::Circle { center, radius }
+            }
+            _ => OutlineLoop::Polygon(merge_collinear(points, tolerance)),
+        }
+    }
+
+    fn area(&self) -> f64 {
+        match self {
+            OutlineLoop::Polygon(points) => polygon_signed_area(points).abs(),
+            OutlineLoop::Circle { radius, .. } => PI * radius * radius,
+        }
+    }
+}
+
+/// Least-squares circle through points (the algebraic fit: x² + y² + ax + by + c = 0)
+pub(crate) fn fit_circle(points: &[[f64; 2]]) -> Option<([f64; 2], f64)> {
+    let mut normal = Matrix3::zeros();
+    let mut rhs = na::Vector3::zeros();
+    for p in points {
+        let row = na::Vector3::new(p[0], p[1], 1.0);
+        normal += row * row.transpose();
+        rhs -= row * (p[0] * p[0] + p[1] * p[1]);
+    }
+    let [a, b, c] = normal.lu().solve(&rhs)?.into();
+    let center = [-a / 2.0, -b / 2.0];
+    let radius_squared = center[0] * center[0] + center[1] * center[1] - c;
+    (radius_squared > 0.0).then(|| (center, radius_squared.sqrt()))
+}
+
+/// Drop corners where the outline runs straight on, such as the extra
+/// vertices a tessellation leaves along an edge
+fn merge_collinear(points: &[[f64; 2]], tolerance: f64) -> Vec<[f64; 2]> {
+    let mut merged: Vec<[f64; 2]> = points.to_vec();
+    let mut i = 0;
+    while merged.len() > 3 && i < merged.len() {
+        let n = merged.len();
+        let (prev, here, next) = (merged[(i + n - 1) % n], merged[i], merged[(i + 1) % n]);
+        let chord = [next[0] - prev[0], next[1] - prev[1]];
+        let length = chord[0].hypot(chord[1]);
+        let off_chord = cross_2d(chord, [here[0] - prev[0], here[1] - prev[1]]).abs() / length.max(EPSILON);
+        if off_chord < tolerance {
+            merged.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    merged
+}
+
+/// Offset every loop of an outline by `distance` into the region it bounds.
+/// Fails if a loop would collapse or turn inside out.
+pub fn offset_outline(loops: &[OutlineLoop], distance: f64) -> Result<Vec<OutlineLoop>, String> {
+    let outer = (0..loops.len())
+        .max_by(|&a, &b| loops[a].area().total_cmp(&loops[b].area()))
+        .ok_or_else(|| "Outline has no loops".to_string())?;
+    loops.iter().enumerate().map(|(i, outline)| {
+        // Distance outward from the loop's own interior
+        let grow = if i == outer { -distance } else { distance };
+        match outline {
+            OutlineLoop::Circle { center, radius } => {
+                let radius = radius + grow;
+                if radius <= EPSILON {
+                    return Err(format!("Offset {} collapses a circular loop", distance));
+                }
+                Ok(OutlineLoop::Circle { center: *center, radius })
+            }
+            OutlineLoop::Polygon(points) => offset_polygon(points, grow)
+                .map(OutlineLoop::Polygon)
+                .ok_or_else(|| format!("Offset {} is too large for the outline", distance)),
+        }
+    }).collect()
+}
+
+/// Move each edge of a polygon `grow` away from its interior and intersect
+/// neighbouring edges again. None if the polygon collapses or turns inside out.
+fn offset_polygon(points: &[[f64; 2]], grow: f64) -> Option<Vec<[f64; 2]>> {
+    let n = points.len();
+    if n < 3 {
+        return None;
+    }
+    // Counter-clockwise, the interior is left of each edge
+    let leftward = if polygon_signed_area(points) > 0.0 { -grow } else { grow };
+    let edges: Vec<([f64; 2], [f64; 2])> = (0..n).map(|i| {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let left = perpendicular_ccw(normalize_2d([b[0] - a[0], b[1] - a[1]]));
+        let shift = [left[0] * leftward, left[1] * leftward];
+        ([a[0] + shift[0], a[1] + shift[1]], [b[0] + shift[0], b[1] + shift[1]])
+    }).collect();
+
+    let corners: Vec<[f64; 2]> = (0..n).map(|i| {
+        let (prev, here) = (edges[(i + n - 1) % n], edges[i]);
+        // Parallel neighbours (a straight run) meet where the shifted edges touch
+        line_line_intersect_unbounded(prev.0, prev.1, here.0, here.1).map_or(here.0, |(p, _, _)| p)
+    }).collect();
+
+    // An edge that flips direction has been consumed by its neighbours
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (c, d) = (corners[i], corners[(i + 1) % n]);
+        if dot_2d([b[0] - a[0], b[1] - a[1]], [d[0] - c[0], d[1] - c[1]]) <= EPSILON {
+            return None;
+        }
+    }
+    Some(corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_shrinks_outer_loop_and_grows_holes() {
+        // Clockwise square, to check winding doesn't matter
+        let square = OutlineLoop::Polygon(vec![[0.0, 0.0], [0.0, 10.0], [10.0, 10.0], [10.0, 0.0]]);
+        let hole = OutlineLoop::Circle { center: [5.0, 5.0], radius: 2.0 };
+        let offset = offset_outline(&[hole, square], 1.0).unwrap();
+        assert_eq!(offset[0], OutlineLoop::Circle { center: [5.0, 5.0], radius: 3.0 });
+        let OutlineLoop::Polygon(corners) = &offset[1] else { panic!("{:?}", offset) };
+        assert_eq!(corners, &[[1.0, 1.0], [1.0, 9.0], [9.0, 9.0], [9.0, 1.0]]);
+
+        let square = OutlineLoop::Polygon(vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]]);
+        assert!(offset_outline(std::slice::from_ref(&square), 6.0).is_err(), "Inside out");
+        let OutlineLoop::Polygon(grown) = &offset_outline(&[square], -1.0).unwrap()[0] else { unreachable!() };
+        assert_eq!(grown[0], [-1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_loops_from_tessellated_points() {
+        let points = [[0.0, 0.0], [5.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 4.0]];
+        let square = OutlineLoop::Polygon(vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]]);
+        assert_eq!(OutlineLoop::from_points(&points, 1e-6), square);
+
+        // Unevenly spaced points on a circle
+        let points: Vec<[f64; 2]> = (0..12).map(|i| {
+            let angle = (i * i) as f64 * 0.05;
+            [3.0 + 2.0 * angle.cos(), -1.0 + 2.0 * angle.sin()]
+        }).collect();
+        let OutlineLoop::Circle { center, radius } = OutlineLoop::from_points(&points, 1e-6) else { panic!("Not a circle") };
+        assert!((center[0] - 3.0).abs() < 1e-9 && (center[1] + 1.0).abs() < 1e-9 && (radius - 2.0).abs() < 1e-9);
+    }
+}
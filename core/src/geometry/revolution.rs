@@ -0,0 +1,249 @@
+//! Analytic surfaces of revolution
+//!
+//! Revolved faces come back from the kernel as triangles. Each face is swept by
+//! one profile curve, so finding that curve tells us the exact surface: a line
+//! sweeps a plane, cylinder or cone, an arc sweeps a sphere or torus band.
+
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+
+use super::{Point3, Tessellation, Vector3};
+use crate::sketch::types::SketchGeometry;
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+
+/// Below this (relative to the curve size) a line counts as parallel/perpendicular to the axis
+const ALIGNMENT_TOLERANCE: f64 = 1e-9;
+
+/// Tessellated vertices are single precision
+const VERTEX_TOLERANCE: f64 = 1e-4;
+
+/// Axis a profile is revolved about, in the frame the profile curves are placed
+/// in (sketch coordinates on z = 0)
+#[derive(Debug, Clone, Copy)]
+pub struct RevolutionAxis {
+    pub origin: Point3,
+    /// Unit direction
+    pub direction: Vector3,
+}
+
+impl RevolutionAxis {
+    pub fn new(origin: Point3, direction: Vector3) -> Self {
+        Self { origin, direction: direction.normalize() }
+    }
+
+    /// Position along the axis and distance from it
+    fn axial_radial(&self, p: Point3) -> (f64, f64) {
+        let v = p - self.origin;
+        let t = v.dot(&self.direction);
+        (t, (v - self.direction * t).norm())
+    }
+
+    fn at(&self, t: f64) -> [f64; 3] {
+        let p = self.origin + self.direction * t;
+        [p.x, p.y, p.z]
+    }
+}
+
+fn on_plane(p: [f64; 2]) -> Point3 {
+    Point3::new(p[0], p[1], 0.0)
+}
+
+/// Surface swept by revolving `curve` about `axis`, or None for curves that
+/// sweep no surface (points, lines on the axis) or no analytic one (ellipses)
+pub fn swept_surface(curve: &SketchGeometry, axis: &RevolutionAxis) -> Option<AnalyticGeometry> {
+    let d = axis.direction;
+    let dir = [d.x, d.y, d.z];
+    match *curve {
+        SketchGeometry::Line { start, end } => {
+            let (t1, r1) = axis.axial_radial(on_plane(start));
+            let (t2, r2) = axis.axial_radial(on_plane(end));
+            let eps = ALIGNMENT_TOLERANCE * (t2 - t1).hypot(r2 - r1).max(1.0);
+            if r1.max(r2) < eps {
+                None
+            } else if (t2 - t1).abs() < eps {
+                Some(AnalyticGeometry::Plane { origin: axis.at(t1), normal: dir })
+            } else if (r2 - r1).abs() < eps {
+                Some(AnalyticGeometry::Cylinder { axis_start: axis.at(t1.min(t2)), axis_dir: dir, radius: r1 })
+            } else {
+                // The cone opens towards increasing radius
+                let slope = (r2 - r1) / (t2 - t1);
+                let sign = slope.signum();
+                Some(AnalyticGeometry::Cone {
+                    apex: axis.at(t1 - r1 / slope),
+                    axis: [dir[0] * sign, dir[1] * sign, dir[2] * sign],
+                    half_angle: slope.abs().atan(),
+                })
+            }
+        }
+        SketchGeometry::Arc { center, radius, .. } | SketchGeometry::Circle { center, radius } => {
+            let (tc, rc) = axis.axial_radial(on_plane(center));
+            if rc < ALIGNMENT_TOLERANCE * radius.max(1.0) {
+                Some(AnalyticGeometry::Sphere { center: axis.at(tc), radius })
+            } else {
+                Some(AnalyticGeometry::Torus { center: axis.at(tc), axis: dir, major_radius: rc, minor_radius: radius })
+            }
+        }
+        SketchGeometry::Point { .. } | SketchGeometry::Ellipse { .. } | SketchGeometry::EllipseArc { .. } => None,
+    }
+}
+
+/// Distance from `q` to `curve` in the sketch plane
+fn distance_to_curve(q: [f64; 2], curve: &SketchGeometry) -> f64 {
+    let dist = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+    match *curve {
+        SketchGeometry::Line { start, end } => {
+            let t = crate::geometry::intersection::point_on_line_parameter(start, end, q).clamp(0.0, 1.0);
+            dist(q, [start[0] + t * (end[0] - start[0]), start[1] + t * (end[1] - start[1])])
+        }
+        SketchGeometry::Circle { center, radius } => (dist(q, center) - radius).abs(),
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+            let sweep = (end_angle - start_angle).rem_euclid(TAU);
+            let angle = ((q[1] - center[1]).atan2(q[0] - center[0]) - start_angle).rem_euclid(TAU);
+            if sweep < 1e-12 || angle <= sweep {
+                (dist(q, center) - radius).abs()
+            } else {
+                let at = |a: f64| [center[0] + radius * a.cos(), center[1] + radius * a.sin()];
+                dist(q, at(start_angle)).min(dist(q, at(end_angle)))
+            }
+        }
+        SketchGeometry::Point { pos } => dist(q, pos),
+        SketchGeometry::Ellipse { .. } | SketchGeometry::EllipseArc { .. } => f64::INFINITY,
+    }
+}
+
+/// A point on the curve, to tell which side of the axis it lies on
+fn sample_point(curve: &SketchGeometry) -> [f64; 2] {
+    match *curve {
+        SketchGeometry::Line { start, end } => [(start[0] + end[0]) / 2.0, (start[1] + end[1]) / 2.0],
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+            let mid = start_angle + (end_angle - start_angle).rem_euclid(TAU) / 2.0;
+            [center[0] + radius * mid.cos(), center[1] + radius * mid.sin()]
+        }
+        SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+            let mid = start_angle + (end_angle - start_angle).rem_euclid(TAU) / 2.0;
+            crate::sketch::types::ellipse_point(center, semi_major, semi_minor, rotation, mid)
+        }
+        SketchGeometry::Circle { center, .. } | SketchGeometry::Ellipse { center, .. } => center,
+        SketchGeometry::Point { pos } => pos,
+    }
+}
+
+/// Index of the profile curve whose sweep contains every point of `points`.
+/// Points are rotated back into the profile plane about the axis and compared
+/// with each curve; arcs allow for the chord sag of a profile discretized at
+/// `segments_per_circle`.
+pub fn sweeping_curve(points: &[Point3], curves: &[SketchGeometry], axis: &RevolutionAxis, segments_per_circle: usize) -> Option<usize> {
+    // Profiles are revolved from the z = 0 plane, about an axis lying in it
+    if points.is_empty() || axis.direction.z.abs() > ALIGNMENT_TOLERANCE {
+        return None;
+    }
+    let normal = Vector3::z().cross(&axis.direction);
+    let sag = |radius: f64| radius * (1.0 - (PI / segments_per_circle.max(3) as f64).cos());
+
+    curves.iter().position(|curve| {
+        let tolerance = VERTEX_TOLERANCE + match curve {
+            SketchGeometry::Arc { radius, .. } | SketchGeometry::Circle { radius, .. } => sag(*radius),
+            _ => 0.0,
+        };
+        let side = (on_plane(sample_point(curve)) - axis.origin).dot(&normal).signum();
+        points.iter().all(|p| {
+            let (t, r) = axis.axial_radial(*p);
+            let q = axis.origin + axis.direction * t + normal * (r * side);
+            distance_to_curve([q.x, q.y], curve) <= tolerance
+        })
+    })
+}
+
+/// Re-register the faces of a revolve, tessellated from `first_triangle` on,
+/// as the analytic surfaces swept by `curves`. Faces that no single curve
+/// sweeps keep their existing geometry. Returns the number of faces updated.
+pub fn classify_revolved_faces(
+    tess: &Tessellation,
+    first_triangle: usize,
+    manifest: &mut HashMap<TopoId, KernelEntity>,
+    curves: &[SketchGeometry],
+    axis: &RevolutionAxis,
+    segments_per_circle: usize,
+) -> usize {
+    let mut face_points: HashMap<TopoId, Vec<Point3>> = HashMap::new();
+    for (t, id) in tess.triangle_ids.iter().enumerate().skip(first_triangle) {
+        if id.rank != TopoRank::Face {
+            continue;
+        }
+        let points = face_points.entry(*id).or_default();
+        for &index in &tess.indices[t * 3..t * 3 + 3] {
+            let i = index as usize * 3;
+            points.push(Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64));
+        }
+    }
+
+    let mut updated = 0;
+    for (id, points) in face_points {
+        let surface = sweeping_curve(&points, curves, axis, segments_per_circle)
+            .and_then(|i| swept_surface(&curves[i], axis));
+        if let (Some(surface), Some(entity)) = (surface, manifest.get_mut(&id)) {
+            entity.geometry = surface;
+            updated += 1;
+        }
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_4;
+
+    #[test]
+    fn test_swept_surfaces_of_lines_and_arcs() {
+        let y_axis = RevolutionAxis::new(Point3::origin(), Vector3::y());
+        let line = |start, end| SketchGeometry::Line { start, end };
+
+        assert!(matches!(swept_surface(&line([1.0, 2.0], [4.0, 2.0]), &y_axis),
+            Some(AnalyticGeometry::Plane { normal: [0.0, 1.0, 0.0], .. })));
+        assert!(matches!(swept_surface(&line([3.0, 0.0], [3.0, 5.0]), &y_axis),
+            Some(AnalyticGeometry::Cylinder { radius, .. }) if (radius - 3.0).abs() < 1e-12));
+        assert!(swept_surface(&line([0.0, 0.0], [0.0, 5.0]), &y_axis).is_none(), "A line on the axis sweeps nothing");
+
+        // 45° slant meeting the axis at y = -1, opening upwards
+        match swept_surface(&line([3.0, 2.0], [1.0, 0.0]), &y_axis) {
+            Some(AnalyticGeometry::Cone { apex, axis, half_angle }) => {
+                assert!(apex[0].abs() < 1e-12 && (apex[1] + 1.0).abs() < 1e-12);
+                assert_eq!(axis, [0.0, 1.0, 0.0]);
+                assert!((half_angle - FRAC_PI_4).abs() < 1e-12);
+            }
+            other => panic!("Expected a cone, got {:?}", other),
+        }
+
+        let arc = |center, radius| SketchGeometry::Arc { center, radius, start_angle: 0.0, end_angle: 1.0 };
+        assert!(matches!(swept_surface(&arc([0.0, 1.0], 2.0), &y_axis),
+            Some(AnalyticGeometry::Sphere { radius, .. }) if radius == 2.0));
+        match swept_surface(&arc([5.0, 1.0], 2.0), &y_axis) {
+            Some(AnalyticGeometry::Torus { center, major_radius, minor_radius, .. }) => {
+                assert_eq!(center, [0.0, 1.0, 0.0]);
+                assert!((major_radius - 5.0).abs() < 1e-12 && minor_radius == 2.0);
+            }
+            other => panic!("Expected a torus, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sweeping_curve_recognizes_rotated_points() {
+        let axis = RevolutionAxis::new(Point3::origin(), Vector3::y());
+        let curves = [
+            SketchGeometry::Line { start: [5.0, 0.0], end: [7.0, 0.0] },
+            SketchGeometry::Arc { center: [5.0, 0.0], radius: 2.0, start_angle: 0.0, end_angle: PI / 2.0 },
+        ];
+        // Points of the arc rotated 120° about Y
+        let (sin, cos) = (TAU / 3.0).sin_cos();
+        let arc_points: Vec<Point3> = (0..=8).map(|i| {
+            let a = i as f64 * PI / 16.0;
+            let (x, y) = (5.0 + 2.0 * a.cos(), 2.0 * a.sin());
+            Point3::new(x * cos, y, -x * sin)
+        }).collect();
+        assert_eq!(sweeping_curve(&arc_points, &curves, &axis, 32), Some(1));
+        assert_eq!(sweeping_curve(&[Point3::new(0.0, 0.0, 6.0)], &curves, &axis, 32), Some(0));
+        assert_eq!(sweeping_curve(&[Point3::new(0.0, 1.0, 3.0)], &curves, &axis, 32), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Planar sections of tessellated geometry
+//!
+//! Intersects every triangle with a plane and chains the resulting segments
+//! into polylines expressed in the plane's 2D coordinates, for drawing views.
+
+use std::collections::HashMap;
+
+use super::datum::plane_axes;
+use super::{Point3, Tessellation, Vector3};
+
+/// Grid used to match segment endpoints computed from neighbouring triangles
+const WELD_TOLERANCE: f64 = 1e-7;
+
+/// Section `tess` with the plane through `plane_origin` with normal `plane_normal`.
+///
+/// Returns closed loops (first point not repeated) and, for open meshes, open
+/// chains. Points are `[x, y]` along the axes from `datum::plane_axes`, relative
+/// to `plane_origin`. Loops follow the triangles' winding: with outward facing
+/// triangles, outer boundaries run counter-clockwise seen from the normal's
+/// side. Collinear runs are merged into single edges. Reference geometry
+/// (datum planes/axes) is skipped.
+pub fn section(tess: &Tessellation, plane_origin: Point3, plane_normal: Vector3) -> Vec<Vec<[f64; 2]>> {
+    if plane_normal.norm() < 1e-12 {
+        return Vec::new();
+    }
+    let normal = plane_normal.normalize();
+    let (x_axis, y_axis) = plane_axes(&normal);
+    let to_2d = |p: Point3| {
+        let v = p - plane_origin;
+        [v.dot(&x_axis), v.dot(&y_axis)]
+    };
+    let vertex = |i: u32| {
+        let i = i as usize * 3;
+        Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64)
+    };
+
+    let mut segments: Vec<([f64; 2], [f64; 2])> = Vec::new();
+    for (t, tri) in tess.indices.chunks_exact(3).enumerate() {
+        if tess.triangle_ids.get(t).is_some_and(|id| tess.is_reference(id)) {
+            continue;
+        }
+        let p = [vertex(tri[0]), vertex(tri[1]), vertex(tri[2])];
+        let d = p.map(|v| (v - plane_origin).dot(&normal));
+        // Vertices on the plane count as above it, so each crossing is seen once
+        let below = d.map(|x| x < 0.0);
+        if below.iter().all(|b| *b) || below.iter().all(|b| !*b) {
+            continue;
+        }
+
+        let mut points = Vec::with_capacity(2);
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            if below[a] != below[b] {
+                points.push(edge_crossing(p[a], d[a], p[b], d[b]));
+            }
+        }
+        let (mut start, mut end) = (points[0], points[1]);
+        if (end - start).norm() < WELD_TOLERANCE {
+            continue;
+        }
+        // Orient so the material (behind the triangle) is on the left of the segment
+        let tri_normal = (p[1] - p[0]).cross(&(p[2] - p[0]));
+        if (end - start).dot(&normal.cross(&tri_normal)) < 0.0 {
+            std::mem::swap(&mut start, &mut end);
+        }
+        segments.push((to_2d(start), to_2d(end)));
+    }
+
+    chain_segments(&segments).into_iter().map(|(chain, closed)| simplify(chain, closed)).collect()
+}
+
+/// Point where the edge a-b crosses the plane. Endpoints are ordered first so
+/// both triangles sharing the edge compute bit-identical points.
+fn edge_crossing(a: Point3, da: f64, b: Point3, db: f64) -> Point3 {
+    let ((a, da), (b, db)) = if (a.x, a.y, a.z) <= (b.x, b.y, b.z) { ((a, da), (b, db)) } else { ((b, db), (a, da)) };
+    let t = da / (da - db);
+    a + (b - a) * t
+}
+
+fn weld_key(p: [f64; 2]) -> (i64, i64) {
+    ((p[0] / WELD_TOLERANCE).round() as i64, (p[1] / WELD_TOLERANCE).round() as i64)
+}
+
+/// Chain directed segments end-to-start. Returns each chain and whether it closes.
+fn chain_segments(segments: &[([f64; 2], [f64; 2])]) -> Vec<(Vec<[f64; 2]>, bool)> {
+    let mut by_start: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let mut by_end: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, (s, e)) in segments.iter().enumerate() {
+        by_start.entry(weld_key(*s)).or_default().push(i);
+        by_end.entry(weld_key(*e)).or_default().push(i);
+    }
+    let mut used = vec![false; segments.len()];
+    let take = |map: &HashMap<(i64, i64), Vec<usize>>, key, used: &mut Vec<bool>| {
+        let next = map.get(&key)?.iter().copied().find(|&i| !used[i])?;
+        used[next] = true;
+        Some(next)
+    };
+
+    let mut chains = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let start_key = weld_key(segments[first].0);
+        let mut chain = vec![segments[first].0, segments[first].1];
+
+        let mut closed = false;
+        loop {
+            let end_key = weld_key(*chain.last().unwrap());
+            if end_key == start_key {
+                chain.pop();
+                closed = true;
+                break;
+            }
+            match take(&by_start, end_key, &mut used) {
+                Some(next) => chain.push(segments[next].1),
+                None => break,
+            }
+        }
+        // Open chain: extend backwards from its start too
+        if !closed {
+            while let Some(prev) = take(&by_end, weld_key(chain[0]), &mut used) {
+                chain.insert(0, segments[prev].0);
+            }
+        }
+        chains.push((chain, closed));
+    }
+    chains
+}
+
+/// Drop points lying on the straight line between their neighbours
+fn simplify(points: Vec<[f64; 2]>, closed: bool) -> Vec<[f64; 2]> {
+    let n = points.len();
+    if n < 3 {
+        return points;
+    }
+    let collinear = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+        let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        let len = (c[0] - a[0]).hypot(c[1] - a[1]);
+        cross.abs() <= WELD_TOLERANCE * len.max(1.0)
+    };
+    let mut kept: Vec<[f64; 2]> = Vec::with_capacity(n);
+    for i in 0..n {
+        let is_end = !closed && (i == 0 || i == n - 1);
+        let prev = kept.last().copied().unwrap_or(points[(i + n - 1) % n]);
+        if is_end || !collinear(prev, points[i], points[(i + 1) % n]) {
+            kept.push(points[i]);
+        }
+    }
+    // The first point of a closed loop was tested against the unsimplified last point
+    if closed && kept.len() >= 3 && collinear(kept[kept.len() - 1], kept[0], kept[1]) {
+        kept.remove(0);
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::utils_2d::polygon_signed_area;
+    use crate::topo::naming::{TopoId, TopoRank};
+    use crate::topo::EntityId;
+
+    fn unit_cube() -> Tessellation {
+        let mut tess = Tessellation::new();
+        let id = TopoId::new(EntityId::new(), 0, TopoRank::Face);
+        let p = |x: f64, y: f64, z: f64| Point3::new(x, y, z);
+        let quads = [
+            [p(0., 0., 0.), p(0., 1., 0.), p(1., 1., 0.), p(1., 0., 0.)],
+            [p(0., 0., 1.), p(1., 0., 1.), p(1., 1., 1.), p(0., 1., 1.)],
+            [p(0., 0., 0.), p(1., 0., 0.), p(1., 0., 1.), p(0., 0., 1.)],
+            [p(0., 1., 0.), p(0., 1., 1.), p(1., 1., 1.), p(1., 1., 0.)],
+            [p(0., 0., 0.), p(0., 0., 1.), p(0., 1., 1.), p(0., 1., 0.)],
+            [p(1., 0., 0.), p(1., 1., 0.), p(1., 1., 1.), p(1., 0., 1.)],
+        ];
+        for q in quads {
+            tess.add_triangle(q[0], q[1], q[2], id);
+            tess.add_triangle(q[0], q[2], q[3], id);
+        }
+        tess
+    }
+
+    #[test]
+    fn test_section_cube_through_middle_is_square() {
+        let loops = section(&unit_cube(), Point3::new(0.5, 0.5, 0.5), Vector3::z());
+        assert_eq!(loops.len(), 1);
+        let square = &loops[0];
+        assert_eq!(square.len(), 4, "Collinear points are merged: {:?}", square);
+        assert!((polygon_signed_area(square) - 1.0).abs() < 1e-9, "Outer boundary is CCW with unit area");
+        for p in square {
+            assert!((p[0].abs() - 0.5).abs() < 1e-9 && (p[1].abs() - 0.5).abs() < 1e-9, "Corner {:?}", p);
+        }
+    }
+
+    #[test]
+    fn test_section_missing_the_solid_is_empty() {
+        assert!(section(&unit_cube(), Point3::new(0.0, 0.0, 2.0), Vector3::z()).is_empty());
+        // Diagonal cut through a corner region yields a triangle
+        let loops = section(&unit_cube(), Point3::new(0.8, 0.8, 0.8), Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 3);
+    }
+}
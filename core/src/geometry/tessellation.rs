@@ -1,7 +1,27 @@
 use super::{Point3, Vector3};
-use crate::topo::naming::TopoId;
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::EntityId;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of line segments used to discretize a full circle or ellipse
+pub const DEFAULT_TESSELLATION_SEGMENTS: usize = 64;
+
+/// Grid, in model units, that coordinates snap to before geometry hashing;
+/// coarse enough to absorb f32 round-off in the mesh
+pub const GEOMETRY_HASH_GRID: f64 = 1e-3;
+
+/// Stable id of a solid body: a Solid-rank TopoId in the namespace of the
+/// feature that produced it, registered in the topology manifest
+pub type BodyId = TopoId;
+
+/// A separately addressable solid in the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TessellationBody {
+    pub id: BodyId,
+    /// Face TopoIds making up the body
+    pub faces: Vec<TopoId>,
+}
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Tessellation {
@@ -22,6 +42,41 @@ pub struct Tessellation {
     // This enables the frontend to map from viewport selections back to feature nodes
     #[serde(default)]
     pub feature_id_map: HashMap<String, String>,
+
+    /// TopoIds of reference geometry (datum planes/axes). Their triangles and lines
+    /// are for display and picking only and are excluded from mass properties.
+    #[serde(default)]
+    pub reference_ids: Vec<TopoId>,
+
+    /// Namespaces (TopoId feature_id) of hidden features. Their geometry is
+    /// still here and counts in mass properties; the client skips drawing it.
+    #[serde(default)]
+    pub hidden_features: Vec<crate::topo::EntityId>,
+
+    /// Disjoint solids in the model: one per solid-producing feature, or one
+    /// per region of a per-region extrude. Each face belongs to at most one body.
+    #[serde(default)]
+    pub bodies: Vec<TessellationBody>,
+
+    /// Smoothing group of each triangle, parallel to `triangle_ids`. Vertex normals
+    /// are shared across edges within a group; a group boundary is a crease.
+    /// Triangles added without a group get one of their own (flat shading).
+    #[serde(default)]
+    pub smoothing_group: Vec<u32>,
+
+    /// Center of mass of the solid geometry, filled in for RENDER_UPDATE only
+    /// while the client shows the marker (see `analysis::mesh_center_of_mass`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub center_of_mass: Option<[f64; 3]>,
+
+    /// Texture coordinates, u and v per vertex, parallel to `vertices`; only
+    /// made when the client asks for them (see `generate_uvs`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvs: Option<Vec<f32>>,
+
+    /// Next unused smoothing group; 0 means "not yet known" after deserializing
+    #[serde(skip)]
+    next_smoothing_group: u32,
 }
 
 impl Tessellation {
@@ -29,7 +84,19 @@ impl Tessellation {
         Self::default()
     }
 
+    /// A smoothing group no triangle uses yet
+    pub fn new_smoothing_group(&mut self) -> u32 {
+        if self.next_smoothing_group == 0 {
+            self.next_smoothing_group = self.smoothing_group.iter().max().map_or(0, |g| g + 1);
+        }
+        let group = self.next_smoothing_group;
+        self.next_smoothing_group += 1;
+        group
+    }
+
     pub fn add_triangle(&mut self, p1: Point3, p2: Point3, p3: Point3, id: TopoId) {
+        let group = self.new_smoothing_group();
+        self.smoothing_group.push(group);
         let idx = (self.vertices.len() / 3) as u32;
         
         // Add vertices
@@ -58,6 +125,14 @@ impl Tessellation {
     }
 
     pub fn add_triangle_with_normals(&mut self, p1: Point3, p2: Point3, p3: Point3, n1: Vector3, n2: Vector3, n3: Vector3, id: TopoId) {
+        let group = self.new_smoothing_group();
+        self.add_smooth_triangle([p1, p2, p3], [n1, n2, n3], id, group);
+    }
+
+    /// Add a triangle with vertex normals to smoothing group `group`
+    pub fn add_smooth_triangle(&mut self, [p1, p2, p3]: [Point3; 3], [n1, n2, n3]: [Vector3; 3], id: TopoId, group: u32) {
+        self.next_smoothing_group = self.next_smoothing_group.max(group + 1);
+        self.smoothing_group.push(group);
         let idx = (self.vertices.len() / 3) as u32;
         
         // Add vertices
@@ -118,188 +193,310 @@ impl Tessellation {
 
         self.point_ids.push(id);
     }
-}
 
-/// Triangulate a 2D polygon using ear-clipping algorithm.
-/// Works for both convex and concave simple polygons.
-/// Returns a list of triangle indices (i, j, k) into the input polygon.
-pub fn ear_clip_triangulate(polygon: &[[f64; 2]]) -> Vec<(usize, usize, usize)> {
-    let n = polygon.len();
-    if n < 3 {
-        return vec![];
+    /// Flag a TopoId as reference geometry
+    pub fn mark_reference(&mut self, id: TopoId) {
+        if !self.reference_ids.contains(&id) {
+            self.reference_ids.push(id);
+        }
     }
-    
-    // Create index list
-    let mut indices: Vec<usize> = (0..n).collect();
-    let mut triangles = Vec::new();
-    
-    // Helper: compute signed area of triangle
-    fn signed_area(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
-        (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])
+
+    pub fn is_reference(&self, id: &TopoId) -> bool {
+        self.reference_ids.contains(id)
     }
-    
-    // Determine overall polygon winding (positive = CCW, negative = CW)
-    let mut total_signed_area = 0.0;
-    for i in 0..n {
-        let a = polygon[i];
-        let b = polygon[(i + 1) % n];
-        total_signed_area += (b[0] - a[0]) * (b[1] + a[1]);
+
+    /// Record the faces of the triangles added since `first_triangle` as body `id`
+    pub fn add_body_since(&mut self, id: BodyId, first_triangle: usize) {
+        let mut faces: Vec<TopoId> = Vec::new();
+        for face in self.triangle_ids.iter().skip(first_triangle) {
+            if !faces.contains(face) {
+                faces.push(*face);
+            }
+        }
+        self.bodies.push(TessellationBody { id, faces });
     }
-    let is_ccw = total_signed_area < 0.0;
-    
-    println!("[EAR_CLIP] n={}, total_signed_area={:.6}, is_ccw={}", n, total_signed_area, is_ccw);
-    
-    // Helper: check if point is inside triangle
-    fn point_in_triangle(p: [f64; 2], a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> bool {
-        let sign = |p1: [f64; 2], p2: [f64; 2], p3: [f64; 2]| -> f64 {
-            (p1[0] - p3[0]) * (p2[1] - p3[1]) - (p2[0] - p3[0]) * (p1[1] - p3[1])
-        };
-        let d1 = sign(p, a, b);
-        let d2 = sign(p, b, c);
-        let d3 = sign(p, c, a);
-        let has_neg = (d1 < 0.0) || (d2 < 0.0) || (d3 < 0.0);
-        let has_pos = (d1 > 0.0) || (d2 > 0.0) || (d3 > 0.0);
-        !(has_neg && has_pos)
+
+    pub fn body_ids(&self) -> Vec<BodyId> {
+        self.bodies.iter().map(|b| b.id).collect()
     }
-    
-    // Ear clipping
-    let mut safety = 0;
-    let no_ear_count = 0;
-    while indices.len() > 3 && safety < n * n {
-        safety += 1;
-        let mut found_ear = false;
-        
-        for i in 0..indices.len() {
-            let len = indices.len();
-            let prev = indices[(i + len - 1) % len];
-            let curr = indices[i];
-            let next = indices[(i + 1) % len];
-            
-            let a = polygon[prev];
-            let b = polygon[curr];
-            let c = polygon[next];
-            
-            let area = signed_area(a, b, c);
-            
-            // Check if this is a convex vertex (ear candidate)
-            let is_convex = if is_ccw { area > 0.0 } else { area < 0.0 };
-            
-            if !is_convex {
+
+    /// Body a face belongs to
+    pub fn body_of(&self, face: &TopoId) -> Option<BodyId> {
+        self.bodies.iter().find(|b| b.faces.contains(face)).map(|b| b.id)
+    }
+
+    /// Drop body `id` and its triangles, e.g. when a Boolean consumes it.
+    /// Its vertices are left in place, unreferenced.
+    pub fn remove_body(&mut self, id: &BodyId) {
+        let Some(index) = self.bodies.iter().position(|b| b.id == *id) else { return };
+        let body = self.bodies.remove(index);
+        self.retain_triangles(|face| !body.faces.contains(face));
+    }
+
+    /// Keep only the triangles, lines and points of the feature whose TopoId
+    /// namespace is `namespace`, and the bodies they make up. Vertices are
+    /// left in place, unreferenced.
+    pub fn retain_feature(&mut self, namespace: EntityId) {
+        self.retain_triangles(|face| face.feature_id == namespace);
+
+        let mut kept = self.line_ids.iter().map(|id| id.feature_id == namespace);
+        self.line_indices = self.line_indices.chunks_exact(2)
+            .filter(|_| kept.next().unwrap_or(true))
+            .flatten()
+            .copied()
+            .collect();
+        self.line_ids.retain(|id| id.feature_id == namespace);
+
+        let mut kept = self.point_ids.iter().map(|id| id.feature_id == namespace);
+        self.point_indices.retain(|_| kept.next().unwrap_or(true));
+        self.point_ids.retain(|id| id.feature_id == namespace);
+
+        for body in &mut self.bodies {
+            body.faces.retain(|face| face.feature_id == namespace);
+        }
+        self.bodies.retain(|body| !body.faces.is_empty());
+    }
+
+    /// Drop the triangles whose face fails `keep`
+    fn retain_triangles(&mut self, keep: impl Fn(&TopoId) -> bool) {
+        let keep: Vec<bool> = self.triangle_ids.iter().map(keep).collect();
+        let mut kept = keep.iter();
+        self.indices = self.indices.chunks_exact(3)
+            .filter(|_| *kept.next().unwrap_or(&true))
+            .flatten()
+            .copied()
+            .collect();
+        let mut kept = keep.iter();
+        self.triangle_ids.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.smoothing_group.retain(|_| *kept.next().unwrap_or(&true));
+    }
+
+    /// ASCII STL of the triangles belonging to body `id`, as solid `name`
+    pub fn body_stl(&self, id: &BodyId, name: &str) -> Option<String> {
+        use std::fmt::Write;
+        let body = self.bodies.iter().find(|b| b.id == *id)?;
+        let mut stl = format!("solid {}\n", name);
+        for (t, face) in self.triangle_ids.iter().enumerate() {
+            if !body.faces.contains(face) {
                 continue;
             }
-            
-            // Check if any other vertex is inside this triangle
-            let mut is_ear = true;
-            for j in 0..indices.len() {
-                if j == (i + len - 1) % len || j == i || j == (i + 1) % len {
-                    continue;
-                }
-                let p = polygon[indices[j]];
-                if point_in_triangle(p, a, b, c) {
-                    is_ear = false;
-                    break;
-                }
-            }
-            
-            if is_ear {
-                triangles.push((prev, curr, next));
-                indices.remove(i);
-                found_ear = true;
-                break;
+            let [a, b, c] = [0, 1, 2].map(|k| {
+                let i = self.indices[t * 3 + k] as usize * 3;
+                Point3::new(self.vertices[i] as f64, self.vertices[i + 1] as f64, self.vertices[i + 2] as f64)
+            });
+            let n = (b - a).cross(&(c - a)).try_normalize(0.0).unwrap_or_else(Vector3::zeros);
+            let _ = writeln!(stl, "  facet normal {:e} {:e} {:e}\n    outer loop", n.x, n.y, n.z);
+            for p in [a, b, c] {
+                let _ = writeln!(stl, "      vertex {:e} {:e} {:e}", p.x, p.y, p.z);
             }
+            stl.push_str("    endloop\n  endfacet\n");
         }
-        
-        if !found_ear {
-            // Fallback: try to find any ear (degenerate case)
-            break;
-        }
+        let _ = writeln!(stl, "endsolid {}", name);
+        Some(stl)
     }
-    
-    // Handle remaining triangle
-    if indices.len() == 3 {
-        triangles.push((indices[0], indices[1], indices[2]));
+
+    /// Axis-aligned bounds of the triangles belonging to body `id`
+    pub fn body_bounds(&self, id: &BodyId) -> Option<(Point3, Point3)> {
+        let body = self.bodies.iter().find(|b| b.id == *id)?;
+        let mut bounds: Option<(Point3, Point3)> = None;
+        for (t, face) in self.triangle_ids.iter().enumerate() {
+            if !body.faces.contains(face) {
+                continue;
+            }
+            for &index in &self.indices[t * 3..t * 3 + 3] {
+                let i = index as usize * 3;
+                let p = Point3::new(self.vertices[i] as f64, self.vertices[i + 1] as f64, self.vertices[i + 2] as f64);
+                bounds = Some(match bounds {
+                    Some((min, max)) => (min.inf(&p), max.sup(&p)),
+                    None => (p, p),
+                });
+            }
+        }
+        bounds
     }
-    
-    triangles
-}
 
-/// Triangulate a polygon with holes using earcutr library.
-/// Returns the merged 2D points and triangle indices.
-pub fn triangulate_polygon_with_holes(outer: &[[f64; 2]], holes: &[Vec<[f64; 2]>]) -> (Vec<[f64; 2]>, Vec<(usize, usize, usize)>) {
-    println!("[TRIANGULATE] outer={} pts, holes={}", outer.len(), holes.len());
-    
-    if outer.len() < 3 {
-        return (outer.to_vec(), vec![]);
+    /// Area of the model's silhouette seen along `dir`: the triangles are
+    /// projected onto the plane perpendicular to it and half the sum of their
+    /// unsigned projected areas is taken, since a closed surface covers each
+    /// point of its shadow at least twice. Winding doesn't matter and reference
+    /// geometry is skipped. Overlap is not removed, so this is exact for a
+    /// single convex body and an over-estimate when parts of the model shadow
+    /// each other (non-convex bodies, or several bodies in line). Open sheets
+    /// count half. A zero `dir` gives 0.
+    pub fn projected_area(&self, dir: Vector3) -> f64 {
+        if dir.norm() < 1e-12 {
+            return 0.0;
+        }
+        let dir = dir.normalize();
+        let vertex = |index: u32| {
+            let i = index as usize * 3;
+            Point3::new(self.vertices[i] as f64, self.vertices[i + 1] as f64, self.vertices[i + 2] as f64)
+        };
+        let doubled: f64 = self.indices.chunks_exact(3)
+            .zip(&self.triangle_ids)
+            .filter(|(_, id)| !self.is_reference(id))
+            .map(|(tri, _)| {
+                let (a, b, c) = (vertex(tri[0]), vertex(tri[1]), vertex(tri[2]));
+                (b - a).cross(&(c - a)).dot(&dir).abs()
+            })
+            .sum();
+        // Each triangle's cross product is twice its area, and each point is covered twice
+        doubled / 4.0
     }
-    
-    // Build flattened vertex array and hole indices for earcutr
-    let mut vertices: Vec<f64> = Vec::with_capacity((outer.len() + holes.iter().map(|h| h.len()).sum::<usize>()) * 2);
-    let mut hole_indices: Vec<usize> = Vec::with_capacity(holes.len());
-    
-    // Add outer polygon vertices
-    for pt in outer {
-        vertices.push(pt[0]);
-        vertices.push(pt[1]);
+
+    /// Order-independent hash of the geometry for regression checks, with
+    /// coordinates snapped to `GEOMETRY_HASH_GRID`
+    pub fn geometry_hash(&self) -> u64 {
+        self.geometry_hash_with_grid(GEOMETRY_HASH_GRID)
     }
-    
-    // Add hole vertices
-    for hole in holes {
-        hole_indices.push(vertices.len() / 2); // Index of first vertex of this hole
-        for pt in hole {
-            vertices.push(pt[0]);
-            vertices.push(pt[1]);
+
+    /// Hash of the triangles, lines and points grouped by TopoId, with
+    /// coordinates snapped to multiples of `grid`. Groups are taken in TopoId
+    /// order and primitives are sorted within a group, so neither emission
+    /// order nor vertex sharing matters; normals and smoothing are left out.
+    /// Only integers are hashed, in a fixed byte order, with SHA-1 (UUID v5,
+    /// as for derived TopoIds), so the value is the same on every platform.
+    pub fn geometry_hash_with_grid(&self, grid: f64) -> u64 {
+        type Snapped = [i64; 3];
+        #[derive(Default)]
+        struct Group {
+            triangles: Vec<[Snapped; 3]>,
+            lines: Vec<[Snapped; 2]>,
+            points: Vec<Snapped>,
         }
+
+        let key = |id: &TopoId| (id.feature_id, id.local_id, id.rank);
+        let vertex = |index: u32| -> Snapped {
+            let i = index as usize * 3;
+            [0, 1, 2].map(|k| (self.vertices[i + k] as f64 / grid).round() as i64)
+        };
+
+        let mut groups: BTreeMap<(EntityId, u64, TopoRank), Group> = BTreeMap::new();
+        for (t, id) in self.triangle_ids.iter().enumerate() {
+            let mut triangle = [0, 1, 2].map(|k| vertex(self.indices[t * 3 + k]));
+            // Start at the smallest corner, keeping the winding
+            let first = (0..3).min_by_key(|&k| triangle[k]).unwrap_or(0);
+            triangle.rotate_left(first);
+            groups.entry(key(id)).or_default().triangles.push(triangle);
+        }
+        for (l, id) in self.line_ids.iter().enumerate() {
+            let mut line = [vertex(self.line_indices[l * 2]), vertex(self.line_indices[l * 2 + 1])];
+            line.sort();
+            groups.entry(key(id)).or_default().lines.push(line);
+        }
+        for (p, id) in self.point_ids.iter().enumerate() {
+            groups.entry(key(id)).or_default().points.push(vertex(self.point_indices[p]));
+        }
+
+        let mut bytes = Vec::new();
+        for ((feature_id, local_id, rank), mut group) in groups {
+            bytes.extend_from_slice(feature_id.0.as_bytes());
+            bytes.extend_from_slice(&local_id.to_le_bytes());
+            bytes.push(rank as u8);
+            group.triangles.sort();
+            group.lines.sort();
+            group.points.sort();
+            let sections: [&[Snapped]; 3] = [group.triangles.as_flattened(), group.lines.as_flattened(), &group.points];
+            for section in sections {
+                bytes.extend_from_slice(&(section.len() as u64).to_le_bytes());
+                for coordinate in section.iter().flatten() {
+                    bytes.extend_from_slice(&coordinate.to_le_bytes());
+                }
+            }
+        }
+
+        let digest = uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, &bytes);
+        let mut first = [0u8; 8];
+        first.copy_from_slice(&digest.as_bytes()[..8]);
+        u64::from_be_bytes(first)
     }
-    
-    // Run earcutr triangulation
-    let indices = earcutr::earcut(&vertices, &hole_indices, 2).unwrap_or_default();
-    
-    // Convert flat indices to triangle tuples
-    let triangles: Vec<(usize, usize, usize)> = indices
-        .chunks(3)
-        .map(|chunk| (chunk[0], chunk[1], chunk[2]))
-        .collect();
-    
-    // Build merged points array for compatibility with existing code
-    let merged_points: Vec<[f64; 2]> = vertices
-        .chunks(2)
-        .map(|chunk| [chunk[0], chunk[1]])
-        .collect();
-    
-    println!("[TRIANGULATE] earcutr produced {} triangles", triangles.len());
-    (merged_points, triangles)
 }
 
-fn is_visible_segment(p1: &[f64; 2], p2: &[f64; 2], poly: &[[f64; 2]]) -> bool {
-    let n = poly.len();
-    for i in 0..n {
-        let a = poly[i];
-        let b = poly[(i+1)%n];
-        
-        // Ignore edges incident to p1 or p2
-        if (p1[0]-a[0]).abs() < 1e-6 && (p1[1]-a[1]).abs() < 1e-6 { continue; }
-        if (p1[0]-b[0]).abs() < 1e-6 && (p1[1]-b[1]).abs() < 1e-6 { continue; }
-        if (p2[0]-a[0]).abs() < 1e-6 && (p2[1]-a[1]).abs() < 1e-6 { continue; }
-        if (p2[0]-b[0]).abs() < 1e-6 && (p2[1]-b[1]).abs() < 1e-6 { continue; }
-        
-        if segments_intersect(*p1, *p2, a, b) {
-            return false;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(tessellation: &mut Tessellation, face: TopoId, z: f64) {
+        let p = |x: f64, y: f64| Point3::new(x, y, z);
+        tessellation.add_triangle(p(0.0, 0.0), p(1.0, 0.0), p(1.0, 1.0), face);
+        tessellation.add_triangle(p(0.0, 0.0), p(1.0, 1.0), p(0.0, 1.0), face);
+    }
+
+    #[test]
+    fn test_geometry_hash_ignores_order_and_round_off() {
+        let feature = EntityId::new();
+        let bottom = TopoId::new(feature, 1, TopoRank::Face);
+        let top = TopoId::new(feature, 2, TopoRank::Face);
+
+        let mut a = Tessellation::new();
+        square(&mut a, bottom, 0.0);
+        square(&mut a, top, 1.0);
+
+        // Faces emitted the other way round, corners rotated, a hair of noise
+        let mut b = Tessellation::new();
+        square(&mut b, top, 1.0 + 1e-5);
+        let p = |x: f64, y: f64| Point3::new(x, y, 0.0);
+        b.add_triangle(p(1.0, 1.0), p(0.0, 1.0), p(0.0, 0.0), bottom);
+        b.add_triangle(p(1.0, 0.0), p(1.0, 1.0), p(0.0, 0.0), bottom);
+        assert_eq!(a.geometry_hash(), b.geometry_hash());
+
+        // Moving a face, renaming it or flipping a triangle changes the hash
+        let mut moved = Tessellation::new();
+        square(&mut moved, bottom, 0.0);
+        square(&mut moved, top, 1.01);
+        assert_ne!(a.geometry_hash(), moved.geometry_hash());
+
+        let mut renamed = Tessellation::new();
+        square(&mut renamed, bottom, 0.0);
+        square(&mut renamed, TopoId::new(feature, 3, TopoRank::Face), 1.0);
+        assert_ne!(a.geometry_hash(), renamed.geometry_hash());
+
+        let mut flipped = Tessellation::new();
+        square(&mut flipped, top, 1.0);
+        flipped.add_triangle(p(0.0, 0.0), p(1.0, 1.0), p(1.0, 0.0), bottom);
+        flipped.add_triangle(p(0.0, 0.0), p(1.0, 1.0), p(0.0, 1.0), bottom);
+        assert_ne!(a.geometry_hash(), flipped.geometry_hash());
+
+        // The grid is configurable: at a coarse enough grid the move vanishes
+        assert_eq!(a.geometry_hash_with_grid(0.1), moved.geometry_hash_with_grid(0.1));
+    }
+
+    #[test]
+    fn test_projected_area_of_unit_cube() {
+        let feature = EntityId::new();
+        let mut cube = Tessellation::new();
+        let p = |x: f64, y: f64, z: f64| Point3::new(x, y, z);
+        let quads = [
+            [p(0., 0., 0.), p(0., 1., 0.), p(1., 1., 0.), p(1., 0., 0.)],
+            [p(0., 0., 1.), p(1., 0., 1.), p(1., 1., 1.), p(0., 1., 1.)],
+            [p(0., 0., 0.), p(1., 0., 0.), p(1., 0., 1.), p(0., 0., 1.)],
+            [p(0., 1., 0.), p(0., 1., 1.), p(1., 1., 1.), p(1., 1., 0.)],
+            [p(0., 0., 0.), p(0., 0., 1.), p(0., 1., 1.), p(0., 1., 0.)],
+            [p(1., 0., 0.), p(1., 1., 0.), p(1., 1., 1.), p(1., 0., 1.)],
+        ];
+        for (n, q) in quads.iter().enumerate() {
+            let face = TopoId::new(feature, n as u64, TopoRank::Face);
+            cube.add_triangle(q[0], q[1], q[2], face);
+            cube.add_triangle(q[0], q[2], q[3], face);
         }
+
+        assert!((cube.projected_area(Vector3::z()) - 1.0).abs() < 1e-9);
+        assert!((cube.projected_area(-Vector3::x() * 5.0) - 1.0).abs() < 1e-9, "Length and sense of the direction don't matter");
+        // Seen along a body diagonal the silhouette is a hexagon of area √3
+        assert!((cube.projected_area(Vector3::new(1.0, 1.0, 1.0)) - 3f64.sqrt()).abs() < 1e-6);
+        assert_eq!(cube.projected_area(Vector3::zeros()), 0.0);
     }
-    true
-}
 
-fn segments_intersect(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> bool {
-    fn ccw(p1: [f64; 2], p2: [f64; 2], p3: [f64; 2]) -> f64 {
-        (p2[0]-p1[0])*(p3[1]-p1[1]) - (p2[1]-p1[1])*(p3[0]-p1[0])
+    #[test]
+    fn test_geometry_hash_is_a_fixed_value() {
+        // Pinned so a change in byte layout or hashing, or a platform
+        // difference, shows up here rather than as a silent golden mismatch
+        let feature = EntityId(uuid::Uuid::from_u128(1));
+        let mut t = Tessellation::new();
+        square(&mut t, TopoId::new(feature, 1, TopoRank::Face), 0.0);
+        t.add_line(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), TopoId::new(feature, 2, TopoRank::Edge));
+        t.add_point(Point3::new(0.0, 0.0, 0.0), TopoId::new(feature, 3, TopoRank::Vertex));
+        assert_eq!(t.geometry_hash(), 8656182147918224637);
     }
-    
-    let d1 = ccw(c, d, a);
-    let d2 = ccw(c, d, b);
-    let d3 = ccw(a, b, c);
-    let d4 = ccw(a, b, d);
-    
-    // Strict intersection
-    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) &&
-    ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
 }
@@ -0,0 +1,359 @@
+//! Triangulation of planar polygons with holes
+//!
+//! Ear clipping after bridging each hole into the outer boundary, following
+//! Eberly's "Triangulation by Ear Clipping". Planar caps built outside the
+//! kernel (such as the patches left by face deletion) use it, so it has to
+//! cope with the boundaries tessellations produce: repeated vertices,
+//! collinear runs and either winding. Inputs it cannot handle, such as
+//! self-intersecting boundaries or holes outside the outer loop, give an
+//! error naming a vertex rather than a panic or a partial cap.
+
+use thiserror::Error;
+
+use super::utils_2d::polygon_signed_area;
+
+/// Vertices closer than this fraction of the polygon's size are one vertex
+const DUPLICATE_TOLERANCE: f64 = 1e-9;
+/// Corners whose doubled area is below this fraction of the size squared are straight
+const COLLINEAR_TOLERANCE: f64 = 1e-12;
+
+/// Why a polygon could not be triangulated
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Cannot triangulate at vertex {vertex}: {reason}")]
+pub struct TriangulateError {
+    /// Index of the offending vertex, counting the outer loop then each hole in order
+    pub vertex: usize,
+    pub reason: String,
+}
+
+impl TriangulateError {
+    fn new(vertex: usize, reason: &str) -> Self {
+        TriangulateError { vertex, reason: reason.to_string() }
+    }
+}
+
+/// Triangulate the region inside `outer` and outside every hole.
+///
+/// Triangles index into the outer loop followed by each hole in turn, as if
+/// the loops were concatenated, and are counter-clockwise whatever the loops'
+/// winding. Repeated and collinear vertices are dropped before clipping, so
+/// they appear in no triangle; a hole enclosing no area is ignored. The loops
+/// must not cross each other or themselves.
+pub fn earcut(outer: &[[f64; 2]], holes: &[Vec<[f64; 2]>]) -> Result<Vec<[usize; 3]>, TriangulateError> {
+    let points: Vec<[f64; 2]> = outer.iter().chain(holes.iter().flatten()).copied().collect();
+    if let Some(bad) = points.iter().position(|p| !p[0].is_finite() || !p[1].is_finite()) {
+        return Err(TriangulateError::new(bad, "coordinate is not finite"));
+    }
+    let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for p in &points {
+        for k in 0..2 {
+            min[k] = min[k].min(p[k]);
+            max[k] = max[k].max(p[k]);
+        }
+    }
+    let size = (max[0] - min[0]).max(max[1] - min[1]);
+    let tolerances = Tolerances { duplicate: size * DUPLICATE_TOLERANCE, area: size * size * COLLINEAR_TOLERANCE };
+    if outer.len() < 3 || size <= 0.0 {
+        return Err(TriangulateError::new(0, "outer boundary encloses no area"));
+    }
+
+    let mut polygon = clean_loop(&points, 0..outer.len(), &tolerances);
+    if polygon.len() < 3 || loop_area(&points, &polygon).abs() <= tolerances.area {
+        return Err(TriangulateError::new(0, "outer boundary encloses no area"));
+    }
+    if loop_area(&points, &polygon) < 0.0 {
+        polygon.reverse();
+    }
+
+    let mut start = outer.len();
+    let mut cleaned_holes = Vec::new();
+    for hole in holes {
+        let mut ring = clean_loop(&points, start..start + hole.len(), &tolerances);
+        start += hole.len();
+        if ring.len() < 3 || loop_area(&points, &ring).abs() <= tolerances.area {
+            continue;
+        }
+        if loop_area(&points, &ring) > 0.0 {
+            ring.reverse();
+        }
+        cleaned_holes.push(ring);
+    }
+
+    // Bridge the holes in from the right, so each bridge only crosses holes already joined
+    let rightmost = |ring: &[usize]| (0..ring.len()).max_by(|&a, &b| points[ring[a]][0].total_cmp(&points[ring[b]][0])).unwrap_or(0);
+    cleaned_holes.sort_by(|a, b| points[b[rightmost(b)]][0].total_cmp(&points[a[rightmost(a)]][0]));
+    for hole in cleaned_holes {
+        let m = rightmost(&hole);
+        let k = bridge_vertex(&points, &polygon, points[hole[m]], &tolerances)
+            .ok_or_else(|| TriangulateError::new(hole[m], "hole is not inside the outer boundary"))?;
+        let spliced: Vec<usize> = polygon[..=k].iter()
+            .chain(hole[m..].iter())
+            .chain(hole[..=m].iter())
+            .chain(polygon[k..].iter())
+            .copied()
+            .collect();
+        polygon = spliced;
+    }
+
+    clip_ears(&points, polygon, &tolerances)
+}
+
+struct Tolerances {
+    duplicate: f64,
+    area: f64,
+}
+
+/// Twice the signed area of triangle `abc`, positive when counter-clockwise
+fn orient(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn same_point(a: [f64; 2], b: [f64; 2], tolerances: &Tolerances) -> bool {
+    (a[0] - b[0]).abs() <= tolerances.duplicate && (a[1] - b[1]).abs() <= tolerances.duplicate
+}
+
+fn loop_area(points: &[[f64; 2]], ring: &[usize]) -> f64 {
+    let corners: Vec<[f64; 2]> = ring.iter().map(|&i| points[i]).collect();
+    polygon_signed_area(&corners)
+}
+
+/// Indices of one loop without repeated or collinear vertices
+fn clean_loop(points: &[[f64; 2]], range: std::ops::Range<usize>, tolerances: &Tolerances) -> Vec<usize> {
+    let mut ring: Vec<usize> = Vec::with_capacity(range.len());
+    for i in range {
+        if ring.last().is_none_or(|&last| !same_point(points[last], points[i], tolerances)) {
+            ring.push(i);
+        }
+    }
+    while ring.len() > 1 && same_point(points[ring[0]], points[ring[ring.len() - 1]], tolerances) {
+        ring.pop();
+    }
+    // Dropping a vertex can straighten its neighbours, so go round until nothing changes
+    let mut i = 0;
+    let mut unchanged = 0;
+    while ring.len() >= 3 && unchanged < ring.len() {
+        let n = ring.len();
+        let (a, b, c) = (points[ring[(i + n - 1) % n]], points[ring[i % n]], points[ring[(i + 1) % n]]);
+        if orient(a, b, c).abs() <= tolerances.area {
+            ring.remove(i % n);
+            unchanged = 0;
+        } else {
+            i = (i + 1) % n;
+            unchanged += 1;
+        }
+    }
+    ring
+}
+
+/// Position in `polygon` (counter-clockwise) to join a hole's rightmost
+/// vertex `m` to: the nearest boundary crossed by a ray from `m` towards +x,
+/// moved to a reflex vertex if one would block the straight bridge
+fn bridge_vertex(points: &[[f64; 2]], polygon: &[usize], m: [f64; 2], tolerances: &Tolerances) -> Option<usize> {
+    let n = polygon.len();
+    let mut hit: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (points[polygon[i]], points[polygon[(i + 1) % n]]);
+        if (a[1] - b[1]).abs() <= tolerances.duplicate || (a[1] - m[1]) * (b[1] - m[1]) > 0.0 {
+            continue;
+        }
+        let x = a[0] + (m[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+        if x >= m[0] - tolerances.duplicate && hit.is_none_or(|(best, _)| x < best) {
+            // The end further right is always visible from m unless a reflex vertex is in the way
+            let end = if a[0] > b[0] { i } else { (i + 1) % n };
+            hit = Some((x, end));
+        }
+    }
+    let (x, end) = hit?;
+    let (i, p) = ([x, m[1]], points[polygon[end]]);
+
+    // A reflex vertex inside triangle m-i-p is nearer; take the one at the smallest angle from the ray
+    let mut best = end;
+    let mut best_key = (f64::INFINITY, f64::INFINITY);
+    let (lo, hi) = if orient(m, i, p) >= 0.0 { (i, p) } else { (p, i) };
+    for k in 0..n {
+        let v = points[polygon[k]];
+        if same_point(v, p, tolerances) || !is_reflex(points, polygon, k) {
+            continue;
+        }
+        if orient(m, lo, v) > 0.0 && orient(lo, hi, v) > 0.0 && orient(hi, m, v) > 0.0 {
+            let (dx, dy) = (v[0] - m[0], v[1] - m[1]);
+            let key = (dy.abs().atan2(dx), dx * dx + dy * dy);
+            if key < best_key {
+                best_key = key;
+                best = k;
+            }
+        }
+    }
+
+    // Earlier bridges leave a vertex at the same place twice; join the copy whose corner faces m
+    let target = points[polygon[best]];
+    (0..n)
+        .filter(|&k| same_point(points[polygon[k]], target, tolerances))
+        .find(|&k| locally_inside(points, polygon, k, m))
+        .or(Some(best))
+}
+
+fn is_reflex(points: &[[f64; 2]], polygon: &[usize], k: usize) -> bool {
+    let n = polygon.len();
+    orient(points[polygon[(k + n - 1) % n]], points[polygon[k]], points[polygon[(k + 1) % n]]) < 0.0
+}
+
+/// Whether `m` is inside the polygon's corner at position `k`
+fn locally_inside(points: &[[f64; 2]], polygon: &[usize], k: usize, m: [f64; 2]) -> bool {
+    let n = polygon.len();
+    let (prev, v, next) = (points[polygon[(k + n - 1) % n]], points[polygon[k]], points[polygon[(k + 1) % n]]);
+    if is_reflex(points, polygon, k) {
+        orient(prev, v, m) > 0.0 || orient(v, next, m) > 0.0
+    } else {
+        orient(prev, v, m) > 0.0 && orient(v, next, m) > 0.0
+    }
+}
+
+/// Clip ears off the counter-clockwise `polygon` until one triangle is left
+fn clip_ears(points: &[[f64; 2]], mut polygon: Vec<usize>, tolerances: &Tolerances) -> Result<Vec<[usize; 3]>, TriangulateError> {
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let corner = |i: usize| (points[polygon[(i + n - 1) % n]], points[polygon[i]], points[polygon[(i + 1) % n]]);
+
+        // Bridges and clipping can leave repeated or straight corners; they hold no area
+        if let Some(flat) = (0..n).find(|&i| {
+            let (a, b, c) = corner(i);
+            same_point(a, b, tolerances) || orient(a, b, c).abs() <= tolerances.area
+        }) {
+            polygon.remove(flat);
+            continue;
+        }
+
+        let reflex: Vec<usize> = (0..n).filter(|&k| is_reflex(points, &polygon, k)).collect();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = corner(i);
+            orient(a, b, c) > 0.0
+                && reflex.iter().all(|&k| {
+                    let p = points[polygon[k]];
+                    same_point(p, a, tolerances) || same_point(p, b, tolerances) || same_point(p, c, tolerances)
+                        || orient(a, b, p) < -tolerances.area || orient(b, c, p) < -tolerances.area || orient(c, a, p) < -tolerances.area
+                })
+        });
+        let Some(i) = ear else {
+            let stuck = reflex.first().map_or(polygon[0], |&k| polygon[k]);
+            return Err(TriangulateError::new(stuck, "no ear to clip; the boundary may cross itself"));
+        };
+        triangles.push([polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]]);
+        polygon.remove(i);
+    }
+    if let [a, b, c] = polygon[..] {
+        if orient(points[a], points[b], points[c]) > tolerances.area {
+            triangles.push([a, b, c]);
+        }
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    /// Small deterministic generator so failures reproduce
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f64 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    /// Star-shaped loop around `center` with radii in `radii`, so simple but usually with reflex corners
+    fn star(rng: &mut Lcg, center: [f64; 2], radii: (f64, f64), corners: usize) -> Vec<[f64; 2]> {
+        (0..corners)
+            .map(|i| {
+                let angle = (i as f64 + (rng.next() - 0.5) * 0.5) * TAU / corners as f64;
+                let r = radii.0 + rng.next() * (radii.1 - radii.0);
+                [center[0] + r * angle.cos(), center[1] + r * angle.sin()]
+            })
+            .collect()
+    }
+
+    fn triangulated_area(outer: &[[f64; 2]], holes: &[Vec<[f64; 2]>], triangles: &[[usize; 3]]) -> f64 {
+        let points: Vec<[f64; 2]> = outer.iter().chain(holes.iter().flatten()).copied().collect();
+        triangles.iter()
+            .map(|t| {
+                let area = orient(points[t[0]], points[t[1]], points[t[2]]) / 2.0;
+                assert!(area > 0.0, "Triangle {:?} is not counter-clockwise", t);
+                area
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_random_polygons_with_holes_keep_their_area() {
+        let mut rng = Lcg(42);
+        for case in 0..200 {
+            // Outer radii of at least 10 keep a disc of radius 8 clear for the holes
+            let mut outer = star(&mut rng, [0.0, 0.0], (10.0, 20.0), 8 + case % 24);
+            let hole_count = case % 3;
+            let mut holes: Vec<Vec<[f64; 2]>> = [[-3.5, 0.0], [3.5, 0.0]][..hole_count].iter()
+                .map(|&c| star(&mut rng, c, (1.0, 2.5), 5 + case % 7))
+                .collect();
+            // Mix in either winding, a repeated vertex and a collinear midpoint
+            if case % 2 == 1 {
+                outer.reverse();
+            }
+            if case % 5 == 0 {
+                outer.insert(3, outer[3]);
+                let mid = [(outer[0][0] + outer[1][0]) / 2.0, (outer[0][1] + outer[1][1]) / 2.0];
+                outer.insert(1, mid);
+            }
+            if let Some(hole) = holes.first_mut() {
+                if case % 4 == 0 {
+                    hole.reverse();
+                }
+            }
+
+            let triangles = earcut(&outer, &holes).unwrap_or_else(|e| panic!("Case {}: {}", case, e));
+            let expected = polygon_signed_area(&outer).abs() - holes.iter().map(|h| polygon_signed_area(h).abs()).sum::<f64>();
+            let area = triangulated_area(&outer, &holes, &triangles);
+            assert!((area - expected).abs() < 1e-9 * expected, "Case {}: {} triangulated, {} expected", case, area, expected);
+        }
+    }
+
+    #[test]
+    fn test_concave_outline_with_two_holes() {
+        // U shape with a square hole in each arm; the hole vertices line up with the notch
+        let outer = vec![[0.0, 0.0], [9.0, 0.0], [9.0, 9.0], [6.0, 9.0], [6.0, 3.0], [3.0, 3.0], [3.0, 9.0], [0.0, 9.0]];
+        let square = |x: f64| vec![[x, 5.0], [x + 1.0, 5.0], [x + 1.0, 6.0], [x, 6.0]];
+        let holes = vec![square(1.0), square(7.0)];
+        let triangles = earcut(&outer, &holes).unwrap();
+        assert!((triangulated_area(&outer, &holes, &triangles) - 61.0).abs() < 1e-9);
+        // Every vertex is used: 8 + 4 + 4 corners and two bridges per hole
+        assert_eq!(triangles.len(), 8 + 2 * 4 + 2 * 2 - 2);
+    }
+
+    #[test]
+    fn test_degenerate_input_is_an_error_not_a_panic() {
+        assert!(earcut(&[], &[]).is_err());
+        assert!(earcut(&[[0.0, 0.0], [1.0, 0.0]], &[]).is_err());
+        assert!(earcut(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [1.0, 0.0]], &[]).is_err(), "Collinear");
+        assert!(earcut(&[[0.0, 0.0], [0.0, 0.0], [0.0, 0.0]], &[]).is_err(), "Repeated point");
+
+        let error = earcut(&[[0.0, 0.0], [1.0, 0.0], [f64::NAN, 1.0], [0.0, 1.0]], &[]).unwrap_err();
+        assert_eq!(error.vertex, 2);
+
+        let square = [[0.0, 0.0], [4.0, 0.0], [4.0, 4.0], [0.0, 4.0]];
+        let outside = vec![[10.0, 1.0], [11.0, 1.0], [11.0, 2.0]];
+        assert_eq!(earcut(&square, &[outside]).unwrap_err().vertex, 5, "Rightmost vertex of the stray hole");
+
+        // A bow tie crosses itself; whatever is reported, it is an index into the input
+        let bow_tie = [[0.0, 0.0], [4.0, 4.0], [4.0, 0.0], [0.0, 4.0], [2.0, 6.0]];
+        if let Err(error) = earcut(&bow_tie, &[]) {
+            assert!(error.vertex < bow_tie.len());
+        }
+
+        // A hole with no area takes nothing away
+        let holes = vec![vec![[1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]];
+        let triangles = earcut(&square, &holes).unwrap();
+        assert!((triangulated_area(&square, &holes, &triangles) - 16.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,310 @@
+//! Texture coordinates for display tessellations
+//!
+//! UVs are derived per face from its analytic surface in the topology
+//! manifest: planes project into the plane's own frame, cylinders and cones
+//! unwrap to (fraction of a turn, distance along the axis), and every other
+//! face, Mesh-classified or unknown, projects onto the coordinate plane facing
+//! its dominant normal. Apart from the angle, coordinates are in model units.
+
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+
+use super::{Point3, Tessellation, Vector3};
+use crate::topo::naming::TopoId;
+use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+
+/// Angles within this of a full turn count as zero, so the seam of a closed
+/// face sits at exactly u = 0 and u = 1
+const ANGLE_TOLERANCE: f64 = 1e-6;
+
+/// Triangles giving a shared vertex UVs further apart than this split it in two
+const UV_TOLERANCE: f32 = 1e-6;
+
+/// How one face maps to UV space
+enum Projection {
+    Planar { origin: Point3, u: Vector3, v: Vector3 },
+    /// Angle about `axis` from `reference`, and distance along `axis` from `origin`
+    Angular { origin: Point3, axis: Vector3, reference: Vector3 },
+}
+
+impl Tessellation {
+    /// Fill `uvs` from the faces' surfaces in `manifest`. A vertex shared by
+    /// triangles that disagree on its UV, as on the seam of a closed
+    /// cylinder, is duplicated. Line and point vertices get (0, 0).
+    /// Decimating rebuilds the mesh without UVs, so decimate first.
+    pub fn generate_uvs(&mut self, manifest: &HashMap<TopoId, KernelEntity>) {
+        let mut faces: Vec<(TopoId, Vec<usize>)> = Vec::new();
+        let mut face_index: HashMap<TopoId, usize> = HashMap::new();
+        for (t, id) in self.triangle_ids.iter().enumerate() {
+            let index = *face_index.entry(*id).or_insert_with(|| {
+                faces.push((*id, Vec::new()));
+                faces.len() - 1
+            });
+            faces[index].1.push(t);
+        }
+
+        let mut uvs: Vec<Option<[f32; 2]>> = vec![None; self.vertices.len() / 3];
+        // Extra copies of a vertex made for other UVs, by original index
+        let mut copies: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (id, triangles) in &faces {
+            let geometry = manifest.get(id).map(|entity| &entity.geometry);
+            let corners = self.face_uvs(geometry, triangles);
+            for (&t, triangle) in triangles.iter().zip(corners) {
+                for (k, [u, v]) in triangle.into_iter().enumerate() {
+                    let uv = [u as f32, v as f32];
+                    let slot = t * 3 + k;
+                    let vertex = self.indices[slot] as usize;
+                    let matches = |other: Option<[f32; 2]>| {
+                        other.is_some_and(|o| (o[0] - uv[0]).abs() <= UV_TOLERANCE && (o[1] - uv[1]).abs() <= UV_TOLERANCE)
+                    };
+                    if uvs[vertex].is_none() {
+                        uvs[vertex] = Some(uv);
+                    } else if !matches(uvs[vertex]) {
+                        let copy = copies.get(&vertex).and_then(|c| c.iter().copied().find(|&c| matches(uvs[c])));
+                        let copy = copy.unwrap_or_else(|| {
+                            let copy = self.duplicate_vertex(vertex);
+                            uvs.push(Some(uv));
+                            copies.entry(vertex).or_default().push(copy);
+                            copy
+                        });
+                        self.indices[slot] = copy as u32;
+                    }
+                }
+            }
+        }
+        self.uvs = Some(uvs.into_iter().flat_map(|uv| uv.unwrap_or([0.0; 2])).collect());
+    }
+
+    /// Append a copy of vertex `index` (position and normal); returns its index
+    fn duplicate_vertex(&mut self, index: usize) -> usize {
+        let copy = self.vertices.len() / 3;
+        self.vertices.extend_from_within(index * 3..index * 3 + 3);
+        if self.normals.len() >= index * 3 + 3 {
+            self.normals.extend_from_within(index * 3..index * 3 + 3);
+        }
+        copy
+    }
+
+    fn corner(&self, t: usize, k: usize) -> Point3 {
+        let i = self.indices[t * 3 + k] as usize * 3;
+        Point3::new(self.vertices[i] as f64, self.vertices[i + 1] as f64, self.vertices[i + 2] as f64)
+    }
+
+    /// UVs of the corners of `triangles`, which make up one face
+    fn face_uvs(&self, geometry: Option<&AnalyticGeometry>, triangles: &[usize]) -> Vec<[[f64; 2]; 3]> {
+        let corners: Vec<[Point3; 3]> = triangles.iter().map(|&t| [0, 1, 2].map(|k| self.corner(t, k))).collect();
+        match projection(geometry, &corners) {
+            Projection::Planar { origin, u, v } => corners
+                .iter()
+                .map(|triangle| triangle.map(|p| [(p - origin).dot(&u), (p - origin).dot(&v)]))
+                .collect(),
+            Projection::Angular { origin, axis, reference } => unwrap(&corners, origin, axis, reference),
+        }
+    }
+}
+
+fn projection(geometry: Option<&AnalyticGeometry>, corners: &[[Point3; 3]]) -> Projection {
+    let angular = |origin: [f64; 3], axis: [f64; 3]| {
+        let axis = Vector3::from(axis).normalize();
+        Projection::Angular { origin: Point3::from(origin), axis, reference: perpendicular(&axis) }
+    };
+    match geometry {
+        Some(AnalyticGeometry::Plane { origin, normal }) => {
+            let normal = Vector3::from(*normal).normalize();
+            let u = perpendicular(&normal);
+            Projection::Planar { origin: Point3::from(*origin), u, v: normal.cross(&u) }
+        }
+        Some(AnalyticGeometry::Cylinder { axis_start, axis_dir, .. }) => angular(*axis_start, *axis_dir),
+        Some(AnalyticGeometry::Cone { apex, axis, .. }) => angular(*apex, *axis),
+        _ => {
+            // Onto the coordinate plane facing the area-weighted normal
+            let normal: Vector3 = corners.iter().map(|[a, b, c]| (b - a).cross(&(c - a))).sum();
+            let dominant = normal.iamax();
+            let u = Vector3::ith((dominant + 1) % 3, 1.0);
+            let v = Vector3::ith((dominant + 2) % 3, 1.0);
+            Projection::Planar { origin: Point3::origin(), u, v }
+        }
+    }
+}
+
+/// Unit vector perpendicular to unit `n`, built from the coordinate axis
+/// furthest from it so the frame is the same on every run
+fn perpendicular(n: &Vector3) -> Vector3 {
+    let axis = Vector3::ith(n.iamin(), 1.0);
+    (axis - n * n.dot(&axis)).normalize()
+}
+
+/// (fraction of a turn, distance along the axis) of each corner. The turn
+/// starts after the widest angular gap between the face's vertices: outside
+/// a partial face, or at a column of vertices on a closed one, where the
+/// triangles closing the loop take u = 1 instead of 0.
+fn unwrap(corners: &[[Point3; 3]], origin: Point3, axis: Vector3, reference: Vector3) -> Vec<[[f64; 2]; 3]> {
+    let side = axis.cross(&reference);
+    let scale = corners.iter().flatten().map(|p| (p - origin).norm()).fold(1.0, f64::max);
+    // Angle in [0, TAU), or None on the axis (a cone's apex)
+    let angle = |p: &Point3| {
+        let d = p - origin;
+        let radial = d - axis * d.dot(&axis);
+        (radial.norm() > ANGLE_TOLERANCE * scale).then(|| d.dot(&side).atan2(d.dot(&reference)).rem_euclid(TAU))
+    };
+
+    let mut angles: Vec<f64> = corners.iter().flatten().filter_map(angle).collect();
+    angles.sort_by(f64::total_cmp);
+    angles.dedup_by(|a, b| *a - *b < ANGLE_TOLERANCE);
+    let mut start = angles.first().copied().unwrap_or(0.0);
+    let mut widest = angles.first().zip(angles.last()).map_or(0.0, |(first, last)| first + TAU - last);
+    for pair in angles.windows(2) {
+        if pair[1] - pair[0] > widest {
+            widest = pair[1] - pair[0];
+            start = pair[1];
+        }
+    }
+
+    corners
+        .iter()
+        .map(|triangle| {
+            let mut turns = triangle.map(|p| {
+                angle(&p).map(|a| {
+                    let a = (a - start).rem_euclid(TAU);
+                    if a > TAU - ANGLE_TOLERANCE { 0.0 } else { a }
+                })
+            });
+            // A triangle closing the loop spans the seam; lift its start side
+            let known: Vec<f64> = turns.iter().flatten().copied().collect();
+            let (min, max) = known.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &a| (lo.min(a), hi.max(a)));
+            if max - min > PI {
+                for a in turns.iter_mut().flatten() {
+                    if *a < PI {
+                        *a += TAU;
+                    }
+                }
+            }
+            // The apex takes the middle of the other corners
+            let middle = turns.iter().flatten().sum::<f64>() / turns.iter().flatten().count().max(1) as f64;
+            let mut uv = [[0.0; 2]; 3];
+            for (k, p) in triangle.iter().enumerate() {
+                uv[k] = [turns[k].unwrap_or(middle) / TAU, (p - origin).dot(&axis)];
+            }
+            uv
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::ast::{Call, Expression, Program, Statement, Value};
+    use crate::evaluator::runtime::Runtime;
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+    use crate::topo::IdGenerator;
+
+    fn rectangle(corners: [[f64; 2]; 4]) -> String {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        for i in 0..4 {
+            sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+        }
+        serde_json::to_string(&sketch).unwrap()
+    }
+
+    fn evaluate(function: &str, args: Vec<Value>) -> crate::evaluator::runtime::EvaluationResult {
+        let prog = Program {
+            statements: vec![Statement::Expression(Expression::Call(Call {
+                function: function.into(),
+                args: args.into_iter().map(Expression::Value).collect(),
+            }))],
+        };
+        Runtime::new().evaluate(&prog, &IdGenerator::new("uv_test")).unwrap()
+    }
+
+    /// Position and UV of every corner of `face`'s triangles
+    fn face_corners(tessellation: &Tessellation, face: &TopoId) -> Vec<(Point3, [f32; 2])> {
+        let uvs = tessellation.uvs.as_ref().unwrap();
+        let mut corners = Vec::new();
+        for (t, id) in tessellation.triangle_ids.iter().enumerate() {
+            if id == face {
+                for k in 0..3 {
+                    let i = tessellation.indices[t * 3 + k] as usize;
+                    corners.push((tessellation.corner(t, k), [uvs[i * 2], uvs[i * 2 + 1]]));
+                }
+            }
+        }
+        corners
+    }
+
+    fn extent(corners: &[(Point3, [f32; 2])], k: usize) -> (f32, f32) {
+        corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), (_, uv)| (lo.min(uv[k]), hi.max(uv[k])))
+    }
+
+    #[test]
+    fn test_cylinder_unwraps_with_a_single_seam() {
+        // A 2 x 3 rectangle 5 from the Y axis sweeps a tube; its outer wall is a cylinder of radius 7
+        let result = evaluate("revolve", vec![
+            Value::String(rectangle([[5.0, 0.0], [7.0, 0.0], [7.0, 3.0], [5.0, 3.0]])),
+            Value::Number(360.0),
+            Value::String("Y".into()),
+        ]);
+        let mut tessellation = result.tessellation;
+        assert!(tessellation.uvs.is_none(), "UVs are only made on request");
+        let vertex_count = tessellation.vertices.len() / 3;
+        tessellation.generate_uvs(&result.topology_manifest);
+        assert_eq!(tessellation.uvs.as_ref().unwrap().len(), tessellation.vertices.len() / 3 * 2);
+        assert_eq!(tessellation.normals.len(), tessellation.vertices.len());
+        assert!(tessellation.vertices.len() / 3 >= vertex_count);
+
+        let wall = result.topology_manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Cylinder { radius, .. } if (radius - 7.0).abs() < 1e-9))
+            .unwrap();
+        let corners = face_corners(&tessellation, &wall.id);
+        let (u_min, u_max) = extent(&corners, 0);
+        let (v_min, v_max) = extent(&corners, 1);
+        assert!(u_min.abs() < 1e-6 && (u_max - 1.0).abs() < 1e-6, "u in [{}, {}]", u_min, u_max);
+        assert!(v_min.abs() < 1e-4 && (v_max - 3.0).abs() < 1e-4, "v in [{}, {}]", v_min, v_max);
+
+        // Every corner on u = 0 or u = 1 lies on one line along the axis, and each shows up on both sides
+        let seam: Vec<_> = corners.iter().filter(|(_, uv)| uv[0] < 1e-6 || uv[0] > 1.0 - 1e-6).collect();
+        let direction = |p: &Point3| Vector3::new(p.x, 0.0, p.z).normalize();
+        let first = direction(&seam[0].0);
+        assert!(seam.iter().all(|(p, _)| (direction(p) - first).norm() < 1e-4));
+        for (p, uv) in &seam {
+            let other = 1.0 - uv[0].round();
+            assert!(seam.iter().any(|(q, w)| (p - q).norm() < 1e-4 && w[0] == other), "Seam vertex {:?} is not duplicated", p);
+        }
+        // No triangle away from the seam spans more than one column of vertices
+        for triangle in corners.chunks(3) {
+            let (lo, hi) = extent(triangle, 0);
+            assert!(hi - lo < 0.1, "Triangle spans u in [{}, {}]", lo, hi);
+        }
+    }
+
+    #[test]
+    fn test_box_face_uvs_match_its_size() {
+        let result = evaluate("extrude", vec![
+            Value::String(rectangle([[0.0, 0.0], [10.0, 0.0], [10.0, 6.0], [0.0, 6.0]])),
+            Value::Number(4.0),
+            Value::String("Add".into()),
+        ]);
+        let mut tessellation = result.tessellation;
+        tessellation.generate_uvs(&result.topology_manifest);
+
+        let top = result.topology_manifest.values()
+            .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { origin, normal } if origin[2] > 3.9 && normal[2] > 0.9))
+            .unwrap();
+        let corners = face_corners(&tessellation, &top.id);
+        let (u_min, u_max) = extent(&corners, 0);
+        let (v_min, v_max) = extent(&corners, 1);
+        assert!(((u_max - u_min) - 10.0).abs() < 1e-4 && ((v_max - v_min) - 6.0).abs() < 1e-4);
+        // The plane's frame keeps lengths: every corner's UV is its (x, y) shifted
+        let (p0, uv0) = corners[0];
+        for (p, uv) in &corners {
+            assert!(((uv[0] - uv0[0]) as f64 - (p.x - p0.x)).abs() < 1e-4 && ((uv[1] - uv0[1]) as f64 - (p.y - p0.y)).abs() < 1e-4);
+        }
+
+        // Without a manifest every face falls back to the coordinate plane facing it
+        let mut fallback = Tessellation::new();
+        let face = top.id;
+        let p = |x: f64, y: f64| Point3::new(x, 4.0, y);
+        fallback.add_triangle(p(0.0, 0.0), p(0.0, 6.0), p(10.0, 6.0), face);
+        fallback.generate_uvs(&HashMap::new());
+        assert_eq!(fallback.uvs.unwrap(), [0.0, 0.0, 6.0, 0.0, 6.0, 10.0]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Boolean subtraction of closed triangle meshes
+//!
+//! The fallback for cuts the B-rep Boolean can't do, such as tools bounded
+//! by revolved surfaces. Each mesh becomes a BSP tree of its polygons; the
+//! parts of each mesh inside the other are clipped away, and the tool's
+//! remaining faces are turned inside out to line the cut. The meshes must be
+//! closed and wound outward; they need not share vertices.
+//!
+//! Triangles keep the face id of the face they were cut from, so the result
+//! tessellates into the same faces as its inputs. The tool's ids are offset
+//! past the target's.
+
+use super::types::{Point3D, TriangleMesh};
+use crate::geometry::{Point3, Vector3};
+
+/// Points closer than this to a splitting plane lie on it
+const PLANE_TOLERANCE: f64 = 1e-7;
+
+#[derive(Debug, Clone, Copy)]
+struct Plane {
+    normal: Vector3,
+    w: f64,
+}
+
+impl Plane {
+    fn through(a: &Point3, b: &Point3, c: &Point3) -> Option<Plane> {
+        let normal = (b - a).cross(&(c - a));
+        let length = normal.norm();
+        (length > 1e-12).then(|| {
+            let normal = normal / length;
+            Plane { normal, w: normal.dot(&a.coords) }
+        })
+    }
+
+    fn flipped(self) -> Plane {
+        Plane { normal: -self.normal, w: -self.w }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Polygon {
+    vertices: Vec<Point3>,
+    plane: Plane,
+    face: u32,
+}
+
+impl Polygon {
+    fn flip(&mut self) {
+        self.vertices.reverse();
+        self.plane = self.plane.flipped();
+    }
+}
+
+/// Where a polygon lies relative to a plane
+#[derive(Default)]
+struct Split {
+    coplanar_front: Vec<Polygon>,
+    coplanar_back: Vec<Polygon>,
+    front: Vec<Polygon>,
+    back: Vec<Polygon>,
+}
+
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+fn split_polygon(plane: &Plane, polygon: Polygon, split: &mut Split) {
+    let sides: Vec<u8> = polygon.vertices.iter().map(|v| {
+        let t = plane.normal.dot(&v.coords) - plane.w;
+        if t < -PLANE_TOLERANCE { BACK } else if t > PLANE_TOLERANCE { FRONT } else { COPLANAR }
+    }).collect();
+    match sides.iter().fold(COPLANAR, |all, side| all | side) {
+        COPLANAR if plane.normal.dot(&polygon.plane.normal) > 0.0 => split.coplanar_front.push(polygon),
+        COPLANAR => split.coplanar_back.push(polygon),
+        FRONT => split.front.push(polygon),
+        BACK => split.back.push(polygon),
+        _ => {
+            let (mut front, mut back) = (Vec::new(), Vec::new());
+            let n = polygon.vertices.len();
+            for i in 0..n {
+                let (a, b) = (polygon.vertices[i], polygon.vertices[(i + 1) % n]);
+                let (side_a, side_b) = (sides[i], sides[(i + 1) % n]);
+                if side_a != BACK {
+                    front.push(a);
+                }
+                if side_a != FRONT {
+                    back.push(a);
+                }
+                if (side_a | side_b) == SPANNING {
+                    let t = (plane.w - plane.normal.dot(&a.coords)) / plane.normal.dot(&(b - a));
+                    let crossing = a + (b - a) * t;
+                    front.push(crossing);
+                    back.push(crossing);
+                }
+            }
+            for (vertices, side) in [(front, &mut split.front), (back, &mut split.back)] {
+                if vertices.len() >= 3 {
+                    side.push(Polygon { vertices, plane: polygon.plane, face: polygon.face });
+                }
+            }
+        }
+    }
+}
+
+/// BSP tree of a closed mesh's polygons; what is behind every plane on the
+/// way down to a leaf is inside the mesh
+#[derive(Default)]
+struct Node {
+    plane: Option<Plane>,
+    polygons: Vec<Polygon>,
+    front: Option<Box<Node>>,
+    back: Option<Box<Node>>,
+}
+
+impl Node {
+    fn new(polygons: Vec<Polygon>) -> Node {
+        let mut node = Node::default();
+        node.build(polygons);
+        node
+    }
+
+    /// Swap inside and outside
+    fn invert(&mut self) {
+        for polygon in &mut self.polygons {
+            polygon.flip();
+        }
+        self.plane = self.plane.map(Plane::flipped);
+        for child in [&mut self.front, &mut self.back].into_iter().flatten() {
+            child.invert();
+        }
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// The parts of `polygons` outside this tree's solid
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        let Some(plane) = &self.plane else { return polygons };
+        let mut split = Split::default();
+        for polygon in polygons {
+            split_polygon(plane, polygon, &mut split);
+        }
+        let mut front = split.front;
+        front.extend(split.coplanar_front);
+        let mut back = split.back;
+        back.extend(split.coplanar_back);
+        let mut kept = match &self.front {
+            Some(node) => node.clip_polygons(front),
+            None => front,
+        };
+        if let Some(node) = &self.back {
+            kept.extend(node.clip_polygons(back));
+        }
+        kept
+    }
+
+    /// Remove the parts of this tree's polygons inside `other`
+    fn clip_to(&mut self, other: &Node) {
+        self.polygons = other.clip_polygons(std::mem::take(&mut self.polygons));
+        for child in [&mut self.front, &mut self.back].into_iter().flatten() {
+            child.clip_to(other);
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        let mut polygons = self.polygons.clone();
+        for child in [&self.front, &self.back].into_iter().flatten() {
+            polygons.extend(child.all_polygons());
+        }
+        polygons
+    }
+
+    fn build(&mut self, polygons: Vec<Polygon>) {
+        let Some(first) = polygons.first() else { return };
+        let plane = *self.plane.get_or_insert(first.plane);
+        let mut split = Split::default();
+        for polygon in polygons {
+            split_polygon(&plane, polygon, &mut split);
+        }
+        self.polygons.extend(split.coplanar_front);
+        self.polygons.extend(split.coplanar_back);
+        for (child, polygons) in [(&mut self.front, split.front), (&mut self.back, split.back)] {
+            if !polygons.is_empty() {
+                child.get_or_insert_with(Default::default).build(polygons);
+            }
+        }
+    }
+}
+
+fn polygons(mesh: &TriangleMesh, face_offset: u32) -> Vec<Polygon> {
+    let point = |i: u32| {
+        let p = &mesh.positions[i as usize];
+        Point3::new(p.x, p.y, p.z)
+    };
+    mesh.triangles.iter().enumerate().filter_map(|(i, &(a, b, c))| {
+        let vertices = vec![point(a), point(b), point(c)];
+        let plane = Plane::through(&vertices[0], &vertices[1], &vertices[2])?;
+        let face = mesh.face_ids.get(i).copied().unwrap_or(0) + face_offset;
+        Some(Polygon { vertices, plane, face })
+    }).collect()
+}
+
+/// `target` with the volume of `tool` removed
+pub fn subtract(target: &TriangleMesh, tool: &TriangleMesh) -> TriangleMesh {
+    let offset = target.face_ids.iter().max().map_or(0, |max| max + 1);
+    let mut a = Node::new(polygons(target, 0));
+    let mut b = Node::new(polygons(tool, offset));
+    a.invert();
+    a.clip_to(&b);
+    b.clip_to(&a);
+    b.invert();
+    b.clip_to(&a);
+    b.invert();
+    a.build(b.all_polygons());
+    a.invert();
+
+    // Split polygons stay convex, so fans triangulate them
+    let mut mesh = TriangleMesh::new();
+    for polygon in a.all_polygons() {
+        let indices: Vec<u32> = polygon.vertices.iter().map(|p| mesh.add_vertex(Point3D::new(p.x, p.y, p.z))).collect();
+        for i in 1..indices.len() - 1 {
+            mesh.add_triangle_with_face(indices[0], indices[i], indices[i + 1], polygon.face);
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Axis-aligned box as an outward-wound mesh, one face id per side
+    fn block(min: [f64; 3], max: [f64; 3]) -> TriangleMesh {
+        let mut mesh = TriangleMesh::new();
+        let corner = |i: usize| Point3D::new(
+            if i & 1 == 0 { min[0] } else { max[0] },
+            if i & 2 == 0 { min[1] } else { max[1] },
+            if i & 4 == 0 { min[2] } else { max[2] },
+        );
+        for i in 0..8 {
+            mesh.add_vertex(corner(i));
+        }
+        let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        for (face, [a, b, c, d]) in quads.into_iter().enumerate() {
+            mesh.add_triangle_with_face(a, b, c, face as u32);
+            mesh.add_triangle_with_face(a, c, d, face as u32);
+        }
+        mesh
+    }
+
+    fn volume(mesh: &TriangleMesh) -> f64 {
+        let p = |i: u32| { let p = &mesh.positions[i as usize]; Vector3::new(p.x, p.y, p.z) };
+        mesh.triangles.iter().map(|&(a, b, c)| p(a).dot(&p(b).cross(&p(c))) / 6.0).sum()
+    }
+
+    #[test]
+    fn test_subtract_removes_the_overlap() {
+        let target = block([0.0; 3], [4.0, 4.0, 4.0]);
+        assert!((volume(&target) - 64.0).abs() < 1e-9, "Outward winding");
+
+        // A notch through one corner, sharing none of the target's faces
+        let cut = subtract(&target, &block([3.0, 3.0, -1.0], [5.0, 5.0, 5.0]));
+        assert!((volume(&cut) - 60.0).abs() < 1e-9, "Removed {}", 64.0 - volume(&cut));
+        assert!(cut.has_face_ids());
+        assert!(cut.face_ids.iter().any(|&f| f >= 6), "The notch is lined with the tool's faces");
+
+        // Tools that miss leave the target whole
+        let missed = subtract(&target, &block([10.0; 3], [11.0; 3]));
+        assert!((volume(&missed) - 64.0).abs() < 1e-9);
+    }
+}
@@ -5,6 +5,8 @@
 //! changing the rest of the codebase.
 
 pub mod types;
+pub mod mesh_boolean;
+mod step;
 mod truck;
 
 #[cfg(test)]
@@ -14,6 +16,7 @@ pub use truck::TruckKernel;
 pub use truck::transform_solid_to_world;
 pub use types::*;
 
+use crate::errors::{CadError, ErrorCode};
 use crate::geometry::Tessellation;
 use thiserror::Error;
 
@@ -31,6 +34,28 @@ pub enum KernelOpError {
     
     #[error("Not implemented: {0}")]
     NotImplemented(String),
+
+    /// Input over one of the caller's limits (size, count, time)
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+}
+
+impl KernelOpError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            KernelOpError::InvalidGeometry(_) => ErrorCode::KernelInvalidGeometry,
+            KernelOpError::OperationFailed(_) => ErrorCode::KernelOperationFailed,
+            KernelOpError::TessellationFailed(_) => ErrorCode::KernelTessellationFailed,
+            KernelOpError::NotImplemented(_) => ErrorCode::KernelNotImplemented,
+            KernelOpError::LimitExceeded(_) => ErrorCode::LimitExceeded,
+        }
+    }
+}
+
+impl From<KernelOpError> for CadError {
+    fn from(error: KernelOpError) -> Self {
+        CadError::new(error.code(), error.to_string())
+    }
 }
 
 /// Result type for kernel operations.
@@ -60,9 +85,9 @@ pub trait GeometryKernel: Send + Sync {
     /// Revolve a 2D profile around an axis to create a solid.
     ///
     /// # Arguments
-    /// * `profile` - Points defining the 2D profile
+    /// * `profile` - The 2D profile (with optional holes, which partial revolutions keep in their end caps)
     /// * `params` - Revolution parameters (angle, axis, etc.)
-    fn revolve_profile(&self, profile: &[Point2D], params: &RevolveParams) -> KernelResult<Self::Solid>;
+    fn revolve_profile(&self, profile: &Polygon2D, params: &RevolveParams) -> KernelResult<Self::Solid>;
     
     /// Convert a solid to a triangle mesh for rendering.
     fn tessellate(&self, solid: &Self::Solid) -> KernelResult<TriangleMesh>;
@@ -93,11 +118,27 @@ pub trait GeometryKernel: Send + Sync {
     /// Export a solid to STEP format and return as a string.
     fn export_step(&self, solid: &Self::Solid) -> KernelResult<String>;
     
-    /// Import a solid from STEP format string.
-    fn import_step(&self, step_data: &str) -> KernelResult<Vec<Self::Solid>>;
+    /// Import the solids of a STEP file, scaled to mm and placed as in its
+    /// assembly. Entities that can't be imported are skipped with a warning.
+    fn import_step(&self, step_data: &str) -> KernelResult<StepImportResult<Self::Solid>>;
 }
 
 /// Get the default kernel implementation.
 pub fn default_kernel() -> TruckKernel {
     TruckKernel::new()
 }
+
+thread_local! {
+    static SOLID_OPERATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Solids built (boxes, extrudes and revolves) on this thread so far.
+/// Lets tests check that a code path, such as a live sketch update, never
+/// reaches the kernel.
+pub fn solid_operation_count() -> usize {
+    SOLID_OPERATIONS.with(|count| count.get())
+}
+
+fn count_solid_operation() {
+    SOLID_OPERATIONS.with(|count| count.set(count.get() + 1));
+}
@@ -0,0 +1,573 @@
+//! STEP file structure: units, product names and assembly placements.
+//!
+//! truck-stepio reads a STEP file's geometry and topology but drops
+//! everything around it. This module reads the DATA section itself to find,
+//! for each MANIFOLD_SOLID_BREP, the length unit of the representation it is
+//! in, the product that representation belongs to, and every placement of
+//! it in the assembly. Kernel-agnostic: matrices are row-major `[[f64; 4]; 4]`
+//! in mm.
+
+use std::collections::HashMap;
+
+/// An instance parameter
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Param {
+    Ref(u64),
+    Str(String),
+    Num(f64),
+    /// `.T.`, `.MILLI.`, ...
+    Enum(String),
+    List(Vec<Param>),
+    /// Typed value, e.g. `LENGTH_MEASURE(25.4)`
+    Typed(String, Vec<Param>),
+    /// `$` or `*`
+    Unset,
+}
+
+/// One `NAME(params)` record; complex instances have several
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Record {
+    pub name: String,
+    pub params: Vec<Param>,
+}
+
+/// A MANIFOLD_SOLID_BREP and where it ends up
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BrepInstance {
+    /// Id of the MANIFOLD_SOLID_BREP entity
+    pub brep: u64,
+    /// Id of its CLOSED_SHELL
+    pub shell: u64,
+    pub name: String,
+    /// Factor from the solid's own length unit to mm
+    pub scale_to_mm: f64,
+    /// Part frame to assembly root, in mm
+    pub transform: [[f64; 4]; 4],
+}
+
+pub(crate) const IDENTITY: [[f64; 4]; 4] = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+/// Entity types that carry solid or surface geometry the import can't build.
+/// Each instance is skipped with a warning instead of failing the import.
+const UNSUPPORTED_SHAPES: [&str; 5] = [
+    "BREP_WITH_VOIDS",
+    "FACETED_BREP",
+    "SHELL_BASED_SURFACE_MODEL",
+    "GEOMETRIC_CURVE_SET",
+    "TESSELLATED_SHAPE_REPRESENTATION",
+];
+
+/// The DATA section of a STEP file, indexed by entity id
+#[derive(Debug, Default)]
+pub(crate) struct StepStructure {
+    entities: HashMap<u64, Vec<Record>>,
+}
+
+impl StepStructure {
+    pub fn parse(step: &str) -> Result<Self, String> {
+        let start = step.find("DATA;").ok_or("No DATA section")? + "DATA;".len();
+        let data = &step[start..];
+        let data = &data[..data.find("ENDSEC;").ok_or("DATA section is not closed")?];
+        let mut entities = HashMap::new();
+        for instance in split_instances(data) {
+            let instance = instance.trim();
+            if instance.is_empty() {
+                continue;
+            }
+            let (id, body) = instance.split_once('=').ok_or_else(|| format!("Malformed instance: {}", preview(instance)))?;
+            let id: u64 = id.trim().trim_start_matches('#').parse().map_err(|_| format!("Malformed entity id: {}", preview(instance)))?;
+            let mut parser = Parser { chars: body.trim().chars().collect(), pos: 0 };
+            let records = parser.records().map_err(|e| format!("#{}: {}", id, e))?;
+            entities.insert(id, records);
+        }
+        Ok(Self { entities })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    fn record(&self, id: u64, name: &str) -> Option<&Record> {
+        self.entities.get(&id)?.iter().find(|r| r.name == name)
+    }
+
+    fn first(&self, id: u64) -> Option<&Record> {
+        self.entities.get(&id)?.first()
+    }
+
+    /// Ids of the simple instances named `name`, in id order
+    fn ids_of(&self, name: &str) -> Vec<u64> {
+        let mut ids: Vec<u64> = self.entities.iter().filter(|(_, r)| r.len() == 1 && r[0].name == name).map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Shapes of a type the import skips, as `(id, type)`
+    pub fn unsupported_shapes(&self) -> Vec<(u64, &'static str)> {
+        let mut found: Vec<_> = UNSUPPORTED_SHAPES.iter().flat_map(|name| self.ids_of(name).into_iter().map(move |id| (id, *name))).collect();
+        found.sort_unstable();
+        found
+    }
+
+    /// The file's length unit: its name and the factor to mm. The first
+    /// LENGTH_UNIT wins; files without one are taken to be in mm.
+    pub fn length_unit(&self) -> Option<(String, f64)> {
+        let mut ids: Vec<u64> = self.entities.iter().filter(|(_, r)| r.iter().any(|r| r.name == "LENGTH_UNIT")).map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        ids.into_iter().find_map(|id| self.unit_to_mm(id, 0))
+    }
+
+    /// Name and mm factor of the length unit `id`
+    fn unit_to_mm(&self, id: u64, depth: usize) -> Option<(String, f64)> {
+        if depth > 8 {
+            return None;
+        }
+        if let Some(si) = self.record(id, "SI_UNIT") {
+            let prefix = match si.params.first() {
+                Some(Param::Enum(p)) => p.as_str(),
+                _ => "",
+            };
+            let (name, factor) = match prefix {
+                "" => ("m", 1000.0),
+                "KILO" => ("km", 1.0e6),
+                "DECI" => ("dm", 100.0),
+                "CENTI" => ("cm", 10.0),
+                "MILLI" => ("mm", 1.0),
+                "MICRO" => ("um", 1.0e-3),
+                "NANO" => ("nm", 1.0e-6),
+                _ => return None,
+            };
+            return Some((name.to_string(), factor));
+        }
+        let conversion = self.record(id, "CONVERSION_BASED_UNIT")?;
+        let name = match conversion.params.first() {
+            Some(Param::Str(name)) => name.to_lowercase(),
+            _ => "unnamed".to_string(),
+        };
+        let Some(Param::Ref(measure)) = conversion.params.get(1) else { return None };
+        let measure = self.first(*measure)?;
+        let value = match measure.params.first()? {
+            Param::Typed(_, v) => match v.first()? {
+                Param::Num(n) => *n,
+                _ => return None,
+            },
+            Param::Num(n) => *n,
+            _ => return None,
+        };
+        let Some(Param::Ref(base)) = measure.params.get(1) else { return None };
+        let (_, base) = self.unit_to_mm(*base, depth + 1)?;
+        Some((name, value * base))
+    }
+
+    /// mm factor of representation `rep`, from its context's length unit
+    fn representation_scale(&self, rep: u64, default: f64) -> f64 {
+        let context = match self.first(rep).and_then(|r| r.params.get(2)) {
+            Some(Param::Ref(context)) => *context,
+            _ => return default,
+        };
+        let Some(units) = self.record(context, "GLOBAL_UNIT_ASSIGNED_CONTEXT") else { return default };
+        let Some(Param::List(units)) = units.params.first() else { return default };
+        units
+            .iter()
+            .filter_map(|u| match u {
+                Param::Ref(id) => self.record(*id, "LENGTH_UNIT").and(self.unit_to_mm(*id, 0)),
+                _ => None,
+            })
+            .map(|(_, factor)| factor)
+            .next()
+            .unwrap_or(default)
+    }
+
+    /// Every placement of every MANIFOLD_SOLID_BREP, in brep id order.
+    /// `default_scale` is used for representations without a length unit.
+    pub fn brep_instances(&self, default_scale: f64) -> Vec<BrepInstance> {
+        let reps = self.representations();
+        let links = self.representation_links();
+        let mut instances = Vec::new();
+        for brep in self.ids_of("MANIFOLD_SOLID_BREP") {
+            let record = &self.entities[&brep][0];
+            let Some(Param::Ref(shell)) = record.params.get(1) else { continue };
+            let own_name = match record.params.first() {
+                Some(Param::Str(s)) if !s.is_empty() => Some(s.clone()),
+                _ => None,
+            };
+            let containing: Vec<u64> = reps.iter().filter(|(_, items)| items.contains(&brep)).map(|(rep, _)| *rep).collect();
+            if containing.is_empty() {
+                let name = own_name.unwrap_or_else(|| format!("Body #{}", brep));
+                instances.push(BrepInstance { brep, shell: *shell, name, scale_to_mm: default_scale, transform: IDENTITY });
+                continue;
+            }
+            for rep in containing {
+                let name = self.product_name(rep, &links).or(own_name.clone()).unwrap_or_else(|| format!("Body #{}", brep));
+                let scale_to_mm = self.representation_scale(rep, default_scale);
+                for transform in self.placements(rep, &links, default_scale, 0) {
+                    instances.push(BrepInstance { brep, shell: *shell, name: name.clone(), scale_to_mm, transform });
+                }
+            }
+        }
+        instances
+    }
+
+    /// Representations (anything with an items list and a context) and their item ids
+    fn representations(&self) -> Vec<(u64, Vec<u64>)> {
+        let mut reps: Vec<(u64, Vec<u64>)> = self
+            .entities
+            .iter()
+            .filter(|(_, r)| r.len() == 1 && r[0].name.ends_with("SHAPE_REPRESENTATION"))
+            .filter_map(|(id, r)| match r[0].params.get(1) {
+                Some(Param::List(items)) => Some((*id, refs(items))),
+                _ => None,
+            })
+            .collect();
+        reps.sort_unstable();
+        reps
+    }
+
+    /// Representation relationships as `(child, parent, transformation)`.
+    /// Plain SHAPE_REPRESENTATION_RELATIONSHIPs tie two representations of
+    /// the same part together and have no transformation.
+    fn representation_links(&self) -> Vec<(u64, u64, Option<u64>)> {
+        let mut links = Vec::new();
+        for (id, records) in &self.entities {
+            let Some(rel) = records.iter().find(|r| r.name == "REPRESENTATION_RELATIONSHIP" || r.name == "SHAPE_REPRESENTATION_RELATIONSHIP") else { continue };
+            let (Some(Param::Ref(child)), Some(Param::Ref(parent))) = (rel.params.get(2), rel.params.get(3)) else { continue };
+            let transformation = records.iter().find(|r| r.name == "REPRESENTATION_RELATIONSHIP_WITH_TRANSFORMATION").and_then(|r| match r.params.first() {
+                Some(Param::Ref(t)) => Some(*t),
+                _ => None,
+            });
+            links.push((*id, *child, *parent, transformation));
+        }
+        links.sort_unstable_by_key(|l| l.0);
+        links.into_iter().map(|(_, child, parent, t)| (child, parent, t)).collect()
+    }
+
+    /// Representations of the same part as `rep` (linked without a transformation)
+    fn same_part(&self, rep: u64, links: &[(u64, u64, Option<u64>)]) -> Vec<u64> {
+        let mut part = vec![rep];
+        let mut i = 0;
+        while i < part.len() {
+            let current = part[i];
+            for (a, b, t) in links {
+                if t.is_some() {
+                    continue;
+                }
+                for (from, to) in [(a, b), (b, a)] {
+                    if *from == current && !part.contains(to) {
+                        part.push(*to);
+                    }
+                }
+            }
+            i += 1;
+        }
+        part
+    }
+
+    /// Name of the product whose shape is `rep` (or a representation of the same part)
+    fn product_name(&self, rep: u64, links: &[(u64, u64, Option<u64>)]) -> Option<String> {
+        let part = self.same_part(rep, links);
+        self.ids_of("SHAPE_DEFINITION_REPRESENTATION").into_iter().find_map(|sdr| {
+            let params = &self.entities[&sdr][0].params;
+            let (Some(Param::Ref(pds)), Some(Param::Ref(shape))) = (params.first(), params.get(1)) else { return None };
+            if !part.contains(shape) {
+                return None;
+            }
+            // PRODUCT_DEFINITION_SHAPE -> PRODUCT_DEFINITION -> PRODUCT_DEFINITION_FORMATION -> PRODUCT
+            let pd = self.ref_param(*pds, 2)?;
+            let formation = self.ref_param(pd, 2)?;
+            let product = self.ref_param(formation, 2)?;
+            match self.first(product)?.params.get(1) {
+                Some(Param::Str(name)) if !name.is_empty() => Some(name.clone()),
+                _ => None,
+            }
+        })
+    }
+
+    fn ref_param(&self, id: u64, index: usize) -> Option<u64> {
+        match self.first(id)?.params.get(index)? {
+            Param::Ref(r) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// Transforms from the part `rep` belongs to into the assembly root, one
+    /// per placement path. Unplaced parts get the identity.
+    fn placements(&self, rep: u64, links: &[(u64, u64, Option<u64>)], default_scale: f64, depth: usize) -> Vec<[[f64; 4]; 4]> {
+        if depth > 32 {
+            return Vec::new();
+        }
+        let part = self.same_part(rep, links);
+        let mut out = Vec::new();
+        for (child, parent, transformation) in links {
+            let Some(transformation) = transformation else { continue };
+            if !part.contains(child) {
+                continue;
+            }
+            let Some(local) = self.item_transformation(*transformation, self.representation_scale(*child, default_scale), self.representation_scale(*parent, default_scale)) else { continue };
+            for above in self.placements(*parent, links, default_scale, depth + 1) {
+                out.push(multiply(&above, &local));
+            }
+        }
+        if out.is_empty() {
+            out.push(IDENTITY);
+        }
+        out
+    }
+
+    /// ITEM_DEFINED_TRANSFORMATION `id`: maps the part's frame (item 1) onto
+    /// its placement in the parent (item 2)
+    fn item_transformation(&self, id: u64, child_scale: f64, parent_scale: f64) -> Option<[[f64; 4]; 4]> {
+        let record = self.record(id, "ITEM_DEFINED_TRANSFORMATION")?;
+        let (Some(Param::Ref(from)), Some(Param::Ref(to))) = (record.params.get(2), record.params.get(3)) else { return None };
+        let from = self.axis_placement(*from, child_scale)?;
+        let to = self.axis_placement(*to, parent_scale)?;
+        Some(multiply(&to, &rigid_inverse(&from)))
+    }
+
+    /// AXIS2_PLACEMENT_3D `id` as a matrix, its location scaled to mm
+    fn axis_placement(&self, id: u64, scale: f64) -> Option<[[f64; 4]; 4]> {
+        let record = self.record(id, "AXIS2_PLACEMENT_3D")?;
+        let Some(Param::Ref(location)) = record.params.get(1) else { return None };
+        let location = self.numbers(*location)?;
+        let direction = |index: usize, default: [f64; 3]| match record.params.get(index) {
+            Some(Param::Ref(d)) => self.numbers(*d).map(|v| normalize([v[0], v[1], v[2]])),
+            _ => Some(default),
+        };
+        let z = direction(2, [0.0, 0.0, 1.0])?;
+        let x = direction(3, [1.0, 0.0, 0.0])?;
+        // Reference direction projected square to the axis
+        let along = dot(x, z);
+        let x = normalize([x[0] - along * z[0], x[1] - along * z[1], x[2] - along * z[2]]);
+        let y = cross(z, x);
+        let p = [location[0] * scale, location[1] * scale, location[2] * scale];
+        Some([[x[0], y[0], z[0], p[0]], [x[1], y[1], z[1], p[1]], [x[2], y[2], z[2], p[2]], [0.0, 0.0, 0.0, 1.0]])
+    }
+
+    /// Coordinates of a CARTESIAN_POINT or DIRECTION
+    fn numbers(&self, id: u64) -> Option<Vec<f64>> {
+        let Some(Param::List(values)) = self.first(id)?.params.get(1) else { return None };
+        let numbers: Vec<f64> = values.iter().filter_map(|v| match v {
+            Param::Num(n) => Some(*n),
+            _ => None,
+        }).collect();
+        (numbers.len() == 3).then_some(numbers)
+    }
+}
+
+fn refs(items: &[Param]) -> Vec<u64> {
+    items.iter().filter_map(|p| match p {
+        Param::Ref(id) => Some(*id),
+        _ => None,
+    }).collect()
+}
+
+fn preview(text: &str) -> String {
+    text.chars().take(60).collect()
+}
+
+/// Split the DATA section at the `;` ending each instance, outside strings
+fn split_instances(data: &str) -> Vec<String> {
+    let mut instances = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !in_string && c == '/' && chars.peek() == Some(&'*') {
+            // Comment
+            chars.next();
+            let mut last = ' ';
+            for c in chars.by_ref() {
+                if last == '*' && c == '/' {
+                    break;
+                }
+                last = c;
+            }
+            continue;
+        }
+        if c == '\'' {
+            in_string = !in_string;
+        }
+        if c == ';' && !in_string {
+            instances.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    instances
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn skip_space(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at {}", c, self.pos))
+        }
+    }
+
+    fn keyword(&mut self) -> String {
+        self.skip_space();
+        let start = self.pos;
+        while self.pos < self.chars.len() && (self.chars[self.pos].is_ascii_alphanumeric() || self.chars[self.pos] == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// A simple `NAME(...)` or complex `( NAME(...) NAME(...) )` instance
+    fn records(&mut self) -> Result<Vec<Record>, String> {
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            let mut records = Vec::new();
+            while self.peek() != Some(')') {
+                if self.peek().is_none() {
+                    return Err("complex instance is not closed".to_string());
+                }
+                records.push(self.record()?);
+            }
+            self.pos += 1;
+            Ok(records)
+        } else {
+            Ok(vec![self.record()?])
+        }
+    }
+
+    fn record(&mut self) -> Result<Record, String> {
+        let name = self.keyword();
+        if name.is_empty() {
+            return Err(format!("expected an entity name at {}", self.pos));
+        }
+        let params = self.list()?;
+        Ok(Record { name, params })
+    }
+
+    fn list(&mut self) -> Result<Vec<Param>, String> {
+        self.expect('(')?;
+        let mut params = Vec::new();
+        if self.peek() == Some(')') {
+            self.pos += 1;
+            return Ok(params);
+        }
+        loop {
+            params.push(self.param()?);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(params);
+                }
+                _ => return Err(format!("expected ',' or ')' at {}", self.pos)),
+            }
+        }
+    }
+
+    fn param(&mut self) -> Result<Param, String> {
+        match self.peek() {
+            Some('#') => {
+                self.pos += 1;
+                let digits = self.keyword();
+                digits.parse().map(Param::Ref).map_err(|_| format!("bad reference #{}", digits))
+            }
+            Some('\'') => {
+                self.pos += 1;
+                let mut text = String::new();
+                loop {
+                    match self.chars.get(self.pos) {
+                        None => return Err("string is not closed".to_string()),
+                        Some('\'') if self.chars.get(self.pos + 1) == Some(&'\'') => {
+                            text.push('\'');
+                            self.pos += 2;
+                        }
+                        Some('\'') => {
+                            self.pos += 1;
+                            return Ok(Param::Str(text));
+                        }
+                        Some(c) => {
+                            text.push(*c);
+                            self.pos += 1;
+                        }
+                    }
+                }
+            }
+            Some('.') => {
+                self.pos += 1;
+                let value = self.keyword();
+                self.expect('.')?;
+                Ok(Param::Enum(value))
+            }
+            Some('$') | Some('*') => {
+                self.pos += 1;
+                Ok(Param::Unset)
+            }
+            Some('(') => self.list().map(Param::List),
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => {
+                let start = self.pos;
+                while self.pos < self.chars.len() && (self.chars[self.pos].is_ascii_digit() || "+-.eE".contains(self.chars[self.pos])) {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                text.parse().map(Param::Num).map_err(|_| format!("bad number '{}'", text))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.keyword();
+                Ok(Param::Typed(name, self.list()?))
+            }
+            other => Err(format!("unexpected {:?} at {}", other, self.pos)),
+        }
+    }
+}
+
+pub(crate) fn multiply(a: &[[f64; 4]; 4], b: &[[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut out = [[0.0; 4]; 4];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+/// Inverse of a rotation + translation
+fn rigid_inverse(m: &[[f64; 4]; 4]) -> [[f64; 4]; 4] {
+    let mut out = IDENTITY;
+    for (i, row) in out.iter_mut().take(3).enumerate() {
+        for (j, value) in row.iter_mut().take(3).enumerate() {
+            *value = m[j][i];
+        }
+        row[3] = -(0..3).map(|k| m[k][i] * m[k][3]).sum::<f64>();
+    }
+    out
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let length = dot(v, v).sqrt();
+    if length < 1e-12 {
+        v
+    } else {
+        [v[0] / length, v[1] / length, v[2] / length]
+    }
+}
@@ -253,6 +253,7 @@ impl GeometryKernel for TruckKernel {
     type Mesh = PolygonMesh;
     
     fn create_box(&self, width: f64, height: f64, depth: f64) -> KernelResult<Self::Solid> {
+        super::count_solid_operation();
         // Create a box using truck-modeling's builder
         // Box is from (0,0,0) to (width, height, depth)
         let v = builder::vertex(Point3::new(0.0, 0.0, 0.0));
@@ -264,11 +265,15 @@ impl GeometryKernel for TruckKernel {
     }
     
     fn extrude_polygon(&self, polygon: &Polygon2D, params: &ExtrudeParams) -> KernelResult<Self::Solid> {
+        super::count_solid_operation();
         if polygon.exterior.len() < 3 {
             return Err(KernelOpError::InvalidGeometry(
                 "Polygon must have at least 3 vertices".into()
             ));
         }
+        if !params.is_straight() {
+            return self.extrude_tapered(polygon, params);
+        }
         
         // Build the exterior wire - detect if it's a circle
         // Native circle edges created with rsweep work with truck_shapeops booleans
@@ -318,12 +323,16 @@ impl GeometryKernel for TruckKernel {
         Ok(solid)
     }
     
-    fn revolve_profile(&self, profile: &[Point2D], params: &RevolveParams) -> KernelResult<Self::Solid> {
-        // 1. Build wire from profile points
-        let wire = self.build_wire_from_points(profile)?;
+    fn revolve_profile(&self, profile: &Polygon2D, params: &RevolveParams) -> KernelResult<Self::Solid> {
+        super::count_solid_operation();
+        // 1. Build wires from the profile and its holes
+        let mut wires = vec![self.build_wire_from_points(&profile.exterior)?];
+        for hole in profile.interiors.iter().filter(|hole| hole.len() >= 3) {
+            wires.push(self.build_wire_from_points(hole)?);
+        }
 
-        // 2. Create face (requires planar, closed wire)
-        let face = builder::try_attach_plane(&[wire])
+        // 2. Create face (requires planar, closed wires); holes stay open in the end caps
+        let face = builder::try_attach_plane(&wires)
              .map_err(|e| KernelOpError::OperationFailed(format!("Failed to create face for revolution: {:?}", e)))?;
 
         // 3. Setup axis
@@ -499,7 +508,7 @@ impl GeometryKernel for TruckKernel {
         
         // When using kernel face IDs, adjacent faces with the same cylindrical axis should be merged
         // This handles Truck's tendency to split circles into multiple edges/faces
-        if use_face_ids {
+        if use_face_ids && !mesh.exact_faces {
             // Collect vertices per face group
             let mut face_group_vertices: HashMap<u32, Vec<[f64; 3]>> = HashMap::new();
             for (tri_idx, (i0, i1, i2)) in triangles.iter().enumerate() {
@@ -728,8 +737,10 @@ impl GeometryKernel for TruckKernel {
             if len > 1e-6 { n[0] /= len; n[1] /= len; n[2] /= len; }
         }
         
-        // 5. Generate TopoIds for face groups and add triangles
+        // 5. Generate TopoIds for face groups and add triangles.
+        // Each group is also a smoothing group, so the client knows where normals are shared.
         let mut group_id_map: HashMap<usize, TopoId> = HashMap::new();
+        let mut smoothing_groups: HashMap<usize, u32> = HashMap::new();
         
         for (tri_idx, (i0, i1, i2)) in triangles.iter().enumerate() {
             let root = if use_face_ids { 
@@ -774,14 +785,20 @@ impl GeometryKernel for TruckKernel {
             let n1 = vertex_smooth_normals.get(&(*i1 as usize, root)).unwrap_or(&default_n);
             let n2 = vertex_smooth_normals.get(&(*i2 as usize, root)).unwrap_or(&default_n);
             
-            tessellation.add_triangle_with_normals(
-                GeoPoint3::new(p0.x, p0.y, p0.z),
-                GeoPoint3::new(p1.x, p1.y, p1.z),
-                GeoPoint3::new(p2.x, p2.y, p2.z),
-                GeoVector3::new(n0[0], n0[1], n0[2]),
-                GeoVector3::new(n1[0], n1[1], n1[2]),
-                GeoVector3::new(n2[0], n2[1], n2[2]),
+            let group = *smoothing_groups.entry(root).or_insert_with(|| tessellation.new_smoothing_group());
+            tessellation.add_smooth_triangle(
+                [
+                    GeoPoint3::new(p0.x, p0.y, p0.z),
+                    GeoPoint3::new(p1.x, p1.y, p1.z),
+                    GeoPoint3::new(p2.x, p2.y, p2.z),
+                ],
+                [
+                    GeoVector3::new(n0[0], n0[1], n0[2]),
+                    GeoVector3::new(n1[0], n1[1], n1[2]),
+                    GeoVector3::new(n2[0], n2[1], n2[2]),
+                ],
                 face_id,
+                group,
             );
         }
         
@@ -1137,15 +1154,144 @@ impl GeometryKernel for TruckKernel {
         Ok(display.to_string())
     }
     
-    fn import_step(&self, _step_data: &str) -> KernelResult<Vec<Self::Solid>> {
-        // Truck v0.3 StepIO import is incomplete for solids (ManifoldSolidBrep not supported)
-        Err(KernelOpError::NotImplemented(
-            "STEP import is not yet supported in Truck v0.3".into()
-        ))
+    fn import_step(&self, step_data: &str) -> KernelResult<StepImportResult<Self::Solid>> {
+        self.import_step_limited(step_data, &StepImportLimits::default())
     }
 }
 
 impl TruckKernel {
+    /// `import_step` under explicit limits.
+    ///
+    /// Truck v0.3's StepIO reads shells but not MANIFOLD_SOLID_BREP, units or
+    /// assemblies; those come from `step::StepStructure`, and each brep's
+    /// shell is built from truck's table.
+    pub fn import_step_limited(&self, step_data: &str, limits: &StepImportLimits) -> KernelResult<StepImportResult<Solid>> {
+        use super::step::{multiply, StepStructure};
+        use truck_modeling::cgmath::Matrix4;
+        use truck_stepio::r#in::Table;
+        use truck_topology::compress::CompressedSolid;
+
+        if step_data.len() > limits.max_bytes {
+            return Err(KernelOpError::LimitExceeded(format!(
+                "STEP file is {} bytes; the limit is {}", step_data.len(), limits.max_bytes
+            )));
+        }
+        let structure = StepStructure::parse(step_data).map_err(|e| KernelOpError::InvalidGeometry(format!("STEP parse failed: {}", e)))?;
+        if structure.len() > limits.max_entities {
+            return Err(KernelOpError::LimitExceeded(format!(
+                "STEP file has {} entities; the limit is {}", structure.len(), limits.max_entities
+            )));
+        }
+        let table = Table::from_step(step_data).ok_or_else(|| KernelOpError::InvalidGeometry("STEP parse failed".into()))?;
+
+        let mut warnings = Vec::new();
+        let (unit, scale_to_mm) = structure.length_unit().unwrap_or_else(|| {
+            warnings.push("No length unit in the file; assuming mm".to_string());
+            ("mm".to_string(), 1.0)
+        });
+        for (id, kind) in structure.unsupported_shapes() {
+            warnings.push(format!("#{}: {} is not supported; skipped", id, kind));
+        }
+
+        let started = std::time::Instant::now();
+        let instances = structure.brep_instances(scale_to_mm);
+        let mut solids = Vec::new();
+        for (i, instance) in instances.iter().enumerate() {
+            if solids.len() == limits.max_solids || started.elapsed() > limits.max_duration {
+                let limit = if solids.len() == limits.max_solids { "solid count" } else { "time" };
+                warnings.push(format!("Import {} limit reached; {} solid(s) skipped", limit, instances.len() - i));
+                break;
+            }
+            let Some(shell) = table.shell.get(&instance.shell) else {
+                warnings.push(format!("#{} '{}': shell #{} not found; skipped", instance.brep, instance.name, instance.shell));
+                continue;
+            };
+            let shell = match table.to_compressed_shell(shell) {
+                Ok(shell) => shell,
+                Err(e) => {
+                    warnings.push(format!("#{} '{}': {}; skipped", instance.brep, instance.name, e));
+                    continue;
+                }
+            };
+            let shell = match Self::step_shell(shell) {
+                Ok(shell) => shell,
+                Err(kind) => {
+                    warnings.push(format!("#{} '{}': {} geometry is not supported; skipped", instance.brep, instance.name, kind));
+                    continue;
+                }
+            };
+            let solid = match Solid::extract(CompressedSolid { boundaries: vec![shell] }) {
+                Ok(solid) => solid,
+                Err(e) => {
+                    warnings.push(format!("#{} '{}': invalid topology ({:?}); skipped", instance.brep, instance.name, e));
+                    continue;
+                }
+            };
+            let s = instance.scale_to_mm;
+            let scale = [[s, 0.0, 0.0, 0.0], [0.0, s, 0.0, 0.0], [0.0, 0.0, s, 0.0], [0.0, 0.0, 0.0, 1.0]];
+            let m = multiply(&instance.transform, &scale);
+            let matrix = Matrix4::new(
+                m[0][0], m[1][0], m[2][0], m[3][0],
+                m[0][1], m[1][1], m[2][1], m[3][1],
+                m[0][2], m[1][2], m[2][2], m[3][2],
+                m[0][3], m[1][3], m[2][3], m[3][3],
+            );
+            solids.push(ImportedSolid {
+                name: instance.name.clone(),
+                transform: instance.transform,
+                solid: builder::transformed(&solid, matrix),
+            });
+        }
+
+        Ok(StepImportResult { solids, unit, scale_to_mm, warnings })
+    }
+
+    /// Serialize a solid's exact geometry, e.g. to store an imported body
+    pub fn solid_to_json(&self, solid: &Solid) -> KernelResult<String> {
+        serde_json::to_string(&solid.compress()).map_err(|e| KernelOpError::OperationFailed(format!("Solid serialization failed: {}", e)))
+    }
+
+    /// Rebuild a solid stored with `solid_to_json`
+    pub fn solid_from_json(&self, json: &str) -> KernelResult<Solid> {
+        let compressed: truck_topology::compress::CompressedSolid<Point3, Curve, Surface> =
+            serde_json::from_str(json).map_err(|e| KernelOpError::InvalidGeometry(format!("Stored solid is invalid: {}", e)))?;
+        Solid::extract(compressed).map_err(|e| KernelOpError::InvalidGeometry(format!("Stored solid is invalid: {:?}", e)))
+    }
+
+    /// A STEP shell with truck-modeling geometry, or the kind of curve or
+    /// surface that has no equivalent
+    fn step_shell(
+        shell: truck_topology::compress::CompressedShell<Point3, truck_stepio::r#in::alias::Curve3D, truck_stepio::r#in::alias::Surface>,
+    ) -> Result<truck_topology::compress::CompressedShell<Point3, Curve, Surface>, &'static str> {
+        use truck_stepio::r#in::alias::{Curve3D, ElementarySurface, Surface as StepSurface};
+        use truck_topology::compress::{CompressedEdge, CompressedFace, CompressedShell};
+
+        let edges = shell.edges.into_iter().map(|edge| {
+            let curve = match edge.curve {
+                Curve3D::Line(line) => Curve::Line(line),
+                Curve3D::BSplineCurve(curve) => Curve::BSplineCurve(curve),
+                Curve3D::NurbsCurve(curve) => Curve::NurbsCurve(curve),
+                Curve3D::Polyline(_) => return Err("polyline"),
+                Curve3D::Conic(_) => return Err("conic curve"),
+                Curve3D::PCurve(_) => return Err("surface curve"),
+            };
+            Ok(CompressedEdge { vertices: edge.vertices, curve })
+        }).collect::<Result<Vec<_>, _>>()?;
+        let faces = shell.faces.into_iter().map(|face| {
+            let surface = match face.surface {
+                StepSurface::ElementarySurface(surface) => match *surface {
+                    ElementarySurface::Plane(plane) => Surface::Plane(plane),
+                    _ => return Err("curved elementary surface"),
+                },
+                StepSurface::BSplineSurface(surface) => Surface::BSplineSurface(*surface),
+                StepSurface::NurbsSurface(surface) => Surface::NurbsSurface(*surface),
+                StepSurface::SweptCurve(_) => return Err("swept surface"),
+            };
+            Ok(CompressedFace { boundaries: face.boundaries, orientation: face.orientation, surface })
+        }).collect::<Result<Vec<_>, _>>()?;
+        Ok(CompressedShell { vertices: shell.vertices, edges, faces })
+    }
+
     /// Build a truck Wire from 2D points at a specified Z position.
     fn build_wire_from_points_at_z(&self, points: &[Point2D], z: f64) -> KernelResult<Wire> {
         if points.len() < 3 {
@@ -1171,6 +1317,63 @@ impl TruckKernel {
         Ok(wire)
     }
     
+    /// Extrude with the end scaled and twisted (`params.scale`, `params.twist`)
+    /// about the profile's origin. Each polygon edge is joined to its image by
+    /// a ruled side face. Faces come in the order a straight `tsweep` gives:
+    /// the bottom, one side per polygon edge (exterior then holes, each in the
+    /// given point order), the top.
+    fn extrude_tapered(&self, polygon: &Polygon2D, params: &ExtrudeParams) -> KernelResult<Solid> {
+        let (sin, cos) = params.twist.sin_cos();
+        let dir = params.direction.normalize();
+        let offset = Vector3::new(dir.x, dir.y, dir.z) * params.distance;
+        let end_point = |p: &Point2D| {
+            let (x, y) = (p.x * params.scale.0, p.y * params.scale.1);
+            Point3::new(x * cos - y * sin, x * sin + y * cos, 0.0) + offset
+        };
+        let closed_wire = |points: &[Point3]| -> Wire {
+            let vertices: Vec<Vertex> = points.iter().map(|p| builder::vertex(*p)).collect();
+            (0..vertices.len()).map(|i| builder::line(&vertices[i], &vertices[(i + 1) % vertices.len()])).collect()
+        };
+
+        let loops = std::iter::once(&polygon.exterior).chain(polygon.interiors.iter().filter(|hole| hole.len() >= 3));
+        let (mut bottom_wires, mut top_wires, mut sides) = (Vec::new(), Vec::new(), Vec::new());
+        for (index, points) in loops.enumerate() {
+            // The exterior runs counter-clockwise, holes clockwise
+            let area: f64 = (0..points.len()).map(|i| {
+                let (a, b) = (&points[i], &points[(i + 1) % points.len()]);
+                a.x * b.y - b.x * a.y
+            }).sum();
+            let reversed = (area > 0.0) != (index == 0);
+            let mut ordered: Vec<Point2D> = points.clone();
+            if reversed {
+                ordered.reverse();
+            }
+            let bottom = closed_wire(&ordered.iter().map(|p| Point3::new(p.x, p.y, 0.0)).collect::<Vec<_>>());
+            let top = closed_wire(&ordered.iter().map(end_point).collect::<Vec<_>>());
+            let mut faces: Vec<_> = builder::try_wire_homotopy(&bottom, &top)
+                .map_err(|e| KernelOpError::OperationFailed(format!("Failed to build tapered sides: {:?}", e)))?
+                .into_iter()
+                .collect();
+            if reversed {
+                // Edge i of the reversed loop is edge n - 2 - i of the given one
+                faces.reverse();
+                faces.rotate_left(1);
+            }
+            sides.extend(faces);
+            bottom_wires.push(bottom);
+            top_wires.push(top);
+        }
+
+        let bottom = builder::try_attach_plane(&bottom_wires)
+            .map_err(|e| KernelOpError::OperationFailed(format!("Failed to create face: {:?}", e)))?
+            .inverse();
+        let top = builder::try_attach_plane(&top_wires)
+            .map_err(|e| KernelOpError::OperationFailed(format!("Failed to create end face: {:?}", e)))?;
+        let shell: truck_modeling::Shell = std::iter::once(bottom).chain(sides).chain(std::iter::once(top)).collect();
+        Solid::try_new(vec![shell])
+            .map_err(|e| KernelOpError::OperationFailed(format!("Tapered extrusion is not a closed solid: {:?}", e)))
+    }
+
     /// Build a truck Wire from 2D points at z=0 (default).
     fn build_wire_from_points(&self, points: &[Point2D]) -> KernelResult<Wire> {
         self.build_wire_from_points_at_z(points, 0.0)
@@ -1210,3 +1413,52 @@ impl TruckKernel {
         Ok(circle_wire)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topo::EntityId;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_cylinder_side_is_one_smoothing_group() {
+        // Closed cylinder with shared vertices and no kernel face ids, so faces
+        // are grouped by normal smoothness
+        let segments = 16;
+        let mut mesh = TriangleMesh::new();
+        let ring = |mesh: &mut TriangleMesh, z: f64| -> Vec<u32> {
+            (0..segments).map(|i| {
+                let a = i as f64 * std::f64::consts::TAU / segments as f64;
+                mesh.add_vertex(Point3D::new(a.cos(), a.sin(), z))
+            }).collect()
+        };
+        let (bottom, top) = (ring(&mut mesh, 0.0), ring(&mut mesh, 2.0));
+        let (bottom_center, top_center) = (mesh.add_vertex(Point3D::new(0.0, 0.0, 0.0)), mesh.add_vertex(Point3D::new(0.0, 0.0, 2.0)));
+        let mut side = Vec::new();
+        for i in 0..segments {
+            let j = (i + 1) % segments;
+            side.extend([mesh.triangles.len(), mesh.triangles.len() + 1]);
+            mesh.add_triangle(bottom[i], bottom[j], top[j]);
+            mesh.add_triangle(bottom[i], top[j], top[i]);
+            mesh.add_triangle(bottom_center, bottom[j], bottom[i]);
+            mesh.add_triangle(top_center, top[i], top[j]);
+        }
+
+        let mut tessellation = Tessellation::new();
+        let ctx = NamingContext::new(EntityId::new());
+        TruckKernel::new().mesh_to_tessellation(&mesh, &mut tessellation, &mut HashMap::new(), &ctx, "Cylinder");
+
+        let groups = &tessellation.smoothing_group;
+        assert_eq!(groups.len(), tessellation.triangle_ids.len(), "One group per triangle");
+        let side_groups: HashSet<u32> = side.iter().map(|&t| groups[t]).collect();
+        assert_eq!(side_groups.len(), 1, "Side triangles share one smoothing group");
+        let cap_group = |center_tri: usize| -> HashSet<u32> {
+            (center_tri..groups.len()).step_by(4).map(|t| groups[t]).collect()
+        };
+        let (bottom_cap, top_cap) = (cap_group(2), cap_group(3));
+        assert_eq!(bottom_cap.len(), 1);
+        assert_eq!(top_cap.len(), 1);
+        let all: HashSet<u32> = side_groups.iter().chain(&bottom_cap).chain(&top_cap).copied().collect();
+        assert_eq!(all.len(), 3, "Caps are separate from the side and from each other");
+    }
+}
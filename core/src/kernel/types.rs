@@ -187,6 +187,19 @@ impl ExtrudeParams {
         self.start_offset = offset;
         self
     }
+
+    /// Scale the end of the extrusion by `scale` and turn it by `twist`
+    /// (radians), both about the profile's origin
+    pub fn with_end_transform(mut self, scale: (f64, f64), twist: f64) -> Self {
+        self.scale = scale;
+        self.twist = twist;
+        self
+    }
+
+    /// No taper or twist: the end is a translated copy of the profile
+    pub fn is_straight(&self) -> bool {
+        self.scale == (1.0, 1.0) && self.twist == 0.0
+    }
 }
 
 /// Parameters for revolution operations.
@@ -269,6 +282,9 @@ pub struct TriangleMesh {
     /// Optional per-triangle topological face ID.
     /// When present, triangles with the same face_id belong to the same logical face.
     pub face_ids: Vec<u32>,
+    /// The face ids already give one face per logical face, so curved faces
+    /// that the kernel split are not merged again when tessellating
+    pub exact_faces: bool,
 }
 
 impl TriangleMesh {
@@ -282,6 +298,7 @@ impl TriangleMesh {
             triangles: Vec::with_capacity(triangles),
             normals: None,
             face_ids: Vec::with_capacity(triangles),
+            exact_faces: false,
         }
     }
     
@@ -307,3 +324,50 @@ impl TriangleMesh {
         !self.face_ids.is_empty() && self.face_ids.len() == self.triangles.len()
     }
 }
+
+/// One solid read from a STEP file, in mm and placed in the assembly's root frame.
+#[derive(Debug, Clone)]
+pub struct ImportedSolid<S> {
+    /// Name of the product the solid belongs to (or of the solid itself)
+    pub name: String,
+    /// Placement applied to the part, row-major, translation in mm
+    pub transform: [[f64; 4]; 4],
+    pub solid: S,
+}
+
+/// Result of importing a STEP file.
+#[derive(Debug, Clone)]
+pub struct StepImportResult<S> {
+    /// One entry per placed solid; a part used twice in an assembly appears twice
+    pub solids: Vec<ImportedSolid<S>>,
+    /// The file's length unit ("mm", "inch", ...)
+    pub unit: String,
+    /// Factor applied to the file's lengths to get mm
+    pub scale_to_mm: f64,
+    /// Entities skipped and limits hit, one message each
+    pub warnings: Vec<String>,
+}
+
+/// Caps on what one STEP import may use.
+#[derive(Debug, Clone)]
+pub struct StepImportLimits {
+    /// Largest file accepted, in bytes
+    pub max_bytes: usize,
+    /// Most entity instances in the DATA section
+    pub max_entities: usize,
+    /// Most solids created; the rest are skipped with a warning
+    pub max_solids: usize,
+    /// Time for building solids; the rest are skipped with a warning
+    pub max_duration: std::time::Duration,
+}
+
+impl Default for StepImportLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024 * 1024,
+            max_entities: 1_000_000,
+            max_solids: 500,
+            max_duration: std::time::Duration::from_secs(60),
+        }
+    }
+}
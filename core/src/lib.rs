@@ -7,6 +7,10 @@ pub mod features;
 pub mod sketch;
 pub mod variables;
 pub mod kernel;
+pub mod analysis;
+pub mod document;
+pub mod errors;
+pub mod reporting;
 
 pub fn version() -> &'static str {
     "0.1.0"
@@ -0,0 +1,475 @@
+//! Reports on the model for drawings and manufacturing.
+//!
+//! `feature_summary` collects a hole table and a parameter table per feature
+//! from a feature graph and its evaluation. Holes are found from the faces,
+//! not the features: any complete concave cylindrical face is a hole, however
+//! it was made. Its axis and diameter come from the face's analytic geometry
+//! when the topology manifest has it, and from a cylinder fitted to the face's
+//! mesh otherwise; its depth is the face's extent along the axis.
+
+use std::collections::HashMap;
+
+use nalgebra::{Matrix3, SymmetricEigen};
+use serde::{Deserialize, Serialize};
+
+use crate::evaluator::runtime::EvaluationResult;
+use crate::features::dag::FeatureGraph;
+use crate::features::types::{Feature, ParameterValue};
+use crate::geometry::datum::plane_axes;
+use crate::geometry::offset::fit_circle;
+use crate::geometry::{Point3, Tessellation, Vector3};
+use crate::topo::naming::TopoId;
+use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+use crate::topo::EntityId;
+
+/// Diameters closer than this are the same hole size
+const SIZE_TOLERANCE: f64 = 1e-3;
+
+/// Largest angle around the axis a hole's mesh may leave uncovered (radians);
+/// anything more is a slot or a fillet, not a hole
+const MAX_ANGULAR_GAP: f64 = std::f64::consts::FRAC_PI_3;
+
+/// Which table a report request is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportKind {
+    Holes,
+    Features,
+}
+
+/// Whether a hole runs through its body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HoleKind {
+    Through,
+    Blind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hole {
+    /// Size letter and number within the size, e.g. "B2"
+    pub tag: String,
+    /// The hole's wall; more than one face where the kernel split it
+    pub faces: Vec<TopoId>,
+    /// Feature that made the face, if it is still in the graph
+    pub feature: Option<EntityId>,
+    pub feature_name: Option<String>,
+    /// On the axis, halfway along the hole
+    pub center: [f64; 3],
+    pub axis: [f64; 3],
+    pub diameter: f64,
+    pub depth: f64,
+    pub kind: HoleKind,
+}
+
+/// Holes of one diameter, smallest size first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoleGroup {
+    pub diameter: f64,
+    pub count: usize,
+    pub holes: Vec<Hole>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterRow {
+    pub name: String,
+    pub value: String,
+}
+
+/// A feature's parameters, in evaluation order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeatureSummary {
+    pub id: EntityId,
+    pub name: String,
+    pub feature_type: String,
+    pub suppressed: bool,
+    pub parameters: Vec<ParameterRow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub holes: Vec<HoleGroup>,
+    pub features: Vec<FeatureSummary>,
+}
+
+/// Hole table and feature parameter tables for an evaluated graph
+pub fn feature_summary(graph: &FeatureGraph, evaluation: &EvaluationResult) -> Report {
+    let owners = graph.topo_owners();
+    let mut holes: Vec<Hole> = find_holes(&evaluation.tessellation, &evaluation.topology_manifest)
+        .into_iter()
+        .map(|mut hole| {
+            hole.feature = owners.get(&hole.faces[0].feature_id).copied();
+            hole.feature_name = hole.feature.and_then(|id| graph.nodes.get(&id)).map(|f| f.name.clone());
+            hole
+        })
+        .collect();
+    holes.sort_by(|a, b| a.diameter.total_cmp(&b.diameter));
+
+    let mut groups: Vec<HoleGroup> = Vec::new();
+    for hole in holes {
+        match groups.last_mut() {
+            Some(group) if (group.diameter - hole.diameter).abs() < SIZE_TOLERANCE => group.holes.push(hole),
+            _ => groups.push(HoleGroup { diameter: hole.diameter, count: 0, holes: vec![hole] }),
+        }
+    }
+    for (index, group) in groups.iter_mut().enumerate() {
+        group.count = group.holes.len();
+        group.holes.sort_by(|a, b| {
+            a.center.iter().zip(b.center).map(|(p, q)| p.total_cmp(&q)).find(|o| o.is_ne()).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for (number, hole) in group.holes.iter_mut().enumerate() {
+            hole.tag = format!("{}{}", size_letter(index), number + 1);
+        }
+    }
+
+    let features = graph.sort_order.iter()
+        .filter_map(|id| graph.nodes.get(id))
+        .map(|feature| FeatureSummary {
+            id: feature.id,
+            name: feature.name.clone(),
+            feature_type: format!("{:?}", feature.feature_type),
+            suppressed: feature.suppressed,
+            parameters: parameter_rows(graph, feature),
+        })
+        .collect();
+    Report { holes: groups, features }
+}
+
+/// "A".."Z", then "AA", "AB", ...
+fn size_letter(index: usize) -> String {
+    let letter = |i: usize| char::from(b'A' + (i % 26) as u8);
+    if index < 26 { letter(index).to_string() } else { format!("{}{}", letter(index / 26 - 1), letter(index)) }
+}
+
+fn parameter_rows(graph: &FeatureGraph, feature: &Feature) -> Vec<ParameterRow> {
+    let mut rows: Vec<ParameterRow> = feature.parameters.iter().map(|(name, value)| {
+        let value = match value {
+            ParameterValue::Float(v) => v.to_string(),
+            ParameterValue::Expression(expr) => format!("{} = {}", expr, graph.resolve_float_param(feature, name, f64::NAN)),
+            ParameterValue::String(s) => s.clone(),
+            ParameterValue::Bool(b) => b.to_string(),
+            ParameterValue::List(items) => items.join(" "),
+            ParameterValue::Reference(id) => format!("{:?} {}", id.rank, id.feature_id),
+            ParameterValue::Sketch(sketch) => format!("{} entities, {} constraints", sketch.entities.len(), sketch.constraints.len()),
+            ParameterValue::ProfileRegions(regions) => format!("{} regions", regions.len()),
+            ParameterValue::RegionSpecs(specs) => format!("{} regions", specs.len()),
+        };
+        ParameterRow { name: name.clone(), value }
+    }).collect();
+    rows.sort_by(|a, b| a.name.cmp(&b.name));
+    rows
+}
+
+/// Axis of a face's cylinder: a point on it, its direction and the radius
+#[derive(Clone)]
+struct FaceCylinder {
+    origin: Point3,
+    axis: Vector3,
+    radius: f64,
+}
+
+impl FaceCylinder {
+    fn tolerance(&self) -> f64 {
+        SIZE_TOLERANCE.max(self.radius * 0.01)
+    }
+
+    /// Same axis line and radius
+    fn matches(&self, other: &FaceCylinder) -> bool {
+        let offset = other.origin - self.origin;
+        let off_axis = (offset - self.axis * offset.dot(&self.axis)).norm();
+        self.axis.dot(&other.axis).abs() > 1.0 - 1e-6
+            && (self.radius - other.radius).abs() < self.tolerance()
+            && off_axis < self.tolerance()
+    }
+}
+
+/// Concave cylindrical faces on one cylinder. Kernels often split a hole's
+/// wall into several faces.
+struct Bore {
+    faces: Vec<TopoId>,
+    /// From the manifest, if any of the faces has it
+    analytic: Option<FaceCylinder>,
+    fitted: FaceCylinder,
+    points: Vec<Point3>,
+    normals: Vec<Vector3>,
+}
+
+/// Complete concave cylindrical faces of the solid geometry
+fn find_holes(tess: &Tessellation, manifest: &HashMap<TopoId, KernelEntity>) -> Vec<Hole> {
+    let vertex = |i: u32| {
+        let i = i as usize * 3;
+        Point3::new(tess.vertices[i] as f64, tess.vertices[i + 1] as f64, tess.vertices[i + 2] as f64)
+    };
+    let normal = |i: u32| {
+        let i = i as usize * 3;
+        Vector3::new(tess.normals[i] as f64, tess.normals[i + 1] as f64, tess.normals[i + 2] as f64)
+    };
+    let mut face_vertices: HashMap<TopoId, Vec<u32>> = HashMap::new();
+    let mut face_order: Vec<TopoId> = Vec::new();
+    for (triangle, id) in tess.indices.chunks(3).zip(&tess.triangle_ids) {
+        if tess.is_reference(id) {
+            continue;
+        }
+        face_vertices.entry(*id).or_insert_with(|| {
+            face_order.push(*id);
+            Vec::new()
+        }).extend_from_slice(triangle);
+    }
+
+    let mut bores: Vec<Bore> = Vec::new();
+    for face in &face_order {
+        let indices = &face_vertices[face];
+        let points: Vec<Point3> = indices.iter().map(|&i| vertex(i)).collect();
+        let normals: Vec<Vector3> = indices.iter().map(|&i| normal(i)).collect();
+        let analytic = match manifest.get(face).map(|e| &e.geometry) {
+            Some(AnalyticGeometry::Cylinder { axis_start, axis_dir, radius }) => Some(FaceCylinder {
+                origin: Point3::from(*axis_start),
+                axis: canonical_direction(Vector3::from(*axis_dir).normalize()),
+                radius: *radius,
+            }),
+            Some(AnalyticGeometry::Plane { .. } | AnalyticGeometry::Mesh) | None => None,
+            Some(_) => continue,
+        };
+        let Some(cylinder) = analytic.clone().or_else(|| fit_cylinder(&points, &normals)) else { continue };
+        if !faces_axis(&cylinder, &points, &normals) {
+            continue;
+        }
+        match bores.iter_mut().find(|b| b.fitted.matches(&cylinder)) {
+            Some(bore) => {
+                bore.faces.push(*face);
+                bore.analytic = bore.analytic.take().or(analytic);
+                bore.points.extend(points);
+                bore.normals.extend(normals);
+            }
+            None => bores.push(Bore { faces: vec![*face], analytic, fitted: cylinder, points, normals }),
+        }
+    }
+
+    bores.into_iter().filter_map(|bore| {
+        // A fit over the whole wall is better than one over its first piece
+        let cylinder = match bore.analytic {
+            Some(cylinder) => cylinder,
+            None => fit_cylinder(&bore.points, &bore.normals)?,
+        };
+        if !goes_around(&cylinder, &bore.points) {
+            return None;
+        }
+
+        let along = |p: &Point3| (p - cylinder.origin).dot(&cylinder.axis);
+        let (low, high) = extent(bore.points.iter().map(along))?;
+        let body_extent = tess.body_of(&bore.faces[0]).and_then(|body| {
+            let body_points = tess.indices.chunks(3).zip(&tess.triangle_ids)
+                .filter(|(_, id)| tess.body_of(id) == Some(body))
+                .flat_map(|(triangle, _)| triangle.iter().map(|&i| along(&vertex(i))));
+            extent(body_points)
+        });
+        let tolerance = SIZE_TOLERANCE * (1.0 + high - low);
+        let kind = match body_extent {
+            Some((min, max)) if low - min < tolerance && max - high < tolerance => HoleKind::Through,
+            _ => HoleKind::Blind,
+        };
+        let center = cylinder.origin + cylinder.axis * ((low + high) / 2.0);
+        Some(Hole {
+            tag: String::new(),
+            faces: bore.faces,
+            feature: None,
+            feature_name: None,
+            center: center.into(),
+            axis: cylinder.axis.into(),
+            diameter: cylinder.radius * 2.0,
+            depth: high - low,
+            kind,
+        })
+    }).collect()
+}
+
+fn extent(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    values.fold(None, |range, v| match range {
+        None => Some((v, v)),
+        Some((low, high)) => Some((low.min(v), high.max(v))),
+    })
+}
+
+/// The direction or its opposite, whichever has its largest component positive
+fn canonical_direction(direction: Vector3) -> Vector3 {
+    if direction[direction.iamax()] < 0.0 { -direction } else { direction }
+}
+
+/// Cylinder through a mesh face: its normals all lie across one direction,
+/// the axis, and its points on a circle around it
+fn fit_cylinder(points: &[Point3], normals: &[Vector3]) -> Option<FaceCylinder> {
+    if points.len() < 6 {
+        return None;
+    }
+    let covariance: Matrix3<f64> = normals.iter().map(|n| n * n.transpose()).sum();
+    let eigen = SymmetricEigen::new(covariance);
+    let mut order = [0, 1, 2];
+    order.sort_by(|&a, &b| eigen.eigenvalues[a].total_cmp(&eigen.eigenvalues[b]));
+    let total = eigen.eigenvalues.sum();
+    // Normals along the axis, or all parallel (a plane), aren't a cylinder
+    if eigen.eigenvalues[order[0]] > 1e-3 * total || eigen.eigenvalues[order[1]] < 1e-3 * total {
+        return None;
+    }
+    let axis = canonical_direction(eigen.eigenvectors.column(order[0]).into_owned().normalize());
+    let (u, v) = plane_axes(&axis);
+    let flat: Vec<[f64; 2]> = points.iter().map(|p| [p.coords.dot(&u), p.coords.dot(&v)]).collect();
+    let (center, radius) = fit_circle(&flat)?;
+    let cylinder = FaceCylinder { origin: Point3::from(u * center[0] + v * center[1]), axis, radius };
+    let on_circle = flat.iter().all(|p| ((p[0] - center[0]).hypot(p[1] - center[1]) - radius).abs() < cylinder.tolerance());
+    on_circle.then_some(cylinder)
+}
+
+/// Every normal points at the axis, as on the wall of a hole
+fn faces_axis(cylinder: &FaceCylinder, points: &[Point3], normals: &[Vector3]) -> bool {
+    points.iter().zip(normals).all(|(p, n)| {
+        let offset = p - cylinder.origin;
+        (offset - cylinder.axis * offset.dot(&cylinder.axis)).dot(n) < 0.0
+    })
+}
+
+/// The points go all the way around the axis
+fn goes_around(cylinder: &FaceCylinder, points: &[Point3]) -> bool {
+    let (u, v) = plane_axes(&cylinder.axis);
+    let mut angles: Vec<f64> = points.iter().map(|p| {
+        let offset = p - cylinder.origin;
+        offset.dot(&v).atan2(offset.dot(&u))
+    }).collect();
+    angles.sort_by(f64::total_cmp);
+    let (Some(first), Some(last)) = (angles.first(), angles.last()) else { return false };
+    let wrap = first + std::f64::consts::TAU - last;
+    angles.windows(2).map(|w| w[1] - w[0]).chain(std::iter::once(wrap)).all(|gap| gap <= MAX_ANGULAR_GAP)
+}
+
+impl Report {
+    /// One table as CSV: a row per hole, or a row per feature parameter
+    pub fn to_csv(&self, kind: ReportKind) -> String {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        match kind {
+            ReportKind::Holes => {
+                rows.push(["tag", "diameter", "x", "y", "z", "depth", "type", "feature"].map(String::from).to_vec());
+                for hole in self.holes.iter().flat_map(|g| &g.holes) {
+                    let [x, y, z] = hole.center;
+                    rows.push(vec![
+                        hole.tag.clone(),
+                        hole.diameter.to_string(),
+                        x.to_string(),
+                        y.to_string(),
+                        z.to_string(),
+                        hole.depth.to_string(),
+                        format!("{:?}", hole.kind),
+                        hole.feature_name.clone().unwrap_or_default(),
+                    ]);
+                }
+            }
+            ReportKind::Features => {
+                rows.push(["feature", "type", "suppressed", "parameter", "value"].map(String::from).to_vec());
+                for feature in &self.features {
+                    for parameter in &feature.parameters {
+                        rows.push(vec![
+                            feature.name.clone(),
+                            feature.feature_type.clone(),
+                            feature.suppressed.to_string(),
+                            parameter.name.clone(),
+                            parameter.value.clone(),
+                        ]);
+                    }
+                }
+            }
+        }
+        rows.iter().map(|row| row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(",") + "\n").collect()
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::runtime::Runtime;
+    use crate::features::types::FeatureType;
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+    use crate::topo::IdGenerator;
+
+    /// 60 x 30 plate, 5 thick, with holes at `holes` ((x, y), radius)
+    fn plate(holes: &[([f64; 2], f64)]) -> FeatureGraph {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let c = [[0.0, 0.0], [60.0, 0.0], [60.0, 30.0], [0.0, 30.0]];
+        for i in 0..4 {
+            sketch.add_entity(SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] });
+        }
+        for &(center, radius) in holes {
+            sketch.add_entity(SketchGeometry::Circle { center, radius });
+        }
+        // The plate region, without the discs inside the holes
+        let regions = crate::sketch::regions::find_regions_with_segments(&sketch.entities, 32);
+        let region = regions.into_iter().max_by_key(|r| r.voids.len()).unwrap();
+        let mut loops = vec![region.boundary_points];
+        loops.extend(region.voids);
+
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch).with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Plate", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(5.0))
+            .with_param("profile_regions", ParameterValue::ProfileRegions(vec![loops]));
+        extrude.dependencies.push(sketch_feature.id);
+        let mut graph = FeatureGraph::new();
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        graph
+    }
+
+    #[test]
+    fn test_hole_table_groups_holes_by_size() {
+        let mut graph = plate(&[([10.0, 10.0], 2.0), ([50.0, 10.0], 2.0), ([30.0, 20.0], 4.0)]);
+        let evaluation = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("Report")).unwrap();
+        let report = feature_summary(&graph, &evaluation);
+
+        assert_eq!(report.holes.len(), 2, "{:#?}", report.holes);
+        let (small, large) = (&report.holes[0], &report.holes[1]);
+        assert!((small.diameter - 4.0).abs() < 1e-3 && small.count == 2);
+        assert!((large.diameter - 8.0).abs() < 1e-3 && large.count == 1);
+        let expected = [("A1", [10.0, 10.0]), ("A2", [50.0, 10.0]), ("B1", [30.0, 20.0])];
+        for (hole, (tag, [x, y])) in small.holes.iter().chain(&large.holes).zip(expected) {
+            assert_eq!(hole.tag, tag);
+            assert!((hole.center[0] - x).abs() < 1e-2 && (hole.center[1] - y).abs() < 1e-2, "{:?}", hole.center);
+            assert!((hole.center[2] - 2.5).abs() < 1e-3 && (hole.depth - 5.0).abs() < 1e-3);
+            assert_eq!(hole.kind, HoleKind::Through);
+            assert!((hole.axis[2] - 1.0).abs() < 1e-9, "{:?}", hole.axis);
+            assert_eq!(hole.feature_name.as_deref(), Some("Plate"));
+        }
+
+        let csv = report.to_csv(ReportKind::Holes);
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.lines().nth(3).unwrap().starts_with("B1,"));
+        let plate = report.features.iter().find(|f| f.name == "Plate").unwrap();
+        assert!(plate.parameters.iter().any(|p| p.name == "distance" && p.value == "5"));
+        assert!(report.to_csv(ReportKind::Features).contains("Plate,Extrude,false,distance,5\n"));
+    }
+
+    #[test]
+    fn test_analytic_cylinder_position_is_used() {
+        // A boss is convex, so it isn't a hole
+        let mut graph = FeatureGraph::new();
+        graph.add_node(Feature::new("Cylinder1", FeatureType::Cylinder));
+        let mut evaluation = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("Report")).unwrap();
+        assert!(feature_summary(&graph, &evaluation).holes.is_empty());
+
+        // Turned inside out it is, centered where the manifest puts the axis
+        for n in evaluation.tessellation.normals.iter_mut() {
+            *n = -*n;
+        }
+        for entity in evaluation.topology_manifest.values_mut() {
+            if let AnalyticGeometry::Cylinder { axis_start, .. } = &mut entity.geometry {
+                *axis_start = [1e-4, 0.0, 0.0];
+            }
+        }
+        let report = feature_summary(&graph, &evaluation);
+        assert_eq!(report.holes.len(), 1);
+        let hole = &report.holes[0].holes[0];
+        assert_eq!(hole.center, [1e-4, 0.0, 5.0]);
+        assert_eq!(hole.diameter, 10.0);
+    }
+}
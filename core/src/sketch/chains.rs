@@ -0,0 +1,770 @@
+//! Chains of connected sketch curves
+//!
+//! Links lines and arcs end to end into ordered chains by hashing their
+//! endpoints, so the result does not depend on the order entities were drawn
+//! or which way round they run. Circles and ellipses are standalone closed
+//! chains. Where three or more curves meet the profile is ambiguous: chains
+//! stop there and the junction is reported as a branch point instead.
+//!
+//! `trim_to` and `extend_to` move a line's end to where it meets another curve.
+//! `pattern_along_path` copies curves to stations spaced evenly along a chain.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use super::edit::constraint_points_mut;
+use super::types::{ellipse_point, ConstraintPoint, Sketch, SketchEntity, SketchGeometry};
+use crate::geometry::utils_2d;
+use crate::topo::EntityId;
+use serde::{Deserialize, Serialize};
+
+/// Endpoints closer than this are treated as connected
+pub const DEFAULT_CHAIN_TOLERANCE: f64 = 1e-6;
+
+/// One curve of a chain, in chain direction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainSegment {
+    pub entity_id: EntityId,
+    /// The chain runs from the entity's end to its start
+    pub reversed: bool,
+    pub start: [f64; 2],
+    pub end: [f64; 2],
+    #[serde(skip)]
+    geometry: SketchGeometry,
+}
+
+impl ChainSegment {
+    /// The entity's curve, as drawn (not reversed)
+    pub fn geometry(&self) -> &SketchGeometry {
+        &self.geometry
+    }
+
+    fn reverse(&mut self) {
+        self.reversed = !self.reversed;
+        std::mem::swap(&mut self.start, &mut self.end);
+    }
+
+    /// Points from `start` towards `end`, excluding `end`
+    fn sample(&self, segments_per_circle: usize, out: &mut Vec<[f64; 2]>) {
+        match self.geometry {
+            SketchGeometry::Line { .. } => out.push(self.start),
+            SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+                let sweep = arc_sweep(start_angle, end_angle);
+                let steps = ((sweep / TAU * segments_per_circle as f64).ceil() as usize).max(1);
+                let (from, step) = if self.reversed {
+                    (start_angle + sweep, -sweep / steps as f64)
+                } else {
+                    (start_angle, sweep / steps as f64)
+                };
+                out.extend((0..steps).map(|i| {
+                    let a = from + step * i as f64;
+                    [center[0] + radius * a.cos(), center[1] + radius * a.sin()]
+                }));
+            }
+            SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+                let sweep = arc_sweep(start_angle, end_angle);
+                let steps = ((sweep / TAU * segments_per_circle as f64).ceil() as usize).max(1);
+                let (from, step) = if self.reversed {
+                    (start_angle + sweep, -sweep / steps as f64)
+                } else {
+                    (start_angle, sweep / steps as f64)
+                };
+                out.extend((0..steps).map(|i| ellipse_point(center, semi_major, semi_minor, rotation, from + step * i as f64)));
+            }
+            SketchGeometry::Circle { center, radius } => {
+                out.extend((0..segments_per_circle).map(|i| {
+                    let a = TAU * i as f64 / segments_per_circle as f64;
+                    [center[0] + radius * a.cos(), center[1] + radius * a.sin()]
+                }));
+            }
+            SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => {
+                let (sin_r, cos_r) = rotation.sin_cos();
+                out.extend((0..segments_per_circle).map(|i| {
+                    let t = TAU * i as f64 / segments_per_circle as f64;
+                    let (x, y) = (semi_major * t.cos(), semi_minor * t.sin());
+                    [center[0] + x * cos_r - y * sin_r, center[1] + x * sin_r + y * cos_r]
+                }));
+            }
+            SketchGeometry::Point { .. } => {}
+        }
+    }
+}
+
+/// Curves connected end to end
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Chain {
+    pub segments: Vec<ChainSegment>,
+    /// The last segment ends where the first starts
+    pub closed: bool,
+}
+
+impl Chain {
+    pub fn entity_ids(&self) -> Vec<EntityId> {
+        self.segments.iter().map(|s| s.entity_id).collect()
+    }
+
+    /// Polyline through the chain, with circles and arcs discretized at
+    /// `segments_per_circle` per full turn. Closed chains don't repeat the first point.
+    pub fn points(&self, segments_per_circle: usize) -> Vec<[f64; 2]> {
+        let segments_per_circle = segments_per_circle.max(3);
+        let mut points = Vec::new();
+        for segment in &self.segments {
+            segment.sample(segments_per_circle, &mut points);
+        }
+        if let (false, Some(last)) = (self.closed, self.segments.last()) {
+            points.push(last.end);
+        }
+        points
+    }
+
+    fn reverse(&mut self) {
+        self.segments.reverse();
+        self.segments.iter_mut().for_each(ChainSegment::reverse);
+    }
+}
+
+/// A point where three or more curve ends meet
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BranchPoint {
+    pub position: [f64; 2],
+    pub entity_ids: Vec<EntityId>,
+}
+
+/// Why part of a sketch cannot be used as a closed profile
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum ProfileDiagnostic {
+    /// A chain whose ends don't meet
+    OpenChain { entity_ids: Vec<EntityId>, ends: [[f64; 2]; 2] },
+    /// Curves meeting at a junction, so there is no single way to continue the profile
+    Branch { position: [f64; 2], entity_ids: Vec<EntityId> },
+}
+
+/// Open curves (lines, partial arcs) with their ends welded into shared nodes
+struct EndpointGraph {
+    segments: Vec<ChainSegment>,
+    /// Node index at the start and end of each segment
+    ends: Vec<[usize; 2]>,
+    nodes: Vec<[f64; 2]>,
+    /// (segment, 0 = start / 1 = end) touching each node
+    incident: Vec<Vec<(usize, usize)>>,
+    /// Circles, ellipses and full arcs
+    closed_curves: Vec<ChainSegment>,
+}
+
+impl EndpointGraph {
+    fn new(entities: &[SketchEntity], tolerance: f64) -> Self {
+        let mut curves: Vec<&SketchEntity> = entities.iter().filter(|e| !e.is_construction).collect();
+        // Sorting by id makes everything below independent of drawing order
+        curves.sort_by_key(|e| e.id.0);
+
+        let mut graph = EndpointGraph {
+            segments: Vec::new(),
+            ends: Vec::new(),
+            nodes: Vec::new(),
+            incident: Vec::new(),
+            closed_curves: Vec::new(),
+        };
+        let cell = tolerance.max(1e-12);
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+        for entity in curves {
+            let (start, end) = match entity.geometry {
+                SketchGeometry::Line { start, end } => (start, end),
+                SketchGeometry::Arc { center, radius, start_angle, end_angle } => (
+                    [center[0] + radius * start_angle.cos(), center[1] + radius * start_angle.sin()],
+                    [center[0] + radius * end_angle.cos(), center[1] + radius * end_angle.sin()],
+                ),
+                SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => (
+                    ellipse_point(center, semi_major, semi_minor, rotation, start_angle),
+                    ellipse_point(center, semi_major, semi_minor, rotation, end_angle),
+                ),
+                // Closed curves start and end where their sampling starts
+                SketchGeometry::Circle { center, radius } => {
+                    let p = [center[0] + radius, center[1]];
+                    (p, p)
+                }
+                SketchGeometry::Ellipse { center, semi_major, rotation, .. } => {
+                    let p = [center[0] + semi_major * rotation.cos(), center[1] + semi_major * rotation.sin()];
+                    (p, p)
+                }
+                SketchGeometry::Point { .. } => continue,
+            };
+            let segment = ChainSegment { entity_id: entity.id, reversed: false, start, end, geometry: entity.geometry.clone() };
+            match entity.geometry {
+                SketchGeometry::Circle { .. } | SketchGeometry::Ellipse { .. } => graph.closed_curves.push(segment),
+                _ if utils_2d::distance(start, end) <= tolerance => {
+                    // A full arc is a closed curve; a zero-length line is nothing
+                    if matches!(entity.geometry, SketchGeometry::Arc { .. } | SketchGeometry::EllipseArc { .. }) {
+                        graph.closed_curves.push(segment);
+                    }
+                }
+                _ => {
+                    let ends = [start, end].map(|p| graph.weld(p, tolerance, cell, &mut grid));
+                    let index = graph.segments.len();
+                    graph.incident[ends[0]].push((index, 0));
+                    graph.incident[ends[1]].push((index, 1));
+                    graph.segments.push(segment);
+                    graph.ends.push(ends);
+                }
+            }
+        }
+        graph
+    }
+
+    /// Node within `tolerance` of `p`, created if there is none
+    fn weld(&mut self, p: [f64; 2], tolerance: f64, cell: f64, grid: &mut HashMap<(i64, i64), Vec<usize>>) -> usize {
+        let key = ((p[0] / cell).floor() as i64, (p[1] / cell).floor() as i64);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let Some(candidates) = grid.get(&(key.0 + dx, key.1 + dy)) else { continue };
+                if let Some(&node) = candidates.iter().find(|&&n| utils_2d::distance(self.nodes[n], p) <= tolerance) {
+                    return node;
+                }
+            }
+        }
+        self.nodes.push(p);
+        self.incident.push(Vec::new());
+        grid.entry(key).or_default().push(self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.incident[node].len()
+    }
+
+    /// Follow segments from `first` (leaving through its `from_end`) across nodes
+    /// where exactly two curves meet
+    fn walk(&self, first: usize, from_end: usize, used: &mut [bool]) -> Chain {
+        let start_node = self.ends[first][from_end];
+        let (mut current, mut entry) = (first, from_end);
+        let mut segments = Vec::new();
+        loop {
+            used[current] = true;
+            let mut segment = self.segments[current].clone();
+            if entry == 1 {
+                segment.reverse();
+            }
+            segments.push(segment);
+
+            let node = self.ends[current][1 - entry];
+            if node == start_node {
+                return Chain { segments, closed: true };
+            }
+            if self.degree(node) != 2 {
+                break;
+            }
+            match self.incident[node].iter().find(|(s, _)| !used[*s]) {
+                Some(&(next, end)) => (current, entry) = (next, end),
+                None => break,
+            }
+        }
+        Chain { segments, closed: false }
+    }
+
+    fn branch_points(&self) -> Vec<BranchPoint> {
+        (0..self.nodes.len()).filter(|&n| self.degree(n) > 2).map(|n| {
+            let mut entity_ids: Vec<EntityId> = self.incident[n].iter().map(|(s, _)| self.segments[*s].entity_id).collect();
+            entity_ids.sort_by_key(|id| id.0);
+            entity_ids.dedup();
+            BranchPoint { position: self.nodes[n], entity_ids }
+        }).collect()
+    }
+}
+
+/// Link the non-construction curves of a sketch into chains.
+///
+/// Chains never continue through a branch point (see `find_branch_points`), so
+/// curves meeting at a junction end up in separate open chains. Closed chains
+/// run counter-clockwise, starting at the curve with the smallest id.
+pub fn build_chains(entities: &[SketchEntity], tolerance: f64) -> Vec<Chain> {
+    let graph = EndpointGraph::new(entities, tolerance);
+    let mut used = vec![false; graph.segments.len()];
+    let mut chains = Vec::new();
+
+    // Open chains start at free ends and junctions
+    for node in 0..graph.nodes.len() {
+        if graph.degree(node) == 2 {
+            continue;
+        }
+        for &(segment, end) in &graph.incident[node] {
+            if !used[segment] {
+                chains.push(graph.walk(segment, end, &mut used));
+            }
+        }
+    }
+    // Whatever is left forms loops
+    for segment in 0..graph.segments.len() {
+        if used[segment] {
+            continue;
+        }
+        let mut chain = graph.walk(segment, 0, &mut used);
+        if chain.closed {
+            if utils_2d::polygon_signed_area(&chain.points(16)) < 0.0 {
+                chain.reverse();
+            }
+            let first = (0..chain.segments.len()).min_by_key(|&i| chain.segments[i].entity_id.0).unwrap_or(0);
+            chain.segments.rotate_left(first);
+        }
+        chains.push(chain);
+    }
+
+    chains.extend(graph.closed_curves.into_iter().map(|segment| Chain { segments: vec![segment], closed: true }));
+    chains
+}
+
+/// Points where three or more curve ends meet
+pub fn find_branch_points(entities: &[SketchEntity], tolerance: f64) -> Vec<BranchPoint> {
+    EndpointGraph::new(entities, tolerance).branch_points()
+}
+
+/// Open chains and branch points that keep curves from forming closed profiles
+pub fn profile_diagnostics(entities: &[SketchEntity], tolerance: f64) -> Vec<ProfileDiagnostic> {
+    let mut diagnostics: Vec<ProfileDiagnostic> = find_branch_points(entities, tolerance).into_iter()
+        .map(|b| ProfileDiagnostic::Branch { position: b.position, entity_ids: b.entity_ids })
+        .collect();
+    diagnostics.extend(build_chains(entities, tolerance).into_iter().filter(|c| !c.closed).map(|chain| {
+        let ends = [chain.segments[0].start, chain.segments[chain.segments.len() - 1].end];
+        ProfileDiagnostic::OpenChain { entity_ids: chain.entity_ids(), ends }
+    }));
+    diagnostics
+}
+
+/// Outcome of `trim_to` and `extend_to`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrimResult {
+    /// The target's end that moved
+    pub endpoint: ConstraintPoint,
+    /// Where it moved to: the intersection with the cutting curve
+    pub point: [f64; 2],
+    /// Constraints on the moved end that would have pulled it back, removed.
+    /// Indices are from before the removal.
+    pub removed_constraints: Vec<usize>,
+}
+
+/// Trim the line `target` back to where it crosses `cutting`, keeping the
+/// side `keep_point` is on. When it crosses more than once, the crossing
+/// nearest `keep_point` is used.
+pub fn trim_to(sketch: &mut Sketch, target: EntityId, cutting: EntityId, keep_point: [f64; 2]) -> Result<TrimResult, String> {
+    let (start, end, crossings) = target_crossings(sketch, target, cutting)?;
+    let keep = utils_2d::project_point_on_line(start, end, keep_point);
+    let t = crossings.into_iter()
+        .filter(|t| (DEFAULT_CHAIN_TOLERANCE..=1.0 - DEFAULT_CHAIN_TOLERANCE).contains(t))
+        .min_by(|a, b| (a - keep).abs().total_cmp(&(b - keep).abs()))
+        .ok_or_else(|| "The line does not cross the cutting curve".to_string())?;
+    // Keeping the part before the crossing moves the end, and vice versa
+    Ok(move_end(sketch, target, if keep < t { 1 } else { 0 }, utils_2d::lerp(start, end, t)))
+}
+
+/// Extend the line `target` until it meets `cutting`, from the end nearest
+/// `near_point`. The nearest meeting point beyond that end is used.
+pub fn extend_to(sketch: &mut Sketch, target: EntityId, cutting: EntityId, near_point: [f64; 2]) -> Result<TrimResult, String> {
+    let (start, end, crossings) = target_crossings(sketch, target, cutting)?;
+    let from_end = utils_2d::distance(near_point, end) < utils_2d::distance(near_point, start);
+    // Distance beyond the chosen end, as a fraction of the line
+    let beyond = |t: f64| if from_end { t - 1.0 } else { -t };
+    let t = crossings.into_iter()
+        .filter(|t| beyond(*t) > DEFAULT_CHAIN_TOLERANCE)
+        .min_by(|a, b| beyond(*a).total_cmp(&beyond(*b)))
+        .ok_or_else(|| "Extending the line does not meet the cutting curve".to_string())?;
+    Ok(move_end(sketch, target, if from_end { 1 } else { 0 }, utils_2d::lerp(start, end, t)))
+}
+
+/// A target line's start and end, and the parameters where it meets the cutting curve
+type Crossings = ([f64; 2], [f64; 2], Vec<f64>);
+
+/// The target line's ends and the parameters along its infinite carrier
+/// (0 = start, 1 = end) where it meets the cutting line, circle or arc
+fn target_crossings(sketch: &Sketch, target: EntityId, cutting: EntityId) -> Result<Crossings, String> {
+    let geometry = |id: EntityId| sketch.entities.iter().find(|e| e.id == id).map(|e| &e.geometry)
+        .ok_or_else(|| format!("Entity {} not found in sketch", id));
+    let SketchGeometry::Line { start, end } = *geometry(target)? else {
+        return Err("Only lines can be trimmed or extended".to_string());
+    };
+    if target == cutting {
+        return Err("A line cannot be trimmed to itself".to_string());
+    }
+    if sketch.external_references.contains_key(&target) {
+        return Err("Projected geometry cannot be trimmed or extended".to_string());
+    }
+
+    let crossings = match *geometry(cutting)? {
+        SketchGeometry::Line { start: s, end: e } => utils_2d::line_line_intersect_unbounded(start, end, s, e)
+            .filter(|(_, _, u)| (-DEFAULT_CHAIN_TOLERANCE..=1.0 + DEFAULT_CHAIN_TOLERANCE).contains(u))
+            .map(|(_, t, _)| t)
+            .into_iter()
+            .collect(),
+        SketchGeometry::Circle { center, radius } => line_circle_parameters(start, end, center, radius),
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+            let sweep = match (end_angle - start_angle).rem_euclid(TAU) {
+                s if s < 1e-12 => TAU,
+                s => s,
+            };
+            line_circle_parameters(start, end, center, radius).into_iter().filter(|t| {
+                let p = utils_2d::lerp(start, end, *t);
+                let along = ((p[1] - center[1]).atan2(p[0] - center[0]) - start_angle).rem_euclid(TAU);
+                along <= sweep + DEFAULT_CHAIN_TOLERANCE || TAU - along < DEFAULT_CHAIN_TOLERANCE
+            }).collect()
+        }
+        _ => return Err("Lines can only be trimmed or extended to lines, circles and arcs".to_string()),
+    };
+    Ok((start, end, crossings))
+}
+
+/// Parameters along the infinite line through `start` and `end` where it meets the circle
+fn line_circle_parameters(start: [f64; 2], end: [f64; 2], center: [f64; 2], radius: f64) -> Vec<f64> {
+    let d = [end[0] - start[0], end[1] - start[1]];
+    let f = [start[0] - center[0], start[1] - center[1]];
+    let a = utils_2d::dot_2d(d, d);
+    let b = 2.0 * utils_2d::dot_2d(f, d);
+    let c = utils_2d::dot_2d(f, f) - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if a < 1e-12 || discriminant < 0.0 {
+        return Vec::new();
+    }
+    let root = discriminant.sqrt();
+    vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+}
+
+/// Move end `index` of the line `target` to `point`, dropping the constraints
+/// that hold that end where it was
+fn move_end(sketch: &mut Sketch, target: EntityId, index: u8, point: [f64; 2]) -> TrimResult {
+    if let Some(SketchGeometry::Line { start, end }) = sketch.entities.iter_mut().find(|e| e.id == target).map(|e| &mut e.geometry) {
+        *if index == 0 { start } else { end } = point;
+    }
+    let endpoint = ConstraintPoint { id: target, index };
+    let mut removed_constraints = Vec::new();
+    let mut i = 0;
+    sketch.constraints.retain_mut(|entry| {
+        let holds_end = constraint_points_mut(&mut entry.constraint).into_iter().any(|p| *p == endpoint);
+        if holds_end {
+            removed_constraints.push(i);
+        }
+        i += 1;
+        !holds_end
+    });
+    TrimResult { endpoint, point, removed_constraints }
+}
+
+/// Copy `entity_ids` to `count` stations spaced evenly by length along the
+/// chain of lines and arcs through `path`, which is built from curves of the
+/// same kind (construction or not) as `path`. The originals count as the first
+/// station: the end of an open path nearest them, or the start of a closed
+/// one. Each copy is moved and turned as the path turns from the first
+/// station to its own. Copies carry no constraints. Returns their ids, one
+/// list per station after the first.
+pub fn pattern_along_path(sketch: &mut Sketch, entity_ids: &[EntityId], path: EntityId, count: usize) -> Result<Vec<Vec<EntityId>>, String> {
+    if count < 2 {
+        return Err("A pattern needs at least two instances".to_string());
+    }
+    if entity_ids.contains(&path) {
+        return Err("The path cannot be patterned along itself".to_string());
+    }
+    let originals = entity_ids.iter()
+        .map(|id| sketch.entities.iter().find(|e| e.id == *id).cloned().ok_or_else(|| format!("Entity {} not found", id)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let Some(anchor) = originals.first().map(|e| reference_point(&e.geometry)) else {
+        return Err("Nothing to pattern".to_string());
+    };
+    let construction = sketch.entities.iter().find(|e| e.id == path).ok_or_else(|| format!("Path {} not found", path))?.is_construction;
+    // Chains only link non-construction curves, so treat the path's kind as such
+    let candidates: Vec<SketchEntity> = sketch.entities.iter()
+        .filter(|e| !entity_ids.contains(&e.id))
+        .map(|e| SketchEntity { is_construction: e.is_construction != construction, ..e.clone() })
+        .collect();
+    let mut chain = build_chains(&candidates, DEFAULT_CHAIN_TOLERANCE).into_iter()
+        .find(|chain| chain.segments.iter().any(|s| s.entity_id == path))
+        .ok_or_else(|| "The path must be a line or arc".to_string())?;
+    if chain.segments.iter().any(|s| !matches!(s.geometry, SketchGeometry::Line { .. } | SketchGeometry::Arc { .. })) {
+        return Err("Paths can only run along lines and arcs".to_string());
+    }
+    if let (false, Some(first), Some(last)) = (chain.closed, chain.segments.first(), chain.segments.last()) {
+        if utils_2d::distance(anchor, last.end) < utils_2d::distance(anchor, first.start) {
+            chain.reverse();
+        }
+    }
+
+    let lengths: Vec<f64> = chain.segments.iter().map(ChainSegment::path_length).collect();
+    let total: f64 = lengths.iter().sum();
+    if total < DEFAULT_CHAIN_TOLERANCE {
+        return Err("The path has no length".to_string());
+    }
+    let spacing = total / if chain.closed { count } else { count - 1 } as f64;
+    let station = |mut s: f64| {
+        let last = chain.segments.len() - 1;
+        for (segment, length) in chain.segments[..last].iter().zip(&lengths) {
+            if s <= *length {
+                return segment.station(s);
+            }
+            s -= length;
+        }
+        chain.segments[last].station(s.min(lengths[last]))
+    };
+
+    let (origin, origin_tangent) = station(0.0);
+    let mut copies = Vec::with_capacity(count - 1);
+    for i in 1..count {
+        let (position, tangent) = station(spacing * i as f64);
+        let turn = tangent[1].atan2(tangent[0]) - origin_tangent[1].atan2(origin_tangent[0]);
+        let ids = originals.iter().map(|original| {
+            let id = sketch.add_entity(moved(&original.geometry, origin, position, turn));
+            if let Some(copy) = sketch.entities.last_mut() {
+                copy.is_construction = original.is_construction;
+            }
+            id
+        }).collect();
+        copies.push(ids);
+    }
+    Ok(copies)
+}
+
+impl ChainSegment {
+    fn path_length(&self) -> f64 {
+        match self.geometry {
+            SketchGeometry::Arc { radius, start_angle, end_angle, .. } => radius * arc_sweep(start_angle, end_angle),
+            _ => utils_2d::distance(self.start, self.end),
+        }
+    }
+
+    /// Point and unit direction `s` along a line or arc segment, in chain direction
+    fn station(&self, s: f64) -> ([f64; 2], [f64; 2]) {
+        match self.geometry {
+            SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+                let (angle, turning) = if self.reversed {
+                    (start_angle + arc_sweep(start_angle, end_angle) - s / radius, -1.0)
+                } else {
+                    (start_angle + s / radius, 1.0)
+                };
+                let (sin, cos) = angle.sin_cos();
+                (utils_2d::arc_point(center, radius, angle), [-sin * turning, cos * turning])
+            }
+            _ => {
+                let length = utils_2d::distance(self.start, self.end);
+                (utils_2d::lerp(self.start, self.end, s / length.max(1e-12)), utils_2d::normalize_2d([self.end[0] - self.start[0], self.end[1] - self.start[1]]))
+            }
+        }
+    }
+}
+
+/// Counter-clockwise sweep of an arc, a full turn when its angles match
+fn arc_sweep(start_angle: f64, end_angle: f64) -> f64 {
+    match (end_angle - start_angle).rem_euclid(TAU) {
+        s if s < 1e-12 => TAU,
+        s => s,
+    }
+}
+
+/// Where a curve is taken to be when choosing which end of a path it sits at
+fn reference_point(geometry: &SketchGeometry) -> [f64; 2] {
+    match *geometry {
+        SketchGeometry::Point { pos } => pos,
+        SketchGeometry::Line { start, .. } => start,
+        SketchGeometry::Circle { center, .. }
+        | SketchGeometry::Arc { center, .. }
+        | SketchGeometry::Ellipse { center, .. }
+        | SketchGeometry::EllipseArc { center, .. } => center,
+    }
+}
+
+/// `geometry` turned by `turn` about `from`, then moved so `from` lands on `to`
+fn moved(geometry: &SketchGeometry, from: [f64; 2], to: [f64; 2], turn: f64) -> SketchGeometry {
+    let (sin, cos) = turn.sin_cos();
+    let place = |p: [f64; 2]| {
+        let (dx, dy) = (p[0] - from[0], p[1] - from[1]);
+        [to[0] + dx * cos - dy * sin, to[1] + dx * sin + dy * cos]
+    };
+    match *geometry {
+        SketchGeometry::Point { pos } => SketchGeometry::Point { pos: place(pos) },
+        SketchGeometry::Line { start, end } => SketchGeometry::Line { start: place(start), end: place(end) },
+        SketchGeometry::Circle { center, radius } => SketchGeometry::Circle { center: place(center), radius },
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => SketchGeometry::Arc {
+            center: place(center),
+            radius,
+            start_angle: start_angle + turn,
+            end_angle: end_angle + turn,
+        },
+        SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => SketchGeometry::Ellipse {
+            center: place(center),
+            semi_major,
+            semi_minor,
+            rotation: rotation + turn,
+        },
+        // Parametric angles are measured in the ellipse's own axes, so they turn with it
+        SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => SketchGeometry::EllipseArc {
+            center: place(center),
+            semi_major,
+            semi_minor,
+            rotation: rotation + turn,
+            start_angle,
+            end_angle,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::types::{SketchConstraint, SketchPlane};
+
+    /// Slot outline: two lines joined by two half circles, one line drawn backwards
+    fn slot_entities() -> Vec<SketchEntity> {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        sketch.add_entity(SketchGeometry::Arc { center: [10.0, 2.0], radius: 2.0, start_angle: -TAU / 4.0, end_angle: TAU / 4.0 });
+        sketch.add_entity(SketchGeometry::Line { start: [0.0, 4.0], end: [10.0, 4.0] });
+        sketch.add_entity(SketchGeometry::Arc { center: [0.0, 2.0], radius: 2.0, start_angle: TAU / 4.0, end_angle: 3.0 * TAU / 4.0 });
+        sketch.entities
+    }
+
+    #[test]
+    fn test_chain_is_independent_of_entity_order() {
+        let entities = slot_entities();
+        let chains = build_chains(&entities, DEFAULT_CHAIN_TOLERANCE);
+        assert_eq!(chains.len(), 1);
+        let chain = &chains[0];
+        assert!(chain.closed);
+        assert_eq!(chain.segments.len(), 4);
+        for pair in chain.segments.windows(2) {
+            assert!(utils_2d::distance(pair[0].end, pair[1].start) < 1e-9, "Segments join end to start");
+        }
+        let points = chain.points(32);
+        assert!(utils_2d::polygon_signed_area(&points) > 0.0, "Closed chains run counter-clockwise");
+        assert!((utils_2d::polygon_area(&points) - (40.0 + TAU * 2.0)).abs() < 0.2, "Slot area");
+
+        // Shuffled and with the top line flipped: same chain
+        let mut shuffled = vec![entities[2].clone(), entities[0].clone(), entities[3].clone(), entities[1].clone()];
+        shuffled[0].geometry = SketchGeometry::Line { start: [10.0, 4.0], end: [0.0, 4.0] };
+        let reshuffled = build_chains(&shuffled, DEFAULT_CHAIN_TOLERANCE);
+        assert_eq!(reshuffled.len(), 1);
+        assert!(reshuffled[0].closed);
+        assert_eq!(reshuffled[0].entity_ids(), chain.entity_ids());
+        assert!(profile_diagnostics(&shuffled, DEFAULT_CHAIN_TOLERANCE).is_empty());
+    }
+
+    #[test]
+    fn test_y_branch_is_reported_not_chained() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let stem = sketch.add_entity(SketchGeometry::Line { start: [0.0, -5.0], end: [0.0, 0.0] });
+        let left = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [-3.0, 4.0] });
+        let right = sketch.add_entity(SketchGeometry::Line { start: [3.0, 4.0], end: [0.0, 0.0] });
+        sketch.add_entity(SketchGeometry::Circle { center: [20.0, 0.0], radius: 1.0 });
+
+        let branches = find_branch_points(&sketch.entities, DEFAULT_CHAIN_TOLERANCE);
+        assert_eq!(branches.len(), 1);
+        assert!(utils_2d::distance(branches[0].position, [0.0, 0.0]) < 1e-9);
+        let mut ids = branches[0].entity_ids.clone();
+        ids.sort_by_key(|id| id.0);
+        let mut expected = vec![stem, left, right];
+        expected.sort_by_key(|id| id.0);
+        assert_eq!(ids, expected);
+
+        // Each arm is its own open chain; only the circle is closed
+        let chains = build_chains(&sketch.entities, DEFAULT_CHAIN_TOLERANCE);
+        assert_eq!(chains.iter().filter(|c| !c.closed).count(), 3);
+        assert!(chains.iter().filter(|c| !c.closed).all(|c| c.segments.len() == 1));
+        assert_eq!(chains.iter().filter(|c| c.closed).count(), 1);
+
+        let diagnostics = profile_diagnostics(&sketch.entities, DEFAULT_CHAIN_TOLERANCE);
+        assert_eq!(diagnostics.iter().filter(|d| matches!(d, ProfileDiagnostic::Branch { .. })).count(), 1);
+        assert_eq!(diagnostics.iter().filter(|d| matches!(d, ProfileDiagnostic::OpenChain { .. })).count(), 3);
+    }
+
+    fn line(sketch: &Sketch, id: EntityId) -> ([f64; 2], [f64; 2]) {
+        match sketch.entities.iter().find(|e| e.id == id).unwrap().geometry {
+            SketchGeometry::Line { start, end } => (start, end),
+            ref other => panic!("Not a line: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_trim_overshooting_line_to_crossing_line() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let wall = sketch.add_entity(SketchGeometry::Line { start: [10.0, -5.0], end: [10.0, 5.0] });
+        let overshoot = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [13.0, 0.0] });
+        let stub = sketch.add_entity(SketchGeometry::Line { start: [13.0, 0.0], end: [13.0, 3.0] });
+        sketch.add_constraint(SketchConstraint::Coincident {
+            points: [ConstraintPoint { id: overshoot, index: 1 }, ConstraintPoint { id: stub, index: 0 }],
+        });
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: overshoot });
+
+        let result = trim_to(&mut sketch, overshoot, wall, [2.0, 0.5]).unwrap();
+        assert_eq!(result.endpoint, ConstraintPoint { id: overshoot, index: 1 });
+        assert_eq!(line(&sketch, overshoot), ([0.0, 0.0], [10.0, 0.0]));
+        assert_eq!(result.removed_constraints, vec![0], "The trimmed-off end no longer meets the stub");
+        assert_eq!(sketch.constraints.len(), 1);
+
+        // Keeping the other side moves the start instead
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let wall = sketch.add_entity(SketchGeometry::Line { start: [10.0, -5.0], end: [10.0, 5.0] });
+        let overshoot = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [13.0, 0.0] });
+        trim_to(&mut sketch, overshoot, wall, [12.0, 0.0]).unwrap();
+        assert_eq!(line(&sketch, overshoot), ([10.0, 0.0], [13.0, 0.0]));
+        assert!(trim_to(&mut sketch, overshoot, wall, [12.0, 0.0]).is_err(), "It no longer crosses the wall");
+    }
+
+    #[test]
+    fn test_extend_short_line_to_meet_another() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let wall = sketch.add_entity(SketchGeometry::Line { start: [10.0, -5.0], end: [10.0, 5.0] });
+        let short = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [4.0, 2.0] });
+
+        let result = extend_to(&mut sketch, short, wall, [4.0, 2.0]).unwrap();
+        assert_eq!(result.endpoint, ConstraintPoint { id: short, index: 1 });
+        assert_eq!(line(&sketch, short), ([0.0, 0.0], [10.0, 5.0]));
+        assert!(extend_to(&mut sketch, short, wall, [0.0, 0.0]).is_err(), "The start points away from the wall");
+
+        // Extending to an arc stops at the near side of the arc
+        let arc = sketch.add_entity(SketchGeometry::Arc { center: [0.0, 20.0], radius: 5.0, start_angle: -TAU / 2.0, end_angle: 0.0 });
+        let riser = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [0.0, 1.0] });
+        extend_to(&mut sketch, riser, arc, [0.0, 1.0]).unwrap();
+        let (_, end) = line(&sketch, riser);
+        assert!(utils_2d::distance(end, [0.0, 15.0]) < 1e-9, "Ends on the arc: {:?}", end);
+
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 1.0 });
+        assert!(extend_to(&mut sketch, circle, wall, [0.0, 0.0]).is_err(), "Only lines extend");
+    }
+
+    #[test]
+    fn test_pattern_circle_evenly_along_line_path() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        // Drawn from the far end: the circle sits at the path's end, which becomes the first station
+        let path = sketch.add_entity(SketchGeometry::Line { start: [12.0, 0.0], end: [0.0, 0.0] });
+        sketch.entities[0].is_construction = true;
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 0.5 });
+
+        let copies = pattern_along_path(&mut sketch, &[circle], path, 4).unwrap();
+        assert_eq!(copies.len(), 3);
+        let centers: Vec<[f64; 2]> = copies.iter().map(|ids| {
+            assert_eq!(ids.len(), 1);
+            match sketch.entities.iter().find(|e| e.id == ids[0]).unwrap().geometry {
+                SketchGeometry::Circle { center, radius } => {
+                    assert_eq!(radius, 0.5);
+                    center
+                }
+                ref other => panic!("Not a circle: {:?}", other),
+            }
+        }).collect();
+        for (center, expected) in centers.iter().zip([[4.0, 0.0], [8.0, 0.0], [12.0, 0.0]]) {
+            assert!(utils_2d::distance(*center, expected) < 1e-9, "{:?} should be at {:?}", center, expected);
+        }
+        assert!(sketch.entities.iter().skip(2).all(|e| !e.is_construction), "Copies keep the originals' kind");
+        assert!(pattern_along_path(&mut sketch, &[circle], path, 1).is_err());
+        assert!(pattern_along_path(&mut sketch, &[path], path, 3).is_err());
+    }
+
+    #[test]
+    fn test_pattern_along_arc_turns_copies_with_path() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let path = sketch.add_entity(SketchGeometry::Arc { center: [0.0, 0.0], radius: 10.0, start_angle: 0.0, end_angle: TAU / 4.0 });
+        // A tick across the path at its start, pointing out from the center
+        let tick = sketch.add_entity(SketchGeometry::Line { start: [10.0, 0.0], end: [11.0, 0.0] });
+
+        let copies = pattern_along_path(&mut sketch, &[tick], path, 3).unwrap();
+        let (start, end) = line(&sketch, copies[1][0]);
+        assert!(utils_2d::distance(start, [0.0, 10.0]) < 1e-9 && utils_2d::distance(end, [0.0, 11.0]) < 1e-9, "{:?} {:?}", start, end);
+        let (start, end) = line(&sketch, copies[0][0]);
+        let diagonal = 10.0 * (TAU / 8.0).cos();
+        assert!(utils_2d::distance(start, [diagonal, diagonal]) < 1e-9);
+        assert!(utils_2d::distance(end, [diagonal * 1.1, diagonal * 1.1]) < 1e-9, "Still points out from the center");
+    }
+}
@@ -0,0 +1,428 @@
+//! Drawing geometry of sketch dimensions.
+//!
+//! Every dimensional constraint with a `DimensionStyle` is laid out here in
+//! sketch coordinates, so clients only draw the lines, arcs, arrowheads and
+//! text they are given. Text sits centered on the dimension line (slid along
+//! it by the style offset) unless it doesn't fit between the arrowheads; then
+//! the arrows flip to point inward and the text moves outside on a leader.
+//! Radius dimensions run along a line through the circle's center.
+
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use super::reference::ReferenceEntity;
+use super::solver::SketchSolver;
+use super::types::{ConstraintPoint, DimensionStyle, Sketch, SketchConstraint, SketchGeometry};
+use crate::geometry::utils_2d;
+use crate::topo::EntityId;
+use crate::units::LengthUnit;
+use crate::variables::AngleUnit;
+use serde::{Deserialize, Serialize};
+
+/// Height of dimension text, in sketch units
+pub const TEXT_HEIGHT: f64 = 2.5;
+
+/// Width taken up by one character of dimension text
+const CHAR_WIDTH: f64 = 0.6 * TEXT_HEIGHT;
+
+/// Length of an arrowhead along the line it ends
+pub const ARROW_LENGTH: f64 = 2.0;
+
+/// Decimal places shown when a dimension doesn't set its own precision
+const DEFAULT_PRECISION: u8 = 2;
+
+/// Lines, arrowheads and text that draw one dimension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DimensionGraphics {
+    /// Index of the dimension's constraint in `Sketch::constraints`
+    pub constraint_index: usize,
+    /// Reference-only dimensions are drawn differently from driving ones
+    pub driven: bool,
+    /// Extension lines from the measured geometry to the dimension line or arc
+    pub witness_lines: Vec<[[f64; 2]; 2]>,
+    /// The dimension line, and the leader out to the text when it is outside
+    pub dimension_lines: Vec<[[f64; 2]; 2]>,
+    /// Arc of an angle dimension, in place of a dimension line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arc: Option<DimensionArc>,
+    pub arrowheads: Vec<Arrowhead>,
+    pub text: DimensionText,
+}
+
+/// Counter-clockwise arc from `start_angle` to `end_angle` (radians)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DimensionArc {
+    pub center: [f64; 2],
+    pub radius: f64,
+    pub start_angle: f64,
+    pub end_angle: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Arrowhead {
+    pub tip: [f64; 2],
+    /// Unit direction the arrow points in, towards its tip
+    pub direction: [f64; 2],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DimensionText {
+    /// Center of the text
+    pub anchor: [f64; 2],
+    /// Counter-clockwise turn of the text baseline from the sketch X axis,
+    /// kept within a quarter turn either way so it never reads upside down
+    pub rotation: f64,
+    pub content: String,
+    /// The text didn't fit and sits on a leader outside the arrowheads
+    pub outside: bool,
+}
+
+/// Lay out every styled dimension of `sketch`, with lengths shown in `unit`.
+/// The text shows what the geometry measures, which differs from the target
+/// while the sketch is unsolved and is all a driven dimension has.
+pub fn layout_dimensions(sketch: &Sketch, unit: LengthUnit) -> Vec<DimensionGraphics> {
+    let id_map: HashMap<EntityId, usize> = sketch.entities.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+    sketch.constraints.iter().enumerate()
+        .filter_map(|(index, entry)| {
+            let measured = SketchSolver::measure_constraint(sketch, &id_map, &entry.constraint);
+            layout_dimension(sketch, index, &entry.constraint, measured, unit)
+        })
+        .collect()
+}
+
+fn layout_dimension(sketch: &Sketch, index: usize, constraint: &SketchConstraint, measured: Option<f64>, unit: LengthUnit) -> Option<DimensionGraphics> {
+    let length = |value: &f64, style: &DimensionStyle| format!("{} {}", fixed(unit.from_mm(measured.unwrap_or(*value)), style), unit);
+    match constraint {
+        SketchConstraint::Distance { points, value, style: Some(style) } => {
+            let (p1, p2) = (point(sketch, points[0])?, point(sketch, points[1])?);
+            Some(aligned(index, style, p1, p2, [1.0, 0.0], length(value, style)))
+        }
+        SketchConstraint::HorizontalDistance { points, value, style: Some(style) } => {
+            let (p1, p2) = (point(sketch, points[0])?, point(sketch, points[1])?);
+            let y = (p1[1] + p2[1]) / 2.0 + style.offset[1];
+            Some(linear(index, style, [p1, p2], [[p1[0], y], [p2[0], y]], style.offset[0], length(value, style)))
+        }
+        SketchConstraint::VerticalDistance { points, value, style: Some(style) } => {
+            let (p1, p2) = (point(sketch, points[0])?, point(sketch, points[1])?);
+            let x = (p1[0] + p2[0]) / 2.0 + style.offset[0];
+            Some(linear(index, style, [p1, p2], [[x, p1[1]], [x, p2[1]]], style.offset[1], length(value, style)))
+        }
+        SketchConstraint::DistancePointLine { point: p, line, value, style: Some(style) } => {
+            let p = point(sketch, *p)?;
+            let (start, end) = line_ends(sketch, *line)?;
+            let foot = utils_2d::lerp(start, end, utils_2d::project_point_on_line(start, end, p));
+            let normal = utils_2d::perpendicular_ccw(utils_2d::normalize_2d([end[0] - start[0], end[1] - start[1]]));
+            Some(aligned(index, style, foot, p, normal, length(value, style)))
+        }
+        SketchConstraint::DistancePointCircle { point: p, circle, value, style: Some(style) } => {
+            let p = point(sketch, *p)?;
+            let (center, radius) = circle_of(sketch, *circle)?;
+            let outward = direction(center, p).unwrap_or([1.0, 0.0]);
+            let rim = [center[0] + outward[0] * radius, center[1] + outward[1] * radius];
+            Some(aligned(index, style, rim, p, outward, length(value, style)))
+        }
+        SketchConstraint::DistanceParallelLines { lines, value, style: Some(style) } => {
+            let (s1, e1) = line_ends(sketch, lines[0])?;
+            let (s2, e2) = line_ends(sketch, lines[1])?;
+            let from = utils_2d::midpoint(s1, e1);
+            let to = utils_2d::lerp(s2, e2, utils_2d::project_point_on_line(s2, e2, from));
+            let normal = utils_2d::perpendicular_ccw(utils_2d::normalize_2d([e1[0] - s1[0], e1[1] - s1[1]]));
+            Some(aligned(index, style, from, to, normal, length(value, style)))
+        }
+        SketchConstraint::Angle { lines, value, unit: angle_unit, style: Some(style) } => {
+            let shown = angle_unit.from_radians(angle_unit.to_radians(measured.unwrap_or(*value)).rem_euclid(TAU));
+            let content = match angle_unit {
+                AngleUnit::Degrees => format!("{}°", fixed(shown, style)),
+                AngleUnit::Radians => format!("{} rad", fixed(shown, style)),
+            };
+            angle(sketch, index, style, *lines, content)
+        }
+        SketchConstraint::Radius { entity, value, style: Some(style) } => {
+            let (center, radius) = circle_of(sketch, *entity)?;
+            Some(radial(index, style, center, radius, format!("R{}", length(value, style))))
+        }
+        _ => None,
+    }
+}
+
+/// Dimension between `from` and `to` measured along the line joining them,
+/// drawn `offset[1]` to the left of it. `fallback` is the measuring direction
+/// when the two points coincide.
+fn aligned(index: usize, style: &DimensionStyle, from: [f64; 2], to: [f64; 2], fallback: [f64; 2], content: String) -> DimensionGraphics {
+    let along = direction(from, to).unwrap_or(fallback);
+    let side = utils_2d::perpendicular_ccw(along);
+    let shift = |p: [f64; 2]| [p[0] + side[0] * style.offset[1], p[1] + side[1] * style.offset[1]];
+    linear(index, style, [from, to], [shift(from), shift(to)], style.offset[0], content)
+}
+
+/// Straight dimension line between `ends`, with witness lines back to the
+/// measured `points` and the text slid `slide` along the line from its middle
+fn linear(index: usize, style: &DimensionStyle, points: [[f64; 2]; 2], ends: [[f64; 2]; 2], slide: f64, content: String) -> DimensionGraphics {
+    let [start, end] = ends;
+    let along = direction(start, end).unwrap_or([1.0, 0.0]);
+    let width = text_width(&content);
+    let outside = width + 2.0 * ARROW_LENGTH > utils_2d::distance(start, end);
+    let at = |p: [f64; 2], t: f64| [p[0] + along[0] * t, p[1] + along[1] * t];
+    let back = [-along[0], -along[1]];
+
+    let mut dimension_lines = vec![[start, end]];
+    let (arrowheads, anchor) = if outside {
+        dimension_lines.push([at(start, -ARROW_LENGTH), start]);
+        dimension_lines.push([end, at(end, ARROW_LENGTH + width)]);
+        let arrows = vec![Arrowhead { tip: start, direction: along }, Arrowhead { tip: end, direction: back }];
+        (arrows, at(end, ARROW_LENGTH + width / 2.0 + slide))
+    } else {
+        let arrows = vec![Arrowhead { tip: start, direction: back }, Arrowhead { tip: end, direction: along }];
+        (arrows, at(utils_2d::midpoint(start, end), slide))
+    };
+    DimensionGraphics {
+        constraint_index: index,
+        driven: style.driven,
+        witness_lines: witnesses(&[[points[0], start], [points[1], end]]),
+        dimension_lines,
+        arc: None,
+        arrowheads,
+        text: DimensionText { anchor, rotation: readable(along[1].atan2(along[0])), content, outside },
+    }
+}
+
+/// Arc from the first line to the second, counter-clockwise about their
+/// corner, at half the shorter line's reach plus `offset[1]`
+fn angle(sketch: &Sketch, index: usize, style: &DimensionStyle, lines: [EntityId; 2], content: String) -> Option<DimensionGraphics> {
+    let (s1, e1) = line_ends(sketch, lines[0])?;
+    let (s2, e2) = line_ends(sketch, lines[1])?;
+    // The corner is at the closest pair of ends, as the solver takes it
+    let (c1, f1, c2, f2) = [(s1, e1, s2, e2), (s1, e1, e2, s2), (e1, s1, s2, e2), (e1, s1, e2, s2)].into_iter()
+        .min_by(|a, b| utils_2d::distance(a.0, a.2).total_cmp(&utils_2d::distance(b.0, b.2)))?;
+    let center = utils_2d::line_line_intersect_unbounded(c1, f1, c2, f2)
+        .map_or_else(|| utils_2d::midpoint(c1, c2), |(p, _, _)| p);
+    let (d1, d2) = (direction(c1, f1)?, direction(c2, f2)?);
+    let reach = utils_2d::distance(center, f1).min(utils_2d::distance(center, f2));
+    let radius = (reach / 2.0 + style.offset[1]).max(ARROW_LENGTH);
+
+    let start_angle = d1[1].atan2(d1[0]);
+    let sweep = (d2[1].atan2(d2[0]) - start_angle).rem_euclid(TAU);
+    let end_angle = start_angle + sweep;
+    let on_arc = |a: f64| utils_2d::arc_point(center, radius, a);
+    let (start, end) = (on_arc(start_angle), on_arc(end_angle));
+    // Counter-clockwise tangents at the arc's ends
+    let (ccw_start, ccw_end) = (utils_2d::perpendicular_ccw(d1), utils_2d::perpendicular_ccw(d2));
+    let reverse = |v: [f64; 2]| [-v[0], -v[1]];
+
+    let width = text_width(&content);
+    let outside = width + 2.0 * ARROW_LENGTH > radius * sweep;
+    let past = |p: [f64; 2], v: [f64; 2], t: f64| [p[0] + v[0] * t, p[1] + v[1] * t];
+    let (dimension_lines, arrowheads, text) = if outside {
+        let leader = vec![[past(start, ccw_start, -ARROW_LENGTH), start], [end, past(end, ccw_end, ARROW_LENGTH + width)]];
+        let arrows = vec![Arrowhead { tip: start, direction: ccw_start }, Arrowhead { tip: end, direction: reverse(ccw_end) }];
+        let anchor = past(end, ccw_end, ARROW_LENGTH + width / 2.0);
+        (leader, arrows, DimensionText { anchor, rotation: readable(ccw_end[1].atan2(ccw_end[0])), content, outside })
+    } else {
+        let arrows = vec![Arrowhead { tip: start, direction: reverse(ccw_start) }, Arrowhead { tip: end, direction: ccw_end }];
+        let middle = start_angle + sweep / 2.0 + style.offset[0] / radius;
+        (Vec::new(), arrows, DimensionText { anchor: on_arc(middle), rotation: readable(middle + FRAC_PI_2), content, outside })
+    };
+    // Witness lines carry a line out to the arc when the arc passes its far end
+    let witness_lines = witnesses(&[
+        [f1, if utils_2d::distance(center, f1) < radius { start } else { f1 }],
+        [f2, if utils_2d::distance(center, f2) < radius { end } else { f2 }],
+    ]);
+    Some(DimensionGraphics {
+        constraint_index: index,
+        driven: style.driven,
+        witness_lines,
+        dimension_lines,
+        arc: Some(DimensionArc { center, radius, start_angle, end_angle }),
+        arrowheads,
+        text,
+    })
+}
+
+/// Radius along the direction `offset[0]` (radians) from the center. Inside,
+/// the line runs from the center to an arrow on the rim with the text on it;
+/// when the text doesn't fit the arrow points in at the center from a leader
+/// outside the rim.
+fn radial(index: usize, style: &DimensionStyle, center: [f64; 2], radius: f64, content: String) -> DimensionGraphics {
+    let (sin, cos) = style.offset[0].sin_cos();
+    let outward = [cos, sin];
+    let at = |t: f64| [center[0] + outward[0] * t, center[1] + outward[1] * t];
+    let rim = at(radius);
+    let width = text_width(&content);
+    let outside = width + ARROW_LENGTH > radius;
+    let (dimension_lines, arrowhead, anchor) = if outside {
+        (vec![[rim, at(radius + ARROW_LENGTH + width)]], Arrowhead { tip: rim, direction: [-cos, -sin] }, at(radius + ARROW_LENGTH + width / 2.0))
+    } else {
+        (vec![[center, rim]], Arrowhead { tip: rim, direction: outward }, at((radius - ARROW_LENGTH) / 2.0))
+    };
+    DimensionGraphics {
+        constraint_index: index,
+        driven: style.driven,
+        witness_lines: Vec::new(),
+        dimension_lines,
+        arc: None,
+        arrowheads: vec![arrowhead],
+        text: DimensionText { anchor, rotation: readable(style.offset[0]), content, outside },
+    }
+}
+
+/// `value` to the style's number of decimal places
+fn fixed(value: f64, style: &DimensionStyle) -> String {
+    format!("{:.*}", style.precision.unwrap_or(DEFAULT_PRECISION) as usize, value)
+}
+
+fn text_width(content: &str) -> f64 {
+    content.chars().count() as f64 * CHAR_WIDTH
+}
+
+/// `angle` turned by a half turn if needed to lie in (-90°, 90°]
+fn readable(angle: f64) -> f64 {
+    let angle = (angle + PI).rem_euclid(TAU) - PI;
+    if angle > FRAC_PI_2 + 1e-12 {
+        angle - PI
+    } else if angle <= -FRAC_PI_2 + 1e-12 {
+        angle + PI
+    } else {
+        angle
+    }
+}
+
+/// The non-degenerate lines of `lines`
+fn witnesses(lines: &[[[f64; 2]; 2]]) -> Vec<[[f64; 2]; 2]> {
+    lines.iter().copied().filter(|[a, b]| utils_2d::distance(*a, *b) > 1e-9).collect()
+}
+
+/// Unit direction from `a` to `b`, None when they coincide
+fn direction(a: [f64; 2], b: [f64; 2]) -> Option<[f64; 2]> {
+    (utils_2d::distance(a, b) > 1e-9).then(|| utils_2d::normalize_2d([b[0] - a[0], b[1] - a[1]]))
+}
+
+fn geometry(sketch: &Sketch, id: EntityId) -> Option<&SketchGeometry> {
+    match ReferenceEntity::from_id(id) {
+        Some(reference) => Some(reference.geometry()),
+        None => sketch.entities.iter().find(|e| e.id == id).map(|e| &e.geometry),
+    }
+}
+
+fn line_ends(sketch: &Sketch, id: EntityId) -> Option<([f64; 2], [f64; 2])> {
+    match geometry(sketch, id)? {
+        SketchGeometry::Line { start, end } => Some((*start, *end)),
+        _ => None,
+    }
+}
+
+fn circle_of(sketch: &Sketch, id: EntityId) -> Option<([f64; 2], f64)> {
+    match geometry(sketch, id)? {
+        SketchGeometry::Circle { center, radius } | SketchGeometry::Arc { center, radius, .. } => Some((*center, *radius)),
+        _ => None,
+    }
+}
+
+/// Position of a constraint point (see `ConstraintPoint::topo_id` for the indices)
+fn point(sketch: &Sketch, cp: ConstraintPoint) -> Option<[f64; 2]> {
+    match geometry(sketch, cp.id)? {
+        SketchGeometry::Line { start, end } => Some(if cp.index == 0 { *start } else { *end }),
+        SketchGeometry::Point { pos } => Some(*pos),
+        SketchGeometry::Circle { center, .. } | SketchGeometry::Ellipse { center, .. } => (cp.index == 0).then_some(*center),
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => match cp.index {
+            0 => Some(*center),
+            1 => Some(utils_2d::arc_point(*center, *radius, *start_angle)),
+            2 => Some(utils_2d::arc_point(*center, *radius, *end_angle)),
+            _ => None,
+        },
+        SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => match cp.index {
+            0 => Some(*center),
+            1 => Some(super::types::ellipse_point(*center, *semi_major, *semi_minor, *rotation, *start_angle)),
+            2 => Some(super::types::ellipse_point(*center, *semi_major, *semi_minor, *rotation, *end_angle)),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::types::SketchPlane;
+
+    fn style(offset: [f64; 2]) -> Option<DimensionStyle> {
+        Some(DimensionStyle { offset, ..Default::default() })
+    }
+
+    fn close(a: [f64; 2], b: [f64; 2]) -> bool {
+        utils_2d::distance(a, b) < 1e-9
+    }
+
+    #[test]
+    fn test_horizontal_distance_text_centered_on_offset_line() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [40.0, 10.0] });
+        sketch.add_constraint(SketchConstraint::HorizontalDistance {
+            points: [ConstraintPoint { id: line, index: 0 }, ConstraintPoint { id: line, index: 1 }],
+            value: 40.0,
+            style: style([0.0, 5.0]),
+        });
+
+        let graphics = layout_dimensions(&sketch, LengthUnit::Millimeter);
+        assert_eq!(graphics.len(), 1);
+        let dimension = &graphics[0];
+        assert_eq!(dimension.constraint_index, 0);
+        assert!(close(dimension.dimension_lines[0][0], [0.0, 10.0]) && close(dimension.dimension_lines[0][1], [40.0, 10.0]));
+        assert_eq!(dimension.witness_lines, vec![[[0.0, 0.0], [0.0, 10.0]]], "The end already on the line needs no witness");
+        assert!(close(dimension.text.anchor, [20.0, 10.0]), "{:?}", dimension.text.anchor);
+        assert_eq!(dimension.text.rotation, 0.0);
+        assert_eq!(dimension.text.content, "40.00 mm");
+        assert!(!dimension.text.outside);
+        assert!(close(dimension.arrowheads[0].direction, [-1.0, 0.0]) && close(dimension.arrowheads[1].direction, [1.0, 0.0]));
+
+        let inches = layout_dimensions(&sketch, LengthUnit::Inch);
+        assert_eq!(inches[0].text.content, "1.57 in");
+    }
+
+    #[test]
+    fn test_angle_between_lines_anchors_text_mid_arc() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let first = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [20.0, 0.0] });
+        // Drawn towards the corner: the dimension still measures from its far end
+        let second = sketch.add_entity(SketchGeometry::Line { start: [0.0, 30.0], end: [0.0, 0.0] });
+        sketch.add_constraint(SketchConstraint::Angle {
+            lines: [first, second],
+            value: 90.0,
+            unit: AngleUnit::Degrees,
+            style: Some(DimensionStyle { offset: [0.0, 0.0], precision: Some(1), ..Default::default() }),
+        });
+
+        let dimension = &layout_dimensions(&sketch, LengthUnit::Millimeter)[0];
+        let arc = dimension.arc.unwrap();
+        assert!(close(arc.center, [0.0, 0.0]));
+        assert!((arc.radius - 10.0).abs() < 1e-9, "Half the shorter line");
+        assert!(arc.start_angle.abs() < 1e-9 && (arc.end_angle - FRAC_PI_2).abs() < 1e-9);
+        let diagonal = 10.0 * (PI / 4.0).cos();
+        assert!(close(dimension.text.anchor, [diagonal, diagonal]), "{:?}", dimension.text.anchor);
+        assert!((dimension.text.rotation + PI / 4.0).abs() < 1e-9, "Tangent to the arc, turned to read upright");
+        assert_eq!(dimension.text.content, "90.0°");
+        assert!(!dimension.text.outside);
+        assert!(dimension.witness_lines.is_empty(), "Both lines reach the arc");
+    }
+
+    #[test]
+    fn test_radius_on_small_circle_flips_text_outside() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [5.0, 5.0], radius: 1.0 });
+        sketch.add_constraint(SketchConstraint::Radius { entity: circle, value: 1.0, style: style([0.0, 0.0]) });
+
+        let dimension = &layout_dimensions(&sketch, LengthUnit::Millimeter)[0];
+        assert_eq!(dimension.text.content, "R1.00 mm");
+        assert!(dimension.text.outside);
+        let width = 8.0 * CHAR_WIDTH;
+        assert!(close(dimension.text.anchor, [6.0 + ARROW_LENGTH + width / 2.0, 5.0]), "{:?}", dimension.text.anchor);
+        assert_eq!(dimension.arrowheads.len(), 1);
+        assert!(close(dimension.arrowheads[0].tip, [6.0, 5.0]));
+        assert!(close(dimension.arrowheads[0].direction, [-1.0, 0.0]), "Points in at the center");
+
+        sketch.constraints.clear();
+        let large = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 50.0 });
+        sketch.add_constraint(SketchConstraint::Radius { entity: large, value: 50.0, style: style([FRAC_PI_2, 0.0]) });
+        let dimension = &layout_dimensions(&sketch, LengthUnit::Millimeter)[0];
+        assert!(!dimension.text.outside);
+        assert!(close(dimension.dimension_lines[0][0], [0.0, 0.0]) && close(dimension.dimension_lines[0][1], [0.0, 50.0]));
+    }
+}
@@ -0,0 +1,804 @@
+//! Structural sketch edits
+//!
+//! Operations that replace entities rather than move them. They keep the
+//! constraint set consistent and report how entity ids changed, so features
+//! referencing the sketch's entities can follow.
+
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+
+use super::chains::{build_chains, DEFAULT_CHAIN_TOLERANCE};
+use super::solver::{AnalysisCancel, SketchSolver};
+use super::types::{ConstraintPoint, Sketch, SketchAddition, SketchConstraint, SketchEntity, SketchGeometry, SketchOperation};
+use crate::geometry::intersection::point_on_line_parameter;
+use crate::topo::EntityId;
+use serde::{Deserialize, Serialize};
+
+/// Splits closer than this (as a fraction of the entity) to an endpoint are rejected
+const SPLIT_EPSILON: f64 = 1e-6;
+
+/// Points closer than this are taken to be the same point when building arcs
+const ARC_POINT_EPSILON: f64 = 1e-9;
+
+/// Arc points whose turn is below this sine are taken to be in a line
+const COLLINEAR_SINE: f64 = 1e-9;
+
+/// Where to split an entity
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SplitAt {
+    /// A sketch point, projected onto the entity
+    Point([f64; 2]),
+    /// Normalized position along the entity (0 = start, 1 = end)
+    Parameter(f64),
+}
+
+/// Outcome of `split_entity`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitResult {
+    /// The two halves, in order from the original start. The first keeps the original id.
+    pub halves: [EntityId; 2],
+    /// Original entity id → ids now covering it
+    pub id_map: HashMap<EntityId, Vec<EntityId>>,
+    /// Position of the new shared endpoint
+    pub split_point: [f64; 2],
+    /// Constraints that referenced the whole entity in a way that depends on its
+    /// extent (e.g. equal length, tangency). They stay on the first half; the
+    /// caller should ask the user to review them.
+    pub ambiguous_constraints: Vec<usize>,
+}
+
+/// Split a line or arc into two entities at `at`.
+///
+/// The halves are joined by a Coincident constraint and kept on one carrier
+/// (the original's Horizontal/Vertical or a Parallel for lines, a shared center
+/// for arcs), so constraints on the whole entity's direction or radius stay
+/// valid on the first half. Constraint
+/// points on the original end move to the second half.
+pub fn split_entity(sketch: &mut Sketch, entity_id: EntityId, at: SplitAt) -> Result<SplitResult, String> {
+    let index = sketch.entities.iter().position(|e| e.id == entity_id)
+        .ok_or_else(|| format!("Entity {} not found in sketch", entity_id))?;
+    let original = sketch.entities[index].clone();
+
+    // Geometry of both halves, the split point, and the constraint point index of the end
+    let (first, second, split_point, end_index) = match original.geometry {
+        SketchGeometry::Line { start, end } => {
+            let t = match at {
+                SplitAt::Point(p) => point_on_line_parameter(start, end, p),
+                SplitAt::Parameter(t) => t,
+            };
+            check_interior(t)?;
+            let p = [start[0] + t * (end[0] - start[0]), start[1] + t * (end[1] - start[1])];
+            (SketchGeometry::Line { start, end: p }, SketchGeometry::Line { start: p, end }, p, 1)
+        }
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => {
+            let sweep = match (end_angle - start_angle).rem_euclid(TAU) {
+                s if s < SPLIT_EPSILON => TAU,
+                s => s,
+            };
+            let t = match at {
+                SplitAt::Point(p) => ((p[1] - center[1]).atan2(p[0] - center[0]) - start_angle).rem_euclid(TAU) / sweep,
+                SplitAt::Parameter(t) => t,
+            };
+            check_interior(t)?;
+            let angle = start_angle + t * sweep;
+            (
+                SketchGeometry::Arc { center, radius, start_angle, end_angle: angle },
+                SketchGeometry::Arc { center, radius, start_angle: angle, end_angle },
+                [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()],
+                2,
+            )
+        }
+        SketchGeometry::Circle { .. } => return Err("Circles need two split points; convert to an arc first".to_string()),
+        _ => return Err("Only lines and arcs can be split".to_string()),
+    };
+
+    let second_id = EntityId::new_deterministic(&format!("{}:split:{}:{}", entity_id, split_point[0], split_point[1]));
+    if sketch.entities.iter().any(|e| e.id == second_id) {
+        return Err("Entity is already split at this point".to_string());
+    }
+    sketch.entities[index].geometry = first;
+    let creation_seq = sketch.take_creation_seq();
+    sketch.entities.insert(index + 1, SketchEntity { id: second_id, geometry: second.clone(), is_construction: original.is_construction, creation_seq, name: None, color: None, locked: false });
+    sketch.history.push(SketchOperation::AddGeometry { id: second_id, geometry: second });
+
+    let mut ambiguous_constraints = Vec::new();
+    let mut second_orientation = Vec::new();
+    for (i, entry) in sketch.constraints.iter_mut().enumerate() {
+        for point in constraint_points_mut(&mut entry.constraint) {
+            if point.id == entity_id && point.index == end_index {
+                point.id = second_id;
+            }
+        }
+        match &entry.constraint {
+            SketchConstraint::Horizontal { entity } if *entity == entity_id => {
+                second_orientation.push(SketchConstraint::Horizontal { entity: second_id });
+            }
+            SketchConstraint::Vertical { entity } if *entity == entity_id => {
+                second_orientation.push(SketchConstraint::Vertical { entity: second_id });
+            }
+            _ => {}
+        }
+        let extent_dependent = match &entry.constraint {
+            SketchConstraint::Tangent { entities } => entities.contains(&entity_id),
+            SketchConstraint::Equal { entities } => end_index == 1 && entities.contains(&entity_id),
+            SketchConstraint::EqualGroup { entities } => end_index == 1 && entities.contains(&entity_id),
+            _ => false,
+        };
+        if extent_dependent {
+            ambiguous_constraints.push(i);
+        }
+    }
+
+    let point = |id, index| ConstraintPoint { id, index };
+    sketch.add_constraint(SketchConstraint::Coincident { points: [point(entity_id, end_index), point(second_id, end_index - 1)] });
+    if end_index == 1 {
+        // A horizontal/vertical line stays so on both halves; otherwise keep them parallel
+        if second_orientation.is_empty() {
+            second_orientation.push(SketchConstraint::Parallel { lines: [entity_id, second_id] });
+        }
+        for constraint in second_orientation {
+            sketch.add_constraint(constraint);
+        }
+    } else {
+        sketch.add_constraint(SketchConstraint::Coincident { points: [point(entity_id, 0), point(second_id, 0)] });
+    }
+
+    Ok(SplitResult {
+        halves: [entity_id, second_id],
+        id_map: HashMap::from([(entity_id, vec![entity_id, second_id])]),
+        split_point,
+        ambiguous_constraints,
+    })
+}
+
+/// What `Sketch::convert_entity` turns an arc or circle into
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EntityConversion {
+    /// An arc's full circle
+    Circle,
+    /// The part of a circle swept counter-clockwise from `start_angle` to `end_angle`
+    Arc { start_angle: f64, end_angle: f64 },
+}
+
+/// An entity as AddSketchEntity describes it: plain geometry, or an arc given
+/// the way it is drawn, for the server to turn into center, radius and angles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EntitySpec {
+    Geometry(SketchGeometry),
+    /// From `start` through `through` to `end`
+    ThreePointArc { start: [f64; 2], through: [f64; 2], end: [f64; 2] },
+    /// Around `center` from `start` to the angle of `end`, counter-clockwise if `ccw`
+    CenterStartEnd { center: [f64; 2], start: [f64; 2], end: [f64; 2], ccw: bool },
+    /// Continuing tangentially from the end `from` of a line or arc to `end`
+    TangentArc { from: ConstraintPoint, end: [f64; 2] },
+}
+
+/// Geometry built from an `EntitySpec`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProposedEntity {
+    pub geometry: SketchGeometry,
+    /// Set when the geometry isn't what was asked for, like a line for arc
+    /// points in a row
+    pub warning: Option<String>,
+    /// Constraint point of `geometry` at the `from` end of a tangent continuation
+    pub joint: Option<u8>,
+}
+
+/// Outcome of `add_entity_spec`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddedEntity {
+    pub entity_id: EntityId,
+    pub geometry: SketchGeometry,
+    pub warning: Option<String>,
+}
+
+/// The arc from `p1` through `p2` to `p3`, on their circumcircle.
+///
+/// Arcs always run counter-clockwise, so when the points turn clockwise the
+/// arc starts at `p3` and ends at `p1`. Points in a row give the line from
+/// `p1` to `p3` instead, with a warning.
+pub fn arc_from_three_points(p1: [f64; 2], p2: [f64; 2], p3: [f64; 2]) -> Result<ProposedEntity, String> {
+    let (a, b) = ([p2[0] - p1[0], p2[1] - p1[1]], [p3[0] - p1[0], p3[1] - p1[1]]);
+    let (la, lb, lc) = (a[0].hypot(a[1]), b[0].hypot(b[1]), (p3[0] - p2[0]).hypot(p3[1] - p2[1]));
+    if la.min(lb).min(lc) < ARC_POINT_EPSILON {
+        return Err("Arc points must be distinct".to_string());
+    }
+    let cross = a[0] * b[1] - a[1] * b[0];
+    if cross.abs() < COLLINEAR_SINE * la * lb {
+        return Ok(ProposedEntity {
+            geometry: SketchGeometry::Line { start: p1, end: p3 },
+            warning: Some("Arc points are in a line; proposing a line instead".to_string()),
+            joint: None,
+        });
+    }
+    // Circumcenter relative to p1
+    let (aa, bb) = (la * la, lb * lb);
+    let offset = [(b[1] * aa - a[1] * bb) / (2.0 * cross), (a[0] * bb - b[0] * aa) / (2.0 * cross)];
+    let center = [p1[0] + offset[0], p1[1] + offset[1]];
+    let radius = offset[0].hypot(offset[1]);
+    let (start, end) = if cross > 0.0 { (p1, p3) } else { (p3, p1) };
+    Ok(ProposedEntity {
+        geometry: SketchGeometry::Arc { center, radius, start_angle: angle_from(center, start), end_angle: angle_from(center, end) },
+        warning: None,
+        joint: None,
+    })
+}
+
+/// The arc around `center` through `start`, ending at the angle of `end`
+/// (which need not be on the circle), counter-clockwise or clockwise from
+/// `start`. A clockwise arc is stored counter-clockwise from the end.
+pub fn arc_center_start_end(center: [f64; 2], start: [f64; 2], end: [f64; 2], ccw: bool) -> Result<SketchGeometry, String> {
+    let radius = (start[0] - center[0]).hypot(start[1] - center[1]);
+    if radius < ARC_POINT_EPSILON || (end[0] - center[0]).hypot(end[1] - center[1]) < ARC_POINT_EPSILON {
+        return Err("Arc start and end must be away from the center".to_string());
+    }
+    let (from, to) = (angle_from(center, start), angle_from(center, end));
+    let (start_angle, end_angle) = if ccw { (from, to) } else { (to, from) };
+    Ok(SketchGeometry::Arc { center, radius, start_angle, end_angle })
+}
+
+/// The arc leaving the line or arc end `from` along its direction there and
+/// ending at `end`. `joint` is the arc's point at `from`: its start when it
+/// turns left, its end when it turns right (arcs run counter-clockwise). An
+/// `end` straight ahead gives a line, with a warning.
+pub fn tangent_arc_from(sketch: &Sketch, from: ConstraintPoint, end: [f64; 2]) -> Result<ProposedEntity, String> {
+    let entity = sketch.entities.iter().find(|e| e.id == from.id)
+        .ok_or_else(|| format!("Entity {} not found in sketch", from.id))?;
+    // Where the continuation starts and the direction it leaves in
+    let (start, direction) = match (&entity.geometry, from.index) {
+        (SketchGeometry::Line { start, end }, 0 | 1) => {
+            let d = [end[0] - start[0], end[1] - start[1]];
+            if from.index == 0 { (*start, [-d[0], -d[1]]) } else { (*end, d) }
+        }
+        (SketchGeometry::Arc { center, radius, start_angle, end_angle }, 1 | 2) => {
+            let angle = if from.index == 1 { *start_angle } else { *end_angle };
+            let point = [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()];
+            let ccw = [-angle.sin(), angle.cos()];
+            if from.index == 1 { (point, [-ccw[0], -ccw[1]]) } else { (point, ccw) }
+        }
+        _ => return Err("A tangent arc continues from the start or end of a line or arc".to_string()),
+    };
+    let length = direction[0].hypot(direction[1]);
+    let chord = [end[0] - start[0], end[1] - start[1]];
+    let chord_length = chord[0].hypot(chord[1]);
+    if length < ARC_POINT_EPSILON || chord_length < ARC_POINT_EPSILON {
+        return Err("Tangent arc needs a direction and an end away from its start".to_string());
+    }
+    let left = [-direction[1] / length, direction[0] / length];
+    let sideways = chord[0] * left[0] + chord[1] * left[1];
+    if sideways.abs() < COLLINEAR_SINE * chord_length {
+        return Ok(ProposedEntity {
+            geometry: SketchGeometry::Line { start, end },
+            warning: Some("Tangent arc end is straight ahead; proposing a line instead".to_string()),
+            joint: Some(0),
+        });
+    }
+    // Signed distance along `left` to the center, equally far from start and end
+    let reach = chord_length * chord_length / (2.0 * sideways);
+    let center = [start[0] + reach * left[0], start[1] + reach * left[1]];
+    let (from_angle, to_angle) = (angle_from(center, start), angle_from(center, end));
+    let (start_angle, end_angle, joint) = if reach > 0.0 { (from_angle, to_angle, 1) } else { (to_angle, from_angle, 2) };
+    Ok(ProposedEntity {
+        geometry: SketchGeometry::Arc { center, radius: reach.abs(), start_angle, end_angle },
+        warning: None,
+        joint: Some(joint),
+    })
+}
+
+/// Add the entity `spec` describes. A tangent continuation is joined to the
+/// entity it continues with Coincident and TangentAtPoint constraints.
+pub fn add_entity_spec(sketch: &mut Sketch, spec: EntitySpec) -> Result<AddedEntity, String> {
+    let (proposed, from) = match spec {
+        EntitySpec::Geometry(geometry) => (ProposedEntity { geometry, warning: None, joint: None }, None),
+        EntitySpec::ThreePointArc { start, through, end } => (arc_from_three_points(start, through, end)?, None),
+        EntitySpec::CenterStartEnd { center, start, end, ccw } => {
+            (ProposedEntity { geometry: arc_center_start_end(center, start, end, ccw)?, warning: None, joint: None }, None)
+        }
+        EntitySpec::TangentArc { from, end } => (tangent_arc_from(sketch, from, end)?, Some(from)),
+    };
+    let entity_id = sketch.add_entity(proposed.geometry.clone());
+    if let (Some(from), Some(index)) = (from, proposed.joint) {
+        let joint = ConstraintPoint { id: entity_id, index };
+        sketch.add_constraint(SketchConstraint::Coincident { points: [from, joint] });
+        sketch.add_constraint(SketchConstraint::TangentAtPoint { a: from, b: joint });
+    }
+    Ok(AddedEntity { entity_id, geometry: proposed.geometry, warning: proposed.warning })
+}
+
+fn angle_from(center: [f64; 2], point: [f64; 2]) -> f64 {
+    (point[1] - center[1]).atan2(point[0] - center[0])
+}
+
+impl Sketch {
+    /// Turn the chain of lines and arcs through `entity_id` into individually
+    /// editable curves held together explicitly: every joint gets a Coincident
+    /// constraint unless it already has one. Returns the chain's entity ids in
+    /// chain order, or an empty list if the entity is not part of a chain.
+    pub fn explode_chain(&mut self, entity_id: EntityId) -> Vec<EntityId> {
+        let Some(chain) = build_chains(&self.entities, DEFAULT_CHAIN_TOLERANCE)
+            .into_iter()
+            .find(|chain| chain.segments.iter().any(|s| s.entity_id == entity_id))
+        else {
+            return Vec::new();
+        };
+        let n = chain.segments.len();
+        let joints = if chain.closed && n > 1 { n } else { n - 1 };
+        for i in 0..joints {
+            let (a, b) = (&chain.segments[i], &chain.segments[(i + 1) % n]);
+            let points = [
+                ConstraintPoint { id: a.entity_id, index: endpoint_index(a.geometry(), !a.reversed) },
+                ConstraintPoint { id: b.entity_id, index: endpoint_index(b.geometry(), b.reversed) },
+            ];
+            let joined = self.constraints.iter().any(|entry| match entry.constraint {
+                SketchConstraint::Coincident { points: [p, q] } => (p, q) == (points[0], points[1]) || (q, p) == (points[0], points[1]),
+                _ => false,
+            });
+            if !joined {
+                self.add_constraint(SketchConstraint::Coincident { points });
+            }
+        }
+        chain.entity_ids()
+    }
+
+    /// Delete the constraints the analysis reports as redundant: exact
+    /// duplicates and coincidents implied through others. One of each
+    /// duplicate set is kept, as is every coincident needed to keep its points
+    /// joined. Returns how many were removed.
+    pub fn remove_redundant_constraints(&mut self) -> usize {
+        let mut removed = 0;
+        // Each coincident of a loop is implied by the rest until one is gone,
+        // so remove the last reported constraint and look again
+        loop {
+            let redundant = SketchSolver::detect_redundant_constraints(self, &AnalysisCancel::default())
+                .expect("analysis is never cancelled here");
+            let Some(last) = redundant.iter().map(|r| r.constraint_index).max() else { break };
+            self.constraints.remove(last);
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Delete entity `id` together with every constraint that refers to it,
+    /// which would otherwise count against the DOF and point at nothing.
+    /// Returns how many constraints were removed.
+    pub fn remove_entity(&mut self, id: EntityId) -> usize {
+        self.entities.retain(|e| e.id != id);
+        self.external_references.remove(&id);
+        let before = self.constraints.len();
+        self.constraints.retain(|entry| !SketchSolver::get_constraint_entities(&entry.constraint).contains(&id));
+        before - self.constraints.len()
+    }
+
+    /// Replace arc `id` with its full circle, keeping the id so references to
+    /// it survive. Constraints on the arc's endpoints, which the circle doesn't
+    /// have, are removed; those on its center and radius stay. Returns how
+    /// many constraints were removed.
+    pub fn arc_to_circle(&mut self, id: EntityId) -> Result<usize, String> {
+        let entity = self.entities.iter_mut().find(|e| e.id == id)
+            .ok_or_else(|| format!("Entity {} not found in sketch", id))?;
+        let SketchGeometry::Arc { center, radius, .. } = entity.geometry else {
+            return Err("Only arcs can be made into circles".to_string());
+        };
+        entity.geometry = SketchGeometry::Circle { center, radius };
+        let before = self.constraints.len();
+        self.constraints.retain(|entry| {
+            let mut constraint = entry.constraint.clone();
+            !constraint_points_mut(&mut constraint).iter().any(|point| point.id == id && point.index > 0)
+        });
+        Ok(before - self.constraints.len())
+    }
+
+    /// Replace circle `id` with the arc swept counter-clockwise from
+    /// `start_angle` to `end_angle` (radians), keeping the id. Every
+    /// constraint on a circle applies to its arc, so none are removed.
+    pub fn circle_to_arc(&mut self, id: EntityId, start_angle: f64, end_angle: f64) -> Result<(), String> {
+        if !start_angle.is_finite() || !end_angle.is_finite() {
+            return Err("Arc angles must be finite".to_string());
+        }
+        if (end_angle - start_angle).rem_euclid(TAU) < SPLIT_EPSILON {
+            return Err("Arc must sweep part of the circle".to_string());
+        }
+        let entity = self.entities.iter_mut().find(|e| e.id == id)
+            .ok_or_else(|| format!("Entity {} not found in sketch", id))?;
+        let SketchGeometry::Circle { center, radius } = entity.geometry else {
+            return Err("Only circles can be made into arcs".to_string());
+        };
+        entity.geometry = SketchGeometry::Arc { center, radius, start_angle, end_angle };
+        Ok(())
+    }
+
+    /// Apply `conversion` to entity `id`; returns how many constraints were removed
+    pub fn convert_entity(&mut self, id: EntityId, conversion: EntityConversion) -> Result<usize, String> {
+        match conversion {
+            EntityConversion::Circle => self.arc_to_circle(id),
+            EntityConversion::Arc { start_angle, end_angle } => self.circle_to_arc(id, start_angle, end_angle).map(|_| 0),
+        }
+    }
+
+    /// Take back the newest `n` additions (see `additions`), newest first.
+    /// Undoing an entity also removes the constraints that still refer to it.
+    /// Returns what was undone, which is less than `n` once the sketch is empty.
+    pub fn undo_last(&mut self, n: usize) -> Vec<SketchAddition> {
+        let mut undone = Vec::new();
+        while undone.len() < n {
+            let Some(newest) = self.additions().pop() else { break };
+            match newest {
+                SketchAddition::Entity { id, .. } => {
+                    self.remove_entity(id);
+                }
+                SketchAddition::Constraint { index, .. } => {
+                    self.constraints.remove(index);
+                }
+            }
+            undone.push(newest);
+        }
+        undone
+    }
+}
+
+/// Constraint point index of a curve's start or end (arcs number their center 0)
+fn endpoint_index(geometry: &SketchGeometry, end: bool) -> u8 {
+    match (geometry, end) {
+        (SketchGeometry::Arc { .. } | SketchGeometry::EllipseArc { .. }, false) => 1,
+        (SketchGeometry::Arc { .. } | SketchGeometry::EllipseArc { .. }, true) => 2,
+        (_, false) => 0,
+        (_, true) => 1,
+    }
+}
+
+fn check_interior(t: f64) -> Result<(), String> {
+    if !(SPLIT_EPSILON..=1.0 - SPLIT_EPSILON).contains(&t) {
+        return Err("Split point must lie strictly inside the entity".to_string());
+    }
+    Ok(())
+}
+
+/// Every point reference held by a constraint
+pub(super) fn constraint_points_mut(constraint: &mut SketchConstraint) -> Vec<&mut ConstraintPoint> {
+    match constraint {
+        SketchConstraint::Coincident { points }
+        | SketchConstraint::Distance { points, .. }
+        | SketchConstraint::HorizontalDistance { points, .. }
+        | SketchConstraint::VerticalDistance { points, .. } => points.iter_mut().collect(),
+        SketchConstraint::Symmetric { p1, p2, .. } | SketchConstraint::TangentAtPoint { a: p1, b: p2 } => vec![p1, p2],
+        SketchConstraint::Fix { point, .. }
+        | SketchConstraint::DistancePointLine { point, .. }
+        | SketchConstraint::DistancePointCircle { point, .. } => vec![point],
+        _ => Vec::new(),
+    }
+}
+
+/// Every entity id held by a constraint, in point references and entity fields.
+/// Matches each variant so a new one can't be missed by id remapping.
+pub(crate) fn constraint_ids_mut(constraint: &mut SketchConstraint) -> Vec<&mut EntityId> {
+    match constraint {
+        SketchConstraint::Coincident { points }
+        | SketchConstraint::Distance { points, .. }
+        | SketchConstraint::HorizontalDistance { points, .. }
+        | SketchConstraint::VerticalDistance { points, .. } => points.iter_mut().map(|p| &mut p.id).collect(),
+        SketchConstraint::Horizontal { entity }
+        | SketchConstraint::Vertical { entity }
+        | SketchConstraint::Radius { entity, .. }
+        | SketchConstraint::ThroughOrigin { line: entity } => vec![entity],
+        SketchConstraint::Angle { lines, .. }
+        | SketchConstraint::Parallel { lines }
+        | SketchConstraint::Perpendicular { lines }
+        | SketchConstraint::DistanceParallelLines { lines, .. }
+        | SketchConstraint::Tangent { entities: lines }
+        | SketchConstraint::Equal { entities: lines } => lines.iter_mut().collect(),
+        SketchConstraint::EqualGroup { entities } => entities.iter_mut().collect(),
+        SketchConstraint::TangentAtPoint { a, b } => vec![&mut a.id, &mut b.id],
+        SketchConstraint::Symmetric { p1, p2, axis } => vec![&mut p1.id, &mut p2.id, axis],
+        SketchConstraint::Fix { point, .. } => vec![&mut point.id],
+        SketchConstraint::DistancePointLine { point, line: entity, .. }
+        | SketchConstraint::DistancePointCircle { point, circle: entity, .. } => vec![&mut point.id, entity],
+        SketchConstraint::OffsetFromExternal { entity, external, .. } => vec![entity, external],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::regions::find_regions;
+    use crate::sketch::solver::SketchSolver;
+    use crate::sketch::types::SketchPlane;
+
+    /// 10 x 5 rectangle with coincident corners, H/V sides and a width dimension.
+    /// Returns the sketch and the side ids (bottom first).
+    fn constrained_rectangle() -> (Sketch, [EntityId; 4]) {
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]];
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let ids = [0, 1, 2, 3].map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] }));
+        for i in 0..4 {
+            sketch.add_constraint(SketchConstraint::Coincident {
+                points: [ConstraintPoint { id: ids[i], index: 1 }, ConstraintPoint { id: ids[(i + 1) % 4], index: 0 }],
+            });
+        }
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: ids[0] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: ids[1] });
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: ids[2] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: ids[3] });
+        sketch.add_constraint(SketchConstraint::Distance {
+            points: [ConstraintPoint { id: ids[0], index: 0 }, ConstraintPoint { id: ids[0], index: 1 }],
+            value: 10.0,
+            style: None,
+        });
+        (sketch, ids)
+    }
+
+    #[test]
+    fn test_split_rectangle_side_keeps_sketch_solvable() {
+        let (mut sketch, ids) = constrained_rectangle();
+        let result = split_entity(&mut sketch, ids[0], SplitAt::Point([4.0, 0.3])).unwrap();
+        let second = result.halves[1];
+        assert_eq!(result.halves[0], ids[0]);
+        assert_eq!(result.id_map[&ids[0]], vec![ids[0], second]);
+        assert!((result.split_point[0] - 4.0).abs() < 1e-9 && result.split_point[1].abs() < 1e-9, "Point is projected onto the line");
+        assert!(result.ambiguous_constraints.is_empty());
+
+        // The corner at the far end now belongs to the second half
+        assert!(sketch.constraints.iter().any(|e| e.constraint == SketchConstraint::Coincident {
+            points: [ConstraintPoint { id: second, index: 1 }, ConstraintPoint { id: ids[1], index: 0 }],
+        }));
+        // The overall width dimension spans both halves
+        assert!(sketch.constraints.iter().any(|e| matches!(e.constraint,
+            SketchConstraint::Distance { points, .. } if points[0].id == ids[0] && points[1].id == second)));
+
+        assert_eq!(find_regions(&sketch.entities).len(), 1, "The split side still closes one region");
+
+        let solved = SketchSolver::solve_with_result(&mut sketch);
+        assert!(solved.converged, "Split sketch should stay solvable: {}", solved.status_message);
+        assert!(solved.redundant_constraints.is_empty());
+
+        // Driving the width still moves the far corner, across the split
+        for entry in &mut sketch.constraints {
+            if let SketchConstraint::Distance { value, .. } = &mut entry.constraint {
+                *value = 12.0;
+            }
+        }
+        let solved = SketchSolver::solve_with_result(&mut sketch);
+        assert!(solved.converged, "{}", solved.status_message);
+        match (&sketch.entities[0].geometry, &sketch.entities[1].geometry) {
+            (SketchGeometry::Line { start: a, end: split }, SketchGeometry::Line { end: b, .. }) => {
+                assert!((split[1] - a[1]).abs() < 1e-4 && (b[1] - a[1]).abs() < 1e-4, "Halves stay collinear");
+                assert!((b[0] - a[0] - 12.0).abs() < 1e-3);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_split_arc_and_report_ambiguous_constraints() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let arc = sketch.add_entity(SketchGeometry::Arc { center: [0.0, 0.0], radius: 2.0, start_angle: 0.0, end_angle: std::f64::consts::PI });
+        let line = sketch.add_entity(SketchGeometry::Line { start: [-2.0, 0.0], end: [-2.0, -3.0] });
+        sketch.add_constraint(SketchConstraint::Tangent { entities: [arc, line] });
+        sketch.add_constraint(SketchConstraint::Coincident {
+            points: [ConstraintPoint { id: arc, index: 2 }, ConstraintPoint { id: line, index: 0 }],
+        });
+
+        let result = split_entity(&mut sketch, arc, SplitAt::Parameter(0.5)).unwrap();
+        assert!((result.split_point[0]).abs() < 1e-9 && (result.split_point[1] - 2.0).abs() < 1e-9);
+        assert_eq!(result.ambiguous_constraints, vec![0], "Tangency depends on which half touches");
+        match &sketch.constraints[1].constraint {
+            SketchConstraint::Coincident { points } => assert_eq!(points[0].id, result.halves[1]),
+            other => panic!("Unexpected constraint {:?}", other),
+        }
+
+        assert!(split_entity(&mut sketch, line, SplitAt::Parameter(1.0)).is_err(), "Endpoints are not split points");
+    }
+
+    #[test]
+    fn test_explode_closed_chain_adds_joint_constraints() {
+        // Four lines drawn as a closed outline, one of them backwards, with no constraints
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]];
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let mut ids: Vec<EntityId> = (0..3)
+            .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[i + 1] }))
+            .collect();
+        ids.push(sketch.add_entity(SketchGeometry::Line { start: corners[0], end: corners[3] }));
+
+        let exploded = sketch.explode_chain(ids[2]);
+        assert_eq!(exploded.len(), 4);
+        assert!(ids.iter().all(|id| exploded.contains(id)));
+        assert_eq!(sketch.constraints.len(), 4, "One coincident per corner closes the loop");
+        assert!(sketch.constraints.iter().all(|e| matches!(e.constraint, SketchConstraint::Coincident { .. })));
+        let joins = |a: EntityId, b: EntityId| sketch.constraints.iter().any(|e| matches!(
+            e.constraint,
+            SketchConstraint::Coincident { points: [p, q] } if (p.id, q.id) == (a, b) || (p.id, q.id) == (b, a)
+        ));
+        assert!(joins(ids[2], ids[3]) && joins(ids[3], ids[0]));
+        // The backwards line meets the first line at its start
+        assert!(sketch.constraints.iter().any(|e| e.constraint == SketchConstraint::Coincident {
+            points: [ConstraintPoint { id: ids[3], index: 0 }, ConstraintPoint { id: ids[0], index: 0 }],
+        } || e.constraint == SketchConstraint::Coincident {
+            points: [ConstraintPoint { id: ids[0], index: 0 }, ConstraintPoint { id: ids[3], index: 0 }],
+        }));
+
+        // Moving a corner keeps the outline closed after solving
+        if let SketchGeometry::Line { end, .. } = &mut sketch.entities[1].geometry {
+            *end = [12.0, 6.0];
+        }
+        assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+        let corner = |i: usize, end: usize| match sketch.entities[i].geometry {
+            SketchGeometry::Line { start, end: e } => if end == 1 { e } else { start },
+            _ => unreachable!(),
+        };
+        let gap = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+        assert!(gap(corner(1, 1), corner(2, 0)) < 1e-6);
+
+        assert!(sketch.explode_chain(ids[0]).len() == 4 && sketch.constraints.len() == 4, "Exploding again adds nothing");
+    }
+
+    #[test]
+    fn test_removing_an_entity_removes_its_constraints() {
+        let (mut sketch, ids) = constrained_rectangle();
+        // The top side's Horizontal and both corner Coincidents go with it
+        assert_eq!(sketch.remove_entity(ids[2]), 3);
+        assert_eq!(sketch.entities.len(), 3);
+        assert_eq!(sketch.constraints.len(), 6);
+        assert!(sketch.constraints.iter().all(|e| !SketchSolver::get_constraint_entities(&e.constraint).contains(&ids[2])));
+        let solved = SketchSolver::solve_with_result(&mut sketch);
+        assert!(solved.converged, "{}", solved.status_message);
+        assert_eq!(solved.dof, 12 - 4 - 3 - 1, "Three sides less two corners, three H/V and the width");
+
+        assert_eq!(sketch.remove_entity(ids[2]), 0, "Already gone");
+    }
+
+    #[test]
+    fn test_arcs_and_circles_convert_in_place() {
+        let mut sketch = Sketch::new(Default::default());
+        let arc = sketch.add_entity(SketchGeometry::Arc { center: [1.0, 2.0], radius: 3.0, start_angle: 0.0, end_angle: 1.5 });
+        let line = sketch.add_entity(SketchGeometry::Line { start: [4.0, 2.0], end: [8.0, 2.0] });
+        let cp = |id, index| ConstraintPoint { id, index };
+        sketch.add_constraint(SketchConstraint::Coincident { points: [cp(arc, 1), cp(line, 0)] });
+        sketch.add_constraint(SketchConstraint::Fix { point: cp(arc, 0), position: [1.0, 2.0] });
+        sketch.add_constraint(SketchConstraint::Radius { entity: arc, value: 3.0, style: None });
+        sketch.add_constraint(SketchConstraint::Distance { points: [cp(arc, 2), cp(line, 1)], value: 5.0, style: None });
+
+        assert_eq!(sketch.arc_to_circle(arc).unwrap(), 2, "Both endpoint constraints go");
+        assert_eq!(sketch.entities[0].id, arc);
+        assert_eq!(sketch.entities[0].geometry, SketchGeometry::Circle { center: [1.0, 2.0], radius: 3.0 });
+        assert!(matches!(sketch.constraints[0].constraint, SketchConstraint::Fix { .. }));
+        assert!(matches!(sketch.constraints[1].constraint, SketchConstraint::Radius { entity, .. } if entity == arc));
+        assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+        assert!(sketch.arc_to_circle(arc).is_err(), "Already a circle");
+
+        assert!(sketch.circle_to_arc(arc, 1.0, 1.0 + TAU).is_err(), "A full sweep isn't an arc");
+        assert!(sketch.circle_to_arc(line, 0.0, 1.0).is_err());
+        sketch.convert_entity(arc, EntityConversion::Arc { start_angle: 0.5, end_angle: 2.0 }).unwrap();
+        assert_eq!(sketch.entities[0].id, arc);
+        assert_eq!(sketch.entities[0].geometry, SketchGeometry::Arc { center: [1.0, 2.0], radius: 3.0, start_angle: 0.5, end_angle: 2.0 });
+        assert_eq!(sketch.constraints.len(), 2, "The circle's constraints all hold on the arc");
+    }
+
+    #[test]
+    fn test_arc_constructors_sweep_the_right_way_in_every_quadrant() {
+        let (center, radius) = ([2.0, -1.0], 3.0);
+        let at = |deg: f64| [center[0] + radius * deg.to_radians().cos(), center[1] + radius * deg.to_radians().sin()];
+        // Whether the counter-clockwise sweep of an arc passes `deg`
+        let passes = |geometry: &SketchGeometry, deg: f64| {
+            let SketchGeometry::Arc { start_angle, end_angle, .. } = *geometry else { panic!("Not an arc: {:?}", geometry) };
+            (deg.to_radians() - start_angle).rem_euclid(TAU) < (end_angle - start_angle).rem_euclid(TAU)
+        };
+        let same_angle = |a: f64, deg: f64| (a - deg.to_radians()).rem_euclid(TAU).min((deg.to_radians() - a).rem_euclid(TAU)) < 1e-9;
+
+        for from in [30.0_f64, 120.0, 210.0, 300.0] {
+            for to in [60.0, 150.0, 240.0, 330.0] {
+                let ccw_middle = from + (to - from).rem_euclid(360.0) / 2.0;
+                for (middle, ccw) in [(ccw_middle, true), (ccw_middle + 180.0, false)] {
+                    let case = format!("{} to {} through {}", from, to, middle);
+                    let arc = arc_from_three_points(at(from), at(middle), at(to)).unwrap();
+                    assert_eq!(arc.warning, None);
+                    let SketchGeometry::Arc { center: c, radius: r, start_angle, end_angle } = arc.geometry else { panic!("{}", case) };
+                    assert!((c[0] - center[0]).abs() < 1e-9 && (c[1] - center[1]).abs() < 1e-9 && (r - radius).abs() < 1e-9, "{}", case);
+                    let (first, last) = if ccw { (from, to) } else { (to, from) };
+                    assert!(same_angle(start_angle, first) && same_angle(end_angle, last), "{}", case);
+                    assert!(passes(&arc.geometry, middle) && !passes(&arc.geometry, middle + 180.0), "{}", case);
+
+                    let by_center = arc_center_start_end(center, at(from), at(to), ccw).unwrap();
+                    assert!(passes(&by_center, middle) && !passes(&by_center, middle + 180.0), "{} by center", case);
+                }
+            }
+        }
+
+        let line = arc_from_three_points([0.0, 0.0], [1.0, 1.0], [3.0, 3.0]).unwrap();
+        assert_eq!(line.geometry, SketchGeometry::Line { start: [0.0, 0.0], end: [3.0, 3.0] });
+        assert!(line.warning.is_some());
+        assert!(arc_from_three_points([0.0, 0.0], [0.0, 0.0], [1.0, 2.0]).is_err());
+        assert!(arc_center_start_end(center, center, at(90.0), true).is_err());
+    }
+
+    #[test]
+    fn test_tangent_arcs_continue_lines_and_arcs_smoothly() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let cp = |id: EntityId, index: u8| ConstraintPoint { id, index };
+
+        // Left off the line's end, right off that arc's end, then straight on from where that arc finishes
+        let left = add_entity_spec(&mut sketch, EntitySpec::TangentArc { from: cp(line, 1), end: [15.0, 5.0] }).unwrap();
+        let SketchGeometry::Arc { center, radius, .. } = left.geometry else { panic!("{:?}", left.geometry) };
+        assert!((center[0] - 10.0).abs() < 1e-9 && (center[1] - 5.0).abs() < 1e-9 && (radius - 5.0).abs() < 1e-9);
+        let right = add_entity_spec(&mut sketch, EntitySpec::TangentArc { from: cp(left.entity_id, 2), end: [25.0, 5.0] }).unwrap();
+        let SketchGeometry::Arc { center, start_angle, end_angle, .. } = right.geometry else { panic!("{:?}", right.geometry) };
+        assert!((center[0] - 20.0).abs() < 1e-9 && (center[1] - 5.0).abs() < 1e-9);
+        assert!(start_angle.abs() < 1e-9 && (end_angle - std::f64::consts::PI).abs() < 1e-9, "Clockwise, so stored from its far end");
+        let straight = add_entity_spec(&mut sketch, EntitySpec::TangentArc { from: cp(right.entity_id, 1), end: [25.0, -5.0] }).unwrap();
+        assert_eq!(straight.geometry, SketchGeometry::Line { start: [25.0, 5.0], end: [25.0, -5.0] });
+        assert!(straight.warning.is_some());
+
+        let drawn = sketch.entities.clone();
+        let joints: Vec<_> = sketch.constraints.iter().filter_map(|entry| match entry.constraint {
+            SketchConstraint::TangentAtPoint { a, b } => Some((a, b)),
+            _ => None,
+        }).collect();
+        assert_eq!(joints.len(), 3);
+        // Direction a line runs, or an arc runs counter-clockwise, at one of its ends
+        let direction = |point: ConstraintPoint| match sketch.entities.iter().find(|e| e.id == point.id).unwrap().geometry {
+            SketchGeometry::Line { start, end } => [end[0] - start[0], end[1] - start[1]],
+            SketchGeometry::Arc { start_angle, end_angle, .. } => {
+                let angle = if point.index == 1 { start_angle } else { end_angle };
+                [-angle.sin(), angle.cos()]
+            }
+            ref other => panic!("Not a line or arc: {:?}", other),
+        };
+        for (a, b) in joints {
+            let (ta, tb) = (direction(a), direction(b));
+            assert!((ta[0] * tb[1] - ta[1] * tb[0]).abs() < 1e-9, "{:?} and {:?} should be tangent", ta, tb);
+        }
+
+        // The constraints already hold, so solving leaves the drawing alone
+        let result = SketchSolver::solve_with_result(&mut sketch);
+        assert!(result.converged, "{}", result.status_message);
+        for (before, after) in drawn.iter().zip(&sketch.entities) {
+            let moved = match (&before.geometry, &after.geometry) {
+                (SketchGeometry::Line { start: s0, end: e0 }, SketchGeometry::Line { start: s1, end: e1 }) => {
+                    (s0[0] - s1[0]).hypot(s0[1] - s1[1]) + (e0[0] - e1[0]).hypot(e0[1] - e1[1])
+                }
+                (SketchGeometry::Arc { center: c0, radius: r0, start_angle: a0, end_angle: b0 },
+                 SketchGeometry::Arc { center: c1, radius: r1, start_angle: a1, end_angle: b1 }) => {
+                    (c0[0] - c1[0]).hypot(c0[1] - c1[1]) + (r0 - r1).abs() + (a0 - a1).abs() + (b0 - b1).abs()
+                }
+                other => panic!("Entity changed kind: {:?}", other),
+            };
+            assert!(moved < 1e-6, "{:?} moved to {:?}", before.geometry, after.geometry);
+        }
+        assert!(tangent_arc_from(&sketch, cp(line, 5), [0.0, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_remapped_ids_follow_every_reference() {
+        use crate::sketch::reference::ReferenceEntity;
+        use crate::topo::naming::{TopoId, TopoRank};
+
+        let (mut sketch, ids) = constrained_rectangle();
+        let axis = ReferenceEntity::YAxis.id();
+        sketch.add_constraint(SketchConstraint::Symmetric {
+            p1: ConstraintPoint { id: ids[0], index: 0 },
+            p2: ConstraintPoint { id: ids[0], index: 1 },
+            axis,
+        });
+        sketch.add_constraint(SketchConstraint::EqualGroup { entities: vec![ids[0], ids[2]] });
+        // Text that merely contains an id is left alone
+        sketch.constraints[0].group = Some(ids[0].to_string());
+        let source = TopoId::new(EntityId::new(), 7, TopoRank::Edge);
+        sketch.external_references.insert(ids[3], source);
+
+        let mapped: HashMap<EntityId, EntityId> = ids.iter().map(|&id| (id, EntityId::new())).collect();
+        let copy = sketch.with_remapped_entity_ids(|id| mapped[&id]);
+
+        assert_eq!(copy.entities.iter().map(|e| e.id).collect::<Vec<_>>(), ids.map(|id| mapped[&id]).to_vec());
+        for entry in &copy.constraints {
+            let mut constraint = entry.constraint.clone();
+            for id in constraint_ids_mut(&mut constraint) {
+                assert!(*id == axis || mapped.values().any(|new| new == id), "{:?} kept an old id", entry.constraint);
+            }
+        }
+        let SketchConstraint::Symmetric { axis: copied_axis, .. } = copy.constraints[9].constraint else { panic!("Expected the symmetry") };
+        assert_eq!(copied_axis, axis, "Reference geometry isn't the sketch's to remap");
+        assert_eq!(copy.constraints[0].group, Some(ids[0].to_string()));
+        assert_eq!(copy.external_references.get(&mapped[&ids[3]]), Some(&source));
+        assert!(copy.history.iter().all(|op| !matches!(op, SketchOperation::AddGeometry { id, .. } if ids.contains(id))));
+    }
+}
@@ -0,0 +1,159 @@
+//! Face outlines offset into a sketch
+//!
+//! A planar model face's boundary, offset into the face, drawn as sketch
+//! geometry: one line per straight edge and one circle per circular loop.
+//! The entities are projected (`external_references` maps them to the face,
+//! so the solver keeps them fixed), and the sketch remembers each outline in
+//! `face_offsets` so regeneration redraws it from the face's current boundary.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::types::{Sketch, SketchGeometry, SketchPlane};
+use crate::geometry::defeature::face_boundary_loops;
+use crate::geometry::offset::{offset_outline, OutlineLoop};
+use crate::geometry::{Point3, Tessellation};
+use crate::topo::naming::TopoId;
+use crate::topo::registry::{AnalyticGeometry, KernelEntity};
+use crate::topo::EntityId;
+
+/// Distance within which f32 tessellation vertices count as on one line or circle
+const OUTLINE_TOLERANCE: f64 = 1e-4;
+
+/// An offset face outline kept in a sketch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaceOffset {
+    pub face: TopoId,
+    /// Distance into the face; negative offsets outward
+    pub offset: f64,
+    /// The entities drawn for each loop of the outline
+    pub loops: Vec<Vec<EntityId>>,
+}
+
+/// Boundary of `face` offset by `offset` into it, in `plane`'s coordinates.
+/// Fails for faces that aren't planar.
+pub fn face_offset_outline(
+    face: &TopoId,
+    offset: f64,
+    plane: &SketchPlane,
+    tessellation: &Tessellation,
+    manifest: &HashMap<TopoId, KernelEntity>,
+) -> Result<Vec<OutlineLoop>, String> {
+    match manifest.get(face).map(|e| &e.geometry) {
+        Some(AnalyticGeometry::Plane { .. }) => {}
+        Some(_) => return Err("Face is not planar; only planar faces can be offset".to_string()),
+        None => return Err("Face not found in the model".to_string()),
+    }
+    let to_2d = |p: &Point3| {
+        let v = p - plane.origin;
+        [v.dot(&plane.x_axis), v.dot(&plane.y_axis)]
+    };
+    let loops: Vec<OutlineLoop> = face_boundary_loops(tessellation, face)?.iter()
+        .map(|points| OutlineLoop::from_points(&points.iter().map(to_2d).collect::<Vec<_>>(), OUTLINE_TOLERANCE))
+        .collect();
+    offset_outline(&loops, offset)
+}
+
+/// Geometry drawn for a loop: a circle, or a line per edge running counter-clockwise
+fn loop_geometry(outline: &OutlineLoop) -> Vec<SketchGeometry> {
+    match outline {
+        OutlineLoop::Circle { center, radius } => vec![SketchGeometry::Circle { center: *center, radius: *radius }],
+        OutlineLoop::Polygon(points) => {
+            let mut points = points.clone();
+            if crate::geometry::utils_2d::polygon_signed_area(&points) < 0.0 {
+                points.reverse();
+            }
+            (0..points.len())
+                .map(|i| SketchGeometry::Line { start: points[i], end: points[(i + 1) % points.len()] })
+                .collect()
+        }
+    }
+}
+
+fn geometry_centroid(geometry: &[SketchGeometry]) -> [f64; 2] {
+    let points: Vec<[f64; 2]> = geometry.iter().map(|g| match g {
+        SketchGeometry::Line { start, end } => [(start[0] + end[0]) * 0.5, (start[1] + end[1]) * 0.5],
+        SketchGeometry::Circle { center, .. } => *center,
+        _ => [0.0, 0.0],
+    }).collect();
+    let n = points.len().max(1) as f64;
+    [points.iter().map(|p| p[0]).sum::<f64>() / n, points.iter().map(|p| p[1]).sum::<f64>() / n]
+}
+
+/// `new` turned so its lines line up with `old`'s, keeping an edge's entity
+/// on the same side of the outline when the loop starts at another corner
+fn align_lines(old: &[SketchGeometry], mut new: Vec<SketchGeometry>) -> Vec<SketchGeometry> {
+    let mismatch = |shift: usize| -> f64 {
+        old.iter().enumerate().map(|(i, g)| {
+            let [a, b] = [geometry_centroid(std::slice::from_ref(g)), geometry_centroid(std::slice::from_ref(&new[(i + shift) % new.len()]))];
+            (a[0] - b[0]).hypot(a[1] - b[1])
+        }).sum()
+    };
+    let best = (0..new.len()).min_by(|&a, &b| mismatch(a).total_cmp(&mismatch(b))).unwrap_or(0);
+    new.rotate_left(best);
+    new
+}
+
+impl Sketch {
+    /// Draw an offset outline of `face` (from `face_offset_outline`), returning
+    /// the new entities
+    pub fn add_face_offset(&mut self, face: TopoId, offset: f64, outline: &[OutlineLoop]) -> Vec<EntityId> {
+        let loops: Vec<Vec<EntityId>> = outline.iter()
+            .map(|l| loop_geometry(l).into_iter().map(|g| self.add_entity(g)).collect())
+            .collect();
+        let created: Vec<EntityId> = loops.iter().flatten().copied().collect();
+        for id in &created {
+            self.external_references.insert(*id, face);
+        }
+        self.face_offsets.push(FaceOffset { face, offset, loops });
+        created
+    }
+
+    /// Redraw the face offsets from the current model. An outline whose face
+    /// is gone or changed shape keeps its last geometry; the reasons are returned.
+    pub fn update_face_offsets(&mut self, tessellation: &Tessellation, manifest: &HashMap<TopoId, KernelEntity>) -> Vec<String> {
+        let mut failures = Vec::new();
+        let mut updates = Vec::new();
+        for face_offset in &self.face_offsets {
+            let geometry_of = |id: &EntityId| self.entities.iter().find(|e| e.id == *id).map(|e| e.geometry.clone());
+            let matched = face_offset_outline(&face_offset.face, face_offset.offset, &self.plane, tessellation, manifest)
+                .and_then(|outline| {
+                    let mut fresh: Vec<Option<Vec<SketchGeometry>>> = outline.iter().map(|l| Some(loop_geometry(l))).collect();
+                    face_offset.loops.iter().map(|ids| {
+                        let old: Vec<SketchGeometry> = ids.iter().filter_map(geometry_of).collect();
+                        if old.is_empty() || old.len() != ids.len() {
+                            // Partly deleted since; what is left stays where it is
+                            return Ok(Vec::new());
+                        }
+                        let centroid = geometry_centroid(&old);
+                        // The unclaimed loop of the same shape nearest this one
+                        let nearest = (0..fresh.len())
+                            .filter(|&i| fresh[i].as_ref().is_some_and(|g| {
+                                g.len() == old.len() && std::mem::discriminant(&g[0]) == std::mem::discriminant(&old[0])
+                            }))
+                            .min_by(|&a, &b| {
+                                let distance = |i: usize| {
+                                    let c = geometry_centroid(fresh[i].as_ref().unwrap());
+                                    (c[0] - centroid[0]).hypot(c[1] - centroid[1])
+                                };
+                                distance(a).total_cmp(&distance(b))
+                            })
+                            .ok_or_else(|| "Face outline changed shape".to_string())?;
+                        let new = fresh[nearest].take().unwrap();
+                        Ok(ids.iter().copied().zip(align_lines(&old, new)).collect::<Vec<_>>())
+                    }).collect::<Result<Vec<_>, String>>()
+                });
+            match matched {
+                Ok(loops) => updates.extend(loops.into_iter().flatten()),
+                Err(e) => failures.push(format!("Offset of face {:?}: {}", face_offset.face, e)),
+            }
+        }
+        for (id, geometry) in updates {
+            if let Some(entity) = self.entities.iter_mut().find(|e| e.id == id) {
+                entity.geometry = geometry;
+            }
+        }
+        failures
+    }
+}
@@ -3,9 +3,55 @@
 //! These are pure geometry calculations for temporary, non-driving measurements.
 //! Measurements are session-only and update live as geometry changes.
 
-use crate::sketch::types::{SketchGeometry, SketchEntity};
+use std::collections::BTreeMap;
+
+use crate::sketch::types::{ellipse_point, Sketch, SketchGeometry, SketchEntity};
+use crate::topo::EntityId;
+use crate::units::LengthUnit;
 use serde::{Deserialize, Serialize};
 
+/// Live lengths and areas reported with the sketch status, in `unit`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SketchMeasurements {
+    pub unit: LengthUnit,
+    /// Length of every curve (see `SketchGeometry::length`); points are left out
+    pub entity_lengths: BTreeMap<EntityId, f64>,
+    /// Total length of the highlighted curves
+    pub highlight_length: f64,
+    /// Total area enclosed by the highlighted closed curves, in `unit` squared
+    pub highlight_area: f64,
+}
+
+impl Default for SketchMeasurements {
+    fn default() -> Self {
+        Self {
+            unit: LengthUnit::Millimeter,
+            entity_lengths: BTreeMap::new(),
+            highlight_length: 0.0,
+            highlight_area: 0.0,
+        }
+    }
+}
+
+/// Measure every curve of `sketch` (drawn in mm) and total the `highlight`ed
+/// ones, converted to `unit`. Highlighted ids missing from the sketch are ignored.
+pub fn measure_sketch(sketch: &Sketch, highlight: &[EntityId], unit: LengthUnit) -> SketchMeasurements {
+    let mut measurements = SketchMeasurements { unit, ..Default::default() };
+    for entity in &sketch.entities {
+        if matches!(entity.geometry, SketchGeometry::Point { .. }) {
+            continue;
+        }
+        let length = unit.from_mm(entity.geometry.length());
+        measurements.entity_lengths.insert(entity.id, length);
+        if highlight.contains(&entity.id) {
+            measurements.highlight_length += length;
+            // Areas scale with the square of the unit
+            measurements.highlight_area += entity.geometry.area().map_or(0.0, |area| unit.from_mm(unit.from_mm(area)));
+        }
+    }
+    measurements
+}
+
 /// Result of a measurement operation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MeasurementResult {
@@ -208,13 +254,18 @@ pub fn get_entity_point(entity: &SketchEntity, point_index: u8) -> Option<[f64;
             _ => None,
         },
         SketchGeometry::Ellipse { center, .. } => Some(*center),
+        SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => match point_index {
+            0 => Some(*center),
+            1 => Some(ellipse_point(*center, *semi_major, *semi_minor, *rotation, *start_angle)),
+            2 => Some(ellipse_point(*center, *semi_major, *semi_minor, *rotation, *end_angle)),
+            _ => None,
+        },
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::topo::EntityId;
 
     #[test]
     fn test_point_point_distance() {
@@ -305,11 +356,19 @@ mod tests {
             id: crate::topo::EntityId::new(),
             geometry: SketchGeometry::Point { pos: [0.0, 0.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         let e2 = SketchEntity {
             id: crate::topo::EntityId::new(),
             geometry: SketchGeometry::Point { pos: [3.0, 4.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         match measure_entities(&e1, &e2) {
@@ -326,11 +385,19 @@ mod tests {
             id: crate::topo::EntityId::new(),
             geometry: SketchGeometry::Line { start: [0.0, 0.0], end: [1.0, 0.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         let e2 = SketchEntity {
             id: crate::topo::EntityId::new(),
             geometry: SketchGeometry::Line { start: [0.0, 0.0], end: [0.0, 1.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         match measure_entities(&e1, &e2) {
@@ -347,10 +414,85 @@ mod tests {
             id: crate::topo::EntityId::new(),
             geometry: SketchGeometry::Line { start: [1.0, 2.0], end: [3.0, 4.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         assert_eq!(get_entity_point(&e, 0), Some([1.0, 2.0]));
         assert_eq!(get_entity_point(&e, 1), Some([3.0, 4.0]));
         assert_eq!(get_entity_point(&e, 2), None);
     }
+
+    fn close(a: f64, b: f64, relative: f64) -> bool {
+        (a - b).abs() <= relative * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_geometry_lengths_and_areas() {
+        use std::f64::consts::{FRAC_PI_2, PI};
+        let line = SketchGeometry::Line { start: [1.0, 1.0], end: [4.0, 5.0] };
+        assert_eq!(line.length(), 5.0);
+        assert_eq!(line.area(), None);
+        assert_eq!(SketchGeometry::Point { pos: [1.0, 2.0] }.length(), 0.0);
+
+        let circle = SketchGeometry::Circle { center: [0.0, 0.0], radius: 2.0 };
+        assert!(close(circle.length(), 4.0 * PI, 1e-12));
+        assert!(close(circle.area().unwrap(), 4.0 * PI, 1e-12));
+
+        let arc = |start_angle: f64, end_angle: f64| SketchGeometry::Arc { center: [0.0, 0.0], radius: 2.0, start_angle, end_angle };
+        assert!(close(arc(0.0, FRAC_PI_2).length(), PI, 1e-12));
+        assert!(close(arc(FRAC_PI_2, 0.0).length(), 3.0 * PI, 1e-12), "Sweeps counter-clockwise");
+        assert_eq!(arc(1.0, 1.0).length(), 0.0, "Zero sweep");
+        assert_eq!(arc(0.0, PI).area(), None);
+
+        // A circle is an ellipse with equal axes
+        let ellipse = |semi_major: f64, semi_minor: f64| SketchGeometry::Ellipse { center: [0.0, 0.0], semi_major, semi_minor, rotation: 0.3 };
+        assert!(close(ellipse(2.0, 2.0).length(), 4.0 * PI, 1e-12));
+        // 2:1 ellipse, perimeter 4a E(e) = 9.688448220547675...
+        assert!(close(ellipse(2.0, 1.0).length(), 9.688448220547675, 1e-9));
+        assert!(close(ellipse(3.0, 1.0).area().unwrap(), 3.0 * PI, 1e-12));
+        // Degenerate: a flattened ellipse is a doubled segment, a collapsed one nothing
+        assert!(close(ellipse(1.0, 0.0).length(), 4.0, 5e-4));
+        assert_eq!(ellipse(1.0, 0.0).area(), Some(0.0));
+        assert_eq!(ellipse(0.0, 0.0).length(), 0.0);
+
+        let ellipse_arc = |semi_minor: f64, start_angle: f64, end_angle: f64| SketchGeometry::EllipseArc {
+            center: [1.0, 1.0], semi_major: 2.0, semi_minor, rotation: 0.0, start_angle, end_angle,
+        };
+        let quarters: f64 = (0..4).map(|i| ellipse_arc(1.0, i as f64 * FRAC_PI_2, (i + 1) as f64 * FRAC_PI_2).length()).sum();
+        assert!(close(quarters, 9.688448220547675, 1e-9), "Quarters add up to the perimeter: {}", quarters);
+        assert!(close(ellipse_arc(2.0, 0.0, PI).length(), 2.0 * PI, 1e-9), "Half a circle");
+        assert!(close(ellipse_arc(0.0, 0.0, PI).length(), 4.0, 1e-3), "Flattened: across and back");
+        assert_eq!(ellipse_arc(1.0, 0.5, 0.5).length(), 0.0, "Zero sweep");
+        assert_eq!(ellipse_arc(1.0, 0.0, PI).area(), None);
+    }
+
+    #[test]
+    fn test_measure_sketch_totals_highlighted_entities_in_unit() {
+        use crate::sketch::types::SketchPlane;
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [25.4, 0.0] });
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 25.4 });
+        let point = sketch.add_entity(SketchGeometry::Point { pos: [1.0, 1.0] });
+        let other = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [0.0, 10.0] });
+
+        let mm = measure_sketch(&sketch, &[], LengthUnit::Millimeter);
+        assert_eq!(mm.entity_lengths.len(), 3, "Points have no length");
+        assert!(!mm.entity_lengths.contains_key(&point));
+        assert_eq!(mm.entity_lengths[&other], 10.0);
+        assert_eq!((mm.highlight_length, mm.highlight_area), (0.0, 0.0));
+
+        let inches = measure_sketch(&sketch, &[line, circle, point, EntityId::new()], LengthUnit::Inch);
+        assert_eq!(inches.unit, LengthUnit::Inch);
+        assert!(close(inches.entity_lengths[&line], 1.0, 1e-12));
+        assert!(close(inches.highlight_length, 1.0 + 2.0 * std::f64::consts::PI, 1e-12));
+        assert!(close(inches.highlight_area, std::f64::consts::PI, 1e-12), "Square inches");
+
+        // Keyed by entity id on the wire
+        let json = serde_json::to_value(&inches).unwrap();
+        assert!(json["entity_lengths"][line.to_string()].is_number());
+        assert_eq!(serde_json::from_value::<SketchMeasurements>(json).unwrap(), inches);
+    }
 }
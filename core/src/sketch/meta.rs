@@ -0,0 +1,151 @@
+//! Per-entity metadata: names, display colors and locking
+//!
+//! A named entity can be referred to by its name wherever an `EntityRef` is
+//! accepted (measurement highlights, preset targets), so templates and
+//! derived measurements keep working when the geometry is redrawn with new
+//! ids. A locked entity is held in place by the solver as though each of its
+//! points had a Fix constraint; the implicit fixes count towards the DOF but
+//! aren't listed with the sketch's constraints. Edits that store a whole new
+//! sketch can't move a locked entity either (see `Sketch::keep_locked`).
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use super::types::Sketch;
+use crate::topo::EntityId;
+
+/// Longest entity name accepted
+pub const MAX_NAME_LEN: usize = 64;
+
+/// An entity given by id or by name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EntityRef {
+    Id(EntityId),
+    Name(String),
+}
+
+impl From<EntityId> for EntityRef {
+    fn from(id: EntityId) -> Self {
+        EntityRef::Id(id)
+    }
+}
+
+/// Partial update of an entity's metadata. Omitted fields are left unchanged;
+/// `name` and `color` are cleared with an explicit null.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntityMetaUpdate {
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub color: Option<Option<[f32; 4]>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<bool>,
+}
+
+/// Distinguish a field given as null (`Some(None)`) from an omitted one (`None`)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Validate an entity name: non-empty, no surrounding whitespace, bounded
+/// length, and not something that would be read as an entity id
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Entity name must not be empty".to_string());
+    }
+    if name.trim() != name {
+        return Err(format!("Entity name '{}' has leading or trailing whitespace", name));
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("Entity name exceeds {} characters", MAX_NAME_LEN));
+    }
+    if uuid::Uuid::parse_str(name).is_ok() {
+        return Err(format!("Entity name '{}' would be read as an entity id", name));
+    }
+    Ok(())
+}
+
+impl Sketch {
+    pub fn entity_by_name(&self, name: &str) -> Option<EntityId> {
+        self.entities.iter().find(|e| e.name.as_deref() == Some(name)).map(|e| e.id)
+    }
+
+    /// The id `entity` refers to; ids aren't checked against the sketch, since
+    /// they may name reference geometry
+    pub fn resolve_entity(&self, entity: &EntityRef) -> Result<EntityId, String> {
+        match entity {
+            EntityRef::Id(id) => Ok(*id),
+            EntityRef::Name(name) => self.entity_by_name(name)
+                .ok_or_else(|| format!("No entity named '{}' in the sketch", name)),
+        }
+    }
+
+    pub fn is_locked(&self, id: EntityId) -> bool {
+        self.entities.iter().any(|e| e.id == id && e.locked)
+    }
+
+    /// Check every entity name is valid and used only once
+    pub fn validate_entity_names(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        for name in self.entities.iter().filter_map(|e| e.name.as_deref()) {
+            validate_name(name)?;
+            if !seen.insert(name) {
+                return Err(format!("Entity name '{}' is used more than once", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo what `edited`, a new version of this sketch, does to the entities
+    /// locked here: they keep their geometry and stay locked, since a lock is
+    /// only released through `set_entity_meta`. Returns the ids put back.
+    pub fn keep_locked(&self, edited: &mut Sketch) -> Vec<EntityId> {
+        let mut restored = Vec::new();
+        for locked in self.entities.iter().filter(|e| e.locked) {
+            if let Some(entity) = edited.entities.iter_mut().find(|e| e.id == locked.id) {
+                if entity.geometry != locked.geometry || !entity.locked {
+                    entity.geometry = locked.geometry.clone();
+                    entity.locked = true;
+                    restored.push(entity.id);
+                }
+            }
+        }
+        restored
+    }
+
+    /// Apply `update` to entity `id`, validating it first so a rejected update
+    /// leaves the entity unchanged. Returns whether the lock changed, which is
+    /// the only change that needs a re-solve.
+    pub fn set_entity_meta(&mut self, id: EntityId, update: &EntityMetaUpdate) -> Result<bool, String> {
+        let index = self.entities.iter().position(|e| e.id == id)
+            .ok_or_else(|| format!("Entity {} not found in the sketch", id.0))?;
+        if let Some(Some(name)) = &update.name {
+            validate_name(name)?;
+            if self.entities.iter().any(|e| e.id != id && e.name.as_deref() == Some(name.as_str())) {
+                return Err(format!("Entity name '{}' is already in use", name));
+            }
+        }
+        if let Some(Some(color)) = &update.color {
+            if color.iter().any(|c| !(0.0..=1.0).contains(c)) {
+                return Err("Color components must be between 0 and 1".to_string());
+            }
+        }
+
+        let entity = &mut self.entities[index];
+        if let Some(name) = &update.name {
+            entity.name = name.clone();
+        }
+        if let Some(color) = update.color {
+            entity.color = color;
+        }
+        let lock_changed = update.locked.is_some_and(|locked| locked != entity.locked);
+        if let Some(locked) = update.locked {
+            entity.locked = locked;
+        }
+        Ok(lock_changed)
+    }
+}
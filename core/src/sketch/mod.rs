@@ -3,6 +3,13 @@ pub mod solver;
 pub mod snap;
 pub mod regions;
 pub mod measurement;
+pub mod edit;
+pub mod presets;
+pub mod chains;
+pub mod reference;
+pub mod face_offset;
+pub mod dimensions;
+pub mod meta;
 
 #[cfg(test)]
 mod tests_infrastructure;
@@ -30,3 +37,15 @@ mod tests_suppression;
 
 #[cfg(test)]
 mod tests_dimensions_hv;
+
+#[cfg(test)]
+mod tests_external;
+
+#[cfg(test)]
+mod tests_analysis;
+
+#[cfg(test)]
+mod tests_tangent;
+
+#[cfg(test)]
+mod tests_entity_meta;
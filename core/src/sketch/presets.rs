@@ -0,0 +1,235 @@
+//! Constraint presets
+//!
+//! Macros that apply a batch of existing constraints to a set of entities,
+//! e.g. turning four roughly drawn lines into a rectangle.
+
+use std::f64::consts::PI;
+
+use super::types::{ConstraintPoint, Sketch, SketchConstraint, SketchGeometry};
+use crate::geometry::utils_2d::polygon_signed_area;
+use crate::variables::AngleUnit;
+use crate::topo::EntityId;
+use serde::Serialize;
+
+/// A preset offered to clients
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Number of lines the preset expects, or None for any closed chain of 3 or more
+    pub entity_count: Option<usize>,
+}
+
+pub const PRESETS: &[PresetInfo] = &[
+    PresetInfo {
+        name: "rectangle",
+        description: "Connect four lines into a rectangle (coincident corners, alternating horizontal/vertical)",
+        entity_count: Some(4),
+    },
+    PresetInfo {
+        name: "regular_polygon",
+        description: "Connect lines into a regular polygon (coincident corners, equal sides, equal angles)",
+        entity_count: None,
+    },
+];
+
+/// Apply the preset called `name` to `entity_ids`.
+/// Returns the number of constraints added.
+pub fn apply_preset(sketch: &mut Sketch, name: &str, entity_ids: &[EntityId]) -> Result<usize, String> {
+    match name {
+        "rectangle" => {
+            let ids: [EntityId; 4] = entity_ids.try_into()
+                .map_err(|_| format!("Rectangle needs 4 lines, got {}", entity_ids.len()))?;
+            make_rectangle(sketch, ids)
+        }
+        "regular_polygon" => make_regular_polygon(sketch, entity_ids, entity_ids.len()),
+        other => Err(format!("Unknown preset '{}'", other)),
+    }
+}
+
+/// Constrain four lines, in order around the loop, into a rectangle: corners
+/// coincident, sides alternately horizontal and vertical starting with the
+/// orientation closest to the first line's.
+pub fn make_rectangle(sketch: &mut Sketch, line_ids: [EntityId; 4]) -> Result<usize, String> {
+    let lines = line_endpoints(sketch, &line_ids)?;
+    let mut added = close_chain(sketch, &line_ids, &lines);
+
+    let (s, e) = lines[0];
+    let first_horizontal = (e[0] - s[0]).abs() >= (e[1] - s[1]).abs();
+    for (i, id) in line_ids.into_iter().enumerate() {
+        let constraint = if (i % 2 == 0) == first_horizontal {
+            SketchConstraint::Horizontal { entity: id }
+        } else {
+            SketchConstraint::Vertical { entity: id }
+        };
+        added += add_unique(sketch, constraint);
+    }
+    Ok(added)
+}
+
+/// Constrain `n` lines, in order around the loop, into a regular polygon:
+/// corners coincident, every side equal to the first, and each corner at
+/// the polygon's exterior angle.
+pub fn make_regular_polygon(sketch: &mut Sketch, line_ids: &[EntityId], n: usize) -> Result<usize, String> {
+    if n < 3 || line_ids.len() != n {
+        return Err(format!("Regular polygon needs at least 3 lines and exactly n of them (n = {}, got {})", n, line_ids.len()));
+    }
+    let lines = line_endpoints(sketch, line_ids)?;
+    let walk = walk_chain(&lines);
+    let mut added = close_chain(sketch, line_ids, &lines);
+
+    for id in &line_ids[1..] {
+        added += add_unique(sketch, SketchConstraint::Equal { entities: [line_ids[0], *id] });
+    }
+
+    // Angles run counter-clockwise from one side to the next, both pointing
+    // away from their corner: the reflex side of the corner when the walk goes
+    // counter-clockwise. The last corner follows from the others.
+    let exterior = 2.0 * PI / n as f64;
+    let corners: Vec<[f64; 2]> = lines.iter().zip(&walk).map(|(line, &(entry, _))| if entry == 0 { line.0 } else { line.1 }).collect();
+    let value = if polygon_signed_area(&corners) > 0.0 { PI + exterior } else { PI - exterior };
+    for i in 0..n - 1 {
+        added += add_unique(sketch, SketchConstraint::Angle { lines: [line_ids[i], line_ids[i + 1]], value, unit: AngleUnit::Radians, style: None });
+    }
+    Ok(added)
+}
+
+/// Line start and end points
+type Segment = ([f64; 2], [f64; 2]);
+
+fn line_endpoints(sketch: &Sketch, line_ids: &[EntityId]) -> Result<Vec<Segment>, String> {
+    line_ids.iter().map(|id| {
+        match sketch.entities.iter().find(|e| e.id == *id).map(|e| &e.geometry) {
+            Some(SketchGeometry::Line { start, end }) => Ok((*start, *end)),
+            Some(_) => Err(format!("Entity {} is not a line", id)),
+            None => Err(format!("Entity {} not found in sketch", id)),
+        }
+    }).collect()
+}
+
+/// For each line, the (entry, exit) endpoint indices when walking the chain in order.
+/// The first line is entered at the end away from the second line; each later
+/// line is entered at its endpoint closest to the previous exit.
+fn walk_chain(lines: &[Segment]) -> Vec<(u8, u8)> {
+    let point = |line: &Segment, index: u8| if index == 0 { line.0 } else { line.1 };
+    let dist = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+    let nearest = |line: &Segment, p: [f64; 2]| if dist(line.0, p) <= dist(line.1, p) { 0 } else { 1 };
+
+    let next = &lines[1];
+    let first_exit = if dist(lines[0].1, next.0).min(dist(lines[0].1, next.1)) <= dist(lines[0].0, next.0).min(dist(lines[0].0, next.1)) { 1 } else { 0 };
+    let mut walk = vec![(1 - first_exit, first_exit)];
+    for line in &lines[1..] {
+        let exit_point = point(&lines[walk.len() - 1], walk[walk.len() - 1].1);
+        let entry = nearest(line, exit_point);
+        walk.push((entry, 1 - entry));
+    }
+    walk
+}
+
+/// Coincident constraints joining each line's exit to the next line's entry, closing the loop
+fn close_chain(sketch: &mut Sketch, line_ids: &[EntityId], lines: &[Segment]) -> usize {
+    let walk = walk_chain(lines);
+    let n = line_ids.len();
+    (0..n).map(|i| {
+        let j = (i + 1) % n;
+        add_unique(sketch, SketchConstraint::Coincident {
+            points: [
+                ConstraintPoint { id: line_ids[i], index: walk[i].1 },
+                ConstraintPoint { id: line_ids[j], index: walk[j].0 },
+            ],
+        })
+    }).sum()
+}
+
+/// Add a constraint unless an equivalent one is already present. Returns 1 if added.
+fn add_unique(sketch: &mut Sketch, constraint: SketchConstraint) -> usize {
+    let swapped = match &constraint {
+        SketchConstraint::Coincident { points: [a, b] } => Some(SketchConstraint::Coincident { points: [*b, *a] }),
+        SketchConstraint::Equal { entities: [a, b] } => Some(SketchConstraint::Equal { entities: [*b, *a] }),
+        _ => None,
+    };
+    let exists = sketch.constraints.iter()
+        .any(|e| e.constraint == constraint || Some(&e.constraint) == swapped.as_ref());
+    if exists {
+        return 0;
+    }
+    sketch.add_constraint(constraint);
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sketch::solver::SketchSolver;
+    use crate::sketch::types::SketchPlane;
+
+    #[test]
+    fn test_make_rectangle_from_rough_lines() {
+        // Hand-drawn quad: slightly skewed sides, corners not quite touching,
+        // and the third line drawn backwards
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let ids = [
+            sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.2, 0.4] }),
+            sketch.add_entity(SketchGeometry::Line { start: [10.0, 0.3], end: [10.3, 5.1] }),
+            sketch.add_entity(SketchGeometry::Line { start: [-0.2, 4.8], end: [10.1, 5.0] }),
+            sketch.add_entity(SketchGeometry::Line { start: [0.1, 5.0], end: [0.0, 0.2] }),
+        ];
+
+        assert_eq!(make_rectangle(&mut sketch, ids).unwrap(), 8);
+        assert_eq!(make_rectangle(&mut sketch, ids).unwrap(), 0, "Re-applying adds nothing");
+        assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+
+        let line = |i: usize| match sketch.entities[i].geometry {
+            SketchGeometry::Line { start, end } => (start, end),
+            _ => unreachable!(),
+        };
+        let corners = [line(0).0, line(0).1, line(2).0, line(2).1];
+        let xs: Vec<f64> = corners.iter().map(|p| p[0]).collect();
+        let ys: Vec<f64> = corners.iter().map(|p| p[1]).collect();
+        for i in 0..4 {
+            let (s, e) = line(i);
+            let horizontal = i % 2 == 0;
+            let off_axis = if horizontal { (e[1] - s[1]).abs() } else { (e[0] - s[0]).abs() };
+            assert!(off_axis < 1e-4, "Side {} should be axis aligned: {:?} -> {:?}", i, s, e);
+            // Every endpoint is one of the four corners
+            for p in [s, e] {
+                assert!(xs.iter().any(|x| (x - p[0]).abs() < 1e-4) && ys.iter().any(|y| (y - p[1]).abs() < 1e-4));
+            }
+        }
+        // Backwards third line: its end meets the second line's end
+        assert!((line(2).1[0] - line(1).1[0]).abs() < 1e-4 && (line(2).1[1] - line(1).1[1]).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_apply_preset_validates_input() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [1.0, 0.0] });
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 1.0 });
+        assert!(apply_preset(&mut sketch, "rectangle", &[line]).is_err());
+        assert!(apply_preset(&mut sketch, "regular_polygon", &[line, line, circle]).unwrap_err().contains("not a line"));
+        assert!(apply_preset(&mut sketch, "spiral", &[line]).is_err());
+        assert!(sketch.constraints.is_empty());
+    }
+
+    #[test]
+    fn test_make_regular_hexagon() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        // Roughly regular, radius ~5, with jittered corners
+        let corners: Vec<[f64; 2]> = (0..6).map(|i| {
+            let a = i as f64 * PI / 3.0 + 0.05 * (i % 2) as f64;
+            let r = 5.0 + 0.2 * (i % 3) as f64;
+            [r * a.cos(), r * a.sin()]
+        }).collect();
+        let ids: Vec<EntityId> = (0..6)
+            .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 6] }))
+            .collect();
+        assert_eq!(make_regular_polygon(&mut sketch, &ids, 6).unwrap(), 6 + 5 + 5);
+        assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+
+        let lengths: Vec<f64> = sketch.entities.iter().map(|e| match e.geometry {
+            SketchGeometry::Line { start, end } => (end[0] - start[0]).hypot(end[1] - start[1]),
+            _ => unreachable!(),
+        }).collect();
+        assert!(lengths.iter().all(|l| (l - lengths[0]).abs() < 1e-4), "Sides should be equal: {:?}", lengths);
+    }
+}
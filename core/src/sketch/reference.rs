@@ -0,0 +1,199 @@
+//! Sketch reference geometry
+//!
+//! Every sketch has an origin point and X/Y axis lines. They are not stored in
+//! `Sketch::entities`, so they never take part in regions or profiles, but
+//! constraints and snaps refer to them by reserved entity ids like any other
+//! entity. The solver treats them as fixed.
+//!
+//! Older documents refer to the origin with the nil UUID; it is still accepted.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::snap::entity_bounds;
+use super::types::{Sketch, SketchGeometry};
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::EntityId;
+
+/// Extra length of the drawn axes beyond the sketch contents, as a fraction
+/// of the contents' size
+const AXIS_MARGIN: f64 = 0.1;
+
+/// Shortest drawn half-axis, so axes of an empty sketch are still visible
+const MIN_AXIS_LENGTH: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReferenceEntity {
+    Origin,
+    XAxis,
+    YAxis,
+}
+
+/// Solver geometry of the axes: unit segments from the origin
+const X_AXIS: SketchGeometry = SketchGeometry::Line { start: [0.0, 0.0], end: [1.0, 0.0] };
+const Y_AXIS: SketchGeometry = SketchGeometry::Line { start: [0.0, 0.0], end: [0.0, 1.0] };
+const ORIGIN: SketchGeometry = SketchGeometry::Point { pos: [0.0, 0.0] };
+
+impl ReferenceEntity {
+    pub const ALL: [ReferenceEntity; 3] = [ReferenceEntity::Origin, ReferenceEntity::XAxis, ReferenceEntity::YAxis];
+
+    /// Reserved entity id, the same in every sketch
+    pub fn id(self) -> EntityId {
+        EntityId(Uuid::from_u128(0x5e7c_0000_0000_4000_8000_0000_0000_0000 | (self.ordinal() + 1) as u128))
+    }
+
+    /// The reference entity `id` names, if any
+    pub fn from_id(id: EntityId) -> Option<ReferenceEntity> {
+        if id.0.is_nil() {
+            return Some(ReferenceEntity::Origin);
+        }
+        Self::ALL.into_iter().find(|r| r.id() == id)
+    }
+
+    pub fn is_reference(id: EntityId) -> bool {
+        Self::from_id(id).is_some()
+    }
+
+    /// Well-known local id of the entity's TopoId within the sketch feature
+    pub fn ordinal(self) -> u64 {
+        match self {
+            ReferenceEntity::Origin => 0,
+            ReferenceEntity::XAxis => 1,
+            ReferenceEntity::YAxis => 2,
+        }
+    }
+
+    pub fn rank(self) -> TopoRank {
+        match self {
+            ReferenceEntity::Origin => TopoRank::Vertex,
+            ReferenceEntity::XAxis | ReferenceEntity::YAxis => TopoRank::Edge,
+        }
+    }
+
+    /// TopoId of the entity in the evaluated sketch whose namespace is `sketch_feature`
+    pub fn topo_id(self, sketch_feature: EntityId) -> TopoId {
+        TopoId::new(sketch_feature, self.ordinal(), self.rank())
+    }
+
+    /// Geometry the solver sees. Axis lines are unit length; only their
+    /// direction and position matter to constraints.
+    pub fn geometry(self) -> &'static SketchGeometry {
+        match self {
+            ReferenceEntity::Origin => &ORIGIN,
+            ReferenceEntity::XAxis => &X_AXIS,
+            ReferenceEntity::YAxis => &Y_AXIS,
+        }
+    }
+
+    /// Geometry to draw: the axes span the sketch contents plus a margin
+    pub fn display_geometry(self, sketch: &Sketch) -> SketchGeometry {
+        let (min, max) = content_bounds(sketch);
+        let margin = AXIS_MARGIN * (max[0] - min[0]).max(max[1] - min[1]);
+        let extent = |axis: usize| {
+            ((min[axis] - margin).min(-MIN_AXIS_LENGTH), (max[axis] + margin).max(MIN_AXIS_LENGTH))
+        };
+        match self {
+            ReferenceEntity::Origin => ORIGIN,
+            ReferenceEntity::XAxis => {
+                let (lo, hi) = extent(0);
+                SketchGeometry::Line { start: [lo, 0.0], end: [hi, 0.0] }
+            }
+            ReferenceEntity::YAxis => {
+                let (lo, hi) = extent(1);
+                SketchGeometry::Line { start: [0.0, lo], end: [0.0, hi] }
+            }
+        }
+    }
+}
+
+/// Bounds of the sketch entities and the origin
+fn content_bounds(sketch: &Sketch) -> ([f64; 2], [f64; 2]) {
+    sketch.entities.iter().map(|e| entity_bounds(&e.geometry)).fold(([0.0, 0.0], [0.0, 0.0]), |(min, max), (lo, hi)| {
+        ([min[0].min(lo[0]), min[1].min(lo[1])], [max[0].max(hi[0]), max[1].max(hi[1])])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::mesh::mesh_volume;
+    use crate::evaluator::runtime::Runtime;
+    use crate::features::dag::FeatureGraph;
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::sketch::regions::find_regions;
+    use crate::sketch::solver::SketchSolver;
+    use crate::sketch::types::{ConstraintPoint, SketchConstraint, SketchPlane};
+    use crate::topo::IdGenerator;
+
+    #[test]
+    fn test_reference_ids_round_trip() {
+        for reference in ReferenceEntity::ALL {
+            assert_eq!(ReferenceEntity::from_id(reference.id()), Some(reference));
+        }
+        assert_eq!(ReferenceEntity::from_id(EntityId(Uuid::nil())), Some(ReferenceEntity::Origin));
+        assert_eq!(ReferenceEntity::from_id(EntityId::new()), None);
+    }
+
+    #[test]
+    fn test_axes_span_contents_with_margin() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Circle { center: [20.0, 5.0], radius: 5.0 });
+        // Contents span x 0..25, y 0..10; margin is 10% of 25
+        assert_eq!(ReferenceEntity::XAxis.display_geometry(&sketch), SketchGeometry::Line { start: [-2.5, 0.0], end: [27.5, 0.0] });
+        assert_eq!(ReferenceEntity::YAxis.display_geometry(&sketch), SketchGeometry::Line { start: [0.0, -2.5], end: [0.0, 12.5] });
+    }
+
+    #[test]
+    fn test_distance_to_origin_entity_matches_nil_convention() {
+        let solve = |origin: EntityId| {
+            let mut sketch = Sketch::new(SketchPlane::default());
+            let line = sketch.add_entity(SketchGeometry::Line { start: [3.0, 4.0], end: [10.0, 4.0] });
+            sketch.add_constraint(SketchConstraint::Distance {
+                points: [ConstraintPoint { id: line, index: 0 }, ConstraintPoint { id: origin, index: 0 }],
+                value: 2.0,
+                style: None,
+            });
+            let result = SketchSolver::solve_with_result(&mut sketch);
+            (sketch.entities[0].geometry.clone(), result.converged, result.dof)
+        };
+
+        let (legacy, legacy_converged, legacy_dof) = solve(EntityId(Uuid::nil()));
+        let (reference, converged, dof) = solve(ReferenceEntity::Origin.id());
+        assert!(legacy_converged && converged);
+        assert_eq!(reference, legacy);
+        assert_eq!(dof, legacy_dof);
+        let SketchGeometry::Line { start, .. } = reference else { unreachable!() };
+        assert!((start[0].hypot(start[1]) - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_reference_entities_stay_out_of_regions_and_profiles() {
+        // Square centered on the origin: the axes cross it but must not split it
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let corners = [[-5.0, -5.0], [5.0, -5.0], [5.0, 5.0], [-5.0, 5.0]];
+        let sides: Vec<EntityId> = (0..4)
+            .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] }))
+            .collect();
+        sketch.add_constraint(SketchConstraint::Parallel { lines: [sides[0], ReferenceEntity::XAxis.id()] });
+
+        let regions = find_regions(&sketch.entities);
+        assert_eq!(regions.len(), 1);
+        assert!(regions[0].boundary_entity_ids.iter().all(|id| !ReferenceEntity::is_reference(EntityId(*id))));
+
+        let mut graph = FeatureGraph::new();
+        let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch));
+        let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+            .with_param("distance", ParameterValue::Float(2.0));
+        extrude.dependencies = vec![sketch_feature.id];
+        graph.add_node(sketch_feature);
+        graph.add_node(extrude);
+        let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("References")).unwrap();
+
+        let tess = &result.tessellation;
+        let registered = result.topology_manifest.keys().filter(|id| tess.is_reference(id)).count();
+        assert_eq!(registered, 3, "Origin and both axes are registered");
+        assert!(tess.triangle_ids.iter().all(|id| !tess.is_reference(id)));
+        assert!((mesh_volume(tess) - 200.0).abs() < 1e-3);
+    }
+}
@@ -4,10 +4,14 @@
 //! 1. Finding all intersection points between curves
 //! 2. Building a planar graph with vertices at endpoints/intersections
 //! 3. Traversing the graph to find minimal enclosed faces
+//!
+//! `compute_medial_axis` approximates the centerline of a region from the
+//! Voronoi diagram of its sampled boundary.
 
 use crate::geometry::utils_2d::{self, EPSILON};
+use crate::geometry::tessellation::DEFAULT_TESSELLATION_SEGMENTS;
 use crate::sketch::types::{SketchEntity, SketchGeometry};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 
@@ -56,6 +60,13 @@ struct HalfEdge {
 
 /// Find all closed regions in the sketch
 pub fn find_regions(entities: &[SketchEntity]) -> Vec<SketchRegion> {
+    find_regions_with_segments(entities, DEFAULT_TESSELLATION_SEGMENTS)
+}
+
+/// Find all closed regions, discretizing full circles and ellipses into `segments`
+/// boundary points (split circle arcs use half that angular resolution).
+pub fn find_regions_with_segments(entities: &[SketchEntity], segments: usize) -> Vec<SketchRegion> {
+    let segments = segments.max(3);
     let mut regions = Vec::new();
     
     // Filter to non-construction entities
@@ -72,13 +83,13 @@ pub fn find_regions(entities: &[SketchEntity]) -> Vec<SketchRegion> {
     let intersections = find_all_intersections(&geom_entities);
     
     // 2. Build planar graph
-    let (vertices, mut edges) = build_planar_graph(&geom_entities, &intersections);
+    let (vertices, mut edges) = build_planar_graph(&geom_entities, &intersections, segments);
     
     if vertices.is_empty() || edges.is_empty() {
         // Handle self-contained loops (circles/ellipses)
         // Don't return early - let containment detection run to identify voids
         for entity in &geom_entities {
-            if let Some(region) = entity_as_region(entity) {
+            if let Some(region) = entity_as_region(entity, segments) {
                 regions.push(region);
             }
         }
@@ -112,7 +123,7 @@ pub fn find_regions(entities: &[SketchEntity]) -> Vec<SketchRegion> {
                     // Check if this entity was split by intersections
                     let was_split = edges.iter().any(|e| e.entity_id == entity.id.0);
                     if !was_split {
-                        if let Some(region) = entity_as_region(entity) {
+                        if let Some(region) = entity_as_region(entity, segments) {
                             regions.push(region);
                         }
                     }
@@ -195,9 +206,37 @@ pub fn find_regions(entities: &[SketchEntity]) -> Vec<SketchRegion> {
     final_regions
 }
 
-/// Test if a point is inside a region using winding number algorithm
+/// Test if a point is inside a region using winding number algorithm.
+/// Points inside one of the region's voids are outside the region.
 pub fn point_in_region(point: [f64; 2], region: &SketchRegion) -> bool {
     utils_2d::point_in_polygon(point, &region.boundary_points)
+        && !region.voids.iter().any(|void| utils_2d::point_in_polygon(point, void))
+}
+
+/// Find the region a picked point belongs to.
+/// Points within `EPSILON` of a boundary (outer or void) count as belonging to that
+/// region. When several regions qualify — nested regions sharing the picked boundary —
+/// the one with the smallest outer boundary wins.
+pub fn region_at_point(regions: &[SketchRegion], point: [f64; 2]) -> Option<String> {
+    regions.iter()
+        .filter(|region| point_in_region(point, region) || point_on_region_boundary(point, region))
+        .min_by(|a, b| {
+            let area_a = utils_2d::polygon_area(&a.boundary_points);
+            let area_b = utils_2d::polygon_area(&b.boundary_points);
+            area_a.partial_cmp(&area_b).unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|region| region.id.clone())
+}
+
+fn point_on_region_boundary(point: [f64; 2], region: &SketchRegion) -> bool {
+    std::iter::once(&region.boundary_points)
+        .chain(region.voids.iter())
+        .any(|ring| {
+            (0..ring.len()).any(|i| {
+                let closest = utils_2d::closest_point_on_segment(ring[i], ring[(i + 1) % ring.len()], point);
+                utils_2d::distance(closest, point) < EPSILON
+            })
+        })
 }
 
 /// Find all intersection points between entities
@@ -258,7 +297,8 @@ fn line_circle_intersect(s: [f64; 2], e: [f64; 2], c: [f64; 2], r: f64) -> Vec<[
 /// Build planar graph from entities and intersection points
 fn build_planar_graph(
     entities: &[&SketchEntity],
-    intersections: &[([f64; 2], Uuid, Uuid)]
+    intersections: &[([f64; 2], Uuid, Uuid)],
+    segments: usize,
 ) -> (Vec<GraphVertex>, Vec<HalfEdge>) {
     let mut vertices: Vec<GraphVertex> = Vec::new();
     let mut edges: Vec<HalfEdge> = Vec::new();
@@ -374,7 +414,8 @@ fn build_planar_graph(
                     let arc_length = angle2 - angle1;
                     
                     // Discretize into segments (more for longer arcs)
-                    let num_segments = ((arc_length / (std::f64::consts::PI / 16.0)).max(1.0)) as usize;
+                    let step = 4.0 * std::f64::consts::PI / segments as f64;
+                    let num_segments = ((arc_length / step).max(1.0)) as usize;
                     
                     let mut prev_vertex = get_or_create_vertex(p1, &mut vertices, &mut pos_to_vertex);
                     
@@ -645,11 +686,10 @@ fn compute_area_and_centroid(pts: &[[f64; 2]]) -> (f64, [f64; 2]) {
 }
 
 /// Convert a self-contained entity (circle/ellipse) to a region
-fn entity_as_region(entity: &SketchEntity) -> Option<SketchRegion> {
+fn entity_as_region(entity: &SketchEntity, segments: usize) -> Option<SketchRegion> {
     match &entity.geometry {
         SketchGeometry::Circle { center, radius } => {
             // Discretize circle
-            let segments = 64;
             let mut pts = Vec::with_capacity(segments);
             for i in 0..segments {
                 let angle = (i as f64 / segments as f64) * 2.0 * std::f64::consts::PI;
@@ -671,7 +711,6 @@ fn entity_as_region(entity: &SketchEntity) -> Option<SketchRegion> {
             })
         }
         SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => {
-            let segments = 64;
             let cos_r = rotation.cos();
             let sin_r = rotation.sin();
             let mut pts = Vec::with_capacity(segments);
@@ -701,6 +740,463 @@ fn entity_as_region(entity: &SketchEntity) -> Option<SketchRegion> {
     }
 }
 
+/// Winding of a region's outer boundary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegionOrientation {
+    CCW,
+    CW,
+}
+
+/// A region with the metadata clients need to offer it as a profile and
+/// re-match the user's choice after later edits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionSummary {
+    #[serde(flatten)]
+    pub region: SketchRegion,
+    /// Identifier derived only from the sorted boundary entity ids (see `summarize_regions`)
+    pub stable_id: String,
+    pub orientation: RegionOrientation,
+    /// Stable id of the smallest region enclosing this one, if nested
+    pub parent: Option<String>,
+}
+
+/// Attach stable ids, orientation and nesting to detected regions.
+///
+/// `SketchRegion::id` hashes the centroid, so it changes whenever the region
+/// moves. The stable id only depends on which entities bound the region.
+/// Regions sharing the same boundary set (e.g. the three faces of two
+/// overlapping circles) are told apart by a suffix in centroid order.
+pub fn summarize_regions(regions: &[SketchRegion]) -> Vec<RegionSummary> {
+    let keys: Vec<String> = regions.iter().map(|r| {
+        let mut ids: Vec<String> = r.boundary_entity_ids.iter().map(|id| id.to_string()).collect();
+        ids.sort();
+        ids.dedup();
+        format!("region_{}", Uuid::new_v5(&Uuid::NAMESPACE_OID, ids.join(",").as_bytes()).simple())
+    }).collect();
+
+    let mut stable_ids = keys.clone();
+    for key in keys.iter().collect::<HashSet<_>>() {
+        let mut shared: Vec<usize> = (0..regions.len()).filter(|&i| keys[i] == *key).collect();
+        if shared.len() < 2 {
+            continue;
+        }
+        shared.sort_by(|&a, &b| {
+            let (ca, cb) = (regions[a].centroid, regions[b].centroid);
+            ca[0].total_cmp(&cb[0]).then(ca[1].total_cmp(&cb[1]))
+        });
+        for (n, i) in shared.into_iter().enumerate() {
+            stable_ids[i] = format!("{}_{}", key, n);
+        }
+    }
+
+    let outer_areas: Vec<f64> = regions.iter().map(|r| utils_2d::polygon_area(&r.boundary_points)).collect();
+    regions.iter().enumerate().map(|(i, region)| {
+        let parent = (0..regions.len())
+            .filter(|&j| j != i && outer_areas[j] > outer_areas[i])
+            .filter(|&j| utils_2d::point_in_polygon(region.centroid, &regions[j].boundary_points))
+            .min_by(|&a, &b| outer_areas[a].total_cmp(&outer_areas[b]))
+            .map(|j| stable_ids[j].clone());
+        RegionSummary {
+            region: region.clone(),
+            stable_id: stable_ids[i].clone(),
+            orientation: if utils_2d::polygon_signed_area(&region.boundary_points) >= 0.0 {
+                RegionOrientation::CCW
+            } else {
+                RegionOrientation::CW
+            },
+            parent,
+        }
+    }).collect()
+}
+
+/// Keys that no longer match may still be re-matched by centroid within this
+/// fraction of the candidate region's size (square root of its area)
+const KEY_FALLBACK_TOLERANCE: f64 = 0.25;
+
+/// Find the regions picked earlier by stable id, optionally with the centroid
+/// each had when picked. Returns, per pick, the index into `summaries`.
+///
+/// Exact key matches are resolved first. A pick whose key drifted (e.g. a
+/// boundary entity was split or replaced) falls back to the nearest unclaimed
+/// region whose centroid lies within `KEY_FALLBACK_TOLERANCE` of the recorded one.
+pub fn match_region_keys(summaries: &[RegionSummary], picks: &[(&str, Option<[f64; 2]>)]) -> Vec<Option<usize>> {
+    let mut claimed = vec![false; summaries.len()];
+    let mut matches: Vec<Option<usize>> = picks.iter().map(|(key, _)| {
+        let index = summaries.iter().position(|s| s.stable_id == *key)?;
+        (!claimed[index]).then(|| {
+            claimed[index] = true;
+            index
+        })
+    }).collect();
+
+    for (pick, matched) in picks.iter().zip(matches.iter_mut()) {
+        let (None, Some(centroid)) = (*matched, pick.1) else { continue };
+        let distance = |s: &RegionSummary| (s.region.centroid[0] - centroid[0]).hypot(s.region.centroid[1] - centroid[1]);
+        *matched = (0..summaries.len())
+            .filter(|&i| !claimed[i])
+            .filter(|&i| distance(&summaries[i]) <= KEY_FALLBACK_TOLERANCE * summaries[i].region.area.abs().sqrt())
+            .min_by(|&a, &b| distance(&summaries[a]).total_cmp(&distance(&summaries[b])));
+        if let Some(i) = *matched {
+            claimed[i] = true;
+        }
+    }
+    matches
+}
+
+/// A polyline in sketch coordinates
+pub type Polyline2D = Vec<[f64; 2]>;
+
+/// Approximate medial axis of a region, or why none could be computed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MedialAxis {
+    pub polylines: Vec<Polyline2D>,
+    /// Set when the axis could not be resolved; `polylines` is then empty
+    pub warning: Option<String>,
+}
+
+/// Boundary samples closer than this many sample spacings along the boundary
+/// lie on the same wall; the Voronoi edges between them are not part of the axis
+const MEDIAL_WALL_SEPARATION: f64 = 2.5;
+/// Boundary samples seen under a smaller angle than this from the Voronoi
+/// point between them are facets of one curved wall (e.g. a tessellated
+/// circle), not two walls
+const MEDIAL_MIN_ANGLE: f64 = std::f64::consts::FRAC_PI_4;
+/// Loose branches shorter than this many sample spacings are pruned as spurs
+const MEDIAL_SPUR_LENGTH: f64 = 3.0;
+/// If more of the interior Voronoi vertices than this fraction fall outside
+/// the region, the walls are too close together for the sample spacing
+const MEDIAL_MAX_OUTSIDE_FRACTION: f64 = 0.1;
+/// Upper bound on boundary samples, to keep the triangulation affordable
+const MEDIAL_MAX_SAMPLES: usize = 4000;
+
+/// Approximate medial axis of `region`, empty if it cannot be resolved at
+/// `sample_spacing` (see `compute_medial_axis`)
+pub fn medial_axis(region: &SketchRegion, sample_spacing: f64) -> Vec<Polyline2D> {
+    compute_medial_axis(region, sample_spacing).polylines
+}
+
+/// Approximate the medial axis of a region (holes included) from the Voronoi
+/// diagram of its boundary, sampled every `sample_spacing`.
+///
+/// The Voronoi vertices are the circumcenters of the Delaunay triangles inside
+/// the region. Two are joined when their triangles share an edge between
+/// samples on different walls (see `MEDIAL_WALL_SEPARATION` and
+/// `MEDIAL_MIN_ANGLE`), then branches shorter than
+/// `MEDIAL_SPUR_LENGTH` spacings are pruned. Walls closer together than the
+/// spacing cannot be resolved: rather than a garbled axis, the result is then
+/// empty with a warning.
+pub fn compute_medial_axis(region: &SketchRegion, sample_spacing: f64) -> MedialAxis {
+    let fail = |warning: String| MedialAxis { polylines: Vec::new(), warning: Some(warning) };
+    if !(sample_spacing.is_finite() && sample_spacing > 0.0) {
+        return fail(format!("Invalid sample spacing {}", sample_spacing));
+    }
+    let perimeter: f64 = std::iter::once(&region.boundary_points).chain(region.voids.iter())
+        .map(|ring| (0..ring.len()).map(|i| utils_2d::distance(ring[i], ring[(i + 1) % ring.len()])).sum::<f64>())
+        .sum();
+    if !perimeter.is_finite() || perimeter / sample_spacing > MEDIAL_MAX_SAMPLES as f64 {
+        return fail(format!("Sample spacing {} is too fine for a boundary of length {}", sample_spacing, perimeter));
+    }
+
+    let (samples, ring_lengths) = sample_boundary(region, sample_spacing);
+    if ring_lengths.is_empty() || samples.iter().filter(|s| s.ring == 0).count() < 3 {
+        return fail("The region boundary is degenerate".to_string());
+    }
+    let wall = MEDIAL_WALL_SEPARATION * sample_spacing;
+    // Distance along the boundary between two samples
+    let separation = |a: usize, b: usize| {
+        let (a, b) = (&samples[a], &samples[b]);
+        if a.ring != b.ring {
+            return f64::INFINITY;
+        }
+        let d = (a.arc - b.arc).abs();
+        d.min(ring_lengths[a.ring] - d)
+    };
+
+    let points: Vec<[f64; 2]> = samples.iter().map(|s| s.pos).collect();
+    let triangles = delaunay(&points);
+    let mut edge_triangles: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+    for (i, t) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            let (a, b) = (t[k], t[(k + 1) % 3]);
+            edge_triangles.entry((a.min(b), a.max(b))).or_default().push(i);
+        }
+    }
+
+    // Every pair of neighbouring samples must be a Delaunay edge, otherwise
+    // another wall comes closer than the spacing and the triangles cross it.
+    // Walls that pass within half the spacing cannot be told apart either.
+    let crossed = (0..samples.len()).any(|i| {
+        let j = if i + 1 < samples.len() && samples[i + 1].ring == samples[i].ring {
+            i + 1
+        } else {
+            samples.iter().position(|s| s.ring == samples[i].ring).unwrap_or(i)
+        };
+        !edge_triangles.contains_key(&(i.min(j), i.max(j)))
+    });
+    let pinched = edge_triangles.keys()
+        .any(|&(a, b)| separation(a, b) > wall && utils_2d::distance(points[a], points[b]) < 0.5 * sample_spacing);
+    if crossed || pinched {
+        return fail(format!("Walls come closer than the sample spacing {}; use a finer spacing", sample_spacing));
+    }
+
+    // Voronoi vertices of the triangles inside the region. Triangles between
+    // samples of one wall only carry rounding noise.
+    let centers: Vec<Option<[f64; 2]>> = triangles.iter().map(|t| {
+        let spans_walls = (0..3).any(|k| separation(t[k], t[(k + 1) % 3]) > wall);
+        let centroid = [
+            (points[t[0]][0] + points[t[1]][0] + points[t[2]][0]) / 3.0,
+            (points[t[0]][1] + points[t[1]][1] + points[t[2]][1]) / 3.0,
+        ];
+        (spans_walls && point_in_region(centroid, region))
+            .then(|| circumcircle(points[t[0]], points[t[1]], points[t[2]]).0)
+    }).collect();
+    let interior = centers.iter().flatten().count();
+    let outside = centers.iter().flatten().filter(|c| !point_in_region(**c, region)).count();
+    if interior == 0 || outside as f64 > MEDIAL_MAX_OUTSIDE_FRACTION * interior as f64 {
+        return fail(format!("The region is too thin for the sample spacing {}; use a finer spacing", sample_spacing));
+    }
+    let node = |t: usize| centers[t].filter(|c| point_in_region(*c, region));
+
+    // Join Voronoi vertices across edges between different walls, merging
+    // vertices that coincide (cocircular samples)
+    let mut parent: Vec<usize> = (0..triangles.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut joins = Vec::new();
+    for (&(a, b), tris) in &edge_triangles {
+        let &[t0, t1] = tris.as_slice() else { continue };
+        let (Some(c0), Some(c1)) = (node(t0), node(t1)) else { continue };
+        let r = utils_2d::distance(utils_2d::midpoint(c0, c1), points[a]);
+        let angle = 2.0 * (utils_2d::distance(points[a], points[b]) / (2.0 * r)).min(1.0).asin();
+        if separation(a, b) <= wall || angle < MEDIAL_MIN_ANGLE {
+            continue;
+        }
+        if utils_2d::distance(c0, c1) < 1e-3 * sample_spacing {
+            let (r0, r1) = (root(&mut parent, t0), root(&mut parent, t1));
+            parent[r1] = r0;
+        } else {
+            joins.push((t0, t1));
+        }
+    }
+    let mut edges: Vec<(usize, usize)> = joins.into_iter()
+        .map(|(t0, t1)| (root(&mut parent, t0), root(&mut parent, t1)))
+        .filter(|(a, b)| a != b)
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+    let position = |n: usize| node(n).unwrap_or_default();
+
+    let graph = MedialGraph::new(edges, triangles.len());
+    let polylines = graph.pruned(MEDIAL_SPUR_LENGTH * sample_spacing, &position).polylines(&position);
+    if polylines.is_empty() {
+        return fail("The medial axis has no branch longer than the spur threshold".to_string());
+    }
+    MedialAxis { polylines, warning: None }
+}
+
+/// A point on a sampled region boundary
+struct BoundarySample {
+    pos: [f64; 2],
+    /// 0 for the outer boundary, then one per void
+    ring: usize,
+    /// Distance along the ring from its first point
+    arc: f64,
+}
+
+/// Sample the outer boundary and voids at most `spacing` apart. Returns the
+/// samples, ring by ring, and the length of each ring.
+fn sample_boundary(region: &SketchRegion, spacing: f64) -> (Vec<BoundarySample>, Vec<f64>) {
+    let mut samples = Vec::new();
+    let mut lengths = Vec::new();
+    for (ring, points) in std::iter::once(&region.boundary_points).chain(region.voids.iter()).enumerate() {
+        let n = match points.len() {
+            n if n > 1 && utils_2d::points_equal(points[0], points[n - 1]) => n - 1,
+            n => n,
+        };
+        let mut arc = 0.0;
+        for i in 0..n {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            let length = utils_2d::distance(a, b);
+            if length < EPSILON {
+                continue;
+            }
+            let steps = (length / spacing).ceil().max(1.0) as usize;
+            for j in 0..steps {
+                // Nudge samples along the boundary so regular layouts (e.g. the
+                // two sides of a rectangle) are not exactly cocircular
+                let nudge = (samples.len() as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 11;
+                let t = (j as f64 + 1e-6 * nudge as f64 / (1u64 << 53) as f64) / steps as f64;
+                samples.push(BoundarySample { pos: utils_2d::lerp(a, b, t), ring, arc: arc + t * length });
+            }
+            arc += length;
+        }
+        lengths.push(arc);
+    }
+    (samples, lengths)
+}
+
+/// Center and squared radius of the circle through three points. Collinear
+/// points get an infinite circle.
+fn circumcircle(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> ([f64; 2], f64) {
+    let (b, c) = ([b[0] - a[0], b[1] - a[1]], [c[0] - a[0], c[1] - a[1]]);
+    let d = 2.0 * utils_2d::cross_2d(b, c);
+    let (b2, c2) = (utils_2d::dot_2d(b, b), utils_2d::dot_2d(c, c));
+    let u = [(c[1] * b2 - b[1] * c2) / d, (b[0] * c2 - c[0] * b2) / d];
+    if u[0].is_finite() && u[1].is_finite() {
+        ([a[0] + u[0], a[1] + u[1]], utils_2d::dot_2d(u, u))
+    } else {
+        (a, f64::INFINITY)
+    }
+}
+
+/// Bowyer–Watson Delaunay triangulation, as triangles of indices into `points`
+fn delaunay(points: &[[f64; 2]]) -> Vec<[usize; 3]> {
+    let n = points.len();
+    let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+    for p in points {
+        for k in 0..2 {
+            min[k] = min[k].min(p[k]);
+            max[k] = max[k].max(p[k]);
+        }
+    }
+    let size = (max[0] - min[0]).max(max[1] - min[1]).max(EPSILON);
+    let mid = utils_2d::midpoint(min, max);
+    // Enclosing triangle, removed again at the end
+    let mut all = points.to_vec();
+    all.extend([
+        [mid[0] - 20.0 * size, mid[1] - 10.0 * size],
+        [mid[0] + 20.0 * size, mid[1] - 10.0 * size],
+        [mid[0], mid[1] + 20.0 * size],
+    ]);
+    let with_circle = |t: [usize; 3]| {
+        let (center, r2) = circumcircle(all[t[0]], all[t[1]], all[t[2]]);
+        (t, center, r2)
+    };
+
+    let mut triangles = vec![with_circle([n, n + 1, n + 2])];
+    for (i, &p) in points.iter().enumerate() {
+        let mut cavity: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+        triangles.retain(|(t, center, r2)| {
+            let bad = utils_2d::distance_squared(*center, p) < *r2;
+            if bad {
+                for k in 0..3 {
+                    let (a, b) = (t[k], t[(k + 1) % 3]);
+                    *cavity.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+                }
+            }
+            !bad
+        });
+        for ((a, b), count) in cavity {
+            if count == 1 {
+                triangles.push(with_circle([a, b, i]));
+            }
+        }
+    }
+    triangles.into_iter().map(|(t, ..)| t).filter(|t| t.iter().all(|&v| v < n)).collect()
+}
+
+/// Undirected graph of medial axis segments between Voronoi vertex ids
+struct MedialGraph {
+    edges: Vec<(usize, usize)>,
+    alive: Vec<bool>,
+    /// Edge indices at each vertex
+    incident: Vec<Vec<usize>>,
+}
+
+impl MedialGraph {
+    fn new(edges: Vec<(usize, usize)>, vertex_count: usize) -> Self {
+        let mut incident = vec![Vec::new(); vertex_count];
+        for (i, &(a, b)) in edges.iter().enumerate() {
+            incident[a].push(i);
+            incident[b].push(i);
+        }
+        let alive = vec![true; edges.len()];
+        MedialGraph { edges, alive, incident }
+    }
+
+    fn degree(&self, v: usize) -> usize {
+        self.incident[v].iter().filter(|&&e| self.alive[e]).count()
+    }
+
+    /// Follow `edge` away from `start` through degree-2 vertices. Returns the
+    /// edges walked and the vertex where the walk stopped.
+    fn walk(&self, start: usize, mut edge: usize) -> (Vec<usize>, usize) {
+        let mut path = Vec::new();
+        let mut v = start;
+        loop {
+            path.push(edge);
+            let (a, b) = self.edges[edge];
+            v = if a == v { b } else { a };
+            if v == start || self.degree(v) != 2 {
+                return (path, v);
+            }
+            edge = self.incident[v].iter().copied().find(|&e| e != edge && self.alive[e]).unwrap_or(edge);
+        }
+    }
+
+    /// Drop loose branches shorter than `min_length`, repeatedly, since
+    /// removing one spur can leave its junction as the end of another
+    fn pruned(mut self, min_length: f64, position: &impl Fn(usize) -> [f64; 2]) -> Self {
+        loop {
+            let mut changed = false;
+            for v in 0..self.incident.len() {
+                if self.degree(v) != 1 {
+                    continue;
+                }
+                let Some(first) = self.incident[v].iter().copied().find(|&e| self.alive[e]) else { continue };
+                let (path, _) = self.walk(v, first);
+                let length: f64 = path.iter()
+                    .map(|&e| utils_2d::distance(position(self.edges[e].0), position(self.edges[e].1)))
+                    .sum();
+                if length < min_length {
+                    for e in path {
+                        self.alive[e] = false;
+                    }
+                    changed = true;
+                }
+            }
+            if !changed {
+                return self;
+            }
+        }
+    }
+
+    /// Split the graph into polylines between ends and junctions; closed
+    /// loops repeat their first point at the end
+    fn polylines(&self, position: &impl Fn(usize) -> [f64; 2]) -> Vec<Polyline2D> {
+        let mut visited: Vec<bool> = self.alive.iter().map(|alive| !alive).collect();
+        let mut polylines = Vec::new();
+        let mut trace = |start: usize, edge: usize, visited: &mut Vec<bool>| {
+            let (path, _) = self.walk(start, edge);
+            let mut v = start;
+            let mut polyline = vec![position(v)];
+            for e in path {
+                visited[e] = true;
+                let (a, b) = self.edges[e];
+                v = if a == v { b } else { a };
+                polyline.push(position(v));
+            }
+            polylines.push(polyline);
+        };
+        let starts = (0..self.incident.len()).filter(|&v| self.degree(v) != 2)
+            .chain(0..self.incident.len());
+        for v in starts {
+            for &e in &self.incident[v] {
+                if !visited[e] {
+                    trace(v, e, &mut visited);
+                }
+            }
+        }
+        polylines
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -713,11 +1209,19 @@ mod tests {
                 id: EntityId::new(),
                 geometry: SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 },
                 is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
             },
             SketchEntity {
                 id: EntityId::new(),
                 geometry: SketchGeometry::Circle { center: [20.0, 0.0], radius: 5.0 },
                 is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
             },
         ];
         
@@ -732,11 +1236,19 @@ mod tests {
                 id: EntityId::new(),
                 geometry: SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 },
                 is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
             },
             SketchEntity {
                 id: EntityId::new(),
                 geometry: SketchGeometry::Circle { center: [6.0, 0.0], radius: 5.0 },
                 is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
             },
         ];
         
@@ -756,6 +1268,10 @@ mod tests {
             id: EntityId::new(),
             geometry: SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let regions = find_regions(&[entity]);
@@ -779,19 +1295,23 @@ mod tests {
     fn test_square_intersected_by_circle() {
         let square_lines = vec![
             // Bottom
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
             // Right
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
             // Top
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
             // Left
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
         ];
         
         let circle = SketchEntity {
             id: EntityId::new(),
             geometry: SketchGeometry::Circle { center: [10.0, 0.0], radius: 5.0 },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let mut entities = square_lines;
@@ -812,10 +1332,10 @@ mod tests {
     fn test_square_crossed_by_line() {
         // Square from -10 to 10
         let square_lines = vec![
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
         ];
         
         // Line crossing from left (-15, 0) to right (15, 0)
@@ -823,6 +1343,10 @@ mod tests {
             id: EntityId::new(),
             geometry: SketchGeometry::Line { start: [-15.0, 0.0], end: [15.0, 0.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let mut entities = square_lines;
@@ -843,10 +1367,10 @@ mod tests {
     fn test_square_two_vertical_lines() {
         // Square from -10 to 10. Area = 400.
         let square_lines = vec![
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
         ];
         
         // Line x = -2
@@ -854,12 +1378,20 @@ mod tests {
             id: EntityId::new(),
             geometry: SketchGeometry::Line { start: [-2.0, -15.0], end: [-2.0, 15.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         // Line x = 2
         let line2 = SketchEntity {
             id: EntityId::new(),
             geometry: SketchGeometry::Line { start: [2.0, -15.0], end: [2.0, 15.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let mut entities = square_lines;
@@ -881,13 +1413,13 @@ mod tests {
     fn test_user_scenario_exact() {
         let rect = vec![
             // Top
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-3.427366411755626, 5.128495017868517], end: [8.188989683086833, 5.128495017868517] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-3.427366411755626, 5.128495017868517], end: [8.188989683086833, 5.128495017868517] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
             // Right
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [8.188989683086833, 5.128495017868517], end: [8.188989683086833, -6.730505441649946] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [8.188989683086833, 5.128495017868517], end: [8.188989683086833, -6.730505441649946] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
             // Bottom
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [8.188989683086833, -6.730505441649946], end: [-3.427366411755626, -6.730505441649946] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [8.188989683086833, -6.730505441649946], end: [-3.427366411755626, -6.730505441649946] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
             // Left
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-3.427366411755626, -6.730505441649946], end: [-3.427366411755626, 5.128495017868517] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-3.427366411755626, -6.730505441649946], end: [-3.427366411755626, 5.128495017868517] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
         ];
         
         // 08d8e4ca-0328-4461-93c8-f64607604196
@@ -895,18 +1427,30 @@ mod tests {
             id: EntityId::new(),
             geometry: SketchGeometry::Line { start: [2.5947459039737835, 6.200382959225407], end: [-4.952031485437824, -5.9013985762803935] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         // fee6a609-aea6-497a-8ace-6d2d6fb07c23
         let line2 = SketchEntity {
             id: EntityId::new(),
             geometry: SketchGeometry::Line { start: [0.0, 6.380964821606707], end: [-5.3890639322594875, -2.260773369926582] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let circle = SketchEntity {
             id: EntityId::new(),
             geometry: SketchGeometry::Circle { center: [8.981862600577657, -9.740999883411394], radius: 8.503277250188482 },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let mut entities = rect;
@@ -929,10 +1473,10 @@ mod tests {
     fn test_square_with_filament() {
         // Square from -10 to 10
         let square_lines = vec![
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false },
-            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, -10.0], end: [10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, -10.0], end: [10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [10.0, 10.0], end: [-10.0, 10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
+            SketchEntity { id: EntityId::new(), geometry: SketchGeometry::Line { start: [-10.0, 10.0], end: [-10.0, -10.0] }, is_construction: false, creation_seq: 0, name: None, color: None, locked: false },
         ];
         
         // Line crossing top edge (-5, 10) and stopping inside (-5, 0)
@@ -940,6 +1484,10 @@ mod tests {
             id: EntityId::new(),
             geometry: SketchGeometry::Line { start: [-5.0, 15.0], end: [-5.0, 0.0] },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         };
         
         let mut entities = square_lines;
@@ -965,11 +1513,19 @@ mod tests {
                 id: EntityId::new(),
                 geometry: SketchGeometry::Circle { center: [0.0, 0.0], radius: 10.0 },
                 is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
             },
             SketchEntity {
                 id: EntityId::new(),
                 geometry: SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 },
                 is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
             },
         ];
         
@@ -998,4 +1554,183 @@ mod tests {
         // Ring area should be outer - inner
         assert!((ring.area - ring_area).abs() < 1.0, "Ring area should be outer - inner = {:.2}, got {:.2}", ring_area, ring.area);
     }
+
+    fn rectangle(min: [f64; 2], max: [f64; 2]) -> Vec<SketchEntity> {
+        let corners = [min, [max[0], min[1]], max, [min[0], max[1]]];
+        (0..4).map(|i| SketchEntity {
+            id: EntityId::new(),
+            geometry: SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] },
+            is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
+        }).collect()
+    }
+
+    #[test]
+    fn test_region_at_point_in_frame() {
+        let mut entities = rectangle([0.0, 0.0], [10.0, 10.0]);
+        entities.extend(rectangle([3.0, 3.0], [7.0, 7.0]));
+        let regions = find_regions(&entities);
+        assert_eq!(regions.len(), 2);
+
+        let inner = regions.iter().find(|r| r.voids.is_empty()).unwrap();
+        let frame = regions.iter().find(|r| r.voids.len() == 1).unwrap();
+
+        assert_eq!(region_at_point(&regions, [5.0, 5.0]), Some(inner.id.clone()));
+        assert_eq!(region_at_point(&regions, [1.0, 5.0]), Some(frame.id.clone()));
+        // On the shared boundary, the smaller enclosing region wins
+        assert_eq!(region_at_point(&regions, [3.0, 5.0]), Some(inner.id.clone()));
+        // On the outer boundary only the frame qualifies
+        assert_eq!(region_at_point(&regions, [0.0, 5.0]), Some(frame.id.clone()));
+        assert_eq!(region_at_point(&regions, [12.0, 5.0]), None);
+    }
+
+    #[test]
+    fn test_region_summary_stable_ids_and_nesting() {
+        let square = |ids: &[EntityId; 4], x0: f64, size: f64| -> Vec<SketchEntity> {
+            let c = [[x0, x0], [x0 + size, x0], [x0 + size, x0 + size], [x0, x0 + size]];
+            (0..4).map(|i| SketchEntity {
+                id: ids[i],
+                geometry: SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] },
+                is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
+            }).collect()
+        };
+        let outer_ids = [0, 1, 2, 3].map(|i| EntityId::new_deterministic(&format!("outer{}", i)));
+        let inner_ids = [0, 1, 2, 3].map(|i| EntityId::new_deterministic(&format!("inner{}", i)));
+
+        let mut entities = square(&outer_ids, 0.0, 10.0);
+        entities.extend(square(&inner_ids, 3.0, 4.0));
+        let summary = summarize_regions(&find_regions(&entities));
+        assert_eq!(summary.len(), 2);
+        let inner = summary.iter().find(|r| r.region.boundary_entity_ids.contains(&inner_ids[0].0)).unwrap();
+        let outer = summary.iter().find(|r| r.region.boundary_entity_ids.contains(&outer_ids[0].0)).unwrap();
+        assert_eq!(inner.parent.as_ref(), Some(&outer.stable_id));
+        assert!(outer.parent.is_none());
+        assert!(summary.iter().all(|r| r.orientation == RegionOrientation::CCW));
+
+        // Moving the inner square changes the geometric id but not the stable id
+        let mut moved = square(&outer_ids, 0.0, 10.0);
+        moved.extend(square(&inner_ids, 4.0, 4.0));
+        let moved_summary = summarize_regions(&find_regions(&moved));
+        let moved_inner = moved_summary.iter().find(|r| r.region.boundary_entity_ids.contains(&inner_ids[0].0)).unwrap();
+        assert_ne!(moved_inner.region.id, inner.region.id);
+        assert_eq!(moved_inner.stable_id, inner.stable_id);
+    }
+
+    #[test]
+    fn test_region_summary_disambiguates_shared_boundaries() {
+        let entities: Vec<SketchEntity> = [[0.0, 0.0], [6.0, 0.0]].iter().enumerate().map(|(i, c)| SketchEntity {
+            id: EntityId::new_deterministic(&format!("circle{}", i)),
+            geometry: SketchGeometry::Circle { center: *c, radius: 5.0 },
+            is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
+        }).collect();
+        let summary = summarize_regions(&find_regions(&entities));
+        let ids: HashSet<&String> = summary.iter().map(|r| &r.stable_id).collect();
+        assert_eq!(ids.len(), 3, "Each face of two overlapping circles gets its own stable id");
+    }
+
+    #[test]
+    fn test_match_region_keys_falls_back_to_centroid() {
+        let square = |seed: &str, x0: f64| -> Vec<SketchEntity> {
+            let c = [[x0, 0.0], [x0 + 4.0, 0.0], [x0 + 4.0, 4.0], [x0, 4.0]];
+            (0..4).map(|i| SketchEntity {
+                id: EntityId::new_deterministic(&format!("{}{}", seed, i)),
+                geometry: SketchGeometry::Line { start: c[i], end: c[(i + 1) % 4] },
+                is_construction: false,
+                creation_seq: 0,
+                name: None,
+                color: None,
+                locked: false,
+            }).collect()
+        };
+        let mut entities = square("left", 0.0);
+        entities.extend(square("right", 10.0));
+        let summary = summarize_regions(&find_regions(&entities));
+        let left = summary.iter().position(|r| r.region.centroid[0] < 5.0).unwrap();
+        let right = 1 - left;
+
+        let picks = [
+            (summary[right].stable_id.as_str(), None),
+            // Key drifted (e.g. an edge was redrawn), centroid barely moved
+            ("region_stale", Some([2.3, 1.9])),
+            // Nothing left near this centroid
+            ("region_gone", Some([30.0, 0.0])),
+            (summary[right].stable_id.as_str(), None),
+        ];
+        assert_eq!(match_region_keys(&summary, &picks), vec![Some(right), Some(left), None, None]);
+    }
+
+    fn polygon_region(corners: &[[f64; 2]]) -> SketchRegion {
+        let entities: Vec<SketchEntity> = (0..corners.len()).map(|i| SketchEntity {
+            id: EntityId::new(),
+            geometry: SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % corners.len()] },
+            is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
+        }).collect();
+        let regions = find_regions(&entities);
+        assert_eq!(regions.len(), 1);
+        regions.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_medial_axis_of_rectangle_is_spine() {
+        let region = polygon_region(&[[0.0, 0.0], [10.0, 0.0], [10.0, 4.0], [0.0, 4.0]]);
+        let axis = compute_medial_axis(&region, 0.2);
+        assert_eq!(axis.warning, None);
+
+        // Every point is equidistant from two sides: on the spine or a corner diagonal
+        for p in axis.polylines.iter().flatten() {
+            let mut clearances = [p[0], 10.0 - p[0], p[1], 4.0 - p[1]];
+            clearances.sort_by(f64::total_cmp);
+            assert!(clearances[1] - clearances[0] < 0.1, "{:?} is off the medial axis", p);
+        }
+        let spine: Vec<&[f64; 2]> = axis.polylines.iter().flatten().filter(|p| (p[1] - 2.0).abs() < 0.05).collect();
+        let (min_x, max_x) = spine.iter().fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p[0]), hi.max(p[0])));
+        assert!(min_x < 2.2 && max_x > 7.8, "Spine spans x = 2..8, got {}..{}", min_x, max_x);
+    }
+
+    #[test]
+    fn test_medial_axis_of_l_shape_has_two_branches_meeting() {
+        let region = polygon_region(&[[0.0, 0.0], [10.0, 0.0], [10.0, 4.0], [4.0, 4.0], [4.0, 10.0], [0.0, 10.0]]);
+        let axis = compute_medial_axis(&region, 0.2);
+        assert_eq!(axis.warning, None);
+
+        let near = |p: [f64; 2], q: [f64; 2]| utils_2d::distance(p, q) < 0.3;
+        let horizontal = axis.polylines.iter().flatten().any(|p| near(*p, [7.5, 2.0]));
+        let vertical = axis.polylines.iter().flatten().any(|p| near(*p, [2.0, 7.5]));
+        assert!(horizontal && vertical, "Both arms have a centerline");
+
+        // The arms meet where the reflex corner and both outer walls are equidistant
+        let junction = [4.0 * 2f64.sqrt() / (1.0 + 2f64.sqrt()); 2];
+        let ends: Vec<[f64; 2]> = axis.polylines.iter().flat_map(|p| [p[0], p[p.len() - 1]]).collect();
+        let meeting = ends.iter().filter(|e| near(**e, junction)).count();
+        assert!(meeting >= 3, "Arms and corner diagonal meet at {:?}: {:?}", junction, ends);
+    }
+
+    #[test]
+    fn test_medial_axis_of_sliver_warns_instead_of_guessing() {
+        // Sheared, so the samples on the two long walls do not line up
+        let region = polygon_region(&[[0.0, 0.0], [10.0, 0.0], [10.1, 0.01], [0.1, 0.01]]);
+        let axis = compute_medial_axis(&region, 0.25);
+        assert!(axis.polylines.is_empty());
+        assert!(axis.warning.is_some());
+
+        // Walls almost touching at a pinch
+        let region = polygon_region(&[[0.0, 0.0], [10.0, 0.0], [10.0, 4.0], [5.0, 0.05], [0.0, 4.0]]);
+        assert!(compute_medial_axis(&region, 0.5).warning.is_some());
+        assert!(compute_medial_axis(&region, f64::NAN).warning.is_some());
+    }
 }
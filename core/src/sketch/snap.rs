@@ -2,9 +2,13 @@
 //! 
 //! Provides snap-to-point detection for professional sketch usability,
 //! supporting endpoint, midpoint, center, intersection, origin, and grid snapping.
+//! `query_snaps` additionally ranks quadrant, on-entity and alignment snaps for
+//! clients that query the server on mouse move.
 
-use super::types::{Sketch, SketchGeometry};
+use super::reference::ReferenceEntity;
+use super::types::{ellipse_point, Sketch, SketchGeometry};
 use crate::geometry::intersection::line_line_intersection;
+use crate::geometry::utils_2d::{circle_circle_intersect, closest_point_on_segment, line_circle_intersect};
 use crate::topo::EntityId;
 use serde::{Deserialize, Serialize};
 
@@ -23,6 +27,14 @@ pub enum SnapType {
     Origin,
     /// Snap to grid points
     Grid,
+    /// Snap to circle/arc/ellipse quadrant points (0, 90, 180, 270 degrees)
+    Quadrant,
+    /// Snap to the nearest point on a line, circle or arc
+    OnEntity,
+    /// Snap horizontally in line with an existing point
+    Horizontal,
+    /// Snap vertically in line with an existing point
+    Vertical,
 }
 
 impl SnapType {
@@ -34,10 +46,27 @@ impl SnapType {
             SnapType::Center => 2,
             SnapType::Intersection => 3,
             SnapType::Midpoint => 4,
+            SnapType::Quadrant => 4,
             SnapType::Origin => 5,
+            SnapType::Horizontal | SnapType::Vertical => 7,
+            SnapType::OnEntity => 8,
             SnapType::Grid => 10,
         }
     }
+
+    pub const ALL: [SnapType; 10] = [
+        SnapType::Endpoint, SnapType::Midpoint, SnapType::Center, SnapType::Intersection,
+        SnapType::Origin, SnapType::Grid, SnapType::Quadrant, SnapType::OnEntity,
+        SnapType::Horizontal, SnapType::Vertical,
+    ];
+
+    /// Ranking score for a snap of this type at `distance` from the cursor.
+    /// Priority dominates, but a lower priority snap right under the cursor
+    /// beats a higher priority one at the edge of the snap radius.
+    pub fn score(&self, distance: f64, radius: f64) -> f64 {
+        let closeness = if radius > 0.0 { 1.0 - 0.5 * (distance / radius).clamp(0.0, 1.0) } else { 1.0 };
+        (20.0 - self.priority() as f64) * closeness
+    }
 }
 
 /// A detected snap point
@@ -245,6 +274,24 @@ pub fn find_snap_points(
                     }
                 }
             }
+
+            SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+                let mut points = Vec::new();
+                if config.enable_center {
+                    points.push((*center, SnapType::Center));
+                }
+                if config.enable_endpoint {
+                    for t in [*start_angle, *end_angle] {
+                        points.push((ellipse_point(*center, *semi_major, *semi_minor, *rotation, t), SnapType::Endpoint));
+                    }
+                }
+                for (position, snap_type) in points {
+                    let d = distance(cursor, position);
+                    if d <= config.snap_radius {
+                        snaps.push(SnapPoint { position, snap_type, entity_id: Some(entity.id), distance: d });
+                    }
+                }
+            }
         }
     }
 
@@ -286,7 +333,7 @@ pub fn find_snap_points(
             snaps.push(SnapPoint {
                 position: origin,
                 snap_type: SnapType::Origin,
-                entity_id: None,
+                entity_id: Some(ReferenceEntity::Origin.id()),
                 distance: d,
             });
         }
@@ -337,6 +384,342 @@ pub fn snap_cursor(
     snaps.into_iter().next()
 }
 
+/// Maximum number of entities inspected by a single `query_snaps` call
+pub const MAX_QUERY_ENTITIES: usize = 4096;
+/// Maximum number of candidates returned by `query_snaps`
+pub const MAX_QUERY_CANDIDATES: usize = 16;
+
+/// A ranked snap candidate returned by `query_snaps`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapCandidate {
+    pub position: [f64; 2],
+    pub snap_type: SnapType,
+    /// Entities the snap comes from (two for intersections, none for origin/grid)
+    pub entity_ids: Vec<EntityId>,
+    /// Defining point on the entity: 0 = start, 1 = end for endpoints; 0..4 for quadrants
+    pub point_index: Option<usize>,
+    /// Distance from cursor
+    pub distance: f64,
+    /// Ranking score, higher is better
+    pub score: f64,
+    /// Alignment guide line (from the aligned point to the snap position)
+    pub guide: Option<[[f64; 2]; 2]>,
+}
+
+/// Parameters of a snap query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapQuery {
+    pub cursor: [f64; 2],
+    /// Snap types to consider; empty means all
+    #[serde(default)]
+    pub types: Vec<SnapType>,
+    pub radius: f64,
+    #[serde(default = "default_grid_spacing")]
+    pub grid_spacing: f64,
+}
+
+fn default_grid_spacing() -> f64 {
+    SnapConfig::default().grid_spacing
+}
+
+impl SnapQuery {
+    fn wants(&self, snap_type: SnapType) -> bool {
+        self.types.is_empty() || self.types.contains(&snap_type)
+    }
+}
+
+/// Collects candidates within the query radius
+struct Candidates<'a> {
+    query: &'a SnapQuery,
+    list: Vec<SnapCandidate>,
+}
+
+impl Candidates<'_> {
+    fn offer(&mut self, position: [f64; 2], snap_type: SnapType, entity_ids: &[EntityId], point_index: Option<usize>) {
+        if !self.query.wants(snap_type) {
+            return;
+        }
+        let d = distance(self.query.cursor, position);
+        if d > self.query.radius {
+            return;
+        }
+        // Coincident snaps of the same kind (e.g. shared endpoints) merge their sources
+        if let Some(existing) = self.list.iter_mut()
+            .find(|c| c.snap_type == snap_type && distance(c.position, position) < 1e-9)
+        {
+            for id in entity_ids {
+                if !existing.entity_ids.contains(id) {
+                    existing.entity_ids.push(*id);
+                }
+            }
+            return;
+        }
+        self.list.push(SnapCandidate {
+            position,
+            snap_type,
+            entity_ids: entity_ids.to_vec(),
+            point_index,
+            distance: d,
+            score: snap_type.score(d, self.query.radius),
+            guide: None,
+        });
+    }
+}
+
+/// Whether `angle` lies on the CCW sweep from `start` to `end`
+fn angle_on_arc(angle: f64, start: f64, end: f64) -> bool {
+    let tau = std::f64::consts::TAU;
+    (angle - start).rem_euclid(tau) <= (end - start).rem_euclid(tau) + 1e-9
+}
+
+/// Axis-aligned bounds of an entity
+pub(crate) fn entity_bounds(geometry: &SketchGeometry) -> ([f64; 2], [f64; 2]) {
+    match geometry {
+        SketchGeometry::Line { start, end } => (
+            [start[0].min(end[0]), start[1].min(end[1])],
+            [start[0].max(end[0]), start[1].max(end[1])],
+        ),
+        SketchGeometry::Circle { center, radius } | SketchGeometry::Arc { center, radius, .. } => (
+            [center[0] - radius, center[1] - radius],
+            [center[0] + radius, center[1] + radius],
+        ),
+        SketchGeometry::Ellipse { center, semi_major, .. } | SketchGeometry::EllipseArc { center, semi_major, .. } => (
+            [center[0] - semi_major, center[1] - semi_major],
+            [center[0] + semi_major, center[1] + semi_major],
+        ),
+        SketchGeometry::Point { pos } => (*pos, *pos),
+    }
+}
+
+/// Center, radius and (for arcs) start/end angles
+type CircleSpan = ([f64; 2], f64, Option<(f64, f64)>);
+
+/// Circle carrying an entity, with the arc sweep if it is an arc
+fn as_circle(geometry: &SketchGeometry) -> Option<CircleSpan> {
+    match geometry {
+        SketchGeometry::Circle { center, radius } => Some((*center, *radius, None)),
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => Some((*center, *radius, Some((*start_angle, *end_angle)))),
+        _ => None,
+    }
+}
+
+fn on_circle(point: [f64; 2], center: [f64; 2], sweep: Option<(f64, f64)>) -> bool {
+    sweep.is_none_or(|(start, end)| angle_on_arc((point[1] - center[1]).atan2(point[0] - center[0]), start, end))
+}
+
+/// Points other snaps can align with: endpoints, centers and sketch points
+fn defining_points(geometry: &SketchGeometry, out: &mut Vec<[f64; 2]>) {
+    match geometry {
+        SketchGeometry::Line { start, end } => out.extend([*start, *end]),
+        SketchGeometry::Circle { center, .. } | SketchGeometry::Ellipse { center, .. } => out.push(*center),
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => out.extend([
+            *center,
+            [center[0] + radius * start_angle.cos(), center[1] + radius * start_angle.sin()],
+            [center[0] + radius * end_angle.cos(), center[1] + radius * end_angle.sin()],
+        ]),
+        SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => out.extend([
+            *center,
+            ellipse_point(*center, *semi_major, *semi_minor, *rotation, *start_angle),
+            ellipse_point(*center, *semi_major, *semi_minor, *rotation, *end_angle),
+        ]),
+        SketchGeometry::Point { pos } => out.push(*pos),
+    }
+}
+
+/// Rank every snap candidate near the cursor, best first.
+///
+/// Entities whose bounds (grown by the radius) miss the cursor are skipped
+/// before any per-type work, and intersections are only computed between
+/// entities that pass this test, so the cost of a query scales with what is
+/// under the cursor. At most `MAX_QUERY_ENTITIES` entities are inspected.
+/// Ellipses offer center and quadrant snaps only, elliptical arcs center and endpoints.
+pub fn query_snaps(sketch: &Sketch, query: &SnapQuery) -> Vec<SnapCandidate> {
+    let cursor = query.cursor;
+    let r = query.radius;
+    let mut candidates = Candidates { query, list: Vec::new() };
+    let mut nearby: Vec<(EntityId, &SketchGeometry)> = Vec::new();
+    let mut align_points: Vec<[f64; 2]> = Vec::new();
+
+    let entities = sketch.entities.iter()
+        .filter(|e| !e.id.to_string().starts_with("preview_"))
+        .take(MAX_QUERY_ENTITIES);
+    for entity in entities {
+        let geometry = &entity.geometry;
+        let ids = [entity.id];
+        defining_points(geometry, &mut align_points);
+
+        let (min, max) = entity_bounds(geometry);
+        if cursor[0] < min[0] - r || cursor[0] > max[0] + r || cursor[1] < min[1] - r || cursor[1] > max[1] + r {
+            continue;
+        }
+        nearby.push((entity.id, geometry));
+
+        match geometry {
+            SketchGeometry::Line { start, end } => {
+                candidates.offer(*start, SnapType::Endpoint, &ids, Some(0));
+                candidates.offer(*end, SnapType::Endpoint, &ids, Some(1));
+                candidates.offer([(start[0] + end[0]) / 2.0, (start[1] + end[1]) / 2.0], SnapType::Midpoint, &ids, None);
+                candidates.offer(closest_point_on_segment(*start, *end, cursor), SnapType::OnEntity, &ids, None);
+            }
+            SketchGeometry::Circle { .. } | SketchGeometry::Arc { .. } => {
+                let Some((center, radius, sweep)) = as_circle(geometry) else { continue };
+                candidates.offer(center, SnapType::Center, &ids, None);
+                if let Some((start, end)) = sweep {
+                    candidates.offer([center[0] + radius * start.cos(), center[1] + radius * start.sin()], SnapType::Endpoint, &ids, Some(0));
+                    candidates.offer([center[0] + radius * end.cos(), center[1] + radius * end.sin()], SnapType::Endpoint, &ids, Some(1));
+                }
+                for i in 0..4 {
+                    let angle = i as f64 * std::f64::consts::FRAC_PI_2;
+                    if sweep.is_none_or(|(start, end)| angle_on_arc(angle, start, end)) {
+                        candidates.offer([center[0] + radius * angle.cos(), center[1] + radius * angle.sin()], SnapType::Quadrant, &ids, Some(i));
+                    }
+                }
+                let to_cursor = [cursor[0] - center[0], cursor[1] - center[1]];
+                let len = (to_cursor[0] * to_cursor[0] + to_cursor[1] * to_cursor[1]).sqrt();
+                if len > 1e-12 {
+                    let nearest = [center[0] + radius * to_cursor[0] / len, center[1] + radius * to_cursor[1] / len];
+                    if on_circle(nearest, center, sweep) {
+                        candidates.offer(nearest, SnapType::OnEntity, &ids, None);
+                    }
+                }
+            }
+            SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => {
+                candidates.offer(*center, SnapType::Center, &ids, None);
+                let (sin, cos) = rotation.sin_cos();
+                let axes = [[semi_major * cos, semi_major * sin], [-semi_minor * sin, semi_minor * cos]];
+                for i in 0..4 {
+                    let sign = if i < 2 { 1.0 } else { -1.0 };
+                    let axis = axes[i % 2];
+                    candidates.offer([center[0] + sign * axis[0], center[1] + sign * axis[1]], SnapType::Quadrant, &ids, Some(i));
+                }
+            }
+            SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+                candidates.offer(*center, SnapType::Center, &ids, None);
+                candidates.offer(ellipse_point(*center, *semi_major, *semi_minor, *rotation, *start_angle), SnapType::Endpoint, &ids, Some(0));
+                candidates.offer(ellipse_point(*center, *semi_major, *semi_minor, *rotation, *end_angle), SnapType::Endpoint, &ids, Some(1));
+            }
+            SketchGeometry::Point { pos } => {
+                candidates.offer(*pos, SnapType::Endpoint, &ids, None);
+            }
+        }
+    }
+
+    if query.wants(SnapType::Intersection) {
+        for (i, (id_a, a)) in nearby.iter().enumerate() {
+            for (id_b, b) in &nearby[i + 1..] {
+                let ids = [*id_a, *id_b];
+                let points = match (a, b) {
+                    (SketchGeometry::Line { start: s1, end: e1 }, SketchGeometry::Line { start: s2, end: e2 }) => {
+                        line_line_intersection(*s1, *e1, *s2, *e2).into_iter().collect()
+                    }
+                    (SketchGeometry::Line { start, end }, other) | (other, SketchGeometry::Line { start, end }) => {
+                        match as_circle(other) {
+                            Some((center, radius, sweep)) => line_circle_intersect(*start, *end, center, radius)
+                                .into_iter()
+                                .filter(|p| on_circle(*p, center, sweep))
+                                .collect(),
+                            None => Vec::new(),
+                        }
+                    }
+                    _ => match (as_circle(a), as_circle(b)) {
+                        (Some((c1, r1, sweep1)), Some((c2, r2, sweep2))) => circle_circle_intersect(c1, r1, c2, r2)
+                            .into_iter()
+                            .filter(|p| on_circle(*p, c1, sweep1) && on_circle(*p, c2, sweep2))
+                            .collect(),
+                        _ => Vec::new(),
+                    },
+                };
+                for p in points {
+                    candidates.offer(p, SnapType::Intersection, &ids, None);
+                }
+            }
+        }
+    }
+
+    candidates.offer([0.0, 0.0], SnapType::Origin, &[ReferenceEntity::Origin.id()], Some(0));
+    candidates.offer([cursor[0], 0.0], SnapType::OnEntity, &[ReferenceEntity::XAxis.id()], None);
+    candidates.offer([0.0, cursor[1]], SnapType::OnEntity, &[ReferenceEntity::YAxis.id()], None);
+
+    if query.grid_spacing > 0.0 {
+        let g = query.grid_spacing;
+        candidates.offer([(cursor[0] / g).round() * g, (cursor[1] / g).round() * g], SnapType::Grid, &[], None);
+    }
+
+    // Alignment with the closest existing point in each direction. Points already
+    // under the cursor are endpoint snaps, not inference lines.
+    for (snap_type, axis) in [(SnapType::Horizontal, 1), (SnapType::Vertical, 0)] {
+        if !query.wants(snap_type) {
+            continue;
+        }
+        let best = align_points.iter()
+            .filter(|p| distance(**p, cursor) > r)
+            .map(|p| (p, (p[axis] - cursor[axis]).abs()))
+            .filter(|(_, offset)| *offset <= r)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((point, offset)) = best {
+            let mut position = cursor;
+            position[axis] = point[axis];
+            candidates.list.push(SnapCandidate {
+                position,
+                snap_type,
+                entity_ids: Vec::new(),
+                point_index: None,
+                distance: offset,
+                score: snap_type.score(offset, r),
+                guide: Some([*point, position]),
+            });
+        }
+    }
+
+    let mut list = candidates.list;
+    list.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.distance.total_cmp(&b.distance)));
+    list.truncate(MAX_QUERY_CANDIDATES);
+    list
+}
+
+/// Snap types a probe can report: those tied to a single entity
+const PROBE_TYPES: [SnapType; 4] = [SnapType::Endpoint, SnapType::Midpoint, SnapType::Center, SnapType::OnEntity];
+
+/// Nearest snappable entity feature under a probed point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SketchProbe {
+    pub entity_id: EntityId,
+    /// "Line", "Circle", "Arc", "Point", "Ellipse" or "EllipseArc"
+    pub entity_type: String,
+    /// Snapped coordinate
+    pub position: [f64; 2],
+    pub snap_type: SnapType,
+    /// Distance from the probed point to `position`
+    pub distance: f64,
+}
+
+/// Snap `point` to the best endpoint, midpoint, center or on-curve position
+/// within `radius`, ranked as `query_snaps` ranks them. None if nothing is in reach.
+pub fn probe_point(sketch: &Sketch, point: [f64; 2], radius: f64) -> Option<SketchProbe> {
+    let query = SnapQuery { cursor: point, types: PROBE_TYPES.to_vec(), radius, grid_spacing: 0.0 };
+    // Sketch entities only; the origin axes aren't probed
+    let (best, entity) = query_snaps(sketch, &query).into_iter().find_map(|candidate| {
+        let entity = candidate.entity_ids.iter().find_map(|id| sketch.entities.iter().find(|e| e.id == *id))?;
+        Some((candidate, entity))
+    })?;
+    let entity_id = entity.id;
+    let entity_type = match entity.geometry {
+        SketchGeometry::Line { .. } => "Line",
+        SketchGeometry::Circle { .. } => "Circle",
+        SketchGeometry::Arc { .. } => "Arc",
+        SketchGeometry::Point { .. } => "Point",
+        SketchGeometry::Ellipse { .. } => "Ellipse",
+        SketchGeometry::EllipseArc { .. } => "EllipseArc",
+    };
+    Some(SketchProbe {
+        entity_id,
+        entity_type: entity_type.to_string(),
+        position: best.position,
+        snap_type: best.snap_type,
+        distance: best.distance,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,6 +736,10 @@ mod tests {
                 end: [10.0, 0.0],
             },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         });
 
         // Add a circle at (5, 5) with radius 2
@@ -363,6 +750,10 @@ mod tests {
                 radius: 2.0,
             },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         });
 
         // Add a second line from (10, 0) to (10, 10) 
@@ -373,6 +764,10 @@ mod tests {
                 end: [10.0, 10.0],
             },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         });
 
         sketch
@@ -432,6 +827,10 @@ mod tests {
                 end: [10.0, 10.0],
             },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         });
         sketch.entities.push(SketchEntity {
             id: EntityId::new_deterministic("line_y"),
@@ -440,6 +839,10 @@ mod tests {
                 end: [10.0, 0.0],
             },
             is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
         });
 
         let config = SnapConfig::default();
@@ -523,4 +926,83 @@ mod tests {
         let result = snap_cursor([100.0, 100.0], &sketch, &config);
         assert!(result.is_none());
     }
+
+    #[test]
+    fn test_query_ranks_endpoint_over_nearby_midpoint() {
+        // Short line: its midpoint (0.5, 0) and endpoint (1, 0) are both within reach
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let id = EntityId::new_deterministic("short_line");
+        sketch.entities.push(SketchEntity {
+            id,
+            geometry: SketchGeometry::Line { start: [0.0, 0.0], end: [1.0, 0.0] },
+            is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
+        });
+        let query = |cursor| SnapQuery {
+            cursor,
+            types: vec![SnapType::Endpoint, SnapType::Midpoint, SnapType::OnEntity],
+            radius: 0.6,
+            grid_spacing: 1.0,
+        };
+
+        // Roughly equidistant: the endpoint wins on priority
+        let ranked = query_snaps(&sketch, &query([0.76, 0.05]));
+        let types: Vec<_> = ranked.iter().map(|c| c.snap_type).collect();
+        assert_eq!(types, vec![SnapType::Endpoint, SnapType::Midpoint, SnapType::OnEntity]);
+        assert_eq!(ranked[0].position, [1.0, 0.0]);
+        assert_eq!(ranked[0].entity_ids, vec![id]);
+        assert_eq!(ranked[0].point_index, Some(1));
+
+        // Right on the midpoint: it beats the endpoint at the edge of the radius
+        let ranked = query_snaps(&sketch, &query([0.5, 0.0]));
+        let types: Vec<_> = ranked.iter().map(|c| c.snap_type).collect();
+        assert_eq!(types, vec![SnapType::Midpoint, SnapType::OnEntity, SnapType::Endpoint, SnapType::Endpoint, SnapType::OnEntity]);
+        assert!(ranked.windows(2).all(|w| w[0].score >= w[1].score));
+        // The line lies on the X axis; the Y axis passes within reach at the origin
+        assert_eq!(ranked[1].entity_ids, vec![id, ReferenceEntity::XAxis.id()]);
+        assert_eq!(ranked[4].entity_ids, vec![ReferenceEntity::YAxis.id()]);
+    }
+
+    #[test]
+    fn test_query_alignment_guides_and_quadrants() {
+        let sketch = create_test_sketch();
+        let query = SnapQuery {
+            cursor: [3.05, 5.1],
+            types: vec![SnapType::Quadrant, SnapType::Horizontal, SnapType::Vertical],
+            radius: 0.2,
+            grid_spacing: 1.0,
+        };
+
+        // Left quadrant of the circle at (5, 5) r = 2
+        let ranked = query_snaps(&sketch, &query);
+        assert_eq!(ranked[0].snap_type, SnapType::Quadrant);
+        assert_eq!(ranked[0].position, [3.0, 5.0]);
+        assert_eq!(ranked[0].point_index, Some(2));
+
+        // Line endpoint (10, 10) aligns horizontally with a cursor at y = 9.9
+        let query = SnapQuery { cursor: [4.0, 9.9], ..query };
+        let ranked = query_snaps(&sketch, &query);
+        let horizontal = ranked.iter().find(|c| c.snap_type == SnapType::Horizontal).expect("horizontal inference");
+        assert_eq!(horizontal.position, [4.0, 10.0]);
+        assert_eq!(horizontal.guide, Some([[10.0, 10.0], [4.0, 10.0]]));
+    }
+
+    #[test]
+    fn test_probe_near_line_midpoint() {
+        let sketch = create_test_sketch();
+        let probe = probe_point(&sketch, [5.1, 0.2], SnapConfig::default().snap_radius).unwrap();
+        assert_eq!(probe.snap_type, SnapType::Midpoint);
+        assert_eq!(probe.entity_id, EntityId::new_deterministic("line1"));
+        assert_eq!(probe.entity_type, "Line");
+        assert!((probe.position[0] - 5.0).abs() < 1e-9 && probe.position[1].abs() < 1e-9);
+
+        // Away from any defining point the probe lands on the curve itself
+        let probe = probe_point(&sketch, [2.0, 0.3], SnapConfig::default().snap_radius).unwrap();
+        assert_eq!(probe.snap_type, SnapType::OnEntity);
+        assert!((probe.position[0] - 2.0).abs() < 1e-9 && probe.position[1].abs() < 1e-9);
+        assert!(probe_point(&sketch, [20.0, 20.0], 0.5).is_none());
+    }
 }
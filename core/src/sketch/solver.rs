@@ -1,4 +1,8 @@
-use super::types::{Sketch, SketchConstraint, SketchGeometry, ConstraintPoint};
+use super::types::{ellipse_angle, ellipse_point, Sketch, SketchAddition, SketchConstraint, SketchGeometry, ConstraintPoint};
+use super::reference::ReferenceEntity;
+use super::measurement::{measure_sketch, SketchMeasurements};
+use super::dimensions::{layout_dimensions, DimensionGraphics};
+use crate::units::LengthUnit;
 #[allow(unused_imports)]
 use crate::topo::EntityId;
 use std::collections::HashMap;
@@ -11,7 +15,8 @@ pub struct SolveResult {
     pub converged: bool,
     /// Number of iterations performed
     pub iterations: usize,
-    /// Final maximum error across all constraints
+    /// Final maximum normalized error across all constraints
+    /// (angular errors as-is, linear errors divided by the sketch size)
     pub max_error: f64,
     /// Number of geometry entities in the sketch
     pub entity_count: usize,
@@ -28,6 +33,28 @@ pub struct SolveResult {
     pub conflicts: Option<ConflictInfo>,
     /// Per-entity constraint status for visual DOF indicators
     pub entity_statuses: Vec<EntityConstraintStatus>,
+    /// Constraints on projected geometry whose model reference no longer
+    /// resolves; they hold against the last projected position
+    #[serde(default)]
+    pub stale_external_constraints: Vec<usize>,
+    /// The redundancy/conflict analysis was deferred (`SketchSolver::solve_deferred`):
+    /// `redundant_constraints` is empty and `conflicts` is None until a
+    /// `SketchAnalysis` is applied
+    #[serde(default)]
+    pub analysis_pending: bool,
+    /// How each constraint group fared, in order of first appearance
+    #[serde(default)]
+    pub groups: Vec<ConstraintGroupStatus>,
+    /// Curve lengths after solving, in mm until `measure` picks a unit and highlights
+    #[serde(default)]
+    pub measurements: SketchMeasurements,
+    /// Drawing geometry of the styled dimensions, with values shown in the
+    /// same unit as `measurements`
+    #[serde(default)]
+    pub dimension_graphics: Vec<DimensionGraphics>,
+    /// Entities and constraints in the order they were added, for a history view
+    #[serde(default)]
+    pub additions: Vec<SketchAddition>,
 }
 
 impl SolveResult {
@@ -45,6 +72,42 @@ impl SolveResult {
     pub fn is_over_constrained(&self) -> bool {
         self.dof < 0 || !self.converged
     }
+
+    /// Re-measure `sketch` in `unit`, totalling the `highlight`ed entities
+    pub fn measure(&mut self, sketch: &Sketch, highlight: &[EntityId], unit: LengthUnit) {
+        self.measurements = measure_sketch(sketch, highlight, unit);
+        self.dimension_graphics = layout_dimensions(sketch, unit);
+    }
+
+    /// Fill in the results of a deferred analysis
+    pub fn apply_analysis(&mut self, analysis: SketchAnalysis) {
+        self.redundant_constraints = analysis.redundant_constraints;
+        self.conflicts = analysis.conflicts;
+        self.entity_statuses = analysis.entity_statuses;
+        self.analysis_pending = false;
+    }
+
+    /// Record the constraints that involve any of the `stale` projected entities
+    pub fn flag_stale_external(&mut self, sketch: &Sketch, stale: &std::collections::HashSet<EntityId>) {
+        self.stale_external_constraints = sketch.constraints.iter().enumerate()
+            .filter(|(_, entry)| SketchSolver::get_constraint_entities(&entry.constraint).iter().any(|id| stale.contains(id)))
+            .map(|(i, _)| i)
+            .collect();
+    }
+}
+
+/// Solve outcome for the members of one constraint group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConstraintGroupStatus {
+    pub name: String,
+    /// Number of constraints in the group
+    pub constraints: usize,
+    /// Members that are suppressed
+    pub suppressed: usize,
+    /// Active members that hold after the solve
+    pub satisfied: usize,
+    /// Active members the solve could not satisfy
+    pub conflicting: usize,
 }
 
 /// Information about a redundant constraint detected during solving
@@ -58,6 +121,42 @@ pub struct RedundantConstraintInfo {
     pub reason: String,
 }
 
+/// Redundant and conflicting constraints of a solved sketch. Only feeds UI
+/// badges, so it can be computed after the solve; see `SketchSolver::analyze`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SketchAnalysis {
+    pub redundant_constraints: Vec<RedundantConstraintInfo>,
+    pub conflicts: Option<ConflictInfo>,
+    /// Entity statuses with `involved_in_conflict` filled in
+    pub entity_statuses: Vec<EntityConstraintStatus>,
+}
+
+impl SketchAnalysis {
+    /// Stand-in until the analysis has run: no findings, statuses without conflicts
+    fn pending(sketch: &Sketch) -> Self {
+        SketchAnalysis {
+            redundant_constraints: Vec::new(),
+            conflicts: None,
+            entity_statuses: SketchSolver::calculate_entity_statuses(sketch, &None),
+        }
+    }
+}
+
+/// Cancels a `SketchSolver::analyze_cancellable` running on another thread.
+/// Clones share the flag.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisCancel(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl AnalysisCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
 /// Information about constraint conflicts when solver fails to converge
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConflictInfo {
@@ -78,7 +177,10 @@ pub struct EntityConstraintStatus {
     pub total_dof: i32,
     /// DOF consumed by constraints affecting this entity
     pub constrained_dof: i32,
-    /// Remaining DOF (total_dof - constrained_dof, clamped to >= 0)
+    /// DOF held by the implicit fixes of a locked entity (all of them, or 0)
+    #[serde(default)]
+    pub locked_dof: i32,
+    /// Remaining DOF (total_dof - constrained_dof - locked_dof, clamped to >= 0)
     pub remaining_dof: i32,
     /// True if all entity DOF are consumed by constraints
     pub is_fully_constrained: bool,
@@ -122,6 +224,58 @@ pub struct RelaxedSolveResult {
     pub final_total_error: f64,
 }
 
+/// Entity/constraint relationship of a sketch, for diagnosing solver behaviour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintGraph {
+    pub nodes: Vec<ConstraintGraphNode>,
+    /// One edge per constraint, in constraint order. Unary constraints (Horizontal,
+    /// Fix, ...) have a single entity; Symmetric connects three.
+    pub edges: Vec<ConstraintGraphEdge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintGraphNode {
+    pub id: EntityId,
+    /// Geometry type: "Line", "Circle", "Arc", "Point", "Ellipse" or "EllipseArc"
+    pub kind: String,
+    pub is_construction: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintGraphEdge {
+    /// Index of the constraint in the constraints vector
+    pub constraint_index: usize,
+    /// Constraint type, e.g. "Coincident"
+    pub kind: String,
+    /// Distinct entities the constraint references
+    pub entities: Vec<EntityId>,
+    pub suppressed: bool,
+}
+
+/// Tuning of the relaxation solver
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SolverConfig {
+    /// Passes over the constraints before giving up
+    pub max_iterations: usize,
+    /// Allowed linear error, in mm
+    pub epsilon: f64,
+    /// Fraction of its angle error a Parallel or Perpendicular constraint
+    /// turns away per pass, split between its two lines. Values below 1 damp
+    /// the turn for coupled systems that still overshoot.
+    pub rotation_damping: f64,
+}
+
+impl Default for SolverConfig {
+    /// No rotation damping: with each Perpendicular turn split between its
+    /// lines, damping only slows convergence. Over perturbed rectangles and
+    /// consistent random Parallel/Perpendicular networks, every factor below 1
+    /// took more passes and converged fewer sketches within 100 iterations,
+    /// 0.7 failing most often.
+    fn default() -> Self {
+        Self { max_iterations: 100, epsilon: 1e-6, rotation_damping: 1.0 }
+    }
+}
+
 pub struct SketchSolver;
 
 impl SketchSolver {
@@ -132,8 +286,30 @@ impl SketchSolver {
 
     /// Extended solve that returns detailed status including DOF
     pub fn solve_with_result(sketch: &mut Sketch) -> SolveResult {
-        let max_iterations = 100;
-        let epsilon = 1e-6;
+        Self::solve_inner(sketch, false, &SolverConfig::default())
+    }
+
+    /// `solve_with_result` with non-default solver tuning
+    pub fn solve_with_config(sketch: &mut Sketch, config: &SolverConfig) -> SolveResult {
+        Self::solve_inner(sketch, false, config)
+    }
+
+    /// Solve without the redundancy/conflict analysis, which only feeds UI
+    /// badges and is slow on large sketches. The result has `analysis_pending`
+    /// set; run `analyze` on the solved sketch for the rest.
+    pub fn solve_deferred(sketch: &mut Sketch) -> SolveResult {
+        Self::solve_deferred_with_config(sketch, &SolverConfig::default())
+    }
+
+    /// `solve_deferred` with non-default solver tuning
+    pub fn solve_deferred_with_config(sketch: &mut Sketch, config: &SolverConfig) -> SolveResult {
+        Self::solve_inner(sketch, true, config)
+    }
+
+    fn solve_inner(sketch: &mut Sketch, defer_analysis: bool, config: &SolverConfig) -> SolveResult {
+        let max_iterations = config.max_iterations;
+        let epsilon = config.epsilon;
+        let damping = config.rotation_damping;
         let mut converged = false;
         let mut final_max_error = 0.0;
         let mut iterations_used = 0;
@@ -144,18 +320,29 @@ impl SketchSolver {
             id_map.insert(entity.id, i);
         }
 
+        // Linear errors are normalized by the sketch size so they can be compared with
+        // angular errors; the tolerance keeps `epsilon` as the allowed linear error in mm.
+        let scale = Self::characteristic_size(sketch);
+        let tolerance = epsilon / scale;
+
+        // Clone constraints to avoid borrowing issues while mutating entities,
+        // skipping suppressed ones and putting the rest in application order
+        let active: Vec<&SketchConstraint> = sketch.active_constraints().collect();
+        let constraints: Vec<SketchConstraint> = Self::application_order(sketch, &id_map, &active, scale)
+            .into_iter()
+            .map(|i| active[i].clone())
+            .collect();
+        let projected = Self::fixed_geometry(sketch);
+
         for iteration in 0..max_iterations {
             iterations_used = iteration + 1;
             let mut max_error = 0.0;
 
-            // Clone constraints to avoid borrowing issues while mutating entities
-            // Filter out suppressed constraints
-            let constraints: Vec<_> = sketch.constraints.iter()
-                .filter(|entry| !entry.suppressed)
-                .map(|entry| entry.constraint.clone())
-                .collect();
-
             for constraint in &constraints {
+                // Measure this constraint in isolation so its error can be normalized
+                let prev_max_error = max_error;
+                max_error = 0.0;
+
                 match constraint {
                     SketchConstraint::Coincident { points } => {
                         let p1 = Self::get_point(sketch, &id_map, points[0]);
@@ -165,9 +352,10 @@ impl SketchSolver {
                             let dist_sq = (pos1[0] - pos2[0]).powi(2) + (pos1[1] - pos2[1]).powi(2);
                             if dist_sq > max_error { max_error = dist_sq; }
 
-                            let mid = [(pos1[0] + pos2[0]) * 0.5, (pos1[1] + pos2[1]) * 0.5];
-                            Self::set_point(sketch, &id_map, points[0], mid);
-                            Self::set_point(sketch, &id_map, points[1], mid);
+                            let (s1, s2) = Self::correction_shares(sketch, points[0].id, points[1].id);
+                            let d = [pos2[0] - pos1[0], pos2[1] - pos1[1]];
+                            Self::set_point(sketch, &id_map, points[0], [pos1[0] + d[0] * s1, pos1[1] + d[1] * s1]);
+                            Self::set_point(sketch, &id_map, points[1], [pos2[0] - d[0] * s2, pos2[1] - d[1] * s2]);
                         }
                     },
                     SketchConstraint::Horizontal { entity } => {
@@ -232,12 +420,13 @@ impl SketchSolver {
                             if error > max_error { max_error = error; }
 
                             if current_dist > epsilon {
-                                let scale = 0.5 * (1.0 - value / current_dist);
+                                let scale = 1.0 - value / current_dist;
                                 let offset_x = dx * scale;
                                 let offset_y = dy * scale;
 
-                                let new_p1 = [pos1[0] + offset_x, pos1[1] + offset_y];
-                                let new_p2 = [pos2[0] - offset_x, pos2[1] - offset_y];
+                                let (s1, s2) = Self::correction_shares(sketch, points[0].id, points[1].id);
+                                let new_p1 = [pos1[0] + offset_x * s1, pos1[1] + offset_y * s1];
+                                let new_p2 = [pos2[0] - offset_x * s2, pos2[1] - offset_y * s2];
 
                                 Self::set_point(sketch, &id_map, points[0], new_p1);
                                 Self::set_point(sketch, &id_map, points[1], new_p2);
@@ -335,22 +524,24 @@ impl SketchSolver {
                                 
                                 if error_par > max_error { max_error = error_par; }
                                 
-                                if error_par > epsilon {
+                                if error_par > tolerance {
                                     // Align both to average direction
                                     // Need to align signs first. Dot product tells us if they are opposed.
                                     let dot = n1[0]*n2[0] + n1[1]*n2[1];
                                     let sign = if dot > 0.0 { 1.0 } else { -1.0 };
                                     
-                                    let avg_x = n1[0] + n2[0] * sign;
-                                    let avg_y = n1[1] + n2[1] * sign;
+                                    // A fixed line keeps its direction; the other turns to it
+                                    let (s1, s2) = Self::correction_shares(sketch, lines[0], lines[1]);
+                                    let avg_x = n1[0] * s2 + n2[0] * sign * s1;
+                                    let avg_y = n1[1] * s2 + n2[1] * sign * s1;
                                     let avg_len = (avg_x*avg_x + avg_y*avg_y).sqrt();
                                     
                                     if avg_len > epsilon {
                                         let target_nx = avg_x / avg_len;
                                         let target_ny = avg_y / avg_len;
                                         
-                                        Self::rotate_line_to_dir(sketch, &id_map, lines[0], [target_nx, target_ny]);
-                                        Self::rotate_line_to_dir(sketch, &id_map, lines[1], [target_nx * sign, target_ny * sign]);
+                                        Self::rotate_line_to_dir(sketch, &id_map, lines[0], damped_direction(n1, [target_nx, target_ny], damping));
+                                        Self::rotate_line_to_dir(sketch, &id_map, lines[1], damped_direction(n2, [target_nx * sign, target_ny * sign], damping));
                                     }
                                 }
                             }
@@ -371,7 +562,9 @@ impl SketchSolver {
                                
                                if dot.abs() > max_error { max_error = dot.abs(); }
 
-                               if dot.abs() > epsilon {
+                               if dot.abs() > tolerance {
+                                   // Each line takes its share of the turn; both taking all of it overshoots
+                                   let (s1, s2) = Self::correction_shares(sketch, lines[0], lines[1]);
                                    // Rotate L2 to be perp to L1? Or rotate both?
                                    // Let's rotate L2 to be -90 deg from L1's current dir, mixed with L2's current dir?
                                    // Better: Average the deviations.
@@ -384,7 +577,7 @@ impl SketchSolver {
                                    let n2_new_y = n2[1] - dot * n1[1];
                                    let n2_len = (n2_new_x*n2_new_x + n2_new_y*n2_new_y).sqrt();
                                    if n2_len > epsilon {
-                                       Self::rotate_line_to_dir(sketch, &id_map, lines[1], [n2_new_x/n2_len, n2_new_y/n2_len]);
+                                       Self::rotate_line_to_dir(sketch, &id_map, lines[1], damped_direction(n2, [n2_new_x/n2_len, n2_new_y/n2_len], damping * s2));
                                    }
                                    
                                    // Balancing: Rotate L1 too?
@@ -392,7 +585,7 @@ impl SketchSolver {
                                    let n1_new_y = n1[1] - dot * n2[1];
                                    let n1_len = (n1_new_x*n1_new_x + n1_new_y*n1_new_y).sqrt();
                                    if n1_len > epsilon {
-                                       Self::rotate_line_to_dir(sketch, &id_map, lines[0], [n1_new_x/n1_len, n1_new_y/n1_len]);
+                                       Self::rotate_line_to_dir(sketch, &id_map, lines[0], damped_direction(n1, [n1_new_x/n1_len, n1_new_y/n1_len], damping * s1));
                                    }
                                }
                            }
@@ -428,6 +621,20 @@ impl SketchSolver {
                             },
                             _ => {}
                         }
+                    },
+                    SketchConstraint::EqualGroup { entities } => {
+                        if let Some(sizes) = Self::equal_group_sizes(sketch, &id_map, entities) {
+                            let average = sizes.iter().sum::<f64>() / sizes.len() as f64;
+                            let error = sizes.iter().map(|size| (size - average).abs()).fold(0.0, f64::max);
+                            if error > max_error { max_error = error; }
+
+                            if error > epsilon {
+                                for id in entities {
+                                    Self::set_line_length(sketch, &id_map, *id, average);
+                                    Self::set_circle_radius(sketch, &id_map, *id, average);
+                                }
+                            }
+                        }
                     },
                      SketchConstraint::Tangent { entities } => {
                         // Implement Line-Circle tangent ONLY for now
@@ -456,38 +663,11 @@ impl SketchSolver {
                             }
                         }
                     },
-                    SketchConstraint::Angle { lines, value, .. } => {
-                        // Solve angle constraint between two lines
-                        let l1_vec = Self::get_line_vector(sketch, &id_map, lines[0]);
-                        let l2_vec = Self::get_line_vector(sketch, &id_map, lines[1]);
-                        
-                        if let (Some(v1), Some(v2)) = (l1_vec, l2_vec) {
-                            let len1 = (v1[0]*v1[0] + v1[1]*v1[1]).sqrt();
-                            let len2 = (v2[0]*v2[0] + v2[1]*v2[1]).sqrt();
-                            
-                            if len1 > epsilon && len2 > epsilon {
-                                let n1 = [v1[0]/len1, v1[1]/len1];
-                                let n2 = [v2[0]/len2, v2[1]/len2];
-                                let dot = n1[0]*n2[0] + n1[1]*n2[1];
-                                let current_angle = dot.clamp(-1.0, 1.0).acos();
-                                let angle_error = (current_angle - value).abs();
-                                
-                                if angle_error > max_error { max_error = angle_error; }
-                                
-                                // Rotate L2 to achieve target angle
-                                if angle_error > epsilon {
-                                    // Target direction for L2 based on L1 + desired angle
-                                    let cos_target = value.cos();
-                                    let sin_target = value.sin();
-                                    // Rotate n1 by target angle to get target n2
-                                    let target_n2 = [
-                                        n1[0] * cos_target - n1[1] * sin_target,
-                                        n1[0] * sin_target + n1[1] * cos_target
-                                    ];
-                                    Self::rotate_line_to_dir(sketch, &id_map, lines[1], target_n2);
-                                }
-                            }
-                        }
+                    SketchConstraint::TangentAtPoint { a, b } => {
+                        Self::solve_tangent_at_point(sketch, &id_map, *a, *b, tolerance, &mut max_error);
+                    },
+                    SketchConstraint::Angle { lines, value, unit, .. } => {
+                        Self::solve_angle(sketch, &id_map, *lines, unit.to_radians(*value), tolerance, &mut max_error);
                     },
                     SketchConstraint::Radius { entity, value, .. } => {
                         let geo = Self::get_geometry(sketch, &id_map, *entity);
@@ -606,23 +786,60 @@ impl SketchSolver {
                                     
                                     // Shift needed along normal
                                     let shift = target_signed_dist - signed_dist;
-                                    // Move P by shift * 0.5 (along Normal)
-                                    // Move Line by -shift * 0.5 (along Normal)
+                                    // Split the shift along the normal between P and the line
+                                    let (s_point, s_line) = Self::correction_shares(sketch, point.id, *line);
                                     
-                                    let p_dx = nx * shift * 0.5;
-                                    let p_dy = ny * shift * 0.5;
+                                    let p_dx = nx * shift * s_point;
+                                    let p_dy = ny * shift * s_point;
                                     
                                     Self::set_point(sketch, &id_map, *point, [pos[0] + p_dx, pos[1] + p_dy]);
                                     
                                     // Move line (both endpoints)
-                                    let l_dx = -p_dx;
-                                    let l_dy = -p_dy;
+                                    let l_dx = -nx * shift * s_line;
+                                    let l_dy = -ny * shift * s_line;
                                     Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 0 }, [start[0] + l_dx, start[1] + l_dy]);
                                     Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 1 }, [end[0] + l_dx, end[1] + l_dy]);
                                 }
                             }
                         }
                     },
+                    SketchConstraint::DistancePointCircle { point, circle, value, .. } => {
+                        Self::solve_distance_point_circle(sketch, &id_map, *point, *circle, *value, epsilon, &mut max_error);
+                    },
+                    SketchConstraint::ThroughOrigin { line } => {
+                        if let Some(SketchGeometry::Line { start, end }) = Self::get_geometry_copy(sketch, &id_map, *line) {
+                            let lx = end[0] - start[0];
+                            let ly = end[1] - start[1];
+                            let len = (lx * lx + ly * ly).sqrt();
+
+                            if len > epsilon {
+                                let nx = -ly / len;
+                                let ny = lx / len;
+
+                                // Signed distance of the origin from the line; the
+                                // origin can't move, so the whole line shifts by it
+                                let shift = -(start[0] * nx + start[1] * ny);
+                                let error = shift.abs();
+                                if error > max_error { max_error = error; }
+
+                                if error > epsilon {
+                                    let (dx, dy) = (nx * shift, ny * shift);
+                                    Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 0 }, [start[0] + dx, start[1] + dy]);
+                                    Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 1 }, [end[0] + dx, end[1] + dy]);
+                                }
+                            }
+                        }
+                    },
+                    SketchConstraint::OffsetFromExternal { entity, external, distance } => {
+                        if let Some(shift) = Self::offset_from_external_shift(sketch, &id_map, *entity, *external, *distance) {
+                            let error = shift[0].hypot(shift[1]);
+                            if error > max_error { max_error = error; }
+
+                            if error > epsilon {
+                                Self::translate_entity(sketch, &id_map, *entity, shift);
+                            }
+                        }
+                    },
                     SketchConstraint::DistanceParallelLines { lines, value, .. } => {
                         // Get both line geometries
                         let l1_geo = Self::get_geometry_copy(sketch, &id_map, lines[0]);
@@ -672,11 +889,14 @@ impl SketchSolver {
                         }
                     }
                 }
+
+                Self::restore_geometry(sketch, &projected);
+                max_error = prev_max_error.max(Self::normalized_error(sketch, &id_map, constraint, max_error, scale));
             }
 
             final_max_error = max_error;
 
-            if max_error < epsilon {
+            if max_error < tolerance {
                 converged = true;
                 break;
             }
@@ -687,17 +907,17 @@ impl SketchSolver {
         let constraint_count = sketch.constraints.len();
         let dof = Self::calculate_dof(sketch);
         
-        // Detect redundant constraints
-        let redundant_constraints = Self::detect_redundant_constraints(sketch);
-        
-        // Detect conflicts if solver didn't converge
-        let conflicts = if !converged {
-            Some(Self::detect_conflicts(sketch, &id_map, epsilon))
-        } else {
+        // Detect redundant constraints, and conflicts if solver didn't converge
+        let analysis = if defer_analysis {
             None
+        } else {
+            Self::run_analysis(sketch, &id_map, converged, epsilon, &AnalysisCancel::default())
         };
+        let analysis_pending = analysis.is_none();
+        let SketchAnalysis { redundant_constraints, conflicts, entity_statuses } = analysis
+            .unwrap_or_else(|| SketchAnalysis::pending(sketch));
         
-        let status_message = if !converged {
+        let mut status_message = if !converged {
             "Solver did not converge - constraints may be conflicting".to_string()
         } else if dof < 0 {
             format!("Over-constrained by {} DOF", -dof)
@@ -706,9 +926,10 @@ impl SketchSolver {
         } else {
             format!("Under-constrained by {} DOF", dof)
         };
-
-        // Calculate per-entity constraint status for visual indicators
-        let entity_statuses = Self::calculate_entity_statuses(sketch, &conflicts);
+        let groups = Self::group_statuses(sketch, &id_map, epsilon);
+        if !converged || dof < 0 {
+            status_message.push_str(&Self::active_groups_note(&groups));
+        }
 
         SolveResult {
             converged,
@@ -721,14 +942,72 @@ impl SketchSolver {
             redundant_constraints,
             conflicts,
             entity_statuses,
+            stale_external_constraints: Vec::new(),
+            analysis_pending,
+            groups,
+            measurements: measure_sketch(sketch, &[], LengthUnit::Millimeter),
+            dimension_graphics: layout_dimensions(sketch, LengthUnit::Millimeter),
+            additions: sketch.additions(),
+        }
+    }
+
+    /// Per-group counts of suppressed, satisfied and conflicting members
+    fn group_statuses(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, epsilon: f64) -> Vec<ConstraintGroupStatus> {
+        sketch.constraint_groups().into_iter().map(|name| {
+            let mut status = ConstraintGroupStatus { name: name.to_string(), constraints: 0, suppressed: 0, satisfied: 0, conflicting: 0 };
+            for entry in sketch.constraints.iter().filter(|e| e.group.as_deref() == Some(name)) {
+                status.constraints += 1;
+                if entry.suppressed {
+                    status.suppressed += 1;
+                } else if Self::calculate_constraint_error(sketch, id_map, &entry.constraint) > epsilon {
+                    status.conflicting += 1;
+                } else {
+                    status.satisfied += 1;
+                }
+            }
+            status
+        }).collect()
+    }
+
+    /// Status suffix naming the groups with active members when more than one
+    /// is: alternative schemes left on together over-constrain a sketch
+    fn active_groups_note(groups: &[ConstraintGroupStatus]) -> String {
+        let active: Vec<String> = groups.iter()
+            .filter(|g| g.suppressed < g.constraints)
+            .map(|g| format!("'{}'", g.name))
+            .collect();
+        if active.len() < 2 {
+            return String::new();
         }
+        format!(" - constraint groups {} are all active; suppress all but one", active.join(", "))
+    }
+
+    /// What adding `constraint` would do, without adding it: solves a copy of
+    /// the sketch with the constraint appended. Compare `dof` with
+    /// `calculate_dof(sketch)` for the DOF it removes; `conflicts` and a
+    /// negative `dof` mean it over-constrains the sketch.
+    pub fn preview_add_constraint(sketch: &Sketch, constraint: &SketchConstraint) -> SolveResult {
+        Self::preview_add_constraint_with_config(sketch, constraint, &SolverConfig::default())
+    }
+
+    /// `preview_add_constraint` with non-default solver tuning
+    pub fn preview_add_constraint_with_config(sketch: &Sketch, constraint: &SketchConstraint, config: &SolverConfig) -> SolveResult {
+        let mut preview = sketch.clone();
+        preview.constraints.push(constraint.clone().into());
+        Self::solve_with_config(&mut preview, config)
     }
 
     /// Relaxed solve that returns detailed per-constraint status and partial progress
     /// This is useful for interactive editing where sketches may be temporarily invalid
     pub fn solve_relaxed(sketch: &mut Sketch) -> RelaxedSolveResult {
-        let max_iterations = 100;
-        let epsilon = 1e-6;
+        Self::solve_relaxed_with_config(sketch, &SolverConfig::default())
+    }
+
+    /// `solve_relaxed` with non-default solver tuning
+    pub fn solve_relaxed_with_config(sketch: &mut Sketch, config: &SolverConfig) -> RelaxedSolveResult {
+        let max_iterations = config.max_iterations;
+        let epsilon = config.epsilon;
+        let damping = config.rotation_damping;
         
         // Map ID to index for fast lookup
         let mut id_map = HashMap::new();
@@ -760,17 +1039,32 @@ impl SketchSolver {
         let mut final_max_error = 0.0;
         let mut iterations_used = 0;
 
+        // Linear errors are normalized by the sketch size so they can be compared with
+        // angular errors; the tolerance keeps `epsilon` as the allowed linear error in mm.
+        let scale = Self::characteristic_size(sketch);
+        let tolerance = epsilon / scale;
+
+        // Statuses keep the sketch's order; only application is reordered
+        let active_refs: Vec<&SketchConstraint> = active_constraints.iter().map(|(_, c)| c).collect();
+        let order = Self::application_order(sketch, &id_map, &active_refs, scale);
+        let projected = Self::fixed_geometry(sketch);
+
         for iteration in 0..max_iterations {
             iterations_used = iteration + 1;
             let mut max_error = 0.0;
 
-            for (active_idx, (_, constraint)) in active_constraints.iter().enumerate() {
+            for &active_idx in &order {
+                let constraint = &active_constraints[active_idx].1;
                 // Check if this constraint is already satisfied and record first satisfaction
                 let pre_error = Self::calculate_constraint_error(sketch, &id_map, constraint);
                 if pre_error < epsilon && first_satisfied_at[active_idx].is_none() {
                     first_satisfied_at[active_idx] = Some(iteration);
                 }
                 
+                // Measure this constraint in isolation so its error can be normalized
+                let prev_max_error = max_error;
+                max_error = 0.0;
+
                 match constraint {
                     SketchConstraint::Coincident { points } => {
                         let p1 = Self::get_point(sketch, &id_map, points[0]);
@@ -780,9 +1074,10 @@ impl SketchSolver {
                             let dist_sq = (pos1[0] - pos2[0]).powi(2) + (pos1[1] - pos2[1]).powi(2);
                             if dist_sq > max_error { max_error = dist_sq; }
 
-                            let mid = [(pos1[0] + pos2[0]) * 0.5, (pos1[1] + pos2[1]) * 0.5];
-                            Self::set_point(sketch, &id_map, points[0], mid);
-                            Self::set_point(sketch, &id_map, points[1], mid);
+                            let (s1, s2) = Self::correction_shares(sketch, points[0].id, points[1].id);
+                            let d = [pos2[0] - pos1[0], pos2[1] - pos1[1]];
+                            Self::set_point(sketch, &id_map, points[0], [pos1[0] + d[0] * s1, pos1[1] + d[1] * s1]);
+                            Self::set_point(sketch, &id_map, points[1], [pos2[0] - d[0] * s2, pos2[1] - d[1] * s2]);
                         }
                     },
                     SketchConstraint::Horizontal { entity } => {
@@ -826,12 +1121,13 @@ impl SketchSolver {
                             if error > max_error { max_error = error; }
 
                             if current_dist > epsilon {
-                                let scale = 0.5 * (1.0 - value / current_dist);
+                                let scale = 1.0 - value / current_dist;
                                 let offset_x = dx * scale;
                                 let offset_y = dy * scale;
 
-                                let new_p1 = [pos1[0] + offset_x, pos1[1] + offset_y];
-                                let new_p2 = [pos2[0] - offset_x, pos2[1] - offset_y];
+                                let (s1, s2) = Self::correction_shares(sketch, points[0].id, points[1].id);
+                                let new_p1 = [pos1[0] + offset_x * s1, pos1[1] + offset_y * s1];
+                                let new_p2 = [pos2[0] - offset_x * s2, pos2[1] - offset_y * s2];
 
                                 Self::set_point(sketch, &id_map, points[0], new_p1);
                                 Self::set_point(sketch, &id_map, points[1], new_p2);
@@ -915,20 +1211,22 @@ impl SketchSolver {
                                 
                                 if error_par > max_error { max_error = error_par; }
                                 
-                                if error_par > epsilon {
+                                if error_par > tolerance {
                                     let dot = n1[0]*n2[0] + n1[1]*n2[1];
                                     let sign = if dot > 0.0 { 1.0 } else { -1.0 };
                                     
-                                    let avg_x = n1[0] + n2[0] * sign;
-                                    let avg_y = n1[1] + n2[1] * sign;
+                                    // A fixed line keeps its direction; the other turns to it
+                                    let (s1, s2) = Self::correction_shares(sketch, lines[0], lines[1]);
+                                    let avg_x = n1[0] * s2 + n2[0] * sign * s1;
+                                    let avg_y = n1[1] * s2 + n2[1] * sign * s1;
                                     let avg_len = (avg_x*avg_x + avg_y*avg_y).sqrt();
                                     
                                     if avg_len > epsilon {
                                         let target_nx = avg_x / avg_len;
                                         let target_ny = avg_y / avg_len;
                                         
-                                        Self::rotate_line_to_dir(sketch, &id_map, lines[0], [target_nx, target_ny]);
-                                        Self::rotate_line_to_dir(sketch, &id_map, lines[1], [target_nx * sign, target_ny * sign]);
+                                        Self::rotate_line_to_dir(sketch, &id_map, lines[0], damped_direction(n1, [target_nx, target_ny], damping));
+                                        Self::rotate_line_to_dir(sketch, &id_map, lines[1], damped_direction(n2, [target_nx * sign, target_ny * sign], damping));
                                     }
                                 }
                             }
@@ -949,19 +1247,20 @@ impl SketchSolver {
                                
                                if dot.abs() > max_error { max_error = dot.abs(); }
 
-                               if dot.abs() > epsilon {
+                               if dot.abs() > tolerance {
+                                   let (s1, s2) = Self::correction_shares(sketch, lines[0], lines[1]);
                                    let n2_new_x = n2[0] - dot * n1[0];
                                    let n2_new_y = n2[1] - dot * n1[1];
                                    let n2_len = (n2_new_x*n2_new_x + n2_new_y*n2_new_y).sqrt();
                                    if n2_len > epsilon {
-                                       Self::rotate_line_to_dir(sketch, &id_map, lines[1], [n2_new_x/n2_len, n2_new_y/n2_len]);
+                                       Self::rotate_line_to_dir(sketch, &id_map, lines[1], damped_direction(n2, [n2_new_x/n2_len, n2_new_y/n2_len], damping * s2));
                                    }
                                    
                                    let n1_new_x = n1[0] - dot * n2[0];
                                    let n1_new_y = n1[1] - dot * n2[1];
                                    let n1_len = (n1_new_x*n1_new_x + n1_new_y*n1_new_y).sqrt();
                                    if n1_len > epsilon {
-                                       Self::rotate_line_to_dir(sketch, &id_map, lines[0], [n1_new_x/n1_len, n1_new_y/n1_len]);
+                                       Self::rotate_line_to_dir(sketch, &id_map, lines[0], damped_direction(n1, [n1_new_x/n1_len, n1_new_y/n1_len], damping * s1));
                                    }
                                }
                            }
@@ -997,6 +1296,20 @@ impl SketchSolver {
                             },
                             _ => {}
                         }
+                    },
+                    SketchConstraint::EqualGroup { entities } => {
+                        if let Some(sizes) = Self::equal_group_sizes(sketch, &id_map, entities) {
+                            let average = sizes.iter().sum::<f64>() / sizes.len() as f64;
+                            let error = sizes.iter().map(|size| (size - average).abs()).fold(0.0, f64::max);
+                            if error > max_error { max_error = error; }
+
+                            if error > epsilon {
+                                for id in entities {
+                                    Self::set_line_length(sketch, &id_map, *id, average);
+                                    Self::set_circle_radius(sketch, &id_map, *id, average);
+                                }
+                            }
+                        }
                     },
                      SketchConstraint::Tangent { entities } => {
                         let g1 = Self::get_geometry_copy(sketch, &id_map, entities[0]);
@@ -1034,34 +1347,11 @@ impl SketchSolver {
                              }
                         }
                     },
-                    SketchConstraint::Angle { lines, value, .. } => {
-                        let l1_vec = Self::get_line_vector(sketch, &id_map, lines[0]);
-                        let l2_vec = Self::get_line_vector(sketch, &id_map, lines[1]);
-                        
-                        if let (Some(v1), Some(v2)) = (l1_vec, l2_vec) {
-                            let len1 = (v1[0]*v1[0] + v1[1]*v1[1]).sqrt();
-                            let len2 = (v2[0]*v2[0] + v2[1]*v2[1]).sqrt();
-                            
-                            if len1 > epsilon && len2 > epsilon {
-                                let n1 = [v1[0]/len1, v1[1]/len1];
-                                let n2 = [v2[0]/len2, v2[1]/len2];
-                                let dot = n1[0]*n2[0] + n1[1]*n2[1];
-                                let current_angle = dot.clamp(-1.0, 1.0).acos();
-                                let angle_error = (current_angle - value).abs();
-                                
-                                if angle_error > max_error { max_error = angle_error; }
-                                
-                                if angle_error > epsilon {
-                                    let cos_target = value.cos();
-                                    let sin_target = value.sin();
-                                    let target_n2 = [
-                                        n1[0] * cos_target - n1[1] * sin_target,
-                                        n1[0] * sin_target + n1[1] * cos_target
-                                    ];
-                                    Self::rotate_line_to_dir(sketch, &id_map, lines[1], target_n2);
-                                }
-                            }
-                        }
+                    SketchConstraint::TangentAtPoint { a, b } => {
+                        Self::solve_tangent_at_point(sketch, &id_map, *a, *b, tolerance, &mut max_error);
+                    },
+                    SketchConstraint::Angle { lines, value, unit, .. } => {
+                        Self::solve_angle(sketch, &id_map, *lines, unit.to_radians(*value), tolerance, &mut max_error);
                     },
                     SketchConstraint::Radius { entity, value, .. } => {
                          let geo = Self::get_geometry(sketch, &id_map, *entity);
@@ -1180,23 +1470,60 @@ impl SketchSolver {
                                     
                                     // Shift needed along normal
                                     let shift = target_signed_dist - signed_dist;
-                                    // Move P by shift * 0.5 (along Normal)
-                                    // Move Line by -shift * 0.5 (along Normal)
+                                    // Split the shift along the normal between P and the line
+                                    let (s_point, s_line) = Self::correction_shares(sketch, point.id, *line);
                                     
-                                    let p_dx = nx * shift * 0.5;
-                                    let p_dy = ny * shift * 0.5;
+                                    let p_dx = nx * shift * s_point;
+                                    let p_dy = ny * shift * s_point;
                                     
                                     Self::set_point(sketch, &id_map, *point, [pos[0] + p_dx, pos[1] + p_dy]);
                                     
                                     // Move line (both endpoints)
-                                    let l_dx = -p_dx;
-                                    let l_dy = -p_dy;
+                                    let l_dx = -nx * shift * s_line;
+                                    let l_dy = -ny * shift * s_line;
                                     Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 0 }, [start[0] + l_dx, start[1] + l_dy]);
                                     Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 1 }, [end[0] + l_dx, end[1] + l_dy]);
                                 }
                             }
                         }
                     },
+                    SketchConstraint::DistancePointCircle { point, circle, value, .. } => {
+                        Self::solve_distance_point_circle(sketch, &id_map, *point, *circle, *value, epsilon, &mut max_error);
+                    },
+                    SketchConstraint::ThroughOrigin { line } => {
+                        if let Some(SketchGeometry::Line { start, end }) = Self::get_geometry_copy(sketch, &id_map, *line) {
+                            let lx = end[0] - start[0];
+                            let ly = end[1] - start[1];
+                            let len = (lx * lx + ly * ly).sqrt();
+
+                            if len > epsilon {
+                                let nx = -ly / len;
+                                let ny = lx / len;
+
+                                // Signed distance of the origin from the line; the
+                                // origin can't move, so the whole line shifts by it
+                                let shift = -(start[0] * nx + start[1] * ny);
+                                let error = shift.abs();
+                                if error > max_error { max_error = error; }
+
+                                if error > epsilon {
+                                    let (dx, dy) = (nx * shift, ny * shift);
+                                    Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 0 }, [start[0] + dx, start[1] + dy]);
+                                    Self::set_point(sketch, &id_map, ConstraintPoint { id: *line, index: 1 }, [end[0] + dx, end[1] + dy]);
+                                }
+                            }
+                        }
+                    },
+                    SketchConstraint::OffsetFromExternal { entity, external, distance } => {
+                        if let Some(shift) = Self::offset_from_external_shift(sketch, &id_map, *entity, *external, *distance) {
+                            let error = shift[0].hypot(shift[1]);
+                            if error > max_error { max_error = error; }
+
+                            if error > epsilon {
+                                Self::translate_entity(sketch, &id_map, *entity, shift);
+                            }
+                        }
+                    },
                     SketchConstraint::DistanceParallelLines { lines, value, .. } => {
                         let l1_geo = Self::get_geometry_copy(sketch, &id_map, lines[0]);
                         let l2_geo = Self::get_geometry_copy(sketch, &id_map, lines[1]);
@@ -1239,11 +1566,14 @@ impl SketchSolver {
                         }
                     }
                 }
+
+                Self::restore_geometry(sketch, &projected);
+                max_error = prev_max_error.max(Self::normalized_error(sketch, &id_map, constraint, max_error, scale));
             }
 
             final_max_error = max_error;
 
-            if max_error < epsilon {
+            if max_error < tolerance {
                 converged = true;
                 break;
             }
@@ -1299,13 +1629,9 @@ impl SketchSolver {
         // Build base result
         let entity_count = sketch.entities.len();
         let dof = Self::calculate_dof(sketch);
-        let redundant_constraints = Self::detect_redundant_constraints(sketch);
-        
-        let conflicts = if !converged {
-            Some(Self::detect_conflicts(sketch, &id_map, epsilon))
-        } else {
-            None
-        };
+        let SketchAnalysis { redundant_constraints, conflicts, entity_statuses } =
+            Self::run_analysis(sketch, &id_map, converged, epsilon, &AnalysisCancel::default())
+                .expect("analysis is never cancelled here");
         
         let status_message = if converged {
             if dof == 0 {
@@ -1320,9 +1646,6 @@ impl SketchSolver {
                     satisfied_count, constraint_count, partial_progress * 100.0)
         };
 
-        // Calculate per-entity constraint status for visual indicators
-        let entity_statuses = Self::calculate_entity_statuses(sketch, &conflicts);
-
         let base_result = SolveResult {
             converged,
             iterations: iterations_used,
@@ -1334,6 +1657,12 @@ impl SketchSolver {
             redundant_constraints,
             conflicts,
             entity_statuses,
+            stale_external_constraints: Vec::new(),
+            analysis_pending: false,
+            groups: Self::group_statuses(sketch, &id_map, epsilon),
+            measurements: measure_sketch(sketch, &[], LengthUnit::Millimeter),
+            dimension_graphics: layout_dimensions(sketch, LengthUnit::Millimeter),
+            additions: sketch.additions(),
         };
 
         RelaxedSolveResult {
@@ -1347,26 +1676,129 @@ impl SketchSolver {
         }
     }
 
+    /// Order in which a relaxation pass applies `constraints`, as indices into it.
+    ///
+    /// Each constraint moves geometry to satisfy itself, undoing a little of what
+    /// earlier ones did, so the application order can decide which of two valid
+    /// solutions a sketch settles into. Sorting by phase (fixes, then structural
+    /// constraints, then dimensions, then orientations) and then by a canonical
+    /// key makes the result independent of the order constraints were added in:
+    /// any permutation of a sketch's constraints solves to the same geometry.
+    /// Dimensions closest to satisfied go first, so large edits move geometry last.
+    fn application_order(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, constraints: &[&SketchConstraint], scale: f64) -> Vec<usize> {
+        let keys: Vec<(u8, f64, String)> = constraints.iter().map(|constraint| {
+            let phase = match constraint {
+                SketchConstraint::Fix { .. } => 0,
+                SketchConstraint::Coincident { .. }
+                | SketchConstraint::Symmetric { .. }
+                | SketchConstraint::Tangent { .. }
+                | SketchConstraint::Equal { .. }
+                | SketchConstraint::EqualGroup { .. }
+                | SketchConstraint::ThroughOrigin { .. } => 1,
+                SketchConstraint::Distance { .. }
+                | SketchConstraint::HorizontalDistance { .. }
+                | SketchConstraint::VerticalDistance { .. }
+                | SketchConstraint::DistancePointLine { .. }
+                | SketchConstraint::DistancePointCircle { .. }
+                | SketchConstraint::DistanceParallelLines { .. }
+                | SketchConstraint::OffsetFromExternal { .. }
+                | SketchConstraint::Radius { .. }
+                | SketchConstraint::Angle { .. } => 2,
+                SketchConstraint::Horizontal { .. }
+                | SketchConstraint::Vertical { .. }
+                | SketchConstraint::Parallel { .. }
+                | SketchConstraint::Perpendicular { .. }
+                | SketchConstraint::TangentAtPoint { .. } => 3,
+            };
+            let error = if phase == 2 {
+                let raw = Self::calculate_constraint_error(sketch, id_map, constraint);
+                Self::normalized_error(sketch, id_map, constraint, raw, scale)
+            } else {
+                0.0
+            };
+            (phase, error, serde_json::to_string(constraint).unwrap_or_default())
+        }).collect();
+
+        let mut order: Vec<usize> = (0..constraints.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (ka, kb) = (&keys[a], &keys[b]);
+            ka.0.cmp(&kb.0).then(ka.1.total_cmp(&kb.1)).then_with(|| ka.2.cmp(&kb.2))
+        });
+        order
+    }
+
+    /// Characteristic length of a sketch: the diagonal of its bounding box, clamped to at
+    /// least 1.0 so that tiny sketches do not inflate linear errors.
+    fn characteristic_size(sketch: &Sketch) -> f64 {
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        let mut include = |p: [f64; 2], r: f64| {
+            for i in 0..2 {
+                min[i] = min[i].min(p[i] - r);
+                max[i] = max[i].max(p[i] + r);
+            }
+        };
+        for entity in &sketch.entities {
+            match &entity.geometry {
+                SketchGeometry::Line { start, end } => { include(*start, 0.0); include(*end, 0.0); },
+                SketchGeometry::Point { pos } => include(*pos, 0.0),
+                SketchGeometry::Circle { center, radius } => include(*center, *radius),
+                SketchGeometry::Arc { center, radius, .. } => include(*center, *radius),
+                SketchGeometry::Ellipse { center, semi_major, .. }
+                | SketchGeometry::EllipseArc { center, semi_major, .. } => include(*center, *semi_major),
+            }
+        }
+        if min[0] > max[0] {
+            return 1.0;
+        }
+        let diagonal = ((max[0] - min[0]).powi(2) + (max[1] - min[1]).powi(2)).sqrt();
+        diagonal.max(1.0)
+    }
+
+    /// Whether a constraint's error is angular (dimensionless, ~sin θ) rather than a length
+    fn is_angular_constraint(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, constraint: &SketchConstraint) -> bool {
+        match constraint {
+            SketchConstraint::Parallel { .. }
+            | SketchConstraint::Perpendicular { .. }
+            | SketchConstraint::TangentAtPoint { .. }
+            | SketchConstraint::Angle { .. } => true,
+            // Horizontal/Vertical on an ellipse constrain its rotation
+            SketchConstraint::Horizontal { entity } | SketchConstraint::Vertical { entity } => {
+                matches!(Self::get_geometry(sketch, id_map, *entity), Some(SketchGeometry::Ellipse { .. }))
+            },
+            _ => false,
+        }
+    }
+
+    /// Bring a constraint's raw error onto a common dimensionless scale:
+    /// angular errors are kept as-is, linear errors are divided by the sketch size.
+    fn normalized_error(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, constraint: &SketchConstraint, raw_error: f64, scale: f64) -> f64 {
+        if Self::is_angular_constraint(sketch, id_map, constraint) {
+            raw_error
+        } else {
+            raw_error / scale
+        }
+    }
+
     /// Calculate estimated degrees of freedom (DOF) for the sketch
     /// DOF = total entity DOF - total constraint DOF removed
-    fn calculate_dof(sketch: &Sketch) -> i32 {
-        // Each geometry type has a certain number of DOF
+    pub fn calculate_dof(sketch: &Sketch) -> i32 {
+        // Each geometry type has a certain number of DOF; projected geometry has none
         let mut total_dof: i32 = 0;
-        for entity in &sketch.entities {
-            total_dof += match &entity.geometry {
-                SketchGeometry::Point { .. } => 2,  // x, y
-                SketchGeometry::Line { .. } => 4,   // start_x, start_y, end_x, end_y
-                SketchGeometry::Circle { .. } => 3, // center_x, center_y, radius
-                SketchGeometry::Arc { .. } => 5,    // center_x, center_y, radius, start_angle, end_angle
-                SketchGeometry::Ellipse { .. } => 5, // center_x, center_y, semi_major, semi_minor, rotation
-            };
+        for entity in sketch.entities.iter().filter(|e| !sketch.is_projected(e.id)) {
+            total_dof += Self::geometry_dof(&entity.geometry);
         }
 
-        // Each constraint removes a certain number of DOF (skip suppressed)
-        let mut constrained_dof: i32 = 0;
+        // A locked entity's implicit fixes remove all of its DOF
+        let mut constrained_dof: i32 = sketch.entities.iter()
+            .filter(|e| e.locked && !sketch.is_projected(e.id))
+            .map(|e| Self::geometry_dof(&e.geometry))
+            .sum();
+
+        // Each constraint removes a certain number of DOF (skip suppressed and degenerate)
         for entry in &sketch.constraints {
             // Skip suppressed constraints
-            if entry.suppressed {
+            if entry.suppressed || Self::is_degenerate_constraint(sketch, &entry.constraint) {
                 continue;
             }
             constrained_dof += match &entry.constraint {
@@ -1380,38 +1812,60 @@ impl SketchSolver {
                 SketchConstraint::Parallel { .. } => 1,   // Removes 1 DOF (angle)
                 SketchConstraint::Perpendicular { .. } => 1, // Removes 1 DOF (angle)
                 SketchConstraint::Tangent { .. } => 1,    // Removes 1 DOF
+                SketchConstraint::TangentAtPoint { .. } => 1, // Removes 1 DOF (direction at the joint)
                 SketchConstraint::Equal { .. } => 1,      // Removes 1 DOF (length/radius)
+                SketchConstraint::EqualGroup { entities } => entities.len().saturating_sub(1) as i32, // 1 DOF per member past the first
                 SketchConstraint::Fix { .. } => 2,        // Removes 2 DOF (x, y)
                 SketchConstraint::Symmetric { .. } => 2,  // Removes 2 DOF (reflection is precise)
                 SketchConstraint::Radius { .. } => 1,     // Removes 1 DOF (radius)
                 SketchConstraint::DistancePointLine { .. } => 1, // Removes 1 DOF (distance)
+                SketchConstraint::DistancePointCircle { .. } => 1, // Removes 1 DOF (distance to the circle)
                 SketchConstraint::DistanceParallelLines { .. } => 1, // Removes 1 DOF (distance between parallel lines)
+                SketchConstraint::ThroughOrigin { .. } => 1, // Removes 1 DOF (offset from the origin)
+                SketchConstraint::OffsetFromExternal { .. } => 1, // Removes 1 DOF (offset from the projection)
             };
         }
 
         total_dof - constrained_dof
     }
+
+    /// DOF an entity's geometry contributes
+    fn geometry_dof(geometry: &SketchGeometry) -> i32 {
+        match geometry {
+            SketchGeometry::Point { .. } => 2,  // x, y
+            SketchGeometry::Line { .. } => 4,   // start_x, start_y, end_x, end_y
+            SketchGeometry::Circle { .. } => 3, // center_x, center_y, radius
+            SketchGeometry::Arc { .. } => 5,    // center_x, center_y, radius, start_angle, end_angle
+            SketchGeometry::Ellipse { .. } => 5, // center_x, center_y, semi_major, semi_minor, rotation
+            SketchGeometry::EllipseArc { .. } => 7, // the ellipse's 5, start_angle, end_angle
+        }
+    }
     
+    /// A constraint that removes no DOF: it references an entity missing from the
+    /// sketch (e.g. left behind by a deletion), ties a point to itself, or only
+    /// involves reference, projected and locked geometry, which can't move anyway.
+    fn is_degenerate_constraint(sketch: &Sketch, constraint: &SketchConstraint) -> bool {
+        let entities = Self::get_constraint_entities(constraint);
+        let missing = entities.iter()
+            .any(|id| !ReferenceEntity::is_reference(*id) && !sketch.entities.iter().any(|e| e.id == *id));
+        let self_coincident = matches!(constraint, SketchConstraint::Coincident { points } if points[0] == points[1]);
+        let all_fixed = entities.iter().all(|id| Self::is_fixed(sketch, *id));
+        missing || self_coincident || all_fixed
+    }
+
     /// Calculate per-entity constraint status for visual DOF indicators
     /// Returns a status for each entity showing how constrained it is
     fn calculate_entity_statuses(sketch: &Sketch, conflicts: &Option<ConflictInfo>) -> Vec<EntityConstraintStatus> {
         let mut entity_dof_map: HashMap<EntityId, (i32, i32)> = HashMap::new(); // (total_dof, constrained_dof)
         
-        // Initialize with total DOF for each entity
-        for entity in &sketch.entities {
-            let total = match &entity.geometry {
-                SketchGeometry::Point { .. } => 2,
-                SketchGeometry::Line { .. } => 4,
-                SketchGeometry::Circle { .. } => 3,
-                SketchGeometry::Arc { .. } => 5,
-                SketchGeometry::Ellipse { .. } => 5,
-            };
-            entity_dof_map.insert(entity.id, (total, 0));
+        // Initialize with total DOF for each entity; projected geometry is fixed
+        for entity in sketch.entities.iter().filter(|e| !sketch.is_projected(e.id)) {
+            entity_dof_map.insert(entity.id, (Self::geometry_dof(&entity.geometry), 0));
         }
         
-        // Accumulate constrained DOF from each active (non-suppressed) constraint
+        // Accumulate constrained DOF from each active (non-suppressed, non-degenerate) constraint
         for entry in &sketch.constraints {
-            if entry.suppressed {
+            if entry.suppressed || Self::is_degenerate_constraint(sketch, &entry.constraint) {
                 continue;
             }
             let (affected_entities, dof_per_entity) = match &entry.constraint {
@@ -1435,16 +1889,22 @@ impl SketchSolver {
                 SketchConstraint::Parallel { lines } => (vec![lines[0], lines[1]], 1),
                 SketchConstraint::Perpendicular { lines } => (vec![lines[0], lines[1]], 1),
                 SketchConstraint::Tangent { entities } => (vec![entities[0], entities[1]], 1),
+                SketchConstraint::TangentAtPoint { a, b } => (vec![a.id, b.id], 1),
                 SketchConstraint::Equal { entities } => (vec![entities[0], entities[1]], 1),
+                SketchConstraint::EqualGroup { entities } => (entities.clone(), 1),
                 SketchConstraint::Fix { point, .. } => (vec![point.id], 2),
                 SketchConstraint::Symmetric { p1, p2, axis } => (vec![p1.id, p2.id, *axis], 2), // 2 DOF distributed?
                 SketchConstraint::Radius { entity, .. } => (vec![*entity], 1),
                 SketchConstraint::DistancePointLine { point, line, .. } => (vec![point.id, *line], 1),
+                SketchConstraint::DistancePointCircle { point, circle, .. } => (vec![point.id, *circle], 1),
                 SketchConstraint::DistanceParallelLines { lines, .. } => (vec![lines[0], lines[1]], 1),
+                SketchConstraint::ThroughOrigin { line } => (vec![*line], 1),
+                SketchConstraint::OffsetFromExternal { entity, .. } => (vec![*entity], 1),
             };
             
-            // Distribute the constraint DOF to affected entities
-            for entity_id in affected_entities {
+            // Distribute the constraint DOF to affected entities; the locked
+            // ones are already held by their implicit fixes
+            for entity_id in affected_entities.into_iter().filter(|id| !sketch.is_locked(*id)) {
                 if let Some((_, constrained)) = entity_dof_map.get_mut(&entity_id) {
                     *constrained += dof_per_entity;
                 }
@@ -1470,15 +1930,17 @@ impl SketchSolver {
         // Build result vector
         sketch.entities.iter().map(|entity| {
             let (total_dof, constrained_dof) = entity_dof_map.get(&entity.id).copied().unwrap_or((0, 0));
-            let remaining_dof = (total_dof - constrained_dof).max(0);
+            let locked_dof = if entity.locked { total_dof } else { 0 };
+            let remaining_dof = (total_dof - constrained_dof - locked_dof).max(0);
             let is_fully_constrained = remaining_dof == 0 && constrained_dof <= total_dof;
-            let is_over_constrained = constrained_dof > total_dof;
+            let is_over_constrained = constrained_dof + locked_dof > total_dof;
             let involved_in_conflict = conflict_entity_ids.contains(&entity.id);
             
             EntityConstraintStatus {
                 id: entity.id,
                 total_dof,
                 constrained_dof,
+                locked_dof,
                 remaining_dof,
                 is_fully_constrained,
                 is_over_constrained,
@@ -1487,290 +1949,240 @@ impl SketchSolver {
         }).collect()
     }
     
+    /// Redundancy and conflict analysis of a sketch solved by `solve_deferred`,
+    /// which returned `solved`. Conflicts are reported if it didn't converge.
+    pub fn analyze(sketch: &Sketch, solved: &SolveResult) -> SketchAnalysis {
+        Self::analyze_cancellable(sketch, solved, &AnalysisCancel::default()).expect("analysis is never cancelled here")
+    }
+
+    /// `analyze` that gives up, returning None, once `cancel` is cancelled
+    pub fn analyze_cancellable(sketch: &Sketch, solved: &SolveResult, cancel: &AnalysisCancel) -> Option<SketchAnalysis> {
+        let epsilon = 1e-6;
+        let id_map: HashMap<EntityId, usize> = sketch.entities.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+        Self::run_analysis(sketch, &id_map, solved.converged, epsilon, cancel)
+    }
+
+    fn run_analysis(
+        sketch: &Sketch,
+        id_map: &HashMap<EntityId, usize>,
+        converged: bool,
+        epsilon: f64,
+        cancel: &AnalysisCancel,
+    ) -> Option<SketchAnalysis> {
+        let redundant_constraints = Self::detect_redundant_constraints(sketch, cancel)?;
+        let conflicts = if converged { None } else { Some(Self::detect_conflicts(sketch, id_map, epsilon, cancel)?) };
+        // Calculate per-entity constraint status for visual indicators
+        let entity_statuses = Self::calculate_entity_statuses(sketch, &conflicts);
+        Some(SketchAnalysis { redundant_constraints, conflicts, entity_statuses })
+    }
+
     /// Detect redundant constraints in the sketch
-    /// Returns a list of constraints that are duplicates or implied by others
-    fn detect_redundant_constraints(sketch: &Sketch) -> Vec<RedundantConstraintInfo> {
-        use std::collections::HashSet;
-        
+    /// Returns a list of constraints that are duplicates or implied by others,
+    /// or None if cancelled
+    pub(crate) fn detect_redundant_constraints(sketch: &Sketch, cancel: &AnalysisCancel) -> Option<Vec<RedundantConstraintInfo>> {
         let mut redundant = Vec::new();
-        let constraints = &sketch.constraints;
-        
-        // Track constraint "signatures" for duplicate detection
-        // Signature is a normalized representation of what the constraint does
-        let mut seen_signatures: HashSet<String> = HashSet::new();
-        
-        // Track coincident relationships for transitive redundancy detection
-        // Uses union-find concept: map each point signature to its "root"
-        let mut coincident_groups: HashMap<String, String> = HashMap::new();
-        
-        // Helper to get normalized point signature
-        fn point_sig(cp: &ConstraintPoint) -> String {
-            format!("{}:{}", cp.id, cp.index)
-        }
-        
-        // Helper to find the root of a coincident group
-        fn find_root(groups: &HashMap<String, String>, key: &str) -> String {
-            let mut current = key.to_string();
-            while let Some(parent) = groups.get(&current) {
-                if parent == &current {
-                    break;
-                }
-                current = parent.clone();
-            }
-            current
-        }
-        
-        // First pass: collect coincident groups (from active constraints only)
-        for entry in constraints.iter() {
+
+        // Exact duplicates: interned signatures map to the first active
+        // constraint that has them
+        let mut first_with_signature: HashMap<String, usize> = HashMap::new();
+        for (i, entry) in sketch.constraints.iter().enumerate() {
             if entry.suppressed {
                 continue;
             }
-            if let SketchConstraint::Coincident { points } = &entry.constraint {
-                let sig1 = point_sig(&points[0]);
-                let sig2 = point_sig(&points[1]);
-                
-                let root1 = find_root(&coincident_groups, &sig1);
-                let root2 = find_root(&coincident_groups, &sig2);
-                
-                // Ensure both points are in the map
-                coincident_groups.entry(sig1.clone()).or_insert_with(|| sig1.clone());
-                coincident_groups.entry(sig2.clone()).or_insert_with(|| sig2.clone());
-                
-                // Union the groups (make root2 point to root1)
-                if root1 != root2 {
-                    coincident_groups.insert(root2, root1);
+            if cancel.is_cancelled() {
+                return None;
+            }
+            match first_with_signature.entry(Self::constraint_signature(&entry.constraint)) {
+                std::collections::hash_map::Entry::Occupied(first) => redundant.push(RedundantConstraintInfo {
+                    constraint_index: i,
+                    duplicates_index: Some(*first.get()),
+                    reason: format!("Exact duplicate of constraint #{}", first.get()),
+                }),
+                std::collections::hash_map::Entry::Vacant(slot) => {
+                    slot.insert(i);
                 }
             }
         }
-        
-        // Second pass: detect redundant constraints (from active constraints only)
-        for (i, entry) in constraints.iter().enumerate() {
-            if entry.suppressed {
+
+        // Transitive coincident redundancy (A=B and B=C already implies A=C):
+        // a coincident is implied by the others unless removing it would
+        // disconnect its points, i.e. unless it is a bridge of the point graph
+        let mut point_ids: HashMap<ConstraintPoint, usize> = HashMap::new();
+        let mut intern = |cp: ConstraintPoint| {
+            let next = point_ids.len();
+            *point_ids.entry(cp).or_insert(next)
+        };
+        let coincidents: Vec<(usize, usize, usize)> = sketch.constraints.iter().enumerate()
+            .filter(|(_, entry)| !entry.suppressed)
+            .filter_map(|(i, entry)| match &entry.constraint {
+                SketchConstraint::Coincident { points } => Some((intern(points[0]), intern(points[1]), i)),
+                _ => None,
+            })
+            .collect();
+        let edges: Vec<(usize, usize)> = coincidents.iter().map(|&(a, b, _)| (a, b)).collect();
+        let bridges = Self::find_bridges(point_ids.len(), &edges, cancel)?;
+
+        for (&(_, _, idx), is_bridge) in coincidents.iter().zip(bridges) {
+            // Only report if not already reported as exact duplicate
+            if !is_bridge && !redundant.iter().any(|r| r.constraint_index == idx) {
+                redundant.push(RedundantConstraintInfo {
+                    constraint_index: idx,
+                    duplicates_index: None,
+                    reason: "Implied by transitivity through other coincident constraints".to_string(),
+                });
+            }
+        }
+
+        Some(redundant)
+    }
+
+    /// Which of the undirected `edges` between `node_count` nodes are bridges:
+    /// edges whose removal disconnects their endpoints. Parallel edges and
+    /// self-loops never are. Iterative Tarjan, so long chains can't overflow
+    /// the stack. None if cancelled.
+    fn find_bridges(node_count: usize, edges: &[(usize, usize)], cancel: &AnalysisCancel) -> Option<Vec<bool>> {
+        let mut adjacency: Vec<Vec<(usize, usize)>> = vec![Vec::new(); node_count];
+        for (e, &(a, b)) in edges.iter().enumerate() {
+            adjacency[a].push((b, e));
+            if a != b {
+                adjacency[b].push((a, e));
+            }
+        }
+
+        const UNVISITED: usize = usize::MAX;
+        let mut discovered = vec![UNVISITED; node_count];
+        let mut low = vec![0; node_count];
+        let mut is_bridge = vec![false; edges.len()];
+        let mut time = 0;
+        for root in 0..node_count {
+            if discovered[root] != UNVISITED {
                 continue;
             }
-            let signature = match &entry.constraint {
-                SketchConstraint::Coincident { points } => {
-                    // Check if this coincident is implied by transitivity
-                    let sig1 = point_sig(&points[0]);
-                    let sig2 = point_sig(&points[1]);
-                    let root1 = find_root(&coincident_groups, &sig1);
-                    let root2 = find_root(&coincident_groups, &sig2);
-                    
-                    // Normalize: smaller string first
-                    let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                    format!("COINC:{}:{}", a, b)
-                },
-                SketchConstraint::Horizontal { entity } => {
-                    format!("HORIZ:{}", entity)
-                },
-                SketchConstraint::Vertical { entity } => {
-                    format!("VERT:{}", entity)
-                },
-                SketchConstraint::Distance { points, value, .. } => {
-                    let sig1 = point_sig(&points[0]);
-                    let sig2 = point_sig(&points[1]);
-                    let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                    format!("DIST:{}:{}:{:.6}", a, b, value)
-                },
-                SketchConstraint::HorizontalDistance { points, value, .. } => {
-                    let sig1 = point_sig(&points[0]);
-                    let sig2 = point_sig(&points[1]);
-                    let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                    format!("HDIST:{}:{}:{:.6}", a, b, value)
-                },
-                SketchConstraint::VerticalDistance { points, value, .. } => {
-                    let sig1 = point_sig(&points[0]);
-                    let sig2 = point_sig(&points[1]);
-                    let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                    format!("VDIST:{}:{}:{:.6}", a, b, value)
-                },
-                SketchConstraint::Parallel { lines } => {
-                    let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                    format!("PAR:{}:{}", a, b)
-                },
-                SketchConstraint::Perpendicular { lines } => {
-                    let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                    format!("PERP:{}:{}", a, b)
-                },
-                SketchConstraint::Tangent { entities } => {
-                    let (a, b) = if entities[0] < entities[1] { (entities[0], entities[1]) } else { (entities[1], entities[0]) };
-                    format!("TAN:{}:{}", a, b)
-                },
-                SketchConstraint::Equal { entities } => {
-                    let (a, b) = if entities[0] < entities[1] { (entities[0], entities[1]) } else { (entities[1], entities[0]) };
-                    format!("EQ:{}:{}", a, b)
-                },
-                SketchConstraint::Fix { point, position } => {
-                    format!("FIX:{}:{}:{:.6}:{:.6}", point.id, point.index, position[0], position[1])
-                },
-                SketchConstraint::Angle { lines, value, .. } => {
-                    let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                    format!("ANGLE:{}:{}:{:.6}", a, b, value)
-                },
-                SketchConstraint::Radius { entity, value, .. } => {
-                    format!("RADIUS:{}:{:.6}", entity, value)
-                },
-                SketchConstraint::Symmetric { p1, p2, axis } => {
-                    let sig1 = point_sig(p1);
-                    let sig2 = point_sig(p2);
-                    let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                    format!("SYM:{}:{}:{}", a, b, axis)
-                },
-                SketchConstraint::DistancePointLine { point, line, value, .. } => {
-                    format!("DIST_PL:{}:{}:{:.6}", point_sig(point), line, value)
-                },
-                SketchConstraint::DistanceParallelLines { lines, value, .. } => {
-                    let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                    format!("DIST_LL:{}:{}:{:.6}", a, b, value)
-                },
-            };
-            
-            // Check for exact duplicate
-            if seen_signatures.contains(&signature) {
-                // Find which constraint this duplicates
-                let dup_index = constraints.iter().position(|entry| {
-                    if entry.suppressed {
-                        return false;
-                    }
-                    let other_sig = match &entry.constraint {
-                        SketchConstraint::Coincident { points } => {
-                            let sig1 = point_sig(&points[0]);
-                            let sig2 = point_sig(&points[1]);
-                            let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                            format!("COINC:{}:{}", a, b)
-                        },
-                        SketchConstraint::Horizontal { entity } => format!("HORIZ:{}", entity),
-                        SketchConstraint::Vertical { entity } => format!("VERT:{}", entity),
-                        SketchConstraint::Distance { points, value, .. } => {
-                            let sig1 = point_sig(&points[0]);
-                            let sig2 = point_sig(&points[1]);
-                            let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                            format!("DIST:{}:{}:{:.6}", a, b, value)
-                        },
-                        SketchConstraint::HorizontalDistance { points, value, .. } => {
-                            let sig1 = point_sig(&points[0]);
-                            let sig2 = point_sig(&points[1]);
-                            let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                            format!("HDIST:{}:{}:{:.6}", a, b, value)
-                        },
-                        SketchConstraint::VerticalDistance { points, value, .. } => {
-                            let sig1 = point_sig(&points[0]);
-                            let sig2 = point_sig(&points[1]);
-                            let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                            format!("VDIST:{}:{}:{:.6}", a, b, value)
-                        },
-                        SketchConstraint::Parallel { lines } => {
-                            let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                            format!("PAR:{}:{}", a, b)
-                        },
-                        SketchConstraint::Perpendicular { lines } => {
-                            let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                            format!("PERP:{}:{}", a, b)
-                        },
-                        SketchConstraint::Tangent { entities } => {
-                            let (a, b) = if entities[0] < entities[1] { (entities[0], entities[1]) } else { (entities[1], entities[0]) };
-                            format!("TAN:{}:{}", a, b)
-                        },
-                        SketchConstraint::Equal { entities } => {
-                            let (a, b) = if entities[0] < entities[1] { (entities[0], entities[1]) } else { (entities[1], entities[0]) };
-                            format!("EQ:{}:{}", a, b)
-                        },
-                        SketchConstraint::Fix { point, position } => {
-                            format!("FIX:{}:{}:{:.6}:{:.6}", point.id, point.index, position[0], position[1])
-                        },
-                        SketchConstraint::Angle { lines, value, .. } => {
-                            let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                            format!("ANGLE:{}:{}:{:.6}", a, b, value)
-                        },
-                        SketchConstraint::Radius { entity, value, .. } => {
-                            format!("RADIUS:{}:{:.6}", entity, value)
-                        },
-                        SketchConstraint::Symmetric { p1, p2, axis } => {
-                            let sig1 = point_sig(p1);
-                            let sig2 = point_sig(p2);
-                            let (a, b) = if sig1 < sig2 { (sig1, sig2) } else { (sig2, sig1) };
-                            format!("SYM:{}:{}:{}", a, b, axis)
-                        },
-                        SketchConstraint::DistancePointLine { point, line, value, .. } => {
-                            format!("DIST_PL:{}:{}:{:.6}", point_sig(point), line, value)
-                        },
-                        SketchConstraint::DistanceParallelLines { lines, value, .. } => {
-                            let (a, b) = if lines[0] < lines[1] { (lines[0], lines[1]) } else { (lines[1], lines[0]) };
-                            format!("DIST_LL:{}:{}:{:.6}", a, b, value)
-                        },
-                    };
-                    other_sig == signature
-                }.into());
-                
-                redundant.push(RedundantConstraintInfo {
-                    constraint_index: i,
-                    duplicates_index: dup_index,
-                    reason: format!("Exact duplicate of constraint #{}", dup_index.map_or("?".to_string(), |idx| idx.to_string())),
-                }.into());
-            } else {
-                seen_signatures.insert(signature);
+            if cancel.is_cancelled() {
+                return None;
             }
-        }
-        
-        // Third pass: check for transitive coincident redundancy
-        // (A=B and B=C already implies A=C)
-        let mut coincident_pairs: Vec<(String, String, usize)> = Vec::new();
-        for (i, entry) in constraints.iter().enumerate() {
-            if entry.suppressed {
-                continue;
-            }
-            if let SketchConstraint::Coincident { points } = &entry.constraint {
-                let sig1 = point_sig(&points[0]);
-                let sig2 = point_sig(&points[1]);
-                coincident_pairs.push((sig1, sig2, i));
-            }
-        }
-        
-        // For each coincident constraint, check if it's implied by transitivity
-        for (sig1, sig2, idx) in &coincident_pairs {
-            let root1 = find_root(&coincident_groups, sig1);
-            let root2 = find_root(&coincident_groups, sig2);
-            
-            // If both points converge to the same root through OTHER constraints,
-            // then this constraint is redundant
-            // We need to check if removing this constraint would still leave them connected
-            
-            // Build a graph without this constraint
-            let mut temp_groups: HashMap<String, String> = HashMap::new();
-            for (s1, s2, other_idx) in &coincident_pairs {
-                if other_idx != idx {
-                    let r1 = find_root(&temp_groups, s1);
-                    let r2 = find_root(&temp_groups, s2);
-                    temp_groups.entry(s1.clone()).or_insert_with(|| s1.clone());
-                    temp_groups.entry(s2.clone()).or_insert_with(|| s2.clone());
-                    if r1 != r2 {
-                        temp_groups.insert(r2, r1);
+            discovered[root] = time;
+            low[root] = time;
+            time += 1;
+            // (node, edge it was reached by, next adjacency entry to visit)
+            let mut stack = vec![(root, usize::MAX, 0)];
+            while let Some(top) = stack.len().checked_sub(1) {
+                let (node, via, next) = stack[top];
+                if let Some(&(neighbor, edge)) = adjacency[node].get(next) {
+                    stack[top].2 += 1;
+                    if edge == via {
+                        continue;
+                    }
+                    if discovered[neighbor] == UNVISITED {
+                        discovered[neighbor] = time;
+                        low[neighbor] = time;
+                        time += 1;
+                        stack.push((neighbor, edge, 0));
+                    } else {
+                        low[node] = low[node].min(discovered[neighbor]);
+                    }
+                } else {
+                    stack.pop();
+                    if let Some(&(parent, _, _)) = stack.last() {
+                        low[parent] = low[parent].min(low[node]);
+                        if low[node] > discovered[parent] {
+                            is_bridge[via] = true;
+                        }
                     }
-                }
-            }
-            
-            let temp_root1 = find_root(&temp_groups, sig1);
-            let temp_root2 = find_root(&temp_groups, sig2);
-            
-            // If they're still in the same group without this constraint, it's redundant
-            if temp_root1 == temp_root2 {
-                // Only report if not already reported as exact duplicate
-                if !redundant.iter().any(|r: &RedundantConstraintInfo| r.constraint_index == *idx) {
-                    redundant.push(RedundantConstraintInfo {
-                        constraint_index: *idx,
-                        duplicates_index: None,
-                        reason: "Implied by transitivity through other coincident constraints".to_string(),
-                    });
                 }
             }
         }
-        
-        redundant
+        Some(is_bridge)
+    }
+
+    /// Normalized description of what a constraint does; two active
+    /// constraints with the same signature are duplicates
+    fn constraint_signature(constraint: &SketchConstraint) -> String {
+        fn point_sig(cp: &ConstraintPoint) -> String {
+            format!("{}:{}", cp.id, cp.index)
+        }
+        fn ordered<T: PartialOrd>(a: T, b: T) -> (T, T) {
+            if a < b { (a, b) } else { (b, a) }
+        }
+
+        match constraint {
+            SketchConstraint::Coincident { points } => {
+                let (a, b) = ordered(point_sig(&points[0]), point_sig(&points[1]));
+                format!("COINC:{}:{}", a, b)
+            },
+            SketchConstraint::Horizontal { entity } => format!("HORIZ:{}", entity),
+            SketchConstraint::Vertical { entity } => format!("VERT:{}", entity),
+            SketchConstraint::Distance { points, value, .. } => {
+                let (a, b) = ordered(point_sig(&points[0]), point_sig(&points[1]));
+                format!("DIST:{}:{}:{:.6}", a, b, value)
+            },
+            SketchConstraint::HorizontalDistance { points, value, .. } => {
+                let (a, b) = ordered(point_sig(&points[0]), point_sig(&points[1]));
+                format!("HDIST:{}:{}:{:.6}", a, b, value)
+            },
+            SketchConstraint::VerticalDistance { points, value, .. } => {
+                let (a, b) = ordered(point_sig(&points[0]), point_sig(&points[1]));
+                format!("VDIST:{}:{}:{:.6}", a, b, value)
+            },
+            SketchConstraint::Parallel { lines } => {
+                let (a, b) = ordered(lines[0], lines[1]);
+                format!("PAR:{}:{}", a, b)
+            },
+            SketchConstraint::Perpendicular { lines } => {
+                let (a, b) = ordered(lines[0], lines[1]);
+                format!("PERP:{}:{}", a, b)
+            },
+            SketchConstraint::Tangent { entities } => {
+                let (a, b) = ordered(entities[0], entities[1]);
+                format!("TAN:{}:{}", a, b)
+            },
+            SketchConstraint::TangentAtPoint { a, b } => {
+                let (a, b) = ordered(point_sig(a), point_sig(b));
+                format!("TANPT:{}:{}", a, b)
+            },
+            SketchConstraint::Equal { entities } => {
+                let (a, b) = ordered(entities[0], entities[1]);
+                format!("EQ:{}:{}", a, b)
+            },
+            SketchConstraint::EqualGroup { entities } => {
+                let mut ids: Vec<String> = entities.iter().map(|id| id.to_string()).collect();
+                ids.sort();
+                format!("EQGROUP:{}", ids.join(":"))
+            },
+            SketchConstraint::Fix { point, position } => {
+                format!("FIX:{}:{}:{:.6}:{:.6}", point.id, point.index, position[0], position[1])
+            },
+            SketchConstraint::Angle { lines, value, unit, .. } => {
+                let value = normalize_angle(unit.to_radians(*value));
+                let (a, b) = ordered(lines[0], lines[1]);
+                format!("ANGLE:{}:{}:{:.6}", a, b, value)
+            },
+            SketchConstraint::Radius { entity, value, .. } => format!("RADIUS:{}:{:.6}", entity, value),
+            SketchConstraint::Symmetric { p1, p2, axis } => {
+                let (a, b) = ordered(point_sig(p1), point_sig(p2));
+                format!("SYM:{}:{}:{}", a, b, axis)
+            },
+            SketchConstraint::DistancePointLine { point, line, value, .. } => {
+                format!("DIST_PL:{}:{}:{:.6}", point_sig(point), line, value)
+            },
+            SketchConstraint::DistancePointCircle { point, circle, value, .. } => {
+                format!("DIST_PC:{}:{}:{:.6}", point_sig(point), circle, value)
+            },
+            SketchConstraint::DistanceParallelLines { lines, value, .. } => {
+                let (a, b) = ordered(lines[0], lines[1]);
+                format!("DIST_LL:{}:{}:{:.6}", a, b, value)
+            },
+            SketchConstraint::ThroughOrigin { line } => format!("ORIGIN:{}", line),
+            SketchConstraint::OffsetFromExternal { entity, external, distance } => {
+                format!("OFFSET:{}:{}:{:.6}", entity, external, distance)
+            },
+        }
     }
     
     /// Detect conflicting constraints when solver fails to converge
-    /// Analyzes which constraints remain unsatisfied and identifies potential conflicts
-    fn detect_conflicts(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, epsilon: f64) -> ConflictInfo {
+    /// Analyzes which constraints remain unsatisfied and identifies potential conflicts;
+    /// None if cancelled
+    fn detect_conflicts(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, epsilon: f64, cancel: &AnalysisCancel) -> Option<ConflictInfo> {
         let mut unsatisfied_constraints = Vec::new();
         let mut constraint_errors = Vec::new();
         let mut possible_conflicts = Vec::new();
@@ -1793,6 +2205,9 @@ impl SketchSolver {
         // they might be in conflict
         for i in 0..unsatisfied_constraints.len() {
             for j in (i + 1)..unsatisfied_constraints.len() {
+                if cancel.is_cancelled() {
+                    return None;
+                }
                 let idx1 = unsatisfied_constraints[i];
                 let idx2 = unsatisfied_constraints[j];
                 
@@ -1828,11 +2243,23 @@ impl SketchSolver {
                 continue;
             }
             for j in (i + 1)..sketch.constraints.len() {
+                if cancel.is_cancelled() {
+                    return None;
+                }
                 if sketch.constraints[j].suppressed {
                     continue;
                 }
                 let c1 = &sketch.constraints[i].constraint;
                 let c2 = &sketch.constraints[j].constraint;
+
+                // Alternative schemes left active together, dimensioning the same geometry
+                if let (Some(g1), Some(g2)) = (&sketch.constraints[i].group, &sketch.constraints[j].group) {
+                    let entities2 = Self::get_constraint_entities(c2);
+                    if g1 != g2 && Self::get_constraint_entities(c1).iter().any(|e| entities2.contains(e))
+                        && !possible_conflicts.iter().any(|(a, b, _)| (*a == i && *b == j) || (*a == j && *b == i)) {
+                        possible_conflicts.push((i, j, "Both constraints affect the same geometry".to_string()));
+                    }
+                }
                 
                 // Check for Horizontal + Vertical on same entity
                 if let (SketchConstraint::Horizontal { entity: e1 }, SketchConstraint::Vertical { entity: e2 }) = (c1, c2) {
@@ -1849,6 +2276,15 @@ impl SketchSolver {
                         }
                     }
                 }
+
+                // Check for Tangent and Perpendicular at the same joint
+                if let (SketchConstraint::TangentAtPoint { a, b }, SketchConstraint::Perpendicular { lines })
+                | (SketchConstraint::Perpendicular { lines }, SketchConstraint::TangentAtPoint { a, b }) = (c1, c2) {
+                    if ((lines[0], lines[1]) == (a.id, b.id) || (lines[1], lines[0]) == (a.id, b.id))
+                        && !possible_conflicts.iter().any(|(a, b, _)| (*a == i && *b == j) || (*a == j && *b == i)) {
+                        possible_conflicts.push((i, j, format!("Tangent and Perpendicular constraints at the joint of {} and {}", a.id, b.id)));
+                    }
+                }
                 
                 // Check for conflicting Distance constraints (same points, different values)
                 if let (
@@ -1927,12 +2363,22 @@ impl SketchSolver {
                 }
             }
         }
-        
-        ConflictInfo {
+
+        // Between grouped constraints the likely cause is the grouping itself:
+        // two alternative schemes left active together
+        for (i, j, reason) in &mut possible_conflicts {
+            match (sketch.constraints[*i].group.as_deref(), sketch.constraints[*j].group.as_deref()) {
+                (Some(a), Some(b)) if a != b => reason.push_str(&format!(" (constraint groups '{}' and '{}' are both active)", a, b)),
+                (Some(a), Some(_)) => reason.push_str(&format!(" (both in constraint group '{}')", a)),
+                _ => {}
+            }
+        }
+
+        Some(ConflictInfo {
             unsatisfied_constraints,
             constraint_errors,
             possible_conflicts,
-        }
+        })
     }
     
     /// Calculate the current error for a single constraint
@@ -1965,29 +2411,14 @@ impl SketchSolver {
                     }
                 } else { 0.0 }
             },
-            SketchConstraint::Distance { points, value, .. } => {
-                let p1 = Self::get_point(sketch, id_map, points[0]);
-                let p2 = Self::get_point(sketch, id_map, points[1]);
-                if let (Some(pos1), Some(pos2)) = (p1, p2) {
-                    let current_dist = ((pos2[0] - pos1[0]).powi(2) + (pos2[1] - pos1[1]).powi(2)).sqrt();
-                    (current_dist - value).abs()
-                } else { 0.0 }
-            },
-            SketchConstraint::HorizontalDistance { points, value, .. } => {
-                let p1 = Self::get_point(sketch, id_map, points[0]);
-                let p2 = Self::get_point(sketch, id_map, points[1]);
-                if let (Some(pos1), Some(pos2)) = (p1, p2) {
-                    let current_dist = (pos2[0] - pos1[0]).abs();
-                    (current_dist - value).abs()
-                } else { 0.0 }
-            },
-            SketchConstraint::VerticalDistance { points, value, .. } => {
-                let p1 = Self::get_point(sketch, id_map, points[0]);
-                let p2 = Self::get_point(sketch, id_map, points[1]);
-                if let (Some(pos1), Some(pos2)) = (p1, p2) {
-                    let current_dist = (pos2[1] - pos1[1]).abs();
-                    (current_dist - value).abs()
-                } else { 0.0 }
+            SketchConstraint::Distance { value, .. }
+            | SketchConstraint::HorizontalDistance { value, .. }
+            | SketchConstraint::VerticalDistance { value, .. }
+            | SketchConstraint::DistancePointLine { value, .. }
+            | SketchConstraint::DistancePointCircle { value, .. }
+            | SketchConstraint::Radius { value, .. }
+            | SketchConstraint::DistanceParallelLines { value, .. } => {
+                Self::measure_constraint(sketch, id_map, constraint).map_or(0.0, |current| (current - value).abs())
             },
             SketchConstraint::Parallel { lines } => {
                 Self::get_parallel_error(sketch, id_map, lines[0], lines[1])
@@ -2010,6 +2441,13 @@ impl SketchSolver {
                     _ => 0.0
                 }
             },
+            SketchConstraint::EqualGroup { entities } => match Self::equal_group_sizes(sketch, id_map, entities) {
+                Some(sizes) => {
+                    let average = sizes.iter().sum::<f64>() / sizes.len() as f64;
+                    sizes.iter().map(|size| (size - average).abs()).fold(0.0, f64::max)
+                },
+                None => 0.0,
+            },
             SketchConstraint::Tangent { entities } => {
                 // Simplified: just check line-circle tangency
                 let g1 = Self::get_geometry(sketch, id_map, entities[0]);
@@ -2031,80 +2469,22 @@ impl SketchSolver {
                     _ => 0.0
                 }
             },
+            SketchConstraint::TangentAtPoint { a, b } => {
+                match (Self::endpoint_tangent(sketch, id_map, *a), Self::endpoint_tangent(sketch, id_map, *b)) {
+                    (Some(ta), Some(tb)) => (ta[0] * tb[1] - ta[1] * tb[0]).abs(),
+                    _ => 0.0
+                }
+            },
             SketchConstraint::Fix { point, position } => {
                 let p = Self::get_point(sketch, id_map, *point);
                 if let Some(pos) = p {
                     ((pos[0] - position[0]).powi(2) + (pos[1] - position[1]).powi(2)).sqrt()
                 } else { 0.0 }
             },
-            SketchConstraint::DistancePointLine { point, line, value, .. } => {
-                let p = Self::get_point(sketch, id_map, *point);
-                let l_geo = Self::get_geometry(sketch, id_map, *line);
-                if let (Some(pos), Some(SketchGeometry::Line { start, end })) = (p, l_geo) {
-                     let lx = end[0] - start[0];
-                     let ly = end[1] - start[1];
-                     let len = (lx*lx + ly*ly).sqrt();
-                     if len > 1e-9 {
-                         let nx = -ly / len;
-                         let ny = lx / len;
-                         let v_x = pos[0] - start[0];
-                         let v_y = pos[1] - start[1];
-                         let dist = (v_x * nx + v_y * ny).abs();
-                         (dist - value).abs()
-                     } else { 0.0 }
-                } else { 0.0 }
-            },
-            SketchConstraint::Angle { lines, value, .. } => {
-                // Calculate angle between two lines
-                let geo1 = Self::get_geometry(sketch, id_map, lines[0]);
-                let geo2 = Self::get_geometry(sketch, id_map, lines[1]);
-                
-                if let (Some(SketchGeometry::Line { start: s1, end: e1 }), Some(SketchGeometry::Line { start: s2, end: e2 })) = (geo1, geo2) {
-                    // Determine connectivity to normalize vectors (outward from vertex)
-                    let d_ss = (s1[0]-s2[0]).powi(2) + (s1[1]-s2[1]).powi(2);
-                    let d_se = (s1[0]-e2[0]).powi(2) + (s1[1]-e2[1]).powi(2);
-                    let d_es = (e1[0]-s2[0]).powi(2) + (e1[1]-s2[1]).powi(2);
-                    let d_ee = (e1[0]-e2[0]).powi(2) + (e1[1]-e2[1]).powi(2);
-                    
-                    let min_dist = d_ss.min(d_se).min(d_es).min(d_ee);
-                    
-                    let v1_raw = [e1[0] - s1[0], e1[1] - s1[1]];
-                    let v2_raw = [e2[0] - s2[0], e2[1] - s2[1]];
-                    
-                    // Determine if we need to flip vectors to point OUTWARD from vertex
-                    let (v1, v2) = if (min_dist - d_ss).abs() < 1e-9 {
-                        // Tail-Tail: Both Outward
-                        (v1_raw, v2_raw)
-                    } else if (min_dist - d_ee).abs() < 1e-9 {
-                        // Head-Head: Both Inward -> Flip Both
-                        ([-v1_raw[0], -v1_raw[1]], [-v2_raw[0], -v2_raw[1]])
-                    } else if (min_dist - d_es).abs() < 1e-9 {
-                        // Head-Tail (L1 End near L2 Start): L1 Inward -> Flip L1
-                        ([-v1_raw[0], -v1_raw[1]], v2_raw)
-                    } else {
-                        // Tail-Head (L1 Start near L2 End): L2 Inward -> Flip L2
-                        (v1_raw, [-v2_raw[0], -v2_raw[1]])
-                    };
-                    
-                    let len1 = (v1[0]*v1[0] + v1[1]*v1[1]).sqrt();
-                    let len2 = (v2[0]*v2[0] + v2[1]*v2[1]).sqrt();
-                    
-                    if len1 > 1e-9 && len2 > 1e-9 {
-                        let n1 = [v1[0]/len1, v1[1]/len1];
-                        let n2 = [v2[0]/len2, v2[1]/len2];
-                        let dot = n1[0]*n2[0] + n1[1]*n2[1];
-                        let current_angle = dot.clamp(-1.0, 1.0).acos();
-                        (current_angle - value).abs()
-                    } else { 0.0 }
-                } else { 0.0 }
-            },
-            SketchConstraint::Radius { entity, value, .. } => {
-                let geo = Self::get_geometry(sketch, id_map, *entity);
-                match geo {
-                    Some(SketchGeometry::Circle { radius, .. }) => (radius - value).abs(),
-                    Some(SketchGeometry::Arc { radius, .. }) => (radius - value).abs(),
-                    _ => 0.0
-                }
+            SketchConstraint::Angle { lines, value, unit, .. } => {
+                Self::angle_directions(sketch, id_map, *lines)
+                    .map(|(n1, n2, _)| angle_difference(signed_angle(n1, n2), unit.to_radians(*value)).abs())
+                    .unwrap_or(0.0)
             },
             SketchConstraint::Symmetric { p1, p2, axis } => {
                 let pos1 = Self::get_point(sketch, id_map, *p1);
@@ -2138,32 +2518,155 @@ impl SketchSolver {
                      } else { 0.0 }
                 } else { 0.0 }
             },
-            SketchConstraint::DistanceParallelLines { lines, value, .. } => {
-                let l1_geo = Self::get_geometry_copy(sketch, id_map, lines[0]);
-                let l2_geo = Self::get_geometry_copy(sketch, id_map, lines[1]);
-                
-                if let (Some(SketchGeometry::Line { start: s1, end: e1 }), 
-                        Some(SketchGeometry::Line { start: s2, end: e2 })) = (l1_geo, l2_geo) {
-                    let dx1 = e1[0] - s1[0];
-                    let dy1 = e1[1] - s1[1];
-                    let len1 = (dx1 * dx1 + dy1 * dy1).sqrt();
-                    
-                    if len1 > 1e-9 {
-                        let nx = -dy1 / len1;
-                        let ny = dx1 / len1;
-                        let l2_mid = [(s2[0] + e2[0]) / 2.0, (s2[1] + e2[1]) / 2.0];
-                        let vx = l2_mid[0] - s1[0];
-                        let vy = l2_mid[1] - s1[1];
-                        let current_dist = (vx * nx + vy * ny).abs();
-                        (current_dist - value).abs()
+            SketchConstraint::ThroughOrigin { line } => {
+                if let Some(SketchGeometry::Line { start, end }) = Self::get_geometry(sketch, id_map, *line) {
+                    let lx = end[0] - start[0];
+                    let ly = end[1] - start[1];
+                    let len = (lx * lx + ly * ly).sqrt();
+                    if len > 1e-9 {
+                        // Distance from the origin to the line
+                        (start[0] * ly - start[1] * lx).abs() / len
                     } else { 0.0 }
                 } else { 0.0 }
             }
+            SketchConstraint::OffsetFromExternal { entity, external, distance } => {
+                Self::offset_from_external_shift(sketch, id_map, *entity, *external, *distance)
+                    .map_or(0.0, |shift| shift[0].hypot(shift[1]))
+            }
+        }
+    }
+    
+    /// Measured value of each dimensional constraint (distances, angles and
+    /// radii) as `(constraint index, value)`, in the constraint's own units.
+    /// Dimensions show these rather than their targets, so a sketch that
+    /// hasn't been solved, or can't be, reports where its geometry really is.
+    pub fn constraint_current_values(sketch: &Sketch) -> Vec<(usize, f64)> {
+        let id_map: HashMap<EntityId, usize> = sketch.entities.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+        sketch.constraints.iter().enumerate()
+            .filter_map(|(i, entry)| Some((i, Self::measure_constraint(sketch, &id_map, &entry.constraint)?)))
+            .collect()
+    }
+
+    /// What a dimensional constraint measures on the current geometry; None
+    /// for other constraints or when its geometry is missing. Angles run
+    /// counter-clockwise from the first line, within one turn.
+    pub(crate) fn measure_constraint(sketch: &Sketch, id_map: &HashMap<EntityId, usize>, constraint: &SketchConstraint) -> Option<f64> {
+        let points = |points: &[ConstraintPoint; 2]| {
+            Some((Self::get_point(sketch, id_map, points[0])?, Self::get_point(sketch, id_map, points[1])?))
+        };
+        match constraint {
+            SketchConstraint::Distance { points: ends, .. } => {
+                let (pos1, pos2) = points(ends)?;
+                Some(((pos2[0] - pos1[0]).powi(2) + (pos2[1] - pos1[1]).powi(2)).sqrt())
+            },
+            SketchConstraint::HorizontalDistance { points: ends, .. } => {
+                let (pos1, pos2) = points(ends)?;
+                Some((pos2[0] - pos1[0]).abs())
+            },
+            SketchConstraint::VerticalDistance { points: ends, .. } => {
+                let (pos1, pos2) = points(ends)?;
+                Some((pos2[1] - pos1[1]).abs())
+            },
+            SketchConstraint::DistancePointLine { point, line, .. } => {
+                let pos = Self::get_point(sketch, id_map, *point)?;
+                let Some(SketchGeometry::Line { start, end }) = Self::get_geometry(sketch, id_map, *line) else { return None };
+                let lx = end[0] - start[0];
+                let ly = end[1] - start[1];
+                let len = (lx*lx + ly*ly).sqrt();
+                if len <= 1e-9 {
+                    return None;
+                }
+                Some(((pos[0] - start[0]) * -ly / len + (pos[1] - start[1]) * lx / len).abs())
+            },
+            SketchConstraint::DistancePointCircle { point, circle, .. } => {
+                Self::point_circle_offset(sketch, id_map, *point, *circle)
+                    .map(|(_, _, radius, distance)| (distance - radius).abs())
+            },
+            SketchConstraint::Angle { lines, unit, .. } => {
+                Self::angle_directions(sketch, id_map, *lines).map(|(n1, n2, _)| unit.from_radians(signed_angle(n1, n2).rem_euclid(std::f64::consts::TAU)))
+            },
+            SketchConstraint::Radius { entity, .. } => match Self::get_geometry(sketch, id_map, *entity) {
+                Some(SketchGeometry::Circle { radius, .. }) | Some(SketchGeometry::Arc { radius, .. }) => Some(*radius),
+                _ => None,
+            },
+            SketchConstraint::DistanceParallelLines { lines, .. } => {
+                let l1_geo = Self::get_geometry_copy(sketch, id_map, lines[0]);
+                let l2_geo = Self::get_geometry_copy(sketch, id_map, lines[1]);
+                let (Some(SketchGeometry::Line { start: s1, end: e1 }), Some(SketchGeometry::Line { start: s2, end: e2 })) = (l1_geo, l2_geo) else {
+                    return None;
+                };
+                let dx1 = e1[0] - s1[0];
+                let dy1 = e1[1] - s1[1];
+                let len1 = (dx1 * dx1 + dy1 * dy1).sqrt();
+                if len1 <= 1e-9 {
+                    return None;
+                }
+                // Offset of the second line's midpoint from the first
+                let l2_mid = [(s2[0] + e2[0]) / 2.0, (s2[1] + e2[1]) / 2.0];
+                Some(((l2_mid[0] - s1[0]) * -dy1 / len1 + (l2_mid[1] - s1[1]) * dx1 / len1).abs())
+            },
+            _ => None,
+        }
+    }
+
+    /// Export the entity/constraint graph of a sketch (nodes = entities, edges = constraints)
+    pub fn constraint_graph(sketch: &Sketch) -> ConstraintGraph {
+        let nodes = sketch.entities.iter().map(|e| ConstraintGraphNode {
+            id: e.id,
+            kind: match e.geometry {
+                SketchGeometry::Line { .. } => "Line",
+                SketchGeometry::Circle { .. } => "Circle",
+                SketchGeometry::Arc { .. } => "Arc",
+                SketchGeometry::Point { .. } => "Point",
+                SketchGeometry::Ellipse { .. } => "Ellipse",
+                SketchGeometry::EllipseArc { .. } => "EllipseArc",
+            }.to_string(),
+            is_construction: e.is_construction,
+        }).collect();
+
+        let edges = sketch.constraints.iter().enumerate().map(|(i, entry)| {
+            let mut entities = Self::get_constraint_entities(&entry.constraint);
+            let mut seen = std::collections::HashSet::new();
+            entities.retain(|id| seen.insert(*id));
+            ConstraintGraphEdge {
+                constraint_index: i,
+                kind: Self::constraint_kind(&entry.constraint).to_string(),
+                entities,
+                suppressed: entry.suppressed,
+            }
+        }).collect();
+
+        ConstraintGraph { nodes, edges }
+    }
+
+    fn constraint_kind(constraint: &SketchConstraint) -> &'static str {
+        match constraint {
+            SketchConstraint::Coincident { .. } => "Coincident",
+            SketchConstraint::Horizontal { .. } => "Horizontal",
+            SketchConstraint::Vertical { .. } => "Vertical",
+            SketchConstraint::Distance { .. } => "Distance",
+            SketchConstraint::HorizontalDistance { .. } => "HorizontalDistance",
+            SketchConstraint::VerticalDistance { .. } => "VerticalDistance",
+            SketchConstraint::Angle { .. } => "Angle",
+            SketchConstraint::Radius { .. } => "Radius",
+            SketchConstraint::Parallel { .. } => "Parallel",
+            SketchConstraint::Perpendicular { .. } => "Perpendicular",
+            SketchConstraint::Tangent { .. } => "Tangent",
+            SketchConstraint::TangentAtPoint { .. } => "TangentAtPoint",
+            SketchConstraint::Equal { .. } => "Equal",
+            SketchConstraint::EqualGroup { .. } => "EqualGroup",
+            SketchConstraint::Symmetric { .. } => "Symmetric",
+            SketchConstraint::Fix { .. } => "Fix",
+            SketchConstraint::DistancePointLine { .. } => "DistancePointLine",
+            SketchConstraint::DistancePointCircle { .. } => "DistancePointCircle",
+            SketchConstraint::DistanceParallelLines { .. } => "DistanceParallelLines",
+            SketchConstraint::ThroughOrigin { .. } => "ThroughOrigin",
+            SketchConstraint::OffsetFromExternal { .. } => "OffsetFromExternal",
         }
     }
     
     /// Get all entity IDs referenced by a constraint
-    fn get_constraint_entities(constraint: &SketchConstraint) -> Vec<EntityId> {
+    pub(crate) fn get_constraint_entities(constraint: &SketchConstraint) -> Vec<EntityId> {
         match constraint {
             SketchConstraint::Coincident { points } => vec![points[0].id, points[1].id],
             SketchConstraint::Horizontal { entity } => vec![*entity],
@@ -2176,11 +2679,16 @@ impl SketchSolver {
             SketchConstraint::Parallel { lines } => vec![lines[0], lines[1]],
             SketchConstraint::Perpendicular { lines } => vec![lines[0], lines[1]],
             SketchConstraint::Tangent { entities } => vec![entities[0], entities[1]],
+            SketchConstraint::TangentAtPoint { a, b } => vec![a.id, b.id],
             SketchConstraint::Equal { entities } => vec![entities[0], entities[1]],
+            SketchConstraint::EqualGroup { entities } => entities.clone(),
             SketchConstraint::Radius { entity, .. } => vec![*entity],
             SketchConstraint::Symmetric { p1, p2, axis } => vec![p1.id, p2.id, *axis],
             SketchConstraint::DistancePointLine { point, line, .. } => vec![point.id, *line],
+            SketchConstraint::DistancePointCircle { point, circle, .. } => vec![point.id, *circle],
             SketchConstraint::DistanceParallelLines { lines, .. } => vec![lines[0], lines[1]],
+            SketchConstraint::ThroughOrigin { line } => vec![*line],
+            SketchConstraint::OffsetFromExternal { entity, external, .. } => vec![*entity, *external],
         }
     }
     
@@ -2221,8 +2729,12 @@ impl SketchSolver {
     // ... helper methods ...
 
     fn get_point(sketch: &Sketch, map: &HashMap<EntityId, usize>, cp: ConstraintPoint) -> Option<[f64; 2]> {
-        if cp.id == EntityId::from_uuid(uuid::Uuid::nil()) {
-            return Some([0.0, 0.0]);
+        // Origin, or an axis: start at the origin, end one unit along it
+        if let Some(reference) = ReferenceEntity::from_id(cp.id) {
+            return match reference.geometry() {
+                SketchGeometry::Line { start, end } => Some(if cp.index == 0 { *start } else { *end }),
+                _ => Some([0.0, 0.0]),
+            };
         }
         
         if let Some(idx) = map.get(&cp.id) {
@@ -2261,6 +2773,14 @@ impl SketchSolver {
                         _ => None,
                     }
                 },
+                SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+                    match cp.index {
+                        0 => Some(*center),
+                        1 => Some(ellipse_point(*center, *semi_major, *semi_minor, *rotation, *start_angle)),
+                        2 => Some(ellipse_point(*center, *semi_major, *semi_minor, *rotation, *end_angle)),
+                        _ => None,
+                    }
+                },
             }
         } else {
             None
@@ -2268,10 +2788,8 @@ impl SketchSolver {
     }
 
     fn get_line_vector(sketch: &Sketch, map: &HashMap<EntityId, usize>, id: EntityId) -> Option<[f64; 2]> {
-        if let Some(idx) = map.get(&id) {
-             if let SketchGeometry::Line { start, end } = sketch.entities[*idx].geometry {
-                 return Some([end[0] - start[0], end[1] - start[1]]);
-             }
+        if let Some(SketchGeometry::Line { start, end }) = Self::get_geometry(sketch, map, id) {
+            return Some([end[0] - start[0], end[1] - start[1]]);
         }
         None
     }
@@ -2280,17 +2798,13 @@ impl SketchSolver {
         if let Some(idx) = map.get(&id) {
             Some(&sketch.entities[*idx].geometry)
         } else {
-            None
+            ReferenceEntity::from_id(id).map(ReferenceEntity::geometry)
         }
     }
     
     // Non-borrowing copy for matching
     fn get_geometry_copy(sketch: &Sketch, map: &HashMap<EntityId, usize>, id: EntityId) -> Option<SketchGeometry> {
-        if let Some(idx) = map.get(&id) {
-            Some(sketch.entities[*idx].geometry.clone())
-        } else {
-            None
-        }
+        Self::get_geometry(sketch, map, id).cloned()
     }
 
     fn set_line_length(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, id: EntityId, new_len: f64) {
@@ -2312,6 +2826,23 @@ impl SketchSolver {
         }
     }
 
+    /// Lengths of an `EqualGroup`'s members if they are all lines, radii if they
+    /// are all circles; None for mixed, missing or empty groups
+    fn equal_group_sizes(sketch: &Sketch, map: &HashMap<EntityId, usize>, entities: &[EntityId]) -> Option<Vec<f64>> {
+        let geometries: Vec<&SketchGeometry> = entities.iter()
+            .map(|id| Self::get_geometry(sketch, map, *id))
+            .collect::<Option<_>>()?;
+        let lengths: Option<Vec<f64>> = geometries.iter().map(|g| match g {
+            SketchGeometry::Line { start, end } => Some((end[0] - start[0]).hypot(end[1] - start[1])),
+            _ => None,
+        }).collect();
+        let radii: Option<Vec<f64>> = geometries.iter().map(|g| match g {
+            SketchGeometry::Circle { radius, .. } => Some(*radius),
+            _ => None,
+        }).collect();
+        lengths.or(radii).filter(|sizes| !sizes.is_empty())
+    }
+
     fn set_circle_radius(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, id: EntityId, new_r: f64) {
         if let Some(idx) = map.get(&id) {
             if let SketchGeometry::Circle { radius, .. } = &mut sketch.entities[*idx].geometry {
@@ -2328,6 +2859,91 @@ impl SketchSolver {
         }
     }
 
+    /// A point, the center and radius of a circle or arc, and the point's
+    /// distance from the center
+    fn point_circle_offset(sketch: &Sketch, map: &HashMap<EntityId, usize>, point: ConstraintPoint, circle: EntityId) -> Option<([f64; 2], [f64; 2], f64, f64)> {
+        let pos = Self::get_point(sketch, map, point)?;
+        let (center, radius) = match Self::get_geometry(sketch, map, circle)? {
+            SketchGeometry::Circle { center, radius } | SketchGeometry::Arc { center, radius, .. } => (*center, *radius),
+            _ => return None,
+        };
+        let distance = ((pos[0] - center[0]).powi(2) + (pos[1] - center[1]).powi(2)).sqrt();
+        Some((pos, center, radius, distance))
+    }
+
+    /// Move `point` radially so it is `value` from the circle's nearest edge,
+    /// staying on the side of the edge it is on. The center takes a small
+    /// share of the move, which keeps chains of these stable.
+    fn solve_distance_point_circle(
+        sketch: &mut Sketch,
+        map: &HashMap<EntityId, usize>,
+        point: ConstraintPoint,
+        circle: EntityId,
+        value: f64,
+        epsilon: f64,
+        max_error: &mut f64,
+    ) {
+        const CENTER_SHARE: f64 = 0.1;
+        let Some((pos, center, radius, distance)) = Self::point_circle_offset(sketch, map, point, circle) else { return };
+        let error = ((distance - radius).abs() - value).abs();
+        if error > *max_error { *max_error = error; }
+        if error <= epsilon {
+            return;
+        }
+
+        // Outside stays outside; inside stays inside unless the circle is too small
+        let target = if distance >= radius || value > radius { radius + value } else { radius - value };
+        let direction = if distance > epsilon {
+            [(pos[0] - center[0]) / distance, (pos[1] - center[1]) / distance]
+        } else {
+            [1.0, 0.0]
+        };
+        let shift = target - distance;
+        let (s_point, s_center) = if Self::is_fixed(sketch, point.id) || Self::is_fixed(sketch, circle) {
+            Self::correction_shares(sketch, point.id, circle)
+        } else {
+            (1.0 - CENTER_SHARE, CENTER_SHARE)
+        };
+        Self::set_point(sketch, map, point, [pos[0] + direction[0] * shift * s_point, pos[1] + direction[1] * shift * s_point]);
+        let center_point = ConstraintPoint { id: circle, index: 0 };
+        Self::set_point(sketch, map, center_point, [center[0] - direction[0] * shift * s_center, center[1] - direction[1] * shift * s_center]);
+    }
+
+    /// Unit directions of an Angle constraint's lines, each pointing away from
+    /// the corner where they meet (their closest pair of endpoints), and
+    /// whether the second line runs towards the corner
+    fn angle_directions(sketch: &Sketch, map: &HashMap<EntityId, usize>, lines: [EntityId; 2]) -> Option<([f64; 2], [f64; 2], bool)> {
+        let (Some(SketchGeometry::Line { start: s1, end: e1 }), Some(SketchGeometry::Line { start: s2, end: e2 })) =
+            (Self::get_geometry(sketch, map, lines[0]), Self::get_geometry(sketch, map, lines[1])) else { return None };
+        let dist = |a: &[f64; 2], b: &[f64; 2]| (a[0] - b[0]).hypot(a[1] - b[1]);
+        // Which end of each line is at the corner
+        let (flip1, flip2) = [(false, false), (false, true), (true, false), (true, true)].into_iter()
+            .min_by(|&(f1, f2), &(g1, g2)| {
+                let corner = |flip1: bool, flip2: bool| dist(if flip1 { e1 } else { s1 }, if flip2 { e2 } else { s2 });
+                corner(f1, f2).total_cmp(&corner(g1, g2))
+            })?;
+        let unit = |start: &[f64; 2], end: &[f64; 2], flip: bool| {
+            let length = dist(start, end);
+            let sign = if flip { -1.0 } else { 1.0 };
+            (length > 1e-9).then(|| [sign * (end[0] - start[0]) / length, sign * (end[1] - start[1]) / length])
+        };
+        Some((unit(s1, e1, flip1)?, unit(s2, e2, flip2)?, flip2))
+    }
+
+    /// Turn the second line so the angle from the first, counter-clockwise
+    /// between their corner-outward directions, is `target` (radians, any turn)
+    fn solve_angle(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, lines: [EntityId; 2], target: f64, tolerance: f64, max_error: &mut f64) {
+        let Some((n1, n2, flip2)) = Self::angle_directions(sketch, map, lines) else { return };
+        let angle_error = angle_difference(signed_angle(n1, n2), target).abs();
+        if angle_error > *max_error { *max_error = angle_error; }
+        if angle_error > tolerance {
+            let (sin, cos) = target.sin_cos();
+            let outward = [n1[0] * cos - n1[1] * sin, n1[0] * sin + n1[1] * cos];
+            let direction = if flip2 { [-outward[0], -outward[1]] } else { outward };
+            Self::rotate_line_to_dir(sketch, map, lines[1], direction);
+        }
+    }
+
     fn solve_line_circle_tangent(
         sketch: &mut Sketch, 
         map: &HashMap<EntityId, usize>, 
@@ -2381,7 +2997,156 @@ impl SketchSolver {
         }
     }
 
+    /// Unit tangent of a line, or an arc running counter-clockwise, at one of its endpoints
+    fn endpoint_tangent(sketch: &Sketch, map: &HashMap<EntityId, usize>, cp: ConstraintPoint) -> Option<[f64; 2]> {
+        match Self::get_geometry(sketch, map, cp.id)? {
+            SketchGeometry::Line { start, end } => {
+                let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+                let length = dx.hypot(dy);
+                (length > 1e-9).then(|| [dx / length, dy / length])
+            }
+            SketchGeometry::Arc { start_angle, end_angle, .. } => {
+                let angle = match cp.index {
+                    1 => start_angle,
+                    2 => end_angle,
+                    _ => return None,
+                };
+                Some([-angle.sin(), angle.cos()])
+            }
+            _ => None,
+        }
+    }
+
+    /// Turn a line or arc rigidly by `angle` about its endpoint `cp`, which stays put
+    fn rotate_about_endpoint(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, cp: ConstraintPoint, angle: f64) {
+        if Self::is_fixed(sketch, cp.id) {
+            return;
+        }
+        let (Some(pivot), Some(idx)) = (Self::get_point(sketch, map, cp), map.get(&cp.id)) else { return };
+        let (sin, cos) = angle.sin_cos();
+        let turn = |p: [f64; 2]| {
+            let (dx, dy) = (p[0] - pivot[0], p[1] - pivot[1]);
+            [pivot[0] + dx * cos - dy * sin, pivot[1] + dx * sin + dy * cos]
+        };
+        match &mut sketch.entities[*idx].geometry {
+            SketchGeometry::Line { start, end } => {
+                if cp.index == 0 { *end = turn(*end); } else { *start = turn(*start); }
+            }
+            SketchGeometry::Arc { center, start_angle, end_angle, .. } if cp.index != 0 => {
+                *center = turn(*center);
+                *start_angle += angle;
+                *end_angle += angle;
+            }
+            _ => {}
+        }
+    }
+
+    /// Turn the two entities about their joint until their tangents there
+    /// line up, either way along the line; each turns by its share of the gap
+    fn solve_tangent_at_point(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, a: ConstraintPoint, b: ConstraintPoint, tolerance: f64, max_error: &mut f64) {
+        let (Some(ta), Some(tb)) = (Self::endpoint_tangent(sketch, map, a), Self::endpoint_tangent(sketch, map, b)) else { return };
+        let error = (ta[0] * tb[1] - ta[1] * tb[0]).abs();
+        if error > *max_error { *max_error = error; }
+        if error > tolerance {
+            // From a's tangent to b's, or to its reverse if that is nearer
+            let mut gap = angle_difference(signed_angle(ta, tb), 0.0);
+            if gap > std::f64::consts::FRAC_PI_2 {
+                gap -= std::f64::consts::PI;
+            } else if gap < -std::f64::consts::FRAC_PI_2 {
+                gap += std::f64::consts::PI;
+            }
+            let (sa, sb) = Self::correction_shares(sketch, a.id, b.id);
+            Self::rotate_about_endpoint(sketch, map, a, gap * sa);
+            Self::rotate_about_endpoint(sketch, map, b, -gap * sb);
+        }
+    }
+
+    /// Reference geometry, geometry projected from the model and locked
+    /// entities: constraints measure against them but never move them
+    fn is_fixed(sketch: &Sketch, id: EntityId) -> bool {
+        ReferenceEntity::is_reference(id) || sketch.is_projected(id) || sketch.is_locked(id)
+    }
+
+    /// How a two-entity correction is split: a fixed side takes none of it and
+    /// the other side all of it; otherwise each takes half
+    fn correction_shares(sketch: &Sketch, a: EntityId, b: EntityId) -> (f64, f64) {
+        match (Self::is_fixed(sketch, a), Self::is_fixed(sketch, b)) {
+            (true, false) => (0.0, 1.0),
+            (false, true) => (1.0, 0.0),
+            _ => (0.5, 0.5),
+        }
+    }
+
+    /// Geometry of the projected and locked entities, to put back after
+    /// constraints that edit geometry directly rather than through `set_point`
+    fn fixed_geometry(sketch: &Sketch) -> Vec<(usize, SketchGeometry)> {
+        sketch.entities.iter().enumerate()
+            .filter(|(_, e)| Self::is_fixed(sketch, e.id))
+            .map(|(i, e)| (i, e.geometry.clone()))
+            .collect()
+    }
+
+    fn restore_geometry(sketch: &mut Sketch, saved: &[(usize, SketchGeometry)]) {
+        for (index, geometry) in saved {
+            sketch.entities[*index].geometry = geometry.clone();
+        }
+    }
+
+    /// Point an entity's offset from a line is measured at: a line's midpoint,
+    /// otherwise its center or position
+    fn offset_anchor(sketch: &Sketch, map: &HashMap<EntityId, usize>, id: EntityId) -> Option<[f64; 2]> {
+        match Self::get_geometry(sketch, map, id)? {
+            SketchGeometry::Line { start, end } => Some([(start[0] + end[0]) * 0.5, (start[1] + end[1]) * 0.5]),
+            _ => Self::get_point(sketch, map, ConstraintPoint { id, index: 0 }),
+        }
+    }
+
+    /// Translation that puts `entity` back at `distance` from line `external`,
+    /// on the side it is on
+    fn offset_from_external_shift(
+        sketch: &Sketch,
+        map: &HashMap<EntityId, usize>,
+        entity: EntityId,
+        external: EntityId,
+        distance: f64,
+    ) -> Option<[f64; 2]> {
+        let SketchGeometry::Line { start, end } = Self::get_geometry(sketch, map, external)? else { return None };
+        let anchor = Self::offset_anchor(sketch, map, entity)?;
+        let (lx, ly) = (end[0] - start[0], end[1] - start[1]);
+        let len = lx.hypot(ly);
+        if len < 1e-9 {
+            return None;
+        }
+        let (nx, ny) = (-ly / len, lx / len);
+        let signed_dist = (anchor[0] - start[0]) * nx + (anchor[1] - start[1]) * ny;
+        let target = if signed_dist >= 0.0 { distance } else { -distance };
+        let shift = target - signed_dist;
+        Some([nx * shift, ny * shift])
+    }
+
+    /// Move a whole entity by `d`
+    fn translate_entity(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, id: EntityId, d: [f64; 2]) {
+        let Some(geometry) = Self::get_geometry_copy(sketch, map, id) else { return };
+        let moved = |p: [f64; 2]| [p[0] + d[0], p[1] + d[1]];
+        match geometry {
+            SketchGeometry::Line { start, end } => {
+                Self::set_point(sketch, map, ConstraintPoint { id, index: 0 }, moved(start));
+                Self::set_point(sketch, map, ConstraintPoint { id, index: 1 }, moved(end));
+            }
+            SketchGeometry::Point { pos } => Self::set_point(sketch, map, ConstraintPoint { id, index: 0 }, moved(pos)),
+            SketchGeometry::Circle { center, .. }
+            | SketchGeometry::Arc { center, .. }
+            | SketchGeometry::Ellipse { center, .. }
+            | SketchGeometry::EllipseArc { center, .. } => {
+                Self::set_point(sketch, map, ConstraintPoint { id, index: 0 }, moved(center));
+            }
+        }
+    }
+
     fn rotate_line_to_dir(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, id: EntityId, dir: [f64; 2]) {
+        if Self::is_fixed(sketch, id) {
+            return;
+        }
         if let Some(idx) = map.get(&id) {
             if let SketchGeometry::Line { start, end } = &mut sketch.entities[*idx].geometry {
                 let mid_x = (start[0] + end[0]) * 0.5;
@@ -2401,7 +3166,7 @@ impl SketchSolver {
     }
 
     fn set_point(sketch: &mut Sketch, map: &HashMap<EntityId, usize>, cp: ConstraintPoint, new_pos: [f64; 2]) {
-        if cp.id == EntityId::from_uuid(uuid::Uuid::nil()) {
+        if Self::is_fixed(sketch, cp.id) {
             return;
         }
 
@@ -2449,15 +3214,49 @@ impl SketchSolver {
                          _ => {}
                      }
                 },
+                SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+                     // Endpoints slide along the ellipse, like an arc's along its circle
+                     match cp.index {
+                         0 => *center = new_pos,
+                         1 => *start_angle = ellipse_angle(*center, *semi_major, *semi_minor, *rotation, new_pos),
+                         2 => *end_angle = ellipse_angle(*center, *semi_major, *semi_minor, *rotation, new_pos),
+                         _ => {}
+                     }
+                },
             }
         }
     }
 }
 
+/// `angle` wrapped into [0, 2π)
+pub fn normalize_angle(angle: f64) -> f64 {
+    let wrapped = angle.rem_euclid(std::f64::consts::TAU);
+    if wrapped >= std::f64::consts::TAU { 0.0 } else { wrapped }
+}
+
+/// Counter-clockwise angle from direction `a` to `b`, in [0, 2π)
+fn signed_angle(a: [f64; 2], b: [f64; 2]) -> f64 {
+    normalize_angle((a[0] * b[1] - a[1] * b[0]).atan2(a[0] * b[0] + a[1] * b[1]))
+}
+
+/// `a - b` the short way round, in (-π, π]
+pub(crate) fn angle_difference(a: f64, b: f64) -> f64 {
+    let difference = normalize_angle(a - b);
+    if difference > std::f64::consts::PI { difference - std::f64::consts::TAU } else { difference }
+}
+
+/// Unit direction `current` turned towards `target` by `damping` of the
+/// short angle between them
+fn damped_direction(current: [f64; 2], target: [f64; 2], damping: f64) -> [f64; 2] {
+    let (sin, cos) = (angle_difference(signed_angle(current, target), 0.0) * damping).sin_cos();
+    [current[0] * cos - current[1] * sin, current[0] * sin + current[1] * cos]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::sketch::types::{SketchPlane, SketchGeometry, SketchConstraint, ConstraintPoint};
+    use crate::sketch::types::{SketchPlane, SketchGeometry, SketchConstraint, ConstraintPoint, DimensionStyle};
+    use crate::variables::AngleUnit;
 
     #[test]
     fn test_rectangle_constraints() {
@@ -2523,6 +3322,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_constraint_graph_of_rectangle() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]];
+        let lines: Vec<EntityId> = (0..4)
+            .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] }))
+            .collect();
+        for i in 0..4 {
+            sketch.add_constraint(SketchConstraint::Coincident { points: [
+                ConstraintPoint { id: lines[i], index: 1 },
+                ConstraintPoint { id: lines[(i + 1) % 4], index: 0 },
+            ]});
+        }
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: lines[0] });
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: lines[2] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: lines[1] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: lines[3] });
+
+        let graph = SketchSolver::constraint_graph(&sketch);
+        assert_eq!(graph.nodes.len(), 4);
+        assert!(graph.nodes.iter().all(|n| n.kind == "Line"));
+        assert_eq!(graph.edges.len(), 8);
+        assert_eq!(graph.edges.iter().filter(|e| e.kind == "Coincident").count(), 4);
+        assert_eq!(graph.edges[0].entities, vec![lines[0], lines[1]]);
+        assert_eq!(graph.edges[4].entities, vec![lines[0]]);
+
+        // Round-trips through JSON for the debug endpoint
+        let json = serde_json::to_string(&graph).unwrap();
+        let parsed: ConstraintGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.edges[7].kind, "Vertical");
+    }
+
     #[test]
     fn test_parallel_perpendicular() {
         let mut sketch = Sketch::new(SketchPlane::default());
@@ -2554,6 +3385,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_perpendicular_chain_converges() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        // Each line about 10 degrees off square with the one before
+        let lines: Vec<EntityId> = [0.0_f64, 100.0, 170.0, 280.0, 10.0].iter().enumerate().map(|(i, degrees)| {
+            let (sin, cos) = degrees.to_radians().sin_cos();
+            let start = [i as f64 * 20.0, 0.0];
+            sketch.add_entity(SketchGeometry::Line { start, end: [start[0] + 10.0 * cos, start[1] + 10.0 * sin] })
+        }).collect();
+        for pair in lines.windows(2) {
+            sketch.constraints.push(SketchConstraint::Perpendicular { lines: [pair[0], pair[1]] }.into());
+        }
+
+        let (mut damped, stuck) = (sketch.clone(), sketch.clone());
+        let result = SketchSolver::solve_with_result(&mut sketch);
+        assert!(result.converged, "Stopped after {} iterations at {}", result.iterations, result.max_error);
+        let config = SolverConfig { rotation_damping: 0.7, ..Default::default() };
+        let damped_result = SketchSolver::solve_with_config(&mut damped, &config);
+        assert!(damped_result.converged);
+        assert!(result.iterations < damped_result.iterations, "Damping only slows the split turns down");
+
+        // Every entry point solves with the config it is given; undamped by zero, nothing turns
+        let frozen = SolverConfig { rotation_damping: 0.0, ..Default::default() };
+        assert!(!SketchSolver::solve_with_config(&mut stuck.clone(), &frozen).converged);
+        assert!(!SketchSolver::solve_deferred_with_config(&mut stuck.clone(), &frozen).converged);
+        assert!(!SketchSolver::solve_relaxed_with_config(&mut stuck.clone(), &frozen).base_result.converged);
+        assert!(SketchSolver::solve_relaxed(&mut stuck.clone()).base_result.converged);
+        let mut open = stuck.clone();
+        let last = open.constraints.pop().unwrap().constraint;
+        assert!(!SketchSolver::preview_add_constraint_with_config(&open, &last, &frozen).converged);
+        assert!(SketchSolver::preview_add_constraint(&open, &last).converged);
+        let direction = |id: EntityId| match damped.entities.iter().find(|e| e.id == id).unwrap().geometry {
+            SketchGeometry::Line { start, end } => {
+                let length = (end[0] - start[0]).hypot(end[1] - start[1]);
+                [(end[0] - start[0]) / length, (end[1] - start[1]) / length]
+            }
+            _ => unreachable!(),
+        };
+        for pair in lines.windows(2) {
+            let (a, b) = (direction(pair[0]), direction(pair[1]));
+            assert!((a[0] * b[0] + a[1] * b[1]).abs() < 1e-6, "Damped solve leaves the chain square");
+        }
+    }
+
     #[test]
     fn test_equal_length() {
         let mut sketch = Sketch::new(SketchPlane::default());
@@ -2801,6 +3676,7 @@ mod tests {
         sketch.constraints.push(SketchConstraint::Angle { 
             lines: [l1, l2],
             value: target_angle,
+            unit: AngleUnit::Radians,
             style: None
         }.into());
 
@@ -2808,4 +3684,144 @@ mod tests {
         let error = SketchSolver::calculate_constraint_error(&sketch, &id_map, &sketch.constraints[1].constraint);
         assert!(error < 1e-3, "Initial error should be zero for matching geometry (120 deg). Got {}", error);
     }
+    #[test]
+    fn test_ellipse_arc_endpoints_and_dof() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let rotation = std::f64::consts::FRAC_PI_2;
+        let arc = sketch.add_entity(SketchGeometry::EllipseArc {
+            center: [1.0, 1.0], semi_major: 3.0, semi_minor: 1.0, rotation,
+            start_angle: 0.0, end_angle: std::f64::consts::FRAC_PI_2,
+        });
+        assert_eq!(SketchSolver::calculate_dof(&sketch), 7);
+
+        // Rotated a quarter turn: the major axis runs along +Y, the minor along -X
+        let map: HashMap<EntityId, usize> = sketch.entities.iter().enumerate().map(|(i, e)| (e.id, i)).collect();
+        let point = |sketch: &Sketch, index| SketchSolver::get_point(sketch, &map, ConstraintPoint { id: arc, index }).unwrap();
+        let close = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).abs() < 1e-9 && (a[1] - b[1]).abs() < 1e-9;
+        assert!(close(point(&sketch, 0), [1.0, 1.0]));
+        assert!(close(point(&sketch, 1), [1.0, 4.0]), "{:?}", point(&sketch, 1));
+        assert!(close(point(&sketch, 2), [0.0, 1.0]), "{:?}", point(&sketch, 2));
+        assert!(SketchSolver::get_point(&sketch, &map, ConstraintPoint { id: arc, index: 3 }).is_none());
+
+        // Moving an endpoint slides it along the ellipse
+        SketchSolver::set_point(&mut sketch, &map, ConstraintPoint { id: arc, index: 2 }, [1.0, -5.0]);
+        assert!(close(point(&sketch, 2), [1.0, -2.0]), "{:?}", point(&sketch, 2));
+        assert!(close(point(&sketch, 1), [1.0, 4.0]), "The start stays put");
+    }
+
+    #[test]
+    fn test_angle_in_degrees_solves_like_radians() {
+        let solve = |value: f64, unit: AngleUnit| {
+            let mut sketch = Sketch::new(SketchPlane::default());
+            let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+            let l2 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [7.0, 6.0] });
+            sketch.constraints.push(SketchConstraint::Coincident { points: [
+                ConstraintPoint { id: l1, index: 0 },
+                ConstraintPoint { id: l2, index: 0 }
+            ]}.into());
+            sketch.constraints.push(SketchConstraint::Angle { lines: [l1, l2], value, unit, style: None }.into());
+            assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+            sketch.entities.iter().map(|e| e.geometry.clone()).collect::<Vec<_>>()
+        };
+
+        let degrees = solve(90.0, AngleUnit::Degrees);
+        let radians = solve(std::f64::consts::FRAC_PI_2, AngleUnit::Radians);
+        assert_eq!(degrees, radians);
+        if let (SketchGeometry::Line { start: s1, end: e1 }, SketchGeometry::Line { start: s2, end: e2 }) = (&degrees[0], &degrees[1]) {
+            let dot = (e1[0] - s1[0]) * (e2[0] - s2[0]) + (e1[1] - s1[1]) * (e2[1] - s2[1]);
+            assert!(dot.abs() < 1e-3, "Lines should be perpendicular, dot = {}", dot);
+        } else {
+            panic!("Expected two lines");
+        }
+    }
+
+    /// Two lines from the origin, constrained `value` degrees apart; the counter-clockwise
+    /// angle from the first to the second once solved, and whether it converged
+    fn solve_corner(value: f64, second_end: [f64; 2]) -> (f64, bool) {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let l2 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: second_end });
+        sketch.constraints.push(SketchConstraint::Fix { point: ConstraintPoint { id: l1, index: 0 }, position: [0.0, 0.0] }.into());
+        sketch.constraints.push(SketchConstraint::Fix { point: ConstraintPoint { id: l1, index: 1 }, position: [10.0, 0.0] }.into());
+        sketch.constraints.push(SketchConstraint::Coincident { points: [
+            ConstraintPoint { id: l1, index: 0 },
+            ConstraintPoint { id: l2, index: 0 }
+        ]}.into());
+        sketch.constraints.push(SketchConstraint::Angle { lines: [l1, l2], value, unit: AngleUnit::Degrees, style: None }.into());
+        let converged = SketchSolver::solve_with_result(&mut sketch).converged;
+        let SketchGeometry::Line { start, end } = sketch.entities[1].geometry else { unreachable!() };
+        let angle = (end[1] - start[1]).atan2(end[0] - start[0]).to_degrees();
+        (if angle < 0.0 { angle + 360.0 } else { angle }, converged)
+    }
+
+    #[test]
+    fn test_angle_constraint_converges_to_obtuse_angle() {
+        let (angle, converged) = solve_corner(135.0, [7.0, 6.0]);
+        assert!(converged);
+        assert!((angle - 135.0).abs() < 1e-3, "{}", angle);
+
+        // Drawn clockwise of the first line, the second line still turns
+        // counter-clockwise; sketches from before signed angles are migrated
+        // rather than flipped (see the legacy test below)
+        let (angle, converged) = solve_corner(135.0, [7.0, -6.0]);
+        assert!(converged && (angle - 135.0).abs() < 1e-3, "{}", angle);
+    }
+
+    #[test]
+    fn test_legacy_clockwise_angle_loads_without_moving() {
+        // Saved while angles were unsigned: 135° held the second line clockwise
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let end = [-5.0 * std::f64::consts::SQRT_2, -5.0 * std::f64::consts::SQRT_2];
+        let l2 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end });
+        let l3 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [-10.0, 0.0] });
+        sketch.constraints.push(SketchConstraint::Fix { point: ConstraintPoint { id: l1, index: 0 }, position: [0.0, 0.0] }.into());
+        sketch.constraints.push(SketchConstraint::Fix { point: ConstraintPoint { id: l1, index: 1 }, position: [10.0, 0.0] }.into());
+        sketch.constraints.push(SketchConstraint::Angle { lines: [l1, l2], value: 135.0, unit: AngleUnit::Degrees, style: None }.into());
+        // Counter-clockwise already, so left as it was
+        let style = DimensionStyle { expression: Some("3.14159".to_string()), ..DimensionStyle::default() };
+        sketch.constraints.push(SketchConstraint::Angle { lines: [l1, l3], value: 180.0, unit: AngleUnit::Degrees, style: Some(style) }.into());
+        let mut json = serde_json::to_value(&sketch).unwrap();
+        json.as_object_mut().unwrap().remove("signed_angles");
+
+        let mut loaded: Sketch = serde_json::from_value(json).unwrap();
+        assert!(loaded.signed_angles);
+        let SketchConstraint::Angle { value, .. } = loaded.constraints[2].constraint else { unreachable!() };
+        assert!((value - 225.0).abs() < 1e-9, "{}", value);
+        let SketchConstraint::Angle { value, style, .. } = &loaded.constraints[3].constraint else { unreachable!() };
+        assert_eq!((*value, style.as_ref().unwrap().expression.as_deref()), (180.0, Some("3.14159")));
+        assert!(SketchSolver::solve_with_result(&mut loaded).converged);
+        let SketchGeometry::Line { end: solved, .. } = loaded.entities[1].geometry else { unreachable!() };
+        assert!((solved[0] - end[0]).abs() < 1e-6 && (solved[1] - end[1]).abs() < 1e-6, "{:?}", solved);
+
+        // A clockwise angle driven by an expression keeps its sense through it
+        let mut json = serde_json::to_value(&sketch).unwrap();
+        json["constraints"][2]["constraint"]["Angle"]["style"] = serde_json::to_value(DimensionStyle {
+            expression: Some("@corner".to_string()),
+            ..DimensionStyle::default()
+        }).unwrap();
+        json.as_object_mut().unwrap().remove("signed_angles");
+        let loaded: Sketch = serde_json::from_value(json).unwrap();
+        let SketchConstraint::Angle { style, .. } = &loaded.constraints[2].constraint else { unreachable!() };
+        assert_eq!(style.as_ref().unwrap().expression.as_deref(), Some("-(@corner)"));
+
+        // Sketches saved since don't change
+        let saved = serde_json::to_string(&loaded).unwrap();
+        assert_eq!(serde_json::from_str::<Sketch>(&saved).unwrap(), loaded);
+    }
+
+    #[test]
+    fn test_reflex_and_negative_angles_wrap() {
+        let (angle, converged) = solve_corner(270.0, [7.0, 6.0]);
+        assert!(converged, "A reflex angle is reachable");
+        assert!((angle - 270.0).abs() < 1e-3, "{}", angle);
+
+        let (angle, converged) = solve_corner(-90.0, [7.0, 6.0]);
+        assert!(converged && (angle - 270.0).abs() < 1e-3, "-90° is 270°: {}", angle);
+        let (angle, _) = solve_corner(450.0, [7.0, -6.0]);
+        assert!((angle - 90.0).abs() < 1e-3, "450° is 90°: {}", angle);
+
+        assert_eq!(normalize_angle(-std::f64::consts::FRAC_PI_2), 1.5 * std::f64::consts::PI);
+        assert!((angle_difference(0.1, std::f64::consts::TAU - 0.1) - 0.2).abs() < 1e-12);
+    }
 }
@@ -0,0 +1,138 @@
+//! Tests for the redundancy/conflict analysis pass
+
+use crate::sketch::solver::{AnalysisCancel, SketchSolver};
+use crate::sketch::types::{ConstraintPoint, Sketch, SketchConstraint, SketchConstraintEntry, SketchGeometry, SketchPlane};
+
+fn cp(id: crate::topo::EntityId, index: u8) -> ConstraintPoint {
+    ConstraintPoint { id, index }
+}
+
+/// Closed rectangle with every corner joined twice, a repeated Horizontal,
+/// a suppressed repeat, and three points tied in a cycle
+fn redundant_rectangle() -> Sketch {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let corners = [[0.0, 0.0], [10.0, 0.0], [10.0, 5.0], [0.0, 5.0]];
+    let sides: Vec<_> = (0..4)
+        .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] }))
+        .collect();
+    for i in 0..4 {
+        sketch.add_constraint(SketchConstraint::Coincident { points: [cp(sides[i], 1), cp(sides[(i + 1) % 4], 0)] });
+    }
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: sides[0] });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: sides[0] });
+    sketch.constraints.push(SketchConstraintEntry::suppressed(SketchConstraint::Horizontal { entity: sides[0] }));
+    sketch.add_constraint(SketchConstraint::Coincident { points: [cp(sides[1], 0), cp(sides[0], 1)] });
+
+    let points: Vec<_> = (0..3).map(|i| sketch.add_entity(SketchGeometry::Point { pos: [20.0 + i as f64, 0.0] })).collect();
+    for i in 0..3 {
+        sketch.add_constraint(SketchConstraint::Coincident { points: [cp(points[i], 0), cp(points[(i + 1) % 3], 0)] });
+    }
+    sketch
+}
+
+/// Circle with two radii and a line both horizontal and vertical
+fn conflicting() -> Sketch {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let circle = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 });
+    let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [3.0, 4.0] });
+    sketch.add_constraint(SketchConstraint::Radius { entity: circle, value: 10.0, style: None });
+    sketch.add_constraint(SketchConstraint::Radius { entity: circle, value: 20.0, style: None });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: line });
+    sketch.add_constraint(SketchConstraint::Vertical { entity: line });
+    sketch
+}
+
+fn json<T: serde::Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap()
+}
+
+#[test]
+fn test_deferred_analysis_matches_synchronous_solve() {
+    for fixture in [redundant_rectangle(), conflicting()] {
+        let mut sync_sketch = fixture.clone();
+        let sync = SketchSolver::solve_with_result(&mut sync_sketch);
+        assert!(!sync.analysis_pending);
+
+        let mut sketch = fixture.clone();
+        let mut deferred = SketchSolver::solve_deferred(&mut sketch);
+        assert!(deferred.analysis_pending);
+        assert!(deferred.redundant_constraints.is_empty() && deferred.conflicts.is_none());
+        assert_eq!(deferred.converged, sync.converged);
+        assert_eq!(deferred.dof, sync.dof);
+
+        let analysis = SketchSolver::analyze(&sketch, &deferred);
+        assert_eq!(json(&analysis.redundant_constraints), json(&sync.redundant_constraints));
+        assert_eq!(json(&analysis.conflicts), json(&sync.conflicts));
+        assert_eq!(json(&analysis.entity_statuses), json(&sync.entity_statuses));
+
+        deferred.apply_analysis(analysis);
+        assert!(!deferred.analysis_pending);
+        assert_eq!(json(&deferred), json(&sync));
+    }
+}
+
+#[test]
+fn test_redundancy_findings() {
+    let mut sketch = redundant_rectangle();
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    let found: Vec<(usize, Option<usize>)> = result.redundant_constraints.iter()
+        .map(|r| (r.constraint_index, r.duplicates_index))
+        .collect();
+    // Duplicates first, in constraint order; the suppressed repeat (6) is ignored.
+    // #0 and its reversed repeat #7 imply each other; 8-10 form a cycle.
+    assert_eq!(found, vec![(5, Some(4)), (7, Some(0)), (0, None), (8, None), (9, None), (10, None)]);
+    assert_eq!(result.redundant_constraints[1].reason, "Exact duplicate of constraint #0");
+    assert!(result.conflicts.is_none());
+
+    let mut sketch = conflicting();
+    let conflicts = SketchSolver::solve_with_result(&mut sketch).conflicts.expect("Conflicting sketch");
+    assert_eq!(conflicts.unsatisfied_constraints, vec![0]);
+    let pairs: Vec<(usize, usize)> = conflicts.possible_conflicts.iter().map(|(a, b, _)| (*a, *b)).collect();
+    assert_eq!(pairs, vec![(2, 3)]);
+}
+
+#[test]
+fn test_remove_redundant_constraints_keeps_one_of_each() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: line });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: line });
+    assert_eq!(sketch.remove_redundant_constraints(), 1);
+    assert_eq!(sketch.constraints.len(), 1);
+    assert_eq!(sketch.remove_redundant_constraints(), 0);
+
+    // The repeated Horizontal, the reversed repeat of #0 and one link of the cycle
+    let mut sketch = redundant_rectangle();
+    let before = sketch.constraints.len();
+    assert_eq!(sketch.remove_redundant_constraints(), 3);
+    assert_eq!(sketch.constraints.len(), before - 3);
+    let solved = SketchSolver::solve_deferred(&mut sketch);
+    assert!(SketchSolver::analyze(&sketch, &solved).redundant_constraints.is_empty());
+    assert!(sketch.constraints.iter().any(|entry| entry.suppressed), "Suppressed constraints are left alone");
+}
+
+#[test]
+fn test_long_coincident_chain() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let points: Vec<_> = (0..3_000).map(|_| sketch.add_entity(SketchGeometry::Point { pos: [0.0, 0.0] })).collect();
+    for pair in points.windows(2) {
+        sketch.add_constraint(SketchConstraint::Coincident { points: [cp(pair[0], 0), cp(pair[1], 0)] });
+    }
+    let solved = SketchSolver::solve_deferred(&mut sketch);
+    assert!(SketchSolver::analyze(&sketch, &solved).redundant_constraints.is_empty(), "A chain implies nothing twice");
+
+    sketch.add_constraint(SketchConstraint::Coincident { points: [cp(points[points.len() - 1], 0), cp(points[0], 0)] });
+    let solved = SketchSolver::solve_deferred(&mut sketch);
+    assert_eq!(SketchSolver::analyze(&sketch, &solved).redundant_constraints.len(), points.len(), "Closing it makes every link implied");
+}
+
+#[test]
+fn test_cancelled_analysis_gives_up() {
+    let mut sketch = conflicting();
+    let solved = SketchSolver::solve_deferred(&mut sketch);
+    let cancel = AnalysisCancel::default();
+    assert!(SketchSolver::analyze_cancellable(&sketch, &solved, &cancel).is_some());
+    cancel.clone().cancel();
+    assert!(cancel.is_cancelled(), "Clones share the flag");
+    assert!(SketchSolver::analyze_cancellable(&sketch, &solved, &cancel).is_none());
+}
@@ -1,5 +1,9 @@
 use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry, SketchConstraint, ConstraintPoint};
 use crate::sketch::solver::SketchSolver;
+use crate::sketch::dimensions::layout_dimensions;
+use crate::sketch::types::DimensionStyle;
+use crate::units::LengthUnit;
+use crate::variables::AngleUnit;
 
 #[test]
 fn test_solver_horizontal_distance() {
@@ -58,3 +62,47 @@ fn test_solver_vertical_distance() {
         assert!((dy - 15.0).abs() < 1e-4, "Vertical distance should be 15.0, got {}", dy);
     } else { panic!("Wrong geometry"); }
 }
+
+#[test]
+fn test_current_values_measure_the_solved_geometry() {
+    let distance = |sketch: &mut Sketch, a, b| {
+        sketch.constraints.push(SketchConstraint::Distance {
+            points: [ConstraintPoint { id: a, index: 0 }, ConstraintPoint { id: b, index: 0 }],
+            value: 10.0,
+            style: None,
+        }.into());
+    };
+
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let p1 = sketch.add_entity(SketchGeometry::Point { pos: [0.0, 0.0] });
+    let p2 = sketch.add_entity(SketchGeometry::Point { pos: [4.0, 3.0] });
+    sketch.constraints.push(SketchConstraint::Fix { point: ConstraintPoint { id: p1, index: 0 }, position: [0.0, 0.0] }.into());
+    distance(&mut sketch, p1, p2);
+    assert!(SketchSolver::solve(&mut sketch));
+    let values = SketchSolver::constraint_current_values(&sketch);
+    assert_eq!(values.len(), 1, "Only the dimension is measured: {:?}", values);
+    assert_eq!(values[0].0, 1);
+    assert!((values[0].1 - 10.0).abs() < 1e-4, "{:?}", values);
+
+    // Before solving the dimension is unmet and shows the real gap, not its target
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let p1 = sketch.add_entity(SketchGeometry::Point { pos: [0.0, 0.0] });
+    let p2 = sketch.add_entity(SketchGeometry::Point { pos: [4.0, 3.0] });
+    distance(&mut sketch, p1, p2);
+    assert_eq!(SketchSolver::constraint_current_values(&sketch), vec![(0, 5.0)]);
+    // and so does its label
+    let SketchConstraint::Distance { style, .. } = &mut sketch.constraints[0].constraint else { unreachable!() };
+    *style = Some(DimensionStyle::default());
+    let labels = layout_dimensions(&sketch, LengthUnit::Millimeter);
+    assert_eq!(labels[0].text.content, "5.00 mm");
+
+    // Angles measure counter-clockwise from the first line, within a turn:
+    // the second line here is 90° clockwise of the first
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let first = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+    let second = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [0.0, -10.0] });
+    sketch.add_constraint(SketchConstraint::Angle { lines: [first, second], value: 45.0, unit: AngleUnit::Degrees, style: Some(DimensionStyle::default()) });
+    let values = SketchSolver::constraint_current_values(&sketch);
+    assert!((values[0].1 - 270.0).abs() < 1e-9, "{:?}", values);
+    assert_eq!(layout_dimensions(&sketch, LengthUnit::Millimeter)[0].text.content, "270.00°");
+}
@@ -85,3 +85,30 @@ fn test_distance_point_line_move_line() {
         assert!((end[1] - 3.0).abs() < 1e-4);
     }
 }
+
+#[test]
+fn test_through_origin_translates_line() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+
+    // Diagonal line offset from the origin
+    let line_id = sketch.add_entity(SketchGeometry::Line {
+        start: [2.0, 1.0],
+        end: [5.0, 5.0]
+    });
+    sketch.constraints.push(SketchConstraint::ThroughOrigin { line: line_id }.into());
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(result.converged);
+    assert_eq!(result.dof, 3, "A free line has 4 DOF; passing through the origin removes 1");
+
+    if let SketchGeometry::Line { start, end } = &sketch.entities[0].geometry {
+        let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+        // Unchanged direction and length
+        assert!((dx - 3.0).abs() < 1e-6 && (dy - 4.0).abs() < 1e-6, "Direction changed: ({}, {})", dx, dy);
+        // The origin lies on the extension of the line
+        let origin_dist = (start[0] * dy - start[1] * dx).abs() / dx.hypot(dy);
+        assert!(origin_dist < 1e-6, "Origin is {} from the line", origin_dist);
+    } else {
+        panic!("Wrong geometry type");
+    }
+}
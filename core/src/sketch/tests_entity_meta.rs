@@ -0,0 +1,69 @@
+use super::meta::{EntityMetaUpdate, EntityRef};
+use super::solver::SketchSolver;
+use super::types::{ConstraintPoint, Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+
+fn named(name: &str) -> EntityMetaUpdate {
+    EntityMetaUpdate { name: Some(Some(name.to_string())), ..Default::default() }
+}
+
+#[test]
+fn test_locked_line_holds_while_a_distance_moves_the_other_entity() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+    let point = sketch.add_entity(SketchGeometry::Point { pos: [13.0, 0.0] });
+    let lock = EntityMetaUpdate { locked: Some(true), ..Default::default() };
+    assert!(sketch.set_entity_meta(line, &lock).unwrap(), "Locking is reported as a change");
+    assert!(!sketch.set_entity_meta(line, &lock).unwrap(), "Locking again changes nothing");
+    sketch.add_constraint(SketchConstraint::Distance {
+        points: [ConstraintPoint { id: line, index: 1 }, ConstraintPoint { id: point, index: 0 }],
+        value: 5.0,
+        style: None,
+    });
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(result.converged);
+    assert!(matches!(sketch.entities[0].geometry, SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] }));
+    let SketchGeometry::Point { pos } = sketch.entities[1].geometry else { panic!("Expected a point") };
+    assert!((pos[0] - 15.0).abs() < 1e-6 && pos[1].abs() < 1e-6, "Only the point moves: {:?}", pos);
+
+    // The implicit fixes remove the line's 4 DOF without being listed
+    assert_eq!(sketch.constraints.len(), 1);
+    assert_eq!(result.dof, 1);
+    let status = result.entity_statuses.iter().find(|s| s.id == line).unwrap();
+    assert_eq!((status.total_dof, status.constrained_dof, status.locked_dof), (4, 0, 4));
+    assert!(status.is_fully_constrained && !status.is_over_constrained);
+
+    // Unlocked, the line takes half of a change again
+    sketch.set_entity_meta(line, &EntityMetaUpdate { locked: Some(false), ..Default::default() }).unwrap();
+    assert_eq!(SketchSolver::calculate_dof(&sketch), 5);
+}
+
+#[test]
+fn test_entity_names_are_unique_and_resolvable() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let a = sketch.add_entity(SketchGeometry::Point { pos: [0.0, 0.0] });
+    let b = sketch.add_entity(SketchGeometry::Point { pos: [1.0, 0.0] });
+    sketch.set_entity_meta(a, &named("base")).unwrap();
+    assert_eq!(sketch.resolve_entity(&EntityRef::Name("base".to_string())), Ok(a));
+    assert_eq!(sketch.resolve_entity(&EntityRef::Id(b)), Ok(b));
+    assert!(sketch.resolve_entity(&EntityRef::Name("top".to_string())).is_err());
+
+    let error = sketch.set_entity_meta(b, &named("base")).unwrap_err();
+    assert_eq!(error, "Entity name 'base' is already in use");
+    assert_eq!(sketch.entities[1].name, None, "A rejected update changes nothing");
+    assert!(sketch.set_entity_meta(b, &named(" base")).is_err());
+    assert!(sketch.set_entity_meta(b, &named(&a.0.to_string())).is_err(), "Names can't look like ids");
+    sketch.set_entity_meta(a, &named("base")).unwrap();
+
+    // Sketches stored whole are checked for duplicates too
+    sketch.entities[1].name = Some("base".to_string());
+    assert!(sketch.validate_entity_names().is_err());
+
+    // A null clears the name and an omitted field is left alone
+    let update: EntityMetaUpdate = serde_json::from_str(r#"{"name": null, "color": [1, 0, 0, 1]}"#).unwrap();
+    assert!(!sketch.set_entity_meta(b, &update).unwrap());
+    assert_eq!((sketch.entities[1].name.clone(), sketch.entities[1].color), (None, Some([1.0, 0.0, 0.0, 1.0])));
+    assert!(sketch.validate_entity_names().is_ok());
+    let json: EntityRef = serde_json::from_value(serde_json::json!(a.0.to_string())).unwrap();
+    assert_eq!(json, EntityRef::Id(a));
+}
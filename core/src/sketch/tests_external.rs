@@ -0,0 +1,261 @@
+//! Tests for constraints against projected (external) geometry
+
+use std::collections::HashSet;
+
+use crate::evaluator::runtime::{EvaluationResult, Runtime};
+use crate::features::dag::FeatureGraph;
+use crate::features::types::{Feature, FeatureType, ParameterValue, RegionSpec};
+use crate::sketch::regions::{find_regions, summarize_regions};
+use crate::sketch::solver::SketchSolver;
+use crate::sketch::types::{ConstraintPoint, Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+use crate::topo::naming::{TopoId, TopoRank};
+use crate::topo::registry::AnalyticGeometry;
+use crate::topo::{EntityId, IdGenerator};
+
+/// Sketch with a projected horizontal line at y = `y` referencing `topo_id`.
+/// Returns the sketch and the projected line's id.
+fn sketch_with_projection(y: f64, topo_id: TopoId) -> (Sketch, EntityId) {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, y], end: [40.0, y] });
+    sketch.external_references.insert(line, topo_id);
+    (sketch, line)
+}
+
+fn dummy_reference() -> TopoId {
+    TopoId::new(EntityId::new(), 0, TopoRank::Edge)
+}
+
+#[test]
+fn test_projected_line_does_not_move() {
+    let (mut sketch, projected) = sketch_with_projection(40.0, dummy_reference());
+    let point = sketch.add_entity(SketchGeometry::Point { pos: [5.0, 30.0] });
+    sketch.add_constraint(SketchConstraint::Coincident {
+        points: [ConstraintPoint { id: projected, index: 0 }, ConstraintPoint { id: point, index: 0 }],
+    });
+
+    assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+    assert_eq!(sketch.entities[0].geometry, SketchGeometry::Line { start: [0.0, 40.0], end: [40.0, 40.0] });
+    let SketchGeometry::Point { pos } = sketch.entities[1].geometry else { unreachable!() };
+    assert!((pos[0]).abs() < 1e-6 && (pos[1] - 40.0).abs() < 1e-6, "The native point moves all the way: {:?}", pos);
+}
+
+#[test]
+fn test_projected_entities_have_no_dof() {
+    let (mut sketch, projected) = sketch_with_projection(40.0, dummy_reference());
+    let hole = sketch.add_entity(SketchGeometry::Circle { center: [20.0, 20.0], radius: 3.0 });
+    assert_eq!(SketchSolver::solve_with_result(&mut sketch).dof, 3, "Only the circle's parameters count");
+
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: projected });
+    sketch.add_constraint(SketchConstraint::OffsetFromExternal { entity: hole, external: projected, distance: 12.0 });
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert_eq!(result.dof, 2, "Constraints on projected geometry alone remove nothing");
+    let status = result.entity_statuses.iter().find(|s| s.id == projected).unwrap();
+    assert!(status.is_fully_constrained && status.total_dof == 0);
+}
+
+#[test]
+fn test_offset_from_external_keeps_side() {
+    let (mut sketch, projected) = sketch_with_projection(40.0, dummy_reference());
+    let above = sketch.add_entity(SketchGeometry::Circle { center: [10.0, 45.0], radius: 2.0 });
+    let below = sketch.add_entity(SketchGeometry::Line { start: [0.0, 10.0], end: [10.0, 20.0] });
+    sketch.add_constraint(SketchConstraint::OffsetFromExternal { entity: above, external: projected, distance: 12.0 });
+    sketch.add_constraint(SketchConstraint::OffsetFromExternal { entity: below, external: projected, distance: 12.0 });
+
+    assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+    assert_eq!(sketch.entities[1].geometry, SketchGeometry::Circle { center: [10.0, 52.0], radius: 2.0 });
+    let SketchGeometry::Line { start, end } = sketch.entities[2].geometry else { unreachable!() };
+    assert!(((start[1] + end[1]) / 2.0 - 28.0).abs() < 1e-6, "Midpoint 12 below the line");
+    assert!((end[1] - start[1] - 10.0).abs() < 1e-6, "Translated, not reshaped");
+}
+
+#[test]
+fn test_stale_reference_flags_constraint() {
+    let (mut sketch, projected) = sketch_with_projection(40.0, dummy_reference());
+    let hole = sketch.add_entity(SketchGeometry::Circle { center: [20.0, 20.0], radius: 3.0 });
+    sketch.add_constraint(SketchConstraint::Radius { entity: hole, value: 3.0, style: None });
+    sketch.add_constraint(SketchConstraint::OffsetFromExternal { entity: hole, external: projected, distance: 12.0 });
+
+    let mut result = SketchSolver::solve_with_result(&mut sketch);
+    result.flag_stale_external(&sketch, &HashSet::from([projected]));
+    assert_eq!(result.stale_external_constraints, vec![1]);
+    assert!(result.converged, "Solved against the last projected position");
+}
+
+/// Square base sketch spanning 0..40 in x and 0..`height` in y
+fn base_sketch(height: f64) -> Sketch {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let corners = [[0.0, 0.0], [40.0, 0.0], [40.0, height], [0.0, height]];
+    for i in 0..4 {
+        sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+    }
+    sketch
+}
+
+/// Move the base square's back edge to y = `height`, keeping entity ids
+fn resize_base(sketch: &mut Sketch, height: f64) {
+    let resized = base_sketch(height);
+    for (entity, geometry) in sketch.entities.iter_mut().zip(resized.entities) {
+        entity.geometry = geometry.geometry;
+    }
+}
+
+fn hole_center(result: &EvaluationResult, hole: EntityId) -> [f64; 3] {
+    match result.topology_manifest[&TopoId::new(hole, 0, TopoRank::Edge)].geometry {
+        AnalyticGeometry::Circle { center, .. } => center,
+        ref other => panic!("Hole is not a circle: {:?}", other),
+    }
+}
+
+#[test]
+fn test_hole_follows_projected_edge_of_upstream_solid() {
+    let mut graph = FeatureGraph::new();
+    let base = Feature::new("Base sketch", FeatureType::Sketch)
+        .with_param("sketch_data", ParameterValue::Sketch(base_sketch(40.0)));
+    let mut extrude = Feature::new("Base", FeatureType::Extrude).with_param("distance", ParameterValue::Float(10.0));
+    extrude.dependencies.push(base.id);
+    let base_id = base.id;
+    let extrude_id = extrude.id;
+    graph.add_node(base);
+    graph.add_node(extrude);
+
+    let runtime = Runtime::new();
+    let generator = IdGenerator::new("External");
+    let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+    // Top back edge of the box
+    let edge = result.topology_manifest.values()
+        .find(|e| matches!(e.geometry, AnalyticGeometry::Line { start, end }
+            if start[1] == 40.0 && end[1] == 40.0 && start[2] == 10.0 && end[2] == 10.0))
+        .map(|e| e.id)
+        .expect("Box has a top back edge");
+
+    let (mut holes, projected) = sketch_with_projection(0.0, edge);
+    let hole = holes.add_entity(SketchGeometry::Circle { center: [20.0, 20.0], radius: 3.0 });
+    holes.add_constraint(SketchConstraint::OffsetFromExternal { entity: hole, external: projected, distance: 12.0 });
+    let mut holes_feature = Feature::new("Holes", FeatureType::Sketch)
+        .with_param("sketch_data", ParameterValue::Sketch(holes));
+    holes_feature.dependencies.push(extrude_id);
+    let holes_id = holes_feature.id;
+    graph.add_node(holes_feature);
+
+    let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+    assert!((hole_center(&result, hole)[1] - 28.0).abs() < 1e-6);
+
+    let Some(ParameterValue::Sketch(sketch)) = graph.nodes.get_mut(&base_id).unwrap().parameters.get_mut("sketch_data") else {
+        unreachable!()
+    };
+    resize_base(sketch, 50.0);
+    let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+    assert!(result.topology_manifest.contains_key(&edge), "The edge keeps its name");
+    assert!((hole_center(&result, hole)[1] - 38.0).abs() < 1e-6, "The hole stays 12mm from the moved edge");
+    assert!(!result.logs.iter().any(|l| l.contains("reference was lost")));
+
+    // The stored sketch follows too, so solving it between regenerations agrees
+    assert_eq!(graph.reproject_sketches(&result.topology_manifest), 1);
+    assert_eq!(graph.reproject_sketches(&result.topology_manifest), 0, "Already current");
+    let Some(ParameterValue::Sketch(stored)) = graph.nodes[&holes_id].parameters.get("sketch_data") else { unreachable!() };
+    let SketchGeometry::Line { start, end } = stored.entities[0].geometry else { unreachable!() };
+    assert_eq!((start[1], end[1]), (50.0, 50.0), "Either way along the edge");
+    let mut solved = stored.clone();
+    SketchSolver::solve_with_result(&mut solved);
+    let SketchGeometry::Circle { center, .. } = solved.entities[1].geometry else { unreachable!() };
+    assert!((center[1] - 38.0).abs() < 1e-6);
+}
+
+/// `width` x 30 plate sketch with a hole of `radius` at its center
+fn plate_sketch(width: f64, radius: f64) -> Sketch {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let corners = [[0.0, 0.0], [width, 0.0], [width, 30.0], [0.0, 30.0]];
+    for i in 0..4 {
+        sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+    }
+    sketch.add_entity(SketchGeometry::Circle { center: [width / 2.0, 15.0], radius });
+    sketch
+}
+
+/// Lengths of the outline's lines and the circle's center and radius, in model space
+fn offset_outline(result: &EvaluationResult, entities: &[EntityId]) -> (Vec<f64>, [f64; 3], f64) {
+    let mut lengths = Vec::new();
+    let mut circle = None;
+    for id in entities {
+        match result.topology_manifest[&TopoId::new(*id, 0, TopoRank::Edge)].geometry {
+            AnalyticGeometry::Line { start, end } => {
+                lengths.push(((end[0] - start[0]).powi(2) + (end[1] - start[1]).powi(2) + (end[2] - start[2]).powi(2)).sqrt());
+            }
+            AnalyticGeometry::Circle { center, radius, .. } => circle = Some((center, radius)),
+            ref other => panic!("Unexpected outline geometry {:?}", other),
+        }
+    }
+    lengths.sort_by(f64::total_cmp);
+    let (center, radius) = circle.expect("The hole is offset too");
+    (lengths, center, radius)
+}
+
+#[test]
+fn test_offset_profile_follows_face() {
+    let mut graph = FeatureGraph::new();
+    let plate = plate_sketch(40.0, 5.0);
+    // The plate around the hole, not the disc inside it
+    let region_key = summarize_regions(&find_regions(&plate.entities)).into_iter()
+        .find(|s| !s.region.voids.is_empty())
+        .expect("The plate has a hole")
+        .stable_id;
+    let base = Feature::new("Plate sketch", FeatureType::Sketch)
+        .with_param("sketch_data", ParameterValue::Sketch(plate));
+    let mut extrude = Feature::new("Plate", FeatureType::Extrude)
+        .with_param("distance", ParameterValue::Float(10.0))
+        .with_param("region_specs", ParameterValue::RegionSpecs(vec![RegionSpec {
+            region_key,
+            depth_override: None,
+            direction: Default::default(),
+            centroid: None,
+        }]));
+    extrude.dependencies.push(base.id);
+    let base_id = base.id;
+    let extrude_id = extrude.id;
+    graph.add_node(base);
+    graph.add_node(extrude);
+
+    let runtime = Runtime::new();
+    let generator = IdGenerator::new("FaceOffset");
+    let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+    let top = result.topology_manifest.values()
+        .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { origin, normal } if origin[2] == 10.0 && normal[2] > 0.5))
+        .map(|e| e.id)
+        .expect("Plate has a top face");
+    let profile = graph.create_offset_profile_from_face(top, 2.0, None, &result.tessellation, &result.topology_manifest).unwrap();
+    assert_eq!(profile.entities.len(), 5, "Four sides and the hole");
+    assert!(graph.nodes[&profile.sketch_id].dependencies.contains(&extrude_id));
+
+    let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+    let (lengths, center, radius) = offset_outline(&result, &profile.entities);
+    let expected = [26.0, 26.0, 36.0, 36.0];
+    assert!(lengths.iter().zip(expected).all(|(l, e)| (l - e).abs() < 1e-4), "Inset 2mm: {:?}", lengths);
+    assert!((radius - 7.0).abs() < 1e-4, "The hole grows by 2mm: {}", radius);
+    assert!((center[0] - 20.0).abs() < 1e-4 && (center[2] - 10.0).abs() < 1e-6, "{:?}", center);
+
+    // Shrink the plate and its hole
+    let Some(ParameterValue::Sketch(sketch)) = graph.nodes.get_mut(&base_id).unwrap().parameters.get_mut("sketch_data") else {
+        unreachable!()
+    };
+    for (entity, geometry) in sketch.entities.iter_mut().zip(plate_sketch(30.0, 4.0).entities) {
+        entity.geometry = geometry.geometry;
+    }
+    let result = runtime.evaluate(&graph.regenerate(), &generator).unwrap();
+    let (lengths, center, radius) = offset_outline(&result, &profile.entities);
+    let expected = [26.0, 26.0, 26.0, 26.0];
+    assert!(lengths.iter().zip(expected).all(|(l, e)| (l - e).abs() < 1e-4), "Follows the narrower face: {:?}", lengths);
+    assert!((radius - 6.0).abs() < 1e-4 && (center[0] - 15.0).abs() < 1e-4, "Follows the smaller hole: {} at {:?}", radius, center);
+    assert!(!result.logs.iter().any(|l| l.contains("Offset of face")), "{:?}", result.logs);
+
+    // The kernel facets the hole's wall; describe one facet as the cylinder it approximates
+    let mut manifest = result.topology_manifest.clone();
+    let side = manifest.values_mut()
+        .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { normal, .. } if normal[2].abs() < 0.5) && e.id.feature_id == top.feature_id)
+        .expect("The plate has sides");
+    side.geometry = AnalyticGeometry::Cylinder { axis_start: [15.0, 15.0, 0.0], axis_dir: [0.0, 0.0, 1.0], radius: 4.0 };
+    let side = side.id;
+    let err = graph.create_offset_profile_from_face(side, 1.0, None, &result.tessellation, &manifest).unwrap_err();
+    assert!(err.contains("not planar"), "{}", err);
+    let err = graph.create_offset_profile_from_face(top, 1.0, Some(base_id), &result.tessellation, &result.topology_manifest).unwrap_err();
+    assert!(err.contains("is not on that face"), "{}", err);
+}
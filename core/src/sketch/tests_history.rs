@@ -45,7 +45,11 @@ mod tests {
         sketch.entities.push(crate::sketch::types::SketchEntity { 
             id: id1, 
             geometry: geom1.clone(), 
-            is_construction: false 
+            is_construction: false, 
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false 
         });
 
         let constraint = SketchConstraint::Horizontal { entity: id1 };
@@ -76,4 +80,94 @@ mod tests {
         sketch.ensure_history();
         assert_eq!(sketch.history.len(), 2);
     }
+
+    #[test]
+    fn test_undo_last_takes_back_additions_in_reverse_order() {
+        use crate::sketch::solver::SketchSolver;
+        use crate::sketch::types::SketchAddition;
+
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [8.0, 1.0] });
+        sketch.add_constraint(SketchConstraint::Fix { point: ConstraintPoint { id: l1, index: 0 }, position: [0.0, 0.0] });
+        sketch.add_constraint(SketchConstraint::Horizontal { entity: l1 });
+        let l2 = sketch.add_entity(SketchGeometry::Line { start: [8.0, 1.0], end: [9.0, 7.0] });
+        sketch.add_constraint(SketchConstraint::Coincident { points: [ConstraintPoint { id: l1, index: 1 }, ConstraintPoint { id: l2, index: 0 }] });
+        sketch.add_constraint(SketchConstraint::Vertical { entity: l2 });
+        sketch.add_constraint(SketchConstraint::Distance {
+            points: [ConstraintPoint { id: l1, index: 0 }, ConstraintPoint { id: l1, index: 1 }], value: 10.0, style: None,
+        });
+
+        let additions = sketch.additions();
+        assert_eq!(additions.len(), 7);
+        assert!(matches!(additions[0], SketchAddition::Entity { id, seq: 1 } if id == l1));
+        assert!(matches!(additions[3], SketchAddition::Entity { id, seq: 4 } if id == l2));
+
+        // Sequences survive a save and don't change what the solver does
+        let reloaded: Sketch = serde_json::from_str(&serde_json::to_string(&sketch).unwrap()).unwrap();
+        assert_eq!(reloaded.additions(), additions);
+        let mut renumbered = sketch.clone();
+        renumbered.entities.iter_mut().for_each(|e| e.creation_seq *= 100);
+        renumbered.constraints.iter_mut().for_each(|e| e.creation_seq *= 100);
+        let mut solved = sketch.clone();
+        let result = SketchSolver::solve_with_result(&mut solved);
+        let renumbered_result = SketchSolver::solve_with_result(&mut renumbered);
+        assert_eq!((result.converged, result.dof), (renumbered_result.converged, renumbered_result.dof));
+        assert_eq!(solved.entities.iter().map(|e| &e.geometry).collect::<Vec<_>>(), renumbered.entities.iter().map(|e| &e.geometry).collect::<Vec<_>>());
+
+        for (step, expected) in additions.iter().rev().enumerate() {
+            assert_eq!(sketch.undo_last(1), vec![*expected], "Step {}", step);
+            let result = SketchSolver::solve_with_result(&mut sketch.clone());
+            assert!(result.converged && result.dof >= 0, "Step {}: {}", step, result.status_message);
+        }
+        assert!(sketch.entities.is_empty() && sketch.constraints.is_empty());
+        assert!(sketch.undo_last(3).is_empty());
+    }
+
+    #[test]
+    fn test_unnumbered_additions_are_numbered_in_list_order() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let drawn = sketch.add_entity(SketchGeometry::Point { pos: [0.0, 0.0] });
+        // Drawn by the client, which doesn't number what it adds
+        let line = EntityId::new();
+        sketch.entities.push(crate::sketch::types::SketchEntity {
+            id: line,
+            geometry: SketchGeometry::Line { start: [0.0, 0.0], end: [1.0, 0.0] },
+            is_construction: false,
+            creation_seq: 0,
+            name: None,
+            color: None,
+            locked: false,
+        });
+        sketch.constraints.push(SketchConstraint::Horizontal { entity: line }.into());
+        sketch.number_additions();
+
+        assert_eq!(sketch.entities.iter().map(|e| e.creation_seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(sketch.constraints[0].creation_seq, 3);
+        // Undoing the line takes its constraint with it; the point stays
+        assert_eq!(sketch.undo_last(2).len(), 2);
+        assert_eq!(sketch.entities.iter().map(|e| e.id).collect::<Vec<_>>(), vec![drawn]);
+    }
+
+    #[test]
+    fn test_sequence_numbers_are_not_reused_after_undo() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        sketch.add_entity(SketchGeometry::Point { pos: [0.0, 0.0] });
+        sketch.add_entity(SketchGeometry::Point { pos: [1.0, 0.0] });
+        assert_eq!(sketch.undo_last(1).len(), 1);
+        let redrawn = sketch.add_entity(SketchGeometry::Point { pos: [2.0, 0.0] });
+        assert_eq!(sketch.entities.iter().find(|e| e.id == redrawn).unwrap().creation_seq, 3);
+
+        // An edit storing the sketch without the undone addition keeps counting on
+        let mut edited = sketch.clone();
+        edited.undo_last(1);
+        edited.next_seq = 0;
+        edited.continue_numbering(&sketch);
+        assert_eq!(edited.take_creation_seq(), 4);
+
+        // Saved before the counter was stored: continues from the highest number
+        let mut json = serde_json::to_value(&sketch).unwrap();
+        json.as_object_mut().unwrap().remove("next_seq");
+        let loaded: Sketch = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.next_seq, 4);
+    }
 }
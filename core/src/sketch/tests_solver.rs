@@ -1,6 +1,30 @@
 use crate::sketch::types::{Sketch, SketchPlane, SketchGeometry, SketchConstraint, ConstraintPoint};
 use crate::sketch::solver::SketchSolver;
 
+#[test]
+fn test_preview_second_distance_over_constrains() {
+    // Line fully constrained: start fixed, horizontal, length 10
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+    let ends = [ConstraintPoint { id: line, index: 0 }, ConstraintPoint { id: line, index: 1 }];
+    sketch.add_constraint(SketchConstraint::Fix { point: ends[0], position: [0.0, 0.0] });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: line });
+    sketch.add_constraint(SketchConstraint::Distance { points: ends, value: 10.0, style: None });
+    assert!(SketchSolver::solve_with_result(&mut sketch).is_fully_constrained());
+    let before = serde_json::to_string(&sketch).unwrap();
+
+    let second = SketchConstraint::Distance { points: ends, value: 12.0, style: None };
+    let preview = SketchSolver::preview_add_constraint(&sketch, &second);
+    assert_eq!(preview.dof - SketchSolver::calculate_dof(&sketch), -1);
+    assert!(preview.is_over_constrained());
+    assert!(preview.conflicts.is_some_and(|c| !c.unsatisfied_constraints.is_empty()));
+    assert_eq!(serde_json::to_string(&sketch).unwrap(), before, "The sketch itself is untouched");
+
+    let redundant = SketchSolver::preview_add_constraint(&sketch, &SketchConstraint::Horizontal { entity: line });
+    assert!(redundant.converged && redundant.dof == -1);
+    assert_eq!(redundant.redundant_constraints[0].duplicates_index, Some(1));
+}
+
 #[test]
 fn test_solver_horizontal() {
     let mut sketch = Sketch::new(SketchPlane::default());
@@ -556,3 +580,226 @@ fn test_ellipse_coincident_with_line() {
         panic!("Geometry mismatch");
     }
 }
+
+#[test]
+fn test_solver_tiny_angular_error_on_large_sketch() {
+    // Two 1000mm lines that are almost (but not exactly) parallel.
+    // The skew is below the raw 1e-6 epsilon, yet it moves the far end by ~0.5µm,
+    // so the solver must keep iterating instead of reporting converged immediately.
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let skew: f64 = 5e-7;
+    let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [1000.0, 0.0] });
+    let l2 = sketch.add_entity(SketchGeometry::Line {
+        start: [0.0, 100.0],
+        end: [1000.0 * skew.cos(), 100.0 + 1000.0 * skew.sin()],
+    });
+
+    // Distances are already satisfied
+    sketch.constraints.push(SketchConstraint::Distance {
+        points: [ConstraintPoint { id: l1, index: 0 }, ConstraintPoint { id: l1, index: 1 }],
+        value: 1000.0,
+        style: None,
+    }.into());
+    sketch.constraints.push(SketchConstraint::Distance {
+        points: [ConstraintPoint { id: l2, index: 0 }, ConstraintPoint { id: l2, index: 1 }],
+        value: 1000.0,
+        style: None,
+    }.into());
+    sketch.constraints.push(SketchConstraint::Parallel { lines: [l1, l2] }.into());
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(result.converged);
+    assert!(result.iterations > 1, "Solver should not accept the skew on the first pass");
+
+    let dir = |g: &SketchGeometry| match g {
+        SketchGeometry::Line { start, end } => {
+            let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+            let len = (dx * dx + dy * dy).sqrt();
+            [dx / len, dy / len]
+        }
+        _ => panic!("Expected line"),
+    };
+    let d1 = dir(&sketch.entities[0].geometry);
+    let d2 = dir(&sketch.entities[1].geometry);
+    let cross = d1[0] * d2[1] - d1[1] * d2[0];
+    assert!(cross.abs() < 1e-9, "Lines should be truly parallel, cross = {}", cross);
+}
+
+#[test]
+fn test_dof_ignores_constraints_on_deleted_entities() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+    sketch.constraints.push(SketchConstraint::Horizontal { entity: l1 }.into());
+
+    // Constraints left behind after their other entity was deleted
+    let deleted = crate::topo::EntityId::new();
+    sketch.constraints.push(SketchConstraint::Fix { point: ConstraintPoint { id: deleted, index: 0 }, position: [0.0, 0.0] }.into());
+    sketch.constraints.push(SketchConstraint::Coincident {
+        points: [ConstraintPoint { id: l1, index: 1 }, ConstraintPoint { id: deleted, index: 0 }],
+    }.into());
+    sketch.constraints.push(SketchConstraint::Parallel { lines: [l1, deleted] }.into());
+    // A point made coincident with itself removes nothing either
+    sketch.constraints.push(SketchConstraint::Coincident {
+        points: [ConstraintPoint { id: l1, index: 0 }, ConstraintPoint { id: l1, index: 0 }],
+    }.into());
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert_eq!(result.dof, 3, "Only Horizontal counts: {}", result.status_message);
+    assert!(!result.is_over_constrained());
+    let status = &result.entity_statuses[0];
+    assert_eq!(status.constrained_dof, 1);
+    assert!(!status.is_over_constrained);
+}
+
+/// Deterministic Fisher-Yates shuffle (xorshift), so failures are reproducible
+fn shuffled<T: Clone>(items: &[T], mut seed: u64) -> Vec<T> {
+    let mut items = items.to_vec();
+    for i in (1..items.len()).rev() {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        items.swap(i, (seed % (i as u64 + 1)) as usize);
+    }
+    items
+}
+
+fn entity_coordinates(sketch: &Sketch) -> Vec<f64> {
+    sketch.entities.iter().flat_map(|e| match e.geometry {
+        SketchGeometry::Line { start, end } => vec![start[0], start[1], end[0], end[1]],
+        SketchGeometry::Arc { center, radius, start_angle, end_angle } => vec![center[0], center[1], radius, start_angle, end_angle],
+        SketchGeometry::Circle { center, radius } => vec![center[0], center[1], radius],
+        SketchGeometry::Point { pos } => pos.to_vec(),
+        SketchGeometry::Ellipse { center, semi_major, semi_minor, rotation } => vec![center[0], center[1], semi_major, semi_minor, rotation],
+        SketchGeometry::EllipseArc { center, semi_major, semi_minor, rotation, start_angle, end_angle } => {
+            vec![center[0], center[1], semi_major, semi_minor, rotation, start_angle, end_angle]
+        }
+    }).collect()
+}
+
+/// Solve `sketch` with its constraints in several random orders and check every
+/// order lands on the same geometry
+fn assert_order_independent(sketch: &Sketch) {
+    let mut reference = sketch.clone();
+    SketchSolver::solve_with_result(&mut reference);
+    let expected = entity_coordinates(&reference);
+    let mut relaxed_reference = sketch.clone();
+    SketchSolver::solve_relaxed(&mut relaxed_reference);
+    let relaxed_expected = entity_coordinates(&relaxed_reference);
+
+    for seed in [1, 7, 42, 1234, 987_654_321] {
+        let mut permuted = sketch.clone();
+        permuted.constraints = shuffled(&sketch.constraints, seed);
+        let mut relaxed = permuted.clone();
+        SketchSolver::solve_with_result(&mut permuted);
+        SketchSolver::solve_relaxed(&mut relaxed);
+        for (actual, expected) in [(entity_coordinates(&permuted), &expected), (entity_coordinates(&relaxed), &relaxed_expected)] {
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-9, "Seed {}: {:?} vs {:?}", seed, actual, expected);
+            }
+        }
+    }
+}
+
+fn point(id: crate::topo::EntityId, index: u8) -> ConstraintPoint {
+    ConstraintPoint { id, index }
+}
+
+#[test]
+fn test_solve_is_independent_of_constraint_order_rectangle() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let corners = [[0.3, -0.2], [9.1, 0.4], [10.5, 6.2], [-0.4, 4.6]];
+    let ids: Vec<_> = (0..4)
+        .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] }))
+        .collect();
+    for i in 0..4 {
+        sketch.add_constraint(SketchConstraint::Coincident { points: [point(ids[i], 1), point(ids[(i + 1) % 4], 0)] });
+    }
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: ids[0] });
+    sketch.add_constraint(SketchConstraint::Vertical { entity: ids[1] });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: ids[2] });
+    sketch.add_constraint(SketchConstraint::Vertical { entity: ids[3] });
+    sketch.add_constraint(SketchConstraint::Distance { points: [point(ids[0], 0), point(ids[0], 1)], value: 10.0, style: None });
+    sketch.add_constraint(SketchConstraint::Distance { points: [point(ids[1], 0), point(ids[1], 1)], value: 5.0, style: None });
+    sketch.add_constraint(SketchConstraint::Fix { point: point(ids[0], 0), position: [0.0, 0.0] });
+
+    assert_order_independent(&sketch);
+}
+
+#[test]
+fn test_solve_is_independent_of_constraint_order_slot() {
+    use std::f64::consts::FRAC_PI_2;
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let bottom = sketch.add_entity(SketchGeometry::Line { start: [0.2, 0.1], end: [9.7, -0.3] });
+    let right = sketch.add_entity(SketchGeometry::Arc { center: [10.2, 2.1], radius: 1.8, start_angle: -FRAC_PI_2, end_angle: FRAC_PI_2 });
+    let top = sketch.add_entity(SketchGeometry::Line { start: [10.3, 4.2], end: [-0.1, 3.9] });
+    let left = sketch.add_entity(SketchGeometry::Arc { center: [0.1, 1.9], radius: 2.2, start_angle: FRAC_PI_2, end_angle: 3.0 * FRAC_PI_2 });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [point(bottom, 1), point(right, 1)] });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [point(right, 2), point(top, 0)] });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [point(top, 1), point(left, 1)] });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [point(left, 2), point(bottom, 0)] });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: bottom });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: top });
+    sketch.add_constraint(SketchConstraint::Equal { entities: [right, left] });
+    sketch.add_constraint(SketchConstraint::Radius { entity: right, value: 2.0, style: None });
+    sketch.add_constraint(SketchConstraint::Distance { points: [point(bottom, 0), point(bottom, 1)], value: 10.0, style: None });
+
+    assert_order_independent(&sketch);
+}
+
+#[test]
+fn test_equal_group_converges_lines_to_mean_length() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let lines: Vec<_> = [2.0, 4.0, 6.0, 8.0].iter().enumerate()
+        .map(|(i, length)| sketch.add_entity(SketchGeometry::Line { start: [0.0, i as f64 * 3.0], end: [*length, i as f64 * 3.0] }))
+        .collect();
+    sketch.add_constraint(SketchConstraint::EqualGroup { entities: lines.clone() });
+    assert_eq!(SketchSolver::calculate_dof(&sketch), 16 - 3, "Removes one DOF per line past the first");
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(result.converged);
+    for geometry in lines.iter().map(|id| &sketch.entities.iter().find(|e| e.id == *id).unwrap().geometry) {
+        assert!((geometry.length() - 5.0).abs() < 1e-6, "Every line takes the mean length: {:?}", geometry);
+    }
+
+    // Circles are equalized by radius; a mixed group is left alone
+    let circles: Vec<_> = [1.0, 2.0, 6.0].iter()
+        .map(|radius| sketch.add_entity(SketchGeometry::Circle { center: [20.0, 0.0], radius: *radius }))
+        .collect();
+    sketch.add_constraint(SketchConstraint::EqualGroup { entities: circles.clone() });
+    sketch.add_constraint(SketchConstraint::EqualGroup { entities: vec![lines[0], circles[0]] });
+    assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+    for id in &circles {
+        let radius = match sketch.entities.iter().find(|e| e.id == *id).unwrap().geometry {
+            SketchGeometry::Circle { radius, .. } => radius,
+            _ => unreachable!(),
+        };
+        assert!((radius - 3.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_distance_point_circle_places_point_outside_hole() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let hole = sketch.add_entity(SketchGeometry::Circle { center: [0.0, 0.0], radius: 5.0 });
+    let marker = sketch.add_entity(SketchGeometry::Point { pos: [6.0, 8.0] });
+    sketch.add_constraint(SketchConstraint::Fix { point: point(hole, 0), position: [0.0, 0.0] });
+    sketch.add_constraint(SketchConstraint::DistancePointCircle { point: point(marker, 0), circle: hole, value: 3.0, style: None });
+    assert_eq!(SketchSolver::calculate_dof(&sketch), 3 + 2 - 2 - 1, "The distance removes one DOF");
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(result.converged);
+    let SketchGeometry::Point { pos } = sketch.entities.iter().find(|e| e.id == marker).unwrap().geometry else { unreachable!() };
+    // Pushed out along its ray from the center to 5 + 3
+    assert!((pos[0] - 4.8).abs() < 1e-6 && (pos[1] - 6.4).abs() < 1e-6, "{:?}", pos);
+
+    // A point inside an arc stays inside; the unfixed center takes part of the move
+    let arc = sketch.add_entity(SketchGeometry::Arc { center: [20.0, 0.0], radius: 4.0, start_angle: 0.0, end_angle: 3.0 });
+    let inner = sketch.add_entity(SketchGeometry::Point { pos: [21.0, 0.0] });
+    sketch.add_constraint(SketchConstraint::DistancePointCircle { point: point(inner, 0), circle: arc, value: 1.5, style: None });
+    assert!(SketchSolver::solve_with_result(&mut sketch).converged);
+    let SketchGeometry::Point { pos } = sketch.entities.iter().find(|e| e.id == inner).unwrap().geometry else { unreachable!() };
+    let SketchGeometry::Arc { center, .. } = sketch.entities.iter().find(|e| e.id == arc).unwrap().geometry else { unreachable!() };
+    let distance = ((pos[0] - center[0]).powi(2) + (pos[1] - center[1]).powi(2)).sqrt();
+    assert!((distance - 2.5).abs() < 1e-6, "{}", distance);
+    assert!(center[0] < 20.0, "The center was nudged away from the point");
+}
@@ -133,3 +133,76 @@ fn test_mixed_active_and_suppressed() {
         assert!((start[1] - end[1]).abs() > 0.1, "L2 should NOT be horizontal (suppressed)");
     }
 }
+
+/// Rectangle with a fixed corner and horizontal/vertical sides, leaving
+/// width and height free, dimensioned by two alternative groups: "overall"
+/// (12 x 6, as drawn) and "edges" (10 x 5)
+fn rectangle_with_two_schemes() -> Sketch {
+    use crate::sketch::types::ConstraintPoint;
+
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let corners = [[0.0, 0.0], [12.0, 0.0], [12.0, 6.0], [0.0, 6.0]];
+    let sides: Vec<_> = (0..4)
+        .map(|i| sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] }))
+        .collect();
+    for i in 0..4 {
+        sketch.add_constraint(SketchConstraint::Coincident { points: [
+            ConstraintPoint { id: sides[i], index: 1 },
+            ConstraintPoint { id: sides[(i + 1) % 4], index: 0 },
+        ]});
+    }
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: sides[0] });
+    sketch.add_constraint(SketchConstraint::Horizontal { entity: sides[2] });
+    sketch.add_constraint(SketchConstraint::Vertical { entity: sides[1] });
+    sketch.add_constraint(SketchConstraint::Vertical { entity: sides[3] });
+    sketch.add_constraint(SketchConstraint::Fix { point: ConstraintPoint { id: sides[0], index: 0 }, position: [0.0, 0.0] });
+
+    let ends = |side| [ConstraintPoint { id: side, index: 0 }, ConstraintPoint { id: side, index: 1 }];
+    sketch.constraints.push(SketchConstraintEntry::new(SketchConstraint::HorizontalDistance { points: ends(sides[0]), value: 12.0, style: None }).in_group("overall"));
+    sketch.constraints.push(SketchConstraintEntry::new(SketchConstraint::VerticalDistance { points: ends(sides[1]), value: 6.0, style: None }).in_group("overall"));
+    sketch.constraints.push(SketchConstraintEntry::new(SketchConstraint::HorizontalDistance { points: ends(sides[0]), value: 10.0, style: None }).in_group("edges"));
+    sketch.constraints.push(SketchConstraintEntry::new(SketchConstraint::VerticalDistance { points: ends(sides[1]), value: 5.0, style: None }).in_group("edges"));
+    sketch
+}
+
+/// Two dimensioning schemes left on together over-constrain the sketch and
+/// are named in the diagnosis; suppressing one leaves it fully constrained
+#[test]
+fn test_constraint_group_suppression() {
+    let mut sketch = rectangle_with_two_schemes();
+    assert_eq!(sketch.constraint_groups(), ["overall", "edges"]);
+
+    let mut both_active = sketch.clone();
+    let both = SketchSolver::solve_with_result(&mut both_active);
+    assert!(both.is_over_constrained() && both.dof < 0);
+    assert!(both.status_message.contains("'overall', 'edges'"), "{}", both.status_message);
+    let conflicts = both.conflicts.expect("Competing dimensions should conflict");
+    assert!(conflicts.possible_conflicts.iter().any(|(_, _, reason)| reason.contains("groups 'overall' and 'edges' are both active")),
+        "{:?}", conflicts.possible_conflicts);
+    assert!(both.groups.iter().any(|g| g.conflicting > 0), "{:?}", both.groups);
+
+    assert_eq!(sketch.set_group_suppression("edges", true), 2);
+    assert_eq!(sketch.set_group_suppression("missing", true), 0);
+    let overall = SketchSolver::solve_with_result(&mut sketch);
+    assert!(overall.is_fully_constrained(), "{}", overall.status_message);
+    assert_eq!(overall.status_message, "Fully constrained");
+    assert_eq!(overall.groups.len(), 2);
+    assert_eq!((overall.groups[0].satisfied, overall.groups[0].conflicting), (2, 0));
+    assert_eq!((overall.groups[1].suppressed, overall.groups[1].satisfied), (2, 0));
+
+    // The other scheme alone removes the same freedoms
+    sketch.set_group_suppression("edges", false);
+    sketch.set_group_suppression("overall", true);
+    assert_eq!(SketchSolver::calculate_dof(&sketch), 0);
+}
+
+#[test]
+fn test_constraint_groups_round_trip() {
+    let sketch = rectangle_with_two_schemes();
+    let json = serde_json::to_value(&sketch).unwrap();
+    assert_eq!(json["constraints"][9]["group"], "overall");
+    assert!(json["constraints"][0].get("group").is_none(), "Ungrouped constraints omit the field");
+
+    let restored: Sketch = serde_json::from_value(json).unwrap();
+    assert_eq!(restored, sketch);
+}
@@ -0,0 +1,67 @@
+//! Tests for tangent joints between lines and arcs
+
+use crate::sketch::solver::SketchSolver;
+use crate::sketch::types::{ConstraintPoint, Sketch, SketchConstraint, SketchGeometry, SketchPlane};
+use crate::topo::EntityId;
+
+fn cp(id: EntityId, index: u8) -> ConstraintPoint {
+    ConstraintPoint { id, index }
+}
+
+/// Direction a line runs, or an arc runs counter-clockwise, at one of its ends
+fn direction_at(sketch: &Sketch, point: ConstraintPoint) -> [f64; 2] {
+    match sketch.entities.iter().find(|e| e.id == point.id).unwrap().geometry {
+        SketchGeometry::Line { start, end } => {
+            let length = (end[0] - start[0]).hypot(end[1] - start[1]);
+            [(end[0] - start[0]) / length, (end[1] - start[1]) / length]
+        }
+        SketchGeometry::Arc { start_angle, end_angle, .. } => {
+            let angle = if point.index == 1 { start_angle } else { end_angle };
+            [-angle.sin(), angle.cos()]
+        }
+        ref other => panic!("Not a line or arc: {:?}", other),
+    }
+}
+
+#[test]
+fn test_line_arc_line_joints_become_tangent() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    // Both joints start a few degrees off tangent
+    let l1 = sketch.add_entity(SketchGeometry::Line { start: [-10.0, -1.0], end: [0.0, 0.0] });
+    let arc = sketch.add_entity(SketchGeometry::Arc {
+        center: [0.0, 5.0],
+        radius: 5.0,
+        start_angle: -std::f64::consts::FRAC_PI_2,
+        end_angle: 0.0,
+    });
+    let l2 = sketch.add_entity(SketchGeometry::Line { start: [5.0, 5.0], end: [6.0, 12.0] });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [cp(l1, 1), cp(arc, 1)] });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [cp(arc, 2), cp(l2, 0)] });
+    sketch.add_constraint(SketchConstraint::TangentAtPoint { a: cp(l1, 1), b: cp(arc, 1) });
+    sketch.add_constraint(SketchConstraint::TangentAtPoint { a: cp(arc, 2), b: cp(l2, 0) });
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(result.converged, "{}", result.status_message);
+    // 4 + 5 + 4, less two per joint and one per tangency
+    assert_eq!(result.dof, 7);
+    for (a, b) in [(cp(l1, 1), cp(arc, 1)), (cp(arc, 2), cp(l2, 0))] {
+        let (ta, tb) = (direction_at(&sketch, a), direction_at(&sketch, b));
+        assert!((ta[0] * tb[1] - ta[1] * tb[0]).abs() < 1e-6, "{:?} and {:?} should be tangent", ta, tb);
+    }
+}
+
+#[test]
+fn test_tangent_and_perpendicular_at_one_joint_conflict() {
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let l1 = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+    let l2 = sketch.add_entity(SketchGeometry::Line { start: [10.0, 0.0], end: [15.0, 5.0] });
+    sketch.add_constraint(SketchConstraint::Coincident { points: [cp(l1, 1), cp(l2, 0)] });
+    sketch.add_constraint(SketchConstraint::TangentAtPoint { a: cp(l1, 1), b: cp(l2, 0) });
+    sketch.add_constraint(SketchConstraint::Perpendicular { lines: [l2, l1] });
+
+    let result = SketchSolver::solve_with_result(&mut sketch);
+    assert!(!result.converged);
+    let conflicts = result.conflicts.expect("Conflicts should be reported");
+    assert!(conflicts.possible_conflicts.iter().any(|(i, j, reason)| (*i, *j) == (1, 2) && reason.starts_with("Tangent and Perpendicular")),
+        "{:?}", conflicts.possible_conflicts);
+}
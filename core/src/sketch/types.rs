@@ -1,5 +1,7 @@
 use crate::geometry::{Point3, Vector3};
+use crate::topo::naming::{TopoId, TopoRank};
 use crate::topo::EntityId;
+use crate::variables::AngleUnit;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -30,6 +32,76 @@ pub enum SketchGeometry {
     /// Ellipse defined by center, semi-major axis, semi-minor axis, and rotation
     /// DOF: 5 (center_x, center_y, semi_major, semi_minor, rotation)
     Ellipse { center: [f64; 2], semi_major: f64, semi_minor: f64, rotation: f64 },
+    /// Part of an ellipse, counter-clockwise from `start_angle` to `end_angle`.
+    /// Angles are parametric (see `ellipse_point`), not polar.
+    /// DOF: 7 (the ellipse's 5, start_angle, end_angle)
+    EllipseArc { center: [f64; 2], semi_major: f64, semi_minor: f64, rotation: f64, start_angle: f64, end_angle: f64 },
+}
+
+impl SketchGeometry {
+    /// Curve length: a line's length, a circle's circumference, an arc's length
+    /// along its counter-clockwise sweep (zero when start and end angles match,
+    /// as nothing is drawn). Points have none.
+    ///
+    /// A full ellipse uses Ramanujan's second approximation: within 1e-9 relative
+    /// for b/a above 0.5, 5e-4 at worst (a flattened ellipse). Elliptical arcs are
+    /// integrated numerically: within 1e-6 relative for b/a above 0.1, 1e-3 at worst.
+    pub fn length(&self) -> f64 {
+        match self {
+            SketchGeometry::Line { start, end } => (end[0] - start[0]).hypot(end[1] - start[1]),
+            SketchGeometry::Circle { radius, .. } => crate::sketch::measurement::measure_circumference(*radius),
+            SketchGeometry::Arc { radius, start_angle, end_angle, .. } => {
+                crate::sketch::measurement::measure_arc_length(*radius, *start_angle, *end_angle)
+            }
+            SketchGeometry::Point { .. } => 0.0,
+            SketchGeometry::Ellipse { semi_major, semi_minor, .. } => {
+                let (a, b) = (semi_major.abs(), semi_minor.abs());
+                if a + b < 1e-15 {
+                    return 0.0;
+                }
+                let h = ((a - b) / (a + b)).powi(2);
+                std::f64::consts::PI * (a + b) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()))
+            }
+            SketchGeometry::EllipseArc { semi_major, semi_minor, start_angle, end_angle, .. } => {
+                let sweep = (end_angle - start_angle).rem_euclid(std::f64::consts::TAU);
+                // Composite Simpson's rule on |d/dt (a cos t, b sin t)|
+                let speed = |t: f64| (semi_major * t.sin()).hypot(semi_minor * t.cos());
+                let n = 2 * ((sweep / std::f64::consts::TAU * 256.0).ceil() as usize).max(1);
+                let h = sweep / n as f64;
+                let sum: f64 = (0..=n).map(|i| {
+                    let weight = if i == 0 || i == n { 1.0 } else if i % 2 == 1 { 4.0 } else { 2.0 };
+                    weight * speed(start_angle + h * i as f64)
+                }).sum();
+                sum * h / 3.0
+            }
+        }
+    }
+
+    /// Enclosed area of a closed curve (circle, ellipse), None for open curves and points
+    pub fn area(&self) -> Option<f64> {
+        match self {
+            SketchGeometry::Circle { radius, .. } => Some(std::f64::consts::PI * radius * radius),
+            SketchGeometry::Ellipse { semi_major, semi_minor, .. } => Some(std::f64::consts::PI * (semi_major * semi_minor).abs()),
+            _ => None,
+        }
+    }
+}
+
+/// Point at parametric angle `t` of an ellipse: (a cos t, b sin t) in the
+/// ellipse's own axes, rotated by `rotation` and moved to `center`
+pub fn ellipse_point(center: [f64; 2], semi_major: f64, semi_minor: f64, rotation: f64, t: f64) -> [f64; 2] {
+    let (x, y) = (semi_major * t.cos(), semi_minor * t.sin());
+    let (sin_r, cos_r) = rotation.sin_cos();
+    [center[0] + x * cos_r - y * sin_r, center[1] + x * sin_r + y * cos_r]
+}
+
+/// Parametric angle of the ellipse point in the direction of `p`, the inverse
+/// of `ellipse_point` for points on the ellipse
+pub fn ellipse_angle(center: [f64; 2], semi_major: f64, semi_minor: f64, rotation: f64, p: [f64; 2]) -> f64 {
+    let (dx, dy) = (p[0] - center[0], p[1] - center[1]);
+    let (sin_r, cos_r) = rotation.sin_cos();
+    let (x, y) = (dx * cos_r + dy * sin_r, -dx * sin_r + dy * cos_r);
+    (y / semi_minor.max(1e-12)).atan2(x / semi_major.max(1e-12))
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -38,14 +110,39 @@ pub struct SketchEntity {
     pub geometry: SketchGeometry,
     #[serde(default)]
     pub is_construction: bool,
+    /// When the entity was added, counted with the sketch's constraints
+    /// (see `Sketch::additions`); 0 for sketches saved before it was recorded
+    #[serde(default)]
+    pub creation_seq: u64,
+    /// Name other systems can refer to the entity by; unique within its sketch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Display color as RGBA, each 0..1; None draws the default style
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[f32; 4]>,
+    /// Held in place by the solver, as if every point had a Fix constraint
+    #[serde(default)]
+    pub locked: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ConstraintPoint {
     pub id: EntityId,
     pub index: u8, // 0=Start/Center/Pos, 1=End
 }
 
+impl ConstraintPoint {
+    /// Vertex TopoId of this point in the evaluated sketch. Derived from the
+    /// entity id and point index alone, so it is the same on every regen:
+    /// - Line: 0 = start, 1 = end
+    /// - Arc, EllipseArc: 0 = center, 1 = start, 2 = end
+    /// - Circle, Ellipse: 0 = center
+    /// - Point: 0 = position
+    pub fn topo_id(&self) -> TopoId {
+        TopoId::new(self.id, self.index as u64, TopoRank::Vertex)
+    }
+}
+
 /// Style configuration for visible dimension annotations
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DimensionStyle {
@@ -57,6 +154,9 @@ pub struct DimensionStyle {
     /// When present, the constraint value is re-evaluated from this expression during regeneration
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub expression: Option<String>,
+    /// Decimal places of the displayed value; two when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u8>,
 }
 
 impl Default for DimensionStyle {
@@ -65,6 +165,7 @@ impl Default for DimensionStyle {
             driven: false,
             offset: [0.0, 0.5], // Default offset above the dimension line
             expression: None,
+            precision: None,
         }
     }
 }
@@ -96,10 +197,15 @@ pub enum SketchConstraint {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         style: Option<DimensionStyle>,
     },
-    /// Angle constraint between two lines
+    /// Angle constraint between two lines, measured counter-clockwise from the
+    /// first line to the second with both pointing away from their corner
     Angle {
         lines: [EntityId; 2],
-        value: f64, // radians
+        /// Target angle, expressed in `unit`; any value, taken modulo a full turn
+        value: f64,
+        /// Unit of `value`; sketches saved before units were stored used radians
+        #[serde(default = "radians")]
+        unit: AngleUnit,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         style: Option<DimensionStyle>,
     },
@@ -113,7 +219,13 @@ pub enum SketchConstraint {
     Parallel { lines: [EntityId; 2] },
     Perpendicular { lines: [EntityId; 2] },
     Tangent { entities: [EntityId; 2] }, // Generic entity reference
+    /// Lines or arcs joined at endpoints `a` and `b` leave the joint along one
+    /// tangent line (G1 continuity), rather than just touching somewhere
+    TangentAtPoint { a: ConstraintPoint, b: ConstraintPoint },
     Equal { entities: [EntityId; 2] },
+    /// All of `entities` (lines, or circles) share one length or radius; one
+    /// constraint instead of a chain of pairwise `Equal`s
+    EqualGroup { entities: Vec<EntityId> },
     /// Symmetric constraint: p2 is the reflection of p1 across the axis line
     Symmetric { p1: ConstraintPoint, p2: ConstraintPoint, axis: EntityId },
     Fix { point: ConstraintPoint, position: [f64; 2] },
@@ -125,6 +237,15 @@ pub enum SketchConstraint {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         style: Option<DimensionStyle>,
     },
+    /// Distance between a point and the nearest edge of a circle or arc,
+    /// `|distance to center - radius|`
+    DistancePointCircle {
+        point: ConstraintPoint,
+        circle: EntityId,
+        value: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        style: Option<DimensionStyle>,
+    },
     /// Distance between two parallel lines (perpendicular distance)
     DistanceParallelLines {
         lines: [EntityId; 2],
@@ -132,6 +253,17 @@ pub enum SketchConstraint {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         style: Option<DimensionStyle>,
     },
+    /// The sketch origin lies on the infinite extension of a line
+    ThroughOrigin { line: EntityId },
+    /// `entity` stays `distance` from the projected line `external`, on the side
+    /// it is on. Measured at the center of points, circles, arcs and ellipses
+    /// and at the midpoint of lines. Only `entity` moves, so it follows the
+    /// projection when the model changes.
+    OffsetFromExternal { entity: EntityId, external: EntityId, distance: f64 },
+}
+
+fn radians() -> AngleUnit {
+    AngleUnit::Radians
 }
 
 /// Wrapper for constraints with suppression state and future metadata
@@ -140,15 +272,29 @@ pub struct SketchConstraintEntry {
     pub constraint: SketchConstraint,
     #[serde(default)]
     pub suppressed: bool,
+    /// Constraint group, e.g. one of several dimensioning schemes, whose
+    /// members are suppressed together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// When the constraint was added, counted with the sketch's entities
+    /// (see `Sketch::additions`); assigned when it is added to a sketch
+    #[serde(default)]
+    pub creation_seq: u64,
 }
 
 impl SketchConstraintEntry {
     pub fn new(constraint: SketchConstraint) -> Self {
-        Self { constraint, suppressed: false }
+        Self { constraint, suppressed: false, group: None, creation_seq: 0 }
     }
 
     pub fn suppressed(constraint: SketchConstraint) -> Self {
-        Self { constraint, suppressed: true }
+        Self { constraint, suppressed: true, group: None, creation_seq: 0 }
+    }
+
+    /// This entry as a member of `group`
+    pub fn in_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
     }
 }
 
@@ -158,6 +304,15 @@ impl From<SketchConstraint> for SketchConstraintEntry {
     }
 }
 
+/// An entity or constraint added to a sketch, as listed by `Sketch::additions`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum SketchAddition {
+    Entity { seq: u64, id: EntityId },
+    /// `index` into `Sketch::constraints`
+    Constraint { seq: u64, index: usize },
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SketchOperation {
     AddGeometry { id: EntityId, geometry: SketchGeometry },
@@ -165,6 +320,7 @@ pub enum SketchOperation {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SketchData")]
 pub struct Sketch {
     pub plane: SketchPlane,
     // Using a Vec for ordered iteration stability, but could be HashMap. 
@@ -176,6 +332,57 @@ pub struct Sketch {
     /// Maps local EntityId (in the sketch) to the stable TopoId (from the 3D kernel) it references.
     #[serde(default)]
     pub external_references: std::collections::HashMap<EntityId, crate::topo::naming::TopoId>,
+    /// Offset face outlines, redrawn from their face on regeneration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub face_offsets: Vec<super::face_offset::FaceOffset>,
+    /// Angle values are counter-clockwise turns (see `SketchConstraint::Angle`).
+    /// Missing from sketches saved while angles were unsigned; those are
+    /// converted by `migrate_angle_sense` as they're loaded.
+    #[serde(default)]
+    pub signed_angles: bool,
+    /// `creation_seq` of the next addition. Only grows, so a number freed by an
+    /// undo or delete isn't handed out again; sketches saved without it
+    /// continue from their highest number.
+    #[serde(default)]
+    pub next_seq: u64,
+}
+
+/// `Sketch` as stored, before `migrate_angle_sense`
+#[derive(Deserialize)]
+struct SketchData {
+    plane: SketchPlane,
+    entities: Vec<SketchEntity>,
+    constraints: Vec<SketchConstraintEntry>,
+    #[serde(default)]
+    history: Vec<SketchOperation>,
+    #[serde(default)]
+    external_references: std::collections::HashMap<EntityId, crate::topo::naming::TopoId>,
+    #[serde(default)]
+    face_offsets: Vec<super::face_offset::FaceOffset>,
+    #[serde(default)]
+    signed_angles: bool,
+    #[serde(default)]
+    next_seq: u64,
+}
+
+impl From<SketchData> for Sketch {
+    fn from(data: SketchData) -> Self {
+        let mut sketch = Sketch {
+            plane: data.plane,
+            entities: data.entities,
+            constraints: data.constraints,
+            history: data.history,
+            external_references: data.external_references,
+            face_offsets: data.face_offsets,
+            signed_angles: data.signed_angles,
+            next_seq: data.next_seq,
+        };
+        let entities = sketch.entities.iter().map(|e| e.creation_seq);
+        let highest = entities.chain(sketch.constraints.iter().map(|e| e.creation_seq)).max().unwrap_or(0);
+        sketch.next_seq = sketch.next_seq.max(highest + 1);
+        sketch.migrate_angle_sense();
+        sketch
+    }
 }
 
 impl Sketch {
@@ -186,25 +393,119 @@ impl Sketch {
             constraints: Vec::new(),
             history: Vec::new(),
             external_references: std::collections::HashMap::new(),
+            face_offsets: Vec::new(),
+            signed_angles: true,
+            next_seq: 1,
+        }
+    }
+
+    /// Whether entity `id` is projected from model geometry (see `external_references`)
+    pub fn is_projected(&self, id: EntityId) -> bool {
+        self.external_references.contains_key(&id)
+    }
+
+    /// Re-project the external references from the current topology.
+    /// Returns the projected entities whose reference no longer resolves; they
+    /// keep their last projected geometry.
+    pub fn reproject_external(
+        &mut self,
+        topology_manifest: &std::collections::HashMap<crate::topo::naming::TopoId, crate::topo::registry::KernelEntity>,
+    ) -> std::collections::HashSet<EntityId> {
+        let plane = &self.plane;
+        let project_to_2d = |p: [f64; 3]| -> [f64; 2] {
+            let v = [p[0] - plane.origin[0], p[1] - plane.origin[1], p[2] - plane.origin[2]];
+            [
+                v[0] * plane.x_axis[0] + v[1] * plane.x_axis[1] + v[2] * plane.x_axis[2],
+                v[0] * plane.y_axis[0] + v[1] * plane.y_axis[1] + v[2] * plane.y_axis[2],
+            ]
+        };
+
+        let mut stale = std::collections::HashSet::new();
+        let mut updates = Vec::new();
+        for (entity_id, topo_id) in &self.external_references {
+            match topology_manifest.get(topo_id).map(|e| &e.geometry) {
+                Some(crate::topo::registry::AnalyticGeometry::Line { start, end }) => {
+                    updates.push((*entity_id, SketchGeometry::Line { start: project_to_2d(*start), end: project_to_2d(*end) }));
+                }
+                // TODO: Support projecting other types (Circle -> Ellipse/Line, etc)
+                Some(_) => {}
+                None => {
+                    stale.insert(*entity_id);
+                }
+            }
+        }
+        for (id, geometry) in updates {
+            if let Some(entity) = self.entities.iter_mut().find(|e| e.id == id) {
+                entity.geometry = geometry;
+            }
         }
+        stale
     }
 
     pub fn add_entity(&mut self, geometry: SketchGeometry) -> EntityId {
         let id = EntityId::new();
-        self.entities.push(SketchEntity { id, geometry: geometry.clone(), is_construction: false });
+        let creation_seq = self.take_creation_seq();
+        self.entities.push(SketchEntity { id, geometry: geometry.clone(), is_construction: false, creation_seq, name: None, color: None, locked: false });
         self.history.push(SketchOperation::AddGeometry { id, geometry });
         id
     }
 
     pub fn add_constraint(&mut self, constraint: SketchConstraint) {
-        self.constraints.push(SketchConstraintEntry::new(constraint.clone()));
-        self.history.push(SketchOperation::AddConstraint { constraint });
+        self.push_constraint(SketchConstraintEntry::new(constraint));
     }
 
     /// Add constraint with explicit suppression state
     pub fn add_constraint_with_suppression(&mut self, constraint: SketchConstraint, suppressed: bool) {
-        self.constraints.push(SketchConstraintEntry { constraint: constraint.clone(), suppressed });
-        self.history.push(SketchOperation::AddConstraint { constraint });
+        self.push_constraint(SketchConstraintEntry { suppressed, ..SketchConstraintEntry::new(constraint) });
+    }
+
+    /// Add `entry` as the sketch's newest constraint
+    pub fn push_constraint(&mut self, mut entry: SketchConstraintEntry) {
+        entry.creation_seq = self.take_creation_seq();
+        self.history.push(SketchOperation::AddConstraint { constraint: entry.constraint.clone() });
+        self.constraints.push(entry);
+    }
+
+    /// Sequence number for a new entity or constraint; never reused
+    pub fn take_creation_seq(&mut self) -> u64 {
+        let seq = self.next_seq.max(1);
+        self.next_seq = seq + 1;
+        seq
+    }
+
+    /// Number the entities and constraints that came in without a sequence
+    /// (drawn by the client, or saved before sequences were recorded), in list
+    /// order after everything already numbered
+    pub fn number_additions(&mut self) {
+        let first = self.next_seq.max(1);
+        let unnumbered = self.entities.iter_mut().map(|e| &mut e.creation_seq)
+            .chain(self.constraints.iter_mut().map(|e| &mut e.creation_seq))
+            .filter(|seq| **seq == 0);
+        let mut next = first;
+        for seq in unnumbered {
+            *seq = next;
+            next += 1;
+        }
+        self.next_seq = next;
+    }
+
+    /// Keep numbering where `stored`, the version this sketch replaces, got
+    /// to, so numbers it handed out before an undo aren't reused
+    pub fn continue_numbering(&mut self, stored: &Sketch) {
+        self.next_seq = self.next_seq.max(stored.next_seq);
+    }
+
+    /// Entities and constraints in the order they were added, oldest first.
+    /// Ties (sketches saved before sequences were recorded) fall back to
+    /// `ensure_history`'s guess: entities, then constraints, in list order.
+    pub fn additions(&self) -> Vec<SketchAddition> {
+        let mut additions: Vec<(u64, u8, usize, SketchAddition)> = self.entities.iter().enumerate()
+            .map(|(i, e)| (e.creation_seq, 0, i, SketchAddition::Entity { seq: e.creation_seq, id: e.id }))
+            .chain(self.constraints.iter().enumerate()
+                .map(|(i, e)| (e.creation_seq, 1, i, SketchAddition::Constraint { seq: e.creation_seq, index: i })))
+            .collect();
+        additions.sort_by_key(|(seq, kind, index, _)| (*seq, *kind, *index));
+        additions.into_iter().map(|(.., addition)| addition).collect()
     }
 
     /// Toggle suppression state for a constraint by index
@@ -224,6 +525,28 @@ impl Sketch {
         }
     }
 
+    /// Suppress or unsuppress every constraint in `group`. Returns how many
+    /// constraints the group has; 0 means there is no such group.
+    pub fn set_group_suppression(&mut self, group: &str, suppressed: bool) -> usize {
+        let mut members = 0;
+        for entry in self.constraints.iter_mut().filter(|e| e.group.as_deref() == Some(group)) {
+            entry.suppressed = suppressed;
+            members += 1;
+        }
+        members
+    }
+
+    /// Names of the constraint groups, in order of first appearance
+    pub fn constraint_groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = Vec::new();
+        for group in self.constraints.iter().filter_map(|e| e.group.as_deref()) {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
     /// Get active (non-suppressed) constraints
     pub fn active_constraints(&self) -> impl Iterator<Item = &SketchConstraint> {
         self.constraints.iter()
@@ -231,6 +554,30 @@ impl Sketch {
             .map(|e| &e.constraint)
     }
 
+    /// Convert the Angle constraints of a sketch saved while angles were
+    /// unsigned. Those held either turn, so one whose lines currently turn
+    /// clockwise by its value gets the counter-clockwise equivalent (a full
+    /// turn less, or its expression negated) and keeps its geometry.
+    pub fn migrate_angle_sense(&mut self) {
+        if self.signed_angles {
+            return;
+        }
+        self.signed_angles = true;
+        let measured = super::solver::SketchSolver::constraint_current_values(self);
+        for (index, current) in measured {
+            let SketchConstraint::Angle { value, unit, style, .. } = &mut self.constraints[index].constraint else { continue };
+            let (target, current) = (unit.to_radians(*value), unit.to_radians(current));
+            let error = |target: f64| super::solver::angle_difference(current, target).abs();
+            if error(-target) >= error(target) {
+                continue;
+            }
+            *value = unit.from_radians(std::f64::consts::TAU - target);
+            if let Some(expression) = style.as_mut().and_then(|style| style.expression.as_mut()) {
+                *expression = format!("-({})", expression);
+            }
+        }
+    }
+
     /// Populates history from entities and constraints if history is empty.
     /// This is for migrating legacy sketches.
     pub fn ensure_history(&mut self) {
@@ -258,6 +605,39 @@ impl Sketch {
         }
     }
 
+    /// Returns a copy of this sketch with every local entity id replaced by `map(id)`.
+    /// Constraints, history, external references and face offset loops are
+    /// rewritten consistently; reference geometry ids are left alone. So the
+    /// copy is structurally identical but its entities don't collide with the source's
+    /// when both are emitted into the same topology manifest.
+    pub fn with_remapped_entity_ids(&self, map: impl Fn(EntityId) -> EntityId) -> Sketch {
+        let ids: std::collections::HashMap<EntityId, EntityId> = self.entities.iter().map(|e| (e.id, map(e.id))).collect();
+        let remap = |id: &mut EntityId| {
+            if let Some(new) = ids.get(id) {
+                *id = *new;
+            }
+        };
+
+        let mut sketch = self.clone();
+        sketch.entities.iter_mut().for_each(|e| remap(&mut e.id));
+        for entry in &mut sketch.constraints {
+            super::edit::constraint_ids_mut(&mut entry.constraint).into_iter().for_each(remap);
+        }
+        for operation in &mut sketch.history {
+            match operation {
+                SketchOperation::AddGeometry { id, .. } => remap(id),
+                SketchOperation::AddConstraint { constraint } => super::edit::constraint_ids_mut(constraint).into_iter().for_each(remap),
+            }
+        }
+        sketch.external_references = sketch.external_references.drain()
+            .map(|(id, topo)| (ids.get(&id).copied().unwrap_or(id), topo))
+            .collect();
+        for offset in &mut sketch.face_offsets {
+            offset.loops.iter_mut().flatten().for_each(remap);
+        }
+        sketch
+    }
+
     /// Resolve all constraint expressions using the given variable store.
     /// Updates constraint numeric values based on their stored expressions.
     /// Returns the number of expressions that were successfully resolved.
@@ -304,8 +684,10 @@ impl Sketch {
                         resolved_count += 1;
                     }
                 }
-                SketchConstraint::Angle { value, style, .. } => {
+                SketchConstraint::Angle { value, unit, style, .. } => {
+                    // Expressions evaluate to radians, the base angle unit
                     if resolve_expr_value(style, value, variables) {
+                        *value = unit.from_radians(*value);
                         resolved_count += 1;
                     }
                 }
@@ -314,7 +696,8 @@ impl Sketch {
                         resolved_count += 1;
                     }
                 }
-                SketchConstraint::DistancePointLine { value, style, .. } => {
+                SketchConstraint::DistancePointLine { value, style, .. }
+                | SketchConstraint::DistancePointCircle { value, style, .. } => {
                     if resolve_expr_value(style, value, variables) {
                         resolved_count += 1;
                     }
@@ -31,6 +31,15 @@ impl IdGenerator {
         EntityId::from_uuid(uuid)
     }
 
+    /// A generator over the same namespace whose sequence starts from the
+    /// beginning, with its own counter rather than this one's
+    pub fn restart(&self) -> IdGenerator {
+        Self {
+            namespace: self.namespace,
+            counter: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
     /// Create a child generator derived from this one.
     /// Useful for hierarchical generation (e.g. features inside a container).
     pub fn fork(&self, discriminator: &str) -> IdGenerator {
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use super::naming::TopoId;
+use super::EntityId;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -7,8 +8,15 @@ pub enum AnalyticGeometry {
     Plane { origin: [f64; 3], normal: [f64; 3] },
     Cylinder { axis_start: [f64; 3], axis_dir: [f64; 3], radius: f64 },
     Sphere { center: [f64; 3], radius: f64 },
+    /// A vertex or sketch point
+    Point { position: [f64; 3] },
     Line { start: [f64; 3], end: [f64; 3] },
     Circle { center: [f64; 3], normal: [f64; 3], radius: f64 },
+    /// Surface swept by a circle of `minor_radius` whose center runs on a circle of
+    /// `major_radius` around `axis` through `center`
+    Torus { center: [f64; 3], axis: [f64; 3], major_radius: f64, minor_radius: f64 },
+    /// Cone opening from `apex` along `axis`, `half_angle` (radians) between axis and surface
+    Cone { apex: [f64; 3], axis: [f64; 3], half_angle: f64 },
     Mesh, // Fallback for freeform
 }
 
@@ -42,9 +50,136 @@ impl AnalyticGeometry {
                 let radius_sim = 1.0 / (1.0 + (r1 - r2).abs());
                 center_sim * 0.5 + radius_sim * 0.5
             },
+            (AnalyticGeometry::Torus { center: c1, major_radius: a1, minor_radius: b1, .. },
+             AnalyticGeometry::Torus { center: c2, major_radius: a2, minor_radius: b2, .. }) => {
+                let dist = ((c1[0]-c2[0]).powi(2) + (c1[1]-c2[1]).powi(2) + (c1[2]-c2[2]).powi(2)).sqrt();
+                let center_sim = 1.0 / (1.0 + dist);
+                let radius_sim = 1.0 / (1.0 + (a1 - a2).abs() + (b1 - b2).abs());
+                center_sim * 0.5 + radius_sim * 0.5
+            },
+            (AnalyticGeometry::Cone { axis: d1, half_angle: h1, .. },
+             AnalyticGeometry::Cone { axis: d2, half_angle: h2, .. }) => {
+                let angle_sim = 1.0 / (1.0 + 10.0 * (h1 - h2).abs());
+                let dot = (d1[0]*d2[0] + d1[1]*d2[1] + d1[2]*d2[2]).abs();
+                angle_sim * 0.5 + dot * 0.5
+            },
+            (AnalyticGeometry::Point { position: p1 },
+             AnalyticGeometry::Point { position: p2 }) => {
+                let dist = ((p1[0]-p2[0]).powi(2) + (p1[1]-p2[1]).powi(2) + (p1[2]-p2[2]).powi(2)).sqrt();
+                1.0 / (1.0 + dist)
+            },
+            (AnalyticGeometry::Line { start: s1, end: e1 },
+             AnalyticGeometry::Line { start: s2, end: e2 }) => {
+                // Endpoint distance, in whichever direction the lines match best
+                let dist = |a: &[f64; 3], b: &[f64; 3]| ((a[0]-b[0]).powi(2) + (a[1]-b[1]).powi(2) + (a[2]-b[2]).powi(2)).sqrt();
+                let forward = dist(s1, s2) + dist(e1, e2);
+                let reversed = dist(s1, e2) + dist(e1, s2);
+                1.0 / (1.0 + forward.min(reversed))
+            },
+            (AnalyticGeometry::Circle { center: c1, radius: r1, .. },
+             AnalyticGeometry::Circle { center: c2, radius: r2, .. }) => {
+                let dist = ((c1[0]-c2[0]).powi(2) + (c1[1]-c2[1]).powi(2) + (c1[2]-c2[2]).powi(2)).sqrt();
+                let center_sim = 1.0 / (1.0 + dist);
+                let radius_sim = 1.0 / (1.0 + (r1 - r2).abs());
+                center_sim * 0.5 + radius_sim * 0.5
+            },
             _ => 0.0, // Different geometry types = no similarity
         }
     }
+
+    /// Variant name, e.g. "Plane"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AnalyticGeometry::Plane { .. } => "Plane",
+            AnalyticGeometry::Cylinder { .. } => "Cylinder",
+            AnalyticGeometry::Sphere { .. } => "Sphere",
+            AnalyticGeometry::Point { .. } => "Point",
+            AnalyticGeometry::Line { .. } => "Line",
+            AnalyticGeometry::Circle { .. } => "Circle",
+            AnalyticGeometry::Torus { .. } => "Torus",
+            AnalyticGeometry::Cone { .. } => "Cone",
+            AnalyticGeometry::Mesh => "Mesh",
+        }
+    }
+
+    /// Named scalar dimensions (lengths, and angles in radians), for measuring
+    /// and for matching `GeometryQuery` parameters
+    pub fn scalar_parameters(&self) -> Vec<(&'static str, f64)> {
+        match self {
+            AnalyticGeometry::Cylinder { radius, .. }
+            | AnalyticGeometry::Sphere { radius, .. }
+            | AnalyticGeometry::Circle { radius, .. } => vec![("radius", *radius)],
+            AnalyticGeometry::Line { start, end } => {
+                let length = ((end[0] - start[0]).powi(2) + (end[1] - start[1]).powi(2) + (end[2] - start[2]).powi(2)).sqrt();
+                vec![("length", length)]
+            }
+            AnalyticGeometry::Torus { major_radius, minor_radius, .. } => {
+                vec![("major_radius", *major_radius), ("minor_radius", *minor_radius)]
+            }
+            AnalyticGeometry::Cone { half_angle, .. } => vec![("half_angle", *half_angle)],
+            AnalyticGeometry::Plane { .. } | AnalyticGeometry::Point { .. } | AnalyticGeometry::Mesh => Vec::new(),
+        }
+    }
+
+    /// Points whose bounds approximate the entity's extent.
+    /// Planes and cylinders carry no boundary, so they contribute their origin /
+    /// axis start (plus the cross-section extent for cylinders); meshes contribute nothing.
+    pub fn representative_points(&self) -> Vec<[f64; 3]> {
+        // Half-extent along each world axis of a circle with the given normal
+        let circle_extent = |normal: &[f64; 3], radius: f64| {
+            let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+            let n = if len > 1e-12 { [normal[0] / len, normal[1] / len, normal[2] / len] } else { [0.0, 0.0, 1.0] };
+            n.map(|c| radius * (1.0 - c * c).max(0.0).sqrt())
+        };
+        let boxed = |center: &[f64; 3], extent: [f64; 3]| vec![
+            [center[0] - extent[0], center[1] - extent[1], center[2] - extent[2]],
+            [center[0] + extent[0], center[1] + extent[1], center[2] + extent[2]],
+        ];
+        match self {
+            AnalyticGeometry::Plane { origin, .. } => vec![*origin],
+            AnalyticGeometry::Point { position } => vec![*position],
+            AnalyticGeometry::Line { start, end } => vec![*start, *end],
+            AnalyticGeometry::Sphere { center, radius } => boxed(center, [*radius; 3]),
+            AnalyticGeometry::Circle { center, normal, radius } => boxed(center, circle_extent(normal, *radius)),
+            AnalyticGeometry::Cylinder { axis_start, axis_dir, radius } => boxed(axis_start, circle_extent(axis_dir, *radius)),
+            AnalyticGeometry::Torus { center, axis, major_radius, minor_radius } => {
+                // Outer equator, plus the tube's reach along the axis
+                let equator = circle_extent(axis, major_radius + minor_radius);
+                let flat = circle_extent(axis, 1.0);
+                boxed(center, [0, 1, 2].map(|k| equator[k] + minor_radius * (1.0 - flat[k] * flat[k]).max(0.0).sqrt()))
+            }
+            AnalyticGeometry::Cone { apex, .. } => vec![*apex],
+            AnalyticGeometry::Mesh => Vec::new(),
+        }
+    }
+}
+
+/// Select-by-geometry criteria: entities of `kind` (as in `AnalyticGeometry::kind`)
+/// whose scalar parameters are within `tolerance` of each given value, e.g.
+/// `{ kind: "Cone", parameters: { half_angle: 0.785 }, tolerance: 0.01 }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeometryQuery {
+    pub kind: String,
+    #[serde(default)]
+    pub parameters: HashMap<String, f64>,
+    #[serde(default = "GeometryQuery::default_tolerance")]
+    pub tolerance: f64,
+}
+
+impl GeometryQuery {
+    fn default_tolerance() -> f64 {
+        1e-6
+    }
+
+    pub fn matches(&self, geometry: &AnalyticGeometry) -> bool {
+        if geometry.kind() != self.kind {
+            return false;
+        }
+        let actual = geometry.scalar_parameters();
+        self.parameters.iter().all(|(name, expected)| {
+            actual.iter().any(|(n, value)| n == name && (value - expected).abs() <= self.tolerance)
+        })
+    }
 }
 
 /// Placeholder for an actual heavy kernel object (e.g. a OpenCascade/Parasolid Pointer).
@@ -72,6 +207,46 @@ pub enum ResolveResult<'a> {
     },
 }
 
+/// A topology reference held by a feature: `param_path` names the parameter,
+/// with an index for list entries, e.g. "ref_a" or "profiles[2]"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceSite {
+    pub id: TopoId,
+    pub owner: EntityId,
+    pub param_path: String,
+}
+
+/// A way to fix a lost reference, applied with `FeatureGraph::apply_zombie_repair`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RepairAction {
+    /// Point the reference at another entity
+    Replace { with: TopoId },
+    /// Remove the reference from its feature
+    ClearReference,
+    /// Suppress the feature holding the reference
+    SuppressOwner,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepairSuggestion {
+    pub action: RepairAction,
+    /// Healing score for replacements, 0.0 for the generic actions
+    pub confidence: f64,
+    pub reason: String,
+}
+
+/// A required reference that no longer resolves, with where it is held and how to fix it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zombie {
+    pub id: TopoId,
+    pub owner: EntityId,
+    pub param_path: String,
+    /// Geometry of the entity when it last existed, if known
+    pub last_geometry: Option<AnalyticGeometry>,
+    /// Healing candidates, best first, then clearing the reference and suppressing the owner
+    pub repairs: Vec<RepairSuggestion>,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct TopoRegistry {
     /// The set of topology that currently exists in the kernel.
@@ -79,6 +254,11 @@ pub struct TopoRegistry {
     
     /// IDs that were expected (referenced by features/constraints) but are missing.
     zombies: HashSet<TopoId>,
+
+    /// Geometry of entities from earlier regenerations that are gone now,
+    /// kept by `carry_history` and pruned to the zombies on validation.
+    #[serde(default)]
+    last_known: HashMap<TopoId, AnalyticGeometry>,
 }
 
 impl TopoRegistry {
@@ -102,19 +282,57 @@ impl TopoRegistry {
         self.active_topology.get(id)
     }
 
-    /// Validates a list of required references.
-    /// If any are missing, they are marked as zombies.
-    pub fn validate_references(&mut self, required_ids: &[TopoId]) -> Vec<TopoId> {
-        let mut missing = Vec::new();
-        for id in required_ids {
+    /// Keeps the geometry `previous` knew for entities missing from this
+    /// registry, so references lost in this regeneration can report it.
+    /// Call after registering the new topology.
+    pub fn carry_history(&mut self, previous: &TopoRegistry) {
+        let known = previous.active_topology.iter()
+            .map(|(id, entity)| (id, &entity.geometry))
+            .chain(previous.last_known.iter());
+        for (id, geometry) in known {
             if !self.active_topology.contains_key(id) {
-                self.zombies.insert(*id);
-                missing.push(*id);
+                self.last_known.entry(*id).or_insert_with(|| geometry.clone());
             }
         }
+    }
+
+    /// Validates the references features hold.
+    /// Missing ones are marked as zombies and returned with their last known
+    /// geometry and suggested repairs, in the order of `required`.
+    pub fn validate_references(&mut self, required: &[ReferenceSite]) -> Vec<Zombie> {
+        let mut missing = Vec::new();
+        for site in required {
+            if self.active_topology.contains_key(&site.id) {
+                continue;
+            }
+            self.zombies.insert(site.id);
+            let last_geometry = self.last_known.get(&site.id).cloned();
+            let mut repairs: Vec<RepairSuggestion> = match self.resolve_with_fallback(&site.id, last_geometry.as_ref()) {
+                ResolveResult::Exact(_) => Vec::new(),
+                ResolveResult::Fallback { entity, confidence, reason } => vec![(entity.id, confidence, reason)],
+                ResolveResult::Broken { suggestions } => suggestions,
+            }
+            .into_iter()
+            .map(|(with, confidence, reason)| RepairSuggestion { action: RepairAction::Replace { with }, confidence, reason })
+            .collect();
+            repairs.push(RepairSuggestion { action: RepairAction::ClearReference, confidence: 0.0, reason: "clear reference".to_string() });
+            repairs.push(RepairSuggestion { action: RepairAction::SuppressOwner, confidence: 0.0, reason: "suppress owner".to_string() });
+            missing.push(Zombie { id: site.id, owner: site.owner, param_path: site.param_path.clone(), last_geometry, repairs });
+        }
+        self.last_known.retain(|id, _| self.zombies.contains(id));
         missing
     }
 
+    /// Ids of the active entities matching `query`, ordered by feature and local id
+    pub fn find_by_geometry(&self, query: &GeometryQuery) -> Vec<TopoId> {
+        let mut ids: Vec<TopoId> = self.active_topology.values()
+            .filter(|entity| query.matches(&entity.geometry))
+            .map(|entity| entity.id)
+            .collect();
+        ids.sort_by_key(|id| (id.feature_id, id.local_id));
+        ids
+    }
+
     pub fn is_zombie(&self, id: &TopoId) -> bool {
         self.zombies.contains(id)
     }
@@ -228,11 +446,83 @@ mod tests {
             geometry: AnalyticGeometry::Plane { origin: [0.0; 3], normal: [0.0, 1.0, 0.0] } 
         });
 
-        let missing = registry.validate_references(&[existing_id, missing_id]);
+        let owner = EntityId::new();
+        let site = |id: TopoId, param_path: &str| ReferenceSite { id, owner, param_path: param_path.to_string() };
+        let missing = registry.validate_references(&[site(existing_id, "a"), site(missing_id, "b")]);
         
         assert_eq!(missing.len(), 1);
-        assert_eq!(missing[0], missing_id);
+        assert_eq!(missing[0].id, missing_id);
+        assert_eq!((missing[0].owner, missing[0].param_path.as_str()), (owner, "b"));
         assert!(registry.is_zombie(&missing_id));
         assert!(!registry.is_zombie(&existing_id));
     }
+
+    #[test]
+    fn test_zombie_reports_last_geometry_and_repairs() {
+        let line = |feature_id: EntityId, y: f64| KernelEntity {
+            id: TopoId::new(feature_id, 0, TopoRank::Edge),
+            geometry: AnalyticGeometry::Line { start: [0.0, y, 0.0], end: [10.0, y, 0.0] },
+        };
+        let (lost, redrawn, far) = (EntityId::new(), EntityId::new(), EntityId::new());
+        let mut previous = TopoRegistry::new();
+        previous.register(line(lost, 0.0));
+
+        // The line was deleted and drawn again at almost the same place
+        let mut registry = TopoRegistry::new();
+        registry.register(line(redrawn, 0.01));
+        registry.register(line(far, 50.0));
+        registry.carry_history(&previous);
+        let site = ReferenceSite { id: line(lost, 0.0).id, owner: EntityId::new(), param_path: "profiles[0]".to_string() };
+        let zombies = registry.validate_references(&[site]);
+
+        assert_eq!(zombies.len(), 1);
+        assert_eq!(zombies[0].last_geometry, Some(line(lost, 0.0).geometry));
+        let actions: Vec<&RepairAction> = zombies[0].repairs.iter().map(|r| &r.action).collect();
+        assert_eq!(actions, vec![
+            &RepairAction::Replace { with: line(redrawn, 0.0).id },
+            &RepairAction::ClearReference,
+            &RepairAction::SuppressOwner,
+        ]);
+
+        // Without history there is nothing to match geometry against
+        let mut fresh = TopoRegistry::new();
+        fresh.register(line(redrawn, 0.01));
+        let zombies = fresh.validate_references(&[ReferenceSite { id: line(lost, 0.0).id, owner: EntityId::new(), param_path: "ref".to_string() }]);
+        assert_eq!(zombies[0].last_geometry, None);
+        assert_eq!(zombies[0].repairs.len(), 2);
+    }
+
+    #[test]
+    fn test_find_by_geometry_matches_cone_half_angle() {
+        let mut registry = TopoRegistry::new();
+        let feat_id = EntityId::new();
+        let cone = |local_id: u64, half_angle: f64| KernelEntity {
+            id: TopoId::new(feat_id, local_id, TopoRank::Face),
+            geometry: AnalyticGeometry::Cone { apex: [0.0; 3], axis: [0.0, 0.0, 1.0], half_angle },
+        };
+        registry.register(cone(1, std::f64::consts::FRAC_PI_4));
+        registry.register(cone(2, std::f64::consts::FRAC_PI_4 + 0.005));
+        registry.register(cone(3, std::f64::consts::FRAC_PI_6));
+        registry.register(KernelEntity {
+            id: TopoId::new(feat_id, 4, TopoRank::Face),
+            geometry: AnalyticGeometry::Torus { center: [0.0; 3], axis: [0.0, 0.0, 1.0], major_radius: 5.0, minor_radius: 1.0 },
+        });
+
+        let query = GeometryQuery {
+            kind: "Cone".to_string(),
+            parameters: HashMap::from([("half_angle".to_string(), std::f64::consts::FRAC_PI_4)]),
+            tolerance: 0.01,
+        };
+        let found: Vec<u64> = registry.find_by_geometry(&query).iter().map(|id| id.local_id).collect();
+        assert_eq!(found, vec![1, 2]);
+
+        let any_torus: GeometryQuery = serde_json::from_str(r#"{"kind": "Torus"}"#).unwrap();
+        assert_eq!(registry.find_by_geometry(&any_torus).len(), 1);
+
+        // Vertices are points, not zero-radius spheres
+        registry.register(KernelEntity { id: TopoId::new(feat_id, 5, TopoRank::Vertex), geometry: AnalyticGeometry::Point { position: [1.0, 2.0, 3.0] } });
+        let query = |kind: &str| GeometryQuery { kind: kind.to_string(), parameters: HashMap::new(), tolerance: 0.0 };
+        assert_eq!(registry.find_by_geometry(&query("Point")).len(), 1);
+        assert!(registry.find_by_geometry(&query("Sphere")).is_empty());
+    }
 }
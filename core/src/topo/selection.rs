@@ -1,5 +1,7 @@
 use super::naming::TopoId;
 use super::registry::TopoRegistry;
+use crate::geometry::primitives::Aabb;
+use crate::geometry::Point3;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -124,6 +126,19 @@ impl SelectionState {
         result
     }
 
+    /// Axis-aligned bounds of the selected entities, computed from the
+    /// representative points of their analytic geometry.
+    /// Returns None if nothing selected resolves to geometry with points.
+    pub fn bounding_box(&self, registry: &TopoRegistry) -> Option<(Point3, Point3)> {
+        let mut bounds: Option<Aabb> = None;
+        for entity in self.selected.iter().filter_map(|id| registry.resolve(id)) {
+            for p in entity.geometry.representative_points() {
+                bounds.get_or_insert_with(Aabb::empty).extend(&Point3::new(p[0], p[1], p[2]));
+            }
+        }
+        bounds.map(|b| (b.min, b.max))
+    }
+
     /// Validates current selection against the registry.
     /// Removes any IDs that are now zombies (no longer exist).
     /// Returns a detailed report of what was kept and what was lost.
@@ -3,3 +3,63 @@
 fn test_placeholder_selection() {
     assert_eq!(1, 1);
 }
+
+#[test]
+fn test_selection_bounds_of_two_box_faces() {
+    use super::naming::TopoRank;
+    use super::registry::{AnalyticGeometry, KernelEntity};
+    use super::{EntityId, IdGenerator, SelectionState, TopoRegistry};
+    use crate::features::dag::FeatureGraph;
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::evaluator::runtime::Runtime;
+    use crate::sketch::types::{Sketch, SketchGeometry, SketchPlane};
+
+    // 20 x 10 rectangle extruded 5 along Z
+    let mut sketch = Sketch::new(SketchPlane::default());
+    let corners = [[0.0, 0.0], [20.0, 0.0], [20.0, 10.0], [0.0, 10.0]];
+    for i in 0..4 {
+        sketch.add_entity(SketchGeometry::Line { start: corners[i], end: corners[(i + 1) % 4] });
+    }
+    let mut graph = FeatureGraph::new();
+    let sketch_feature = Feature::new("Sketch1", FeatureType::Sketch)
+        .with_param("sketch_data", ParameterValue::Sketch(sketch));
+    let mut extrude = Feature::new("Extrude1", FeatureType::Extrude)
+        .with_param("distance", ParameterValue::Float(5.0));
+    extrude.dependencies = vec![sketch_feature.id];
+    graph.add_node(sketch_feature);
+    graph.add_node(extrude);
+    let result = Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("selection_bounds")).unwrap();
+
+    let mut registry = TopoRegistry::new();
+    for entity in result.topology_manifest.values() {
+        registry.register(entity.clone());
+    }
+    let face_along_x = |sign: f64| result.topology_manifest.values()
+        .find(|e| matches!(e.geometry, AnalyticGeometry::Plane { normal, .. } if normal[0] * sign > 0.9))
+        .map(|e| e.id)
+        .expect("box has a face along X");
+
+    let mut selection = SelectionState::new();
+    assert!(selection.bounding_box(&registry).is_none(), "Empty selection has no bounds");
+    selection.select(face_along_x(-1.0), true);
+    selection.select(face_along_x(1.0), true);
+
+    // Planar faces contribute a point on the face, so the X span is exact
+    let (min, max) = selection.bounding_box(&registry).expect("faces have bounds");
+    assert!(min.x.abs() < 1e-6 && (max.x - 20.0).abs() < 1e-6, "Spans both sides: {:?} {:?}", min, max);
+    for p in [min, max] {
+        assert!((-1e-6..=10.0 + 1e-6).contains(&p.y) && (-1e-6..=5.0 + 1e-6).contains(&p.z));
+    }
+
+    // Mixed types: a circular edge extends the bounds by its in-plane extent
+    let circle = super::naming::TopoId::new(EntityId::new(), 0, TopoRank::Edge);
+    registry.register(KernelEntity {
+        id: circle,
+        geometry: AnalyticGeometry::Circle { center: [30.0, 5.0, 2.0], normal: [0.0, 0.0, 1.0], radius: 3.0 },
+    });
+    selection.select(circle, true);
+    let (min, max) = selection.bounding_box(&registry).unwrap();
+    assert!(min.x.abs() < 1e-6 && (max.x - 33.0).abs() < 1e-6);
+    assert!(min.y <= 2.0 + 1e-6 && max.y >= 8.0 - 1e-6);
+    assert!(max.z <= 5.0 + 1e-6, "Circle in the XY plane adds no Z extent");
+}
@@ -3,3 +3,98 @@
 fn test_placeholder_stability() {
     assert_eq!(1, 1);
 }
+
+mod sketch_vertices {
+    use std::collections::HashSet;
+
+    use crate::evaluator::runtime::{EvaluationResult, Runtime};
+    use crate::features::dag::FeatureGraph;
+    use crate::features::types::{Feature, FeatureType, ParameterValue};
+    use crate::sketch::types::{ConstraintPoint, Sketch, SketchGeometry, SketchPlane};
+    use crate::topo::naming::{TopoId, TopoRank};
+    use crate::topo::{EntityId, IdGenerator, SelectionState, TopoRegistry};
+
+    fn evaluate(sketch: &Sketch) -> EvaluationResult {
+        let mut graph = FeatureGraph::new();
+        let mut feature = Feature::new("Sketch1", FeatureType::Sketch)
+            .with_param("sketch_data", ParameterValue::Sketch(sketch.clone()));
+        // Fixed feature id so separate graphs share a namespace
+        feature.id = EntityId::new_deterministic("stability_sketch");
+        graph.add_node(feature);
+        Runtime::new().evaluate(&graph.regenerate(), &IdGenerator::new("stability")).unwrap()
+    }
+
+    /// Vertices of sketch entities, leaving out the sketch origin
+    fn vertex_ids(result: &EvaluationResult) -> HashSet<TopoId> {
+        result.topology_manifest.keys().copied()
+            .filter(|id| id.rank == TopoRank::Vertex && !result.tessellation.is_reference(id))
+            .collect()
+    }
+
+    fn registry(result: &EvaluationResult) -> TopoRegistry {
+        let mut registry = TopoRegistry::new();
+        for entity in result.topology_manifest.values() {
+            registry.register(entity.clone());
+        }
+        registry
+    }
+
+    fn vertex(id: EntityId, index: u8) -> TopoId {
+        ConstraintPoint { id, index }.topo_id()
+    }
+
+    #[test]
+    fn test_sketch_vertex_ids_repeat_across_regens() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let arc = sketch.add_entity(SketchGeometry::Arc { center: [10.0, 5.0], radius: 5.0, start_angle: -1.5, end_angle: 1.5 });
+        let circle = sketch.add_entity(SketchGeometry::Circle { center: [30.0, 0.0], radius: 2.0 });
+
+        let first = vertex_ids(&evaluate(&sketch));
+        let second = vertex_ids(&evaluate(&sketch));
+        assert_eq!(first, second);
+
+        // Indices follow ConstraintPoint: line start/end, arc center/start/end, circle center
+        let expected: HashSet<TopoId> = [
+            vertex(line, 0), vertex(line, 1),
+            vertex(arc, 0), vertex(arc, 1), vertex(arc, 2),
+            vertex(circle, 0),
+        ].into_iter().collect();
+        assert_eq!(first, expected);
+    }
+
+    #[test]
+    fn test_selected_sketch_vertices_survive_entity_edits() {
+        let mut sketch = Sketch::new(SketchPlane::default());
+        let line = sketch.add_entity(SketchGeometry::Line { start: [0.0, 0.0], end: [10.0, 0.0] });
+        let arc = sketch.add_entity(SketchGeometry::Arc { center: [20.0, 0.0], radius: 3.0, start_angle: 0.0, end_angle: 2.0 });
+
+        let mut selection = SelectionState::new();
+        selection.select(vertex(line, 1), true);
+        selection.select(vertex(arc, 2), true);
+        let report = selection.validate(&registry(&evaluate(&sketch)));
+        assert_eq!(report.kept.len(), 2);
+
+        // Move the line end, resize and re-sweep the arc
+        for entity in &mut sketch.entities {
+            match &mut entity.geometry {
+                SketchGeometry::Line { end, .. } => *end = [12.0, 4.0],
+                SketchGeometry::Arc { radius, end_angle, .. } => {
+                    *radius = 4.5;
+                    *end_angle = 3.0;
+                }
+                _ => {}
+            }
+        }
+        let report = selection.validate(&registry(&evaluate(&sketch)));
+        assert!(report.lost.is_empty(), "Lost {:?}", report.lost);
+        assert_eq!(report.kept.len(), 2);
+
+        // Deleting the line loses its vertex only
+        sketch.entities.retain(|e| e.id != line);
+        let report = selection.validate(&registry(&evaluate(&sketch)));
+        assert_eq!(report.lost.len(), 1);
+        assert_eq!(report.lost[0].0, vertex(line, 1));
+        assert_eq!(selection.selected.iter().copied().collect::<Vec<_>>(), vec![vertex(arc, 2)]);
+    }
+}
@@ -30,6 +30,18 @@ impl LengthUnit {
         }
     }
 
+    /// Unit written as its symbol ("mm", "in", ...), the inverse of `Display`
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol.trim() {
+            "mm" => Some(Self::Millimeter),
+            "cm" => Some(Self::Centimeter),
+            "m" => Some(Self::Meter),
+            "in" => Some(Self::Inch),
+            "ft" => Some(Self::Foot),
+            _ => None,
+        }
+    }
+
     pub fn from_mm(&self, mm: f64) -> f64 {
         match self {
             Self::Millimeter => mm,
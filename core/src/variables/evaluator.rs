@@ -69,15 +69,15 @@ impl<'a> EvalContext<'a> {
             return Err(EvalError::CircularDependency(self.eval_path.clone()));
         }
 
-        // Look up variable
-        let var = self
-            .store
-            .get_by_name(name)
-            .ok_or_else(|| EvalError::UndefinedVariable(name.to_string()))?;
+        // Look up variable, then published measurements
+        let Some(var) = self.store.get_by_name(name) else {
+            return self.store.measurements.get(name).copied()
+                .ok_or_else(|| EvalError::UndefinedVariable(name.to_string()));
+        };
 
         // Parse expression
         let expr = super::parser::parse_expression(&var.expression)
-            .map_err(|e| EvalError::ParseError(e.message))?;
+            .map_err(|e| EvalError::ParseError(e.message()))?;
 
         // Mark as being evaluated
         self.evaluating.insert(name.to_string());
@@ -196,7 +196,7 @@ impl<'a> EvalContext<'a> {
 /// Returns value in the expression's implied unit (or dimensionless)
 pub fn evaluate(expression: &str, store: &VariableStore) -> Result<f64, EvalError> {
     let expr = super::parser::parse_expression(expression)
-        .map_err(|e| EvalError::ParseError(e.message))?;
+        .map_err(|e| EvalError::ParseError(e.message()))?;
 
     let mut ctx = EvalContext::new(store);
     ctx.eval_expr(&expr)
@@ -225,7 +225,7 @@ pub fn evaluate_variable(
 
     // Evaluate
     let expr = super::parser::parse_expression(&expression)
-        .map_err(|e| EvalError::ParseError(e.message))?;
+        .map_err(|e| EvalError::ParseError(e.message()))?;
 
     // Create temp store ref for context
     let store_ref = &*store;
@@ -269,7 +269,7 @@ pub fn evaluate_all(store: &mut VariableStore) {
             Err(e) => {
                 if let Some(var) = store.get_mut(var_id) {
                     var.cached_value = None;
-                    var.error = Some(e.message);
+                    var.error = Some(e.message());
                 }
             }
             Ok(expr) => {
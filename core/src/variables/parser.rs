@@ -13,14 +13,28 @@ use std::str::Chars;
 
 /// Parse error with location info
 #[derive(Debug, Clone, PartialEq)]
-pub struct ParseError {
-    pub message: String,
-    pub position: usize,
+pub enum ParseError {
+    /// The expression is empty or only whitespace
+    Empty,
+    Syntax { message: String, position: usize },
+}
+
+impl ParseError {
+    /// Description without the position
+    pub fn message(&self) -> String {
+        match self {
+            Self::Empty => "Expression is empty".to_string(),
+            Self::Syntax { message, .. } => message.clone(),
+        }
+    }
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Parse error at position {}: {}", self.position, self.message)
+        match self {
+            Self::Empty => write!(f, "Parse error: {}", self.message()),
+            Self::Syntax { message, position } => write!(f, "Parse error at position {}: {}", position, message),
+        }
     }
 }
 
@@ -140,22 +154,34 @@ impl<'a> Lexer<'a> {
                 }
                 '@' => {
                     self.advance();
-                    let name = self.read_identifier()?;
+                    let mut name = self.read_identifier()?;
                     if name.is_empty() {
-                        Err(ParseError {
+                        return Err(ParseError::Syntax {
                             message: "Expected variable name after @".to_string(),
                             position: pos,
-                        })
-                    } else {
-                        Ok(Token::VarRef(name))
+                        });
                     }
+                    // Published measurements are dotted: @Extrude_1.size_z
+                    while self.chars.peek() == Some(&'.') {
+                        self.advance();
+                        let part = self.read_identifier()?;
+                        if part.is_empty() {
+                            return Err(ParseError::Syntax {
+                                message: format!("Expected measurement name after @{}.", name),
+                                position: self.position,
+                            });
+                        }
+                        name.push('.');
+                        name.push_str(&part);
+                    }
+                    Ok(Token::VarRef(name))
                 }
                 c if c.is_ascii_digit() || c == '.' => self.read_number(),
                 c if c.is_ascii_alphabetic() || c == '_' => {
                     let name = self.read_identifier()?;
                     Ok(Token::Identifier(name))
                 }
-                _ => Err(ParseError {
+                _ => Err(ParseError::Syntax {
                     message: format!("Unexpected character: '{}'", c),
                     position: pos,
                 }),
@@ -218,7 +244,7 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        num_str.parse::<f64>().map(Token::Number).map_err(|_| ParseError {
+        num_str.parse::<f64>().map(Token::Number).map_err(|_| ParseError::Syntax {
             message: format!("Invalid number: '{}'", num_str),
             position: pos,
         })
@@ -259,7 +285,7 @@ impl<'a> Parser<'a> {
     fn parse(&mut self) -> Result<Expr, ParseError> {
         let expr = self.parse_additive()?;
         if self.current != Token::Eof {
-            return Err(ParseError {
+            return Err(ParseError::Syntax {
                 message: format!("Unexpected token after expression: {:?}", self.current),
                 position: self.lexer.position,
             });
@@ -368,7 +394,7 @@ impl<'a> Parser<'a> {
                         self.advance()?; // consume '('
                         let arg = self.parse_additive()?;
                         if self.current != Token::RParen {
-                            return Err(ParseError {
+                            return Err(ParseError::Syntax {
                                 message: "Expected ')' after function argument".to_string(),
                                 position: self.lexer.position,
                             });
@@ -379,7 +405,7 @@ impl<'a> Parser<'a> {
                             arg: Box::new(arg),
                         })
                     }
-                    _ => Err(ParseError {
+                    _ => Err(ParseError::Syntax {
                         message: format!("Unknown identifier: '{}'. Did you mean '@{}'?", name, name),
                         position: self.lexer.position,
                     }),
@@ -389,7 +415,7 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 let expr = self.parse_additive()?;
                 if self.current != Token::RParen {
-                    return Err(ParseError {
+                    return Err(ParseError::Syntax {
                         message: "Expected ')'".to_string(),
                         position: self.lexer.position,
                     });
@@ -397,7 +423,7 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 Ok(expr)
             }
-            _ => Err(ParseError {
+            _ => Err(ParseError::Syntax {
                 message: format!("Unexpected token: {:?}", self.current),
                 position: self.lexer.position,
             }),
@@ -408,10 +434,7 @@ impl<'a> Parser<'a> {
 /// Parse an expression string into an AST
 pub fn parse_expression(input: &str) -> Result<Expr, ParseError> {
     if input.trim().is_empty() {
-        return Err(ParseError {
-            message: "Empty expression".to_string(),
-            position: 0,
-        });
+        return Err(ParseError::Empty);
     }
     let mut parser = Parser::new(input)?;
     parser.parse()
@@ -453,6 +476,13 @@ mod parser_tests {
         assert_eq!(expr, Expr::VarRef("thickness".to_string()));
     }
 
+    #[test]
+    fn test_parse_measurement_ref() {
+        let expr = parse_expression("@Extrude_1.size_z").unwrap();
+        assert_eq!(expr, Expr::VarRef("Extrude_1.size_z".to_string()));
+        assert!(parse_expression("@Extrude_1.").is_err());
+    }
+
     #[test]
     fn test_parse_addition() {
         let expr = parse_expression("1 + 2").unwrap();
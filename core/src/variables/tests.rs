@@ -71,6 +71,20 @@ fn test_variable_store_update_expression() {
     assert_eq!(store.get(id).unwrap().expression, "2 + 2");
 }
 
+#[test]
+fn test_empty_expression_is_rejected() {
+    assert_eq!(parse_expression("  \t"), Err(ParseError::Empty));
+
+    let mut store = VariableStore::new();
+    let error = store.add(Variable::with_expression("width", " ", Unit::Dimensionless)).unwrap_err();
+    assert_eq!(error, "Variable 'width' not added: Parse error: Expression is empty");
+    assert!(store.get_by_name("width").is_none());
+
+    let id = store.add(Variable::new("x", 1.0, Unit::Dimensionless)).unwrap();
+    assert!(store.update_expression(id, "").unwrap_err().contains("Expression is empty"));
+    assert_eq!(store.get(id).unwrap().expression, "1", "Rejected update leaves the expression");
+}
+
 #[test]
 fn test_variable_store_ordering() {
     let mut store = VariableStore::new();
@@ -1,5 +1,6 @@
 //! Core types for the variable system.
 
+use super::parser::ParseError;
 use crate::topo::EntityId;
 use crate::units::LengthUnit;
 use serde::{Deserialize, Serialize};
@@ -147,6 +148,10 @@ pub struct VariableStore {
     pub by_name: HashMap<String, EntityId>,
     /// User-defined ordering for UI display
     pub order: Vec<EntityId>,
+    /// Read-only values measured from the last regeneration, in base units,
+    /// referenced as `@Feature.measure` (see `analysis::bindings`)
+    #[serde(skip)]
+    pub measurements: HashMap<String, f64>,
 }
 
 impl VariableStore {
@@ -161,6 +166,9 @@ impl VariableStore {
         if self.by_name.contains_key(&variable.name) {
             return Err(format!("Variable '{}' already exists", variable.name));
         }
+        if variable.expression.trim().is_empty() {
+            return Err(format!("Variable '{}' not added: {}", variable.name, ParseError::Empty));
+        }
 
         let id = variable.id;
         self.by_name.insert(variable.name.clone(), id);
@@ -187,6 +195,9 @@ impl VariableStore {
     /// Update a variable's expression
     pub fn update_expression(&mut self, id: EntityId, expression: &str) -> Result<(), String> {
         if let Some(var) = self.variables.get_mut(&id) {
+            if expression.trim().is_empty() {
+                return Err(format!("Variable '{}' not updated: {}", var.name, ParseError::Empty));
+            }
             var.expression = expression.to_string();
             var.cached_value = None; // Invalidate cache
             var.error = None;
@@ -0,0 +1,73 @@
+use cad_core::document::{diff, Document, FeatureChange, VariableChange};
+use cad_core::features::types::ParameterValue;
+use cad_core::topo::EntityId;
+
+/// Fixtures are saved documents; the graph sits under "graph"
+fn load(json: &str) -> Document {
+    let saved: serde_json::Value = serde_json::from_str(json).unwrap();
+    serde_json::from_value(saved["graph"].clone()).unwrap()
+}
+
+fn id(n: u128) -> EntityId {
+    EntityId(uuid::Uuid::from_u128(n))
+}
+
+#[test]
+fn test_diff_fixture_documents() {
+    let before = load(include_str!("fixtures/diff_before.json"));
+    let after = load(include_str!("fixtures/diff_after.json"));
+
+    let changes = diff(&before, &after);
+    assert_eq!(changes.features, vec![FeatureChange::ParameterChanged {
+        id: id(2),
+        name: "Extrude 1".to_string(),
+        parameter: "distance".to_string(),
+        from: Some(Box::new(ParameterValue::Float(10.0))),
+        to: Some(Box::new(ParameterValue::Float(12.5))),
+    }], "Round-off in draft_angle is not a change");
+    assert_eq!(changes.variables, vec![VariableChange::Added {
+        id: id(0xa2),
+        name: "width".to_string(),
+        expression: "@thickness * 4".to_string(),
+        unit: "mm".to_string(),
+    }]);
+    assert_eq!(
+        changes.to_string(),
+        "Features:\n  ~ Extrude 1: distance 10 -> 12.5\nVariables:\n  + @width = @thickness * 4 mm\n"
+    );
+
+    let json = serde_json::to_value(&changes).unwrap();
+    assert_eq!(json["features"][0]["kind"], "ParameterChanged");
+    assert_eq!(json["features"][0]["to"], serde_json::json!({ "Float": 12.5 }));
+    assert_eq!(json["variables"][0]["kind"], "Added");
+
+    assert!(diff(&after, &after).is_empty());
+    let reverse = diff(&after, &before);
+    assert!(matches!(&reverse.variables[..], [VariableChange::Removed { name, .. }] if name == "width"));
+}
+
+#[test]
+fn test_fixture_geometry_hash_is_reproducible() {
+    use cad_core::evaluator::runtime::{EvaluationSummary, Runtime};
+    use cad_core::topo::IdGenerator;
+
+    let summarize = |json: &str| -> EvaluationSummary {
+        let program = load(json).regenerate();
+        Runtime::new().evaluate(&program, &IdGenerator::new("golden")).unwrap().summary()
+    };
+
+    // Two evaluations of the same program on the same seed agree exactly
+    let before = summarize(include_str!("fixtures/diff_before.json"));
+    assert!(before.triangles > 0, "Fixture should produce geometry");
+    assert_eq!(before, summarize(include_str!("fixtures/diff_before.json")));
+    // Golden value: a kernel or evaluator change that alters this model must
+    // update it deliberately
+    assert_eq!(before.geometry_hash, "1fff41fe4c525e16");
+
+    // Saving and reloading the document does not change the model
+    let document = load(include_str!("fixtures/diff_before.json"));
+    let mut reloaded: Document = serde_json::from_str(&serde_json::to_string(&document).unwrap()).unwrap();
+    let program = reloaded.regenerate();
+    let result = Runtime::new().evaluate(&program, &IdGenerator::new("golden")).unwrap();
+    assert_eq!(result.summary(), before);
+}
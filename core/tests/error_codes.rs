@@ -0,0 +1,43 @@
+use cad_core::errors::{CadError, ErrorCode};
+use cad_core::kernel::KernelOpError;
+
+/// One ERROR_UPDATE payload per code, pinning the shape of its details
+fn fixtures() -> serde_json::Map<String, serde_json::Value> {
+    serde_json::from_str(include_str!("fixtures/error_codes.json")).unwrap()
+}
+
+#[test]
+fn test_every_code_has_a_fixture_matching_its_contract() {
+    let fixtures = fixtures();
+    let mut named: Vec<&str> = fixtures.keys().map(String::as_str).collect();
+    let mut registered: Vec<&str> = ErrorCode::ALL.iter().map(|c| c.as_str()).collect();
+    named.sort_unstable();
+    registered.sort_unstable();
+    assert_eq!(named, registered);
+
+    for (name, payload) in fixtures {
+        let error: CadError = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(error.code.as_str(), name);
+        assert_eq!(error.severity, error.code.default_severity(), "{}", name);
+        error.check_details().unwrap();
+        assert_eq!(serde_json::to_value(&error).unwrap(), payload, "{} round-trips", name);
+
+        // The same payload built through the constructor
+        let mut built = CadError::new(error.code, error.message.clone());
+        for (key, value) in error.details.as_object().unwrap() {
+            built = built.with_detail(key, value);
+        }
+        assert_eq!(built, error, "{}", name);
+    }
+}
+
+#[test]
+fn test_kernel_errors_map_to_registered_codes() {
+    let error = CadError::from(KernelOpError::LimitExceeded("STEP file is too large".into()));
+    assert_eq!(error.code, ErrorCode::LimitExceeded);
+    assert_eq!(error.message, "Limit exceeded: STEP file is too large");
+    error.check_details().unwrap();
+
+    let error = CadError::from(cad_core::evaluator::runtime::KernelError::FeatureError("Profile is empty".into()));
+    assert_eq!((error.code, error.message.as_str()), (ErrorCode::FeatureError, "Feature error: Profile is empty"));
+}
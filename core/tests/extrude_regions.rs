@@ -12,6 +12,10 @@ fn make_line(x1: f64, y1: f64, x2: f64, y2: f64) -> SketchEntity {
             end: [x2, y2],
         },
         is_construction: false,
+        creation_seq: 0,
+        name: None,
+        color: None,
+        locked: false,
     }
 }
 
@@ -0,0 +1,82 @@
+use cad_core::kernel::{default_kernel, GeometryKernel, StepImportLimits, TruckKernel};
+
+/// Sorted mesh vertices of an imported solid, rounded to 1e-6 mm
+fn vertices(kernel: &TruckKernel, solid: &<TruckKernel as GeometryKernel>::Solid) -> Vec<[i64; 3]> {
+    let mesh = kernel.tessellate(solid).unwrap();
+    let mut points: Vec<[i64; 3]> = mesh.positions.iter().map(|p| [p.x, p.y, p.z].map(|c| (c * 1e6).round() as i64)).collect();
+    points.sort_unstable();
+    points.dedup();
+    points
+}
+
+fn bounds(kernel: &TruckKernel, solid: &<TruckKernel as GeometryKernel>::Solid) -> ([f64; 3], [f64; 3]) {
+    let mesh = kernel.tessellate(solid).unwrap();
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in &mesh.positions {
+        for (i, c) in [p.x, p.y, p.z].into_iter().enumerate() {
+            min[i] = min[i].min(c);
+            max[i] = max[i].max(c);
+        }
+    }
+    (min, max)
+}
+
+fn assert_close(actual: [f64; 3], expected: [f64; 3]) {
+    assert!(actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-6), "{:?} != {:?}", actual, expected);
+}
+
+#[test]
+fn test_inch_file_matches_mm_file_after_scaling() {
+    let kernel = default_kernel();
+    let mm = kernel.import_step(include_str!("fixtures/block_mm.step")).unwrap();
+    let inch = kernel.import_step(include_str!("fixtures/block_inch.step")).unwrap();
+
+    assert_eq!((mm.unit.as_str(), mm.scale_to_mm), ("mm", 1.0));
+    assert_eq!(inch.unit, "inch");
+    assert!((inch.scale_to_mm - 25.4).abs() < 1e-12);
+    assert!(mm.warnings.is_empty() && inch.warnings.is_empty(), "{:?} {:?}", mm.warnings, inch.warnings);
+    assert_eq!(mm.solids.len(), 1);
+    assert_eq!(inch.solids.len(), 1);
+    assert_eq!(inch.solids[0].name, "Block");
+
+    let (min, max) = bounds(&kernel, &inch.solids[0].solid);
+    assert_close(min, [0.0; 3]);
+    assert_close(max, [25.4, 50.8, 76.2]);
+    assert_eq!(vertices(&kernel, &mm.solids[0].solid), vertices(&kernel, &inch.solids[0].solid));
+}
+
+#[test]
+fn test_assembly_places_each_instance_and_skips_unsupported_shapes() {
+    let kernel = default_kernel();
+    let result = kernel.import_step(include_str!("fixtures/bracket_assembly_inch.step")).unwrap();
+
+    let names: Vec<_> = result.solids.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["Block", "Block"]);
+    // 10 in along x, unrotated
+    assert_eq!(result.solids[0].transform[0][3], 254.0);
+    let (min, max) = bounds(&kernel, &result.solids[0].solid);
+    assert_close(min, [254.0, 0.0, 0.0]);
+    assert_close(max, [279.4, 50.8, 76.2]);
+    // 5 in along y, turned 90 degrees about z
+    let (min, max) = bounds(&kernel, &result.solids[1].solid);
+    assert_close(min, [-50.8, 127.0, 0.0]);
+    assert_close(max, [0.0, 152.4, 76.2]);
+
+    assert_eq!(result.warnings, ["#240: SHELL_BASED_SURFACE_MODEL is not supported; skipped"]);
+}
+
+#[test]
+fn test_import_limits() {
+    let kernel = default_kernel();
+    let data = include_str!("fixtures/bracket_assembly_inch.step");
+    let limits = StepImportLimits { max_solids: 1, ..Default::default() };
+    let result = kernel.import_step_limited(data, &limits).unwrap();
+    assert_eq!(result.solids.len(), 1);
+    assert!(result.warnings.iter().any(|w| w.contains("solid count limit reached; 1 solid(s) skipped")), "{:?}", result.warnings);
+
+    let limits = StepImportLimits { max_bytes: 1024, ..Default::default() };
+    assert!(kernel.import_step_limited(data, &limits).is_err());
+    let limits = StepImportLimits { max_entities: 10, ..Default::default() };
+    assert!(kernel.import_step_limited(data, &limits).is_err());
+}
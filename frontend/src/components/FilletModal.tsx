@@ -17,6 +17,9 @@ interface FilletModalProps {
 const FilletModal: Component<FilletModalProps> = (props) => {
     // Store radius as expression string to support variables
     const [radiusExpr, setRadiusExpr] = createSignal("1");
+    // End radius of a variable-radius fillet; empty keeps the radius constant
+    const [radiusEndExpr, setRadiusEndExpr] = createSignal("");
+    const [continuity, setContinuity] = createSignal<"G1" | "G2">("G1");
 
     // Store selected edges locally to avoid clearing when global selection changes
     // This allows accumulating selection or handling implicit deselection gracefully
@@ -28,8 +31,16 @@ const FilletModal: Component<FilletModalProps> = (props) => {
     // Initialize from props
     onMount(() => {
         const params = props.initialParams;
-        if (params['radius'] && typeof params['radius'] === 'object' && 'Float' in params['radius']) {
-            setRadiusExpr(String((params['radius'] as any).Float));
+        const exprOf = (value: ParameterValue | undefined) => {
+            if (value && 'Float' in value) return String(value.Float);
+            if (value && 'Expression' in value) return value.Expression;
+            return null;
+        };
+        setRadiusExpr(exprOf(params['radius']) ?? "1");
+        setRadiusEndExpr(exprOf(params['radius_end']) ?? "");
+        const saved = params['continuity'];
+        if (saved && 'String' in saved && (saved.String === 'G1' || saved.String === 'G2')) {
+            setContinuity(saved.String);
         }
 
         // Initialize selection
@@ -125,15 +136,37 @@ const FilletModal: Component<FilletModalProps> = (props) => {
         }
     });
 
-    const handleRadiusChange = (expr: string) => {
-        setRadiusExpr(expr);
+    // Radii referencing variables are saved as expressions so they follow the variable
+    const radiusParam = (expr: string): ParameterValue | null => {
         const variables = props.graph?.variables || { variables: {}, order: [] };
         const val = parseValueOrExpression(expr, variables);
-        if (val !== null) {
-            props.onUpdate(props.featureId, { radius: { Float: val } });
+        if (val === null) return null;
+        return expr.includes('@') ? { Expression: expr } : { Float: val };
+    };
+
+    const handleRadiusChange = (expr: string) => {
+        setRadiusExpr(expr);
+        const param = radiusParam(expr);
+        if (param !== null) {
+            props.onUpdate(props.featureId, { radius: param });
         }
     };
 
+    const handleRadiusEndChange = (expr: string) => {
+        setRadiusEndExpr(expr);
+        // Cleared: back to a constant radius
+        const param = expr.trim() === "" ? radiusParam(radiusExpr()) : radiusParam(expr);
+        if (param !== null) {
+            props.onUpdate(props.featureId, { radius_end: param });
+        }
+    };
+
+    const handleContinuityChange = (value: string) => {
+        if (value !== 'G1' && value !== 'G2') return;
+        setContinuity(value);
+        props.onUpdate(props.featureId, { continuity: { String: value } });
+    };
+
     const handleClearSelection = () => {
         setSelectedDetailEdges([]);
         syncEdgesToBackend([]);
@@ -179,6 +212,30 @@ const FilletModal: Component<FilletModalProps> = (props) => {
                             placeholder="1.0"
                         />
                     </div>
+                    <div class="flex gap-2 items-center mt-1">
+                        <span class="text-xs text-gray-400 w-12">End:</span>
+                        <NumericInput
+                            value={radiusEndExpr()}
+                            onChange={handleRadiusEndChange}
+                            onEvaluate={(expr) => parseValueOrExpression(expr, props.graph?.variables || { variables: {}, order: [] })}
+                            variables={props.graph?.variables || { variables: {}, order: [] }}
+                            unit="mm"
+                            step={0.5}
+                            min={0.01}
+                            placeholder="Same as radius"
+                        />
+                    </div>
+                    <div class="flex gap-2 items-center mt-1">
+                        <span class="text-xs text-gray-400 w-12">Blend:</span>
+                        <select
+                            value={continuity()}
+                            onInput={(e) => handleContinuityChange(e.currentTarget.value)}
+                            class="bg-gray-700 text-white p-1 rounded text-sm border border-gray-600 outline-none focus:border-blue-500"
+                        >
+                            <option value="G1">Tangent (G1)</option>
+                            <option value="G2">Curvature (G2)</option>
+                        </select>
+                    </div>
                 </div>
 
                 <div class="h-px bg-gray-700 w-full"></div>
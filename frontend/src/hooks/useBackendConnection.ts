@@ -1,5 +1,5 @@
 import { createSignal, onMount, onCleanup, type Accessor } from 'solid-js';
-import { type FeatureGraphState, type Tessellation, type SolveResult, type Sketch, type KernelError, type WebSocketCommand } from '../types';
+import { type FeatureGraphState, type Tessellation, type SolveResult, type SketchAnalysis, type Sketch, type KernelError, type WebSocketCommand } from '../types';
 
 export interface SelectionGroup {
     name: string;
@@ -135,13 +135,27 @@ export function useBackendConnection(props: BackendConnectionProps) {
                     } catch (e) {
                         console.error("Failed to parse sketch status", e);
                     }
+                } else if (msg.startsWith("SKETCH_ANALYSIS:")) {
+                    try {
+                        const json = msg.substring("SKETCH_ANALYSIS:".length);
+                        const data = JSON.parse(json) as SketchAnalysis;
+                        setSolveResult(prev => prev && {
+                            ...prev,
+                            entity_statuses: data.entity_statuses,
+                            analysis_pending: false,
+                        });
+                    } catch (e) {
+                        console.error("Failed to parse sketch analysis", e);
+                    }
                 } else if (msg.startsWith("REGIONS_UPDATE:")) {
                     try {
                         const json = msg.substring("REGIONS_UPDATE:".length);
                         const data = JSON.parse(json);
-                        console.log("Got backend regions:", data.length, "regions");
-                        console.log("First backend region:", JSON.stringify(data[0]));
-                        setBackendRegions(data);
+                        // Regions are computed on the solved sketch; the solve status comes along
+                        const regions = data.regions;
+                        console.log("Got backend regions:", regions.length, "regions, solve converged=", data.solve?.converged);
+                        console.log("First backend region:", JSON.stringify(regions[0]));
+                        setBackendRegions(regions);
                     } catch (e) {
                         console.error("Failed to parse regions update", e);
                     }
@@ -164,7 +178,7 @@ export function useBackendConnection(props: BackendConnectionProps) {
                             code: data.code || 'UNKNOWN',
                             message: data.message || 'Unknown error',
                             severity: data.severity || 'error',
-                            context: data.context,
+                            details: data.details,
                             timestamp: Date.now()
                         };
                         console.error("Kernel error:", error);
@@ -155,11 +155,21 @@ export function useDimensionSystem(props: UseDimensionSystemProps) {
                     result = { Distance: { value: dist(p, projection) } };
                 }
                 displayPosition = [(line1.start[0] + line1.end[0]) / 2, (line1.start[1] + line1.end[1]) / 2]; // Approximation
-            } else { // Angle
-                const angle1 = Math.atan2(dy1, dx1);
-                const angle2 = Math.atan2(dy2, dx2);
-                let angle = Math.abs(angle1 - angle2);
-                if (angle > Math.PI) angle = 2 * Math.PI - angle;
+            } else { // Angle: counter-clockwise from line 1 to line 2, both pointing away from their corner
+                const ends1 = [line1.start, line1.end];
+                const ends2 = [line2.start, line2.end];
+                let corner = [0, 0];
+                for (const i of [0, 1]) {
+                    for (const j of [0, 1]) {
+                        if (dist(ends1[i], ends2[j]) < dist(ends1[corner[0]], ends2[corner[1]])) corner = [i, j];
+                    }
+                }
+                const sign1 = corner[0] === 0 ? 1 : -1;
+                const sign2 = corner[1] === 0 ? 1 : -1;
+                const angle1 = Math.atan2(sign1 * dy1, sign1 * dx1);
+                const angle2 = Math.atan2(sign2 * dy2, sign2 * dx2);
+                let angle = angle2 - angle1;
+                if (angle < 0) angle += 2 * Math.PI;
                 result = { Angle: { value: angle * 180 / Math.PI } };
                 displayPosition = [
                     (line1.start[0] + line1.end[0] + line2.start[0] + line2.end[0]) / 4,
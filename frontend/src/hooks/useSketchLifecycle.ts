@@ -102,7 +102,8 @@ export function useSketchLifecycle(props: UseSketchLifecycleProps) {
             plane: plane,
             entities: [],
             constraints: [],
-            history: []
+            history: [],
+            signed_angles: true
         };
 
         setCurrentSketch(newSketch);
@@ -49,7 +49,8 @@ export function useSketching(props: UseSketchingProps) {
     plane: { type: 'xy', origin: [0, 0, 0], x_axis: [1, 0, 0], y_axis: [0, 1, 0], z_axis: [0, 0, 1] },
     entities: [],
     constraints: [],
-    history: []
+    history: [],
+    signed_angles: true
   });
   // Camera alignment trigger for sketch mode
   const [cameraAlignPlane, setCameraAlignPlane] = createSignal<SketchPlane | null>(null);
@@ -202,10 +202,10 @@ export class SlotTool extends BaseTool {
             constraints.push({ Coincident: { points: [{ id: l2_id, index: 1 }, { id: a2_id, index: 1 }] } }); // L2 End - A2 Start (Bottom)
 
             // Tangency
-            constraints.push({ Tangent: { points: [{ id: l1_id, index: 0 }, { id: a1_id, index: 1 }] } });
-            constraints.push({ Tangent: { points: [{ id: l1_id, index: 1 }, { id: a2_id, index: 2 }] } });
-            constraints.push({ Tangent: { points: [{ id: l2_id, index: 0 }, { id: a1_id, index: 2 }] } });
-            constraints.push({ Tangent: { points: [{ id: l2_id, index: 1 }, { id: a2_id, index: 1 }] } });
+            constraints.push({ TangentAtPoint: { a: { id: l1_id, index: 0 }, b: { id: a1_id, index: 1 } } });
+            constraints.push({ TangentAtPoint: { a: { id: l1_id, index: 1 }, b: { id: a2_id, index: 2 } } });
+            constraints.push({ TangentAtPoint: { a: { id: l2_id, index: 0 }, b: { id: a1_id, index: 2 } } });
+            constraints.push({ TangentAtPoint: { a: { id: l2_id, index: 1 }, b: { id: a2_id, index: 1 } } });
         }
 
         return { entities, constraints };
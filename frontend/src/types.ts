@@ -10,17 +10,24 @@ export interface Tessellation {
     // Maps TopoId feature_id -> FeatureGraph node UUID
     // Enables viewport selection to map back to features
     feature_id_map?: Record<string, string>;
+    hidden_features?: string[];
+    // Present while the center of mass marker is shown (ShowCenterOfMass)
+    center_of_mass?: [number, number, number];
+    // u, v per vertex, present while enabled with SetRenderOptions
+    uvs?: number[];
 }
 
-export type FeatureType = 'Sketch' | 'Extrude' | 'Revolve' | 'Fillet' | 'Chamfer' | 'Boolean' | 'Cut' | 'LinearPattern' | 'CircularPattern' | 'Plane' | 'Axis' | 'Point';
+export type FeatureType = 'Sketch' | 'Extrude' | 'Revolve' | 'Fillet' | 'Chamfer' | 'Boolean' | 'Cut' | 'LinearPattern' | 'CircularPattern' | 'Plane' | 'Axis' | 'Point' | 'ImportedBody' | 'Box' | 'Torus' | 'Cylinder';
 
 export interface Feature {
     id: string; // EntityId is UUID string
     name: string;
     feature_type: FeatureType;
     suppressed: boolean;
+    hidden?: boolean; // Drawn or not; hidden geometry stays in the model
     parameters: Record<string, any>; // ParameterValue equivalent
     dependencies?: string[]; // IDs of features this feature depends on
+    error?: string; // Set when an input was deleted out from under the feature
 }
 
 // Graph structure from backend
@@ -29,6 +36,21 @@ export interface FeatureGraphState {
     sort_order: string[];
     variables?: VariableStore;
     rollback_point?: string;  // If set, regeneration stops at this feature (inclusive)
+    pending_rebuild?: string[];  // Built from a sketch being edited live; shown as of before the edit
+}
+
+// IMPORT payload: what an ImportStep added
+export interface ImportReport {
+    features: string[];  // ImportedBody feature ids, one per placed solid
+    unit: string;        // The file's length unit; geometry is converted to mm
+    scale_to_mm: number;
+    warnings: string[];  // Entities skipped and limits hit
+}
+
+// SKETCH_RENDER payload: one sketch's curves, drawn without regenerating the model
+export interface SketchRender {
+    feature_id: string;
+    tessellation: Tessellation;
 }
 
 // Variable unit types (matching backend)
@@ -68,12 +90,31 @@ export interface SketchGeometry {
     Arc?: { center: [number, number], radius: number, start_angle: number, end_angle: number };
     Point?: { pos: [number, number] };
     Ellipse?: { center: [number, number], semi_major: number, semi_minor: number, rotation: number };
+    EllipseArc?: { center: [number, number], semi_major: number, semi_minor: number, rotation: number, start_angle: number, end_angle: number };
 }
 
 export interface SketchEntity {
     id: string;
     geometry: SketchGeometry;
     is_construction?: boolean;
+    /** Order added, shared with constraints; the server numbers entities added without one */
+    creation_seq?: number;
+    /** Name other systems can refer to the entity by; unique within its sketch */
+    name?: string;
+    /** Display color as RGBA, each 0..1 */
+    color?: [number, number, number, number];
+    /** Held in place by the solver, as if every point were fixed */
+    locked?: boolean;
+}
+
+/** A sketch entity given by id or by name */
+export type EntityRef = string;
+
+/** Partial update of an entity's metadata; null clears the name or color */
+export interface EntityMetaUpdate {
+    name?: string | null;
+    color?: [number, number, number, number] | null;
+    locked?: boolean;
 }
 
 export type EntityId = string;
@@ -114,6 +155,7 @@ export interface DimensionStyle {
     driven: boolean;    // true = reference-only, false = driving constraint
     offset: [number, number];  // Position offset for annotation text
     expression?: string;  // Optional expression (e.g., "@thickness") for re-evaluation when variables change
+    precision?: number;  // Decimal places of the displayed value (2 when unset)
 }
 
 export interface SketchConstraint {
@@ -128,17 +170,24 @@ export interface SketchConstraint {
     Parallel?: { lines: [EntityId, EntityId] };
     Perpendicular?: { lines: [EntityId, EntityId] };
     Tangent?: { entities: [EntityId, EntityId] };
+    TangentAtPoint?: { a: ConstraintPoint, b: ConstraintPoint };
     Equal?: { entities: [EntityId, EntityId] };
+    EqualGroup?: { entities: EntityId[] };
     Symmetric?: { p1: ConstraintPoint, p2: ConstraintPoint, axis: EntityId };
     Fix?: { point: ConstraintPoint, position: [number, number] };
     DistancePointLine?: { point: ConstraintPoint, line: EntityId, value: number, style?: DimensionStyle };
+    DistancePointCircle?: { point: ConstraintPoint, circle: EntityId, value: number, style?: DimensionStyle };
     DistanceParallelLines?: { lines: [EntityId, EntityId], value: number, style?: DimensionStyle };
+    ThroughOrigin?: { line: EntityId };
+    OffsetFromExternal?: { entity: EntityId; external: EntityId; distance: number };
 }
 
 /** Wrapper for constraints with suppression state */
 export interface SketchConstraintEntry {
     constraint: SketchConstraint;
     suppressed?: boolean;
+    /** Order added, shared with entities */
+    creation_seq?: number;
 }
 
 /** Helper to wrap a SketchConstraint in a SketchConstraintEntry */
@@ -157,6 +206,12 @@ export interface Sketch {
     constraints: SketchConstraintEntry[];
     history: SketchOperation[];
     external_references?: Record<string, TopoId>;
+    /** Offset face outlines, redrawn from their face on regeneration */
+    face_offsets?: { face: TopoId, offset: number, loops: string[][] }[];
+    /** Angles are counter-clockwise turns; unset on sketches saved before, which the backend migrates */
+    signed_angles?: boolean;
+    /** creation_seq of the next addition; never reused after an undo */
+    next_seq?: number;
 }
 
 /** A detected closed region in a sketch (for extrude profile selection) */
@@ -184,7 +239,18 @@ export type ParameterValue =
     | { Reference: any }
     | { List: string[] }
     | { ProfileRegions: [number, number][][][] } // Profile regions with 2D boundary points
-    | { Expression: string }; // Variable reference expression, e.g. "@thickness * 2"
+    | { Expression: string } // Variable reference expression, e.g. "@thickness * 2"
+    | { RegionSpecs: RegionSpec[] }; // Per-region bodies of a multi-body extrude
+
+/** One region of a multi-body extrude, keyed by the region's stable_id */
+export interface RegionSpec {
+    region_key: string;
+    /** Depth expression replacing the feature's distance */
+    depth_override?: string | null;
+    direction?: "Normal" | "Reverse";
+    /** Centroid when picked, used to re-match the region if its key drifts */
+    centroid?: [number, number] | null;
+}
 
 
 // Snap types for sketch snapping
@@ -248,6 +314,53 @@ export interface SolveResult {
     status_message: string;
     /** Per-entity constraint status for visual DOF indicators */
     entity_statuses?: EntityConstraintStatus[];
+    /** Indices of constraints on projected geometry whose model reference was lost */
+    stale_external_constraints?: number[];
+    /** Redundancy/conflict analysis follows in a SKETCH_ANALYSIS message */
+    analysis_pending?: boolean;
+    /** Curve lengths, and totals for the entities highlighted in the UpdateFeature */
+    measurements?: SketchMeasurements;
+    /** Drawing geometry of the styled dimensions, in sketch coordinates */
+    dimension_graphics?: DimensionGraphics[];
+    /** Entities and constraints in the order they were added, oldest first */
+    additions?: SketchAddition[];
+}
+
+/** One step of a sketch's history; `index` is into the sketch's constraints */
+export type SketchAddition =
+    | { kind: "Entity"; seq: number; id: string }
+    | { kind: "Constraint"; seq: number; index: number };
+
+/** Lines, arrowheads and text of one dimension, keyed by its constraint index */
+export interface DimensionGraphics {
+    constraint_index: number;
+    driven: boolean;
+    witness_lines: [[number, number], [number, number]][];
+    dimension_lines: [[number, number], [number, number]][];
+    /** Counter-clockwise arc of an angle dimension (radians) */
+    arc?: { center: [number, number]; radius: number; start_angle: number; end_angle: number };
+    arrowheads: { tip: [number, number]; direction: [number, number] }[];
+    /** Text centered at `anchor`, turned `rotation` radians; `outside` when it sits on a leader */
+    text: { anchor: [number, number]; rotation: number; content: string; outside: boolean };
+}
+
+/** Live sketch lengths in the document unit; areas in that unit squared */
+export interface SketchMeasurements {
+    unit: "Millimeter" | "Centimeter" | "Meter" | "Inch" | "Foot";
+    entity_lengths: Record<string, number>;
+    highlight_length: number;
+    highlight_area: number;
+}
+
+/** Deferred redundancy/conflict analysis of the last solve (SKETCH_ANALYSIS) */
+export interface SketchAnalysis {
+    redundant_constraints: { constraint_index: number; duplicates_index: number | null; reason: string }[];
+    conflicts: {
+        unsatisfied_constraints: number[];
+        constraint_errors: [number, number][];
+        possible_conflicts: [number, number, string][];
+    } | null;
+    entity_statuses: EntityConstraintStatus[];
 }
 
 /** Per-entity constraint status for visual DOF indicators */
@@ -258,7 +371,9 @@ export interface EntityConstraintStatus {
     total_dof: number;
     /** DOF consumed by constraints affecting this entity */
     constrained_dof: number;
-    /** Remaining DOF (total_dof - constrained_dof, clamped to >= 0) */
+    /** DOF held by the implicit fixes of a locked entity (all of them, or 0) */
+    locked_dof?: number;
+    /** Remaining DOF (total_dof - constrained_dof - locked_dof, clamped to >= 0) */
     remaining_dof: number;
     /** True if all entity DOF are consumed by constraints */
     is_fully_constrained: boolean;
@@ -288,11 +403,28 @@ export interface ShortcutConfig {
 // ===== Kernel Error Types =====
 
 /** Error codes for kernel errors */
+// Mirrors core::errors::ErrorCode; each code's details keys are documented there
 export type KernelErrorCode =
-    | 'REGEN_FAILED'
+    | 'COMMAND_ERROR'
     | 'FEATURE_ERROR'
-    | 'CONSTRAINT_ERROR'
-    | 'PARSE_ERROR'
+    | 'REGEN_FAILED'
+    | 'SKETCH_SOLVE_FAILED'
+    | 'OPEN_PROFILE'
+    | 'KERNEL_INVALID_GEOMETRY'
+    | 'KERNEL_OPERATION_FAILED'
+    | 'KERNEL_TESSELLATION_FAILED'
+    | 'KERNEL_NOT_IMPLEMENTED'
+    | 'ZOMBIE_REFERENCE'
+    | 'VALIDATION_FAILED'
+    | 'LIMIT_EXCEEDED'
+    | 'ID_COLLISION'
+    | 'BODY_NAME_LOST'
+    | 'IMPORT_FAILED'
+    | 'EXPORT_FAILED'
+    | 'PROJECTION_FAILED'
+    | 'SAVE_FAILED'
+    | 'REORDER_FAILED'
+    | 'INSERT_FAILED'
     | 'UNKNOWN';
 
 /** Kernel error from backend that should be shown to user */
@@ -303,8 +435,8 @@ export interface KernelError {
     message: string;
     /** Severity level for styling */
     severity: 'error' | 'warning';
-    /** Optional context (e.g., which feature failed) */
-    context?: Record<string, string>;
+    /** Machine-readable details; the keys are fixed per code, null when unknown */
+    details?: Record<string, unknown>;
     /** Timestamp when error occurred */
     timestamp: number;
 }
@@ -353,9 +485,14 @@ export type WebSocketCommand =
     | { command: "Select", payload: { id: string, modifier?: string } }
     | { command: "SetFilter", payload: { filter: string } }
     | { command: "ClearSelection" }
-    | { command: "CreateFeature", payload: { type: string, name: string, dependencies?: string[] } }
-    | { command: "UpdateFeature", payload: { id: string, params: Record<string, any> } }
-    | { command: "DeleteFeature", payload: { id: string } }
+    | { command: "CreateFeature", payload: { type: string, name: string, dependencies?: string[], initial_params?: Record<string, any>, from_selection?: boolean, seed?: string } }
+    | { command: "UpdateFeature", payload: { id: string, params: Record<string, any>, highlight_entities?: EntityRef[] } }
+    | { command: "UpdateSketchLive", payload: { feature_id: string, sketch_data: Sketch } }
+    | { command: "CommitSketch" }
+    | { command: "SetLiveSketchDebounce", payload: { ms: number } }
+    | { command: "DeleteFeature", payload: { id: string; policy?: "Refuse" | "Cascade" | "Orphan"; dry_run?: boolean } }
+    | { command: "UndoDelete" }
+    | { command: "RevertToLastGood" }
     | { command: "VariableAdd", payload: { name: string, expression: string, unit?: VariableUnit, description?: string } }
     | { command: "VariableUpdate", payload: { id: string, name?: string, expression?: string, unit?: VariableUnit, description?: string } }
     | { command: "VariableDelete", payload: { id: string } }
@@ -366,6 +503,92 @@ export type WebSocketCommand =
     | { command: "SelectionGroupDelete", payload: { name: string } }
     | { command: "SelectionGroupsList" }
     | { command: "ToggleSuppression", payload: { id: string } }
+    | { command: "ToggleVisibility", payload: { id: string } }
     | { command: "SetRollback", payload: { id: string | null } }
     | { command: "ReorderFeature", payload: { id: string, new_index: number } }
-    | { command: "InsertFeature", payload: { feature_type: string, name: string, after_id?: string | null, dependencies?: string[] } };
+    | { command: "PreviewConstraint", payload: { sketch_id: string, constraint: SketchConstraint } }
+    | { command: "InsertFeature", payload: { feature_type: string, name: string, after_id?: string | null, dependencies?: string[] } }
+    | { command: "ImportStep", payload: { data: string } }
+    | { command: "SetRenderOptions", payload: { uvs: boolean } }
+    | { command: "CleanupConstraints", payload: { sketch_id: string } }
+    | { command: "DeleteSketchEntity", payload: { feature_id: string; entity_id: string } }
+    | { command: "UpdateSketchEntityMeta", payload: { feature_id: string; entity_id: string; meta: EntityMetaUpdate } }
+    | { command: "ConvertSketchEntity", payload: { feature_id: string; entity_id: string; to: "Circle" | { Arc: { start_angle: number; end_angle: number } } } }
+    | { command: "CreateOffsetProfileFromFace", payload: { face: TopoId; offset: number; target_sketch?: string } }
+    | { command: "RunAnalysis", payload: AnalysisSpec }
+    | { command: "GetReport", payload: { kind: "Holes" | "Features" } }
+    | { command: "DraftAnalysis", payload: { pull_dir: [number, number, number]; min_angle?: number } }
+    | { command: "GetProjectedArea", payload: { dir: [number, number, number] } }
+    | { command: "AddSketchEntity", payload: { feature_id: string; entity: EntitySpec } }
+    | { command: "EvaluateAt", payload: { rollback_id: string | null } }
+    | { command: "DiffLastRegen" }
+    | { command: "GetDimensionValues", payload: { sketch_id: string } };
+
+/** PREVIEW_UPDATE: the model evaluated at a rollback point, leaving the live render as it is */
+export interface PreviewUpdate {
+    rollback_id: string | null;
+    summary: {
+        geometry_hash: string;
+        triangles: number;
+        lines: number;
+        points: number;
+        bodies: number;
+        topology_entities: number;
+        feature_errors: number;
+    };
+    tessellation: Tessellation;
+}
+
+/** How AddSketchEntity describes an entity; the server works out arc centers and angles */
+export type EntitySpec =
+    | { Geometry: SketchGeometry }
+    | { ThreePointArc: { start: [number, number]; through: [number, number]; end: [number, number] } }
+    | { CenterStartEnd: { center: [number, number]; start: [number, number]; end: [number, number]; ccw: boolean } }
+    | { TangentArc: { from: ConstraintPoint; end: [number, number] } };
+
+/** DRAFT_ANALYSIS: each face's draft towards the pull direction */
+export interface DraftAnalysisResult {
+    pull_dir: [number, number, number];
+    min_angle: number;
+    faces: { face: TopoId; status: "Positive" | "Negative" | "NearVertical" }[];
+}
+
+/** SESSION: reconnecting with `/ws?token=<token>` brings back the active document and selection */
+export interface SessionInfo {
+    token: string;
+    restored: boolean;
+}
+
+/** Face, edge and vertex counts and bounding box of one regeneration */
+export interface RegenCounts {
+    faces: number;
+    edges: number;
+    vertices: number;
+    bounds: [[number, number, number], [number, number, number]] | null;
+}
+
+/** DIMENSION_VALUES: each dimension's measured value on the solved sketch, by constraint index */
+export interface DimensionValues {
+    sketch_id: string;
+    values: { index: number; value: number }[];
+}
+
+/** REGEN_DIFF: what the last regeneration that changed the model added, removed or moved */
+export interface RegenDiff {
+    added: TopoId[];
+    removed: TopoId[];
+    moved: { id: TopoId; from: [number, number, number]; to: [number, number, number] }[];
+    before: RegenCounts;
+    after: RegenCounts;
+}
+
+/** PROJECTED_AREA: silhouette area along `dir`, overlapping parts counted each time */
+export interface ProjectedAreaResult {
+    dir: [number, number, number];
+    area: number;
+}
+
+/** A check run by RunAnalysis; the reply is ANALYSIS_UPDATE */
+export type AnalysisSpec =
+    | { type: "WallThickness"; body: TopoId; threshold: number; sample_density: number; max_samples: number }
+    | { type: "Stability"; body: TopoId; up_axis: [number, number, number] };